@@ -0,0 +1,599 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::error::{status, ClientError, Result};
+use crate::proto::shuffle_server_client::ShuffleServerClient;
+use crate::proto::{
+    ChecksumType, GetLocalShuffleDataRequest, GetLocalShuffleIndexRequest,
+    GetMemoryShuffleDataRequest, GetShuffleResultRequest, PartitionToBlockIds,
+    ReportShuffleResultRequest, RequireBufferRequest, SendShuffleDataRequest, ShuffleBlock,
+    ShuffleData, ShuffleRegisterRequest,
+};
+use crate::retry::RetryPolicy;
+use bytes::{Buf, Bytes};
+use croaring::Treemap;
+use std::collections::HashSet;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::transport::{Channel, Endpoint};
+
+// offset, length, uncompress_length, crc, block_id, task_attempt_id
+const INDEX_ENTRY_LEN: usize = 40;
+const READ_STREAM_BUFFER: usize = 16;
+
+#[derive(Debug, Clone)]
+pub struct ClientOptions {
+    pub connect_timeout: Duration,
+    // the blocks of one sending request are limited by it, except the single huge block
+    pub max_message_size: usize,
+    // the data of one reading request
+    pub read_buffer_size: usize,
+    pub retry: RetryPolicy,
+}
+
+impl Default for ClientOptions {
+    fn default() -> Self {
+        Self {
+            connect_timeout: Duration::from_secs(10),
+            max_message_size: 64 * 1024 * 1024,
+            read_buffer_size: 14 * 1024 * 1024,
+            retry: Default::default(),
+        }
+    }
+}
+
+/// The block to write, whose crc is computed by the client.
+#[derive(Debug, Clone)]
+pub struct Block {
+    pub block_id: i64,
+    pub task_attempt_id: i64,
+    pub data: Bytes,
+}
+
+impl Block {
+    pub fn new(block_id: i64, data: Bytes) -> Self {
+        Self {
+            block_id,
+            task_attempt_id: 0,
+            data,
+        }
+    }
+
+    fn into_proto(self) -> ShuffleBlock {
+        ShuffleBlock {
+            block_id: self.block_id,
+            length: self.data.len() as i32,
+            uncompress_length: self.data.len() as i32,
+            crc: crc32fast::hash(&self.data) as i64,
+            data: self.data,
+            task_attempt_id: self.task_attempt_id,
+        }
+    }
+}
+
+pub type BlockStream = ReceiverStream<Result<Block>>;
+
+fn check(method: &'static str, status: i32, message: String) -> Result<()> {
+    if status == status::SUCCESS {
+        return Ok(());
+    }
+    Err(ClientError::Server {
+        method,
+        status,
+        message,
+    })
+}
+
+/// Split the blocks into the chunks whose size is not more than the max size,
+/// the order is kept and the huge block is in its own chunk.
+fn chunk_blocks(blocks: Vec<ShuffleBlock>, max_size: usize) -> Vec<Vec<ShuffleBlock>> {
+    let mut chunks = vec![];
+    let mut chunk = vec![];
+    let mut chunk_size = 0;
+    for block in blocks {
+        let len = block.data.len();
+        if !chunk.is_empty() && chunk_size + len > max_size {
+            chunks.push(std::mem::take(&mut chunk));
+            chunk_size = 0;
+        }
+        chunk_size += len;
+        chunk.push(block);
+    }
+    if !chunk.is_empty() {
+        chunks.push(chunk);
+    }
+    chunks
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct IndexEntry {
+    offset: i64,
+    length: i32,
+    block_id: i64,
+    task_attempt_id: i64,
+}
+
+/// Group the index entries into the ranges of the data file to read, every range is not
+/// more than the read buffer size except the single huge block.
+fn split_index(mut index: Bytes, read_buffer_size: usize) -> Result<Vec<Vec<IndexEntry>>> {
+    if index.len() % INDEX_ENTRY_LEN != 0 {
+        return Err(ClientError::InvalidData(format!(
+            "The index len: {} is not aligned with the entry len",
+            index.len()
+        )));
+    }
+    let mut ranges: Vec<Vec<IndexEntry>> = vec![];
+    let mut range: Vec<IndexEntry> = vec![];
+    while index.has_remaining() {
+        let offset = index.get_i64();
+        let length = index.get_i32();
+        index.get_i32();
+        index.get_i64();
+        let block_id = index.get_i64();
+        let task_attempt_id = index.get_i64();
+        let entry = IndexEntry {
+            offset,
+            length,
+            block_id,
+            task_attempt_id,
+        };
+        if let Some(first) = range.first() {
+            let range_len = (entry.offset + entry.length as i64 - first.offset) as usize;
+            let contiguous = range
+                .last()
+                .map(|x| x.offset + x.length as i64 == entry.offset)
+                .unwrap_or(true);
+            if !contiguous || range_len > read_buffer_size {
+                ranges.push(std::mem::take(&mut range));
+            }
+        }
+        range.push(entry);
+    }
+    if !range.is_empty() {
+        ranges.push(range);
+    }
+    Ok(ranges)
+}
+
+/// The typed client of the shuffle server, which is cheap to clone.
+#[derive(Clone)]
+pub struct RiffleClient {
+    inner: ShuffleServerClient<Channel>,
+    options: ClientOptions,
+}
+
+impl RiffleClient {
+    pub async fn connect(address: impl Into<String>) -> Result<Self> {
+        Self::connect_with(address, ClientOptions::default()).await
+    }
+
+    /// Connect to the address like `http://127.0.0.1:19999`.
+    pub async fn connect_with(address: impl Into<String>, options: ClientOptions) -> Result<Self> {
+        let channel = Endpoint::from_shared(address.into())?
+            .connect_timeout(options.connect_timeout)
+            .connect()
+            .await?;
+        let inner = ShuffleServerClient::new(channel)
+            .max_decoding_message_size(usize::MAX)
+            .max_encoding_message_size(usize::MAX);
+        Ok(Self { inner, options })
+    }
+
+    /// The raw grpc client for the apis not wrapped yet.
+    pub fn raw(&self) -> ShuffleServerClient<Channel> {
+        self.inner.clone()
+    }
+
+    pub async fn register_app(&self, app_id: &str, shuffle_id: i32) -> Result<()> {
+        let request = ShuffleRegisterRequest {
+            app_id: app_id.to_string(),
+            shuffle_id,
+            shuffle_data_distribution: 1,
+            max_concurrency_per_partition_to_write: 10,
+            ..Default::default()
+        };
+        self.options
+            .retry
+            .call("register_shuffle", || {
+                let mut client = self.inner.clone();
+                let request = request.clone();
+                async move {
+                    let response = client.register_shuffle(request).await?.into_inner();
+                    check("register_shuffle", response.status, response.ret_msg)
+                }
+            })
+            .await
+    }
+
+    /// Returns the required buffer id.
+    pub async fn require_buffer(
+        &self,
+        app_id: &str,
+        shuffle_id: i32,
+        partition_ids: Vec<i32>,
+        size: i32,
+    ) -> Result<i64> {
+        let request = RequireBufferRequest {
+            require_size: size,
+            app_id: app_id.to_string(),
+            shuffle_id,
+            partition_ids,
+        };
+        self.options
+            .retry
+            .call("require_buffer", || {
+                self.require_buffer_once(request.clone())
+            })
+            .await
+    }
+
+    async fn require_buffer_once(&self, request: RequireBufferRequest) -> Result<i64> {
+        let response = self
+            .inner
+            .clone()
+            .require_buffer(request)
+            .await?
+            .into_inner();
+        check("require_buffer", response.status, response.ret_msg)?;
+        Ok(response.require_buffer_id)
+    }
+
+    /// Send the blocks of the partition, which are chunked by the max message size. Every
+    /// chunk requires its own buffer, and it's resent with a new buffer only if rejected
+    /// by the server before applied. The timeout or the transport failure is returned as is,
+    /// since the data may have been accepted.
+    pub async fn send_blocks(
+        &self,
+        app_id: &str,
+        shuffle_id: i32,
+        partition_id: i32,
+        blocks: Vec<Block>,
+    ) -> Result<()> {
+        let blocks = blocks.into_iter().map(|x| x.into_proto()).collect();
+        for chunk in chunk_blocks(blocks, self.options.max_message_size) {
+            self.options
+                .retry
+                .call_when("send_shuffle_data", ClientError::is_send_rejected, || {
+                    self.send_chunk(app_id, shuffle_id, partition_id, chunk.clone())
+                })
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn send_chunk(
+        &self,
+        app_id: &str,
+        shuffle_id: i32,
+        partition_id: i32,
+        blocks: Vec<ShuffleBlock>,
+    ) -> Result<()> {
+        let size: usize = blocks.iter().map(|x| x.data.len()).sum();
+        // nothing is written by requiring, which is retried on the transient failures
+        let require_buffer_id = self
+            .require_buffer(app_id, shuffle_id, vec![partition_id], size as i32)
+            .await?;
+        let response = self
+            .inner
+            .clone()
+            .send_shuffle_data(SendShuffleDataRequest {
+                app_id: app_id.to_string(),
+                shuffle_id,
+                require_buffer_id,
+                shuffle_data: vec![ShuffleData {
+                    partition_id,
+                    block: blocks,
+                }],
+                checksum_type: ChecksumType::Crc32.into(),
+                ..Default::default()
+            })
+            .await?
+            .into_inner();
+        check("send_shuffle_data", response.status, response.ret_msg)
+    }
+
+    pub async fn report_shuffle_result(
+        &self,
+        app_id: &str,
+        shuffle_id: i32,
+        task_attempt_id: i64,
+        partition_id: i32,
+        block_ids: Vec<i64>,
+    ) -> Result<()> {
+        let request = ReportShuffleResultRequest {
+            app_id: app_id.to_string(),
+            shuffle_id,
+            task_attempt_id,
+            bitmap_num: 1,
+            partition_to_block_ids: vec![PartitionToBlockIds {
+                partition_id,
+                block_ids,
+            }],
+        };
+        self.options
+            .retry
+            .call("report_shuffle_result", || {
+                let mut client = self.inner.clone();
+                let request = request.clone();
+                async move {
+                    let response = client.report_shuffle_result(request).await?.into_inner();
+                    check("report_shuffle_result", response.status, response.ret_msg)
+                }
+            })
+            .await
+    }
+
+    /// Returns the reported block ids of the partition in order.
+    pub async fn get_shuffle_result(
+        &self,
+        app_id: &str,
+        shuffle_id: i32,
+        partition_id: i32,
+    ) -> Result<Vec<i64>> {
+        let request = GetShuffleResultRequest {
+            app_id: app_id.to_string(),
+            shuffle_id,
+            partition_id,
+        };
+        let bitmap = self
+            .options
+            .retry
+            .call("get_shuffle_result", || {
+                let mut client = self.inner.clone();
+                let request = request.clone();
+                async move {
+                    let response = client.get_shuffle_result(request).await?.into_inner();
+                    check("get_shuffle_result", response.status, response.ret_msg)?;
+                    Ok(response.serialized_bitmap)
+                }
+            })
+            .await?;
+        let bitmap = Treemap::deserialize(&bitmap).map_err(|e| {
+            ClientError::InvalidData(format!("Illegal block ids bitmap. err: {}", e))
+        })?;
+        Ok(bitmap.iter().map(|x| x as i64).collect())
+    }
+
+    /// Read all the blocks of the partition from the memory and then the localfile. The block
+    /// being flushed meanwhile may be in both, which is only yielded once.
+    pub fn read_partition(&self, app_id: &str, shuffle_id: i32, partition_id: i32) -> BlockStream {
+        let (tx, rx) = mpsc::channel(READ_STREAM_BUFFER);
+        let client = self.clone();
+        let app_id = app_id.to_string();
+        tokio::spawn(async move {
+            if let Err(e) = client
+                .read_partition_into(&app_id, shuffle_id, partition_id, &tx)
+                .await
+            {
+                let _ = tx.send(Err(e)).await;
+            }
+        });
+        ReceiverStream::new(rx)
+    }
+
+    async fn read_partition_into(
+        &self,
+        app_id: &str,
+        shuffle_id: i32,
+        partition_id: i32,
+        tx: &mpsc::Sender<Result<Block>>,
+    ) -> Result<()> {
+        // the yielded block ids
+        let mut read = HashSet::new();
+        // the memory data is paged by the last block id
+        let mut last_block_id = -1;
+        loop {
+            let request = GetMemoryShuffleDataRequest {
+                app_id: app_id.to_string(),
+                shuffle_id,
+                partition_id,
+                last_block_id,
+                read_buffer_size: self.options.read_buffer_size as i32,
+                ..Default::default()
+            };
+            let response = self
+                .options
+                .retry
+                .call("get_memory_shuffle_data", || {
+                    let mut client = self.inner.clone();
+                    let request = request.clone();
+                    async move {
+                        let response = client.get_memory_shuffle_data(request).await?.into_inner();
+                        check(
+                            "get_memory_shuffle_data",
+                            response.status,
+                            response.ret_msg.clone(),
+                        )?;
+                        Ok(response)
+                    }
+                })
+                .await?;
+            let last = match response.shuffle_data_block_segments.last() {
+                Some(last) => last.block_id,
+                _ => break,
+            };
+            for segment in &response.shuffle_data_block_segments {
+                let start = segment.offset as usize;
+                let end = start + segment.length as usize;
+                if end > response.data.len() {
+                    return Err(ClientError::InvalidData(format!(
+                        "The segment of block: {} is beyond the memory data",
+                        segment.block_id
+                    )));
+                }
+                if !read.insert(segment.block_id) {
+                    continue;
+                }
+                let block = Block {
+                    block_id: segment.block_id,
+                    task_attempt_id: segment.task_attempt_id,
+                    data: response.data.slice(start..end),
+                };
+                // the closed stream stops the reading
+                if tx.send(Ok(block)).await.is_err() {
+                    return Ok(());
+                }
+            }
+            // the last block has been flushed, the reading would restart from the head
+            if last == last_block_id {
+                break;
+            }
+            last_block_id = last;
+        }
+
+        let request = GetLocalShuffleIndexRequest {
+            app_id: app_id.to_string(),
+            shuffle_id,
+            partition_id,
+            partition_num_per_range: 1,
+            partition_num: 0,
+        };
+        let index = self
+            .options
+            .retry
+            .call("get_local_shuffle_index", || {
+                let mut client = self.inner.clone();
+                let request = request.clone();
+                async move {
+                    let response = client.get_local_shuffle_index(request).await?.into_inner();
+                    check("get_local_shuffle_index", response.status, response.ret_msg)?;
+                    Ok(response.index_data)
+                }
+            })
+            .await?;
+        for range in split_index(index, self.options.read_buffer_size)? {
+            let offset = range[0].offset;
+            let last = &range[range.len() - 1];
+            let length = last.offset + last.length as i64 - offset;
+            let request = GetLocalShuffleDataRequest {
+                app_id: app_id.to_string(),
+                shuffle_id,
+                partition_id,
+                partition_num_per_range: 1,
+                partition_num: 0,
+                offset,
+                length: length as i32,
+                timestamp: 0,
+            };
+            let data = self
+                .options
+                .retry
+                .call("get_local_shuffle_data", || {
+                    let mut client = self.inner.clone();
+                    let request = request.clone();
+                    async move {
+                        let response = client.get_local_shuffle_data(request).await?.into_inner();
+                        check("get_local_shuffle_data", response.status, response.ret_msg)?;
+                        Ok(response.data)
+                    }
+                })
+                .await?;
+            if (data.len() as i64) < length {
+                return Err(ClientError::InvalidData(format!(
+                    "The read data len: {} is less than the expected: {}",
+                    data.len(),
+                    length
+                )));
+            }
+            for entry in range {
+                if !read.insert(entry.block_id) {
+                    continue;
+                }
+                let start = (entry.offset - offset) as usize;
+                let block = Block {
+                    block_id: entry.block_id,
+                    task_attempt_id: entry.task_attempt_id,
+                    data: data.slice(start..start + entry.length as usize),
+                };
+                if tx.send(Ok(block)).await.is_err() {
+                    return Ok(());
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::client::{chunk_blocks, split_index, IndexEntry, INDEX_ENTRY_LEN};
+    use crate::proto::ShuffleBlock;
+    use bytes::{BufMut, Bytes, BytesMut};
+
+    #[test]
+    fn test_chunk_blocks() {
+        let block = |block_id: i64, len: usize| ShuffleBlock {
+            block_id,
+            data: Bytes::from(vec![0u8; len]),
+            ..Default::default()
+        };
+        let blocks = vec![
+            block(0, 4),
+            block(1, 4),
+            block(2, 3),
+            block(3, 20),
+            block(4, 1),
+        ];
+        let chunks: Vec<Vec<i64>> = chunk_blocks(blocks, 10)
+            .iter()
+            .map(|x| x.iter().map(|x| x.block_id).collect())
+            .collect();
+        assert_eq!(vec![vec![0, 1], vec![2], vec![3], vec![4]], chunks);
+        assert!(chunk_blocks(vec![], 10).is_empty());
+    }
+
+    #[test]
+    fn test_split_index() -> crate::error::Result<()> {
+        let entry = |offset: i64, length: i32, block_id: i64| IndexEntry {
+            offset,
+            length,
+            block_id,
+            task_attempt_id: 1,
+        };
+        let entries = vec![
+            entry(0, 4, 0),
+            entry(4, 4, 1),
+            entry(8, 20, 2),
+            entry(28, 2, 3),
+        ];
+        let mut index = BytesMut::new();
+        for x in &entries {
+            index.put_i64(x.offset);
+            index.put_i32(x.length);
+            index.put_i32(x.length);
+            index.put_i64(0);
+            index.put_i64(x.block_id);
+            index.put_i64(x.task_attempt_id);
+        }
+        let index = index.freeze();
+        assert_eq!(entries.len() * INDEX_ENTRY_LEN, index.len());
+
+        let ranges = split_index(index.clone(), 10)?;
+        assert_eq!(
+            vec![
+                entries[0..2].to_vec(),
+                entries[2..3].to_vec(),
+                entries[3..4].to_vec()
+            ],
+            ranges
+        );
+        assert_eq!(1, split_index(index.clone(), 100)?.len());
+        assert!(split_index(index.slice(1..), 100).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,90 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use std::time::Duration;
+use thiserror::Error;
+
+/// The status code in the response of the shuffle server.
+pub mod status {
+    pub const SUCCESS: i32 = 0;
+    pub const NO_BUFFER: i32 = 2;
+    pub const TIMEOUT: i32 = 7;
+    pub const NO_BUFFER_FOR_HUGE_PARTITION: i32 = 8;
+    pub const CHECKSUM_MISMATCHED: i32 = 12;
+    pub const REASSIGNED: i32 = 14;
+}
+
+#[derive(Error, Debug)]
+pub enum ClientError {
+    #[error("Errors on connecting to the server. err: {0}")]
+    Transport(#[from] tonic::transport::Error),
+
+    #[error("Errors on calling the rpc. err: {0}")]
+    Rpc(#[from] tonic::Status),
+
+    #[error("The server responded the status: {status} of {method}. msg: {message}")]
+    Server {
+        method: &'static str,
+        status: i32,
+        message: String,
+    },
+
+    #[error("The {0} is timeout after {1:?}")]
+    Timeout(&'static str, Duration),
+
+    #[error("Invalid data from the server. {0}")]
+    InvalidData(String),
+}
+
+impl ClientError {
+    /// Whether the failure is transient and the request could be retried as is.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            ClientError::Transport(_) | ClientError::Timeout(..) => true,
+            ClientError::Rpc(status) => matches!(
+                status.code(),
+                tonic::Code::Unavailable
+                    | tonic::Code::DeadlineExceeded
+                    | tonic::Code::ResourceExhausted
+            ),
+            ClientError::Server { status: code, .. } => matches!(
+                *code,
+                status::NO_BUFFER
+                    | status::NO_BUFFER_FOR_HUGE_PARTITION
+                    | status::TIMEOUT
+                    | status::CHECKSUM_MISMATCHED
+            ),
+            ClientError::InvalidData(_) => false,
+        }
+    }
+
+    /// Whether the sending is rejected by the server before the data is applied, which is
+    /// safe to resend with a new buffer. The others like the timeout may have been applied,
+    /// and resending them would duplicate the data.
+    pub fn is_send_rejected(&self) -> bool {
+        matches!(
+            self,
+            ClientError::Server {
+                method: "send_shuffle_data",
+                status: status::NO_BUFFER | status::CHECKSUM_MISMATCHED,
+                ..
+            }
+        )
+    }
+}
+
+pub type Result<T> = std::result::Result<T, ClientError>;
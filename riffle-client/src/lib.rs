@@ -0,0 +1,54 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! The typed client of the riffle shuffle server.
+//!
+//! ```no_run
+//! use bytes::Bytes;
+//! use riffle_client::{Block, RiffleClient, StreamExt};
+//!
+//! # async fn round_trip() -> riffle_client::Result<()> {
+//! let client = RiffleClient::connect("http://127.0.0.1:19999").await?;
+//! client.register_app("app", 0).await?;
+//!
+//! let blocks = vec![Block::new(1, Bytes::from("hello")), Block::new(2, Bytes::from("world"))];
+//! client.send_blocks("app", 0, 0, blocks).await?;
+//! client.report_shuffle_result("app", 0, 0, 0, vec![1, 2]).await?;
+//! assert_eq!(vec![1, 2], client.get_shuffle_result("app", 0, 0).await?);
+//!
+//! let mut stream = client.read_partition("app", 0, 0);
+//! while let Some(block) = stream.next().await {
+//!     let block = block?;
+//!     println!("block: {} with {} bytes", block.block_id, block.data.len());
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod client;
+pub mod error;
+pub mod retry;
+
+#[allow(clippy::all)]
+pub mod proto {
+    tonic::include_proto!("rss.common");
+}
+
+pub use client::{Block, BlockStream, ClientOptions, RiffleClient};
+pub use error::{ClientError, Result};
+pub use retry::RetryPolicy;
+pub use tokio_stream::StreamExt;
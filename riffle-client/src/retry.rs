@@ -0,0 +1,253 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::error::{ClientError, Result};
+use log::warn;
+use std::future::Future;
+use std::time::Duration;
+
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    // including the first attempt
+    pub max_attempts: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    // the timeout of every attempt
+    pub request_timeout: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(5),
+            request_timeout: Duration::from_secs(60),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// No retry, only the timeout is applied.
+    pub fn no_retry(request_timeout: Duration) -> Self {
+        Self {
+            max_attempts: 1,
+            request_timeout,
+            ..Default::default()
+        }
+    }
+
+    fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 1u32
+            .checked_shl(attempt.saturating_sub(1))
+            .unwrap_or(u32::MAX);
+        self.initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+
+    /// Call the op until it succeeds, fails with the non-retryable error or the attempts
+    /// are exhausted. The op is rebuilt for every attempt, so the request should be cheap to clone.
+    pub async fn call<T, F, Fut>(&self, method: &'static str, op: F) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        self.call_when(method, ClientError::is_retryable, op).await
+    }
+
+    /// Like the `call`, but only the errors accepted by the `retryable` are retried.
+    pub async fn call_when<T, F, Fut>(
+        &self,
+        method: &'static str,
+        retryable: fn(&ClientError) -> bool,
+        mut op: F,
+    ) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let mut attempt = 1;
+        loop {
+            let result = match tokio::time::timeout(self.request_timeout, op()).await {
+                Ok(result) => result,
+                Err(_) => Err(ClientError::Timeout(method, self.request_timeout)),
+            };
+            match result {
+                Err(e) if retryable(&e) && attempt < self.max_attempts => {
+                    let backoff = self.backoff(attempt);
+                    warn!(
+                        "Retrying the {} after {:?} for the attempt: {}. err: {}",
+                        method, backoff, attempt, e
+                    );
+                    tokio::time::sleep(backoff).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::{status, ClientError};
+    use crate::retry::RetryPolicy;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::time::Duration;
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(2),
+            request_timeout: Duration::from_millis(100),
+        }
+    }
+
+    #[test]
+    fn test_backoff() {
+        let policy = RetryPolicy {
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_millis(300),
+            ..Default::default()
+        };
+        assert_eq!(Duration::from_millis(100), policy.backoff(1));
+        assert_eq!(Duration::from_millis(200), policy.backoff(2));
+        assert_eq!(Duration::from_millis(300), policy.backoff(3));
+        assert_eq!(Duration::from_millis(300), policy.backoff(100));
+    }
+
+    #[tokio::test]
+    async fn test_retry() {
+        let no_buffer = || ClientError::Server {
+            method: "require_buffer",
+            status: status::NO_BUFFER,
+            message: "".to_string(),
+        };
+
+        // succeeds in the last attempt
+        let attempts = AtomicU32::new(0);
+        let result = policy()
+            .call("require_buffer", || async {
+                match attempts.fetch_add(1, Ordering::SeqCst) {
+                    0 | 1 => Err(no_buffer()),
+                    _ => Ok(1),
+                }
+            })
+            .await;
+        assert_eq!(1, result.unwrap());
+        assert_eq!(3, attempts.load(Ordering::SeqCst));
+
+        // exhausted
+        let attempts = AtomicU32::new(0);
+        let result: Result<(), _> = policy()
+            .call("require_buffer", || async {
+                attempts.fetch_add(1, Ordering::SeqCst);
+                Err(no_buffer())
+            })
+            .await;
+        assert!(result.is_err());
+        assert_eq!(3, attempts.load(Ordering::SeqCst));
+
+        // not retryable
+        let attempts = AtomicU32::new(0);
+        let result: Result<(), _> = policy()
+            .call("get_shuffle_result", || async {
+                attempts.fetch_add(1, Ordering::SeqCst);
+                Err(ClientError::InvalidData("broken bitmap".to_string()))
+            })
+            .await;
+        assert!(result.is_err());
+        assert_eq!(1, attempts.load(Ordering::SeqCst));
+
+        // the timeout is retried too
+        let attempts = AtomicU32::new(0);
+        let result = policy()
+            .call("send_shuffle_data", || async {
+                if attempts.fetch_add(1, Ordering::SeqCst) == 0 {
+                    tokio::time::sleep(Duration::from_secs(1)).await;
+                }
+                Ok(())
+            })
+            .await;
+        assert!(result.is_ok());
+        assert_eq!(2, attempts.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn test_retry_rejected_send_only() {
+        let rejected = |status: i32| ClientError::Server {
+            method: "send_shuffle_data",
+            status,
+            message: "".to_string(),
+        };
+
+        // the rejected before applied is resent
+        let attempts = AtomicU32::new(0);
+        let result = policy()
+            .call_when(
+                "send_shuffle_data",
+                ClientError::is_send_rejected,
+                || async {
+                    match attempts.fetch_add(1, Ordering::SeqCst) {
+                        0 => Err(rejected(status::NO_BUFFER)),
+                        1 => Err(rejected(status::CHECKSUM_MISMATCHED)),
+                        _ => Ok(()),
+                    }
+                },
+            )
+            .await;
+        assert!(result.is_ok());
+        assert_eq!(3, attempts.load(Ordering::SeqCst));
+
+        // the timeout may have been applied, which is not resent
+        let attempts = AtomicU32::new(0);
+        let result = policy()
+            .call_when(
+                "send_shuffle_data",
+                ClientError::is_send_rejected,
+                || async {
+                    attempts.fetch_add(1, Ordering::SeqCst);
+                    tokio::time::sleep(Duration::from_secs(1)).await;
+                    Ok(())
+                },
+            )
+            .await;
+        assert!(matches!(result, Err(ClientError::Timeout(..))));
+        assert_eq!(1, attempts.load(Ordering::SeqCst));
+
+        // the exhausted buffer requiring is not retried again
+        let attempts = AtomicU32::new(0);
+        let result: Result<(), _> = policy()
+            .call_when(
+                "send_shuffle_data",
+                ClientError::is_send_rejected,
+                || async {
+                    attempts.fetch_add(1, Ordering::SeqCst);
+                    Err(ClientError::Server {
+                        method: "require_buffer",
+                        status: status::NO_BUFFER,
+                        message: "".to_string(),
+                    })
+                },
+            )
+            .await;
+        assert!(result.is_err());
+        assert_eq!(1, attempts.load(Ordering::SeqCst));
+    }
+}
@@ -15,19 +15,30 @@
 // specific language governing permissions and limitations
 // under the License.
 
-use crate::config::Config;
+use crate::block_id::BlockIdLayout;
+use crate::busy_score::{BusyScoreSampler, BUSY_SCORE_SAMPLE_INTERVAL};
+use crate::config::{AppQuotaConfig, Config};
+use crate::decommission::DecommissionState;
 use crate::error::WorkerError;
+use crate::health::HEALTH_REGISTRY;
 use crate::metric::{
-    GAUGE_APP_NUMBER, GAUGE_TOPN_APP_RESIDENT_DATA_SIZE, TOTAL_APP_NUMBER,
+    GAUGE_APP_NUMBER, GAUGE_FLUSH_WATERMARK_LAG, GAUGE_TOPN_APP_RESIDENT_DATA_SIZE,
+    TOTAL_APP_NUMBER, TOTAL_APP_REGISTRATION_REJECTED, TOTAL_DUPLICATE_BLOCKS_DROPPED,
     TOTAL_HUGE_PARTITION_REQUIRE_BUFFER_FAILED, TOTAL_READ_DATA, TOTAL_READ_DATA_FROM_LOCALFILE,
-    TOTAL_READ_DATA_FROM_MEMORY, TOTAL_RECEIVED_DATA, TOTAL_REQUIRE_BUFFER_FAILED,
+    TOTAL_READ_DATA_FROM_MEMORY, TOTAL_REASSIGNED_BLOCKS_REJECTED, TOTAL_REASSIGNED_PARTITIONS,
+    TOTAL_RECEIVED_DATA, TOTAL_REQUIRE_BUFFER_FAILED, TOTAL_SHUFFLE_RESULT_OFFLOADED,
+    TOTAL_SHUFFLE_RESULT_RELOADED,
 };
+use serde::{Deserialize, Serialize};
 
 use crate::readable_size::ReadableSize;
 use crate::runtime::manager::RuntimeManager;
+use crate::runtime::RuntimeRef;
+use crate::sketch::{HeavyHitter, ShardedSpaceSaving};
 use crate::store::hybrid::HybridStore;
 use crate::store::{
-    Block, RequireBufferResponse, ResponseData, ResponseDataIndex, Store, StoreProvider,
+    Block, BlockSource, PartitionStorageStat, PartitionedMergedData, RequireBufferResponse,
+    ResponseData, ResponseDataIndex, SpillStatus, Store, StoreProvider,
 };
 use crate::util::now_timestamp_as_sec;
 use anyhow::{anyhow, Result};
@@ -35,25 +46,27 @@ use bytes::Bytes;
 use croaring::treemap::JvmSerializer;
 use croaring::Treemap;
 
-use dashmap::DashMap;
-use log::{debug, error, info};
+use dashmap::mapref::entry::Entry;
+use dashmap::{DashMap, DashSet};
+use log::{debug, error, info, warn};
 
 use std::collections::hash_map::DefaultHasher;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeSet, HashMap, HashSet};
 
 use std::hash::{Hash, Hasher};
-
-use std::str::FromStr;
+use std::path::{Path, PathBuf};
 
 use crate::await_tree::AWAIT_TREE_REGISTRY;
 use crate::grpc::protobuf::uniffle::RemoteStorage;
 use crate::store::mem::capacity::CapacitySnapshot;
+use crate::store::mem::snapshot::MemorySnapshot;
+use crate::store::read_cache::ReadCacheStats;
 use await_tree::InstrumentAwait;
-use parking_lot::RwLock;
+use parking_lot::{Mutex, RwLock};
 use std::sync::atomic::Ordering::SeqCst;
-use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 use std::sync::{Arc, OnceLock};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tracing::Instrument;
 
 pub static SHUFFLE_SERVER_ID: OnceLock<String> = OnceLock::new();
@@ -68,11 +81,21 @@ pub enum DataDistribution {
 
 pub const MAX_CONCURRENCY_PER_PARTITION_TO_WRITE: i32 = 20;
 
+// the capacity is per shard, see ShardedSpaceSaving
+const DEFAULT_HOT_PARTITION_SKETCH_CAPACITY: usize = 32;
+const HOT_PARTITION_SKETCH_SHARDS: usize = 16;
+
+const FLUSH_BARRIER_CHECK_INTERVAL: Duration = Duration::from_millis(20);
+
+const APP_MEMORY_QUOTA_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
 #[derive(Debug, Clone)]
 pub struct AppConfigOptions {
     pub data_distribution: DataDistribution,
     pub max_concurrency_per_partition_to_write: i32,
     pub remote_storage_config_option: Option<RemoteStorageConfig>,
+    // declared by the client in registration
+    pub block_id_layout: Option<BlockIdLayout>,
 }
 
 impl AppConfigOptions {
@@ -85,6 +108,7 @@ impl AppConfigOptions {
             data_distribution,
             max_concurrency_per_partition_to_write,
             remote_storage_config_option,
+            block_id_layout: None,
         }
     }
 }
@@ -95,6 +119,7 @@ impl Default for AppConfigOptions {
             data_distribution: DataDistribution::LOCAL_ORDER,
             max_concurrency_per_partition_to_write: 20,
             remote_storage_config_option: None,
+            block_id_layout: None,
         }
     }
 }
@@ -124,20 +149,184 @@ impl From<RemoteStorage> for RemoteStorageConfig {
 
 // =============================================================
 
+/// The resolved quota of the app, unlimited if none.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct AppQuota {
+    pub max_total_bytes: Option<u64>,
+    pub max_memory_bytes: Option<u64>,
+}
+
+impl AppQuota {
+    pub fn from(config: &AppQuotaConfig) -> Result<Self> {
+        Ok(Self {
+            max_total_bytes: config.max_total_bytes()?,
+            max_memory_bytes: config.max_memory_bytes()?,
+        })
+    }
+}
+
+// =============================================================
+
+/// The partition number of all apps in this server, shared by the apps to be limited.
+pub struct PartitionCounter {
+    count: AtomicUsize,
+    max: Option<usize>,
+}
+
+impl PartitionCounter {
+    pub fn new(max: Option<usize>) -> Self {
+        Self {
+            count: Default::default(),
+            max,
+        }
+    }
+
+    fn try_inc(&self) -> Result<(), WorkerError> {
+        let max = match self.max {
+            Some(max) => max,
+            _ => {
+                self.count.fetch_add(1, SeqCst);
+                return Ok(());
+            }
+        };
+        self.count
+            .fetch_update(SeqCst, SeqCst, |x| if x < max { Some(x + 1) } else { None })
+            .map(|_| ())
+            .map_err(|_| WorkerError::PARTITION_NUMBER_EXCEEDED(max))
+    }
+
+    fn dec(&self, n: usize) {
+        self.count.fetch_sub(n, SeqCst);
+    }
+
+    pub fn count(&self) -> usize {
+        self.count.load(SeqCst)
+    }
+
+    fn is_exhausted(&self) -> bool {
+        self.max.map(|max| self.count() >= max).unwrap_or(false)
+    }
+}
+
+/// The cap of the concurrent apps in this server, unlimited if not set. The slot is
+/// reserved by the compare-and-swap to not be overshot by the concurrent registrations.
+pub struct AppNumberLimit {
+    count: AtomicUsize,
+    max: Option<usize>,
+}
+
+impl AppNumberLimit {
+    pub fn new(max: Option<usize>) -> Self {
+        Self {
+            count: Default::default(),
+            max,
+        }
+    }
+
+    pub fn is_reached(&self) -> bool {
+        self.max
+            .map(|max| self.count.load(SeqCst) >= max)
+            .unwrap_or(false)
+    }
+
+    /// Reserve the slot for the new app, it should be released once the app is purged.
+    pub fn admit(&self) -> Result<(), WorkerError> {
+        let max = match self.max {
+            Some(max) => max,
+            _ => {
+                self.count.fetch_add(1, SeqCst);
+                return Ok(());
+            }
+        };
+        self.count
+            .fetch_update(SeqCst, SeqCst, |x| if x < max { Some(x + 1) } else { None })
+            .map(|_| ())
+            .map_err(|_| {
+                TOTAL_APP_REGISTRATION_REJECTED.inc();
+                WorkerError::APP_NUMBER_EXCEEDED(max)
+            })
+    }
+
+    pub fn release(&self) {
+        self.count.fetch_sub(1, SeqCst);
+    }
+}
+
 pub struct App {
     app_id: String,
-    // key: shuffleId, value: partitionIds
-    partitions: DashMap<i32, HashSet<i32>>,
+    // key: (shuffle_id, partition_id), the partitions counted by the partition counter
+    partitions: DashSet<(i32, i32)>,
+    // key: shuffle_id. The writing holds the read lock during the insertion, and the
+    // purging holds the write lock to wait out the in-flight writing
+    shuffle_barriers: DashMap<i32, Arc<tokio::sync::RwLock<()>>>,
+    partition_counter: Arc<PartitionCounter>,
     app_config_options: AppConfigOptions,
+    block_id_layout: Option<BlockIdLayout>,
+    registered_timestamp: u64,
     latest_heartbeat_time: AtomicU64,
     store: Arc<HybridStore>,
     // key: (shuffle_id, partition_id)
     bitmap_of_blocks: DashMap<(i32, i32), PartitionedMeta>,
+    // key: (shuffle_id, partition_id), value: the block sequence that the reassigned partition
+    // is split at, the blocks beyond it are written to the other servers. It's not persisted
+    // and lost on restart, together with the shuffle data that's wiped on the startup
+    partition_splits: DashMap<(i32, i32), i64>,
     huge_partition_marked_threshold: Option<u64>,
     huge_partition_memory_max_available_size: Option<u64>,
+    // the top written partitions, every shard is bounded by the sketch capacity
+    hot_partitions: ShardedSpaceSaving<(i32, i32)>,
+    // key: shuffle_id
+    write_sequences: DashMap<i32, WriteSequences>,
+    shuffle_result_offload: Option<ShuffleResultOffload>,
+    // the shuffles whose results have been read, which are not expected to be reported anymore
+    read_shuffles: Mutex<HashSet<i32>>,
 
     total_received_data_size: AtomicU64,
     total_resident_data_size: AtomicU64,
+
+    quota: AppQuota,
+    // the rejected writing by the total quota, which is reset on purge
+    quota_rejected: AtomicU64,
+}
+
+/// The write sequences of the shuffle, which are assigned to the accepted requests in order.
+#[derive(Default)]
+struct WriteSequences {
+    accepted: u64,
+    // the sequences whose blocks are being inserted into the store
+    inserting: BTreeSet<u64>,
+}
+
+/// The write sequence being inserted, which is finished on drop even if the writing is
+/// cancelled halfway by the client disconnection, otherwise the flushed sequence is stuck.
+pub struct WriteSequenceGuard<'a> {
+    app: &'a App,
+    shuffle_id: i32,
+    sequence: u64,
+}
+
+impl WriteSequenceGuard<'_> {
+    pub fn sequence(&self) -> u64 {
+        self.sequence
+    }
+}
+
+impl Drop for WriteSequenceGuard<'_> {
+    fn drop(&mut self) {
+        self.app
+            .finish_write_sequence(self.shuffle_id, self.sequence);
+    }
+}
+
+const SHUFFLE_RESULT_OFFLOAD_DIR: &str = "_shuffle_results";
+
+struct ShuffleResultOffload {
+    // the resident block ids number of the app
+    threshold: u64,
+    // the app dir holding the offloaded bitmaps, like {dir}/{shuffle_id}/{partition_id}
+    dir: PathBuf,
+    // reloading the offloaded bitmaps in the blocking threads
+    runtime: RuntimeRef,
 }
 
 #[derive(Clone)]
@@ -147,7 +336,13 @@ struct PartitionedMeta {
 
 struct PartitionedMetaInner {
     blocks_bitmap: Treemap,
+    // the received block ids, which are kept after the blocks spilled
+    received_blocks_bitmap: Treemap,
     total_size: u64,
+    // the blocks bitmap is released from memory once offloaded into this file
+    offloaded_path: Option<PathBuf>,
+    // the serialized offloaded bitmap cached on reloading, released by the next offloading
+    reloaded: Option<Bytes>,
 }
 
 impl PartitionedMeta {
@@ -155,7 +350,10 @@ impl PartitionedMeta {
         PartitionedMeta {
             inner: Arc::new(RwLock::new(PartitionedMetaInner {
                 blocks_bitmap: Treemap::default(),
+                received_blocks_bitmap: Treemap::default(),
                 total_size: 0,
+                offloaded_path: None,
+                reloaded: None,
             })),
         }
     }
@@ -182,6 +380,39 @@ impl PartitionedMeta {
         Ok(Bytes::from(serialized_data))
     }
 
+    /// The offloaded bitmap is read in the blocking thread without holding the lock,
+    /// and then cached until the next offloading.
+    async fn get_block_ids_with_reloading(&self, runtime: &RuntimeRef) -> Result<Bytes> {
+        let path = {
+            let meta = self.inner.read();
+            match (&meta.reloaded, &meta.offloaded_path) {
+                (Some(data), _) => return Ok(data.clone()),
+                (_, Some(path)) => path.clone(),
+                _ => return Ok(Bytes::from(meta.blocks_bitmap.serialize()?)),
+            }
+        };
+        let reading = path.clone();
+        let data = Bytes::from(
+            runtime
+                .spawn_blocking(move || std::fs::read(reading))
+                .await??,
+        );
+        TOTAL_SHUFFLE_RESULT_RELOADED.inc();
+
+        let mut meta = self.inner.write();
+        if meta.offloaded_path.as_ref() == Some(&path) {
+            meta.reloaded = Some(data.clone());
+            return Ok(data);
+        }
+        // the late reported blocks have been merged into the memory meanwhile
+        Ok(Bytes::from(meta.blocks_bitmap.serialize()?))
+    }
+
+    async fn get_block_ids_bitmap_with_reloading(&self, runtime: &RuntimeRef) -> Result<Treemap> {
+        let data = self.get_block_ids_with_reloading(runtime).await?;
+        Ok(Treemap::deserialize(&data)?)
+    }
+
     fn report_block_ids(&mut self, ids: Vec<i64>) -> Result<()> {
         let mut meta = self.inner.write();
         for id in ids {
@@ -189,6 +420,87 @@ impl PartitionedMeta {
         }
         Ok(())
     }
+
+    /// The late reported blocks are merged into the reloaded bitmap. The stale offloaded
+    /// file is left to be overwritten by the next offloading or removed by the purge.
+    async fn report_block_ids_with_reloading(
+        &mut self,
+        ids: Vec<i64>,
+        runtime: &RuntimeRef,
+    ) -> Result<()> {
+        let offloaded_path = self.inner.read().offloaded_path.clone();
+        if let Some(path) = offloaded_path {
+            let data = self.get_block_ids_with_reloading(runtime).await?;
+            let reloaded = Treemap::deserialize(&data)?;
+            let mut meta = self.inner.write();
+            if meta.offloaded_path.as_ref() == Some(&path) {
+                meta.blocks_bitmap = reloaded;
+                meta.offloaded_path = None;
+                meta.reloaded = None;
+            }
+        }
+        self.report_block_ids(ids)
+    }
+
+    /// Drop the received blocks, like the ones resent by the speculative tasks.
+    fn dedup_blocks(&mut self, blocks: Vec<Block>) -> Vec<Block> {
+        let mut meta = self.inner.write();
+        let received = &mut meta.received_blocks_bitmap;
+        blocks
+            .into_iter()
+            .filter(|block| {
+                let id = block.block_id as u64;
+                if received.contains(id) {
+                    TOTAL_DUPLICATE_BLOCKS_DROPPED.inc();
+                    false
+                } else {
+                    received.add(id);
+                    true
+                }
+            })
+            .collect()
+    }
+
+    fn resident_block_number(&self) -> u64 {
+        self.inner.read().blocks_bitmap.cardinality()
+    }
+
+    fn offload_block_ids(&self, path: &Path) -> Result<bool> {
+        let mut meta = self.inner.write();
+        if meta.offloaded_path.is_some() {
+            meta.reloaded = None;
+            return Ok(false);
+        }
+        if meta.blocks_bitmap.is_empty() {
+            return Ok(false);
+        }
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, meta.blocks_bitmap.serialize()?)?;
+        meta.blocks_bitmap = Treemap::default();
+        meta.offloaded_path = Some(path.to_path_buf());
+        Ok(true)
+    }
+
+    // the offloaded file is removed with its dir by the purge
+    fn clear_offloaded_blocks(&mut self) {
+        let mut meta = self.inner.write();
+        meta.offloaded_path = None;
+        meta.reloaded = None;
+    }
+
+    fn clear_received_blocks(&mut self) {
+        self.inner.write().received_blocks_bitmap = Treemap::default();
+    }
+
+    // the failed blocks could be resent by the client
+    fn unmark_received_blocks(&mut self, blocks: &[i64]) {
+        let mut meta = self.inner.write();
+        for id in blocks {
+            meta.received_blocks_bitmap.remove(*id as u64);
+        }
+    }
 }
 
 impl App {
@@ -198,15 +510,17 @@ impl App {
         store: Arc<HybridStore>,
         runtime_manager: RuntimeManager,
         config: &Config,
+        partition_counter: Arc<PartitionCounter>,
     ) -> Self {
         // todo: should throw exception if register failed.
+        let read_runtime = runtime_manager.read_runtime.clone();
         let copy_app_id = app_id.to_string();
         let app_options = config_options.clone();
         let cloned_store = store.clone();
         let register_result = futures::executor::block_on(async move {
             runtime_manager
                 .default_runtime
-                .spawn(async move {
+                .spawn_guarded("app_register", async move {
                     cloned_store
                         .register_app(RegisterAppContext {
                             app_id: copy_app_id,
@@ -225,34 +539,69 @@ impl App {
 
         let huge_partition_marked_threshold =
             match &config.app_config.huge_partition_marked_threshold {
-                Some(v) => Some(
-                    ReadableSize::from_str(v.clone().as_str())
-                        .unwrap()
-                        .as_bytes(),
-                ),
+                Some(v) => Some(ReadableSize::parse(v.clone().as_str()).unwrap().as_bytes()),
                 _ => None,
             };
 
-        let mem_capacity = ReadableSize::from_str(&config.memory_store.clone().unwrap().capacity)
+        let mem_capacity = config
+            .memory_store
+            .as_ref()
             .unwrap()
-            .as_bytes();
+            .capacity_bytes()
+            .unwrap();
         let huge_partition_backpressure_size =
             match &config.app_config.huge_partition_memory_limit_percent {
                 Some(v) => Some(((mem_capacity as f64) * *v) as u64),
                 _ => None,
             };
 
+        let shuffle_result_offload = match (
+            config.app_config.shuffle_result_offload_threshold,
+            config
+                .localfile_store
+                .as_ref()
+                .and_then(|x| x.effective_write_paths().first()),
+        ) {
+            (Some(threshold), Some(path)) => Some(ShuffleResultOffload {
+                threshold,
+                dir: Path::new(path)
+                    .join(SHUFFLE_RESULT_OFFLOAD_DIR)
+                    .join(&app_id),
+                runtime: read_runtime,
+            }),
+            _ => None,
+        };
+
+        let quota = AppQuota::from(&config.app_config.quota_of(&app_id)).unwrap();
+
         App {
             app_id,
-            partitions: DashMap::new(),
+            partitions: DashSet::new(),
+            shuffle_barriers: DashMap::new(),
+            partition_counter,
+            block_id_layout: config_options.block_id_layout,
             app_config_options: config_options,
+            registered_timestamp: now_timestamp_as_sec(),
             latest_heartbeat_time: AtomicU64::new(now_timestamp_as_sec()),
             store,
             bitmap_of_blocks: DashMap::new(),
+            partition_splits: DashMap::new(),
             huge_partition_marked_threshold,
             huge_partition_memory_max_available_size: huge_partition_backpressure_size,
+            hot_partitions: ShardedSpaceSaving::new(
+                config
+                    .app_config
+                    .hot_partition_sketch_capacity
+                    .unwrap_or(DEFAULT_HOT_PARTITION_SKETCH_CAPACITY),
+                HOT_PARTITION_SKETCH_SHARDS,
+            ),
+            write_sequences: DashMap::new(),
+            shuffle_result_offload,
+            read_shuffles: Mutex::new(HashSet::new()),
             total_received_data_size: Default::default(),
             total_resident_data_size: Default::default(),
+            quota,
+            quota_rejected: Default::default(),
         }
     }
 
@@ -260,6 +609,153 @@ impl App {
         self.latest_heartbeat_time.load(SeqCst)
     }
 
+    pub fn app_id(&self) -> &str {
+        &self.app_id
+    }
+
+    pub fn registered_timestamp(&self) -> u64 {
+        self.registered_timestamp
+    }
+
+    pub fn latest_heartbeat_time(&self) -> u64 {
+        self.get_latest_heartbeat_time()
+    }
+
+    pub fn partition_number(&self) -> usize {
+        self.partitions.len()
+    }
+
+    pub fn partition_ids(&self, shuffle_id: i32) -> Vec<i32> {
+        let mut ids: Vec<i32> = self
+            .partitions
+            .iter()
+            .filter(|x| x.key().0 == shuffle_id)
+            .map(|x| x.key().1)
+            .collect();
+        ids.sort();
+        ids
+    }
+
+    pub fn all_partition_uids(&self) -> Vec<PartitionedUId> {
+        self.partitions
+            .iter()
+            .map(|x| PartitionedUId::from(self.app_id.to_string(), x.key().0, x.key().1))
+            .collect()
+    }
+
+    pub fn is_huge_partition_limit_enabled(&self) -> bool {
+        self.is_limit_huge_partition()
+    }
+
+    pub fn is_marked_huge_partition(&self, uid: &PartitionedUId) -> bool {
+        match self.huge_partition_marked_threshold {
+            Some(threshold) => self
+                .bitmap_of_blocks
+                .get(&(uid.shuffle_id, uid.partition_id))
+                .map(|meta| meta.get_size().unwrap_or(0) > threshold)
+                .unwrap_or(false),
+            _ => false,
+        }
+    }
+
+    pub async fn partition_storage_stat(&self, uid: &PartitionedUId) -> PartitionStorageStat {
+        self.store.partition_storage_stat(uid).await
+    }
+
+    /// Assign the next write sequence of the shuffle to the accepted request, which is
+    /// finished once the returned guard is dropped after its blocks are inserted.
+    pub fn next_write_sequence(&self, shuffle_id: i32) -> WriteSequenceGuard<'_> {
+        let mut sequences = self.write_sequences.entry(shuffle_id).or_default();
+        sequences.accepted += 1;
+        let sequence = sequences.accepted;
+        sequences.inserting.insert(sequence);
+        WriteSequenceGuard {
+            app: self,
+            shuffle_id,
+            sequence,
+        }
+    }
+
+    fn finish_write_sequence(&self, shuffle_id: i32, sequence: u64) {
+        if let Some(mut sequences) = self.write_sequences.get_mut(&shuffle_id) {
+            sequences.inserting.remove(&sequence);
+        }
+    }
+
+    /// The write sequence of the shuffle, that the data of it and all the previous ones
+    /// have been persisted.
+    pub fn flushed_write_sequence(&self, shuffle_id: i32) -> u64 {
+        // the inserting sequences are taken before checking the buffers, the finished
+        // ones must have been appended into the buffers
+        let (accepted, inserting) = match self.write_sequences.get(&shuffle_id) {
+            Some(sequences) => (sequences.accepted, sequences.inserting.first().copied()),
+            _ => return 0,
+        };
+        let unflushed = self
+            .partition_uids(shuffle_id)
+            .iter()
+            .filter_map(|uid| self.store.unflushed_write_sequence(uid))
+            .chain(inserting)
+            .min();
+        match unflushed {
+            Some(sequence) => sequence - 1,
+            _ => accepted,
+        }
+    }
+
+    /// The max number of the write sequences accepted but not flushed among the shuffles.
+    pub fn flush_watermark_lag(&self) -> u64 {
+        let shuffle_ids: Vec<(i32, u64)> = self
+            .write_sequences
+            .iter()
+            .map(|x| (*x.key(), x.value().accepted))
+            .collect();
+        shuffle_ids
+            .into_iter()
+            .map(|(shuffle_id, accepted)| {
+                accepted.saturating_sub(self.flushed_write_sequence(shuffle_id))
+            })
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Wait until the data of the shuffle written with the sequence and the previous ones
+    /// have been persisted, so that they are readable from the persistent store after the
+    /// client side commit. The staging data is spilled at once instead of waiting for the
+    /// memory watermark.
+    pub async fn wait_until_flushed(
+        &self,
+        shuffle_id: i32,
+        sequence: u64,
+        timeout: Duration,
+    ) -> Result<u64, WorkerError> {
+        self.heartbeat()?;
+        let deadline = Instant::now() + timeout;
+        loop {
+            let flushed = self.flushed_write_sequence(shuffle_id);
+            if flushed >= sequence {
+                return Ok(flushed);
+            }
+            if Instant::now() >= deadline {
+                return Err(WorkerError::FLUSH_BARRIER_TIMEOUT(sequence, flushed));
+            }
+            // the blocks being inserted may be appended into the staging after the last spill
+            self.store
+                .flush_partitions(self.partition_uids(shuffle_id))
+                .await?;
+            tokio::time::sleep(FLUSH_BARRIER_CHECK_INTERVAL).await;
+        }
+    }
+
+    fn partition_uids(&self, shuffle_id: i32) -> Vec<PartitionedUId> {
+        self.partition_ids(shuffle_id)
+            .into_iter()
+            .map(|partition_id| {
+                PartitionedUId::from(self.app_id.to_string(), shuffle_id, partition_id)
+            })
+            .collect()
+    }
+
     pub fn heartbeat(&self) -> Result<()> {
         let timestamp = now_timestamp_as_sec();
         self.latest_heartbeat_time.store(timestamp, SeqCst);
@@ -268,12 +764,50 @@ impl App {
 
     pub fn register_shuffle(&self, shuffle_id: i32) -> Result<()> {
         self.heartbeat()?;
-        self.partitions
+        self.shuffle_barrier(shuffle_id);
+        Ok(())
+    }
+
+    fn shuffle_barrier(&self, shuffle_id: i32) -> Arc<tokio::sync::RwLock<()>> {
+        if let Some(barrier) = self.shuffle_barriers.get(&shuffle_id) {
+            return barrier.clone();
+        }
+        self.shuffle_barriers
             .entry(shuffle_id)
-            .or_insert_with(|| HashSet::new());
+            .or_default()
+            .value()
+            .clone()
+    }
+
+    // the first write of the partition is rejected when the partition limit is reached
+    fn track_partition(&self, uid: &PartitionedUId) -> Result<(), WorkerError> {
+        let key = (uid.shuffle_id, uid.partition_id);
+        // only the shard read lock for the tracked one
+        if self.partitions.contains(&key) {
+            return Ok(());
+        }
+        self.partition_counter.try_inc()?;
+        if !self.partitions.insert(key) {
+            // tracked by the concurrent writing
+            self.partition_counter.dec(1);
+        }
         Ok(())
     }
 
+    fn untrack_partitions(&self, shuffle_id: Option<i32>) {
+        let keys: Vec<(i32, i32)> = self
+            .partitions
+            .iter()
+            .map(|x| *x.key())
+            .filter(|(id, _)| shuffle_id.map_or(true, |shuffle_id| *id == shuffle_id))
+            .collect();
+        let removed = keys
+            .iter()
+            .filter(|key| self.partitions.remove(key).is_some())
+            .count();
+        self.partition_counter.dec(removed);
+    }
+
     fn is_limit_huge_partition(&self) -> bool {
         if self.huge_partition_marked_threshold.is_none() {
             return false;
@@ -286,30 +820,112 @@ impl App {
 
     pub async fn insert(&self, ctx: WritingViewContext) -> Result<i32, WorkerError> {
         self.heartbeat()?;
+        let barrier = self.shuffle_barrier(ctx.uid.shuffle_id);
+        let _barrier_guard = barrier
+            .read()
+            .instrument_await("waiting for the shuffle purging")
+            .await;
+        self.track_partition(&ctx.uid)?;
+        self.check_partition_split(&ctx)?;
+
+        let mut meta = self.get_partition_meta(&ctx.uid);
+        let blocks = meta.dedup_blocks(ctx.data_blocks);
+        if blocks.is_empty() {
+            return Ok(0);
+        }
+        let block_ids: Vec<i64> = blocks.iter().map(|block| block.block_id).collect();
+        let ctx =
+            WritingViewContext::new(ctx.uid, blocks, ctx.owned_by_huge_partition, ctx.data_size)
+                .with_write_sequence(ctx.write_sequence);
 
         let len: u64 = ctx
             .data_blocks
             .iter()
             .map(|block| block.length)
             .sum::<i32>() as u64;
+        // checked without reservation, the concurrent writing may overshoot a little
+        if let Some(max) = self.quota.max_total_bytes {
+            if self.total_resident_data_size.load(SeqCst) + len > max {
+                meta.unmark_received_blocks(&block_ids);
+                self.quota_rejected.fetch_add(1, SeqCst);
+                return Err(WorkerError::APP_QUOTA_EXCEEDED(max));
+            }
+        }
         TOTAL_RECEIVED_DATA.inc_by(len);
 
         self.total_received_data_size.fetch_add(len, SeqCst);
         self.total_resident_data_size.fetch_add(len, SeqCst);
+        self.record_written(&mut meta, &ctx.uid, len)?;
 
         let context = if self.is_limit_huge_partition() {
-            match self.is_huge_partition(&ctx.uid, Some(len)).await {
-                Ok(true) => WritingViewContext::new(ctx.uid, ctx.data_blocks, true, len),
+            match self.is_huge_partition(&ctx.uid).await {
+                Ok(true) => WritingViewContext::new(ctx.uid, ctx.data_blocks, true, len)
+                    .with_write_sequence(ctx.write_sequence),
                 _ => ctx,
             }
         } else {
             WritingViewContext::new(ctx.uid, ctx.data_blocks, false, len)
+                .with_write_sequence(ctx.write_sequence)
         };
 
-        self.store.insert(context).await?;
+        if let Err(err) = self.store.insert(context).await {
+            meta.unmark_received_blocks(&block_ids);
+            return Err(err);
+        }
         Ok(len as i32)
     }
 
+    /// Split the partition at the block sequence for the reassignment, the writing of the
+    /// later blocks is rejected since then. The former split point is kept if split already,
+    /// since the blocks up to it may have been accepted. Returns the effective split point.
+    pub fn split_partition(&self, uid: &PartitionedUId, sequence: i64) -> Result<i64> {
+        let layout = self.block_id_layout.unwrap_or_default();
+        if sequence < 0 || sequence > layout.max_sequence() {
+            return Err(anyhow!(
+                "Illegal split block sequence: {} beyond the layout: {}",
+                sequence,
+                layout
+            ));
+        }
+        let split = *self
+            .partition_splits
+            .entry((uid.shuffle_id, uid.partition_id))
+            .or_insert_with(|| {
+                TOTAL_REASSIGNED_PARTITIONS.inc();
+                sequence
+            });
+        info!(
+            "The partition: {:?} is split at the block sequence: {}",
+            uid, split
+        );
+        Ok(split)
+    }
+
+    pub fn partition_split(&self, uid: &PartitionedUId) -> Option<i64> {
+        self.partition_splits
+            .get(&(uid.shuffle_id, uid.partition_id))
+            .map(|x| *x.value())
+    }
+
+    // the whole request is rejected, so that the client could resend the blocks beyond the split
+    fn check_partition_split(&self, ctx: &WritingViewContext) -> Result<(), WorkerError> {
+        let split = match self.partition_split(&ctx.uid) {
+            Some(split) => split,
+            _ => return Ok(()),
+        };
+        let layout = self.block_id_layout.unwrap_or_default();
+        let rejected = ctx
+            .data_blocks
+            .iter()
+            .filter(|block| layout.decode(block.block_id).sequence > split)
+            .count();
+        if rejected > 0 {
+            TOTAL_REASSIGNED_BLOCKS_REJECTED.inc_by(rejected as u64);
+            return Err(WorkerError::PARTITION_REASSIGNED(split));
+        }
+        Ok(())
+    }
+
     pub async fn select(&self, ctx: ReadingViewContext) -> Result<ResponseData, WorkerError> {
         self.heartbeat()?;
 
@@ -332,6 +948,29 @@ impl App {
         })
     }
 
+    pub async fn select_merged(
+        &self,
+        uid: &PartitionedUId,
+        task_ids: Option<Treemap>,
+    ) -> Result<PartitionedMergedData, WorkerError> {
+        self.heartbeat()?;
+
+        let data = self.store.get_merged(uid, task_ids).await?;
+        for (segment, source) in data
+            .shuffle_data_block_segments
+            .iter()
+            .zip(data.block_sources.iter())
+        {
+            let length = segment.length as u64;
+            match source {
+                BlockSource::MEMORY => TOTAL_READ_DATA_FROM_MEMORY.inc_by(length),
+                BlockSource::LOCALFILE => TOTAL_READ_DATA_FROM_LOCALFILE.inc_by(length),
+            }
+            TOTAL_READ_DATA.inc_by(length);
+        }
+        Ok(data)
+    }
+
     pub async fn list_index(
         &self,
         ctx: ReadingIndexViewContext,
@@ -341,13 +980,43 @@ impl App {
         self.store.get_index(ctx).await
     }
 
-    async fn is_huge_partition(&self, uid: &PartitionedUId, len: Option<u64>) -> Result<bool> {
-        let huge_partition_threshold = self.huge_partition_marked_threshold.unwrap();
-        let mut meta = self.get_partition_meta(uid);
+    // the partition size and the hot partitions are updated together, both of the huge
+    // partition marking and the hot partitions report are derived from them
+    fn record_written(
+        &self,
+        meta: &mut PartitionedMeta,
+        uid: &PartitionedUId,
+        len: u64,
+    ) -> Result<()> {
+        meta.inc_size(len as i32)?;
+        self.hot_partitions
+            .add((uid.shuffle_id, uid.partition_id), len);
+        Ok(())
+    }
 
-        if len.is_some() {
-            meta.inc_size(len.unwrap() as i32)?;
+    /// The top n partitions by the written bytes, the counts are overestimated by the errors
+    /// at most when more partitions than the sketch capacity are written.
+    pub fn hot_partitions(&self, n: usize) -> HotPartitions {
+        HotPartitions {
+            total: self.hot_partitions.total(),
+            partitions: self.hot_partitions.top(n),
         }
+    }
+
+    /// The tracked partitions written more than the share of the app total.
+    pub fn skewed_partitions(&self, share: f64) -> Vec<HeavyHitter<(i32, i32)>> {
+        let sketch = &self.hot_partitions;
+        let total = sketch.total();
+        sketch
+            .top(sketch.len())
+            .into_iter()
+            .filter(|x| total > 0 && x.count as f64 > total as f64 * share)
+            .collect()
+    }
+
+    async fn is_huge_partition(&self, uid: &PartitionedUId) -> Result<bool> {
+        let huge_partition_threshold = self.huge_partition_marked_threshold.unwrap();
+        let meta = self.get_partition_meta(uid);
 
         let data_size = meta.get_size()?;
         if data_size > huge_partition_threshold {
@@ -357,7 +1026,7 @@ impl App {
     }
 
     async fn is_backpressure_for_huge_partition(&self, uid: &PartitionedUId) -> Result<bool> {
-        if !self.is_huge_partition(uid, None).await? {
+        if !self.is_huge_partition(uid).await? {
             return Ok(false);
         }
         let huge_partition_memory_used = &self.huge_partition_memory_max_available_size;
@@ -414,43 +1083,192 @@ impl App {
     }
 
     fn get_partition_meta(&self, uid: &PartitionedUId) -> PartitionedMeta {
-        let shuffle_id = uid.shuffle_id;
-        let partition_id = uid.partition_id;
-        let partitioned_meta = self
-            .bitmap_of_blocks
-            .entry((shuffle_id, partition_id))
-            .or_insert_with(|| PartitionedMeta::new());
-        partitioned_meta.clone()
+        let key = (uid.shuffle_id, uid.partition_id);
+        // only the shard read lock for the existing one
+        if let Some(partitioned_meta) = self.bitmap_of_blocks.get(&key) {
+            return partitioned_meta.clone();
+        }
+        self.bitmap_of_blocks
+            .entry(key)
+            .or_insert_with(|| PartitionedMeta::new())
+            .clone()
     }
 
-    pub fn get_block_ids(&self, ctx: GetBlocksContext) -> Result<Bytes> {
+    pub async fn get_block_ids(&self, ctx: GetBlocksContext) -> Result<Bytes> {
         debug!("get blocks: {:?}", ctx.clone());
+        self.read_shuffles.lock().insert(ctx.uid.shuffle_id);
         let partitioned_meta = self.get_partition_meta(&ctx.uid);
-        partitioned_meta.get_block_ids()
+        match &self.shuffle_result_offload {
+            Some(offload) => {
+                partitioned_meta
+                    .get_block_ids_with_reloading(&offload.runtime)
+                    .await
+            }
+            _ => partitioned_meta.get_block_ids(),
+        }
     }
 
-    pub fn get_block_ids_bitmap(&self, ctx: GetBlocksContext) -> Result<Treemap> {
+    pub async fn get_block_ids_bitmap(&self, ctx: GetBlocksContext) -> Result<Treemap> {
+        self.read_shuffles.lock().insert(ctx.uid.shuffle_id);
         let partitioned_meta = self.get_partition_meta(&ctx.uid);
-        partitioned_meta.get_block_ids_bitmap()
+        match &self.shuffle_result_offload {
+            Some(offload) => {
+                partitioned_meta
+                    .get_block_ids_bitmap_with_reloading(&offload.runtime)
+                    .await
+            }
+            _ => partitioned_meta.get_block_ids_bitmap(),
+        }
     }
 
     pub async fn report_block_ids(&self, ctx: ReportBlocksContext) -> Result<()> {
         self.heartbeat()?;
 
         debug!("Report blocks: {:?}", ctx.clone());
+        // only checked for the client declaring its layout
+        if let Some(layout) = &self.block_id_layout {
+            let partition_id = ctx.uid.partition_id;
+            if let Some(id) = ctx
+                .blocks
+                .iter()
+                .find(|id| layout.decode(**id).partition_id != partition_id)
+            {
+                return Err(WorkerError::ILLEGAL_BLOCK_ID(*id, partition_id).into());
+            }
+        }
         let mut partitioned_meta = self.get_partition_meta(&ctx.uid);
-        partitioned_meta.report_block_ids(ctx.blocks)?;
+        match &self.shuffle_result_offload {
+            Some(offload) => {
+                partitioned_meta
+                    .report_block_ids_with_reloading(ctx.blocks, &offload.runtime)
+                    .await?
+            }
+            _ => partitioned_meta.report_block_ids(ctx.blocks)?,
+        }
 
         Ok(())
     }
 
+    /// The block ids number of the shuffle results kept in memory.
+    pub fn resident_shuffle_result_blocks(&self) -> u64 {
+        let metas: Vec<PartitionedMeta> = self
+            .bitmap_of_blocks
+            .iter()
+            .map(|x| x.value().clone())
+            .collect();
+        metas.iter().map(|x| x.resident_block_number()).sum()
+    }
+
+    /// Offload the results of the read shuffles to the localfile once the resident block ids
+    /// exceed the threshold, they will be reloaded on reading. Returns the offloaded partitions number.
+    pub fn offload_shuffle_results(&self) -> Result<usize> {
+        let offload = match &self.shuffle_result_offload {
+            Some(offload) => offload,
+            _ => return Ok(0),
+        };
+        if self.resident_shuffle_result_blocks() <= offload.threshold {
+            return Ok(0);
+        }
+        let read_shuffles = self.read_shuffles.lock().clone();
+        // collected firstly to avoid holding the dashmap shard locks on the io
+        let metas: Vec<((i32, i32), PartitionedMeta)> = self
+            .bitmap_of_blocks
+            .iter()
+            .filter(|x| read_shuffles.contains(&x.key().0))
+            .map(|x| (*x.key(), x.value().clone()))
+            .collect();
+        let mut offloaded = 0;
+        for ((shuffle_id, partition_id), meta) in metas {
+            let path = offload
+                .dir
+                .join(shuffle_id.to_string())
+                .join(partition_id.to_string());
+            if meta.offload_block_ids(&path)? {
+                offloaded += 1;
+            }
+        }
+        TOTAL_SHUFFLE_RESULT_OFFLOADED.inc_by(offloaded as u64);
+        Ok(offloaded)
+    }
+
+    fn remove_offloaded_shuffle_results(&self, shuffle_id: Option<i32>) {
+        if let Some(offload) = &self.shuffle_result_offload {
+            let dir = match shuffle_id {
+                Some(shuffle_id) => offload.dir.join(shuffle_id.to_string()),
+                _ => offload.dir.clone(),
+            };
+            match std::fs::remove_dir_all(&dir) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                    warn!(
+                        "Errors on removing the offloaded shuffle results: {:?}. error: {:?}",
+                        &dir, e
+                    );
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// The in-flight writing of the purged shuffles is waited out, and the later one
+    /// is regarded as the rewriting after purged.
     pub async fn purge(&self, app_id: String, shuffle_id: Option<i32>) -> Result<()> {
+        let barriers: Vec<(i32, Arc<tokio::sync::RwLock<()>>)> = match shuffle_id {
+            Some(shuffle_id) => vec![(shuffle_id, self.shuffle_barrier(shuffle_id))],
+            _ => {
+                let mut barriers: Vec<_> = self
+                    .shuffle_barriers
+                    .iter()
+                    .map(|x| (*x.key(), x.value().clone()))
+                    .collect();
+                // locked in the same order
+                barriers.sort_by_key(|(shuffle_id, _)| *shuffle_id);
+                barriers
+            }
+        };
+        // held until purged
+        let mut barrier_guards = Vec::with_capacity(barriers.len());
+        for (_, barrier) in barriers {
+            barrier_guards.push(
+                barrier
+                    .write_owned()
+                    .instrument_await("waiting for the in-flight writing")
+                    .await,
+            );
+        }
+
         let removed_size = self
             .store
             .purge(PurgeDataContext::new(app_id, shuffle_id))
             .await?;
         self.total_resident_data_size
             .fetch_sub(removed_size as u64, SeqCst);
+        self.quota_rejected.store(0, SeqCst);
+        match shuffle_id {
+            Some(shuffle_id) => {
+                self.untrack_partitions(Some(shuffle_id));
+                // the shuffle could be rewritten with the same block ids after unregistered
+                for mut meta in self.bitmap_of_blocks.iter_mut() {
+                    if meta.key().0 == shuffle_id {
+                        meta.value_mut().clear_received_blocks();
+                        meta.value_mut().clear_offloaded_blocks();
+                    }
+                }
+                self.read_shuffles.lock().remove(&shuffle_id);
+                self.remove_offloaded_shuffle_results(Some(shuffle_id));
+                self.hot_partitions.retain(|(id, _)| *id != shuffle_id);
+                self.write_sequences.remove(&shuffle_id);
+                self.partition_splits.retain(|(id, _), _| *id != shuffle_id);
+                self.shuffle_barriers.remove(&shuffle_id);
+            }
+            _ => {
+                self.hot_partitions.retain(|_| false);
+                self.write_sequences.clear();
+                self.partition_splits.clear();
+                self.read_shuffles.lock().clear();
+                self.remove_offloaded_shuffle_results(None);
+                self.untrack_partitions(None);
+            }
+        }
         Ok(())
     }
 
@@ -461,6 +1279,47 @@ impl App {
     pub fn total_resident_data_size(&self) -> u64 {
         self.total_resident_data_size.load(SeqCst)
     }
+
+    pub fn quota(&self) -> AppQuota {
+        self.quota
+    }
+
+    pub fn quota_rejected(&self) -> u64 {
+        self.quota_rejected.load(SeqCst)
+    }
+
+    pub fn is_quota_exceeded(&self) -> bool {
+        self.quota
+            .max_total_bytes
+            .map_or(false, |max| self.total_resident_data_size() >= max)
+    }
+
+    /// Spill the staging buffers of the app once its memory exceeds the quota,
+    /// returns whether spilled.
+    pub async fn memory_quota_spill(&self) -> Result<bool> {
+        let max = match self.quota.max_memory_bytes {
+            Some(max) => max,
+            _ => return Ok(false),
+        };
+        let uids = self.all_partition_uids();
+        let memory_bytes = self.store.memory_buffer_size(&uids)?;
+        if memory_bytes <= max {
+            return Ok(false);
+        }
+        info!(
+            "Spilling the app:[{}] whose memory: {} exceeds the quota: {}",
+            &self.app_id, memory_bytes, max
+        );
+        self.store.app_memory_quota_spill(uids).await?;
+        Ok(true)
+    }
+}
+
+pub struct HotPartitions {
+    // the written bytes of the app partitions being tracked
+    pub total: u64,
+    // key: (shuffle_id, partition_id)
+    pub partitions: Vec<HeavyHitter<(i32, i32)>>,
 }
 
 #[derive(Debug, Clone)]
@@ -501,6 +1360,8 @@ pub struct WritingViewContext {
     pub data_blocks: Vec<Block>,
     pub owned_by_huge_partition: bool,
     pub data_size: u64,
+    // the write sequence of the shuffle assigned to the accepted request
+    pub write_sequence: Option<u64>,
 }
 
 impl WritingViewContext {
@@ -510,6 +1371,7 @@ impl WritingViewContext {
             data_blocks,
             owned_by_huge_partition: false,
             data_size: 0,
+            write_sequence: None,
         }
     }
 
@@ -524,8 +1386,14 @@ impl WritingViewContext {
             data_blocks,
             owned_by_huge_partition,
             data_size,
+            write_sequence: None,
         }
     }
+
+    pub fn with_write_sequence(mut self, write_sequence: Option<u64>) -> Self {
+        self.write_sequence = write_sequence;
+        self
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -597,25 +1465,45 @@ pub struct AppManager {
     receiver: async_channel::Receiver<PurgeEvent>,
     sender: async_channel::Sender<PurgeEvent>,
     store: Arc<HybridStore>,
-    app_heartbeat_timeout_min: u32,
+    app_heartbeat_timeout: Duration,
+    // the heartbeat timeout apps are not evicted within this period after started
+    startup_grace: Duration,
+    started_timestamp: u64,
     config: Config,
     runtime_manager: RuntimeManager,
+    decommission_state: RwLock<DecommissionState>,
+    partition_counter: Arc<PartitionCounter>,
+    app_number_limit: AppNumberLimit,
+    busy_score_sampler: BusyScoreSampler,
 }
 
 impl AppManager {
     fn new(runtime_manager: RuntimeManager, config: Config) -> Self {
         let (sender, receiver) = async_channel::unbounded();
-        let app_heartbeat_timeout_min = config.app_config.app_heartbeat_timeout_min;
+        let app_heartbeat_timeout = config.app_config.app_heartbeat_timeout().unwrap();
+        let startup_grace = config.app_config.startup_grace();
         let store = Arc::new(StoreProvider::get(runtime_manager.clone(), config.clone()));
         store.clone().start();
+        HEALTH_REGISTRY.register("store", store.clone());
+        let partition_counter = Arc::new(PartitionCounter::new(
+            config.app_config.max_partitions_per_server,
+        ));
+        let app_number_limit = AppNumberLimit::new(config.app_config.max_concurrent_apps());
+        let busy_score_sampler = BusyScoreSampler::new(config.busy_score.clone(), store.clone());
         let manager = AppManager {
             apps: DashMap::new(),
             receiver,
             sender,
             store,
-            app_heartbeat_timeout_min,
+            app_heartbeat_timeout,
+            startup_grace,
+            started_timestamp: now_timestamp_as_sec(),
             config,
             runtime_manager: runtime_manager.clone(),
+            decommission_state: RwLock::new(DecommissionState::NONE),
+            partition_counter,
+            app_number_limit,
+            busy_score_sampler,
         };
         manager
     }
@@ -626,84 +1514,227 @@ impl AppManager {
         let app_ref = Arc::new(AppManager::new(runtime_manager.clone(), config));
         let app_manager_ref_cloned = app_ref.clone();
 
-        runtime_manager.default_runtime.spawn(async move {
-            let await_root = AWAIT_TREE_REGISTRY.clone()
-                .register(format!("App heartbeat periodic checker"))
-                .await;
-            await_root.instrument(async move {
-                info!("Starting app heartbeat checker...");
-                loop {
-                    // task1: find out heartbeat timeout apps
-                    tokio::time::sleep(Duration::from_secs(10))
-                        .instrument_await("sleeping for 10s...")
-                        .await;
-
-                    for item in app_manager_ref_cloned.apps.iter() {
-                        let (key, app) = item.pair();
-                        let last_time = app.get_latest_heartbeat_time();
-                        let current = now_timestamp_as_sec();
-
-                        if current - last_time
-                            > (app_manager_ref_cloned.app_heartbeat_timeout_min * 60) as u64
-                        {
-                            info!("Detected app:{:?} heartbeat timeout. now: {:?}, latest heartbeat: {:?}. timeout threshold: {:?}(min)",
-                            key, current, last_time, app_manager_ref_cloned.app_heartbeat_timeout_min);
-                            if app_manager_ref_cloned
-                                .sender
-                                .send(PurgeEvent::HEARTBEAT_TIMEOUT(key.clone()))
-                                .await
-                                .is_err()
-                            {
-                                error!(
-                                "Errors on sending purge event when app: {} heartbeat timeout",
-                                key
-                            );
+        runtime_manager
+            .default_runtime
+            .spawn_guarded("app_heartbeat_checker", async move {
+                let await_root = AWAIT_TREE_REGISTRY
+                    .clone()
+                    .register_long_running(format!("App heartbeat periodic checker"))
+                    .await;
+                await_root
+                    .instrument(async move {
+                        info!("Starting app heartbeat checker...");
+                        let heartbeat_timeout = app_manager_ref_cloned.app_heartbeat_timeout;
+                        let check_interval = heartbeat_timeout.min(Duration::from_secs(10));
+                        loop {
+                            // task1: find out heartbeat timeout apps
+                            tokio::time::sleep(check_interval)
+                                .instrument_await(format!("sleeping for {:?}...", check_interval))
+                                .await;
+
+                            let timeout_apps = app_manager_ref_cloned
+                                .heartbeat_timeout_apps(now_timestamp_as_sec());
+                            for key in timeout_apps {
+                                if app_manager_ref_cloned
+                                    .sender
+                                    .send(PurgeEvent::HEARTBEAT_TIMEOUT(key.clone()))
+                                    .await
+                                    .is_err()
+                                {
+                                    error!(
+                                        "Errors on sending purge event when app: {} heartbeat timeout",
+                                        key
+                                    );
+                                }
                             }
                         }
-                    }
-                }
-            }).await;
-        });
+                    })
+                    .await;
+            });
 
         // calculate topN app shuffle data size
         let app_manager_ref = app_ref.clone();
-        runtime_manager.default_runtime.spawn(async move {
-            let await_root = AWAIT_TREE_REGISTRY
-                .clone()
-                .register(format!("App topN periodic statistics"))
-                .await;
-            await_root
-                .instrument(async move {
-                    info!("Starting calculating topN app shuffle data size...");
-                    loop {
-                        tokio::time::sleep(Duration::from_secs(10))
-                            .instrument_await("sleeping for 10s...")
-                            .await;
-
-                        let view = app_manager_ref.apps.clone().into_read_only();
-                        let mut apps: Vec<_> = view.values().collect();
-                        apps.sort_by_key(|x| 0 - x.total_resident_data_size());
-
-                        let top_n = 10;
-                        let limit = if apps.len() > top_n {
-                            top_n
-                        } else {
-                            apps.len()
-                        };
-                        for idx in 0..limit {
-                            GAUGE_TOPN_APP_RESIDENT_DATA_SIZE
-                                .with_label_values(&[&apps[idx].app_id])
-                                .set(apps[idx].total_resident_data_size() as i64);
+        runtime_manager
+            .default_runtime
+            .spawn_guarded("app_topn_statistics", async move {
+                let await_root = AWAIT_TREE_REGISTRY
+                    .clone()
+                    .register_long_running(format!("App topN periodic statistics"))
+                    .await;
+                await_root
+                    .instrument(async move {
+                        info!("Starting calculating topN app shuffle data size...");
+                        loop {
+                            tokio::time::sleep(Duration::from_secs(10))
+                                .instrument_await("sleeping for 10s...")
+                                .await;
+
+                            let view = app_manager_ref.apps.clone().into_read_only();
+                            let mut apps: Vec<_> = view.values().collect();
+                            apps.sort_by_key(|x| 0 - x.total_resident_data_size());
+
+                            let top_n = 10;
+                            let limit = if apps.len() > top_n {
+                                top_n
+                            } else {
+                                apps.len()
+                            };
+                            for idx in 0..limit {
+                                GAUGE_TOPN_APP_RESIDENT_DATA_SIZE
+                                    .with_label_values(&[&apps[idx].app_id])
+                                    .set(apps[idx].total_resident_data_size() as i64);
+                            }
+
+                            let lag = apps.iter().map(|x| x.flush_watermark_lag()).max();
+                            GAUGE_FLUSH_WATERMARK_LAG.set(lag.unwrap_or(0) as i64);
                         }
-                    }
-                })
-                .await;
-        });
+                    })
+                    .await;
+            });
+
+        // sample the busy score attached in the responses
+        let app_manager_ref = app_ref.clone();
+        runtime_manager
+            .default_runtime
+            .spawn_guarded("busy_score_sampler", async move {
+                let await_root = AWAIT_TREE_REGISTRY
+                    .clone()
+                    .register_long_running(format!("Busy score periodic sampler"))
+                    .await;
+                await_root
+                    .instrument(async move {
+                        info!("Starting sampling the busy score...");
+                        loop {
+                            app_manager_ref.busy_score_sampler.sample();
+                            tokio::time::sleep(BUSY_SCORE_SAMPLE_INTERVAL)
+                                .instrument_await("sleeping for 1s...")
+                                .await;
+                        }
+                    })
+                    .await;
+            });
+
+        // spill the apps exceeding the memory quota periodically
+        if app_ref.config.app_config.has_memory_quota() {
+            let app_manager_ref = app_ref.clone();
+            runtime_manager
+                .default_runtime
+                .spawn_guarded("app_memory_quota_checker", async move {
+                    let await_root = AWAIT_TREE_REGISTRY
+                        .clone()
+                        .register_long_running(format!("App memory quota periodic checker"))
+                        .await;
+                    await_root
+                        .instrument(async move {
+                            info!("Starting checking the app memory quota...");
+                            loop {
+                                tokio::time::sleep(APP_MEMORY_QUOTA_CHECK_INTERVAL)
+                                    .instrument_await("sleeping for 1s...")
+                                    .await;
+
+                                for app in app_manager_ref.list_apps() {
+                                    if let Err(e) = app.memory_quota_spill().await {
+                                        error!(
+                                            "Errors on spilling the app:[{}] exceeding the memory quota. error: {:?}",
+                                            app.app_id(),
+                                            e
+                                        );
+                                    }
+                                }
+                            }
+                        })
+                        .await;
+                });
+        }
+
+        // offload the shuffle results of the apps exceeding the threshold periodically
+        if app_ref
+            .config
+            .app_config
+            .shuffle_result_offload_threshold
+            .is_some()
+            && app_ref.config.localfile_store.is_some()
+        {
+            let app_manager_ref = app_ref.clone();
+            runtime_manager
+                .default_runtime
+                .spawn_guarded("app_shuffle_result_offloader", async move {
+                    let await_root = AWAIT_TREE_REGISTRY
+                        .clone()
+                        .register_long_running(format!("App shuffle result periodic offloader"))
+                        .await;
+                    await_root
+                        .instrument(async move {
+                            info!("Starting offloading the shuffle results...");
+                            loop {
+                                tokio::time::sleep(Duration::from_secs(10))
+                                    .instrument_await("sleeping for 10s...")
+                                    .await;
+
+                                for app in app_manager_ref.list_apps() {
+                                    match app.offload_shuffle_results() {
+                                        Ok(0) => {}
+                                        Ok(offloaded) => info!(
+                                            "Offloaded {} partitions shuffle results of app:[{}]",
+                                            offloaded,
+                                            app.app_id()
+                                        ),
+                                        Err(e) => error!(
+                                            "Errors on offloading the shuffle results of app:[{}]. error: {:?}",
+                                            app.app_id(),
+                                            e
+                                        ),
+                                    }
+                                }
+                            }
+                        })
+                        .await;
+                });
+        }
+
+        // report the skewed partitions of apps periodically
+        if let Some(share) = app_ref.config.app_config.hot_partition_report_share {
+            let app_manager_ref = app_ref.clone();
+            runtime_manager
+                .default_runtime
+                .spawn_guarded("app_hot_partitions_reporter", async move {
+                    let await_root = AWAIT_TREE_REGISTRY
+                        .clone()
+                        .register_long_running(format!("App hot partitions periodic reporter"))
+                        .await;
+                    await_root
+                        .instrument(async move {
+                            info!("Starting reporting hot partitions exceeding the share: {}...", share);
+                            loop {
+                                tokio::time::sleep(Duration::from_secs(60))
+                                    .instrument_await("sleeping for 60s...")
+                                    .await;
+
+                                for app in app_manager_ref.list_apps() {
+                                    let total = app.hot_partitions(0).total;
+                                    for hitter in app.skewed_partitions(share) {
+                                        let (shuffle_id, partition_id) = hitter.key;
+                                        warn!(
+                                            "Detected skewed partition of app:[{}], shuffle_id: {}, partition_id: {}. written: {} (error: {}) of the total: {}",
+                                            app.app_id(),
+                                            shuffle_id,
+                                            partition_id,
+                                            hitter.count,
+                                            hitter.error,
+                                            total
+                                        );
+                                    }
+                                }
+                            }
+                        })
+                        .await;
+                });
+        }
 
         let app_manager_cloned = app_ref.clone();
-        runtime_manager.default_runtime.spawn(async move {
+        runtime_manager.default_runtime.spawn_guarded("app_purger", async move {
             let await_root = AWAIT_TREE_REGISTRY.clone()
-                .register(format!("App periodic purger"))
+                .register_long_running(format!("App periodic purger"))
                 .await;
             await_root.instrument(async move {
                 info!("Starting purge event handler...");
@@ -736,6 +1767,30 @@ impl AppManager {
         app_ref
     }
 
+    /// The apps whose heartbeat timeout at the given time, which are kept within the startup grace.
+    fn heartbeat_timeout_apps(&self, current: u64) -> Vec<String> {
+        let started = current.saturating_sub(self.started_timestamp);
+        if started < self.startup_grace.as_secs() {
+            debug!(
+                "Skipped checking the app heartbeat within the startup grace: {:?}",
+                self.startup_grace
+            );
+            return vec![];
+        }
+        let heartbeat_timeout = self.app_heartbeat_timeout;
+        let mut timeout_apps = vec![];
+        for item in self.apps.iter() {
+            let (key, app) = item.pair();
+            let last_time = app.get_latest_heartbeat_time();
+            if current.saturating_sub(last_time) > heartbeat_timeout.as_secs() {
+                info!("Detected app:{:?} heartbeat timeout. now: {:?}, latest heartbeat: {:?}. timeout threshold: {:?}",
+                    key, current, last_time, heartbeat_timeout);
+                timeout_apps.push(key.clone());
+            }
+        }
+        timeout_apps
+    }
+
     pub async fn store_is_healthy(&self) -> Result<bool> {
         self.store.is_healthy().await
     }
@@ -748,6 +1803,61 @@ impl AppManager {
         self.store.memory_spill_event_num()
     }
 
+    pub async fn store_spill_status(&self) -> SpillStatus {
+        self.store.spill_status().await
+    }
+
+    pub fn store_memory_buffers_snapshot(&self) -> MemorySnapshot {
+        self.store.memory_store_snapshot()
+    }
+
+    pub fn store_read_cache_stats(&self) -> ReadCacheStats {
+        self.store.read_cache_stats()
+    }
+
+    pub fn store_clear_read_cache(&self) -> u64 {
+        self.store.clear_read_cache()
+    }
+
+    pub async fn store_spill_all(&self) -> Result<()> {
+        self.store.spill_all().await
+    }
+
+    /// The latest sampled busy score from 0 to 100.
+    pub fn busy_score(&self) -> u32 {
+        self.busy_score_sampler.score()
+    }
+
+    pub fn decommission_state(&self) -> DecommissionState {
+        *self.decommission_state.read()
+    }
+
+    pub(crate) fn set_decommission_state(&self, state: DecommissionState) {
+        *self.decommission_state.write() = state;
+    }
+
+    pub fn app_number(&self) -> usize {
+        self.apps.len()
+    }
+
+    pub fn partition_number(&self) -> usize {
+        self.partition_counter.count()
+    }
+
+    pub fn max_app_number(&self) -> Option<usize> {
+        self.app_number_limit.max
+    }
+
+    pub fn max_partition_number(&self) -> Option<usize> {
+        self.partition_counter.max
+    }
+
+    /// Whether the app or partition limit is reached. It's not reported as unhealthy,
+    /// the coordinator compares the counts with the limits in the heartbeat instead.
+    pub fn is_limit_reached(&self) -> bool {
+        self.app_number_limit.is_reached() || self.partition_counter.is_exhausted()
+    }
+
     async fn purge_app_data(&self, app_id: String, shuffle_id_option: Option<i32>) -> Result<()> {
         let app = self.get_app(&app_id).ok_or(anyhow!(format!(
             "App:{} don't exist when purging data, this should not happen",
@@ -756,7 +1866,9 @@ impl AppManager {
         app.purge(app_id.clone(), shuffle_id_option).await?;
 
         if shuffle_id_option.is_none() {
-            self.apps.remove(&app_id);
+            if self.apps.remove(&app_id).is_some() {
+                self.app_number_limit.release();
+            }
 
             GAUGE_APP_NUMBER.dec();
             let _ = GAUGE_TOPN_APP_RESIDENT_DATA_SIZE.remove_label_values(&[&app_id]);
@@ -769,6 +1881,10 @@ impl AppManager {
         self.apps.get(app_id).map(|v| v.value().clone())
     }
 
+    pub fn list_apps(&self) -> Vec<Arc<App>> {
+        self.apps.iter().map(|v| v.value().clone()).collect()
+    }
+
     pub fn register(
         &self,
         app_id: String,
@@ -780,18 +1896,55 @@ impl AppManager {
             app_id.clone(),
             shuffle_id
         );
-        let app_ref = self.apps.entry(app_id.clone()).or_insert_with(|| {
-            TOTAL_APP_NUMBER.inc();
-            GAUGE_APP_NUMBER.inc();
-
-            Arc::new(App::from(
-                app_id,
-                app_config_options,
-                self.store.clone(),
-                self.runtime_manager.clone(),
-                &self.config,
-            ))
-        });
+        // the registered apps are still being served when decommissioning
+        if !self.decommission_state().is_accepting_new_apps() && !self.apps.contains_key(&app_id) {
+            return Err(anyhow!(
+                "Rejected the registry of app: {} due to decommissioning",
+                &app_id
+            ));
+        }
+        // the client packing the block ids differently is rejected before writing
+        if let Some(layout) = app_config_options.block_id_layout {
+            if layout != self.config.block_id_layout {
+                return Err(WorkerError::BLOCK_ID_LAYOUT_MISMATCHED(
+                    layout,
+                    self.config.block_id_layout,
+                )
+                .into());
+            }
+        }
+        // the limits are checked within the entry lock to admit and insert the new app atomically
+        let app_ref = match self.apps.entry(app_id.clone()) {
+            Entry::Occupied(entry) => entry.get().clone(),
+            Entry::Vacant(entry) => {
+                if self.partition_counter.is_exhausted() {
+                    return Err(WorkerError::PARTITION_NUMBER_EXCEEDED(
+                        self.config
+                            .app_config
+                            .max_partitions_per_server
+                            .unwrap_or(0),
+                    )
+                    .into());
+                }
+                if let Err(e) = self.app_number_limit.admit() {
+                    warn!("Rejected the registry of app: {}. {}", &app_id, e);
+                    return Err(e.into());
+                }
+                TOTAL_APP_NUMBER.inc();
+                GAUGE_APP_NUMBER.inc();
+
+                entry
+                    .insert(Arc::new(App::from(
+                        app_id,
+                        app_config_options,
+                        self.store.clone(),
+                        self.runtime_manager.clone(),
+                        &self.config,
+                        self.partition_counter.clone(),
+                    )))
+                    .clone()
+            }
+        };
         app_ref.register_shuffle(shuffle_id)
     }
 
@@ -841,16 +1994,34 @@ impl PartitionedUId {
 #[cfg(test)]
 mod test {
     use crate::app::{
-        AppManager, GetBlocksContext, PartitionedUId, ReadingOptions, ReadingViewContext,
-        ReportBlocksContext, WritingViewContext,
+        AppConfigOptions, AppManager, AppNumberLimit, AppQuota, GetBlocksContext, PartitionedUId,
+        ReadingIndexViewContext, ReadingOptions, ReadingViewContext, ReportBlocksContext,
+        WritingViewContext, SHUFFLE_RESULT_OFFLOAD_DIR,
+    };
+    use crate::block_id::BlockIdLayout;
+    use crate::config::{
+        AppQuotaConfig, Config, HybridStoreConfig, LocalfileStoreConfig, MemoryStoreConfig,
+        StorageType,
     };
-    use crate::config::{Config, HybridStoreConfig, LocalfileStoreConfig, MemoryStoreConfig};
 
+    use crate::constant::StatusCode;
+    use crate::error::WorkerError;
+    use crate::metric::{
+        TOTAL_APP_REGISTRATION_REJECTED, TOTAL_DUPLICATE_BLOCKS_DROPPED,
+        TOTAL_MEMORY_SPILL_TRIGGERED, TOTAL_SHUFFLE_RESULT_RELOADED,
+    };
     use crate::runtime::manager::RuntimeManager;
-    use crate::store::{Block, ResponseData};
+    use crate::store::fault::{FaultKind, FaultOperation, FaultRule, FAULT_INJECTOR};
+    use crate::store::{Block, ResponseData, ResponseDataIndex};
+    use bytes::Bytes;
     use croaring::treemap::JvmSerializer;
     use croaring::Treemap;
     use dashmap::DashMap;
+    use rand::rngs::StdRng;
+    use rand::seq::SliceRandom;
+    use rand::SeedableRng;
+    use std::path::Path;
+    use std::time::Duration;
 
     #[test]
     fn test_uid_hash() {
@@ -951,6 +2122,743 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_duplicate_blocks_dropped() -> anyhow::Result<()> {
+        let runtime_manager: RuntimeManager = Default::default();
+        let mut config = mock_config();
+        config.store_type = StorageType::MEMORY_LOCALFILE;
+        let app_manager_ref = AppManager::get_ref(runtime_manager.clone(), config);
+        app_manager_ref.register("app_1".to_string(), 1, Default::default())?;
+        let app = app_manager_ref.get_app("app_1").unwrap();
+
+        let uid = PartitionedUId::from("app_1".to_string(), 1, 0);
+        let blocks: Vec<Block> = (0..2)
+            .map(|block_id| Block {
+                block_id,
+                length: 10,
+                uncompress_length: 20,
+                crc: 0,
+                data: Bytes::from(vec![0; 10]),
+                task_attempt_id: 0,
+            })
+            .collect();
+        let insert = |blocks: Vec<Block>| {
+            runtime_manager.wait(app.insert(WritingViewContext::new(
+                uid.clone(),
+                blocks,
+                false,
+                20,
+            )))
+        };
+        let dropped = TOTAL_DUPLICATE_BLOCKS_DROPPED.get();
+
+        // case1: the batch is sent twice
+        assert_eq!(20, insert(blocks.clone())?);
+        assert_eq!(0, insert(blocks.clone())?);
+        assert_eq!(20, app.total_resident_data_size());
+        assert_eq!(
+            20,
+            runtime_manager.wait(app.store.get_hot_store_memory_partitioned_buffer_size(&uid))?
+        );
+        let reading_ctx = ReadingViewContext {
+            uid: uid.clone(),
+            reading_options: ReadingOptions::MEMORY_LAST_BLOCK_ID_AND_MAX_SIZE(-1, 1000000),
+            serialized_expected_task_ids_bitmap: Default::default(),
+        };
+        match runtime_manager.wait(app.select(reading_ctx))? {
+            ResponseData::Mem(data) => assert_eq!(2, data.shuffle_data_block_segments.len()),
+            _ => panic!(),
+        }
+
+        // case2: the first copy has been flushed to disk
+        app.store.inc_used(20)?;
+        runtime_manager.wait(app.store.spill_all())?;
+        awaitility::at_most(Duration::from_secs(5))
+            .until(|| app.store.memory_spill_event_num().unwrap() == 0);
+        assert_eq!(0, insert(blocks.clone())?);
+        assert_eq!(
+            0,
+            runtime_manager.wait(app.store.get_hot_store_memory_partitioned_buffer_size(&uid))?
+        );
+        assert_eq!(20, app.total_resident_data_size());
+
+        assert_eq!(4, TOTAL_DUPLICATE_BLOCKS_DROPPED.get() - dropped);
+        Ok(())
+    }
+
+    #[test]
+    fn test_hot_partitions() -> anyhow::Result<()> {
+        let runtime_manager: RuntimeManager = Default::default();
+        let mut config = mock_config();
+        config.app_config.hot_partition_sketch_capacity = Some(16);
+        config.app_config.huge_partition_marked_threshold = Some("5K".to_string());
+        let app_manager_ref = AppManager::get_ref(runtime_manager.clone(), config);
+        app_manager_ref.register("app_1".to_string(), 1, Default::default())?;
+        let app = app_manager_ref.get_app("app_1").unwrap();
+
+        // the partition ranked i is written with the bytes proportional to 1 / i^2
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut partition_ids: Vec<i32> = (0..64).collect();
+        let mut exact = vec![0u64; 64];
+        let mut block_id = 0;
+        for _ in 0..10 {
+            partition_ids.shuffle(&mut rng);
+            for partition_id in &partition_ids {
+                let length = (1000 / (partition_id + 1).pow(2)).max(1);
+                let uid = PartitionedUId::from("app_1".to_string(), 1, *partition_id);
+                let block = Block {
+                    block_id,
+                    length,
+                    uncompress_length: length,
+                    crc: 0,
+                    data: Bytes::from(vec![0; length as usize]),
+                    task_attempt_id: 0,
+                };
+                block_id += 1;
+                runtime_manager.wait(app.insert(WritingViewContext::from(uid, vec![block])))?;
+                exact[*partition_id as usize] += length as u64;
+            }
+        }
+
+        let hot = app.hot_partitions(4);
+        assert_eq!(exact.iter().sum::<u64>(), hot.total);
+        assert_eq!(
+            vec![(1, 0), (1, 1), (1, 2), (1, 3)],
+            hot.partitions.iter().map(|x| x.key).collect::<Vec<_>>()
+        );
+        for hitter in &hot.partitions {
+            assert!(hitter.count >= exact[hitter.key.1 as usize]);
+        }
+
+        // the huge partition marking agrees with the report
+        let skewed = app.skewed_partitions(0.5);
+        assert_eq!(1, skewed.len());
+        assert_eq!((1, 0), skewed[0].key);
+        for partition_id in 0..64 {
+            let uid = PartitionedUId::from("app_1".to_string(), 1, partition_id);
+            assert_eq!(partition_id == 0, app.is_marked_huge_partition(&uid));
+        }
+
+        // reset on purging
+        runtime_manager.wait(app.purge("app_1".to_string(), Some(1)))?;
+        let hot = app.hot_partitions(4);
+        assert_eq!(0, hot.total);
+        assert!(hot.partitions.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_purge_with_concurrent_writing() -> anyhow::Result<()> {
+        let runtime_manager: RuntimeManager = Default::default();
+        let app_manager_ref = AppManager::get_ref(runtime_manager.clone(), mock_config());
+        let writers = 8;
+        let partitions = 16;
+        for round in 0..20 {
+            let app_id = format!("test_purge_with_concurrent_writing-{}", round);
+            app_manager_ref.register(app_id.clone(), 1, Default::default())?;
+            let app = app_manager_ref.get_app(&app_id).unwrap();
+
+            let mut handles = vec![];
+            for writer in 0..writers {
+                let app = app.clone();
+                let app_id = app_id.clone();
+                handles.push(runtime_manager.write_runtime.spawn(async move {
+                    for partition_id in 0..partitions {
+                        let block = Block {
+                            block_id: (writer * partitions + partition_id) as i64,
+                            length: 10,
+                            uncompress_length: 10,
+                            crc: 0,
+                            data: Bytes::from(vec![0; 10]),
+                            task_attempt_id: writer as i64,
+                        };
+                        let uid = PartitionedUId::from(app_id.clone(), 1, partition_id);
+                        app.insert(WritingViewContext::from(uid, vec![block]))
+                            .await
+                            .unwrap();
+                    }
+                }));
+            }
+            // racing with the writing, which is either purged or kept as the rewriting
+            runtime_manager.wait(app.purge(app_id.clone(), Some(1)))?;
+            for handle in handles {
+                runtime_manager.wait(handle)?;
+            }
+
+            // the kept data is always tracked by its partition
+            let tracked = app.partition_ids(1);
+            let mut resident = 0;
+            for partition_id in 0..partitions {
+                let uid = PartitionedUId::from(app_id.clone(), 1, partition_id);
+                let size = app.store.memory_buffer_size(&[uid])?;
+                assert!(size == 0 || tracked.contains(&partition_id));
+                resident += size;
+            }
+            assert_eq!(resident, app.total_resident_data_size());
+
+            runtime_manager.wait(app.purge(app_id.clone(), None))?;
+            assert_eq!(0, app.partition_number());
+            assert_eq!(0, app.total_resident_data_size());
+        }
+        assert_eq!(0, app_manager_ref.partition_number());
+        Ok(())
+    }
+
+    #[test]
+    fn test_flush_barrier() -> anyhow::Result<()> {
+        let app_id = "test_flush_barrier-----id";
+        let temp_dir = tempdir::TempDir::new("test_flush_barrier")?;
+        let temp_path = temp_dir.path().to_str().unwrap().to_string();
+        let config = Config::create_mem_localfile_config(21101, "1M".to_string(), temp_path);
+        let runtime_manager: RuntimeManager = Default::default();
+        let app_manager_ref = AppManager::get_ref(runtime_manager.clone(), config);
+        app_manager_ref.register(app_id.to_string(), 1, Default::default())?;
+        let app = app_manager_ref.get_app(app_id).unwrap();
+
+        // the slow flush of this app only
+        let mut rule = FaultRule::new(
+            FaultOperation::SpillInsert,
+            FaultKind::Latency { millis: 500 },
+        );
+        rule.app_id = Some(app_id.to_string());
+        FAULT_INJECTOR.add_rule(rule);
+
+        let writing_ctx = |partition_id: i32, block_id: i64, sequence: u64| {
+            let block = Block {
+                block_id,
+                length: 10,
+                uncompress_length: 10,
+                crc: 0,
+                data: Bytes::from(vec![0; 10]),
+                task_attempt_id: 0,
+            };
+            let uid = PartitionedUId::from(app_id.to_string(), 1, partition_id);
+            WritingViewContext::from(uid, vec![block]).with_write_sequence(Some(sequence))
+        };
+        let write = |partition_id: i32, block_id: i64| -> anyhow::Result<u64> {
+            let sequence = app.next_write_sequence(1);
+            runtime_manager.wait(app.insert(writing_ctx(
+                partition_id,
+                block_id,
+                sequence.sequence(),
+            )))?;
+            Ok(sequence.sequence())
+        };
+        let persisted_len = |partition_id: i32| -> anyhow::Result<i64> {
+            let ctx = ReadingIndexViewContext {
+                partition_id: PartitionedUId::from(app_id.to_string(), 1, partition_id),
+            };
+            match runtime_manager.wait(app.list_index(ctx))? {
+                ResponseDataIndex::Local(index) => Ok(index.data_file_len),
+            }
+        };
+
+        write(0, 0)?;
+        let committed = write(1, 1)?;
+        assert_eq!(2, committed);
+        assert_eq!(0, app.flushed_write_sequence(1));
+        assert_eq!(2, app.flush_watermark_lag());
+
+        // the flush is slower than the barrier timeout
+        match runtime_manager.wait(app.wait_until_flushed(1, committed, Duration::from_millis(100)))
+        {
+            Err(WorkerError::FLUSH_BARRIER_TIMEOUT(2, 0)) => {}
+            other => panic!("unexpected barrier result: {:?}", other),
+        }
+
+        // the later writes are not waited by the committed sequence
+        let uncommitted = write(0, 2)?;
+        let flushed =
+            runtime_manager.wait(app.wait_until_flushed(1, committed, Duration::from_secs(5)))?;
+        assert!(flushed >= committed);
+        assert_eq!(10, persisted_len(1)?);
+        assert!(persisted_len(0)? >= 10);
+
+        runtime_manager.wait(app.wait_until_flushed(1, uncommitted, Duration::from_secs(5)))?;
+        assert_eq!(20, persisted_len(0)?);
+        assert_eq!(3, app.flushed_write_sequence(1));
+        assert_eq!(0, app.flush_watermark_lag());
+
+        // the unaccepted sequence is never reached
+        assert!(runtime_manager
+            .wait(app.wait_until_flushed(1, 4, Duration::from_millis(50)))
+            .is_err());
+
+        // the writing dropped halfway like the client disconnection doesn't stick the barrier
+        let cancelled = async {
+            let sequence = app.next_write_sequence(1);
+            app.insert(writing_ctx(0, 3, sequence.sequence())).await?;
+            std::future::pending::<()>().await;
+            Ok::<(), WorkerError>(())
+        };
+        assert!(runtime_manager
+            .wait(tokio::time::timeout(Duration::from_millis(100), cancelled))
+            .is_err());
+        let flushed = runtime_manager.wait(app.wait_until_flushed(1, 4, Duration::from_secs(5)))?;
+        assert_eq!(4, flushed);
+        assert_eq!(30, persisted_len(0)?);
+
+        FAULT_INJECTOR.set_rules(
+            FAULT_INJECTOR
+                .rules()
+                .into_iter()
+                .filter(|x| x.app_id.as_deref() != Some(app_id))
+                .collect(),
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_block_id_layout() -> anyhow::Result<()> {
+        let runtime_manager: RuntimeManager = Default::default();
+        let mut config = mock_config();
+        config.block_id_layout = BlockIdLayout::new(20, 22, 21)?;
+        let app_manager_ref = AppManager::get_ref(runtime_manager.clone(), config);
+
+        // case1: the mismatched client is rejected in registration
+        let mut options = AppConfigOptions::default();
+        options.block_id_layout = Some(BlockIdLayout::default());
+        let error = app_manager_ref
+            .register("app_1".to_string(), 1, options)
+            .unwrap_err();
+        match error.downcast_ref::<WorkerError>() {
+            Some(error @ WorkerError::BLOCK_ID_LAYOUT_MISMATCHED(_, _)) => {
+                assert_eq!(StatusCode::INVALID_REQUEST, error.status_code())
+            }
+            _ => panic!(),
+        }
+        assert!(app_manager_ref.get_app("app_1").is_none());
+
+        // case2: the block ids of the matched client are checked by the layout
+        let layout = BlockIdLayout::new(20, 22, 21)?;
+        let mut options = AppConfigOptions::default();
+        options.block_id_layout = Some(layout);
+        app_manager_ref.register("app_1".to_string(), 1, options)?;
+        let app = app_manager_ref.get_app("app_1").unwrap();
+        let report = |partition_id: i32, blocks: Vec<i64>| {
+            runtime_manager.wait(app.report_block_ids(ReportBlocksContext {
+                uid: PartitionedUId::from("app_1".to_string(), 1, partition_id),
+                blocks,
+            }))
+        };
+        let max_partition_id = layout.max_partition_id();
+        report(
+            max_partition_id,
+            vec![
+                layout.encode(0, max_partition_id, 0)?,
+                layout.encode(layout.max_sequence(), max_partition_id, 1)?,
+            ],
+        )?;
+        let illegal = layout.encode(1, 3, 1)?;
+        let error = report(2, vec![layout.encode(1, 2, 1)?, illegal]).unwrap_err();
+        match error.downcast_ref::<WorkerError>() {
+            Some(WorkerError::ILLEGAL_BLOCK_ID(id, 2)) => assert_eq!(illegal, *id),
+            _ => panic!(),
+        }
+
+        // case3: the client without the layout is not checked
+        app_manager_ref.register("app_2".to_string(), 1, Default::default())?;
+        let app = app_manager_ref.get_app("app_2").unwrap();
+        runtime_manager.wait(app.report_block_ids(ReportBlocksContext {
+            uid: PartitionedUId::from("app_2".to_string(), 1, 2),
+            blocks: vec![illegal],
+        }))?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_shuffle_result_offload() -> anyhow::Result<()> {
+        let app_id = "test_shuffle_result_offload-----id";
+        let temp_dir = tempdir::TempDir::new("test_shuffle_result_offload")?;
+        let temp_path = temp_dir.path().to_str().unwrap().to_string();
+        let mut config =
+            Config::create_mem_localfile_config(21102, "1M".to_string(), temp_path.clone());
+        config.app_config.shuffle_result_offload_threshold = Some(10);
+        let runtime_manager: RuntimeManager = Default::default();
+        let app_manager_ref = AppManager::get_ref(runtime_manager.clone(), config);
+        app_manager_ref.register(app_id.to_string(), 1, Default::default())?;
+        let app = app_manager_ref.get_app(app_id).unwrap();
+
+        let uid = |shuffle_id: i32, partition_id: i32| {
+            PartitionedUId::from(app_id.to_string(), shuffle_id, partition_id)
+        };
+        for partition_id in 0..2 {
+            runtime_manager.wait(app.report_block_ids(ReportBlocksContext {
+                uid: uid(1, partition_id),
+                blocks: (0..10).map(|x| x + partition_id as i64 * 100).collect(),
+            }))?;
+        }
+        runtime_manager.wait(app.report_block_ids(ReportBlocksContext {
+            uid: uid(2, 0),
+            blocks: vec![1, 2, 3],
+        }))?;
+        assert_eq!(23, app.resident_shuffle_result_blocks());
+
+        // case1: only the read shuffle is offloaded
+        let expected =
+            runtime_manager.wait(app.get_block_ids(GetBlocksContext { uid: uid(1, 1) }))?;
+        assert_eq!(2, app.offload_shuffle_results()?);
+        assert_eq!(3, app.resident_shuffle_result_blocks());
+        assert_eq!(0, app.offload_shuffle_results()?);
+
+        // case2: the reloaded results are identical, which are cached after the first reloading
+        let reloaded = TOTAL_SHUFFLE_RESULT_RELOADED.get();
+        for _ in 0..2 {
+            assert_eq!(
+                expected,
+                runtime_manager.wait(app.get_block_ids(GetBlocksContext { uid: uid(1, 1) }))?
+            );
+        }
+        assert_eq!(1, TOTAL_SHUFFLE_RESULT_RELOADED.get() - reloaded);
+        let bitmap =
+            runtime_manager.wait(app.get_block_ids_bitmap(GetBlocksContext { uid: uid(1, 0) }))?;
+        assert_eq!(10, bitmap.cardinality());
+        assert_eq!(3, app.resident_shuffle_result_blocks());
+
+        // case3: the late reported blocks are merged
+        runtime_manager.wait(app.report_block_ids(ReportBlocksContext {
+            uid: uid(1, 0),
+            blocks: vec![1000],
+        }))?;
+        assert_eq!(14, app.resident_shuffle_result_blocks());
+        let bitmap =
+            runtime_manager.wait(app.get_block_ids_bitmap(GetBlocksContext { uid: uid(1, 0) }))?;
+        assert_eq!(11, bitmap.cardinality());
+
+        // case4: the offloaded files are removed by the purge
+        let offload_dir = Path::new(&temp_path)
+            .join(SHUFFLE_RESULT_OFFLOAD_DIR)
+            .join(app_id);
+        assert!(offload_dir.join("1").join("1").exists());
+        runtime_manager.wait(app.purge(app_id.to_string(), None))?;
+        assert!(!offload_dir.exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_heartbeat_timeout_with_startup_grace() -> anyhow::Result<()> {
+        let runtime_manager: RuntimeManager = Default::default();
+        let mut config = mock_config();
+        config.app_config.app_heartbeat_timeout = Some("10s".to_string());
+        config.app_config.startup_grace_min = Some(1);
+        let app_manager_ref = AppManager::get_ref(runtime_manager.clone(), config);
+        app_manager_ref.register("app_1".to_string(), 1, Default::default())?;
+
+        let started = app_manager_ref.started_timestamp;
+
+        // the app is kept within the grace even though its heartbeat is timeout
+        assert!(app_manager_ref
+            .heartbeat_timeout_apps(started + 59)
+            .is_empty());
+
+        // evicted once the grace passed
+        assert_eq!(
+            vec!["app_1".to_string()],
+            app_manager_ref.heartbeat_timeout_apps(started + 60)
+        );
+
+        // evicted immediately without the grace
+        let mut config = mock_config();
+        config.app_config.app_heartbeat_timeout = Some("10s".to_string());
+        config.app_config.startup_grace_min = Some(0);
+        let app_manager_ref = AppManager::get_ref(runtime_manager.clone(), config);
+        app_manager_ref.register("app_2".to_string(), 1, Default::default())?;
+        let heartbeat = app_manager_ref
+            .get_app("app_2")
+            .unwrap()
+            .latest_heartbeat_time();
+        assert_eq!(
+            vec!["app_2".to_string()],
+            app_manager_ref.heartbeat_timeout_apps(heartbeat + 11)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_app_number_limit() {
+        let unlimited = AppNumberLimit::new(None);
+        assert!(unlimited.admit().is_ok());
+        assert!(!unlimited.is_reached());
+
+        let rejected = TOTAL_APP_REGISTRATION_REJECTED.get();
+        let limit = AppNumberLimit::new(Some(2));
+        assert!(limit.admit().is_ok());
+        assert!(!limit.is_reached());
+        assert!(limit.admit().is_ok());
+        assert!(limit.is_reached());
+        assert!(matches!(
+            limit.admit(),
+            Err(WorkerError::APP_NUMBER_EXCEEDED(2))
+        ));
+        assert!(TOTAL_APP_REGISTRATION_REJECTED.get() >= rejected + 1);
+
+        limit.release();
+        assert!(!limit.is_reached());
+        assert!(limit.admit().is_ok());
+    }
+
+    #[test]
+    fn test_concurrent_registration_with_app_limit() {
+        let mut config = mock_config();
+        config.app_config.max_concurrent_apps = Some(4);
+        let app_manager_ref = AppManager::get_ref(Default::default(), config);
+
+        let handles = (0..32)
+            .map(|idx| {
+                let app_manager_ref = app_manager_ref.clone();
+                std::thread::spawn(move || {
+                    app_manager_ref
+                        .register(format!("app_{}", idx), 1, Default::default())
+                        .is_ok()
+                })
+            })
+            .collect::<Vec<_>>();
+        let admitted = handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap())
+            .filter(|admitted| *admitted)
+            .count();
+
+        assert_eq!(4, admitted);
+        assert_eq!(4, app_manager_ref.app_number());
+    }
+
+    #[test]
+    fn test_app_and_partition_limit() -> anyhow::Result<()> {
+        let runtime_manager: RuntimeManager = Default::default();
+        let mut config = mock_config();
+        config.app_config.max_concurrent_apps = Some(2);
+        config.app_config.max_partitions_per_server = Some(2);
+        let app_manager_ref = AppManager::get_ref(runtime_manager.clone(), config);
+
+        let insert = |app_id: &str, partition_id: i32| {
+            let app = app_manager_ref.get_app(app_id).unwrap();
+            let block = Block {
+                block_id: partition_id as i64,
+                length: 10,
+                uncompress_length: 10,
+                crc: 0,
+                data: Bytes::from(vec![0; 10]),
+                task_attempt_id: 0,
+            };
+            runtime_manager.wait(app.insert(WritingViewContext::new(
+                PartitionedUId::from(app_id.to_string(), 1, partition_id),
+                vec![block],
+                false,
+                10,
+            )))
+        };
+
+        // case1: the app limit
+        app_manager_ref.register("app_1".to_string(), 1, Default::default())?;
+        app_manager_ref.register("app_2".to_string(), 1, Default::default())?;
+        let error = app_manager_ref
+            .register("app_3".to_string(), 1, Default::default())
+            .unwrap_err();
+        match error.downcast_ref::<WorkerError>() {
+            Some(error @ WorkerError::APP_NUMBER_EXCEEDED(2)) => {
+                assert_eq!(StatusCode::SERVER_LIMIT_EXCEEDED, error.status_code())
+            }
+            _ => panic!(),
+        }
+        assert!(app_manager_ref.is_limit_reached());
+        // the registered app is not limited
+        app_manager_ref.register("app_2".to_string(), 2, Default::default())?;
+
+        // case2: the partition limit on the first write
+        insert("app_1", 0)?;
+        insert("app_1", 0)?;
+        insert("app_2", 0)?;
+        match insert("app_2", 1) {
+            Err(WorkerError::PARTITION_NUMBER_EXCEEDED(2)) => {}
+            _ => panic!(),
+        }
+        assert_eq!(2, app_manager_ref.partition_number());
+
+        // case3: recovered after purging
+        runtime_manager.wait(app_manager_ref.purge_app_data("app_1".to_string(), None))?;
+        assert_eq!(1, app_manager_ref.partition_number());
+        assert!(!app_manager_ref.is_limit_reached());
+        insert("app_2", 1)?;
+        assert!(app_manager_ref.is_limit_reached());
+        match app_manager_ref.register("app_3".to_string(), 1, Default::default()) {
+            Err(error) => assert!(matches!(
+                error.downcast_ref::<WorkerError>(),
+                Some(WorkerError::PARTITION_NUMBER_EXCEEDED(2))
+            )),
+            _ => panic!(),
+        }
+
+        runtime_manager.wait(app_manager_ref.purge_app_data("app_2".to_string(), Some(1)))?;
+        assert_eq!(0, app_manager_ref.partition_number());
+        app_manager_ref.register("app_3".to_string(), 1, Default::default())?;
+        insert("app_3", 0)?;
+        assert_eq!(1, app_manager_ref.partition_number());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_app_quota() -> anyhow::Result<()> {
+        let runtime_manager: RuntimeManager = Default::default();
+        let mut config = mock_config();
+        config.store_type = StorageType::MEMORY_LOCALFILE;
+        config.app_config.quota = Some(AppQuotaConfig {
+            max_total_bytes: Some("20".to_string()),
+            max_memory_bytes: None,
+        });
+        config.app_config.quota_overrides.insert(
+            "vip_".to_string(),
+            AppQuotaConfig {
+                max_total_bytes: Some("100".to_string()),
+                max_memory_bytes: Some("15".to_string()),
+            },
+        );
+        let app_manager_ref = AppManager::get_ref(runtime_manager.clone(), config);
+        for app_id in ["app_1", "app_2", "vip_app"] {
+            app_manager_ref.register(app_id.to_string(), 1, Default::default())?;
+        }
+
+        let insert = |app_id: &str, block_id: i64| {
+            let app = app_manager_ref.get_app(app_id).unwrap();
+            let block = Block {
+                block_id,
+                length: 10,
+                uncompress_length: 10,
+                crc: 0,
+                data: Bytes::from(vec![0; 10]),
+                task_attempt_id: 0,
+            };
+            runtime_manager.wait(app.insert(WritingViewContext::new(
+                PartitionedUId::from(app_id.to_string(), 1, 0),
+                vec![block],
+                false,
+                10,
+            )))
+        };
+
+        // case1: rejected once the total quota is reached
+        let app = app_manager_ref.get_app("app_1").unwrap();
+        insert("app_1", 0)?;
+        insert("app_1", 1)?;
+        assert!(app.is_quota_exceeded());
+        match insert("app_1", 2) {
+            Err(error @ WorkerError::APP_QUOTA_EXCEEDED(20)) => {
+                assert_eq!(StatusCode::QUOTA_EXCEEDED, error.status_code())
+            }
+            _ => panic!(),
+        }
+        assert_eq!(1, app.quota_rejected());
+        assert_eq!(20, app.total_resident_data_size());
+
+        // case2: the other apps are unaffected
+        insert("app_2", 0)?;
+        assert!(!app_manager_ref
+            .get_app("app_2")
+            .unwrap()
+            .is_quota_exceeded());
+
+        // case3: relaxed by the override rule
+        let vip_app = app_manager_ref.get_app("vip_app").unwrap();
+        assert_eq!(
+            AppQuota {
+                max_total_bytes: Some(100),
+                max_memory_bytes: Some(15),
+            },
+            vip_app.quota()
+        );
+        for block_id in 0..5 {
+            insert("vip_app", block_id)?;
+        }
+        assert!(!vip_app.is_quota_exceeded());
+
+        // case4: the buffers are spilled beyond the memory quota
+        assert!(!runtime_manager.wait(app.memory_quota_spill())?);
+        assert!(runtime_manager.wait(vip_app.memory_quota_spill())?);
+        assert!(
+            TOTAL_MEMORY_SPILL_TRIGGERED
+                .with_label_values(&["app_memory_quota"])
+                .get()
+                > 0
+        );
+
+        // case5: reset on purge, the rejected block could be resent
+        runtime_manager.wait(app_manager_ref.purge_app_data("app_1".to_string(), Some(1)))?;
+        assert_eq!(0, app.quota_rejected());
+        assert!(!app.is_quota_exceeded());
+        insert("app_1", 2)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_partition_reassignment() -> anyhow::Result<()> {
+        let runtime_manager: RuntimeManager = Default::default();
+        let app_manager_ref = AppManager::get_ref(runtime_manager.clone(), mock_config());
+        app_manager_ref.register("app_1".to_string(), 1, Default::default())?;
+        let app = app_manager_ref.get_app("app_1").unwrap();
+
+        let layout = BlockIdLayout::default();
+        let uid = PartitionedUId::from("app_1".to_string(), 1, 0);
+        let insert = |sequences: Vec<i64>| {
+            let blocks = sequences
+                .into_iter()
+                .map(|sequence| Block {
+                    block_id: layout.encode(sequence, 0, 1).unwrap(),
+                    length: 10,
+                    uncompress_length: 10,
+                    crc: 0,
+                    data: Bytes::from(vec![0; 10]),
+                    task_attempt_id: 1,
+                })
+                .collect();
+            runtime_manager.wait(app.insert(WritingViewContext::from(uid.clone(), blocks)))
+        };
+        insert(vec![0, 1])?;
+        assert_eq!(None, app.partition_split(&uid));
+
+        // case1: split at the sequence, the later blocks are rejected with the split point
+        assert_eq!(2, app.split_partition(&uid, 2)?);
+        assert_eq!(Some(2), app.partition_split(&uid));
+        insert(vec![2])?;
+        match insert(vec![2, 3]) {
+            Err(error @ WorkerError::PARTITION_REASSIGNED(2)) => {
+                assert_eq!(StatusCode::REASSIGNED, error.status_code())
+            }
+            _ => panic!(),
+        }
+        assert_eq!(30, app.total_received_data_size());
+
+        // case2: the former split point is kept
+        assert_eq!(2, app.split_partition(&uid, 1)?);
+        assert!(app.split_partition(&uid, -1).is_err());
+        assert!(app
+            .split_partition(&uid, layout.max_sequence() + 1)
+            .is_err());
+
+        // case3: the reported blocks are read with the split, the other partitions are unaffected
+        let reported: Vec<i64> = (0..3).map(|x| layout.encode(x, 0, 1).unwrap()).collect();
+        runtime_manager.wait(app.report_block_ids(ReportBlocksContext {
+            uid: uid.clone(),
+            blocks: reported.clone(),
+        }))?;
+        let bitmap = runtime_manager
+            .wait(app.get_block_ids_bitmap(GetBlocksContext { uid: uid.clone() }))?;
+        assert_eq!(
+            reported,
+            bitmap.iter().map(|x| x as i64).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            None,
+            app.partition_split(&PartitionedUId::from("app_1".to_string(), 1, 1))
+        );
+
+        // case4: cleared on purge
+        runtime_manager.wait(app_manager_ref.purge_app_data("app_1".to_string(), Some(1)))?;
+        assert_eq!(None, app.partition_split(&uid));
+        Ok(())
+    }
+
     #[test]
     fn app_manager_test() {
         let app_manager_ref = AppManager::get_ref(Default::default(), mock_config()).clone();
@@ -984,14 +2892,14 @@ mod test {
             }))
             .expect("TODO: panic message");
 
-        let data = app
-            .get_block_ids(GetBlocksContext {
+        let data = runtime_manager
+            .wait(app.get_block_ids(GetBlocksContext {
                 uid: PartitionedUId {
                     app_id,
                     shuffle_id: 1,
                     partition_id: 0,
                 },
-            })
+            }))
             .expect("TODO: panic message");
 
         let deserialized = Treemap::deserialize(&data).unwrap();
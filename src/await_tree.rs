@@ -15,20 +15,56 @@
 // specific language governing permissions and limitations
 // under the License.
 
+use crate::metric::GAUGE_STUCK_TASKS;
+use crate::runtime::RuntimeRef;
 use await_tree::{Registry, TreeRoot};
 
+use log::{info, warn};
 use once_cell::sync::Lazy;
+use std::collections::HashMap;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 type AwaitTreeRegistryRef = Arc<Mutex<Registry<u64>>>;
 
 pub static AWAIT_TREE_REGISTRY: Lazy<AwaitTreeInner> = Lazy::new(|| AwaitTreeInner::new());
 
+const WATCHDOG_INTERVAL: Duration = Duration::from_secs(30);
+
+// the tasks with these keywords in the name are flushing data for the stores
+const STORE_FLUSH_TASK_KEYWORDS: [&str; 2] = ["flush", "spill"];
+
+struct FrameState {
+    msg: String,
+    // the long-running loops are idle in the same frame, which should not be watched
+    watched: bool,
+    // the rendered tree without the elapsed time, to detect the frame transitions
+    snapshot: Option<String>,
+    last_transition: Instant,
+}
+
+#[derive(Debug, Clone)]
+pub struct StuckTask {
+    pub id: u64,
+    pub msg: String,
+    pub stuck_duration: Duration,
+    pub tree: String,
+}
+
+impl StuckTask {
+    pub fn is_store_flush_task(&self) -> bool {
+        let msg = self.msg.to_lowercase();
+        STORE_FLUSH_TASK_KEYWORDS.iter().any(|x| msg.contains(x))
+    }
+}
+
 #[derive(Clone)]
 pub struct AwaitTreeInner {
     inner: AwaitTreeRegistryRef,
     next_id: Arc<AtomicU64>,
+    frames: Arc<Mutex<HashMap<u64, FrameState>>>,
+    stuck_tasks: Arc<Mutex<Vec<StuckTask>>>,
 }
 
 impl AwaitTreeInner {
@@ -36,16 +72,187 @@ impl AwaitTreeInner {
         Self {
             inner: Arc::new(Mutex::new(Registry::new(await_tree::Config::default()))),
             next_id: Arc::new(Default::default()),
+            frames: Arc::new(Mutex::new(HashMap::new())),
+            stuck_tasks: Arc::new(Mutex::new(vec![])),
         }
     }
 
     pub async fn register(&self, msg: String) -> TreeRoot {
+        self.register_internal(msg, true)
+    }
+
+    /// Register the long-running loop, like the periodic checker, which
+    /// may be idle in the same frame forever and is skipped by the watchdog.
+    pub async fn register_long_running(&self, msg: String) -> TreeRoot {
+        self.register_internal(msg, false)
+    }
+
+    fn register_internal(&self, msg: String, watched: bool) -> TreeRoot {
         let id = self.next_id.fetch_add(1, Ordering::SeqCst);
         let msg = format!("actor=[{}], {}", id, msg);
-        self.inner.lock().unwrap().register(id, msg)
+        let root = self.inner.lock().unwrap().register(id, msg.clone());
+        self.frames.lock().unwrap().insert(
+            id,
+            FrameState {
+                msg,
+                watched,
+                snapshot: None,
+                last_transition: Instant::now(),
+            },
+        );
+        root
+    }
+
+    /// Scan the registry to find the tasks whose frames haven't changed beyond the threshold.
+    pub fn scan_stuck_tasks(&self, threshold: Duration) -> Vec<StuckTask> {
+        let scan_start = Instant::now();
+        let mut trees = HashMap::new();
+        {
+            let registry = self.inner.lock().unwrap();
+            for (id, tree) in registry.iter() {
+                trees.insert(*id, format!("{}", tree));
+            }
+        }
+
+        let now = Instant::now();
+        let mut stuck_tasks = vec![];
+        let mut frames = self.frames.lock().unwrap();
+        // the finished tasks have been removed from the registry, except the just registered
+        frames.retain(|id, state| trees.contains_key(id) || state.last_transition > scan_start);
+        for (id, state) in frames.iter_mut() {
+            let tree = match trees.remove(id) {
+                Some(tree) => tree,
+                _ => continue,
+            };
+            let snapshot = strip_elapsed_time(&tree);
+            match &state.snapshot {
+                None => state.snapshot = Some(snapshot),
+                Some(prev) if *prev != snapshot => {
+                    state.snapshot = Some(snapshot);
+                    state.last_transition = now;
+                }
+                _ => {}
+            }
+            let stuck_duration = now.duration_since(state.last_transition);
+            if state.watched && stuck_duration > threshold {
+                stuck_tasks.push(StuckTask {
+                    id: *id,
+                    msg: state.msg.clone(),
+                    stuck_duration,
+                    tree,
+                });
+            }
+        }
+        drop(frames);
+
+        stuck_tasks.sort_by_key(|x| x.id);
+        GAUGE_STUCK_TASKS.set(stuck_tasks.len() as i64);
+        *self.stuck_tasks.lock().unwrap() = stuck_tasks.clone();
+        stuck_tasks
+    }
+
+    /// The stuck tasks found in the latest scan.
+    pub fn stuck_tasks(&self) -> Vec<StuckTask> {
+        self.stuck_tasks.lock().unwrap().clone()
+    }
+
+    pub fn start_watchdog(&self, runtime: &RuntimeRef, threshold: Duration) {
+        info!(
+            "Starting the await tree watchdog with the stuck threshold: {:?}",
+            threshold
+        );
+        let registry = self.clone();
+        runtime.spawn_guarded("await_tree_watchdog", async move {
+            loop {
+                tokio::time::sleep(WATCHDOG_INTERVAL).await;
+                for task in registry.scan_stuck_tasks(threshold) {
+                    warn!(
+                        "Task [{}] is stuck for {:?}. await tree: \n{}",
+                        &task.msg, task.stuck_duration, &task.tree
+                    );
+                }
+            }
+        });
     }
 
     pub fn get_inner(&self) -> AwaitTreeRegistryRef {
         self.inner.clone()
     }
 }
+
+// the span line is rendered like "span [1.234s]  <== current"
+fn strip_elapsed_time(tree: &str) -> String {
+    let mut stripped = String::with_capacity(tree.len());
+    for line in tree.lines() {
+        let (line, current) = match line.strip_suffix("  <== current") {
+            Some(line) => (line, true),
+            None => (line, false),
+        };
+        let line = match line.rfind(" [") {
+            Some(idx) if line.ends_with(']') => &line[..idx],
+            _ => line,
+        };
+        stripped.push_str(line);
+        if current {
+            stripped.push_str("  <== current");
+        }
+        stripped.push('\n');
+    }
+    stripped
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::await_tree::{strip_elapsed_time, AwaitTreeInner};
+    use await_tree::InstrumentAwait;
+    use std::time::Duration;
+
+    #[test]
+    fn test_strip_elapsed_time() {
+        let tree = "actor=[1], task [1.234s]\n  waiting [!!! 12.3s]  <== current\n";
+        assert_eq!(
+            "actor=[1], task\n  waiting  <== current\n",
+            strip_elapsed_time(tree)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_stuck_detection() {
+        let registry = AwaitTreeInner::new();
+        let stuck_root = registry.register("spill parked task".to_string()).await;
+        let _stuck = tokio::spawn(stuck_root.instrument(async {
+            futures::future::pending::<()>()
+                .instrument_await("parking forever")
+                .await
+        }));
+        let idle_root = registry
+            .register_long_running("idle loop".to_string())
+            .await;
+        let _idle = tokio::spawn(idle_root.instrument(futures::future::pending::<()>()));
+        let progressing_root = registry.register("progressing task".to_string()).await;
+        let _progressing = tokio::spawn(progressing_root.instrument(async {
+            let mut idx = 0;
+            loop {
+                tokio::time::sleep(Duration::from_millis(10))
+                    .instrument_await(format!("sleeping {}", idx))
+                    .await;
+                idx += 1;
+            }
+        }));
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(registry
+            .scan_stuck_tasks(Duration::from_secs(60))
+            .is_empty());
+
+        let threshold = Duration::from_millis(200);
+        tokio::time::sleep(threshold).await;
+        let stuck_tasks = registry.scan_stuck_tasks(threshold);
+        assert_eq!(1, stuck_tasks.len());
+        let task = &stuck_tasks[0];
+        assert!(task.msg.contains("spill parked task"));
+        assert!(task.tree.contains("parking forever"));
+        assert!(task.is_store_flush_task());
+        assert_eq!(1, registry.stuck_tasks().len());
+    }
+}
@@ -0,0 +1,636 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! The synthetic client driving the grpc apis of a running server, which is used by
+//! the `riffle-bench` to evaluate the config changes without the real spark jobs.
+
+use crate::grpc::protobuf::uniffle::shuffle_server_client::ShuffleServerClient;
+use crate::grpc::protobuf::uniffle::{
+    ChecksumType, GetLocalShuffleDataRequest, GetLocalShuffleIndexRequest,
+    GetMemoryShuffleDataRequest, GetShuffleResultRequest, PartitionToBlockIds,
+    ReportShuffleResultRequest, RequireBufferRequest, SendShuffleDataRequest, ShuffleBlock,
+    ShuffleData, ShuffleRegisterRequest,
+};
+use anyhow::{anyhow, Result};
+use bytes::{Buf, Bytes};
+use parking_lot::Mutex;
+use rand::Rng;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::{Display, Formatter};
+use std::sync::atomic::{AtomicI64, AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tonic::transport::Channel;
+
+const OP_REGISTER: &str = "register_shuffle";
+const OP_REQUIRE_BUFFER: &str = "require_buffer";
+const OP_SEND_DATA: &str = "send_shuffle_data";
+const OP_REPORT_RESULT: &str = "report_shuffle_result";
+const OP_GET_RESULT: &str = "get_shuffle_result";
+const OP_GET_MEMORY_DATA: &str = "get_memory_shuffle_data";
+
+// offset(8) + length(4) + uncompress_length(4) + crc(8) + block_id(8) + task_attempt_id(8)
+const INDEX_ENTRY_LEN: usize = 40;
+
+#[derive(Debug, Clone)]
+pub struct BenchConfig {
+    // like "http://127.0.0.1:19999"
+    pub address: String,
+    pub apps: usize,
+    pub partitions: usize,
+    pub blocks_per_partition: usize,
+    // the block size is uniformly distributed in [min, max]
+    pub block_size_min: usize,
+    pub block_size_max: usize,
+    pub concurrency: usize,
+    // the probability of reading the partition after every write
+    pub read_ratio: f64,
+    // read back everything written and check the checksums at the end
+    pub verify: bool,
+}
+
+impl Default for BenchConfig {
+    fn default() -> Self {
+        Self {
+            address: "http://127.0.0.1:19999".to_string(),
+            apps: 1,
+            partitions: 10,
+            blocks_per_partition: 10,
+            block_size_min: 1024,
+            block_size_max: 1024,
+            concurrency: 4,
+            read_ratio: 0.0,
+            verify: false,
+        }
+    }
+}
+
+impl BenchConfig {
+    pub fn validate(&self) -> Result<()> {
+        if self.apps == 0 || self.partitions == 0 || self.concurrency == 0 {
+            return Err(anyhow!("apps, partitions and concurrency must be positive"));
+        }
+        if self.block_size_min == 0 || self.block_size_min > self.block_size_max {
+            return Err(anyhow!(
+                "Illegal block size range: [{}, {}]",
+                self.block_size_min,
+                self.block_size_max
+            ));
+        }
+        if !(0.0..=1.0).contains(&self.read_ratio) {
+            return Err(anyhow!("Illegal read ratio: {}", self.read_ratio));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct OperationReport {
+    pub name: String,
+    pub count: usize,
+    pub errors: usize,
+    pub p50: Duration,
+    pub p99: Duration,
+    // operations per second
+    pub throughput: f64,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct VerifyReport {
+    pub expected_blocks: usize,
+    pub missing_blocks: usize,
+    pub corrupted_blocks: usize,
+    pub errors: usize,
+}
+
+impl VerifyReport {
+    pub fn is_passed(&self) -> bool {
+        self.missing_blocks == 0 && self.corrupted_blocks == 0 && self.errors == 0
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct BenchReport {
+    pub elapsed: Duration,
+    pub written_bytes: u64,
+    pub operations: Vec<OperationReport>,
+    pub verify: Option<VerifyReport>,
+}
+
+impl BenchReport {
+    pub fn total_errors(&self) -> usize {
+        self.operations.iter().map(|x| x.errors).sum()
+    }
+
+    pub fn operation(&self, name: &str) -> Option<&OperationReport> {
+        self.operations.iter().find(|x| x.name == name)
+    }
+}
+
+impl Display for BenchReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let secs = self.elapsed.as_secs_f64().max(f64::EPSILON);
+        writeln!(
+            f,
+            "elapsed: {:?}, written: {} bytes, {:.2} MB/s",
+            self.elapsed,
+            self.written_bytes,
+            self.written_bytes as f64 / 1024.0 / 1024.0 / secs
+        )?;
+        writeln!(
+            f,
+            "{:<24} {:>10} {:>8} {:>12} {:>12} {:>12}",
+            "operation", "count", "errors", "p50", "p99", "ops/s"
+        )?;
+        for op in &self.operations {
+            writeln!(
+                f,
+                "{:<24} {:>10} {:>8} {:>12} {:>12} {:>12.1}",
+                op.name,
+                op.count,
+                op.errors,
+                format!("{:?}", op.p50),
+                format!("{:?}", op.p99),
+                op.throughput
+            )?;
+        }
+        if let Some(verify) = &self.verify {
+            writeln!(
+                f,
+                "verify: {}. expected: {}, missing: {}, corrupted: {}, errors: {}",
+                if verify.is_passed() {
+                    "passed"
+                } else {
+                    "failed"
+                },
+                verify.expected_blocks,
+                verify.missing_blocks,
+                verify.corrupted_blocks,
+                verify.errors
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+struct OperationStats {
+    latencies: Vec<Duration>,
+    errors: usize,
+}
+
+#[derive(Default)]
+struct Recorder {
+    stats: Mutex<HashMap<&'static str, OperationStats>>,
+}
+
+impl Recorder {
+    /// Record the latency of the call, the non-zero status is counted as an error.
+    async fn record<T, F>(&self, name: &'static str, call: F) -> Option<T>
+    where
+        F: std::future::Future<Output = Result<(i32, T)>>,
+    {
+        let timer = Instant::now();
+        let result = call.await;
+        let elapsed = timer.elapsed();
+        let mut stats = self.stats.lock();
+        let stat = stats.entry(name).or_default();
+        stat.latencies.push(elapsed);
+        match result {
+            Ok((0, value)) => Some(value),
+            _ => {
+                stat.errors += 1;
+                None
+            }
+        }
+    }
+
+    fn report(&self, elapsed: Duration) -> Vec<OperationReport> {
+        let secs = elapsed.as_secs_f64().max(f64::EPSILON);
+        let mut stats = self.stats.lock();
+        let mut reports: Vec<OperationReport> = stats
+            .iter_mut()
+            .map(|(name, stat)| {
+                stat.latencies.sort();
+                OperationReport {
+                    name: name.to_string(),
+                    count: stat.latencies.len(),
+                    errors: stat.errors,
+                    p50: percentile(&stat.latencies, 0.5),
+                    p99: percentile(&stat.latencies, 0.99),
+                    throughput: stat.latencies.len() as f64 / secs,
+                }
+            })
+            .collect();
+        reports.sort_by(|a, b| a.name.cmp(&b.name));
+        reports
+    }
+}
+
+fn percentile(sorted: &[Duration], quantile: f64) -> Duration {
+    if sorted.is_empty() {
+        return Duration::ZERO;
+    }
+    let idx = ((sorted.len() as f64 * quantile).ceil() as usize).clamp(1, sorted.len()) - 1;
+    sorted[idx]
+}
+
+fn app_id(idx: usize) -> String {
+    format!("riffle-bench-app-{}", idx)
+}
+
+struct Bench {
+    config: BenchConfig,
+    recorder: Recorder,
+    block_id: AtomicI64,
+    written_bytes: AtomicU64,
+    // key: (app_id, partition_id), value: block_id -> crc
+    written: Mutex<BTreeMap<(String, i32), HashMap<i64, i64>>>,
+}
+
+impl Bench {
+    async fn write(
+        &self,
+        client: &mut ShuffleServerClient<Channel>,
+        app_id: &str,
+        partition_id: i32,
+    ) {
+        let (min, max) = (self.config.block_size_min, self.config.block_size_max);
+        let data: Vec<u8> = {
+            let mut rng = rand::thread_rng();
+            let len = rng.gen_range(min..=max);
+            (0..len).map(|_| rng.gen()).collect()
+        };
+        let data = Bytes::from(data);
+        let len = data.len() as i32;
+        let crc = crc32fast::hash(&data) as i64;
+        let block_id = self.block_id.fetch_add(1, Ordering::SeqCst);
+
+        let ticket = self
+            .recorder
+            .record(OP_REQUIRE_BUFFER, async {
+                let response = client
+                    .require_buffer(RequireBufferRequest {
+                        require_size: len,
+                        app_id: app_id.to_string(),
+                        shuffle_id: 0,
+                        partition_ids: vec![partition_id],
+                    })
+                    .await?
+                    .into_inner();
+                Ok((response.status, response.require_buffer_id))
+            })
+            .await;
+        let ticket = match ticket {
+            Some(ticket) => ticket,
+            _ => return,
+        };
+
+        let sent = self
+            .recorder
+            .record(OP_SEND_DATA, async {
+                let response = client
+                    .send_shuffle_data(SendShuffleDataRequest {
+                        app_id: app_id.to_string(),
+                        shuffle_id: 0,
+                        require_buffer_id: ticket,
+                        shuffle_data: vec![ShuffleData {
+                            partition_id,
+                            block: vec![ShuffleBlock {
+                                block_id,
+                                length: len,
+                                uncompress_length: len,
+                                crc,
+                                data: data.clone(),
+                                task_attempt_id: 0,
+                            }],
+                        }],
+                        timestamp: 0,
+                        stage_attempt_number: 0,
+                        contiguous_shuffle_data: Default::default(),
+                        checksum_type: ChecksumType::Crc32.into(),
+                    })
+                    .await?
+                    .into_inner();
+                Ok((response.status, ()))
+            })
+            .await;
+        if sent.is_none() {
+            return;
+        }
+        self.written_bytes.fetch_add(len as u64, Ordering::SeqCst);
+        self.written
+            .lock()
+            .entry((app_id.to_string(), partition_id))
+            .or_default()
+            .insert(block_id, crc);
+
+        self.recorder
+            .record(OP_REPORT_RESULT, async {
+                let response = client
+                    .report_shuffle_result(ReportShuffleResultRequest {
+                        app_id: app_id.to_string(),
+                        shuffle_id: 0,
+                        task_attempt_id: 0,
+                        bitmap_num: 1,
+                        partition_to_block_ids: vec![PartitionToBlockIds {
+                            partition_id,
+                            block_ids: vec![block_id],
+                        }],
+                    })
+                    .await?
+                    .into_inner();
+                Ok((response.status, ()))
+            })
+            .await;
+    }
+
+    async fn read(
+        &self,
+        client: &mut ShuffleServerClient<Channel>,
+        app_id: &str,
+        partition_id: i32,
+    ) {
+        self.recorder
+            .record(OP_GET_RESULT, async {
+                let response = client
+                    .get_shuffle_result(GetShuffleResultRequest {
+                        app_id: app_id.to_string(),
+                        shuffle_id: 0,
+                        partition_id,
+                    })
+                    .await?
+                    .into_inner();
+                Ok((response.status, ()))
+            })
+            .await;
+        self.recorder
+            .record(OP_GET_MEMORY_DATA, async {
+                let response = client
+                    .get_memory_shuffle_data(GetMemoryShuffleDataRequest {
+                        app_id: app_id.to_string(),
+                        shuffle_id: 0,
+                        partition_id,
+                        last_block_id: -1,
+                        read_buffer_size: 10 * 1024 * 1024,
+                        timestamp: 0,
+                        serialized_expected_task_ids_bitmap: Default::default(),
+                    })
+                    .await?
+                    .into_inner();
+                Ok((response.status, ()))
+            })
+            .await;
+    }
+}
+
+/// Read back all the blocks of the partition from the memory and localfile,
+/// and check them by the crc written.
+async fn verify_partition(
+    client: &mut ShuffleServerClient<Channel>,
+    app_id: &str,
+    partition_id: i32,
+    expected: &HashMap<i64, i64>,
+    report: &mut VerifyReport,
+) -> Result<()> {
+    let mut found = HashSet::new();
+    let mut check = |block_id: i64, crc: i64, data: &[u8]| {
+        if !expected.contains_key(&block_id) || !found.insert(block_id) {
+            return;
+        }
+        if expected[&block_id] != crc || crc32fast::hash(data) as i64 != crc {
+            report.corrupted_blocks += 1;
+        }
+    };
+
+    // the memory data is paged by the last block id
+    let mut last_block_id = -1;
+    loop {
+        let response = client
+            .get_memory_shuffle_data(GetMemoryShuffleDataRequest {
+                app_id: app_id.to_string(),
+                shuffle_id: 0,
+                partition_id,
+                last_block_id,
+                read_buffer_size: 10 * 1024 * 1024,
+                timestamp: 0,
+                serialized_expected_task_ids_bitmap: Default::default(),
+            })
+            .await?
+            .into_inner();
+        if response.status != 0 {
+            return Err(anyhow!("Errors on reading memory: {}", response.ret_msg));
+        }
+        let segments = response.shuffle_data_block_segments;
+        let last = match segments.last() {
+            Some(last) => last.block_id,
+            _ => break,
+        };
+        for segment in &segments {
+            let start = segment.offset as usize;
+            let end = start + segment.length as usize;
+            check(segment.block_id, segment.crc, &response.data[start..end]);
+        }
+        // the last block has been flushed, the reading would restart from the head
+        if last == last_block_id {
+            break;
+        }
+        last_block_id = last;
+    }
+
+    let response = client
+        .get_local_shuffle_index(GetLocalShuffleIndexRequest {
+            app_id: app_id.to_string(),
+            shuffle_id: 0,
+            partition_id,
+            partition_num_per_range: 1,
+            partition_num: 0,
+        })
+        .await?
+        .into_inner();
+    if response.status != 0 {
+        return Err(anyhow!("Errors on reading index: {}", response.ret_msg));
+    }
+    let mut index = response.index_data;
+    let mut entries = vec![];
+    while index.remaining() >= INDEX_ENTRY_LEN {
+        let offset = index.get_i64();
+        let length = index.get_i32();
+        index.get_i32();
+        let crc = index.get_i64();
+        let block_id = index.get_i64();
+        index.get_i64();
+        entries.push((offset, length, crc, block_id));
+    }
+    let data_len = entries
+        .iter()
+        .map(|(offset, length, _, _)| offset + *length as i64)
+        .max()
+        .unwrap_or(0);
+    if data_len > 0 {
+        let response = client
+            .get_local_shuffle_data(GetLocalShuffleDataRequest {
+                app_id: app_id.to_string(),
+                shuffle_id: 0,
+                partition_id,
+                partition_num_per_range: 1,
+                partition_num: 0,
+                offset: 0,
+                length: data_len as i32,
+                timestamp: 0,
+            })
+            .await?
+            .into_inner();
+        if response.status != 0 || (response.data.len() as i64) < data_len {
+            return Err(anyhow!("Errors on reading data: {}", response.ret_msg));
+        }
+        for (offset, length, crc, block_id) in entries {
+            let start = offset as usize;
+            check(
+                block_id,
+                crc,
+                &response.data[start..start + length as usize],
+            );
+        }
+    }
+
+    report.missing_blocks += expected.keys().filter(|x| !found.contains(x)).count();
+    Ok(())
+}
+
+/// Run the benchmark against the server, the operation errors are counted in the report
+/// rather than failing the whole run.
+pub async fn run(config: BenchConfig) -> Result<BenchReport> {
+    config.validate()?;
+    let client = ShuffleServerClient::connect(config.address.clone()).await?;
+    let bench = Arc::new(Bench {
+        config: config.clone(),
+        recorder: Default::default(),
+        block_id: AtomicI64::new(0),
+        written_bytes: AtomicU64::new(0),
+        written: Default::default(),
+    });
+
+    let timer = Instant::now();
+    for idx in 0..config.apps {
+        let mut client = client.clone();
+        bench
+            .recorder
+            .record(OP_REGISTER, async {
+                let response = client
+                    .register_shuffle(ShuffleRegisterRequest {
+                        app_id: app_id(idx),
+                        shuffle_id: 0,
+                        partition_ranges: vec![],
+                        remote_storage: None,
+                        user: "".to_string(),
+                        shuffle_data_distribution: 1,
+                        max_concurrency_per_partition_to_write: 10,
+                        block_id_layout: None,
+                    })
+                    .await?
+                    .into_inner();
+                Ok((response.status, ()))
+            })
+            .await;
+    }
+
+    // every task is one block written into the (app, partition)
+    let total = config.apps * config.partitions * config.blocks_per_partition;
+    let cursor = Arc::new(AtomicUsize::new(0));
+    let mut handles = Vec::with_capacity(config.concurrency);
+    for _ in 0..config.concurrency {
+        let bench = bench.clone();
+        let cursor = cursor.clone();
+        let mut client = client.clone();
+        handles.push(tokio::spawn(async move {
+            loop {
+                let task = cursor.fetch_add(1, Ordering::SeqCst);
+                if task >= total {
+                    break;
+                }
+                let partitions = bench.config.partitions;
+                let app_id = app_id(task / partitions % bench.config.apps);
+                let partition_id = (task % partitions) as i32;
+                bench.write(&mut client, &app_id, partition_id).await;
+                if rand::thread_rng().gen_bool(bench.config.read_ratio) {
+                    bench.read(&mut client, &app_id, partition_id).await;
+                }
+            }
+        }));
+    }
+    for handle in handles {
+        handle.await?;
+    }
+    let elapsed = timer.elapsed();
+
+    let verify = match config.verify {
+        true => {
+            let written = bench.written.lock().clone();
+            let mut report = VerifyReport::default();
+            let mut client = client.clone();
+            for ((app_id, partition_id), expected) in written.iter() {
+                report.expected_blocks += expected.len();
+                if verify_partition(&mut client, app_id, *partition_id, expected, &mut report)
+                    .await
+                    .is_err()
+                {
+                    report.errors += 1;
+                }
+            }
+            Some(report)
+        }
+        _ => None,
+    };
+
+    Ok(BenchReport {
+        elapsed,
+        written_bytes: bench.written_bytes.load(Ordering::SeqCst),
+        operations: bench.recorder.report(elapsed),
+        verify,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bench::{percentile, BenchConfig};
+    use std::time::Duration;
+
+    #[test]
+    fn test_percentile() {
+        let latencies: Vec<Duration> = (1..=100).map(Duration::from_millis).collect();
+        assert_eq!(Duration::from_millis(50), percentile(&latencies, 0.5));
+        assert_eq!(Duration::from_millis(99), percentile(&latencies, 0.99));
+        assert_eq!(Duration::ZERO, percentile(&[], 0.5));
+        assert_eq!(
+            Duration::from_millis(1),
+            percentile(&[Duration::from_millis(1)], 0.99)
+        );
+    }
+
+    #[test]
+    fn test_config_validate() {
+        assert!(BenchConfig::default().validate().is_ok());
+        let mut config = BenchConfig::default();
+        config.block_size_min = 10;
+        config.block_size_max = 1;
+        assert!(config.validate().is_err());
+        let mut config = BenchConfig::default();
+        config.read_ratio = 1.5;
+        assert!(config.validate().is_err());
+    }
+}
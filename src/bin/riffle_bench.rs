@@ -0,0 +1,79 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use anyhow::Result;
+use clap::{App, Arg, ArgMatches};
+use std::str::FromStr;
+use uniffle_worker::bench::{run, BenchConfig};
+
+fn parse_arg<T: FromStr>(args: &ArgMatches, name: &str) -> Result<T> {
+    let value = args.value_of(name).unwrap();
+    value
+        .parse()
+        .map_err(|_| anyhow::anyhow!("Illegal value of --{}: {}", name, value))
+}
+
+fn arg<'a>(name: &'a str, default_value: &'a str, help: &'a str) -> Arg<'a> {
+    Arg::with_name(name)
+        .long(name)
+        .default_value(default_value)
+        .help(help)
+        .takes_value(true)
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args = App::new("Riffle Bench")
+        .about("The synthetic client to benchmark a running shuffle server")
+        .arg(arg("address", "http://127.0.0.1:19999", "The grpc address of the server"))
+        .arg(arg("apps", "1", "The number of apps"))
+        .arg(arg("partitions", "10", "The number of partitions per app"))
+        .arg(arg("blocks", "10", "The number of blocks written per partition"))
+        .arg(arg("block-size-min", "1024", "The min block size in bytes"))
+        .arg(arg("block-size-max", "1024", "The max block size in bytes"))
+        .arg(arg("concurrency", "4", "The number of concurrent writers"))
+        .arg(arg(
+            "read-ratio",
+            "0",
+            "The probability of reading the partition after every write",
+        ))
+        .arg(
+            Arg::with_name("verify")
+                .long("verify")
+                .help("Read back everything written and check the checksums"),
+        )
+        .get_matches();
+
+    let config = BenchConfig {
+        address: parse_arg(&args, "address")?,
+        apps: parse_arg(&args, "apps")?,
+        partitions: parse_arg(&args, "partitions")?,
+        blocks_per_partition: parse_arg(&args, "blocks")?,
+        block_size_min: parse_arg(&args, "block-size-min")?,
+        block_size_max: parse_arg(&args, "block-size-max")?,
+        concurrency: parse_arg(&args, "concurrency")?,
+        read_ratio: parse_arg(&args, "read-ratio")?,
+        verify: args.is_present("verify"),
+    };
+
+    let report = run(config).await?;
+    println!("{}", report);
+    if report.verify.as_ref().map_or(false, |x| !x.is_passed()) {
+        std::process::exit(1);
+    }
+    Ok(())
+}
@@ -0,0 +1,233 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::grpc::protobuf::uniffle;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+
+const BLOCK_ID_BITS: u32 = 63;
+
+/// The bit widths that the client packs the (sequence, partition, task attempt) into the
+/// block id, from the high bits to the low. The defaults are the same as the Uniffle client.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BlockIdLayout {
+    #[serde(default = "as_default_sequence_bits")]
+    pub sequence_bits: u32,
+    #[serde(default = "as_default_partition_bits")]
+    pub partition_bits: u32,
+    #[serde(default = "as_default_task_bits")]
+    pub task_bits: u32,
+}
+
+fn as_default_sequence_bits() -> u32 {
+    18
+}
+
+fn as_default_partition_bits() -> u32 {
+    24
+}
+
+fn as_default_task_bits() -> u32 {
+    21
+}
+
+impl Default for BlockIdLayout {
+    fn default() -> Self {
+        BlockIdLayout {
+            sequence_bits: as_default_sequence_bits(),
+            partition_bits: as_default_partition_bits(),
+            task_bits: as_default_task_bits(),
+        }
+    }
+}
+
+impl Display for BlockIdLayout {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "[sequence: {}, partition: {}, task: {}] bits",
+            self.sequence_bits, self.partition_bits, self.task_bits
+        )
+    }
+}
+
+impl From<uniffle::BlockIdLayout> for BlockIdLayout {
+    fn from(layout: uniffle::BlockIdLayout) -> Self {
+        // the negative ones are rejected by the validation
+        BlockIdLayout {
+            sequence_bits: layout.sequence_no_bits as u32,
+            partition_bits: layout.partition_id_bits as u32,
+            task_bits: layout.task_attempt_id_bits as u32,
+        }
+    }
+}
+
+/// The fields decomposed from the block id.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BlockId {
+    pub sequence: i64,
+    pub partition_id: i32,
+    pub task_attempt_id: i64,
+}
+
+impl BlockIdLayout {
+    pub fn new(sequence_bits: u32, partition_bits: u32, task_bits: u32) -> Result<Self> {
+        let layout = BlockIdLayout {
+            sequence_bits,
+            partition_bits,
+            task_bits,
+        };
+        layout.validate()?;
+        Ok(layout)
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        let widths = [self.sequence_bits, self.partition_bits, self.task_bits];
+        // the partition id is kept in the i32
+        if widths.iter().any(|x| *x == 0)
+            || self.partition_bits > 31
+            || widths.iter().map(|x| *x as u64).sum::<u64>() != BLOCK_ID_BITS as u64
+        {
+            return Err(anyhow!(
+                "Illegal block_id_layout: {}, every width should be positive with the partition one at most 31, and the sum should be {}",
+                self,
+                BLOCK_ID_BITS
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn max_sequence(&self) -> i64 {
+        (1 << self.sequence_bits) - 1
+    }
+
+    pub fn max_partition_id(&self) -> i32 {
+        ((1i64 << self.partition_bits) - 1) as i32
+    }
+
+    pub fn max_task_attempt_id(&self) -> i64 {
+        (1 << self.task_bits) - 1
+    }
+
+    pub fn encode(&self, sequence: i64, partition_id: i32, task_attempt_id: i64) -> Result<i64> {
+        let fields = [
+            ("sequence", sequence, self.max_sequence()),
+            (
+                "partition_id",
+                partition_id as i64,
+                self.max_partition_id() as i64,
+            ),
+            (
+                "task_attempt_id",
+                task_attempt_id,
+                self.max_task_attempt_id(),
+            ),
+        ];
+        for (name, value, max) in fields {
+            if value < 0 || value > max {
+                return Err(anyhow!(
+                    "The {}: {} is out of the range [0, {}] of the block id layout: {}",
+                    name,
+                    value,
+                    max,
+                    self
+                ));
+            }
+        }
+        Ok(sequence << (self.partition_bits + self.task_bits)
+            | (partition_id as i64) << self.task_bits
+            | task_attempt_id)
+    }
+
+    pub fn decode(&self, block_id: i64) -> BlockId {
+        BlockId {
+            sequence: (block_id >> (self.partition_bits + self.task_bits)) & self.max_sequence(),
+            partition_id: ((block_id >> self.task_bits) & self.max_partition_id() as i64) as i32,
+            task_attempt_id: block_id & self.max_task_attempt_id(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::block_id::{BlockId, BlockIdLayout};
+
+    #[test]
+    fn round_trip_test() -> anyhow::Result<()> {
+        for layout in [
+            BlockIdLayout::default(),
+            BlockIdLayout::new(21, 20, 22)?,
+            BlockIdLayout::new(1, 31, 31)?,
+            BlockIdLayout::new(61, 1, 1)?,
+        ] {
+            let sequences = [0, 1, layout.max_sequence()];
+            let partition_ids = [0, 1, layout.max_partition_id()];
+            let task_attempt_ids = [0, 1, layout.max_task_attempt_id()];
+            for sequence in sequences {
+                for partition_id in partition_ids {
+                    for task_attempt_id in task_attempt_ids {
+                        let block_id = layout.encode(sequence, partition_id, task_attempt_id)?;
+                        assert!(block_id >= 0);
+                        assert_eq!(
+                            BlockId {
+                                sequence,
+                                partition_id,
+                                task_attempt_id,
+                            },
+                            layout.decode(block_id)
+                        );
+                    }
+                }
+            }
+
+            // the max of every field is all ones
+            let max = layout.encode(
+                layout.max_sequence(),
+                layout.max_partition_id(),
+                layout.max_task_attempt_id(),
+            )?;
+            assert_eq!(i64::MAX, max);
+
+            assert!(layout.encode(layout.max_sequence() + 1, 0, 0).is_err());
+            assert!(layout
+                .encode(0, 0, layout.max_task_attempt_id() + 1)
+                .is_err());
+            assert!(layout.encode(-1, 0, 0).is_err());
+            assert!(layout.encode(0, -1, 0).is_err());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn uniffle_layout_test() -> anyhow::Result<()> {
+        let layout = BlockIdLayout::default();
+        assert_eq!((1 << 45) | (3 << 21) | 7, layout.encode(1, 3, 7)?);
+        assert!(layout.encode(0, 1 << 24, 0).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn validate_test() {
+        assert!(BlockIdLayout::new(18, 24, 20).is_err());
+        assert!(BlockIdLayout::new(18, 24, 22).is_err());
+        assert!(BlockIdLayout::new(0, 31, 32).is_err());
+        assert!(BlockIdLayout::new(1, 32, 30).is_err());
+        assert!(BlockIdLayout::new(u32::MAX, 32, 32).is_err());
+        assert!(BlockIdLayout::default().validate().is_ok());
+    }
+}
@@ -0,0 +1,180 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::config::BusyScoreConfig;
+use crate::metric::GAUGE_BUSY_SCORE;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// The metadata key of the busy score in the responses of requireBuffer and sendShuffleData.
+pub const BUSY_SCORE_METADATA_KEY: &str = "x-riffle-busy-score";
+
+pub const BUSY_SCORE_SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+
+pub const MAX_BUSY_SCORE: u32 = 100;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BusyComponents {
+    // (used + allocated) / capacity of the memory store
+    pub memory_used_ratio: f64,
+    pub event_bus_pending: usize,
+    // unbounded if none
+    pub event_bus_capacity: Option<usize>,
+    // the max writing queue ratio among the disks
+    pub disk_queue_ratio: f64,
+}
+
+pub trait BusySource: Send + Sync {
+    fn busy_components(&self) -> BusyComponents;
+}
+
+/// Composing the components into the score from 0 to 100, which is recomputed by the
+/// periodic sampling rather than on every response.
+pub struct BusyScoreSampler {
+    config: BusyScoreConfig,
+    source: Arc<dyn BusySource>,
+    score: AtomicU32,
+}
+
+impl BusyScoreSampler {
+    pub fn new(config: BusyScoreConfig, source: Arc<dyn BusySource>) -> Self {
+        Self {
+            config,
+            source,
+            score: AtomicU32::new(0),
+        }
+    }
+
+    /// The latest sampled score.
+    pub fn score(&self) -> u32 {
+        self.score.load(Ordering::SeqCst)
+    }
+
+    pub fn sample(&self) -> u32 {
+        let score = self.compute(&self.source.busy_components());
+        self.score.store(score, Ordering::SeqCst);
+        GAUGE_BUSY_SCORE.set(score as i64);
+        score
+    }
+
+    fn compute(&self, components: &BusyComponents) -> u32 {
+        let saturated = components
+            .event_bus_capacity
+            .unwrap_or(self.config.event_bus_saturated_pending)
+            .max(1);
+        let event_bus_ratio = components.event_bus_pending as f64 / saturated as f64;
+
+        let weighted = [
+            (components.memory_used_ratio, self.config.memory_weight),
+            (event_bus_ratio, self.config.event_bus_weight),
+            (components.disk_queue_ratio, self.config.disk_weight),
+        ];
+        let total_weight: f64 = weighted.iter().map(|(_, weight)| weight).sum();
+        if total_weight <= 0.0 {
+            return 0;
+        }
+        let ratio = weighted
+            .iter()
+            .map(|(ratio, weight)| ratio.clamp(0.0, 1.0) * weight)
+            .sum::<f64>()
+            / total_weight;
+        (ratio * MAX_BUSY_SCORE as f64).round() as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::busy_score::{BusyComponents, BusyScoreSampler, BusySource};
+    use crate::config::BusyScoreConfig;
+    use parking_lot::Mutex;
+    use std::sync::Arc;
+
+    #[derive(Default)]
+    struct MockedSource {
+        components: Mutex<BusyComponents>,
+    }
+
+    impl BusySource for MockedSource {
+        fn busy_components(&self) -> BusyComponents {
+            self.components.lock().clone()
+        }
+    }
+
+    #[test]
+    fn test_busy_score() {
+        let source = Arc::new(MockedSource::default());
+        let config = BusyScoreConfig {
+            memory_weight: 0.5,
+            event_bus_weight: 0.25,
+            disk_weight: 0.25,
+            event_bus_saturated_pending: 100,
+        };
+        let sampler = BusyScoreSampler::new(config, source.clone());
+        assert_eq!(0, sampler.sample());
+
+        // memory only
+        source.components.lock().memory_used_ratio = 0.8;
+        assert_eq!(40, sampler.sample());
+        assert_eq!(40, sampler.score());
+
+        // the unbounded event bus is saturated by the config
+        source.components.lock().event_bus_pending = 40;
+        assert_eq!(50, sampler.sample());
+        source.components.lock().event_bus_pending = 1000;
+        assert_eq!(65, sampler.sample());
+
+        // the bounded event bus is saturated by its capacity
+        source.components.lock().event_bus_capacity = Some(2500);
+        assert_eq!(50, sampler.sample());
+
+        // the disk queue
+        source.components.lock().disk_queue_ratio = 1.0;
+        assert_eq!(75, sampler.sample());
+
+        // all saturated
+        *source.components.lock() = BusyComponents {
+            memory_used_ratio: 1.2,
+            event_bus_pending: 10,
+            event_bus_capacity: Some(10),
+            disk_queue_ratio: 1.0,
+        };
+        assert_eq!(100, sampler.sample());
+
+        // the score is kept until the next sampling
+        *source.components.lock() = BusyComponents::default();
+        assert_eq!(100, sampler.score());
+        assert_eq!(0, sampler.sample());
+    }
+
+    #[test]
+    fn test_busy_score_weights() {
+        let source = Arc::new(MockedSource::default());
+        source.components.lock().disk_queue_ratio = 0.5;
+        let config = BusyScoreConfig {
+            memory_weight: 0.0,
+            event_bus_weight: 0.0,
+            disk_weight: 2.0,
+            event_bus_saturated_pending: 100,
+        };
+        let sampler = BusyScoreSampler::new(config, source.clone());
+        assert_eq!(50, sampler.sample());
+
+        source.components.lock().memory_used_ratio = 1.0;
+        assert_eq!(50, sampler.sample());
+    }
+}
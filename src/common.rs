@@ -1,11 +1,25 @@
 use crate::app::{SHUFFLE_SERVER_ID, SHUFFLE_SERVER_IP};
 use crate::config::Config;
-use crate::util::{generate_worker_uid, get_local_ip};
+use crate::health::HEALTH_REGISTRY;
+use crate::runtime::TaskPanicHealthProvider;
+use crate::slow_log::init_slow_request_threshold;
+use crate::util::{get_advertised_ip, load_or_generate_worker_uid};
+use std::sync::Arc;
 
 pub fn init_global_variable(config: &Config) {
-    let worker_uid = generate_worker_uid(&config);
+    let worker_uid = load_or_generate_worker_uid(&config);
     SHUFFLE_SERVER_ID.get_or_init(|| worker_uid.clone());
 
-    let worker_ip = get_local_ip().unwrap().to_string();
+    let worker_ip = get_advertised_ip(config).unwrap().to_string();
     SHUFFLE_SERVER_IP.get_or_init(|| worker_ip);
+
+    // it has been checked in the config validation
+    init_slow_request_threshold(config.server.slow_request_threshold().unwrap());
+
+    if let Some(threshold) = config.server.task_panic_threshold_per_minute {
+        HEALTH_REGISTRY.register(
+            "task_panics",
+            Arc::new(TaskPanicHealthProvider::new(threshold)),
+        );
+    }
 }
@@ -15,9 +15,23 @@
 // specific language governing permissions and limitations
 // under the License.
 
+use crate::block_id::BlockIdLayout;
+use crate::readable_size::ReadableSize;
+use crate::shutdown::DEFAULT_SHUTDOWN_DEADLINE;
+use anyhow::{anyhow, Result};
+use flate2::read::GzDecoder;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
 use std::fs;
+use std::io::Read;
+use std::net::IpAddr;
+use std::ops::Range;
 use std::path::Path;
+use std::str::FromStr;
+use std::time::Duration;
+use thiserror::Error;
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 pub struct MemoryStoreConfig {
@@ -30,6 +44,24 @@ pub struct MemoryStoreConfig {
 
     #[serde(default = "as_default_dashmap_shard_amount")]
     pub dashmap_shard_amount: usize,
+    // the key distribution over the shards interacts with the hasher
+    #[serde(default)]
+    pub dashmap_hasher: MapHasherType,
+    // the interval of sampling the shard imbalance stats
+    #[serde(default = "as_default_dashmap_shard_stats_interval")]
+    pub dashmap_shard_stats_interval: String,
+
+    // if enabled, the buffer requirements lacking the capacity wait for it in the FIFO order
+    // for at most 1s rather than failing immediately, the others go without waiting
+    pub ticket_fairness: Option<bool>,
+
+    // the partition buffer exceeding it will be spilled regardless of the global watermark
+    pub partition_buffer_max_size: Option<String>,
+    // if enabled, the writes of the exceeded partition are rejected until its spill finished
+    pub partition_buffer_strict: Option<bool>,
+
+    // the drift between the tracked and summed totals of the memory snapshot beyond it is warned
+    pub snapshot_drift_threshold: Option<String>,
 }
 
 fn as_default_buffer_ticket_timeout_check_interval_sec() -> i64 {
@@ -40,17 +72,82 @@ fn as_default_dashmap_shard_amount() -> usize {
     128
 }
 
+fn as_default_dashmap_shard_stats_interval() -> String {
+    "1m".to_string()
+}
+
+pub const DEFAULT_SNAPSHOT_DRIFT_THRESHOLD: u64 = ReadableSize::mb(64).as_bytes();
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum MapHasherType {
+    Std,
+    Ahash,
+    #[default]
+    Fxhash,
+}
+
 fn as_default_buffer_ticket_timeout_sec() -> i64 {
     5 * 60
 }
 
 impl MemoryStoreConfig {
+    /// Resolve the capacity which is the absolute size like "10G",
+    /// or the ratio of the total system memory like "60%".
+    pub fn capacity_bytes(&self) -> Result<u64> {
+        self.capacity_bytes_with(system_total_memory)
+    }
+
+    pub fn dashmap_shard_stats_interval(&self) -> Result<Duration> {
+        let interval = humantime::parse_duration(&self.dashmap_shard_stats_interval)?;
+        if interval.is_zero() {
+            return Err(anyhow!(
+                "Illegal memory_store.dashmap_shard_stats_interval: {}, it should be positive",
+                &self.dashmap_shard_stats_interval
+            ));
+        }
+        Ok(interval)
+    }
+
+    pub fn partition_buffer_max_size_bytes(&self) -> Result<Option<u64>> {
+        match &self.partition_buffer_max_size {
+            Some(size) => Ok(Some(
+                parse_readable_size("memory_store.partition_buffer_max_size", size)?.as_bytes(),
+            )),
+            _ => Ok(None),
+        }
+    }
+
+    pub fn snapshot_drift_threshold_bytes(&self) -> Result<u64> {
+        match &self.snapshot_drift_threshold {
+            Some(threshold) => Ok(parse_readable_size(
+                "memory_store.snapshot_drift_threshold",
+                threshold,
+            )?
+            .as_bytes()),
+            _ => Ok(DEFAULT_SNAPSHOT_DRIFT_THRESHOLD),
+        }
+    }
+
+    fn capacity_bytes_with<F: FnOnce() -> u64>(&self, total_memory: F) -> Result<u64> {
+        match parse_percent("memory_store.capacity", &self.capacity)? {
+            Some(ratio) => Ok((total_memory() as f64 * ratio) as u64),
+            _ => Ok(parse_readable_size("memory_store.capacity", &self.capacity)?.as_bytes()),
+        }
+    }
+
     pub fn new(capacity: String) -> Self {
         Self {
             capacity,
             buffer_ticket_timeout_sec: as_default_buffer_ticket_timeout_sec(),
             buffer_ticket_check_interval_sec: as_default_buffer_ticket_timeout_check_interval_sec(),
             dashmap_shard_amount: as_default_dashmap_shard_amount(),
+            dashmap_hasher: Default::default(),
+            dashmap_shard_stats_interval: as_default_dashmap_shard_stats_interval(),
+            ticket_fairness: None,
+            partition_buffer_max_size: None,
+            partition_buffer_strict: None,
+            snapshot_drift_threshold: None,
         }
     }
 
@@ -60,6 +157,12 @@ impl MemoryStoreConfig {
             buffer_ticket_timeout_sec,
             buffer_ticket_check_interval_sec: as_default_buffer_ticket_timeout_check_interval_sec(),
             dashmap_shard_amount: as_default_dashmap_shard_amount(),
+            dashmap_hasher: Default::default(),
+            dashmap_shard_stats_interval: as_default_dashmap_shard_stats_interval(),
+            ticket_fairness: None,
+            partition_buffer_max_size: None,
+            partition_buffer_strict: None,
+            snapshot_drift_threshold: None,
         }
     }
 }
@@ -70,6 +173,8 @@ impl MemoryStoreConfig {
 pub struct HdfsStoreConfig {
     #[serde(default = "as_default_max_concurrency")]
     pub max_concurrency: usize,
+    /// The optional capacity budget of the hdfs, like "10T". It's unbounded if not set.
+    pub capacity: Option<String>,
 }
 fn as_default_max_concurrency() -> usize {
     100
@@ -79,7 +184,11 @@ fn as_default_max_concurrency() -> usize {
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 pub struct LocalfileStoreConfig {
+    #[serde(default)]
     pub data_paths: Vec<String>,
+    /// The paths for the reading and writing, both fall back to the data_paths if not set.
+    pub read_paths: Option<Vec<String>>,
+    pub write_paths: Option<Vec<String>>,
     #[serde(default = "as_default_healthy_check_min_disks")]
     pub healthy_check_min_disks: i32,
     #[serde(default = "as_default_disk_high_watermark")]
@@ -90,6 +199,104 @@ pub struct LocalfileStoreConfig {
     pub disk_max_concurrency: i32,
     #[serde(default = "as_default_disk_write_buf_capacity")]
     pub disk_write_buf_capacity: String,
+    /// The max number of partitions whose parsed index is cached, 0 to disable.
+    #[serde(default = "as_default_index_cache_capacity")]
+    pub index_cache_capacity: usize,
+    /// Whether the spilled files are fsync'd, `Never` if not set.
+    pub fsync_policy: Option<FsyncPolicy>,
+    /// The background compaction of the partition files, disabled if not set.
+    pub compaction: Option<LocalfileCompactionConfig>,
+    /// The engine of reading the data files, `pread` if not set.
+    pub read_engine: Option<ReadEngine>,
+    /// The max number of the mapped data files for the `mmap` engine.
+    #[serde(default = "as_default_mmap_cache_capacity")]
+    pub mmap_cache_capacity: usize,
+    /// The disk is marked as suspect once the read doesn't finish within this.
+    #[serde(default = "as_default_read_timeout")]
+    pub read_timeout: String,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ReadEngine {
+    /// Read the range into the allocated buffer.
+    #[default]
+    Pread,
+    /// Map the data file on the first read, the range is served as the view over the
+    /// mapping which saves the copy and the syscall for the repeated reads.
+    Mmap,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct LocalfileCompactionConfig {
+    /// The partition is compacted once its flushed segments exceed this.
+    #[serde(default = "as_default_compaction_segment_threshold")]
+    pub segment_threshold: u64,
+    /// The partition not written or read within this duration is regarded as idle.
+    #[serde(default = "as_default_compaction_idle_duration")]
+    pub idle_duration: String,
+    #[serde(default = "as_default_compaction_interval")]
+    pub interval: String,
+    /// The read and write bytes per second of the compaction, like "64M".
+    #[serde(default = "as_default_compaction_io_rate_limit")]
+    pub io_rate_limit: String,
+}
+
+fn as_default_compaction_segment_threshold() -> u64 {
+    100
+}
+fn as_default_compaction_idle_duration() -> String {
+    "10m".to_string()
+}
+fn as_default_compaction_interval() -> String {
+    "1m".to_string()
+}
+fn as_default_compaction_io_rate_limit() -> String {
+    "64M".to_string()
+}
+
+impl Default for LocalfileCompactionConfig {
+    fn default() -> Self {
+        LocalfileCompactionConfig {
+            segment_threshold: as_default_compaction_segment_threshold(),
+            idle_duration: as_default_compaction_idle_duration(),
+            interval: as_default_compaction_interval(),
+            io_rate_limit: as_default_compaction_io_rate_limit(),
+        }
+    }
+}
+
+impl LocalfileCompactionConfig {
+    pub fn idle_duration(&self) -> Result<Duration> {
+        Ok(humantime::parse_duration(&self.idle_duration)?)
+    }
+
+    pub fn interval(&self) -> Result<Duration> {
+        Ok(humantime::parse_duration(&self.interval)?)
+    }
+
+    pub fn io_rate_limit(&self) -> Result<u64> {
+        Ok(parse_readable_size(
+            "localfile_store.compaction.io_rate_limit",
+            &self.io_rate_limit,
+        )?
+        .as_bytes())
+    }
+}
+
+/// The fsync policy of the localfile appending.
+///
+/// Before this was introduced, the appended data was only flushed into the page cache
+/// and the file was never fsync'd, which is kept as the default `Never`.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Default)]
+pub enum FsyncPolicy {
+    /// Rely on the OS to write back the page cache.
+    #[default]
+    Never,
+    /// Fsync once the writer of every append is closed.
+    OnClose,
+    /// Fsync after every written buffer, the most durable and the slowest.
+    Always,
 }
 fn as_default_disk_max_concurrency() -> i32 {
     2000
@@ -106,17 +313,170 @@ fn as_default_healthy_check_min_disks() -> i32 {
 fn as_default_disk_write_buf_capacity() -> String {
     "1M".to_string()
 }
+fn as_default_index_cache_capacity() -> usize {
+    10000
+}
+fn as_default_mmap_cache_capacity() -> usize {
+    1024
+}
+fn as_default_read_timeout() -> String {
+    "30s".to_string()
+}
 
 impl LocalfileStoreConfig {
     pub fn new(data_paths: Vec<String>) -> Self {
         LocalfileStoreConfig {
             data_paths,
+            read_paths: None,
+            write_paths: None,
             healthy_check_min_disks: as_default_healthy_check_min_disks(),
             disk_high_watermark: as_default_disk_high_watermark(),
             disk_low_watermark: as_default_disk_low_watermark(),
             disk_max_concurrency: as_default_disk_max_concurrency(),
             disk_write_buf_capacity: as_default_disk_write_buf_capacity(),
+            index_cache_capacity: as_default_index_cache_capacity(),
+            fsync_policy: None,
+            compaction: None,
+            read_engine: None,
+            mmap_cache_capacity: as_default_mmap_cache_capacity(),
+            read_timeout: as_default_read_timeout(),
+        }
+    }
+
+    pub fn effective_read_paths(&self) -> &[String] {
+        self.read_paths.as_deref().unwrap_or(&self.data_paths)
+    }
+
+    pub fn effective_write_paths(&self) -> &[String] {
+        self.write_paths.as_deref().unwrap_or(&self.data_paths)
+    }
+
+    pub fn fsync_policy(&self) -> FsyncPolicy {
+        self.fsync_policy.unwrap_or_default()
+    }
+
+    pub fn read_engine(&self) -> ReadEngine {
+        self.read_engine.unwrap_or_default()
+    }
+
+    pub fn read_timeout(&self) -> Result<Duration> {
+        Ok(humantime::parse_duration(&self.read_timeout)?)
+    }
+
+    /// All the distinct paths of reading and writing, in the order of declaration.
+    pub fn all_paths(&self) -> Vec<String> {
+        let mut paths: Vec<String> = vec![];
+        for path in self
+            .effective_write_paths()
+            .iter()
+            .chain(self.effective_read_paths())
+        {
+            if !paths.contains(path) {
+                paths.push(path.clone());
+            }
+        }
+        paths
+    }
+
+    /// Check every configured path at startup. The unmounted disk's path silently
+    /// falls on the root filesystem, which is reported by the `distinct_mount`.
+    pub fn check_mounts(&self) -> Vec<PathHealth> {
+        self.all_paths()
+            .into_iter()
+            .map(|path| PathHealth::check(&path))
+            .collect()
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.effective_read_paths().is_empty() {
+            return Err(anyhow!(
+                "There is no path for reading, localfile_store.read_paths or data_paths must be set"
+            ));
+        }
+        if self.effective_write_paths().is_empty() {
+            return Err(anyhow!(
+                "There is no path for writing, localfile_store.write_paths or data_paths must be set"
+            ));
+        }
+        // the negated check also rejects the NaN
+        if !(self.disk_high_watermark > 0.0 && self.disk_high_watermark < 1.0) {
+            return Err(anyhow!(
+                "Illegal localfile_store.disk_high_watermark: {}, it should be in (0, 1)",
+                self.disk_high_watermark
+            ));
+        }
+        if self.read_timeout()?.is_zero() {
+            return Err(anyhow!(
+                "Illegal localfile_store.read_timeout: {}, it should be positive",
+                &self.read_timeout
+            ));
+        }
+        if let Some(compaction) = &self.compaction {
+            compaction.idle_duration()?;
+            compaction.interval()?;
+            if compaction.io_rate_limit()? == 0 {
+                return Err(anyhow!(
+                    "Illegal localfile_store.compaction.io_rate_limit: {}, it should be positive",
+                    &compaction.io_rate_limit
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PathHealth {
+    pub path: String,
+    pub exists: bool,
+    pub is_dir: bool,
+    // the probe file could be written and deleted
+    pub writable: bool,
+    // whether it's on a different device from the `/`, None if unknown
+    pub distinct_mount: Option<bool>,
+    pub error: Option<String>,
+}
+
+impl PathHealth {
+    const PROBE_FILE_NAME: &'static str = ".riffle_mount_probe";
+
+    fn check(path: &str) -> PathHealth {
+        let mut health = PathHealth {
+            path: path.to_string(),
+            ..Default::default()
+        };
+        let metadata = match fs::metadata(path) {
+            Ok(metadata) => metadata,
+            Err(e) => {
+                health.error = Some(e.to_string());
+                return health;
+            }
+        };
+        health.exists = true;
+        health.is_dir = metadata.is_dir();
+        if !health.is_dir {
+            health.error = Some("not a directory".to_string());
+            return health;
+        }
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            health.distinct_mount = fs::metadata("/")
+                .ok()
+                .map(|root| root.dev() != metadata.dev());
+        }
+
+        let probe = Path::new(path).join(Self::PROBE_FILE_NAME);
+        match fs::write(&probe, b"probe").and_then(|_| fs::remove_file(&probe)) {
+            Ok(_) => health.writable = true,
+            Err(e) => health.error = Some(e.to_string()),
         }
+        health
+    }
+
+    pub fn is_healthy(&self) -> bool {
+        self.exists && self.is_dir && self.writable
     }
 }
 
@@ -130,6 +490,28 @@ pub struct RuntimeConfig {
     pub http_thread_num: usize,
     pub default_thread_num: usize,
     pub dispatch_thread_num: usize,
+    // for the memory spill flushing, isolated from the foreground writing
+    pub flush_thread_num: usize,
+
+    /// The optional cpus to pin the pool threads, like "0-7" or "0,2,4-6".
+    pub read_thread_cpuset: Option<String>,
+    pub write_thread_cpuset: Option<String>,
+    pub http_thread_cpuset: Option<String>,
+    pub default_thread_cpuset: Option<String>,
+    pub dispatch_thread_cpuset: Option<String>,
+    pub flush_thread_cpuset: Option<String>,
+
+    /// The type of the pools with only 1 thread, the larger ones are always multi thread.
+    pub single_thread_pool_type: PoolType,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum PoolType {
+    #[default]
+    MultiThread,
+    /// All the tasks are run on the single thread without the work stealing.
+    CurrentThread,
 }
 
 impl Default for RuntimeConfig {
@@ -140,8 +522,118 @@ impl Default for RuntimeConfig {
             http_thread_num: 2,
             default_thread_num: 10,
             dispatch_thread_num: 100,
+            flush_thread_num: 20,
+            read_thread_cpuset: None,
+            write_thread_cpuset: None,
+            http_thread_cpuset: None,
+            default_thread_cpuset: None,
+            dispatch_thread_cpuset: None,
+            flush_thread_cpuset: None,
+            single_thread_pool_type: PoolType::MultiThread,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WorkloadProfile {
+    ReadHeavy,
+    WriteHeavy,
+    Balanced,
+}
+
+impl RuntimeConfig {
+    /// Build the runtime config tuned for the given workload profile based on the cpu cores.
+    /// It is only the starting point, every field could still be overridden.
+    ///
+    /// With `n` cpu cores, the thread nums are:
+    ///
+    /// | profile    | read | write | http | default      | dispatch | flush |
+    /// |------------|------|-------|------|--------------|----------|-------|
+    /// | ReadHeavy  | 4n   | n     | 2    | max(n/4, 2)  | n        | n     |
+    /// | WriteHeavy | n    | 4n    | 2    | max(n/4, 2)  | 4n       | 2n    |
+    /// | Balanced   | 2n   | 2n    | 2    | max(n/4, 2)  | 2n       | n     |
+    pub fn for_profile(profile: WorkloadProfile) -> Self {
+        Self::for_profile_with_cpus(profile, num_cpus::get())
+    }
+
+    fn for_profile_with_cpus(profile: WorkloadProfile, cpus: usize) -> Self {
+        let cpus = cpus.max(1);
+        let (read, write, dispatch, flush) = match profile {
+            WorkloadProfile::ReadHeavy => (4 * cpus, cpus, cpus, cpus),
+            WorkloadProfile::WriteHeavy => (cpus, 4 * cpus, 4 * cpus, 2 * cpus),
+            WorkloadProfile::Balanced => (2 * cpus, 2 * cpus, 2 * cpus, cpus),
+        };
+        RuntimeConfig {
+            read_thread_num: read,
+            write_thread_num: write,
+            http_thread_num: 2,
+            default_thread_num: (cpus / 4).max(2),
+            dispatch_thread_num: dispatch,
+            flush_thread_num: flush,
+            ..Default::default()
+        }
+    }
+
+    pub fn pool_type(&self, thread_num: usize) -> PoolType {
+        match thread_num {
+            1 => self.single_thread_pool_type,
+            _ => PoolType::MultiThread,
+        }
+    }
+
+    fn cpusets(&self) -> [(&str, &Option<String>); 6] {
+        [
+            ("read_thread_cpuset", &self.read_thread_cpuset),
+            ("write_thread_cpuset", &self.write_thread_cpuset),
+            ("http_thread_cpuset", &self.http_thread_cpuset),
+            ("default_thread_cpuset", &self.default_thread_cpuset),
+            ("dispatch_thread_cpuset", &self.dispatch_thread_cpuset),
+            ("flush_thread_cpuset", &self.flush_thread_cpuset),
+        ]
+    }
+
+    fn validate(&self) -> Result<()> {
+        for (name, cpuset) in self.cpusets() {
+            if let Some(cpuset) = cpuset {
+                parse_cpuset(&format!("runtime_config.{}", name), cpuset)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Parse the cpuset like "0-3,6,8-9" into the sorted and distinct cpu ids.
+pub fn parse_cpuset(name: &str, cpuset: &str) -> Result<Vec<usize>> {
+    let illegal = |reason: &str| anyhow!("Illegal cpuset of {}: [{}]. {}", name, cpuset, reason);
+    let parse_id = |id: &str| {
+        usize::from_str(id.trim()).map_err(|_| illegal(&format!("[{}] is not a cpu id", id)))
+    };
+
+    let mut cpus = vec![];
+    for part in cpuset.split(',') {
+        let (start, end) = match part.split_once('-') {
+            Some((start, end)) => (parse_id(start)?, parse_id(end)?),
+            _ => {
+                let id = parse_id(part)?;
+                (id, id)
+            }
+        };
+        if start > end {
+            return Err(illegal(&format!("the range [{}] is reversed", part)));
         }
+        cpus.extend(start..=end);
+    }
+    cpus.sort();
+    cpus.dedup();
+
+    let available = num_cpus::get();
+    if let Some(cpu) = cpus.iter().find(|&&cpu| cpu >= available) {
+        return Err(illegal(&format!(
+            "the cpu {} exceeds the available {} cpus",
+            cpu, available
+        )));
     }
+    Ok(cpus)
 }
 
 // =========================================================
@@ -159,6 +651,52 @@ pub struct HybridStoreConfig {
 
     #[serde(default = "as_default_memory_spill_max_concurrency")]
     pub memory_spill_max_concurrency: i32,
+
+    // the capacity of the recently read disk segments cache like "1G", which is out of the
+    // memory store capacity. disabled if not set
+    pub read_cache_capacity: Option<String>,
+
+    // the buffer requirements are admitted by the projected usage after the spill, disabled if not set
+    pub spill_debt_admission: Option<SpillDebtAdmissionConfig>,
+
+    // the concurrency of the spill to the localfile shrinks as its disk usage climbs from the
+    // disk_low_watermark to the disk_high_watermark, the spill to hdfs is not throttled.
+    // disabled by default
+    pub spill_disk_throttle_enable: Option<bool>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct SpillDebtAdmissionConfig {
+    // the ratio of the projected usage (allocated + used - spill debt + requested) to admit,
+    // which should be above the memory_spill_high_watermark to keep the spill triggered
+    #[serde(default = "as_default_admission_projected_watermark")]
+    pub projected_watermark: f32,
+    // the requirement not admitted is delayed at most this duration before rejected,
+    // and it's rejected at once if zero
+    #[serde(default = "as_default_admission_max_delay")]
+    pub max_delay: String,
+}
+
+fn as_default_admission_projected_watermark() -> f32 {
+    0.9
+}
+fn as_default_admission_max_delay() -> String {
+    "1s".to_string()
+}
+
+impl Default for SpillDebtAdmissionConfig {
+    fn default() -> Self {
+        SpillDebtAdmissionConfig {
+            projected_watermark: as_default_admission_projected_watermark(),
+            max_delay: as_default_admission_max_delay(),
+        }
+    }
+}
+
+impl SpillDebtAdmissionConfig {
+    pub fn max_delay(&self) -> Result<Duration> {
+        Ok(humantime::parse_duration(&self.max_delay)?)
+    }
 }
 
 fn as_default_memory_spill_high_watermark() -> f32 {
@@ -183,6 +721,9 @@ impl HybridStoreConfig {
             memory_single_buffer_max_spill_size,
             memory_spill_to_cold_threshold_size: None,
             memory_spill_max_concurrency: 100,
+            read_cache_capacity: None,
+            spill_debt_admission: None,
+            spill_disk_throttle_enable: None,
         }
     }
 }
@@ -195,6 +736,9 @@ impl Default for HybridStoreConfig {
             memory_single_buffer_max_spill_size: None,
             memory_spill_to_cold_threshold_size: None,
             memory_spill_max_concurrency: as_default_memory_spill_max_concurrency(),
+            read_cache_capacity: None,
+            spill_debt_admission: None,
+            spill_disk_throttle_enable: None,
         }
     }
 }
@@ -214,17 +758,31 @@ pub struct Config {
 
     #[serde(default = "as_default_storage_type")]
     pub store_type: StorageType,
+    // drop the localfile tier rather than crashing once all its paths are unusable at startup,
+    // the availability is kept at the cost of the durability
+    #[serde(default)]
+    pub degrade_on_store_init_failure: bool,
 
     #[serde(default = "as_default_runtime_config")]
     pub runtime_config: RuntimeConfig,
 
     pub metrics: Option<MetricsConfig>,
 
+    // the address that the grpc, urpc and http services are bound to, default is 0.0.0.0
+    pub bind_address: Option<String>,
     #[serde(default = "as_default_grpc_port")]
     pub grpc_port: i32,
     pub urpc_port: Option<i32>,
+    pub grpc_tls: Option<TlsConfig>,
+    // bounding the whole graceful shutdown like "2m", the worker is forcibly exited beyond it. 60s if not set
+    pub shutdown_timeout: Option<String>,
+    // the max message sizes of the grpc transport like "512M", default is 1G
+    pub grpc_max_recv_message_size: Option<String>,
+    pub grpc_max_send_message_size: Option<String>,
 
     pub coordinator_quorum: Vec<String>,
+    #[serde(default = "as_default_coordinator_config")]
+    pub coordinator: CoordinatorConfig,
     pub tags: Option<Vec<String>>,
 
     #[serde(default = "as_default_log_config")]
@@ -233,10 +791,86 @@ pub struct Config {
     #[serde(default = "as_default_app_config")]
     pub app_config: AppConfig,
 
+    // the layout of the block ids packed by the clients
+    #[serde(default)]
+    pub block_id_layout: BlockIdLayout,
+
     #[serde(default = "as_default_http_monitor_port")]
     pub http_monitor_service_port: u16,
 
     pub tracing: Option<TracingConfig>,
+
+    #[serde(default = "as_default_server_config")]
+    pub server: ServerConfig,
+
+    // key: the scope of event bus like `spill`, and the unlisted ones use the `default`
+    #[serde(default)]
+    pub event_bus: HashMap<String, EventBusConfig>,
+
+    #[serde(default = "as_default_busy_score_config")]
+    pub busy_score: BusyScoreConfig,
+
+    #[serde(default)]
+    pub urpc: UrpcConfig,
+}
+
+pub const DEFAULT_EVENT_BUS_SCOPE: &str = "default";
+pub const SPILL_EVENT_BUS_SCOPE: &str = "spill";
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Default)]
+pub struct EventBusConfig {
+    /// The max number of the pending events like "10000", unbounded if not set.
+    pub queue_capacity: Option<String>,
+    /// `block` or `reject` the publishing when the queue is full, `block` if not set.
+    pub overflow: Option<String>,
+}
+
+/// The behavior of publishing into the full queue of the event bus.
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+pub enum QueueOverflow {
+    /// Wait until the queue has room.
+    #[default]
+    Block,
+    /// Fail the publishing at once.
+    Reject,
+}
+
+impl FromStr for QueueOverflow {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "block" => Ok(QueueOverflow::Block),
+            "reject" => Ok(QueueOverflow::Reject),
+            _ => Err(anyhow!(
+                "Illegal overflow policy: [{}], it should be block or reject",
+                s
+            )),
+        }
+    }
+}
+
+impl EventBusConfig {
+    pub fn queue_capacity(&self) -> Result<Option<usize>> {
+        let capacity = match &self.queue_capacity {
+            Some(capacity) => capacity,
+            _ => return Ok(None),
+        };
+        match capacity.trim().parse::<usize>() {
+            Ok(capacity) if capacity > 0 => Ok(Some(capacity)),
+            _ => Err(anyhow!(
+                "Illegal queue_capacity: [{}], it should be a positive integer",
+                capacity
+            )),
+        }
+    }
+
+    pub fn overflow(&self) -> Result<QueueOverflow> {
+        match &self.overflow {
+            Some(overflow) => QueueOverflow::from_str(overflow),
+            _ => Ok(QueueOverflow::default()),
+        }
+    }
 }
 
 // ====
@@ -259,22 +893,78 @@ fn as_default_grpc_port() -> i32 {
     19999
 }
 
+const DEFAULT_BIND_ADDRESS: &str = "0.0.0.0";
+
+const DEFAULT_GRPC_MAX_MESSAGE_SIZE: u64 = 1024 * 1024 * 1024;
+// the smaller one will reject the normal shuffle data
+const MIN_GRPC_MAX_MESSAGE_SIZE: u64 = 1024 * 1024;
+
 // ===========
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Default)]
 pub struct AppConfig {
+    // deprecated: use the app_heartbeat_timeout instead. it will be as the fallback
     #[serde(default = "as_default_app_heartbeat_timeout_min")]
     pub app_heartbeat_timeout_min: u32,
+    // the duration form like "30s", "5m", "2h"
+    pub app_heartbeat_timeout: Option<String>,
+    // the heartbeat timeout eviction is suspended within this warmup period after the server
+    // started, giving the apps time to reconnect. zero or not set means disabled
+    pub startup_grace_min: Option<u32>,
 
     pub huge_partition_marked_threshold: Option<String>,
     pub huge_partition_memory_limit_percent: Option<f64>,
+
+    // the limits to protect the server from the misconfigured jobs, unlimited if not set
+    pub max_concurrent_apps: Option<u32>,
+    pub max_partitions_per_server: Option<usize>,
+    // deprecated: use the max_concurrent_apps instead. it will be as the fallback
+    pub max_apps_per_server: Option<usize>,
+
+    // the max spill concurrency of a single app, which is bounded by the hybrid store's
+    pub per_app_spill_concurrency: Option<i32>,
+
+    // the tracked partitions number of every shard of the per app hot partitions sketch,
+    // 32 if not set. The sketch has 16 shards, so up to 512 partitions are tracked per app
+    pub hot_partition_sketch_capacity: Option<usize>,
+    // the partitions written more than this share of the app total are logged periodically
+    pub hot_partition_report_share: Option<f64>,
+    // the resident block ids number of the app shuffle results, beyond which the results
+    // of the read shuffles are offloaded to the first localfile path
+    pub shuffle_result_offload_threshold: Option<u64>,
+
+    // the default quota of every app, unlimited if not set
+    pub quota: Option<AppQuotaConfig>,
+    // key: the app id prefix, the longest matched one overrides the default quota
+    #[serde(default)]
+    pub quota_overrides: HashMap<String, AppQuotaConfig>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Default)]
+pub struct AppQuotaConfig {
+    /// The written bytes of the app across all the tiers, like "100G". The writing
+    /// is rejected once reached, until the shuffles are purged.
+    pub max_total_bytes: Option<String>,
+    /// The memory bytes of the app, beyond which its buffers are spilled.
+    pub max_memory_bytes: Option<String>,
 }
 
 fn as_default_app_config() -> AppConfig {
     AppConfig {
         app_heartbeat_timeout_min: as_default_app_heartbeat_timeout_min(),
+        app_heartbeat_timeout: None,
+        startup_grace_min: None,
         huge_partition_marked_threshold: None,
         huge_partition_memory_limit_percent: None,
+        max_concurrent_apps: None,
+        max_partitions_per_server: None,
+        max_apps_per_server: None,
+        per_app_spill_concurrency: None,
+        hot_partition_sketch_capacity: None,
+        hot_partition_report_share: None,
+        shuffle_result_offload_threshold: None,
+        quota: None,
+        quota_overrides: Default::default(),
     }
 }
 
@@ -282,113 +972,936 @@ fn as_default_app_heartbeat_timeout_min() -> u32 {
     5
 }
 
-// =========================================================
-#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
-pub struct TracingConfig {
-    pub jaeger_reporter_endpoint: String,
-    pub jaeger_service_name: String,
-}
+impl AppConfig {
+    pub fn app_heartbeat_timeout(&self) -> Result<Duration> {
+        match &self.app_heartbeat_timeout {
+            Some(timeout) => Ok(humantime::parse_duration(timeout)?),
+            _ => Ok(Duration::from_secs(
+                self.app_heartbeat_timeout_min as u64 * 60,
+            )),
+        }
+    }
 
-// =========================================================
-#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
-pub struct MetricsConfig {
-    pub push_gateway_endpoint: Option<String>,
+    pub fn max_concurrent_apps(&self) -> Option<usize> {
+        self.max_concurrent_apps
+            .map(|x| x as usize)
+            .or(self.max_apps_per_server)
+    }
 
-    #[serde(default = "as_default_push_interval_sec")]
-    pub push_interval_sec: u32,
+    pub fn startup_grace(&self) -> Duration {
+        Duration::from_secs(self.startup_grace_min.unwrap_or(0) as u64 * 60)
+    }
+
+    /// The quota config of the app, the unset fields of the override fall back to the default.
+    pub fn quota_of(&self, app_id: &str) -> AppQuotaConfig {
+        let default = self.quota.clone().unwrap_or_default();
+        let matched = self
+            .quota_overrides
+            .iter()
+            .filter(|(prefix, _)| app_id.starts_with(prefix.as_str()))
+            .max_by_key(|(prefix, _)| prefix.len());
+        match matched {
+            Some((_, quota)) => AppQuotaConfig {
+                max_total_bytes: quota.max_total_bytes.clone().or(default.max_total_bytes),
+                max_memory_bytes: quota.max_memory_bytes.clone().or(default.max_memory_bytes),
+            },
+            _ => default,
+        }
+    }
+
+    /// Whether any app could be limited by the memory quota.
+    pub fn has_memory_quota(&self) -> bool {
+        self.quota
+            .iter()
+            .chain(self.quota_overrides.values())
+            .any(|x| x.max_memory_bytes.is_some())
+    }
 }
 
-fn as_default_push_interval_sec() -> u32 {
-    10
+impl AppQuotaConfig {
+    pub fn max_total_bytes(&self) -> Result<Option<u64>> {
+        self.max_total_bytes
+            .as_ref()
+            .map(|x| Ok(parse_readable_size("max_total_bytes", x)?.as_bytes()))
+            .transpose()
+    }
+
+    pub fn max_memory_bytes(&self) -> Result<Option<u64>> {
+        self.max_memory_bytes
+            .as_ref()
+            .map(|x| Ok(parse_readable_size("max_memory_bytes", x)?.as_bytes()))
+            .transpose()
+    }
 }
 
 // =========================================================
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
-pub struct LogConfig {
-    pub path: String,
-    #[serde(default = "as_default_rotation_config")]
-    pub rotation: RotationConfig,
-}
-fn as_default_rotation_config() -> RotationConfig {
-    RotationConfig::Daily
+pub struct ServerConfig {
+    // the duration form like "500ms", "2s"
+    #[serde(default = "as_default_slow_request_threshold")]
+    pub slow_request_threshold: String,
+    // the file to persist the generated server id, which keeps the identity across restarts
+    pub id_storage_path: Option<String>,
+    // the server will be unhealthy once the task panics in the last minute reach it
+    pub task_panic_threshold_per_minute: Option<u64>,
+    // the task is regarded as stuck once its await frame hasn't changed beyond it
+    #[serde(default = "as_default_stuck_task_threshold")]
+    pub stuck_task_threshold: String,
+    // exposing the unauthenticated /debug/faults endpoint to inject the store faults, only for the testing
+    #[serde(default)]
+    pub fault_injection_http_enabled: bool,
 }
 
-impl Default for LogConfig {
+impl Default for ServerConfig {
     fn default() -> Self {
-        LogConfig {
-            path: "/tmp/".to_string(),
-            rotation: RotationConfig::Hourly,
+        ServerConfig {
+            slow_request_threshold: as_default_slow_request_threshold(),
+            id_storage_path: None,
+            task_panic_threshold_per_minute: None,
+            stuck_task_threshold: as_default_stuck_task_threshold(),
+            fault_injection_http_enabled: false,
         }
     }
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
-pub enum RotationConfig {
-    Hourly,
-    Daily,
-    Never,
+impl ServerConfig {
+    pub fn slow_request_threshold(&self) -> Result<Duration> {
+        Ok(humantime::parse_duration(&self.slow_request_threshold)?)
+    }
+
+    pub fn stuck_task_threshold(&self) -> Result<Duration> {
+        Ok(humantime::parse_duration(&self.stuck_task_threshold)?)
+    }
+}
+
+fn as_default_server_config() -> ServerConfig {
+    ServerConfig::default()
 }
 
 // =========================================================
 
-#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Copy)]
-#[allow(non_camel_case_types)]
-pub enum StorageType {
-    MEMORY = 1,
-    LOCALFILE = 2,
-    MEMORY_LOCALFILE = 3,
-    HDFS = 4,
-    MEMORY_HDFS = 5,
-    MEMORY_LOCALFILE_HDFS = 7,
+/// The weights of the components composing the busy score attached in the responses,
+/// which are normalized by their sum.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct BusyScoreConfig {
+    #[serde(default = "as_default_busy_score_memory_weight")]
+    pub memory_weight: f64,
+    #[serde(default = "as_default_busy_score_event_bus_weight")]
+    pub event_bus_weight: f64,
+    #[serde(default = "as_default_busy_score_disk_weight")]
+    pub disk_weight: f64,
+    // the pending spill events regarded as fully busy when the event bus queue is unbounded
+    #[serde(default = "as_default_busy_score_event_bus_saturated_pending")]
+    pub event_bus_saturated_pending: usize,
 }
 
-impl Default for StorageType {
+impl Default for BusyScoreConfig {
     fn default() -> Self {
-        StorageType::MEMORY
+        BusyScoreConfig {
+            memory_weight: as_default_busy_score_memory_weight(),
+            event_bus_weight: as_default_busy_score_event_bus_weight(),
+            disk_weight: as_default_busy_score_disk_weight(),
+            event_bus_saturated_pending: as_default_busy_score_event_bus_saturated_pending(),
+        }
     }
 }
 
-impl StorageType {
-    pub fn contains_localfile(storage_type: &StorageType) -> bool {
-        let val = *storage_type as u8;
-        val & *&StorageType::LOCALFILE as u8 != 0
+impl BusyScoreConfig {
+    fn validate(&self) -> Result<()> {
+        let weights = [self.memory_weight, self.event_bus_weight, self.disk_weight];
+        if weights.iter().any(|x| !x.is_finite() || *x < 0.0) {
+            return Err(anyhow!(
+                "Illegal busy_score weights: {:?}, they should be non-negative",
+                weights
+            ));
+        }
+        if weights.iter().sum::<f64>() <= 0.0 {
+            return Err(anyhow!(
+                "Illegal busy_score weights: {:?}, at least one of them should be positive",
+                weights
+            ));
+        }
+        if self.event_bus_saturated_pending == 0 {
+            return Err(anyhow!(
+                "Illegal busy_score.event_bus_saturated_pending: 0, it should be positive"
+            ));
+        }
+        Ok(())
     }
+}
 
-    pub fn contains_memory(storage_type: &StorageType) -> bool {
-        let val = *storage_type as u8;
-        val & *&StorageType::MEMORY as u8 != 0
+/// Coalescing the response frames of one urpc connection into one flush, disabled by default.
+/// It's enabled once either of the budgets is set, and the frame with the flush hint like
+/// the sending ack is always flushed immediately.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Default)]
+pub struct UrpcConfig {
+    /// The pending frames are flushed once reaching this size, like "64K". 64K if not set.
+    pub write_coalesce_bytes: Option<String>,
+    /// The pending frames are flushed after this delay at most. 200 if not set.
+    pub write_coalesce_micros: Option<u64>,
+}
+
+const DEFAULT_URPC_WRITE_COALESCE_BYTES: u64 = 64 * 1024;
+const DEFAULT_URPC_WRITE_COALESCE_MICROS: u64 = 200;
+
+impl UrpcConfig {
+    /// The (bytes, delay) budget of the coalescing, none if disabled.
+    pub fn write_coalesce(&self) -> Result<Option<(usize, Duration)>> {
+        if self.write_coalesce_bytes.is_none() && self.write_coalesce_micros.is_none() {
+            return Ok(None);
+        }
+        let bytes = match &self.write_coalesce_bytes {
+            Some(size) => parse_readable_size("urpc.write_coalesce_bytes", size)?.as_bytes(),
+            _ => DEFAULT_URPC_WRITE_COALESCE_BYTES,
+        };
+        if bytes == 0 {
+            return Err(anyhow!(
+                "Illegal urpc.write_coalesce_bytes: 0, it should be positive"
+            ));
+        }
+        let micros = self
+            .write_coalesce_micros
+            .unwrap_or(DEFAULT_URPC_WRITE_COALESCE_MICROS);
+        Ok(Some((bytes as usize, Duration::from_micros(micros))))
     }
 
-    pub fn contains_hdfs(storage_type: &StorageType) -> bool {
-        let val = *storage_type as u8;
-        val & *&StorageType::HDFS as u8 != 0
+    fn validate(&self) -> Result<()> {
+        self.write_coalesce()?;
+        Ok(())
     }
 }
 
-const CONFIG_FILE_PATH_KEY: &str = "WORKER_CONFIG_PATH";
+fn as_default_busy_score_config() -> BusyScoreConfig {
+    BusyScoreConfig::default()
+}
 
-impl Config {
-    pub fn from(cfg_path: &str) -> Self {
-        let path = Path::new(cfg_path);
+fn as_default_busy_score_memory_weight() -> f64 {
+    0.5
+}
 
-        // Read the file content as a string
-        let file_content = fs::read_to_string(path).expect("Failed to read file");
+fn as_default_busy_score_event_bus_weight() -> f64 {
+    0.25
+}
 
-        toml::from_str(&file_content).unwrap()
-    }
+fn as_default_busy_score_disk_weight() -> f64 {
+    0.25
+}
 
-    pub fn create_from_env() -> Config {
-        let path = match std::env::var(CONFIG_FILE_PATH_KEY) {
-            Ok(val) => val,
-            _ => panic!(
-                "config path must be set in env args. key: {}",
-                CONFIG_FILE_PATH_KEY
-            ),
-        };
+fn as_default_busy_score_event_bus_saturated_pending() -> usize {
+    1000
+}
 
-        Config::from(&path)
-    }
+fn as_default_slow_request_threshold() -> String {
+    "2s".to_string()
+}
+
+fn as_default_stuck_task_threshold() -> String {
+    "5m".to_string()
+}
+
+// =========================================================
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct CoordinatorConfig {
+    // the duration form like "10s", it's the timeout of connecting and every heartbeat
+    #[serde(default = "as_default_coordinator_connect_timeout")]
+    pub connect_timeout: String,
+    // the base interval of retrying the failed coordinators
+    #[serde(default = "as_default_coordinator_retry_interval")]
+    pub retry_interval: String,
+}
+
+impl Default for CoordinatorConfig {
+    fn default() -> Self {
+        CoordinatorConfig {
+            connect_timeout: as_default_coordinator_connect_timeout(),
+            retry_interval: as_default_coordinator_retry_interval(),
+        }
+    }
+}
+
+impl CoordinatorConfig {
+    pub fn connect_timeout(&self) -> Result<Duration> {
+        Ok(humantime::parse_duration(&self.connect_timeout)?)
+    }
+
+    pub fn retry_interval(&self) -> Result<Duration> {
+        Ok(humantime::parse_duration(&self.retry_interval)?)
+    }
+}
+
+fn as_default_coordinator_config() -> CoordinatorConfig {
+    CoordinatorConfig::default()
+}
+
+fn as_default_coordinator_connect_timeout() -> String {
+    "10s".to_string()
+}
+
+fn as_default_coordinator_retry_interval() -> String {
+    "1s".to_string()
+}
+
+// =========================================================
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct TlsConfig {
+    pub cert_path: String,
+    pub key_path: String,
+    // the CA certs to verify the client certs. If set, the mTLS will be enabled
+    pub ca_path: Option<String>,
+}
+
+impl TlsConfig {
+    pub fn validate(&self) -> Result<()> {
+        let mut paths = vec![("cert_path", &self.cert_path), ("key_path", &self.key_path)];
+        if let Some(ca_path) = &self.ca_path {
+            paths.push(("ca_path", ca_path));
+        }
+        for (name, path) in paths {
+            std::fs::File::open(path).map_err(|e| {
+                anyhow!(
+                    "The tls {} of [{}] is not readable. error: {:?}",
+                    name,
+                    path,
+                    e
+                )
+            })?;
+        }
+        Ok(())
+    }
+}
+
+// =========================================================
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct TracingConfig {
+    pub jaeger_reporter_endpoint: String,
+    pub jaeger_service_name: String,
+}
+
+// =========================================================
+#[derive(Clone, Serialize, Deserialize, PartialEq)]
+pub struct MetricsConfig {
+    pub push_gateway_endpoint: Option<String>,
+
+    #[serde(default = "as_default_push_interval_sec")]
+    pub push_interval_sec: u32,
+
+    // the summaries of the latencies are more expensive than the histograms, disabled by default
+    pub enable_summaries: Option<bool>,
+    // the quantiles exported by the summaries, like [0.5, 0.95, 0.99]
+    pub summary_quantiles: Option<Vec<f64>>,
+
+    // the basic auth of the push gateway, the password could be loaded from the file
+    pub push_gateway_username: Option<String>,
+    pub push_gateway_password: Option<String>,
+    pub push_gateway_password_file: Option<String>,
+}
+
+fn as_default_push_interval_sec() -> u32 {
+    10
+}
+
+const REDACTED_SECRET: &str = "******";
+
+// the inline password is redacted, since the whole config is logged on the startup
+impl std::fmt::Debug for MetricsConfig {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MetricsConfig")
+            .field("push_gateway_endpoint", &self.push_gateway_endpoint)
+            .field("push_interval_sec", &self.push_interval_sec)
+            .field("enable_summaries", &self.enable_summaries)
+            .field("summary_quantiles", &self.summary_quantiles)
+            .field("push_gateway_username", &self.push_gateway_username)
+            .field(
+                "push_gateway_password",
+                &self.push_gateway_password.as_ref().map(|_| REDACTED_SECRET),
+            )
+            .field(
+                "push_gateway_password_file",
+                &self.push_gateway_password_file,
+            )
+            .finish()
+    }
+}
+
+const DEFAULT_SUMMARY_QUANTILES: [f64; 3] = [0.5, 0.95, 0.99];
+
+impl MetricsConfig {
+    pub fn summaries_enabled(&self) -> bool {
+        self.enable_summaries.unwrap_or(false)
+    }
+
+    pub fn summary_quantiles(&self) -> Result<Vec<f64>> {
+        let quantiles = match &self.summary_quantiles {
+            Some(quantiles) => quantiles.clone(),
+            _ => DEFAULT_SUMMARY_QUANTILES.to_vec(),
+        };
+        if quantiles.is_empty() || quantiles.iter().any(|x| !(0.0..=1.0).contains(x)) {
+            return Err(anyhow!(
+                "Illegal metrics.summary_quantiles: {:?}, each of them should be in [0, 1]",
+                quantiles
+            ));
+        }
+        Ok(quantiles)
+    }
+
+    pub fn push_gateway_password(&self) -> Result<Option<String>> {
+        load_secret(
+            "metrics.push_gateway_password",
+            &self.push_gateway_password,
+            &self.push_gateway_password_file,
+        )
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        self.summary_quantiles()?;
+        let password = self.push_gateway_password()?;
+        if password.is_some() && self.push_gateway_username.is_none() {
+            return Err(anyhow!(
+                "metrics.push_gateway_username must be set with the password"
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Resolving the sensitive field from either the inline value or its `_file` sibling, which
+/// is read with the trailing newline trimmed.
+pub fn load_secret(
+    name: &str,
+    inline: &Option<String>,
+    file: &Option<String>,
+) -> Result<Option<String>> {
+    match (inline, file) {
+        (Some(_), Some(_)) => Err(anyhow!("{} and {}_file are mutually exclusive", name, name)),
+        (Some(value), None) => Ok(Some(value.clone())),
+        (None, Some(path)) => {
+            let value = std::fs::read_to_string(path).map_err(|e| {
+                anyhow!(
+                    "The secret file of {} from [{}] is not readable. error: {:?}",
+                    name,
+                    path,
+                    e
+                )
+            })?;
+            Ok(Some(value.trim_end_matches(['\n', '\r']).to_string()))
+        }
+        (None, None) => Ok(None),
+    }
+}
+
+// =========================================================
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct LogConfig {
+    pub path: String,
+    #[serde(default = "as_default_rotation_config")]
+    pub rotation: RotationConfig,
+}
+fn as_default_rotation_config() -> RotationConfig {
+    RotationConfig::Daily
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        LogConfig {
+            path: "/tmp/".to_string(),
+            rotation: RotationConfig::Hourly,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub enum RotationConfig {
+    Hourly,
+    Daily,
+    Never,
+}
+
+// =========================================================
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Copy)]
+#[allow(non_camel_case_types)]
+pub enum StorageType {
+    MEMORY = 1,
+    LOCALFILE = 2,
+    MEMORY_LOCALFILE = 3,
+    HDFS = 4,
+    MEMORY_HDFS = 5,
+    MEMORY_LOCALFILE_HDFS = 7,
+}
+
+impl Default for StorageType {
+    fn default() -> Self {
+        StorageType::MEMORY
+    }
+}
+
+impl StorageType {
+    /// All the variants, it should be updated once the new one is introduced.
+    pub fn all() -> &'static [StorageType] {
+        &[
+            StorageType::MEMORY,
+            StorageType::LOCALFILE,
+            StorageType::MEMORY_LOCALFILE,
+            StorageType::HDFS,
+            StorageType::MEMORY_HDFS,
+            StorageType::MEMORY_LOCALFILE_HDFS,
+        ]
+    }
+
+    pub fn variants() -> impl Iterator<Item = StorageType> {
+        StorageType::all().iter().copied()
+    }
+
+    pub fn contains_localfile(storage_type: &StorageType) -> bool {
+        let val = *storage_type as u8;
+        val & *&StorageType::LOCALFILE as u8 != 0
+    }
+
+    pub fn contains_memory(storage_type: &StorageType) -> bool {
+        let val = *storage_type as u8;
+        val & *&StorageType::MEMORY as u8 != 0
+    }
+
+    pub fn contains_hdfs(storage_type: &StorageType) -> bool {
+        let val = *storage_type as u8;
+        val & *&StorageType::HDFS as u8 != 0
+    }
+
+    /// The memory staged type without the localfile tier, like MEMORY_LOCALFILE_HDFS -> MEMORY_HDFS.
+    pub fn without_localfile(&self) -> StorageType {
+        if StorageType::contains_hdfs(self) {
+            StorageType::MEMORY_HDFS
+        } else {
+            StorageType::MEMORY
+        }
+    }
+}
+
+impl Display for StorageType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            StorageType::MEMORY => "MEMORY",
+            StorageType::LOCALFILE => "LOCALFILE",
+            StorageType::MEMORY_LOCALFILE => "MEMORY_LOCALFILE",
+            StorageType::HDFS => "HDFS",
+            StorageType::MEMORY_HDFS => "MEMORY_HDFS",
+            StorageType::MEMORY_LOCALFILE_HDFS => "MEMORY_LOCALFILE_HDFS",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for StorageType {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        StorageType::variants()
+            .find(|x| x.to_string().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| {
+                anyhow!(
+                    "Unknown storage type: [{}], it should be one of {:?}",
+                    s,
+                    StorageType::all()
+                )
+            })
+    }
+}
+
+const CONFIG_FILE_PATH_KEY: &str = "WORKER_CONFIG_PATH";
+const DEFAULT_CONFIG_FILE_PATHS: &[&str] = &["./rifflex.toml", "/etc/rifflex/config.toml"];
+const MIN_MEMORY_SPILL_WATERMARK_GAP: f32 = 0.05;
+// the OS usually reserves ~5% of the disk for the root, beyond which the spill can't stop in time
+const MAX_ADVISED_DISK_HIGH_WATERMARK: f32 = 0.95;
+
+impl Config {
+    pub fn bind_address(&self) -> Result<IpAddr> {
+        let address = self.bind_address.as_deref().unwrap_or(DEFAULT_BIND_ADDRESS);
+        IpAddr::from_str(address.trim()).map_err(|_| {
+            anyhow!(
+                "Illegal bind_address: [{}], it should be an ip address",
+                address
+            )
+        })
+    }
+
+    pub fn shutdown_timeout(&self) -> Result<Duration> {
+        let timeout = match &self.shutdown_timeout {
+            Some(timeout) => humantime::parse_duration(timeout)
+                .map_err(|e| anyhow!("Illegal shutdown_timeout: [{}]. err: {}", timeout, e))?,
+            _ => DEFAULT_SHUTDOWN_DEADLINE,
+        };
+        if timeout.is_zero() {
+            return Err(anyhow!(
+                "Illegal shutdown_timeout: 0, it should be positive"
+            ));
+        }
+        Ok(timeout)
+    }
+
+    pub fn grpc_max_recv_message_size(&self) -> Result<usize> {
+        parse_grpc_max_message_size(
+            "grpc_max_recv_message_size",
+            &self.grpc_max_recv_message_size,
+        )
+    }
+
+    pub fn grpc_max_send_message_size(&self) -> Result<usize> {
+        parse_grpc_max_message_size(
+            "grpc_max_send_message_size",
+            &self.grpc_max_send_message_size,
+        )
+    }
+
+    /// Parse the yaml content, which is validated like the toml one.
+    pub fn from_yaml_str(contents: &str) -> Result<Config, ConfigError> {
+        let config: Config = serde_yaml::from_str(contents)?;
+        config.validate().map_err(ConfigError::Invalid)?;
+        Ok(config)
+    }
+
+    /// The gzip compressed file like `config.toml.gz` is decompressed transparently.
+    pub fn from(cfg_path: &str) -> Self {
+        let path = Path::new(cfg_path);
+
+        // Read the file content as a string
+        let file_content = read_config_content(path).expect("Failed to read file");
+
+        parse_toml(&file_content)
+            .unwrap_or_else(|e| panic!("Failed to parse the config file: {}. {}", cfg_path, e))
+    }
+
+    /// The config of the event bus by its scope, falling back to the default one.
+    pub fn event_bus_config(&self, scope: &str) -> EventBusConfig {
+        self.event_bus
+            .get(scope)
+            .or_else(|| self.event_bus.get(DEFAULT_EVENT_BUS_SCOPE))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    fn validate_event_bus(&self) -> Result<()> {
+        for (scope, config) in &self.event_bus {
+            config
+                .queue_capacity()
+                .and_then(|_| config.overflow())
+                .map_err(|e| anyhow!("Illegal event_bus.{}. {}", scope, e))?;
+        }
+        // the spill event can't be dropped, otherwise the spilling buffer is leaked
+        if self.event_bus_config(SPILL_EVENT_BUS_SCOPE).overflow()? == QueueOverflow::Reject {
+            return Err(anyhow!(
+                "Illegal event_bus.{}.overflow: reject, the spill events must not be rejected",
+                SPILL_EVENT_BUS_SCOPE
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        let app_heartbeat_timeout = self.app_config.app_heartbeat_timeout()?;
+        if app_heartbeat_timeout.is_zero() {
+            return Err(anyhow!("app heartbeat timeout must be greater than zero"));
+        }
+        if self.app_config.max_concurrent_apps() == Some(0) {
+            return Err(anyhow!(
+                "app_config.max_concurrent_apps must be greater than zero"
+            ));
+        }
+        self.coordinator.connect_timeout()?;
+        self.coordinator.retry_interval()?;
+        self.server.slow_request_threshold()?;
+        self.server.stuck_task_threshold()?;
+        self.bind_address()?;
+        self.validate_ports()?;
+        self.block_id_layout.validate()?;
+        self.runtime_config.validate()?;
+        self.grpc_max_recv_message_size()?;
+        self.grpc_max_send_message_size()?;
+        self.validate_event_bus()?;
+        self.busy_score.validate()?;
+        self.urpc.validate()?;
+        self.shutdown_timeout()?;
+        if let Some(tls_config) = &self.grpc_tls {
+            tls_config.validate()?;
+        }
+        if let Some(metrics) = &self.metrics {
+            metrics.validate()?;
+        }
+
+        if let Some(memory_store) = &self.memory_store {
+            memory_store.capacity_bytes()?;
+            memory_store.partition_buffer_max_size_bytes()?;
+            memory_store.dashmap_shard_stats_interval()?;
+            memory_store.snapshot_drift_threshold_bytes()?;
+        }
+        if let Some(localfile_store) = &self.localfile_store {
+            localfile_store.validate()?;
+        }
+        if let Some(capacity) = self.hdfs_store.as_ref().and_then(|x| x.capacity.as_ref()) {
+            parse_readable_size("hdfs_store.capacity", capacity)?;
+        }
+        let hybrid_store = &self.hybrid_store;
+        if let Some(size) = &hybrid_store.memory_single_buffer_max_spill_size {
+            parse_readable_size("hybrid_store.memory_single_buffer_max_spill_size", size)?;
+        }
+        if let Some(size) = &hybrid_store.memory_spill_to_cold_threshold_size {
+            parse_readable_size("hybrid_store.memory_spill_to_cold_threshold_size", size)?;
+        }
+        if let Some(size) = &hybrid_store.read_cache_capacity {
+            parse_readable_size("hybrid_store.read_cache_capacity", size)?;
+        }
+        let high_watermark = hybrid_store.memory_spill_high_watermark;
+        let low_watermark = hybrid_store.memory_spill_low_watermark;
+        if !(0.0..=1.0).contains(&high_watermark)
+            || !(0.0..=1.0).contains(&low_watermark)
+            || low_watermark > high_watermark
+        {
+            return Err(anyhow!(
+                "Illegal memory spill watermarks. high: {}, low: {}",
+                high_watermark,
+                low_watermark
+            ));
+        }
+        if let Some(admission) = &hybrid_store.spill_debt_admission {
+            let watermark = admission.projected_watermark;
+            if watermark <= high_watermark || watermark > 1.0 {
+                return Err(anyhow!(
+                    "Illegal hybrid_store.spill_debt_admission.projected_watermark: {}, it should be in ({}, 1]",
+                    watermark,
+                    high_watermark
+                ));
+            }
+            admission.max_delay()?;
+        }
+        if let Some(concurrency) = self.app_config.per_app_spill_concurrency {
+            let max_concurrency = hybrid_store.memory_spill_max_concurrency;
+            if concurrency < 1 || concurrency > max_concurrency {
+                return Err(anyhow!(
+                    "Illegal app_config.per_app_spill_concurrency: {}, it should be in [1, {}]",
+                    concurrency,
+                    max_concurrency
+                ));
+            }
+        }
+        if let Some(share) = self.app_config.hot_partition_report_share {
+            if share <= 0.0 || share > 1.0 {
+                return Err(anyhow!(
+                    "Illegal app_config.hot_partition_report_share: {}, it should be in (0, 1]",
+                    share
+                ));
+            }
+        }
+        for quota in self
+            .app_config
+            .quota
+            .iter()
+            .chain(self.app_config.quota_overrides.values())
+        {
+            quota.max_total_bytes()?;
+            quota.max_memory_bytes()?;
+        }
+        Ok(())
+    }
+
+    fn validate_ports(&self) -> Result<()> {
+        let mut ports = vec![("grpc_port", self.grpc_port as i64)];
+        if let Some(urpc_port) = self.urpc_port {
+            ports.push(("urpc_port", urpc_port as i64));
+        }
+        ports.push((
+            "http_monitor_service_port",
+            self.http_monitor_service_port as i64,
+        ));
+        for (idx, (name, port)) in ports.iter().enumerate() {
+            // the port 0 is assigned by the OS, which never collides
+            if *port == 0 {
+                continue;
+            }
+            if let Some((other, _)) = ports[idx + 1..].iter().find(|(_, x)| x == port) {
+                return Err(anyhow!(
+                    "The {} collides with the {}, both are {}",
+                    name,
+                    other,
+                    port
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Resolve the capacity of every activated storage tier. The localfile budget is
+    /// the total disk space of the write paths applied with the high watermark, the
+    /// unreachable data paths will be ignored.
+    pub fn total_capacity_report(&self) -> CapacityReport {
+        let store_type = &self.store_type;
+
+        let memory = match &self.memory_store {
+            Some(conf) if StorageType::contains_memory(store_type) => conf.capacity_bytes().ok(),
+            _ => None,
+        };
+
+        let localfile = match &self.localfile_store {
+            Some(conf) if StorageType::contains_localfile(store_type) => {
+                let capacities: Vec<u64> = conf
+                    .effective_write_paths()
+                    .iter()
+                    .filter_map(|path| fs2::total_space(path).ok())
+                    .map(|capacity| (capacity as f64 * conf.disk_high_watermark as f64) as u64)
+                    .collect();
+                if capacities.is_empty() {
+                    None
+                } else {
+                    Some(capacities.iter().sum())
+                }
+            }
+            _ => None,
+        };
+
+        let hdfs = match &self.hdfs_store {
+            Some(HdfsStoreConfig {
+                capacity: Some(capacity),
+                ..
+            }) if StorageType::contains_hdfs(store_type) => {
+                ReadableSize::parse(capacity).ok().map(|x| x.as_bytes())
+            }
+            _ => None,
+        };
+
+        CapacityReport {
+            memory,
+            localfile,
+            hdfs,
+            total: memory.unwrap_or(0) + localfile.unwrap_or(0) + hdfs.unwrap_or(0),
+        }
+    }
+
+    /// Degrade the store type by dropping the localfile tier once none of its paths is
+    /// usable, only if the `degrade_on_store_init_failure` is enabled. The checked paths
+    /// are returned on degrading, and it should be called before the `resolve_stores`.
+    pub fn degrade_unusable_stores(&mut self) -> Option<Vec<PathHealth>> {
+        if !self.degrade_on_store_init_failure || !StorageType::contains_localfile(&self.store_type)
+        {
+            return None;
+        }
+        let health = self.localfile_store.as_ref()?.check_mounts();
+        if health.iter().any(|x| x.is_healthy()) {
+            return None;
+        }
+        self.store_type = self.store_type.without_localfile();
+        self.localfile_store = None;
+        Some(health)
+    }
+
+    /// Cross check the `store_type` with the presence of the matching store configs,
+    /// all the problems are collected rather than failing on the first one.
+    /// The `hybrid_store` falls back to the default one, so it's never missing.
+    pub fn resolve_stores(&self) -> std::result::Result<ResolvedStores, Vec<String>> {
+        let store_type = self.store_type;
+        let mut problems = vec![];
+        let mut require = |activated: bool, present: bool, name: &str| {
+            if activated && !present {
+                problems.push(format!(
+                    "The store_type: {} requires the [{}] config",
+                    store_type, name
+                ));
+            }
+            activated && present
+        };
+
+        let memory = require(
+            StorageType::contains_memory(&store_type),
+            self.memory_store.is_some(),
+            "memory_store",
+        );
+        let localfile = require(
+            StorageType::contains_localfile(&store_type),
+            self.localfile_store.is_some(),
+            "localfile_store",
+        );
+        let hdfs = require(
+            StorageType::contains_hdfs(&store_type),
+            self.hdfs_store.is_some(),
+            "hdfs_store",
+        );
+
+        // the persistent stores are always staged in memory
+        if !StorageType::contains_memory(&store_type) {
+            problems.push(format!(
+                "The store_type: {} is not supported without the memory",
+                store_type
+            ));
+        }
+        #[cfg(not(feature = "hdfs"))]
+        if StorageType::contains_hdfs(&store_type) {
+            problems.push(format!(
+                "The store_type: {} requires the binary compiled with the hdfs feature",
+                store_type
+            ));
+        }
+
+        if !problems.is_empty() {
+            return Err(problems);
+        }
+
+        let tiers = [memory, localfile, hdfs].iter().filter(|x| **x).count();
+        Ok(ResolvedStores {
+            store_type,
+            memory: self.memory_store.clone().filter(|_| memory),
+            localfile: self.localfile_store.clone().filter(|_| localfile),
+            hdfs: self.hdfs_store.clone().filter(|_| hdfs),
+            hybrid: Some(self.hybrid_store.clone()).filter(|_| tiers > 1),
+        })
+    }
+
+    /// Collect the soft advices which are valid but almost always mistakes,
+    /// they are only warned rather than rejected like the `validate`.
+    pub fn lint(&self) -> Vec<String> {
+        let mut warnings = vec![];
+        let high_watermark = self.hybrid_store.memory_spill_high_watermark;
+        let low_watermark = self.hybrid_store.memory_spill_low_watermark;
+        if high_watermark - low_watermark < MIN_MEMORY_SPILL_WATERMARK_GAP {
+            warnings.push(format!(
+                "The gap of memory spill watermarks (high: {}, low: {}) is less than {}, \
+                which will cause the spill thrashing",
+                high_watermark, low_watermark, MIN_MEMORY_SPILL_WATERMARK_GAP
+            ));
+        }
+        if let Some(localfile_store) = &self.localfile_store {
+            if localfile_store.disk_high_watermark > MAX_ADVISED_DISK_HIGH_WATERMARK {
+                warnings.push(format!(
+                    "The localfile_store.disk_high_watermark: {} is more than {}, \
+                    the disk may be full before the spill stops due to the reserved space",
+                    localfile_store.disk_high_watermark, MAX_ADVISED_DISK_HIGH_WATERMARK
+                ));
+            }
+        }
+        warnings
+    }
+
+    /// Load the config from the path specified by the env of `WORKER_CONFIG_PATH`,
+    /// or the first existing one of the default candidate paths.
+    pub fn create_from_env() -> Result<Config> {
+        Config::create_from_env_or(DEFAULT_CONFIG_FILE_PATHS)
+    }
+
+    fn create_from_env_or(candidates: &[&str]) -> Result<Config> {
+        let mut tried = vec![];
+        if let Ok(path) = std::env::var(CONFIG_FILE_PATH_KEY) {
+            tried.push(path);
+        }
+        tried.extend(candidates.iter().map(|x| x.to_string()));
+
+        match tried.iter().find(|path| Path::new(path).is_file()) {
+            Some(path) => Ok(Config::from(path)),
+            _ => Err(anyhow!(
+                "No config file is found, env key: {}, tried paths: {:?}",
+                CONFIG_FILE_PATH_KEY,
+                tried
+            )),
+        }
+    }
 
     pub fn create_mem_localfile_config(
         grpc_port: i32,
@@ -401,42 +1914,980 @@ impl Config {
         coordinator_quorum = [""]
         grpc_port = {:?}
 
-        [memory_store]
-        capacity = {:?}
+        [memory_store]
+        capacity = {:?}
+
+        [localfile_store]
+        data_paths = [{:?}]
+        "#,
+            grpc_port, capacity, local_data_path
+        );
+
+        toml::from_str(toml_str.as_str()).unwrap()
+    }
+
+    pub fn create_simple_config() -> Config {
+        let toml_str = r#"
+        store_type = "MEMORY"
+        coordinator_quorum = [""]
+        grpc_port = 19999
+
+        [memory_store]
+        capacity = "1M"
+
+        [hybrid_store]
+        memory_spill_high_watermark = 0.8
+        memory_spill_low_watermark = 0.2
+        memory_single_buffer_max_spill_size = "256M"
+        "#;
+
+        Config::from_str(toml_str).unwrap()
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error("{0}")]
+    Parse(TomlParseError),
+
+    #[error("Illegal yaml config. {0}")]
+    Yaml(#[from] serde_yaml::Error),
+
+    #[error("Invalid config. {0:#}")]
+    Invalid(anyhow::Error),
+}
+
+/// Parse the in-memory toml content with the same defaulting and validation of the file.
+impl FromStr for Config {
+    type Err = ConfigError;
+
+    fn from_str(contents: &str) -> Result<Self, Self::Err> {
+        let config: Config = parse_toml(contents)?;
+        config.validate().map_err(ConfigError::Invalid)?;
+        Ok(config)
+    }
+}
+
+fn parse_toml<T: DeserializeOwned>(contents: &str) -> Result<T, ConfigError> {
+    toml::from_str(contents).map_err(|e| ConfigError::Parse(TomlParseError::new(e, contents)))
+}
+
+/// The toml error with the snippet of the offending lines, which is rendered like:
+/// ```text
+/// Illegal toml config at line 5, column 12: invalid type: integer `1024`, expected a string
+///   |
+/// 4 | [memory_store]
+/// 5 | capacity = 1024
+///   |            ^^^^
+/// ```
+#[derive(Debug)]
+pub struct TomlParseError {
+    source: toml::de::Error,
+    // both start from 1, none if the error has no span
+    line: Option<usize>,
+    column: Option<usize>,
+    snippet: String,
+}
+
+impl TomlParseError {
+    fn new(source: toml::de::Error, contents: &str) -> Self {
+        let mut error = TomlParseError {
+            source,
+            line: None,
+            column: None,
+            snippet: String::new(),
+        };
+        if let Some(span) = error.source.span() {
+            let start = span.start.min(contents.len());
+            let line_start = contents[..start].rfind('\n').map_or(0, |x| x + 1);
+            let line = contents[..start].matches('\n').count();
+            let column = contents[line_start..start].chars().count();
+            error.line = Some(line + 1);
+            error.column = Some(column + 1);
+            error.snippet = Self::render_snippet(contents, span, line, column);
+        }
+        error
+    }
+
+    fn render_snippet(contents: &str, span: Range<usize>, line: usize, column: usize) -> String {
+        let lines: Vec<&str> = contents
+            .split('\n')
+            .map(|x| x.trim_end_matches('\r'))
+            .collect();
+        let width = (line + 1).to_string().len();
+        let mut snippet = format!("{:width$} |\n", "");
+        // the previous line as the context
+        if line > 0 {
+            snippet.push_str(&format!("{:>width$} | {}\n", line, lines[line - 1]));
+        }
+        let current = lines.get(line).copied().unwrap_or_default();
+        snippet.push_str(&format!("{:>width$} | {}\n", line + 1, current));
+        // the span crossing lines is marked till the end of the first line
+        let marked = current
+            .chars()
+            .count()
+            .saturating_sub(column)
+            .min(span.len())
+            .max(1);
+        snippet.push_str(&format!(
+            "{:width$} | {}{}",
+            "",
+            " ".repeat(column),
+            "^".repeat(marked)
+        ));
+        snippet
+    }
+
+    pub fn line(&self) -> Option<usize> {
+        self.line
+    }
+
+    pub fn column(&self) -> Option<usize> {
+        self.column
+    }
+
+    /// The byte range of the original content.
+    pub fn span(&self) -> Option<Range<usize>> {
+        self.source.span()
+    }
+
+    pub fn message(&self) -> &str {
+        self.source.message()
+    }
+}
+
+impl Display for TomlParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match (self.line, self.column) {
+            (Some(line), Some(column)) => write!(
+                f,
+                "Illegal toml config at line {}, column {}: {}\n{}",
+                line,
+                column,
+                self.message(),
+                &self.snippet
+            ),
+            _ => write!(f, "Illegal toml config. {}", &self.source),
+        }
+    }
+}
+
+impl std::error::Error for TomlParseError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// Read the config file, which is decompressed when it has the `.gz` extension
+/// or starts with the gzip magic bytes.
+fn read_config_content(path: &Path) -> Result<String> {
+    let bytes = fs::read(path)?;
+    let gzipped = path.extension().map_or(false, |x| x == "gz") || bytes.starts_with(&GZIP_MAGIC);
+    if !gzipped {
+        return Ok(String::from_utf8(bytes)?);
+    }
+    let mut content = String::new();
+    GzDecoder::new(bytes.as_slice())
+        .read_to_string(&mut content)
+        .map_err(|e| anyhow!("Failed to decompress the config file {:?}. {:?}", path, e))?;
+    Ok(content)
+}
+
+/// The store configs of the activated tiers resolved by the `store_type`, the `hybrid`
+/// is only set for the multiple tiers.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ResolvedStores {
+    pub store_type: StorageType,
+    pub memory: Option<MemoryStoreConfig>,
+    pub localfile: Option<LocalfileStoreConfig>,
+    pub hdfs: Option<HdfsStoreConfig>,
+    pub hybrid: Option<HybridStoreConfig>,
+}
+
+/// The capacity in bytes of every storage tier, `None` means the tier is not activated.
+#[derive(Clone, Debug, Serialize, PartialEq, Default)]
+pub struct CapacityReport {
+    pub memory: Option<u64>,
+    pub localfile: Option<u64>,
+    pub hdfs: Option<u64>,
+    pub total: u64,
+}
+
+fn system_total_memory() -> u64 {
+    let mut system = sysinfo::System::new();
+    system.refresh_memory();
+    system.total_memory()
+}
+
+/// Parse the percentage form like "60%" into the ratio in (0, 1], none if it's not the percentage.
+fn parse_percent(name: &str, value: &str) -> Result<Option<f64>> {
+    let percent = match value.trim().strip_suffix('%') {
+        Some(percent) => percent,
+        _ => return Ok(None),
+    };
+    let ratio = f64::from_str(percent.trim())
+        .map_err(|e| anyhow!("Illegal percentage of {}: [{}]. err: {}", name, value, e))?
+        / 100.0;
+    if ratio <= 0.0 || ratio > 1.0 {
+        return Err(anyhow!(
+            "Illegal percentage of {}: [{}], it should be in (0%, 100%]",
+            name,
+            value
+        ));
+    }
+    Ok(Some(ratio))
+}
+
+fn parse_grpc_max_message_size(name: &str, size: &Option<String>) -> Result<usize> {
+    let bytes = match size {
+        Some(size) => parse_readable_size(name, size)?.as_bytes(),
+        _ => DEFAULT_GRPC_MAX_MESSAGE_SIZE,
+    };
+    if bytes < MIN_GRPC_MAX_MESSAGE_SIZE {
+        return Err(anyhow!(
+            "Illegal size of {}: [{}], it should not be less than 1M",
+            name,
+            size.as_deref().unwrap_or_default()
+        ));
+    }
+    Ok(bytes as usize)
+}
+
+fn parse_readable_size(name: &str, size: &str) -> Result<ReadableSize> {
+    ReadableSize::parse(size)
+        .map_err(|e| anyhow!("Illegal size of {}: [{}]. err: {}", name, size, e))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::block_id::BlockIdLayout;
+    use crate::config::{
+        as_default_app_heartbeat_timeout_min, parse_cpuset, AppQuotaConfig, BusyScoreConfig,
+        Config, ConfigError, EventBusConfig, FsyncPolicy, HdfsStoreConfig,
+        LocalfileCompactionConfig, LocalfileStoreConfig, MemoryStoreConfig, PathHealth, PoolType,
+        QueueOverflow, ReadEngine, RuntimeConfig, StorageType, WorkloadProfile,
+        CONFIG_FILE_PATH_KEY, REDACTED_SECRET,
+    };
+    use crate::event_bus::EventBusOptions;
+    use crate::readable_size::ReadableSize;
+    use std::fs;
+    use std::str::FromStr;
+    use std::time::Duration;
+
+    #[test]
+    fn simple_config_test() {
+        let config = Config::create_simple_config();
+        assert!(config.validate().is_ok());
+
+        let capacity = ReadableSize::parse(&config.memory_store.as_ref().unwrap().capacity)
+            .unwrap()
+            .as_bytes();
+        assert_eq!(1024 * 1024, capacity);
+
+        let spill_size = config
+            .hybrid_store
+            .memory_single_buffer_max_spill_size
+            .as_ref()
+            .unwrap();
+        assert_eq!(
+            256 * 1024 * 1024,
+            ReadableSize::parse(spill_size).unwrap().as_bytes()
+        );
+
+        // illegal size will be rejected
+        let mut config = Config::create_simple_config();
+        config.memory_store.as_mut().unwrap().capacity = "1X".to_string();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn total_capacity_report_test() -> anyhow::Result<()> {
+        let temp_dir = tempdir::TempDir::new("total_capacity_report_test")?;
+        let data_path = temp_dir.path().to_str().unwrap().to_string();
+
+        let mut config =
+            Config::create_mem_localfile_config(100, "20G".to_string(), data_path.clone());
+        config.hdfs_store = Some(HdfsStoreConfig {
+            max_concurrency: 10,
+            capacity: Some("1T".to_string()),
+        });
+
+        let report = config.total_capacity_report();
+        let memory = 20 * 1024 * 1024 * 1024;
+        let localfile = (fs2::total_space(&data_path)? as f64 * 0.8) as u64;
+        assert_eq!(Some(memory), report.memory);
+        assert_eq!(Some(localfile), report.localfile);
+        // hdfs is not activated by the MEMORY_LOCALFILE
+        assert_eq!(None, report.hdfs);
+        assert_eq!(memory + localfile, report.total);
+
+        config.store_type = StorageType::MEMORY;
+        let report = config.total_capacity_report();
+        assert_eq!(None, report.localfile);
+        assert_eq!(memory, report.total);
+
+        Ok(())
+    }
+
+    #[test]
+    fn create_from_env_test() -> anyhow::Result<()> {
+        let temp_dir = tempdir::TempDir::new("create_from_env_test")?;
+        let env_path = temp_dir.path().join("env.toml");
+        let fallback_path = temp_dir.path().join("fallback.toml");
+        let missing_path = temp_dir.path().join("missing.toml");
+        let toml_str = r#"
+        coordinator_quorum = ["xxxxxxx"]
+        grpc_port = 10000
+        "#;
+        std::fs::write(&env_path, toml_str)?;
+        std::fs::write(&fallback_path, toml_str.replace("10000", "20000"))?;
+
+        let fallback = fallback_path.to_str().unwrap();
+        let missing = missing_path.to_str().unwrap();
+
+        // case1: env is set
+        std::env::set_var(CONFIG_FILE_PATH_KEY, env_path.to_str().unwrap());
+        let config = Config::create_from_env_or(&[fallback])?;
+        assert_eq!(10000, config.grpc_port);
+
+        // case2: fallback to the candidates
+        std::env::remove_var(CONFIG_FILE_PATH_KEY);
+        let config = Config::create_from_env_or(&[missing, fallback])?;
+        assert_eq!(20000, config.grpc_port);
+
+        // case3: nothing found
+        let err = Config::create_from_env_or(&[missing]).unwrap_err();
+        assert!(err.to_string().contains(missing));
+
+        Ok(())
+    }
+
+    #[test]
+    fn gzipped_config_test() -> anyhow::Result<()> {
+        use flate2::write::GzEncoder;
+        use flate2::Compression;
+        use std::io::Write;
+
+        let temp_dir = tempdir::TempDir::new("gzipped_config_test")?;
+        let toml_str = r#"
+        store_type = "MEMORY_LOCALFILE"
+        coordinator_quorum = ["xxxxxxx"]
+        grpc_port = 10000
+
+        [memory_store]
+        capacity = "1G"
+
+        [localfile_store]
+        data_paths = ["/data1", "/data2"]
+        "#;
+        let mut encoder = GzEncoder::new(vec![], Compression::default());
+        encoder.write_all(toml_str.as_bytes())?;
+        let gzipped = encoder.finish()?;
+
+        let plain_path = temp_dir.path().join("config.toml");
+        std::fs::write(&plain_path, toml_str)?;
+        let expected = Config::from(plain_path.to_str().unwrap());
+
+        // detected by the extension
+        let gz_path = temp_dir.path().join("config.toml.gz");
+        std::fs::write(&gz_path, &gzipped)?;
+        assert_eq!(expected, Config::from(gz_path.to_str().unwrap()));
+
+        // detected by the magic bytes
+        let magic_path = temp_dir.path().join("config.compressed");
+        std::fs::write(&magic_path, &gzipped)?;
+        assert_eq!(expected, Config::from(magic_path.to_str().unwrap()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn memory_capacity_bytes_test() {
+        let total_memory = || 100 * 1024 * 1024;
+
+        // case1: absolute size
+        let conf = MemoryStoreConfig::new("10M".to_string());
+        assert_eq!(
+            10 * 1024 * 1024,
+            conf.capacity_bytes_with(total_memory).unwrap()
+        );
+        assert_eq!(10 * 1024 * 1024, conf.capacity_bytes().unwrap());
+
+        // case2: the percentage of the total system memory
+        let conf = MemoryStoreConfig::new("60%".to_string());
+        assert_eq!(
+            60 * 1024 * 1024,
+            conf.capacity_bytes_with(total_memory).unwrap()
+        );
+        let conf = MemoryStoreConfig::new("100%".to_string());
+        assert_eq!(
+            100 * 1024 * 1024,
+            conf.capacity_bytes_with(total_memory).unwrap()
+        );
+
+        // case3: out of (0, 1]
+        for illegal in ["0%", "101%", "-1%", "x%"] {
+            let conf = MemoryStoreConfig::new(illegal.to_string());
+            assert!(conf.capacity_bytes_with(total_memory).is_err());
+
+            let mut config = Config::create_simple_config();
+            config.memory_store = Some(conf);
+            assert!(config.validate().is_err());
+        }
+    }
+
+    #[test]
+    fn read_cache_capacity_test() {
+        let mut config = Config::create_simple_config();
+        config.hybrid_store.read_cache_capacity = Some("1M".to_string());
+        assert!(config.validate().is_ok());
+
+        config.hybrid_store.read_cache_capacity = Some("1X".to_string());
+        let err = config.validate().unwrap_err().to_string();
+        assert!(err.contains("hybrid_store.read_cache_capacity"));
+    }
+
+    #[test]
+    fn lint_test() {
+        let mut config = Config::create_simple_config();
+        assert!(config.lint().is_empty());
+
+        config.hybrid_store.memory_spill_high_watermark = 0.8;
+        config.hybrid_store.memory_spill_low_watermark = 0.79;
+        assert!(config.validate().is_ok());
+        let warnings = config.lint();
+        assert_eq!(1, warnings.len());
+        assert!(warnings[0].contains("thrashing"));
+    }
+
+    #[test]
+    fn disk_high_watermark_test() {
+        let mut config =
+            Config::create_mem_localfile_config(19999, "1G".to_string(), "/data1".to_string());
+        assert!(config.validate().is_ok());
+        assert!(config.lint().is_empty());
+
+        // warned only
+        config.localfile_store.as_mut().unwrap().disk_high_watermark = 0.97;
+        assert!(config.validate().is_ok());
+        let warnings = config.lint();
+        assert_eq!(1, warnings.len());
+        assert!(warnings[0].contains("disk_high_watermark: 0.97"));
+
+        for illegal in [1.0, 1.2, 0.0, -0.1, f32::NAN] {
+            config.localfile_store.as_mut().unwrap().disk_high_watermark = illegal;
+            let err = config.validate().unwrap_err();
+            assert!(err.to_string().contains("disk_high_watermark"));
+        }
+    }
+
+    #[test]
+    fn resolve_stores_test() {
+        let config =
+            Config::create_mem_localfile_config(19999, "1G".to_string(), "/data1".to_string());
+        let stores = config.resolve_stores().unwrap();
+        assert_eq!(StorageType::MEMORY_LOCALFILE, stores.store_type);
+        assert_eq!(config.memory_store, stores.memory);
+        assert_eq!(config.localfile_store, stores.localfile);
+        assert!(stores.hdfs.is_none());
+        assert_eq!(Some(config.hybrid_store.clone()), stores.hybrid);
+
+        // the single tier needs no hybrid store
+        let stores = Config::create_simple_config().resolve_stores().unwrap();
+        assert!(stores.memory.is_some());
+        assert!(stores.hybrid.is_none());
+
+        // the type says hdfs, but no hdfs_store
+        let mut config = config;
+        config.store_type = StorageType::MEMORY_LOCALFILE_HDFS;
+        let problems = config.resolve_stores().unwrap_err();
+        assert!(problems.iter().any(|x| x.contains("[hdfs_store]")));
+
+        config.hdfs_store = Some(HdfsStoreConfig::default());
+        config.memory_store = None;
+        let problems = config.resolve_stores().unwrap_err();
+        assert!(problems.iter().any(|x| x.contains("[memory_store]")));
+        assert!(problems.iter().all(|x| !x.contains("[hdfs_store]")));
+    }
+
+    #[test]
+    fn degrade_unusable_stores_test() {
+        let temp_dir = tempdir::TempDir::new("degrade_unusable_stores_test").unwrap();
+        let unusable_path = temp_dir.path().join("not_existed");
+        let mut config = Config::create_mem_localfile_config(
+            19999,
+            "1G".to_string(),
+            unusable_path.to_str().unwrap().to_string(),
+        );
+
+        // disabled by default, the failure is left to the store initialization
+        let mut origin = config.clone();
+        assert!(origin.degrade_unusable_stores().is_none());
+        assert_eq!(config, origin);
+
+        config.degrade_on_store_init_failure = true;
+        let health = config.degrade_unusable_stores().unwrap();
+        assert_eq!(1, health.len());
+        assert!(!health[0].is_healthy());
+
+        let stores = config.resolve_stores().unwrap();
+        assert_eq!(StorageType::MEMORY, stores.store_type);
+        assert!(stores.memory.is_some());
+        assert!(stores.localfile.is_none());
+        assert!(stores.hybrid.is_none());
+
+        // the hdfs tier is kept
+        assert_eq!(
+            StorageType::MEMORY_HDFS,
+            StorageType::MEMORY_LOCALFILE_HDFS.without_localfile()
+        );
+
+        // not degraded with any usable path
+        let mut config = Config::create_mem_localfile_config(
+            19999,
+            "1G".to_string(),
+            temp_dir.path().to_str().unwrap().to_string(),
+        );
+        config.degrade_on_store_init_failure = true;
+        assert!(config.degrade_unusable_stores().is_none());
+        assert_eq!(StorageType::MEMORY_LOCALFILE, config.store_type);
+    }
+
+    #[test]
+    fn grpc_max_message_size_test() {
+        let mut config = Config::create_simple_config();
+        assert_eq!(
+            1024 * 1024 * 1024,
+            config.grpc_max_recv_message_size().unwrap()
+        );
+        assert_eq!(
+            1024 * 1024 * 1024,
+            config.grpc_max_send_message_size().unwrap()
+        );
+
+        config.grpc_max_recv_message_size = Some("512M".to_string());
+        config.grpc_max_send_message_size = Some("1M".to_string());
+        assert!(config.validate().is_ok());
+        assert_eq!(
+            512 * 1024 * 1024,
+            config.grpc_max_recv_message_size().unwrap()
+        );
+        assert_eq!(1024 * 1024, config.grpc_max_send_message_size().unwrap());
+
+        // below the floor
+        config.grpc_max_send_message_size = Some("512K".to_string());
+        let err = config.validate().unwrap_err().to_string();
+        assert!(err.contains("grpc_max_send_message_size"));
+
+        config.grpc_max_send_message_size = None;
+        config.grpc_max_recv_message_size = Some("1x".to_string());
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn port_collision_test() {
+        let mut config = Config::create_simple_config();
+        config.urpc_port = Some(20000);
+        assert!(config.validate().is_ok());
+
+        config.http_monitor_service_port = config.grpc_port as u16;
+        let err = config.validate().unwrap_err().to_string();
+        assert!(err.contains("grpc_port"));
+        assert!(err.contains("http_monitor_service_port"));
+
+        config.http_monitor_service_port = 20010;
+        config.urpc_port = Some(20010);
+        let err = config.validate().unwrap_err().to_string();
+        assert!(err.contains("urpc_port"));
+        assert!(err.contains("http_monitor_service_port"));
+    }
+
+    #[test]
+    fn block_id_layout_test() {
+        let config = Config::create_simple_config();
+        assert_eq!(BlockIdLayout::default(), config.block_id_layout);
+
+        let toml_str = r#"
+        store_type = "MEMORY"
+        coordinator_quorum = [""]
+
+        [block_id_layout]
+        sequence_bits = 19
+        partition_bits = 22
+        "#;
+        let config: Config = toml::from_str(toml_str).unwrap();
+        assert_eq!(22, config.block_id_layout.partition_bits);
+        assert_eq!(21, config.block_id_layout.task_bits);
+        let err = config.validate().unwrap_err().to_string();
+        assert!(err.contains("block_id_layout"), "{}", err);
+
+        let mut config = config;
+        config.block_id_layout.sequence_bits = 20;
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn bind_address_test() {
+        let mut config = Config::create_simple_config();
+        assert_eq!("0.0.0.0", config.bind_address().unwrap().to_string());
+
+        config.bind_address = Some("10.0.0.12".to_string());
+        assert!(config.validate().is_ok());
+        assert_eq!("10.0.0.12", config.bind_address().unwrap().to_string());
+
+        config.bind_address = Some("::1".to_string());
+        assert!(config.validate().is_ok());
+
+        for illegal in ["10.0.0.256", "localhost", "10.0.0.12:19999", ""] {
+            config.bind_address = Some(illegal.to_string());
+            let err = config.validate().unwrap_err().to_string();
+            assert!(err.contains("bind_address"), "{}", err);
+        }
+    }
+
+    #[test]
+    fn per_app_spill_concurrency_validate_test() {
+        let mut config = Config::create_simple_config();
+        config.hybrid_store.memory_spill_max_concurrency = 10;
+        assert!(config.validate().is_ok());
+
+        for (concurrency, valid) in [(0, false), (-1, false), (1, true), (10, true), (11, false)] {
+            config.app_config.per_app_spill_concurrency = Some(concurrency);
+            assert_eq!(valid, config.validate().is_ok(), "{}", concurrency);
+        }
+    }
+
+    #[test]
+    fn check_mounts_test() -> anyhow::Result<()> {
+        let temp_dir = tempdir::TempDir::new("check_mounts_test")?;
+        let healthy = temp_dir.path().join("healthy");
+        let readonly = temp_dir.path().join("readonly");
+        let file = temp_dir.path().join("file");
+        let missing = temp_dir.path().join("missing");
+        fs::create_dir(&healthy)?;
+        fs::create_dir(&readonly)?;
+        fs::write(&file, b"")?;
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&readonly, fs::Permissions::from_mode(0o555))?;
+        }
+        // the permission is bypassed by the root user
+        let readonly_enforced = fs::write(readonly.join("probe"), b"").is_err();
+
+        let paths: Vec<String> = [&healthy, &readonly, &file, &missing]
+            .iter()
+            .map(|x| x.to_str().unwrap().to_string())
+            .collect();
+        let conf = LocalfileStoreConfig::new(paths.clone());
+        let health = conf.check_mounts();
+        assert_eq!(4, health.len());
+        assert_eq!(paths[0], health[0].path);
+
+        assert!(health[0].is_healthy());
+        assert!(health[0].error.is_none());
+        assert!(!healthy.join(PathHealth::PROBE_FILE_NAME).exists());
+        #[cfg(unix)]
+        assert!(health[0].distinct_mount.is_some());
+
+        if readonly_enforced {
+            assert!(health[1].exists && health[1].is_dir);
+            assert!(!health[1].writable);
+            assert!(!health[1].is_healthy());
+            assert!(health[1].error.is_some());
+        }
+
+        assert!(health[2].exists);
+        assert!(!health[2].is_dir);
+        assert!(!health[2].is_healthy());
+
+        assert!(!health[3].exists);
+        assert!(!health[3].is_healthy());
+        assert!(health[3].error.is_some());
+
+        Ok(())
+    }
+
+    #[test]
+    fn fsync_policy_test() {
+        let conf = LocalfileStoreConfig::new(vec!["/data1".to_string()]);
+        assert_eq!(FsyncPolicy::Never, conf.fsync_policy());
+
+        for (raw, policy) in [
+            ("Never", FsyncPolicy::Never),
+            ("OnClose", FsyncPolicy::OnClose),
+            ("Always", FsyncPolicy::Always),
+        ] {
+            let toml_str = format!(
+                r#"
+                data_paths = ["/data1"]
+                fsync_policy = "{}"
+                "#,
+                raw
+            );
+            let conf: LocalfileStoreConfig = toml::from_str(&toml_str).unwrap();
+            assert_eq!(policy, conf.fsync_policy());
+        }
+
+        let toml_str = r#"
+        data_paths = ["/data1"]
+        fsync_policy = "Sometimes"
+        "#;
+        assert!(toml::from_str::<LocalfileStoreConfig>(toml_str).is_err());
+    }
+
+    #[test]
+    fn event_bus_config_test() {
+        let toml_str = r#"
+        store_type = "MEMORY_LOCALFILE"
+        coordinator_quorum = [""]
+        grpc_port = 19999
+        [memory_store]
+        capacity = "1G"
+        [localfile_store]
+        data_paths = ["/data1"]
+        [event_bus.spill]
+        queue_capacity = "10000"
+        overflow = "block"
+        [event_bus.default]
+        queue_capacity = "100"
+        overflow = "reject"
+        "#;
+        let config: Config = toml::from_str(toml_str).unwrap();
+        assert!(config.validate().is_ok());
+
+        let options = EventBusOptions::from(&config.event_bus_config("spill")).unwrap();
+        assert_eq!(Some(10000), options.queue_capacity);
+        assert_eq!(QueueOverflow::Block, options.overflow);
+
+        // fallback to the default for the unlisted bus
+        let options = EventBusOptions::from(&config.event_bus_config("purge")).unwrap();
+        assert_eq!(Some(100), options.queue_capacity);
+        assert_eq!(QueueOverflow::Reject, options.overflow);
+
+        // unbounded and blocking without any config
+        let config = Config::create_simple_config();
+        let options = EventBusOptions::from(&config.event_bus_config("spill")).unwrap();
+        assert_eq!(None, options.queue_capacity);
+        assert_eq!(QueueOverflow::Block, options.overflow);
+
+        // illegal overflow policy
+        let mut config = Config::create_simple_config();
+        config.event_bus.insert(
+            "spill".to_string(),
+            EventBusConfig {
+                queue_capacity: None,
+                overflow: Some("drop".to_string()),
+            },
+        );
+        assert!(config.validate().is_err());
+
+        // illegal capacity
+        config.event_bus.insert(
+            "spill".to_string(),
+            EventBusConfig {
+                queue_capacity: Some("0".to_string()),
+                overflow: None,
+            },
+        );
+        assert!(config.validate().is_err());
+
+        // the spill events must not be rejected, even inherited from the default
+        config.event_bus.clear();
+        config.event_bus.insert(
+            "default".to_string(),
+            EventBusConfig {
+                queue_capacity: Some("10".to_string()),
+                overflow: Some("reject".to_string()),
+            },
+        );
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn busy_score_config_test() {
+        let config = Config::create_simple_config();
+        assert_eq!(BusyScoreConfig::default(), config.busy_score);
+
+        let toml_str = r#"
+        store_type = "MEMORY"
+        coordinator_quorum = [""]
+        grpc_port = 19999
+        [memory_store]
+        capacity = "1G"
+        [busy_score]
+        memory_weight = 1.0
+        disk_weight = 0.0
+        "#;
+        let mut config: Config = toml::from_str(toml_str).unwrap();
+        assert_eq!(1.0, config.busy_score.memory_weight);
+        assert_eq!(0.25, config.busy_score.event_bus_weight);
+        assert_eq!(0.0, config.busy_score.disk_weight);
+        assert!(config.validate().is_ok());
+
+        config.busy_score.memory_weight = -1.0;
+        assert!(config.validate().is_err());
+
+        config.busy_score.memory_weight = 0.0;
+        config.busy_score.event_bus_weight = 0.0;
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn app_quota_config_test() {
+        let toml_str = r#"
+        store_type = "MEMORY"
+        coordinator_quorum = [""]
+        grpc_port = 19999
+        [memory_store]
+        capacity = "1G"
+        [app_config.quota]
+        max_total_bytes = "10G"
+        max_memory_bytes = "1G"
+        [app_config.quota_overrides.application_]
+        max_total_bytes = "100G"
+        [app_config.quota_overrides.application_vip_]
+        max_total_bytes = "1T"
+        "#;
+        let mut config: Config = toml::from_str(toml_str).unwrap();
+        assert!(config.validate().is_ok());
+        assert!(config.app_config.has_memory_quota());
+
+        // the unset field of the override falls back to the default
+        let quota = config.app_config.quota_of("application_1");
+        assert_eq!(
+            Some(100 * 1024 * 1024 * 1024),
+            quota.max_total_bytes().unwrap()
+        );
+        assert_eq!(Some(1024 * 1024 * 1024), quota.max_memory_bytes().unwrap());
+
+        // the longest prefix wins
+        let quota = config.app_config.quota_of("application_vip_1");
+        assert_eq!(Some("1T".to_string()), quota.max_total_bytes);
+        let quota = config.app_config.quota_of("spark_1");
+        assert_eq!(Some("10G".to_string()), quota.max_total_bytes);
+
+        config.app_config.quota_overrides.insert(
+            "app".to_string(),
+            AppQuotaConfig {
+                max_total_bytes: Some("10X".to_string()),
+                max_memory_bytes: None,
+            },
+        );
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn read_engine_test() {
+        let conf = LocalfileStoreConfig::new(vec!["/data1".to_string()]);
+        assert_eq!(ReadEngine::Pread, conf.read_engine());
+
+        let toml_str = r#"
+        data_paths = ["/data1"]
+        read_engine = "mmap"
+        mmap_cache_capacity = 10
+        "#;
+        let conf: LocalfileStoreConfig = toml::from_str(toml_str).unwrap();
+        assert_eq!(ReadEngine::Mmap, conf.read_engine());
+        assert_eq!(10, conf.mmap_cache_capacity);
 
-        [localfile_store]
-        data_paths = [{:?}]
-        "#,
-            grpc_port, capacity, local_data_path
-        );
+        let toml_str = r#"
+        data_paths = ["/data1"]
+        read_engine = "sendfile"
+        "#;
+        assert!(toml::from_str::<LocalfileStoreConfig>(toml_str).is_err());
+    }
 
-        toml::from_str(toml_str.as_str()).unwrap()
+    #[test]
+    fn localfile_read_timeout_test() {
+        let mut conf = LocalfileStoreConfig::new(vec!["/data1".to_string()]);
+        assert_eq!(Duration::from_secs(30), conf.read_timeout().unwrap());
+        assert!(conf.validate().is_ok());
+
+        conf.read_timeout = "500ms".to_string();
+        assert_eq!(Duration::from_millis(500), conf.read_timeout().unwrap());
+
+        conf.read_timeout = "0s".to_string();
+        assert!(conf.validate().is_err());
+        conf.read_timeout = "30".to_string();
+        assert!(conf.validate().is_err());
     }
 
-    pub fn create_simple_config() -> Config {
+    #[test]
+    fn localfile_read_write_paths_test() {
+        // fallback to the data paths
+        let conf = LocalfileStoreConfig::new(vec!["/data1".to_string(), "/data2".to_string()]);
+        assert_eq!(vec!["/data1", "/data2"], conf.effective_read_paths());
+        assert_eq!(vec!["/data1", "/data2"], conf.effective_write_paths());
+        assert_eq!(vec!["/data1", "/data2"], conf.all_paths());
+
+        // explicit read/write split
         let toml_str = r#"
-        store_type = "MEMORY"
+        store_type = "MEMORY_LOCALFILE"
         coordinator_quorum = [""]
         grpc_port = 19999
-
         [memory_store]
-        capacity = "1M"
-
-        [hybrid_store]
-        memory_spill_high_watermark = 0.8
-        memory_spill_low_watermark = 0.2
-        memory_single_buffer_max_spill_size = "256M"
+        capacity = "1G"
+        [localfile_store]
+        data_paths = ["/data1"]
+        read_paths = ["/ssd1", "/data1"]
+        write_paths = ["/data2"]
         "#;
-
-        toml::from_str(toml_str).unwrap()
+        let config: Config = toml::from_str(toml_str).unwrap();
+        assert!(config.validate().is_ok());
+        let conf = config.localfile_store.unwrap();
+        assert_eq!(vec!["/ssd1", "/data1"], conf.effective_read_paths());
+        assert_eq!(vec!["/data2"], conf.effective_write_paths());
+        assert_eq!(vec!["/data2", "/ssd1", "/data1"], conf.all_paths());
+
+        // the data paths could be omitted when both are explicit
+        let mut conf = LocalfileStoreConfig::new(vec![]);
+        conf.read_paths = Some(vec!["/data1".to_string()]);
+        conf.write_paths = Some(vec!["/data2".to_string()]);
+        assert!(conf.validate().is_ok());
+
+        // no effective path for writing
+        let mut conf = LocalfileStoreConfig::new(vec![]);
+        conf.read_paths = Some(vec!["/data1".to_string()]);
+        assert!(conf.validate().is_err());
+
+        let mut conf = LocalfileStoreConfig::new(vec!["/data1".to_string()]);
+        conf.read_paths = Some(vec![]);
+        assert!(conf.validate().is_err());
     }
-}
 
-#[cfg(test)]
-mod test {
-    use crate::config::{as_default_app_heartbeat_timeout_min, Config, RuntimeConfig, StorageType};
-    use crate::readable_size::ReadableSize;
-    use std::str::FromStr;
+    #[test]
+    fn localfile_compaction_test() {
+        let toml_str = r#"
+        store_type = "MEMORY_LOCALFILE"
+        grpc_port = 19999
+        [memory_store]
+        capacity = "1G"
+        [localfile_store]
+        data_paths = ["/data1"]
+        compaction = { segment_threshold = 50, idle_duration = "30m" }
+        "#;
+        let config: Config = toml::from_str(toml_str).unwrap();
+        assert!(config.validate().is_ok());
+        let compaction = config.localfile_store.unwrap().compaction.unwrap();
+        assert_eq!(50, compaction.segment_threshold);
+        assert_eq!(
+            Duration::from_secs(30 * 60),
+            compaction.idle_duration().unwrap()
+        );
+        assert_eq!(Duration::from_secs(60), compaction.interval().unwrap());
+        assert_eq!(64 * 1024 * 1024, compaction.io_rate_limit().unwrap());
+
+        let mut conf = LocalfileStoreConfig::new(vec!["/data1".to_string()]);
+        conf.compaction = Some(LocalfileCompactionConfig {
+            io_rate_limit: "0".to_string(),
+            ..Default::default()
+        });
+        assert!(conf.validate().is_err());
+    }
 
     #[test]
     fn storage_type_test() {
@@ -451,6 +2902,20 @@ mod test {
         assert_eq!(true, StorageType::contains_hdfs(&stype));
     }
 
+    #[test]
+    fn storage_type_all_test() {
+        assert_eq!(6, StorageType::all().len());
+        assert_eq!(6, StorageType::variants().count());
+        for stype in StorageType::variants() {
+            assert_eq!(stype, StorageType::from_str(&stype.to_string()).unwrap());
+        }
+        assert_eq!(
+            StorageType::MEMORY_LOCALFILE,
+            StorageType::from_str("memory_localfile").unwrap()
+        );
+        assert!(StorageType::from_str("LOCALFILE_MEMORY").is_err());
+    }
+
     #[test]
     fn config_create() {
         let config =
@@ -479,8 +2944,10 @@ mod test {
         let decoded: Config = toml::from_str(toml_str).unwrap();
         println!("{:#?}", decoded);
 
-        let capacity = ReadableSize::from_str(&decoded.memory_store.unwrap().capacity).unwrap();
+        let memory_store = decoded.memory_store.clone().unwrap();
+        let capacity = ReadableSize::parse(&memory_store.capacity).unwrap();
         assert_eq!(1024 * 1024 * 1024, capacity.as_bytes());
+        assert_eq!(None, memory_store.ticket_fairness);
 
         assert_eq!(
             decoded.runtime_config.read_thread_num,
@@ -492,5 +2959,476 @@ mod test {
             decoded.app_config.app_heartbeat_timeout_min,
             as_default_app_heartbeat_timeout_min(),
         );
+
+        // check the coordinator config
+        assert_eq!(
+            Duration::from_secs(10),
+            decoded.coordinator.connect_timeout().unwrap()
+        );
+        assert_eq!(
+            Duration::from_secs(1),
+            decoded.coordinator.retry_interval().unwrap()
+        );
+
+        // check the server config
+        assert_eq!(
+            Duration::from_secs(2),
+            decoded.server.slow_request_threshold().unwrap()
+        );
+        assert_eq!(
+            Duration::from_secs(300),
+            decoded.server.stuck_task_threshold().unwrap()
+        );
+        assert!(!decoded.server.fault_injection_http_enabled);
+
+        let mut config = Config::create_simple_config();
+        config.server.slow_request_threshold = "2 seconds later".to_string();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn runtime_config_for_profile_test() {
+        let conf = RuntimeConfig::for_profile_with_cpus(WorkloadProfile::ReadHeavy, 16);
+        assert_eq!(64, conf.read_thread_num);
+        assert_eq!(16, conf.write_thread_num);
+        assert_eq!(2, conf.http_thread_num);
+        assert_eq!(4, conf.default_thread_num);
+        assert_eq!(16, conf.dispatch_thread_num);
+        assert_eq!(16, conf.flush_thread_num);
+
+        let conf = RuntimeConfig::for_profile_with_cpus(WorkloadProfile::WriteHeavy, 16);
+        assert_eq!(16, conf.read_thread_num);
+        assert_eq!(64, conf.write_thread_num);
+        assert_eq!(2, conf.http_thread_num);
+        assert_eq!(4, conf.default_thread_num);
+        assert_eq!(64, conf.dispatch_thread_num);
+        assert_eq!(32, conf.flush_thread_num);
+
+        let conf = RuntimeConfig::for_profile_with_cpus(WorkloadProfile::Balanced, 16);
+        assert_eq!(32, conf.read_thread_num);
+        assert_eq!(32, conf.write_thread_num);
+        assert_eq!(2, conf.http_thread_num);
+        assert_eq!(4, conf.default_thread_num);
+        assert_eq!(32, conf.dispatch_thread_num);
+        assert_eq!(16, conf.flush_thread_num);
+
+        // the zero cpu will be treated as one core
+        let conf = RuntimeConfig::for_profile_with_cpus(WorkloadProfile::Balanced, 0);
+        assert_eq!(2, conf.read_thread_num);
+        assert_eq!(2, conf.default_thread_num);
+    }
+
+    #[test]
+    fn runtime_config_pool_type_test() {
+        let conf = RuntimeConfig::default();
+        assert_eq!(PoolType::MultiThread, conf.pool_type(1));
+
+        let toml_str = r#"
+        http_thread_num = 1
+        default_thread_num = 4
+        single_thread_pool_type = "current_thread"
+        "#;
+        let conf: RuntimeConfig = toml::from_str(toml_str).unwrap();
+        assert_eq!(
+            PoolType::CurrentThread,
+            conf.pool_type(conf.http_thread_num)
+        );
+        assert_eq!(
+            PoolType::MultiThread,
+            conf.pool_type(conf.default_thread_num)
+        );
+    }
+
+    #[test]
+    fn runtime_config_cpuset_test() -> anyhow::Result<()> {
+        assert_eq!(vec![0], parse_cpuset("test", "0")?);
+        assert_eq!(vec![0], parse_cpuset("test", "0-0,0")?);
+        if num_cpus::get() >= 4 {
+            assert_eq!(vec![0, 1, 2, 3], parse_cpuset("test", "2-3, 0,1")?);
+        }
+
+        for illegal in ["", "a", "0-", "-1", "1-0", "0,,1", "0-1-2", "100000"] {
+            assert!(
+                parse_cpuset("test", illegal).is_err(),
+                "cpuset: [{}]",
+                illegal
+            );
+        }
+
+        let mut config = Config::create_simple_config();
+        config.runtime_config.read_thread_cpuset = Some("0".to_string());
+        assert!(config.validate().is_ok());
+        config.runtime_config.write_thread_cpuset = Some("3-1".to_string());
+        let err = config.validate().unwrap_err().to_string();
+        assert!(err.contains("runtime_config.write_thread_cpuset"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn spill_debt_admission_test() {
+        let toml_str = r#"
+        store_type = "MEMORY"
+        coordinator_quorum = ["xxxxxxx"]
+
+        [hybrid_store]
+        spill_debt_admission = {}
+        "#;
+        let decoded: Config = toml::from_str(toml_str).unwrap();
+        assert!(decoded.validate().is_ok());
+        let admission = decoded.hybrid_store.spill_debt_admission.unwrap();
+        assert_eq!(0.9, admission.projected_watermark);
+        assert_eq!(Duration::from_secs(1), admission.max_delay().unwrap());
+
+        // the projected watermark under the spill high watermark is rejected
+        let toml_str = r#"
+        store_type = "MEMORY"
+        coordinator_quorum = ["xxxxxxx"]
+
+        [hybrid_store]
+        memory_spill_high_watermark = 0.8
+        spill_debt_admission = { projected_watermark = 0.7, max_delay = "0s" }
+        "#;
+        let decoded: Config = toml::from_str(toml_str).unwrap();
+        assert!(decoded.validate().is_err());
+    }
+
+    #[test]
+    fn startup_grace_test() {
+        let toml_str = r#"
+        store_type = "MEMORY"
+        coordinator_quorum = ["xxxxxxx"]
+        "#;
+        let decoded: Config = toml::from_str(toml_str).unwrap();
+        assert_eq!(None, decoded.app_config.startup_grace_min);
+        assert!(decoded.app_config.startup_grace().is_zero());
+
+        let toml_str = r#"
+        store_type = "MEMORY"
+        coordinator_quorum = ["xxxxxxx"]
+
+        [app_config]
+        startup_grace_min = 3
+        "#;
+        let decoded: Config = toml::from_str(toml_str).unwrap();
+        assert!(decoded.validate().is_ok());
+        assert_eq!(Duration::from_secs(180), decoded.app_config.startup_grace());
+
+        // zero means disabled
+        let toml_str = r#"
+        store_type = "MEMORY"
+        coordinator_quorum = ["xxxxxxx"]
+
+        [app_config]
+        startup_grace_min = 0
+        "#;
+        let decoded: Config = toml::from_str(toml_str).unwrap();
+        assert!(decoded.validate().is_ok());
+        assert!(decoded.app_config.startup_grace().is_zero());
+    }
+
+    #[test]
+    fn from_str_test() {
+        let toml_str = r#"
+        store_type = "MEMORY"
+        coordinator_quorum = ["xxxxxxx"]
+
+        [memory_store]
+        capacity = "1G"
+        "#;
+        let config = Config::from_str(toml_str).unwrap();
+        assert_eq!(
+            as_default_app_heartbeat_timeout_min(),
+            config.app_config.app_heartbeat_timeout_min
+        );
+        assert_eq!(BlockIdLayout::default(), config.block_id_layout);
+        assert_eq!(
+            config,
+            toml::from_str::<Config>(toml_str).unwrap(),
+            "the defaults should be same with the file loading"
+        );
+
+        let yaml_str = "store_type: MEMORY\ncoordinator_quorum: [\"xxxxxxx\"]\nmemory_store:\n  capacity: 1G\n";
+        assert_eq!(config, Config::from_yaml_str(yaml_str).unwrap());
+
+        // the illegal and invalid content are rejected
+        assert!(matches!(
+            Config::from_str("store_type = "),
+            Err(ConfigError::Parse(_))
+        ));
+        assert!(matches!(
+            Config::from_yaml_str("store_type: ["),
+            Err(ConfigError::Yaml(_))
+        ));
+        let toml_str = r#"
+        store_type = "MEMORY"
+        coordinator_quorum = ["xxxxxxx"]
+
+        [app_config]
+        app_heartbeat_timeout = "0s"
+        "#;
+        assert!(matches!(
+            Config::from_str(toml_str),
+            Err(ConfigError::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn toml_parse_error_test() {
+        let toml_str = "store_type = \"MEMORY\"\ncoordinator_quorum = [\"xxxxxxx\"]\n\n[memory_store]\ncapacity = 1024\n";
+        let error = match Config::from_str(toml_str) {
+            Err(ConfigError::Parse(error)) => error,
+            _ => panic!("the type error should be rejected on parsing"),
+        };
+        assert_eq!(Some(5), error.line());
+        assert!(error.span().is_some());
+
+        let message = error.to_string();
+        assert!(message.contains("at line 5"));
+        assert!(message.contains("4 | [memory_store]"));
+        assert!(message.contains("5 | capacity = 1024"));
+        assert!(message.contains("^"));
+
+        // the error at the end of content
+        let error = match Config::from_str("store_type = ") {
+            Err(ConfigError::Parse(error)) => error,
+            _ => panic!(),
+        };
+        assert_eq!(Some(1), error.line());
+        assert!(error.to_string().contains("1 | store_type = "));
+    }
+
+    #[test]
+    fn app_heartbeat_timeout_test() {
+        let toml_str = r#"
+        store_type = "MEMORY"
+        coordinator_quorum = ["xxxxxxx"]
+
+        [app_config]
+        app_heartbeat_timeout = "30s"
+        "#;
+        let decoded: Config = toml::from_str(toml_str).unwrap();
+        assert!(decoded.validate().is_ok());
+        assert_eq!(
+            Duration::from_secs(30),
+            decoded.app_config.app_heartbeat_timeout().unwrap()
+        );
+
+        // fallback to the legacy minutes field
+        let toml_str = r#"
+        store_type = "MEMORY"
+        coordinator_quorum = ["xxxxxxx"]
+
+        [app_config]
+        app_heartbeat_timeout_min = 2
+        "#;
+        let decoded: Config = toml::from_str(toml_str).unwrap();
+        assert!(decoded.validate().is_ok());
+        assert_eq!(
+            Duration::from_secs(120),
+            decoded.app_config.app_heartbeat_timeout().unwrap()
+        );
+
+        // zero duration is rejected
+        let toml_str = r#"
+        store_type = "MEMORY"
+        coordinator_quorum = ["xxxxxxx"]
+
+        [app_config]
+        app_heartbeat_timeout = "0s"
+        "#;
+        let decoded: Config = toml::from_str(toml_str).unwrap();
+        assert!(decoded.validate().is_err());
+    }
+
+    #[test]
+    fn max_concurrent_apps_test() {
+        let toml_str = r#"
+        store_type = "MEMORY"
+        coordinator_quorum = ["xxxxxxx"]
+        "#;
+        let decoded: Config = toml::from_str(toml_str).unwrap();
+        assert!(decoded.validate().is_ok());
+        assert_eq!(None, decoded.app_config.max_concurrent_apps());
+
+        let toml_str = r#"
+        store_type = "MEMORY"
+        coordinator_quorum = ["xxxxxxx"]
+
+        [app_config]
+        max_concurrent_apps = 100
+        max_apps_per_server = 10
+        "#;
+        let decoded: Config = toml::from_str(toml_str).unwrap();
+        assert!(decoded.validate().is_ok());
+        assert_eq!(Some(100), decoded.app_config.max_concurrent_apps());
+
+        // fallback to the legacy field
+        let mut config = decoded.clone();
+        config.app_config.max_concurrent_apps = None;
+        assert_eq!(Some(10), config.app_config.max_concurrent_apps());
+
+        // zero is rejected
+        config.app_config.max_concurrent_apps = Some(0);
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn shutdown_timeout_test() {
+        let toml_str = r#"
+        store_type = "MEMORY"
+        coordinator_quorum = ["xxxxxxx"]
+        "#;
+        let decoded: Config = toml::from_str(toml_str).unwrap();
+        assert!(decoded.validate().is_ok());
+        assert_eq!(Duration::from_secs(60), decoded.shutdown_timeout().unwrap());
+
+        let toml_str = r#"
+        store_type = "MEMORY"
+        coordinator_quorum = ["xxxxxxx"]
+        shutdown_timeout = "2m 30s"
+        "#;
+        let decoded: Config = toml::from_str(toml_str).unwrap();
+        assert!(decoded.validate().is_ok());
+        assert_eq!(
+            Duration::from_secs(150),
+            decoded.shutdown_timeout().unwrap()
+        );
+
+        let mut config = decoded.clone();
+        config.shutdown_timeout = Some("0s".to_string());
+        assert!(config.validate().is_err());
+        config.shutdown_timeout = Some("soon".to_string());
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn urpc_write_coalesce_test() {
+        let toml_str = r#"
+        store_type = "MEMORY"
+        coordinator_quorum = ["xxxxxxx"]
+        "#;
+        let decoded: Config = toml::from_str(toml_str).unwrap();
+        assert_eq!(None, decoded.urpc.write_coalesce().unwrap());
+
+        let toml_str = r#"
+        store_type = "MEMORY"
+        coordinator_quorum = ["xxxxxxx"]
+
+        [urpc]
+        write_coalesce_bytes = "16K"
+        "#;
+        let decoded: Config = toml::from_str(toml_str).unwrap();
+        assert!(decoded.validate().is_ok());
+        assert_eq!(
+            Some((16 * 1024, Duration::from_micros(200))),
+            decoded.urpc.write_coalesce().unwrap()
+        );
+
+        let mut config = decoded.clone();
+        config.urpc.write_coalesce_bytes = None;
+        config.urpc.write_coalesce_micros = Some(50);
+        assert_eq!(
+            Some((64 * 1024, Duration::from_micros(50))),
+            config.urpc.write_coalesce().unwrap()
+        );
+
+        config.urpc.write_coalesce_bytes = Some("0".to_string());
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn grpc_tls_config_test() {
+        let temp_dir = tempdir::TempDir::new("grpc_tls_config_test").unwrap();
+        let cert_path = temp_dir.path().join("server.crt");
+        let key_path = temp_dir.path().join("server.key");
+        std::fs::write(&cert_path, "cert").unwrap();
+        std::fs::write(&key_path, "key").unwrap();
+
+        let toml_str = format!(
+            r#"
+        store_type = "MEMORY"
+        coordinator_quorum = ["xxxxxxx"]
+
+        [grpc_tls]
+        cert_path = {:?}
+        key_path = {:?}
+        "#,
+            cert_path.to_str().unwrap(),
+            key_path.to_str().unwrap()
+        );
+        let decoded: Config = toml::from_str(&toml_str).unwrap();
+        let tls_config = decoded.grpc_tls.as_ref().unwrap();
+        assert!(tls_config.ca_path.is_none());
+        assert!(decoded.validate().is_ok());
+
+        // case2: the missing ca file
+        let mut config = decoded.clone();
+        config.grpc_tls.as_mut().unwrap().ca_path =
+            Some(temp_dir.path().join("ca.crt").to_str().unwrap().to_string());
+        let err = config.validate().unwrap_err();
+        assert!(err.to_string().contains("ca_path"));
+
+        // case3: the missing key file
+        let mut config = decoded.clone();
+        std::fs::remove_file(&key_path).unwrap();
+        assert!(config.validate().is_err());
+        config.grpc_tls = None;
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn secret_file_test() {
+        let temp_dir = tempdir::TempDir::new("secret_file_test").unwrap();
+        let password_path = temp_dir.path().join("pg");
+        std::fs::write(&password_path, "secret\n").unwrap();
+
+        let toml_str = format!(
+            r#"
+        store_type = "MEMORY"
+        coordinator_quorum = ["xxxxxxx"]
+
+        [metrics]
+        push_gateway_endpoint = "http://localhost:9091"
+        push_gateway_username = "riffle"
+        push_gateway_password_file = {:?}
+        "#,
+            password_path.to_str().unwrap()
+        );
+        let decoded: Config = toml::from_str(&toml_str).unwrap();
+        assert!(decoded.validate().is_ok());
+        let metrics = decoded.metrics.as_ref().unwrap();
+        assert_eq!(
+            Some("secret".to_string()),
+            metrics.push_gateway_password().unwrap()
+        );
+
+        // case2: both the inline and the file are set
+        let mut config = decoded.clone();
+        config.metrics.as_mut().unwrap().push_gateway_password = Some("inline".to_string());
+        let err = config.validate().unwrap_err();
+        assert!(err.to_string().contains("mutually exclusive"));
+
+        // case3: the inline only
+        config.metrics.as_mut().unwrap().push_gateway_password_file = None;
+        assert_eq!(
+            Some("inline".to_string()),
+            config
+                .metrics
+                .as_ref()
+                .unwrap()
+                .push_gateway_password()
+                .unwrap()
+        );
+        let printed = format!("{:#?}", &config);
+        assert!(!printed.contains("inline"));
+        assert!(printed.contains(REDACTED_SECRET));
+
+        // case4: the unreadable file
+        let mut config = decoded.clone();
+        std::fs::remove_file(&password_path).unwrap();
+        assert!(config.validate().is_err());
+        config.metrics.as_mut().unwrap().push_gateway_password_file = None;
+        assert!(config.validate().is_ok());
     }
 }
@@ -1,4 +1,5 @@
 #[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum StatusCode {
     SUCCESS = 0,
     DOUBLE_REGISTER = 1,
@@ -9,6 +10,15 @@ pub enum StatusCode {
     INTERNAL_ERROR = 6,
     TIMEOUT = 7,
     NO_BUFFER_FOR_HUGE_PARTITION = 8,
+    INVALID_REQUEST = 9,
+    // the server reaches the app or partition number limit, the client should pick another server
+    SERVER_LIMIT_EXCEEDED = 11,
+    // the blocks are corrupted in the transport, the client should resend them
+    CHECKSUM_MISMATCHED = 12,
+    // the app has written beyond its quota, it should not be retried until the shuffles purged
+    QUOTA_EXCEEDED = 13,
+    // the partition has been split, the blocks beyond the split point should be sent to the others
+    REASSIGNED = 14,
 }
 
 impl Into<i32> for StatusCode {
@@ -0,0 +1,341 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::app::AppManagerRef;
+use crate::grpc::protobuf::uniffle::ServerStatus;
+use crate::util::now_timestamp_as_sec;
+use anyhow::{anyhow, Result};
+use log::{info, warn};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// The decommission stages are walked through in order:
+/// 1. STOP_NEW_APPS: reject the new apps registry, keep serving the existing apps
+/// 2. DRAIN: flush all the memory data via the spill path, keep serving reads
+/// 3. TERMINATE_WHEN_IDLE: exit once no registered apps remain or the deadline passes
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DecommissionState {
+    NONE,
+    STOP_NEW_APPS,
+    DRAIN,
+    TERMINATE_WHEN_IDLE,
+    TERMINATED,
+}
+
+impl DecommissionState {
+    pub fn is_accepting_new_apps(&self) -> bool {
+        *self == DecommissionState::NONE
+    }
+
+    pub fn server_status(&self) -> ServerStatus {
+        match self {
+            DecommissionState::NONE => ServerStatus::Active,
+            DecommissionState::TERMINATED => ServerStatus::Decommissioned,
+            _ => ServerStatus::Decommissioning,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DecommissionProgress {
+    pub state: DecommissionState,
+    pub apps_remaining: usize,
+    pub memory_bytes_remaining: i64,
+    pub deadline_timestamp: Option<u64>,
+}
+
+#[derive(Clone)]
+pub struct DecommissionManager {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    app_manager_ref: AppManagerRef,
+    check_interval: Duration,
+    on_terminate: Box<dyn Fn() + Send + Sync>,
+    // the transitions are guarded by this lock, the generation will be
+    // increased on every start and cancel to stop the stale driving task.
+    generation: Mutex<u64>,
+    deadline: Mutex<Option<u64>>,
+}
+
+impl DecommissionManager {
+    /// Once terminated, the SIGTERM will be raised to trigger the graceful shutdown.
+    pub fn new(app_manager_ref: AppManagerRef) -> Self {
+        Self::with_options(app_manager_ref, Duration::from_secs(10), || {
+            #[cfg(unix)]
+            if let Err(e) = signal_hook::low_level::raise(signal_hook::consts::SIGTERM) {
+                warn!("Errors on raising the terminate signal. err: {:?}", e);
+            }
+        })
+    }
+
+    pub fn with_options(
+        app_manager_ref: AppManagerRef,
+        check_interval: Duration,
+        on_terminate: impl Fn() + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                app_manager_ref,
+                check_interval,
+                on_terminate: Box::new(on_terminate),
+                generation: Mutex::new(0),
+                deadline: Mutex::new(None),
+            }),
+        }
+    }
+
+    pub fn state(&self) -> DecommissionState {
+        self.inner.app_manager_ref.decommission_state()
+    }
+
+    pub fn start(&self, timeout: Option<Duration>) -> Result<()> {
+        let generation = {
+            let mut generation = self.inner.generation.lock();
+            let state = self.state();
+            if state != DecommissionState::NONE {
+                return Err(anyhow!(
+                    "The decommission is in progress. state: {:?}",
+                    state
+                ));
+            }
+            *generation += 1;
+            *self.inner.deadline.lock() = timeout.map(|x| now_timestamp_as_sec() + x.as_secs());
+            self.inner
+                .app_manager_ref
+                .set_decommission_state(DecommissionState::STOP_NEW_APPS);
+            *generation
+        };
+        info!("Starting decommission with the timeout: {:?}", timeout);
+
+        let manager = self.clone();
+        self.inner
+            .app_manager_ref
+            .runtime_manager()
+            .default_runtime
+            .spawn_guarded(
+                "decommission",
+                async move { manager.drive(generation).await },
+            );
+        Ok(())
+    }
+
+    pub fn cancel(&self) -> Result<()> {
+        let mut generation = self.inner.generation.lock();
+        let state = self.state();
+        if state == DecommissionState::NONE || state == DecommissionState::TERMINATED {
+            return Err(anyhow!(
+                "The decommission can't be cancelled. state: {:?}",
+                state
+            ));
+        }
+        *generation += 1;
+        *self.inner.deadline.lock() = None;
+        self.inner
+            .app_manager_ref
+            .set_decommission_state(DecommissionState::NONE);
+        info!(
+            "The decommission has been cancelled from the state: {:?}",
+            state
+        );
+        Ok(())
+    }
+
+    pub async fn progress(&self) -> DecommissionProgress {
+        let app_manager = &self.inner.app_manager_ref;
+        let memory_bytes_remaining = app_manager
+            .store_memory_snapshot()
+            .await
+            .map(|x| x.used())
+            .unwrap_or(0);
+        DecommissionProgress {
+            state: self.state(),
+            apps_remaining: app_manager.app_number(),
+            memory_bytes_remaining,
+            deadline_timestamp: *self.inner.deadline.lock(),
+        }
+    }
+
+    fn transit(&self, generation: u64, state: DecommissionState) -> bool {
+        let current = self.inner.generation.lock();
+        if *current != generation {
+            return false;
+        }
+        info!("The decommission goes into the state: {:?}", state);
+        self.inner.app_manager_ref.set_decommission_state(state);
+        true
+    }
+
+    fn is_cancelled(&self, generation: u64) -> bool {
+        *self.inner.generation.lock() != generation
+    }
+
+    fn is_deadline_passed(&self) -> bool {
+        match *self.inner.deadline.lock() {
+            Some(deadline) => now_timestamp_as_sec() >= deadline,
+            _ => false,
+        }
+    }
+
+    async fn drive(&self, generation: u64) {
+        if !self.transit(generation, DecommissionState::DRAIN) {
+            return;
+        }
+        loop {
+            if let Err(e) = self.inner.app_manager_ref.store_spill_all().await {
+                warn!("Errors on draining memory for decommission. err: {:?}", e);
+            }
+            let progress = self.progress().await;
+            if progress.memory_bytes_remaining <= 0
+                || progress.apps_remaining == 0
+                || self.is_deadline_passed()
+            {
+                break;
+            }
+            tokio::time::sleep(self.inner.check_interval).await;
+            if self.is_cancelled(generation) {
+                return;
+            }
+        }
+
+        if !self.transit(generation, DecommissionState::TERMINATE_WHEN_IDLE) {
+            return;
+        }
+        loop {
+            if self.inner.app_manager_ref.app_number() == 0 || self.is_deadline_passed() {
+                break;
+            }
+            tokio::time::sleep(self.inner.check_interval).await;
+            if self.is_cancelled(generation) {
+                return;
+            }
+        }
+
+        if !self.transit(generation, DecommissionState::TERMINATED) {
+            return;
+        }
+        (self.inner.on_terminate)();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::app::{AppManager, AppManagerRef, PartitionedUId, WritingViewContext};
+    use crate::config::{
+        Config, HybridStoreConfig, LocalfileStoreConfig, MemoryStoreConfig, StorageType,
+    };
+    use crate::decommission::{DecommissionManager, DecommissionState};
+    use crate::runtime::manager::RuntimeManager;
+    use crate::store::Block;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    fn create_app_manager(runtime_manager: RuntimeManager) -> AppManagerRef {
+        let temp_dir = tempdir::TempDir::new("test_decommission").unwrap();
+        let temp_path = temp_dir.into_path().to_str().unwrap().to_string();
+
+        let mut config = Config::default();
+        config.memory_store = Some(MemoryStoreConfig::new((1024 * 1024).to_string()));
+        config.localfile_store = Some(LocalfileStoreConfig::new(vec![temp_path]));
+        config.hybrid_store = HybridStoreConfig::default();
+        config.store_type = StorageType::MEMORY_LOCALFILE;
+        AppManager::get_ref(runtime_manager, config)
+    }
+
+    #[test]
+    fn test_decommission_stages() -> anyhow::Result<()> {
+        let runtime_manager: RuntimeManager = Default::default();
+        let app_manager_ref = create_app_manager(runtime_manager.clone());
+
+        app_manager_ref.register("app_1".to_string(), 1, Default::default())?;
+        let app = app_manager_ref.get_app("app_1").unwrap();
+        let block = Block {
+            block_id: 0,
+            length: 10,
+            uncompress_length: 20,
+            crc: 0,
+            data: bytes::Bytes::copy_from_slice(&[0; 10]),
+            task_attempt_id: 0,
+        };
+        let uid = PartitionedUId::from("app_1".to_string(), 1, 0);
+        runtime_manager.wait(app.insert(WritingViewContext::from(uid, vec![block])))?;
+
+        let terminated = Arc::new(AtomicBool::new(false));
+        let terminated_cloned = terminated.clone();
+        let manager = DecommissionManager::with_options(
+            app_manager_ref.clone(),
+            Duration::from_millis(50),
+            move || terminated_cloned.store(true, Ordering::SeqCst),
+        );
+        manager.start(None)?;
+        assert!(manager.start(None).is_err());
+
+        // the new apps are rejected, but the existing apps are still served
+        assert!(app_manager_ref
+            .register("app_2".to_string(), 1, Default::default())
+            .is_err());
+        app_manager_ref.register("app_1".to_string(), 2, Default::default())?;
+
+        // the memory data will be drained, and then wait for the apps finished
+        awaitility::at_most(Duration::from_secs(5))
+            .until(|| manager.state() == DecommissionState::TERMINATE_WHEN_IDLE);
+        let progress = runtime_manager.wait(manager.progress());
+        assert_eq!(0, progress.memory_bytes_remaining);
+        assert_eq!(1, progress.apps_remaining);
+        assert_eq!(false, terminated.load(Ordering::SeqCst));
+
+        runtime_manager.wait(app_manager_ref.unregister_app("app_1".to_string()))?;
+        awaitility::at_most(Duration::from_secs(5)).until(|| terminated.load(Ordering::SeqCst));
+        assert_eq!(DecommissionState::TERMINATED, manager.state());
+        assert!(manager.cancel().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_decommission_cancel() -> anyhow::Result<()> {
+        let runtime_manager: RuntimeManager = Default::default();
+        let app_manager_ref = create_app_manager(runtime_manager.clone());
+        app_manager_ref.register("app_1".to_string(), 1, Default::default())?;
+
+        let terminated = Arc::new(AtomicBool::new(false));
+        let terminated_cloned = terminated.clone();
+        let manager = DecommissionManager::with_options(
+            app_manager_ref.clone(),
+            Duration::from_millis(50),
+            move || terminated_cloned.store(true, Ordering::SeqCst),
+        );
+        assert!(manager.cancel().is_err());
+
+        manager.start(None)?;
+        manager.cancel()?;
+        assert_eq!(DecommissionState::NONE, manager.state());
+        app_manager_ref.register("app_2".to_string(), 1, Default::default())?;
+
+        // the deadline makes it terminated even if apps remain
+        manager.start(Some(Duration::from_secs(0)))?;
+        awaitility::at_most(Duration::from_secs(5)).until(|| terminated.load(Ordering::SeqCst));
+        assert_eq!(2, app_manager_ref.app_number());
+
+        Ok(())
+    }
+}
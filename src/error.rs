@@ -17,11 +17,16 @@
 
 use anyhow::Error;
 use std::string::FromUtf8Error;
+use std::time::Duration;
 
+use crate::block_id::BlockIdLayout;
+use crate::constant::StatusCode;
+use crate::metric::TOTAL_WORKER_ERROR;
 use log::error;
 use poem::error::ParseQueryError;
 use thiserror::Error;
 use tokio::sync::AcquireError;
+use tonic::{Code, Status};
 
 #[derive(Error, Debug)]
 #[allow(non_camel_case_types)]
@@ -47,6 +52,17 @@ pub enum WorkerError {
     #[error("The memory usage is limited by huge partition mechanism")]
     MEMORY_USAGE_LIMITED_BY_HUGE_PARTITION,
 
+    #[error(
+        "The partition buffer exceeds the max size, retry after {0}ms until its spill finished"
+    )]
+    MEMORY_USAGE_LIMITED_BY_PARTITION_BUFFER(u64),
+
+    #[error("The app number exceeds the limit: {0} of this server")]
+    APP_NUMBER_EXCEEDED(usize),
+
+    #[error("The partition number exceeds the limit: {0} of this server")]
+    PARTITION_NUMBER_EXCEEDED(usize),
+
     #[error(transparent)]
     Other(#[from] anyhow::Error),
 
@@ -76,6 +92,146 @@ pub enum WorkerError {
 
     #[error("urpc stream message type not found")]
     STREAM_MESSAGE_TYPE_NOT_FOUND,
+
+    #[error("Injected fault: {0}")]
+    INJECTED_FAULT(String),
+
+    #[error("The operation: {0} is not supported by the store: {1}")]
+    UNSUPPORTED_STORE_OPERATION(&'static str, &'static str),
+
+    #[error("The write sequence: {0} is not flushed within the timeout, flushed: {1}")]
+    FLUSH_BARRIER_TIMEOUT(u64, u64),
+
+    #[error("The block id layout: {0} of client mismatches the layout: {1} of server")]
+    BLOCK_ID_LAYOUT_MISMATCHED(BlockIdLayout, BlockIdLayout),
+
+    #[error("The block id: {0} doesn't belong to the partition: {1}")]
+    ILLEGAL_BLOCK_ID(i64, i32),
+
+    #[error("The checksum of the blocks: {0:?} is mismatched with the data")]
+    BLOCK_CHECKSUM_MISMATCHED(Vec<i64>),
+
+    #[error("The written bytes of the app exceed its quota: {0}")]
+    APP_QUOTA_EXCEEDED(u64),
+
+    #[error("Reading from the local disk:[{0}] timed out after {1:?}")]
+    DISK_TIMEOUT(String, Duration),
+
+    #[error(
+        "The partition has been reassigned, only the blocks up to the sequence: {0} are accepted"
+    )]
+    PARTITION_REASSIGNED(i64),
+}
+
+impl WorkerError {
+    pub fn name(&self) -> &'static str {
+        match self {
+            WorkerError::NO_AVAILABLE_LOCAL_DISK => "NO_AVAILABLE_LOCAL_DISK",
+            WorkerError::INTERNAL_ERROR => "INTERNAL_ERROR",
+            WorkerError::PARTIAL_DATA_LOST(_) => "PARTIAL_DATA_LOST",
+            WorkerError::LOCAL_DISK_UNHEALTHY(_) => "LOCAL_DISK_UNHEALTHY",
+            WorkerError::LOCAL_DISK_OWNED_BY_PARTITION_CORRUPTED(_) => {
+                "LOCAL_DISK_OWNED_BY_PARTITION_CORRUPTED"
+            }
+            WorkerError::NO_ENOUGH_MEMORY_TO_BE_ALLOCATED => "NO_ENOUGH_MEMORY_TO_BE_ALLOCATED",
+            WorkerError::MEMORY_USAGE_LIMITED_BY_HUGE_PARTITION => {
+                "MEMORY_USAGE_LIMITED_BY_HUGE_PARTITION"
+            }
+            WorkerError::MEMORY_USAGE_LIMITED_BY_PARTITION_BUFFER(_) => {
+                "MEMORY_USAGE_LIMITED_BY_PARTITION_BUFFER"
+            }
+            WorkerError::APP_NUMBER_EXCEEDED(_) => "APP_NUMBER_EXCEEDED",
+            WorkerError::PARTITION_NUMBER_EXCEEDED(_) => "PARTITION_NUMBER_EXCEEDED",
+            WorkerError::Other(_) => "Other",
+            WorkerError::HTTP_SERVICE_ERROR(_) => "HTTP_SERVICE_ERROR",
+            WorkerError::TICKET_ID_NOT_EXIST(_) => "TICKET_ID_NOT_EXIST",
+            WorkerError::HDFS_NATIVE_CLIENT_NOT_FOUND(_) => "HDFS_NATIVE_CLIENT_NOT_FOUND",
+            WorkerError::NOT_READ_HDFS_DATA_FROM_SERVER => "NOT_READ_HDFS_DATA_FROM_SERVER",
+            WorkerError::SPILL_EVENT_EXCEED_RETRY_MAX_LIMIT(_) => {
+                "SPILL_EVENT_EXCEED_RETRY_MAX_LIMIT"
+            }
+            WorkerError::STREAM_INCOMPLETE => "STREAM_INCOMPLETE",
+            WorkerError::STREAM_INCORRECT(_) => "STREAM_INCORRECT",
+            WorkerError::STREAM_ABNORMAL => "STREAM_ABNORMAL",
+            WorkerError::STREAM_MESSAGE_TYPE_NOT_FOUND => "STREAM_MESSAGE_TYPE_NOT_FOUND",
+            WorkerError::INJECTED_FAULT(_) => "INJECTED_FAULT",
+            WorkerError::UNSUPPORTED_STORE_OPERATION(_, _) => "UNSUPPORTED_STORE_OPERATION",
+            WorkerError::FLUSH_BARRIER_TIMEOUT(_, _) => "FLUSH_BARRIER_TIMEOUT",
+            WorkerError::BLOCK_ID_LAYOUT_MISMATCHED(_, _) => "BLOCK_ID_LAYOUT_MISMATCHED",
+            WorkerError::ILLEGAL_BLOCK_ID(_, _) => "ILLEGAL_BLOCK_ID",
+            WorkerError::BLOCK_CHECKSUM_MISMATCHED(_) => "BLOCK_CHECKSUM_MISMATCHED",
+            WorkerError::APP_QUOTA_EXCEEDED(_) => "APP_QUOTA_EXCEEDED",
+            WorkerError::DISK_TIMEOUT(_, _) => "DISK_TIMEOUT",
+            WorkerError::PARTITION_REASSIGNED(_) => "PARTITION_REASSIGNED",
+        }
+    }
+
+    /// The status code responded to the client by both grpc and urpc, so that the
+    /// client could retry for the recoverable errors rather than failing directly.
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            WorkerError::NO_ENOUGH_MEMORY_TO_BE_ALLOCATED => StatusCode::NO_BUFFER,
+            // the ticket has been expired, the client should require the buffer again
+            WorkerError::TICKET_ID_NOT_EXIST(_) => StatusCode::NO_BUFFER,
+            WorkerError::MEMORY_USAGE_LIMITED_BY_PARTITION_BUFFER(_) => StatusCode::NO_BUFFER,
+            WorkerError::MEMORY_USAGE_LIMITED_BY_HUGE_PARTITION => {
+                StatusCode::NO_BUFFER_FOR_HUGE_PARTITION
+            }
+            WorkerError::APP_NUMBER_EXCEEDED(_) | WorkerError::PARTITION_NUMBER_EXCEEDED(_) => {
+                StatusCode::SERVER_LIMIT_EXCEEDED
+            }
+            WorkerError::FLUSH_BARRIER_TIMEOUT(_, _) | WorkerError::DISK_TIMEOUT(_, _) => {
+                StatusCode::TIMEOUT
+            }
+            WorkerError::BLOCK_CHECKSUM_MISMATCHED(_) => StatusCode::CHECKSUM_MISMATCHED,
+            WorkerError::APP_QUOTA_EXCEEDED(_) => StatusCode::QUOTA_EXCEEDED,
+            WorkerError::PARTITION_REASSIGNED(_) => StatusCode::REASSIGNED,
+            WorkerError::NO_AVAILABLE_LOCAL_DISK
+            | WorkerError::LOCAL_DISK_UNHEALTHY(_)
+            | WorkerError::LOCAL_DISK_OWNED_BY_PARTITION_CORRUPTED(_)
+            | WorkerError::HDFS_NATIVE_CLIENT_NOT_FOUND(_) => StatusCode::INVALID_STORAGE,
+            WorkerError::NOT_READ_HDFS_DATA_FROM_SERVER
+            | WorkerError::STREAM_INCOMPLETE
+            | WorkerError::STREAM_INCORRECT(_)
+            | WorkerError::STREAM_ABNORMAL
+            | WorkerError::STREAM_MESSAGE_TYPE_NOT_FOUND
+            | WorkerError::UNSUPPORTED_STORE_OPERATION(_, _)
+            | WorkerError::BLOCK_ID_LAYOUT_MISMATCHED(_, _)
+            | WorkerError::ILLEGAL_BLOCK_ID(_, _) => StatusCode::INVALID_REQUEST,
+            WorkerError::INTERNAL_ERROR
+            | WorkerError::PARTIAL_DATA_LOST(_)
+            | WorkerError::Other(_)
+            | WorkerError::HTTP_SERVICE_ERROR(_)
+            | WorkerError::SPILL_EVENT_EXCEED_RETRY_MAX_LIMIT(_)
+            | WorkerError::INJECTED_FAULT(_) => StatusCode::INTERNAL_ERROR,
+        }
+    }
+
+    /// Count the error by its variant and return the status code to respond.
+    pub fn observe_status_code(&self) -> i32 {
+        TOTAL_WORKER_ERROR.with_label_values(&[self.name()]).inc();
+        self.status_code().into()
+    }
+}
+
+impl From<WorkerError> for Status {
+    fn from(error: WorkerError) -> Self {
+        let code = match error.status_code() {
+            StatusCode::NO_BUFFER
+            | StatusCode::NO_BUFFER_FOR_HUGE_PARTITION
+            | StatusCode::QUOTA_EXCEEDED => Code::ResourceExhausted,
+            StatusCode::INVALID_STORAGE | StatusCode::SERVER_LIMIT_EXCEEDED => Code::Unavailable,
+            StatusCode::INVALID_REQUEST => Code::InvalidArgument,
+            StatusCode::NO_REGISTER | StatusCode::NO_PARTITION => Code::NotFound,
+            StatusCode::TIMEOUT => Code::DeadlineExceeded,
+            StatusCode::CHECKSUM_MISMATCHED => Code::DataLoss,
+            StatusCode::REASSIGNED => Code::FailedPrecondition,
+            StatusCode::SUCCESS => Code::Ok,
+            StatusCode::DOUBLE_REGISTER | StatusCode::INTERNAL_ERROR => Code::Internal,
+        };
+        error.observe_status_code();
+        Status::new(code, error.to_string())
+    }
 }
 
 impl From<AcquireError> for WorkerError {
@@ -104,8 +260,80 @@ impl From<FromUtf8Error> for WorkerError {
 
 #[cfg(test)]
 mod tests {
+    use crate::constant::StatusCode;
+    use crate::error::WorkerError;
+    use crate::metric::TOTAL_WORKER_ERROR;
+    use anyhow::{anyhow, Result};
+    use std::time::Duration;
+    use tonic::{Code, Status};
 
-    use anyhow::Result;
+    #[test]
+    fn status_code_test() {
+        let cases = vec![
+            (
+                WorkerError::NO_ENOUGH_MEMORY_TO_BE_ALLOCATED,
+                StatusCode::NO_BUFFER,
+                Code::ResourceExhausted,
+            ),
+            (
+                WorkerError::TICKET_ID_NOT_EXIST(1),
+                StatusCode::NO_BUFFER,
+                Code::ResourceExhausted,
+            ),
+            (
+                WorkerError::MEMORY_USAGE_LIMITED_BY_PARTITION_BUFFER(100),
+                StatusCode::NO_BUFFER,
+                Code::ResourceExhausted,
+            ),
+            (
+                WorkerError::MEMORY_USAGE_LIMITED_BY_HUGE_PARTITION,
+                StatusCode::NO_BUFFER_FOR_HUGE_PARTITION,
+                Code::ResourceExhausted,
+            ),
+            (
+                WorkerError::LOCAL_DISK_UNHEALTHY("/data1".to_string()),
+                StatusCode::INVALID_STORAGE,
+                Code::Unavailable,
+            ),
+            (
+                WorkerError::LOCAL_DISK_OWNED_BY_PARTITION_CORRUPTED("/data1".to_string()),
+                StatusCode::INVALID_STORAGE,
+                Code::Unavailable,
+            ),
+            (
+                WorkerError::DISK_TIMEOUT("/data1".to_string(), Duration::from_secs(30)),
+                StatusCode::TIMEOUT,
+                Code::DeadlineExceeded,
+            ),
+            (
+                WorkerError::NOT_READ_HDFS_DATA_FROM_SERVER,
+                StatusCode::INVALID_REQUEST,
+                Code::InvalidArgument,
+            ),
+            (
+                WorkerError::PARTIAL_DATA_LOST("/data1".to_string()),
+                StatusCode::INTERNAL_ERROR,
+                Code::Internal,
+            ),
+            (
+                WorkerError::Other(anyhow!("mock")),
+                StatusCode::INTERNAL_ERROR,
+                Code::Internal,
+            ),
+        ];
+        for (error, status_code, grpc_code) in cases {
+            let name = error.name();
+            let counted = TOTAL_WORKER_ERROR.with_label_values(&[name]).get();
+
+            // the urpc writes the status code directly
+            assert_eq!(status_code as i32, error.observe_status_code(), "{}", name);
+            assert_eq!(grpc_code, Status::from(error).code(), "{}", name);
+            assert_eq!(
+                counted + 2,
+                TOTAL_WORKER_ERROR.with_label_values(&[name]).get()
+            );
+        }
+    }
 
     #[test]
     pub fn error_test() -> Result<()> {
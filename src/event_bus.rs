@@ -1,17 +1,46 @@
 use crate::await_tree::AWAIT_TREE_REGISTRY;
+use crate::config::{EventBusConfig, QueueOverflow};
+use crate::health::{ComponentHealth, HealthProvider, HealthStatus};
 use crate::metric::{
-    EVENT_BUS_HANDLE_DURATION, GAUGE_EVENT_BUS_QUEUE_HANDLING_SIZE,
-    GAUGE_EVENT_BUS_QUEUE_PENDING_SIZE, TOTAL_EVENT_BUS_EVENT_HANDLED_SIZE,
-    TOTAL_EVENT_BUS_EVENT_PUBLISHED_SIZE,
+    EVENT_BUS_HANDLE_DURATION, EVENT_BUS_HANDLE_SUMMARY, GAUGE_EVENT_BUS_CIRCUIT_BREAKER_STATE,
+    GAUGE_EVENT_BUS_EFFECTIVE_CONCURRENCY, GAUGE_EVENT_BUS_OLDEST_PENDING_AGE_SECONDS,
+    GAUGE_EVENT_BUS_QUEUE_HANDLING_SIZE, GAUGE_EVENT_BUS_QUEUE_PENDING_SIZE,
+    TOTAL_EVENT_BUS_CONCURRENCY_STARVED_SIZE, TOTAL_EVENT_BUS_EVENT_ACK_EXHAUSTED_SIZE,
+    TOTAL_EVENT_BUS_EVENT_DEDUPED_SIZE, TOTAL_EVENT_BUS_EVENT_HANDLED_SIZE,
+    TOTAL_EVENT_BUS_EVENT_PUBLISHED_SIZE, TOTAL_EVENT_BUS_EVENT_REDELIVERED_SIZE,
+    TOTAL_EVENT_BUS_EVENT_REJECTED_SIZE, TOTAL_EVENT_BUS_EVENT_SHORT_CIRCUITED_SIZE,
+    TOTAL_EVENT_BUS_SUBSCRIBER_TIMEOUT_SIZE,
 };
 use crate::runtime::RuntimeRef;
+use crate::util;
 use async_trait::async_trait;
 use await_tree::InstrumentAwait;
 use dashmap::DashMap;
-use std::sync::atomic::{AtomicUsize, Ordering};
+use futures::future::BoxFuture;
+use hashlink::LruCache;
+use log::{debug, warn};
+use parking_lot::{Mutex, RwLock};
+use prometheus::Histogram;
+use std::collections::{BTreeSet, VecDeque};
+use std::hash::Hash;
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 use std::sync::Arc;
-use tokio::sync::Semaphore;
-use tracing::Instrument;
+use std::time::{Duration, Instant};
+use tokio::sync::{oneshot, watch, Semaphore};
+use tracing::{info_span, Instrument, Span};
+
+// the starvation warning is logged at most once in this interval
+const CONCURRENCY_STARVED_WARN_INTERVAL_SEC: u64 = 10;
+
+// the throttled event rechecks the effective concurrency in this interval
+const THROTTLE_RECHECK_INTERVAL_MILLIS: u64 = 10;
+
+// the source of the event published without one
+pub const UNKNOWN_EVENT_SOURCE: &str = "unknown";
+
+// shared by all the buses, so that the event id is unique in the process
+static EVENT_ID_GENERATOR: AtomicU64 = AtomicU64::new(1);
 
 #[async_trait]
 pub trait Subscriber: Send + Sync {
@@ -22,11 +51,25 @@ pub trait Subscriber: Send + Sync {
 
 pub struct Event<T> {
     pub data: T,
+    // assigned on publishing, 0 for the unpublished one
+    id: u64,
+    // the publisher's span, which will be as the parent of handler span
+    span: Span,
+    // set on publishing, including the waiting for the bounded queue
+    enqueued_at: Option<Instant>,
+    // the publishing component, which should be a bounded set to label the metrics
+    source: &'static str,
 }
 
 impl<T: Send + Sync + Clone> Event<T> {
     pub fn new(data: T) -> Event<T> {
-        Event { data }
+        Event {
+            data,
+            id: 0,
+            span: Span::none(),
+            enqueued_at: None,
+            source: UNKNOWN_EVENT_SOURCE,
+        }
     }
 
     pub fn get_data(&self) -> &T {
@@ -34,30 +77,103 @@ impl<T: Send + Sync + Clone> Event<T> {
     }
 }
 
+impl<T> Event<T> {
+    /// The monotonically increasing id to correlate the published event with its handling.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    pub fn source(&self) -> &'static str {
+        self.source
+    }
+}
+
 impl<T: Send + Sync + Clone> From<T> for Event<T> {
     fn from(data: T) -> Self {
         Event::new(data)
     }
 }
 
+pub type SubscriberId = usize;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EventBusOptions {
+    // the stuck subscriber will be cancelled after this to release the concurrency permit
+    pub handle_timeout: Option<Duration>,
+    // unbounded if none
+    pub queue_capacity: Option<usize>,
+    pub overflow: QueueOverflow,
+}
+
+impl EventBusOptions {
+    pub fn from(config: &EventBusConfig) -> anyhow::Result<Self> {
+        Ok(Self {
+            handle_timeout: None,
+            queue_capacity: config.queue_capacity()?,
+            overflow: config.overflow()?,
+        })
+    }
+}
+
+/// Untracking the pending event unless it's queued, like the publishing is failed or
+/// cancelled while waiting for the full queue.
+struct PendingGuard<'a, T> {
+    bus: &'a EventBus<T>,
+    key: (Instant, u64),
+    queued: bool,
+}
+
+impl<T> PendingGuard<'_, T> {
+    fn queued(mut self) {
+        self.queued = true;
+    }
+}
+
+impl<T> Drop for PendingGuard<'_, T> {
+    fn drop(&mut self) {
+        if !self.queued {
+            self.bus.inner.pending_events.lock().remove(&self.key);
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct EventBus<T> {
     inner: Arc<Inner<T>>,
 }
 
+type SubscriberRef<T> = Arc<Box<dyn Subscriber<Input = T> + 'static>>;
+
+// the default order of the subscribers, which are handled by the order ascending
+pub const DEFAULT_SUBSCRIBER_ORDER: i32 = 0;
+
 struct Inner<T> {
-    subscribers: DashMap<usize, Arc<Box<dyn Subscriber<Input = T> + 'static>>>,
+    // value: (order, subscriber)
+    subscribers: DashMap<SubscriberId, (i32, SubscriberRef<T>)>,
     key_counter: Arc<AtomicUsize>,
+    // the handler sees either none or all of the subscribers registered together
+    subscribe_lock: RwLock<()>,
 
     /// Using the async_channel to keep the immutable self to
     /// the self as the Arc<xxx> rather than mpsc::channel, which
     /// uses the recv(&mut self). I don't hope so.
     queue_recv: async_channel::Receiver<Event<T>>,
     queue_send: async_channel::Sender<Event<T>>,
+    overflow: QueueOverflow,
 
     name: String,
     runtime: RuntimeRef,
     concurrency_limit: Arc<Semaphore>,
+    max_concurrency: usize,
+    // the concurrency could be throttled under the max_concurrency
+    effective_concurrency: Arc<watch::Sender<usize>>,
+    // the stuck subscriber will be cancelled after this to release the concurrency permit
+    handle_timeout: Option<Duration>,
+    // the handler loop stops dequeuing when paused, and the events are kept in the queue
+    paused: watch::Sender<bool>,
+    last_starved_warn_sec: AtomicU64,
+    // (enqueued_at, event id) of the events not handled yet, the first is the oldest
+    pending_events: Mutex<BTreeSet<(Instant, u64)>>,
 }
 
 unsafe impl<T: Send + Sync + 'static> Send for EventBus<T> {}
@@ -65,25 +181,58 @@ unsafe impl<T: Send + Sync + 'static> Sync for EventBus<T> {}
 
 impl<T: Send + Sync + Clone + 'static> EventBus<T> {
     pub fn new(runtime: RuntimeRef, name: String, concurrency_limit: usize) -> EventBus<T> {
-        let (send, recv) = async_channel::unbounded();
+        EventBus::with_handle_timeout(runtime, name, concurrency_limit, None)
+    }
+
+    pub fn with_handle_timeout(
+        runtime: RuntimeRef,
+        name: String,
+        concurrency_limit: usize,
+        handle_timeout: Option<Duration>,
+    ) -> EventBus<T> {
+        let options = EventBusOptions {
+            handle_timeout,
+            ..Default::default()
+        };
+        EventBus::with_options(runtime, name, concurrency_limit, options)
+    }
+
+    pub fn with_options(
+        runtime: RuntimeRef,
+        name: String,
+        concurrency_limit: usize,
+        options: EventBusOptions,
+    ) -> EventBus<T> {
+        let (send, recv) = match options.queue_capacity {
+            Some(capacity) => async_channel::bounded(capacity),
+            _ => async_channel::unbounded(),
+        };
         let concurrency_limiter = Arc::new(Semaphore::new(concurrency_limit));
         let event_bus = EventBus {
             inner: Arc::new(Inner {
                 subscribers: Default::default(),
                 key_counter: Default::default(),
+                subscribe_lock: RwLock::new(()),
                 queue_recv: recv,
                 queue_send: send,
+                overflow: options.overflow,
                 name: name.to_string(),
                 runtime: runtime.clone(),
                 concurrency_limit: concurrency_limiter,
+                max_concurrency: concurrency_limit,
+                effective_concurrency: Arc::new(watch::channel(concurrency_limit).0),
+                handle_timeout: options.handle_timeout,
+                paused: watch::channel(false).0,
+                last_starved_warn_sec: AtomicU64::new(0),
+                pending_events: Default::default(),
             }),
         };
 
         let cloned = event_bus.clone();
-        runtime.spawn(async move {
+        runtime.spawn_guarded("event_bus", async move {
             let await_root = AWAIT_TREE_REGISTRY
                 .clone()
-                .register(format!("EventBus - [{}]", &name))
+                .register_long_running(format!("EventBus - [{}]", &name))
                 .await;
             await_root
                 .instrument(async move {
@@ -95,14 +244,28 @@ impl<T: Send + Sync + Clone + 'static> EventBus<T> {
         event_bus
     }
 
+    async fn recv(&self, paused: &mut watch::Receiver<bool>) -> Option<Event<T>> {
+        loop {
+            if paused.wait_for(|paused| !*paused).await.is_err() {
+                return None;
+            }
+            tokio::select! {
+                message = self.inner.queue_recv.recv() => return message.ok(),
+                _ = paused.changed() => continue,
+            }
+        }
+    }
+
     async fn handle(event_bus: EventBus<T>) {
-        while let Ok(message) = event_bus
-            .inner
-            .queue_recv
-            .recv()
+        let mut paused = event_bus.inner.paused.subscribe();
+        while let Some(message) = event_bus
+            .recv(&mut paused)
             .instrument_await("receiving event")
             .await
         {
+            if event_bus.inner.concurrency_limit.available_permits() == 0 {
+                event_bus.on_concurrency_starved();
+            }
             let concurrency_guarder = event_bus
                 .inner
                 .concurrency_limit
@@ -111,6 +274,10 @@ impl<T: Send + Sync + Clone + 'static> EventBus<T> {
                 .instrument_await("waiting for the spill concurrent limit.")
                 .await
                 .unwrap();
+            event_bus
+                .wait_for_effective_concurrency()
+                .instrument_await("waiting for the throttled concurrency.")
+                .await;
 
             let bus = event_bus.clone();
             let await_root = AWAIT_TREE_REGISTRY
@@ -118,10 +285,9 @@ impl<T: Send + Sync + Clone + 'static> EventBus<T> {
                 .register(format!("EventBus - [{}] - Handler", &event_bus.inner.name))
                 .await;
 
-            event_bus
-                .inner
-                .runtime
-                .spawn(await_root.instrument(async move {
+            event_bus.inner.runtime.spawn_guarded(
+                "event_bus_handler",
+                await_root.instrument(async move {
                     let timer = EVENT_BUS_HANDLE_DURATION
                         .with_label_values(&[&bus.inner.name])
                         .start_timer();
@@ -131,13 +297,30 @@ impl<T: Send + Sync + Clone + 'static> EventBus<T> {
                     GAUGE_EVENT_BUS_QUEUE_PENDING_SIZE
                         .with_label_values(&[&bus.inner.name])
                         .dec();
+                    bus.on_dequeued(&message);
 
-                    let subscribers = bus.inner.subscribers.clone().into_read_only();
-                    for (_, subscriber) in subscribers.iter() {
-                        subscriber.on_event(&message).await;
+                    let span = info_span!(
+                        parent: &message.span,
+                        "event_bus_handle",
+                        name = %&bus.inner.name,
+                        event_id = message.id
+                    );
+                    let subscribers = {
+                        let _guard = bus.inner.subscribe_lock.read();
+                        bus.ordered_subscribers()
+                    };
+                    async {
+                        for subscriber in subscribers.iter() {
+                            bus.handle_with_timeout(subscriber, &message).await;
+                        }
                     }
+                    .instrument(span)
+                    .await;
 
-                    timer.observe_duration();
+                    let duration = timer.stop_and_record();
+                    if let Some(summary) = EVENT_BUS_HANDLE_SUMMARY.get() {
+                        summary.observe(&bus.inner.name, duration);
+                    }
                     GAUGE_EVENT_BUS_QUEUE_HANDLING_SIZE
                         .with_label_values(&[&bus.inner.name])
                         .dec();
@@ -146,84 +329,2096 @@ impl<T: Send + Sync + Clone + 'static> EventBus<T> {
                         .inc();
 
                     drop(concurrency_guarder);
-                }));
+                }),
+            );
+        }
+    }
+
+    fn on_dequeued(&self, event: &Event<T>) {
+        if let Some(enqueued_at) = event.enqueued_at {
+            self.inner
+                .pending_events
+                .lock()
+                .remove(&(enqueued_at, event.id));
+        }
+        self.observe_oldest_pending_age();
+    }
+
+    /// The waiting time of the oldest event which has been published but not handled yet,
+    /// including the ones dequeued and waiting for the concurrency permit.
+    pub fn oldest_pending_age(&self) -> Option<Duration> {
+        self.inner
+            .pending_events
+            .lock()
+            .first()
+            .map(|(enqueued_at, _)| enqueued_at.elapsed())
+    }
+
+    /// The event has to wait for the permit, that means the queue is deep
+    /// due to the concurrency ceiling rather than the slow handlers.
+    fn on_concurrency_starved(&self) {
+        TOTAL_EVENT_BUS_CONCURRENCY_STARVED_SIZE
+            .with_label_values(&[&self.inner.name])
+            .inc();
+        let now = util::now_timestamp_as_sec();
+        let last = self.inner.last_starved_warn_sec.load(Ordering::SeqCst);
+        if now >= last + CONCURRENCY_STARVED_WARN_INTERVAL_SEC
+            && self
+                .inner
+                .last_starved_warn_sec
+                .compare_exchange(last, now, Ordering::SeqCst, Ordering::SeqCst)
+                .is_ok()
+        {
+            warn!(
+                "Event bus: [{}] is starved for the concurrency permits. pending: {}",
+                &self.inner.name,
+                self.inner.queue_recv.len()
+            );
+        }
+    }
+
+    /// The permit of the current event has been acquired, so it's counted in the handling ones.
+    async fn wait_for_effective_concurrency(&self) {
+        let mut effective = self.inner.effective_concurrency.subscribe();
+        loop {
+            let handling =
+                self.inner.max_concurrency - self.inner.concurrency_limit.available_permits();
+            if handling <= *effective.borrow_and_update() {
+                return;
+            }
+            tokio::select! {
+                _ = effective.changed() => continue,
+                _ = tokio::time::sleep(Duration::from_millis(THROTTLE_RECHECK_INTERVAL_MILLIS)) => continue,
+            }
+        }
+    }
+
+    /// Sorted by the order ascending, and the ties fall back to the registration order.
+    fn ordered_subscribers(&self) -> Vec<SubscriberRef<T>> {
+        let mut subscribers: Vec<(i32, SubscriberId, SubscriberRef<T>)> = self
+            .inner
+            .subscribers
+            .iter()
+            .map(|x| (x.value().0, *x.key(), x.value().1.clone()))
+            .collect();
+        subscribers.sort_by_key(|(order, id, _)| (*order, *id));
+        subscribers.into_iter().map(|(_, _, x)| x).collect()
+    }
+
+    async fn handle_with_timeout(&self, subscriber: &SubscriberRef<T>, event: &Event<T>) {
+        let timeout = match self.inner.handle_timeout {
+            Some(timeout) => timeout,
+            _ => return subscriber.on_event(event).await,
+        };
+        if tokio::time::timeout(timeout, subscriber.on_event(event))
+            .await
+            .is_err()
+        {
+            warn!(
+                "Event: {} handling timeout in event bus: [{}] after {:?}",
+                event.id, &self.inner.name, timeout
+            );
+            TOTAL_EVENT_BUS_SUBSCRIBER_TIMEOUT_SIZE
+                .with_label_values(&[&self.inner.name])
+                .inc();
+        }
+    }
+
+    /// Stop dequeuing the events until resumed. The published events
+    /// are kept in the queue and the in-flight ones won't be interrupted.
+    pub fn pause(&self) {
+        self.inner.paused.send_replace(true);
+    }
+
+    pub fn resume(&self) {
+        self.inner.paused.send_replace(false);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        *self.inner.paused.borrow()
+    }
+
+    pub fn pending_size(&self) -> usize {
+        self.inner.queue_recv.len()
+    }
+
+    /// None for the unbounded queue.
+    pub fn queue_capacity(&self) -> Option<usize> {
+        self.inner.queue_send.capacity()
+    }
+
+    pub fn max_concurrency(&self) -> usize {
+        self.inner.max_concurrency
+    }
+
+    pub fn available_permits(&self) -> usize {
+        self.inner.concurrency_limit.available_permits()
+    }
+
+    pub fn effective_concurrency(&self) -> usize {
+        *self.inner.effective_concurrency.borrow()
+    }
+
+    /// The handle to throttle the concurrency, which doesn't hold the bus itself
+    /// so that it could be kept by the subscriber.
+    pub fn concurrency_limiter(&self) -> ConcurrencyLimiter {
+        ConcurrencyLimiter {
+            name: self.inner.name.clone(),
+            max_concurrency: self.inner.max_concurrency,
+            effective_concurrency: self.inner.effective_concurrency.clone(),
+            acquired: Arc::new(watch::channel(0).0),
         }
     }
 
-    pub fn subscribe<R: Subscriber<Input = T> + 'static + Send + Sync>(&self, listener: R) {
+    pub fn subscribe<R: Subscriber<Input = T> + 'static + Send + Sync>(
+        &self,
+        listener: R,
+    ) -> SubscriberId {
+        self.subscribe_with_order(listener, DEFAULT_SUBSCRIBER_ORDER)
+    }
+
+    /// The subscriber with the smaller order handles the event first.
+    pub fn subscribe_with_order<R: Subscriber<Input = T> + 'static + Send + Sync>(
+        &self,
+        listener: R,
+        order: i32,
+    ) -> SubscriberId {
+        let _guard = self.inner.subscribe_lock.write();
+        self.insert_subscriber(Box::new(listener), order)
+    }
+
+    fn insert_subscriber(
+        &self,
+        listener: Box<dyn Subscriber<Input = T>>,
+        order: i32,
+    ) -> SubscriberId {
         let idx = self.inner.key_counter.fetch_add(1, Ordering::SeqCst);
         self.inner
             .subscribers
-            .insert(idx, Arc::new(Box::new(listener)));
+            .insert(idx, (order, Arc::new(listener)));
+        idx
     }
 
-    pub async fn publish(&self, event: Event<T>) -> anyhow::Result<()> {
-        self.inner.queue_send.send(event).await?;
+    /// Register the subscribers together, the events will be
+    /// handled by either none or all of them.
+    pub fn subscribe_all(
+        &self,
+        listeners: Vec<Box<dyn Subscriber<Input = T>>>,
+    ) -> Vec<SubscriberId> {
+        let _guard = self.inner.subscribe_lock.write();
+        let mut ids = Vec::with_capacity(listeners.len());
+        for listener in listeners {
+            ids.push(self.insert_subscriber(listener, DEFAULT_SUBSCRIBER_ORDER));
+        }
+        ids
+    }
+
+    /// Returns whether the subscriber was registered. The in-flight events
+    /// dispatched to it won't be interrupted.
+    pub fn unsubscribe(&self, id: SubscriberId) -> bool {
+        let _guard = self.inner.subscribe_lock.write();
+        self.inner.subscribers.remove(&id).is_some()
+    }
+
+    pub fn subscriber_count(&self) -> usize {
+        self.inner.subscribers.len()
+    }
+
+    /// Publish the event tagged with the source component, like `publish_from("hybrid_store", event)`.
+    pub async fn publish_from(
+        &self,
+        source: &'static str,
+        mut event: Event<T>,
+    ) -> anyhow::Result<()> {
+        event.source = source;
+        self.publish(event).await
+    }
+
+    /// Publish the event without waiting for the queue space whatever the overflow policy,
+    /// which is required for the subscriber republishing onto its own bus. Otherwise the
+    /// handlers holding the permits are blocked by the full queue that nobody drains.
+    pub fn try_publish_from(
+        &self,
+        source: &'static str,
+        mut event: Event<T>,
+    ) -> anyhow::Result<()> {
+        event.source = source;
+        let pending = self.track_pending(&mut event);
+        self.try_send(event)?;
+        pending.queued();
+        self.on_published(source);
+        Ok(())
+    }
+
+    pub async fn publish(&self, mut event: Event<T>) -> anyhow::Result<()> {
+        let source = event.source;
+        let pending = self.track_pending(&mut event);
+        match self.inner.overflow {
+            QueueOverflow::Block => self.inner.queue_send.send(event).await?,
+            QueueOverflow::Reject => self.try_send(event)?,
+        }
+        pending.queued();
+        self.on_published(source);
+        Ok(())
+    }
 
+    fn track_pending(&self, event: &mut Event<T>) -> PendingGuard<'_, T> {
+        event.id = EVENT_ID_GENERATOR.fetch_add(1, Ordering::SeqCst);
+        event.span = Span::current();
+        // tracked before sending, the handler may dequeue it before the sending returns
+        let key = (Instant::now(), event.id);
+        event.enqueued_at = Some(key.0);
+        self.inner.pending_events.lock().insert(key);
+        PendingGuard {
+            bus: self,
+            key,
+            queued: false,
+        }
+    }
+
+    fn try_send(&self, event: Event<T>) -> anyhow::Result<()> {
+        if let Err(e) = self.inner.queue_send.try_send(event) {
+            TOTAL_EVENT_BUS_EVENT_REJECTED_SIZE
+                .with_label_values(&[&self.inner.name])
+                .inc();
+            return Err(anyhow::anyhow!(
+                "Event bus: [{}] rejected the event. {}",
+                &self.inner.name,
+                e
+            ));
+        }
+        Ok(())
+    }
+
+    fn observe_oldest_pending_age(&self) {
+        let age = self.oldest_pending_age().unwrap_or_default();
+        GAUGE_EVENT_BUS_OLDEST_PENDING_AGE_SECONDS
+            .with_label_values(&[&self.inner.name])
+            .set(age.as_secs() as i64);
+    }
+
+    fn on_published(&self, source: &'static str) {
+        self.observe_oldest_pending_age();
         GAUGE_EVENT_BUS_QUEUE_PENDING_SIZE
             .with_label_values(&[&self.inner.name])
             .inc();
         TOTAL_EVENT_BUS_EVENT_PUBLISHED_SIZE
-            .with_label_values(&[&self.inner.name])
+            .with_label_values(&[&self.inner.name, source])
             .inc();
-        Ok(())
     }
 }
 
-#[cfg(test)]
-mod test {
-    use crate::event_bus::{Event, EventBus, Subscriber};
-    use crate::metric::{TOTAL_EVENT_BUS_EVENT_HANDLED_SIZE, TOTAL_EVENT_BUS_EVENT_PUBLISHED_SIZE};
-    use crate::runtime::manager::create_runtime;
-    use async_trait::async_trait;
-    use std::sync::atomic::{AtomicI64, Ordering};
-    use std::sync::Arc;
-    use std::time::Duration;
+#[async_trait]
+impl<T: Send + Sync + Clone + 'static> HealthProvider for EventBus<T> {
+    async fn component_health(&self) -> Vec<ComponentHealth> {
+        vec![ComponentHealth::new(
+            format!("event_bus:{}", &self.inner.name),
+            HealthStatus::Healthy,
+            false,
+            serde_json::json!({
+                "pending": self.pending_size(),
+                "paused": self.is_paused(),
+            }),
+        )]
+    }
+}
 
-    #[test]
-    fn test_event_bus() -> anyhow::Result<()> {
-        let runtime = create_runtime(1, "test");
-        let mut event_bus = EventBus::new(runtime.clone(), "test".to_string(), 1usize);
+#[derive(Clone)]
+pub struct ConcurrencyLimiter {
+    name: String,
+    max_concurrency: usize,
+    effective_concurrency: Arc<watch::Sender<usize>>,
+    // the permits acquired from the standalone limiter, the bus counts its own handling
+    acquired: Arc<watch::Sender<usize>>,
+}
 
-        let flag = Arc::new(AtomicI64::new(0));
+impl ConcurrencyLimiter {
+    /// The standalone limiter not bound to any bus, which is acquired by the handling
+    /// that should be throttled only, like the spill to the localfile.
+    pub fn new(name: &str, max_concurrency: usize) -> Self {
+        Self {
+            name: name.to_string(),
+            max_concurrency,
+            effective_concurrency: Arc::new(watch::channel(max_concurrency).0),
+            acquired: Arc::new(watch::channel(0).0),
+        }
+    }
 
-        struct SimpleCallback {
-            flag: Arc<AtomicI64>,
+    /// Wait until the acquired permits are below the effective concurrency.
+    pub async fn acquire(&self) -> ConcurrencyPermit {
+        let mut effective = self.effective_concurrency.subscribe();
+        let mut acquired = self.acquired.subscribe();
+        loop {
+            let concurrency = *effective.borrow_and_update();
+            acquired.borrow_and_update();
+            let admitted = self.acquired.send_if_modified(|x| {
+                if *x < concurrency {
+                    *x += 1;
+                    return true;
+                }
+                false
+            });
+            if admitted {
+                return ConcurrencyPermit {
+                    acquired: self.acquired.clone(),
+                };
+            }
+            tokio::select! {
+                _ = effective.changed() => continue,
+                _ = acquired.changed() => continue,
+            }
         }
+    }
 
-        #[async_trait]
-        impl Subscriber for SimpleCallback {
-            type Input = String;
+    pub fn acquired(&self) -> usize {
+        *self.acquired.borrow()
+    }
 
-            async fn on_event(&self, event: &Event<Self::Input>) {
-                println!("SimpleCallback has accepted event: {:?}", event.get_data());
-                self.flag.fetch_add(1, Ordering::SeqCst);
-            }
+    pub fn max_concurrency(&self) -> usize {
+        self.max_concurrency
+    }
+
+    pub fn get(&self) -> usize {
+        *self.effective_concurrency.borrow()
+    }
+
+    /// The in-flight events won't be interrupted when shrinking,
+    /// and the later ones wait until the handling ones are below it.
+    pub fn set(&self, concurrency: usize) {
+        let concurrency = concurrency.max(1).min(self.max_concurrency);
+        let previous = self.effective_concurrency.send_replace(concurrency);
+        if previous != concurrency {
+            debug!(
+                "Effective concurrency of event bus: [{}] is changed from {} to {}",
+                &self.name, previous, concurrency
+            );
         }
+        GAUGE_EVENT_BUS_EFFECTIVE_CONCURRENCY
+            .with_label_values(&[&self.name])
+            .set(concurrency as i64);
+    }
+}
 
-        let flag_cloned = flag.clone();
-        event_bus.subscribe(SimpleCallback { flag: flag_cloned });
+pub struct ConcurrencyPermit {
+    acquired: Arc<watch::Sender<usize>>,
+}
 
-        let bus = event_bus.clone();
-        let _ =
-            runtime.block_on(async move { bus.publish("singleEvent".to_string().into()).await });
+impl Drop for ConcurrencyPermit {
+    fn drop(&mut self) {
+        self.acquired.send_modify(|x| *x -= 1);
+    }
+}
 
-        // case1: check the handle logic
-        awaitility::at_most(Duration::from_secs(1)).until(|| flag.load(Ordering::SeqCst) == 1);
+/// The used ratio in [0, 1] of the resource consumed by the handling, like the disk
+/// of the spill. None if it's unknown.
+pub trait UsageSource: Send + Sync {
+    fn used_ratio(&self) -> Option<f64>;
+}
 
-        // case2: check the metrics
-        assert_eq!(
-            1,
-            TOTAL_EVENT_BUS_EVENT_HANDLED_SIZE
-                .with_label_values(&["test"])
-                .get()
-        );
-        assert_eq!(
-            1,
-            TOTAL_EVENT_BUS_EVENT_PUBLISHED_SIZE
-                .with_label_values(&["test"])
-                .get()
-        );
+/// Reporting the usage after every handled event to throttle the limiter, which is the
+/// bus concurrency or the standalone one acquired by the part of handling. It's the max
+/// under the low watermark, and shrinks linearly to 1 at the high watermark.
+pub struct ThrottledSubscriber<T> {
+    inner: Box<dyn Subscriber<Input = T>>,
+    usage: Arc<dyn UsageSource>,
+    limiter: ConcurrencyLimiter,
+    low_watermark: f64,
+    high_watermark: f64,
+}
+
+impl<T> ThrottledSubscriber<T> {
+    pub fn new<R: Subscriber<Input = T> + 'static>(
+        inner: R,
+        usage: Arc<dyn UsageSource>,
+        limiter: ConcurrencyLimiter,
+        low_watermark: f64,
+        high_watermark: f64,
+    ) -> Self {
+        Self {
+            inner: Box::new(inner),
+            usage,
+            limiter,
+            low_watermark,
+            high_watermark,
+        }
+    }
+
+    fn throttled_concurrency(&self, used_ratio: f64) -> usize {
+        let max = self.limiter.max_concurrency();
+        if used_ratio <= self.low_watermark {
+            return max;
+        }
+        if used_ratio >= self.high_watermark {
+            return 1;
+        }
+        let headroom =
+            (self.high_watermark - used_ratio) / (self.high_watermark - self.low_watermark);
+        (max as f64 * headroom) as usize
+    }
+}
+
+#[async_trait]
+impl<T: Send + Sync> Subscriber for ThrottledSubscriber<T> {
+    type Input = T;
+
+    async fn on_event(&self, event: &Event<Self::Input>) {
+        self.inner.on_event(event).await;
+        if let Some(used_ratio) = self.usage.used_ratio() {
+            self.limiter.set(self.throttled_concurrency(used_ratio));
+        }
+    }
+}
+
+/// Keeping the most recent N events for debugging, like checking
+/// whether the spill event has been fired. The cloned one shares the same buffer.
+#[derive(Clone)]
+pub struct RingBufferSubscriber<T> {
+    capacity: usize,
+    // with the event id and source
+    buffer: Arc<Mutex<VecDeque<(u64, &'static str, T)>>>,
+}
+
+impl<T: Clone> RingBufferSubscriber<T> {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            buffer: Arc::new(Mutex::new(VecDeque::with_capacity(capacity))),
+        }
+    }
+
+    /// Return the recent events in the handled order, the oldest one first.
+    pub fn recent(&self) -> Vec<T> {
+        self.buffer
+            .lock()
+            .iter()
+            .map(|(_, _, x)| x.clone())
+            .collect()
+    }
+
+    /// Same as the `recent`, with the id of every event.
+    pub fn recent_with_ids(&self) -> Vec<(u64, T)> {
+        self.buffer
+            .lock()
+            .iter()
+            .map(|(id, _, x)| (*id, x.clone()))
+            .collect()
+    }
+
+    /// Same as the `recent`, with the source of every event.
+    pub fn recent_with_sources(&self) -> Vec<(&'static str, T)> {
+        self.buffer
+            .lock()
+            .iter()
+            .map(|(_, source, x)| (*source, x.clone()))
+            .collect()
+    }
+
+    fn record(&self, id: u64, source: &'static str, data: T) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut buffer = self.buffer.lock();
+        if buffer.len() >= self.capacity {
+            buffer.pop_front();
+        }
+        buffer.push_back((id, source, data));
+    }
+}
+
+#[async_trait]
+impl<T: Send + Sync + Clone> Subscriber for RingBufferSubscriber<T> {
+    type Input = T;
+
+    async fn on_event(&self, event: &Event<Self::Input>) {
+        self.record(event.id, event.source, event.data.clone());
+    }
+}
+
+/// Observing the payload size of every event into the histogram, which is passive and
+/// could be subscribed to any bus to profile the size distribution without the producers.
+pub struct SizeMetricSubscriber<T, F> {
+    histogram: Histogram,
+    size_fn: F,
+    _marker: PhantomData<fn(T)>,
+}
+
+impl<T, F: Fn(&T) -> u64 + Send + Sync> SizeMetricSubscriber<T, F> {
+    pub fn new(histogram: Histogram, size_fn: F) -> Self {
+        Self {
+            histogram,
+            size_fn,
+            _marker: PhantomData,
+        }
+    }
+}
+
+#[async_trait]
+impl<T: Send + Sync, F: Fn(&T) -> u64 + Send + Sync> Subscriber for SizeMetricSubscriber<T, F> {
+    type Input = T;
+
+    async fn on_event(&self, event: &Event<Self::Input>) {
+        self.histogram
+            .observe((self.size_fn)(event.get_data()) as f64);
+    }
+}
+
+/// The subscriber from the sync closure, like `FnSubscriber::new(|event| counter.inc())`.
+pub struct FnSubscriber<T, F> {
+    f: F,
+    _marker: PhantomData<fn(T)>,
+}
+
+impl<T, F: Fn(&Event<T>) + Send + Sync> FnSubscriber<T, F> {
+    pub fn new(f: F) -> Self {
+        Self {
+            f,
+            _marker: PhantomData,
+        }
+    }
+}
+
+#[async_trait]
+impl<T: Send + Sync, F: Fn(&Event<T>) + Send + Sync> Subscriber for FnSubscriber<T, F> {
+    type Input = T;
+
+    async fn on_event(&self, event: &Event<Self::Input>) {
+        (self.f)(event)
+    }
+}
+
+/// The subscriber from the async closure, like `AsyncFnSubscriber::new(|event| async {..}.boxed())`.
+pub struct AsyncFnSubscriber<T, F> {
+    f: F,
+    _marker: PhantomData<fn(T)>,
+}
+
+impl<T, F> AsyncFnSubscriber<T, F>
+where
+    F: for<'a> Fn(&'a Event<T>) -> BoxFuture<'a, ()> + Send + Sync,
+{
+    pub fn new(f: F) -> Self {
+        Self {
+            f,
+            _marker: PhantomData,
+        }
+    }
+}
+
+#[async_trait]
+impl<T, F> Subscriber for AsyncFnSubscriber<T, F>
+where
+    T: Send + Sync,
+    F: for<'a> Fn(&'a Event<T>) -> BoxFuture<'a, ()> + Send + Sync,
+{
+    type Input = T;
+
+    async fn on_event(&self, event: &Event<Self::Input>) {
+        (self.f)(event).await
+    }
+}
+
+/// Skipping the event whose key has been seen in the recent `capacity` keys,
+/// which gives the effectively-once delivery to the inner subscriber.
+pub struct DedupSubscriber<T, K, F> {
+    name: String,
+    inner: Box<dyn Subscriber<Input = T>>,
+    key_fn: F,
+    recent_keys: Mutex<LruCache<K, ()>>,
+}
+
+impl<T, K, F> DedupSubscriber<T, K, F>
+where
+    K: Hash + Eq,
+    F: Fn(&T) -> K,
+{
+    pub fn new<R: Subscriber<Input = T> + 'static>(
+        name: &str,
+        inner: R,
+        key_fn: F,
+        capacity: usize,
+    ) -> Self {
+        Self {
+            name: name.to_string(),
+            inner: Box::new(inner),
+            key_fn,
+            recent_keys: Mutex::new(LruCache::new(capacity)),
+        }
+    }
+
+    /// Return true if the key has been seen recently.
+    fn check_and_record(&self, key: K) -> bool {
+        let mut recent_keys = self.recent_keys.lock();
+        if recent_keys.get(&key).is_some() {
+            return true;
+        }
+        recent_keys.insert(key, ());
+        false
+    }
+}
+
+#[async_trait]
+impl<T, K, F> Subscriber for DedupSubscriber<T, K, F>
+where
+    T: Send + Sync,
+    K: Hash + Eq + Send,
+    F: Fn(&T) -> K + Send + Sync,
+{
+    type Input = T;
+
+    async fn on_event(&self, event: &Event<Self::Input>) {
+        if self.check_and_record((self.key_fn)(event.get_data())) {
+            TOTAL_EVENT_BUS_EVENT_DEDUPED_SIZE
+                .with_label_values(&[&self.name])
+                .inc();
+            return;
+        }
+        self.inner.on_event(event).await;
+    }
+}
+
+/// Recording the outcome of the passed event, which is the failure if the handling is
+/// cancelled like the timeout. Otherwise the half-open breaker is stuck with the trial.
+struct CircuitOutcomeGuard<'a, T> {
+    breaker: &'a CircuitBreakerSubscriber<T>,
+    recorded: bool,
+}
+
+impl<T> CircuitOutcomeGuard<'_, T> {
+    fn record(mut self, success: bool) {
+        self.recorded = true;
+        self.breaker.on_result(success);
+    }
+}
+
+impl<T> Drop for CircuitOutcomeGuard<'_, T> {
+    fn drop(&mut self) {
+        if !self.recorded {
+            warn!(
+                "The handling is cancelled by the circuit breaker: [{}]",
+                &self.breaker.name
+            );
+            self.breaker.on_result(false);
+        }
+    }
+}
+
+/// The subscriber reporting the handling failure, which could be wrapped
+/// by the [`CircuitBreakerSubscriber`].
+#[async_trait]
+pub trait FallibleSubscriber: Send + Sync {
+    type Input;
+
+    async fn try_on_event(&self, event: &Event<Self::Input>) -> anyhow::Result<()>;
+}
+
+/// The short-circuited events are dropped or handed to the dead-letter subscriber.
+pub enum ShortCircuitPolicy<T> {
+    Drop,
+    DeadLetter(Box<dyn Subscriber<Input = T>>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitState {
+    Closed,
+    Open,
+    HalfOpen,
+}
+
+struct CircuitBreakerState {
+    state: CircuitState,
+    consecutive_failures: usize,
+    opened_at: Instant,
+}
+
+/// Opening after the `failure_threshold` consecutive failures of the inner subscriber,
+/// the events are short-circuited until the `cooldown` elapses. Then one trial event is
+/// passed through to probe, the success of which closes the breaker.
+pub struct CircuitBreakerSubscriber<T> {
+    name: String,
+    inner: Box<dyn FallibleSubscriber<Input = T>>,
+    policy: ShortCircuitPolicy<T>,
+    failure_threshold: usize,
+    cooldown: Duration,
+    state: Mutex<CircuitBreakerState>,
+}
+
+impl<T> CircuitBreakerSubscriber<T> {
+    pub fn new<R: FallibleSubscriber<Input = T> + 'static>(
+        name: &str,
+        inner: R,
+        policy: ShortCircuitPolicy<T>,
+        failure_threshold: usize,
+        cooldown: Duration,
+    ) -> Self {
+        GAUGE_EVENT_BUS_CIRCUIT_BREAKER_STATE
+            .with_label_values(&[name])
+            .set(0);
+        Self {
+            name: name.to_string(),
+            inner: Box::new(inner),
+            policy,
+            failure_threshold: failure_threshold.max(1),
+            cooldown,
+            state: Mutex::new(CircuitBreakerState {
+                state: CircuitState::Closed,
+                consecutive_failures: 0,
+                opened_at: Instant::now(),
+            }),
+        }
+    }
+
+    pub fn state(&self) -> CircuitState {
+        self.state.lock().state
+    }
+
+    fn transit(&self, state: &mut CircuitBreakerState, to: CircuitState) {
+        if state.state != to {
+            warn!(
+                "Circuit breaker: [{}] is transited from {:?} to {:?}",
+                &self.name, state.state, to
+            );
+        }
+        state.state = to;
+        let value = match to {
+            CircuitState::Closed => 0,
+            CircuitState::Open => 1,
+            CircuitState::HalfOpen => 2,
+        };
+        GAUGE_EVENT_BUS_CIRCUIT_BREAKER_STATE
+            .with_label_values(&[&self.name])
+            .set(value);
+    }
+
+    /// Return true if the event could be passed to the inner subscriber.
+    fn try_pass(&self) -> bool {
+        let mut state = self.state.lock();
+        match state.state {
+            CircuitState::Closed => true,
+            CircuitState::Open if state.opened_at.elapsed() >= self.cooldown => {
+                // only this event is passed as the trial, the others are short-circuited
+                self.transit(&mut state, CircuitState::HalfOpen);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn on_result(&self, success: bool) {
+        let mut state = self.state.lock();
+        if success {
+            state.consecutive_failures = 0;
+            self.transit(&mut state, CircuitState::Closed);
+            return;
+        }
+        state.consecutive_failures += 1;
+        if state.state == CircuitState::HalfOpen
+            || state.consecutive_failures >= self.failure_threshold
+        {
+            state.opened_at = Instant::now();
+            self.transit(&mut state, CircuitState::Open);
+        }
+    }
+}
+
+#[async_trait]
+impl<T: Send + Sync> Subscriber for CircuitBreakerSubscriber<T> {
+    type Input = T;
+
+    async fn on_event(&self, event: &Event<Self::Input>) {
+        if !self.try_pass() {
+            TOTAL_EVENT_BUS_EVENT_SHORT_CIRCUITED_SIZE
+                .with_label_values(&[&self.name])
+                .inc();
+            debug!(
+                "Short-circuited the event: {} by the circuit breaker: [{}]",
+                event.id, &self.name
+            );
+            if let ShortCircuitPolicy::DeadLetter(dead_letter) = &self.policy {
+                dead_letter.on_event(event).await;
+            }
+            return;
+        }
+        let outcome = CircuitOutcomeGuard {
+            breaker: self,
+            recorded: false,
+        };
+        let result = self.inner.try_on_event(event).await;
+        if let Err(error) = &result {
+            warn!(
+                "Errors on handling event: {} by the subscriber: [{}]. error: {:#?}",
+                event.id, &self.name, error
+            );
+        }
+        outcome.record(result.is_ok());
+    }
+}
+
+/// The handle to confirm the event has been handled. The event is redelivered to the
+/// subscriber if the handle is dropped without acking, like the subscriber panicked.
+pub struct Ack {
+    attempt: u32,
+    acked: Option<oneshot::Sender<()>>,
+}
+
+impl Ack {
+    /// Starting from 1.
+    pub fn attempt(&self) -> u32 {
+        self.attempt
+    }
+
+    pub fn ack(mut self) {
+        if let Some(acked) = self.acked.take() {
+            let _ = acked.send(());
+        }
+    }
+}
+
+#[async_trait]
+pub trait AckableSubscriber: Send + Sync {
+    type Input;
+
+    /// The ack could be kept and acked after the event is handled asynchronously.
+    async fn on_event(&self, event: &Event<Self::Input>, ack: Ack);
+}
+
+#[derive(Clone)]
+struct Delivery<T> {
+    data: T,
+    attempt: u32,
+    // the redelivered event is only for the subscriber who didn't ack it
+    target: Option<SubscriberId>,
+}
+
+/// The event bus whose event is redelivered until acked by every subscriber or the
+/// max attempts are reached. The concurrency permit is held until the event is acked.
+#[derive(Clone)]
+pub struct AckableEventBus<T> {
+    bus: EventBus<Delivery<T>>,
+    max_attempts: u32,
+    key_counter: Arc<AtomicUsize>,
+}
+
+impl<T: Send + Sync + Clone + 'static> AckableEventBus<T> {
+    pub fn new(
+        runtime: RuntimeRef,
+        name: String,
+        concurrency_limit: usize,
+        max_attempts: u32,
+    ) -> AckableEventBus<T> {
+        AckableEventBus {
+            bus: EventBus::new(runtime, name, concurrency_limit),
+            max_attempts: max_attempts.max(1),
+            key_counter: Default::default(),
+        }
+    }
+
+    pub fn subscribe<R: AckableSubscriber<Input = T> + 'static>(
+        &self,
+        subscriber: R,
+    ) -> SubscriberId {
+        let id = self.key_counter.fetch_add(1, Ordering::SeqCst);
+        self.bus.subscribe(AckingSubscriber {
+            id,
+            inner: Arc::new(Box::new(subscriber)),
+            bus: self.bus.clone(),
+            max_attempts: self.max_attempts,
+        });
+        id
+    }
+
+    pub async fn publish(&self, event: Event<T>) -> anyhow::Result<()> {
+        let delivery = Delivery {
+            data: event.data,
+            attempt: 1,
+            target: None,
+        };
+        self.bus.publish_from(event.source, delivery.into()).await
+    }
+
+    pub fn pending_size(&self) -> usize {
+        self.bus.pending_size()
+    }
+}
+
+struct AckingSubscriber<T> {
+    id: SubscriberId,
+    inner: Arc<Box<dyn AckableSubscriber<Input = T>>>,
+    bus: EventBus<Delivery<T>>,
+    max_attempts: u32,
+}
+
+#[async_trait]
+impl<T: Send + Sync + Clone + 'static> Subscriber for AckingSubscriber<T> {
+    type Input = Delivery<T>;
+
+    async fn on_event(&self, event: &Event<Self::Input>) {
+        let delivery = &event.data;
+        if delivery.target.map_or(false, |target| target != self.id) {
+            return;
+        }
+        let (sender, acked) = oneshot::channel();
+        let ack = Ack {
+            attempt: delivery.attempt,
+            acked: Some(sender),
+        };
+        let acked_event = Event {
+            data: delivery.data.clone(),
+            id: event.id,
+            span: event.span.clone(),
+            enqueued_at: event.enqueued_at,
+            source: event.source,
+        };
+        // handled in the separated task, so that the panicked subscriber only drops the ack
+        let inner = self.inner.clone();
+        self.bus
+            .inner
+            .runtime
+            .spawn_guarded("event_bus_ackable_handler", async move {
+                inner.on_event(&acked_event, ack).await;
+            });
+        if acked.await.is_ok() {
+            return;
+        }
+
+        let name = &self.bus.inner.name;
+        if delivery.attempt >= self.max_attempts {
+            TOTAL_EVENT_BUS_EVENT_ACK_EXHAUSTED_SIZE
+                .with_label_values(&[name])
+                .inc();
+            warn!(
+                "Event: {} is dropped without the ack of the subscriber: {} in event bus: [{}] after {} attempts",
+                event.id, self.id, name, delivery.attempt
+            );
+            return;
+        }
+        TOTAL_EVENT_BUS_EVENT_REDELIVERED_SIZE
+            .with_label_values(&[name])
+            .inc();
+        let redelivery = Delivery {
+            data: delivery.data.clone(),
+            attempt: delivery.attempt + 1,
+            target: Some(self.id),
+        };
+        if let Err(e) = self.bus.publish_from(event.source, redelivery.into()).await {
+            warn!(
+                "Errors on redelivering the event: {} in event bus: [{}]. error: {:#?}",
+                event.id, name, e
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::config::{Config, MetricsConfig, QueueOverflow};
+    use crate::event_bus::{
+        Ack, AckableEventBus, AckableSubscriber, AsyncFnSubscriber, CircuitBreakerSubscriber,
+        CircuitState, ConcurrencyLimiter, DedupSubscriber, Event, EventBus, EventBusOptions,
+        FallibleSubscriber, FnSubscriber, RingBufferSubscriber, ShortCircuitPolicy,
+        SizeMetricSubscriber, Subscriber, ThrottledSubscriber, UsageSource, UNKNOWN_EVENT_SOURCE,
+    };
+    use crate::metric::{MetricService, REGISTRY};
+    use crate::metric::{
+        GAUGE_EVENT_BUS_CIRCUIT_BREAKER_STATE, GAUGE_EVENT_BUS_OLDEST_PENDING_AGE_SECONDS,
+        GAUGE_EVENT_BUS_QUEUE_PENDING_SIZE, TOTAL_EVENT_BUS_CONCURRENCY_STARVED_SIZE,
+        TOTAL_EVENT_BUS_EVENT_ACK_EXHAUSTED_SIZE, TOTAL_EVENT_BUS_EVENT_DEDUPED_SIZE,
+        TOTAL_EVENT_BUS_EVENT_HANDLED_SIZE, TOTAL_EVENT_BUS_EVENT_PUBLISHED_SIZE,
+        TOTAL_EVENT_BUS_EVENT_REDELIVERED_SIZE, TOTAL_EVENT_BUS_EVENT_REJECTED_SIZE,
+        TOTAL_EVENT_BUS_EVENT_SHORT_CIRCUITED_SIZE, TOTAL_EVENT_BUS_SUBSCRIBER_TIMEOUT_SIZE,
+    };
+    use crate::runtime::manager::{create_runtime, RuntimeManager};
+    use async_trait::async_trait;
+    use futures::FutureExt;
+    use parking_lot::Mutex;
+    use prometheus::{Histogram, HistogramOpts};
+    use std::sync::atomic::{AtomicBool, AtomicI64, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tracing::span::{Attributes, Id};
+    use tracing::{info_span, Instrument};
+    use tracing_subscriber::layer::{Context, SubscriberExt};
+    use tracing_subscriber::registry::LookupSpan;
+    use tracing_subscriber::Layer;
+
+    #[test]
+    fn test_event_bus() -> anyhow::Result<()> {
+        let runtime = create_runtime(1, "test");
+        let mut event_bus = EventBus::new(runtime.clone(), "test".to_string(), 1usize);
+
+        let flag = Arc::new(AtomicI64::new(0));
+
+        let flag_cloned = flag.clone();
+        event_bus.subscribe(FnSubscriber::new(move |event: &Event<String>| {
+            println!("FnSubscriber has accepted event: {:?}", event.get_data());
+            flag_cloned.fetch_add(1, Ordering::SeqCst);
+        }));
+
+        let bus = event_bus.clone();
+        let _ =
+            runtime.block_on(async move { bus.publish("singleEvent".to_string().into()).await });
+
+        // case1: check the handle logic
+        awaitility::at_most(Duration::from_secs(1)).until(|| flag.load(Ordering::SeqCst) == 1);
+
+        // case2: check the metrics
+        assert_eq!(
+            1,
+            TOTAL_EVENT_BUS_EVENT_HANDLED_SIZE
+                .with_label_values(&["test"])
+                .get()
+        );
+        assert_eq!(
+            1,
+            TOTAL_EVENT_BUS_EVENT_PUBLISHED_SIZE
+                .with_label_values(&["test", UNKNOWN_EVENT_SOURCE])
+                .get()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_handle_summary() -> anyhow::Result<()> {
+        let mut config = Config::create_simple_config();
+        config.metrics = Some(MetricsConfig {
+            push_gateway_endpoint: None,
+            push_interval_sec: 10,
+            enable_summaries: Some(true),
+            summary_quantiles: None,
+            push_gateway_username: None,
+            push_gateway_password: None,
+            push_gateway_password_file: None,
+        });
+        MetricService::init(&config, RuntimeManager::default());
+
+        let runtime = create_runtime(1, "test_handle_summary");
+        let event_bus = EventBus::new(runtime.clone(), "test_handle_summary".to_string(), 1usize);
+        event_bus.subscribe(FnSubscriber::new(|_: &Event<i32>| {}));
+        let bus = event_bus.clone();
+        runtime.block_on(async move { bus.publish(1.into()).await })?;
+
+        let handled_count = || {
+            REGISTRY
+                .gather()
+                .into_iter()
+                .filter(|x| x.get_name() == "eventbus_handle_operation_duration_summary")
+                .flat_map(|x| x.get_metric().to_vec())
+                .filter(|x| x.get_label()[0].get_value() == "test_handle_summary")
+                .map(|x| x.get_summary().get_sample_count())
+                .sum::<u64>()
+        };
+        awaitility::at_most(Duration::from_secs(1)).until(|| handled_count() == 1);
+        let family = REGISTRY
+            .gather()
+            .into_iter()
+            .find(|x| x.get_name() == "eventbus_handle_operation_duration_summary")
+            .unwrap();
+        let metric = family
+            .get_metric()
+            .iter()
+            .find(|x| x.get_label()[0].get_value() == "test_handle_summary")
+            .unwrap();
+        assert_eq!(3, metric.get_summary().get_quantile().len());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_fn_subscribers() -> anyhow::Result<()> {
+        let runtime = create_runtime(1, "test_fn_subscribers");
+        let event_bus = EventBus::new(runtime.clone(), "test_fn_subscribers".to_string(), 1usize);
+
+        let received = Arc::new(parking_lot::Mutex::new(vec![]));
+        let cloned = received.clone();
+        event_bus.subscribe(FnSubscriber::new(move |event: &Event<i32>| {
+            cloned.lock().push(*event.get_data())
+        }));
+
+        let sum = Arc::new(AtomicI64::new(0));
+        let cloned = sum.clone();
+        event_bus.subscribe(AsyncFnSubscriber::new(move |event: &Event<i32>| {
+            let sum = cloned.clone();
+            async move {
+                tokio::time::sleep(Duration::from_millis(1)).await;
+                sum.fetch_add(*event.get_data() as i64, Ordering::SeqCst);
+            }
+            .boxed()
+        }));
+
+        let bus = event_bus.clone();
+        runtime.block_on(async move {
+            for i in 1..=3 {
+                bus.publish(i.into()).await?;
+            }
+            anyhow::Ok(())
+        })?;
+
+        awaitility::at_most(Duration::from_secs(1)).until(|| sum.load(Ordering::SeqCst) == 6);
+        assert_eq!(vec![1, 2, 3], *received.lock());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_size_metric_subscriber() -> anyhow::Result<()> {
+        let runtime = create_runtime(1, "test_size_metric_subscriber");
+        let event_bus = EventBus::new(
+            runtime.clone(),
+            "test_size_metric_subscriber".to_string(),
+            1usize,
+        );
+
+        let histogram = Histogram::with_opts(
+            HistogramOpts::new("test_event_size", "none").buckets(vec![10.0, 100.0, 1000.0]),
+        )?;
+        event_bus.subscribe(SizeMetricSubscriber::new(
+            histogram.clone(),
+            |data: &String| data.len() as u64,
+        ));
+        // observed among the other subscribers
+        let subscriber = RingBufferSubscriber::new(10);
+        event_bus.subscribe(subscriber.clone());
+
+        let bus = event_bus.clone();
+        runtime.block_on(async move {
+            for size in [1, 20, 300] {
+                bus.publish("a".repeat(size).into()).await?;
+            }
+            anyhow::Ok(())
+        })?;
+
+        awaitility::at_most(Duration::from_secs(1)).until(|| histogram.get_sample_count() == 3);
+        assert_eq!(321.0, histogram.get_sample_sum());
+        awaitility::at_most(Duration::from_secs(1)).until(|| subscriber.recent().len() == 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_subscriber_order() -> anyhow::Result<()> {
+        let runtime = create_runtime(1, "test_subscriber_order");
+        let event_bus = EventBus::new(runtime.clone(), "test_subscriber_order".to_string(), 1usize);
+
+        let executed = Arc::new(parking_lot::Mutex::new(vec![]));
+        for (name, order) in [
+            ("spill", 10),
+            ("metrics", -1),
+            ("default", 0),
+            ("audit", 10),
+        ] {
+            let cloned = executed.clone();
+            event_bus.subscribe_with_order(
+                FnSubscriber::new(move |_: &Event<i32>| cloned.lock().push(name)),
+                order,
+            );
+        }
+        let cloned = executed.clone();
+        event_bus.subscribe(FnSubscriber::new(move |_: &Event<i32>| {
+            cloned.lock().push("default_later")
+        }));
+
+        let bus = event_bus.clone();
+        runtime.block_on(async move { bus.publish(1.into()).await })?;
+
+        awaitility::at_most(Duration::from_secs(1)).until(|| executed.lock().len() == 5);
+        assert_eq!(
+            vec!["metrics", "default", "default_later", "spill", "audit"],
+            *executed.lock()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_subscriber_count() {
+        let runtime = create_runtime(1, "test_subscriber_count");
+        let event_bus = EventBus::new(runtime, "test_subscriber_count".to_string(), 1usize);
+        assert_eq!(0, event_bus.subscriber_count());
+
+        let first = event_bus.subscribe(FnSubscriber::new(|_: &Event<i32>| {}));
+        event_bus.subscribe(FnSubscriber::new(|_: &Event<i32>| {}));
+        assert_eq!(2, event_bus.subscriber_count());
+
+        assert!(event_bus.unsubscribe(first));
+        assert_eq!(1, event_bus.subscriber_count());
+        assert!(!event_bus.unsubscribe(first));
+        assert_eq!(1, event_bus.subscriber_count());
+    }
+
+    #[test]
+    fn test_event_id() -> anyhow::Result<()> {
+        let runtime = create_runtime(1, "test_event_id");
+        let event_bus = EventBus::new(runtime.clone(), "test_event_id".to_string(), 1usize);
+
+        let observed = Arc::new(parking_lot::Mutex::new(vec![]));
+        let observed_cloned = observed.clone();
+        event_bus.subscribe(FnSubscriber::new(move |event: &Event<i32>| {
+            observed_cloned.lock().push((event.id(), event.data));
+        }));
+        let recorder = RingBufferSubscriber::new(10);
+        event_bus.subscribe(recorder.clone());
+
+        // assigned on publishing rather than by the user
+        let event: Event<i32> = 1.into();
+        assert_eq!(0, event.id());
+        let bus = event_bus.clone();
+        runtime.block_on(async move {
+            bus.publish(event).await?;
+            bus.publish(2.into()).await
+        })?;
+
+        awaitility::at_most(Duration::from_secs(1))
+            .until(|| recorder.recent().len() == 2 && observed.lock().len() == 2);
+        let observed = observed.lock().clone();
+        let (first, second) = (observed[0].0, observed[1].0);
+        assert!(first > 0);
+        assert!(second > first);
+        assert_eq!(vec![(first, 1), (second, 2)], observed);
+        assert_eq!(observed, recorder.recent_with_ids());
+        Ok(())
+    }
+
+    #[test]
+    fn test_publish_from() -> anyhow::Result<()> {
+        let runtime = create_runtime(1, "test_publish_from");
+        let event_bus = EventBus::new(runtime.clone(), "test_publish_from".to_string(), 1usize);
+        let recorder = RingBufferSubscriber::new(10);
+        event_bus.subscribe(recorder.clone());
+
+        let bus = event_bus.clone();
+        runtime.block_on(async move {
+            bus.publish_from("spill", 1.into()).await?;
+            bus.publish_from("spill", 2.into()).await?;
+            bus.publish_from("purge", 3.into()).await?;
+            bus.publish(4.into()).await
+        })?;
+
+        awaitility::at_most(Duration::from_secs(1)).until(|| recorder.recent().len() == 4);
+        assert_eq!(
+            vec![
+                ("spill", 1),
+                ("spill", 2),
+                ("purge", 3),
+                (UNKNOWN_EVENT_SOURCE, 4)
+            ],
+            recorder.recent_with_sources()
+        );
+        let published = |source: &str| {
+            TOTAL_EVENT_BUS_EVENT_PUBLISHED_SIZE
+                .with_label_values(&["test_publish_from", source])
+                .get()
+        };
+        assert_eq!(2, published("spill"));
+        assert_eq!(1, published("purge"));
+        assert_eq!(1, published(UNKNOWN_EVENT_SOURCE));
+        Ok(())
+    }
+
+    #[test]
+    fn test_subscribe_all() -> anyhow::Result<()> {
+        let runtime = create_runtime(1, "test_subscribe_all");
+        let event_bus = EventBus::new(runtime.clone(), "test_subscribe_all".to_string(), 1usize);
+
+        let received = Arc::new(parking_lot::Mutex::new(vec![]));
+        let mut listeners: Vec<Box<dyn Subscriber<Input = i32>>> = vec![];
+        for idx in 0..3 {
+            let cloned = received.clone();
+            listeners.push(Box::new(FnSubscriber::new(move |event: &Event<i32>| {
+                cloned.lock().push((idx, *event.get_data()))
+            })));
+        }
+        let ids = event_bus.subscribe_all(listeners);
+        assert_eq!(vec![0, 1, 2], ids);
+        assert_eq!(
+            3,
+            event_bus.subscribe(FnSubscriber::new(|_: &Event<i32>| {}))
+        );
+
+        let bus = event_bus.clone();
+        runtime.block_on(async move { bus.publish(1.into()).await })?;
+
+        awaitility::at_most(Duration::from_secs(1)).until(|| received.lock().len() == 3);
+        let mut received = received.lock().clone();
+        received.sort();
+        assert_eq!(vec![(0, 1), (1, 1), (2, 1)], received);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_handle_timeout() -> anyhow::Result<()> {
+        let runtime = create_runtime(2, "test_handle_timeout");
+        let event_bus = EventBus::with_handle_timeout(
+            runtime.clone(),
+            "test_handle_timeout".to_string(),
+            1usize,
+            Some(Duration::from_millis(100)),
+        );
+
+        struct SleepCallback {
+            handled: Arc<AtomicI64>,
+        }
+
+        #[async_trait]
+        impl Subscriber for SleepCallback {
+            type Input = bool;
+
+            async fn on_event(&self, event: &Event<Self::Input>) {
+                if *event.get_data() {
+                    tokio::time::sleep(Duration::from_secs(60)).await;
+                }
+                self.handled.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        let handled = Arc::new(AtomicI64::new(0));
+        event_bus.subscribe(SleepCallback {
+            handled: handled.clone(),
+        });
+
+        let bus = event_bus.clone();
+        runtime.block_on(async move {
+            bus.publish(true.into()).await?;
+            bus.publish(false.into()).await
+        })?;
+
+        // the stuck one is cancelled and the permit is released for the next event
+        awaitility::at_most(Duration::from_secs(2)).until(|| handled.load(Ordering::SeqCst) == 1);
+        assert_eq!(
+            1,
+            TOTAL_EVENT_BUS_SUBSCRIBER_TIMEOUT_SIZE
+                .with_label_values(&["test_handle_timeout"])
+                .get()
+        );
+        awaitility::at_most(Duration::from_secs(1))
+            .until(|| event_bus.inner.concurrency_limit.available_permits() == 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_concurrency_starved() -> anyhow::Result<()> {
+        let runtime = create_runtime(2, "test_starved");
+        let event_bus = EventBus::new(runtime.clone(), "test_starved".to_string(), 1usize);
+
+        struct SlowCallback {
+            handled: Arc<AtomicI64>,
+        }
+
+        #[async_trait]
+        impl Subscriber for SlowCallback {
+            type Input = i32;
+
+            async fn on_event(&self, _event: &Event<Self::Input>) {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                self.handled.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        let handled = Arc::new(AtomicI64::new(0));
+        event_bus.subscribe(SlowCallback {
+            handled: handled.clone(),
+        });
+
+        let bus = event_bus.clone();
+        runtime.block_on(async move {
+            for i in 0..3 {
+                bus.publish(i.into()).await?;
+            }
+            anyhow::Ok(())
+        })?;
+
+        awaitility::at_most(Duration::from_secs(2)).until(|| handled.load(Ordering::SeqCst) == 3);
+        // the later 2 events have to wait for the only permit
+        assert_eq!(
+            2,
+            TOTAL_EVENT_BUS_CONCURRENCY_STARVED_SIZE
+                .with_label_values(&["test_starved"])
+                .get()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_pause_and_resume() -> anyhow::Result<()> {
+        let runtime = create_runtime(2, "test_pause");
+        let event_bus = EventBus::new(runtime.clone(), "test_pause".to_string(), 1usize);
+
+        let subscriber = RingBufferSubscriber::new(10);
+        event_bus.subscribe(subscriber.clone());
+
+        event_bus.pause();
+        assert!(event_bus.is_paused());
+
+        let bus = event_bus.clone();
+        runtime.block_on(async move {
+            for i in 0..5 {
+                bus.publish(i.into()).await?;
+            }
+            anyhow::Ok(())
+        })?;
+
+        std::thread::sleep(Duration::from_millis(200));
+        assert!(subscriber.recent().is_empty());
+        assert_eq!(5, event_bus.inner.queue_recv.len());
+        assert_eq!(
+            5,
+            GAUGE_EVENT_BUS_QUEUE_PENDING_SIZE
+                .with_label_values(&["test_pause"])
+                .get()
+        );
+
+        event_bus.resume();
+        awaitility::at_most(Duration::from_secs(1)).until(|| subscriber.recent().len() == 5);
+        assert_eq!(vec![0, 1, 2, 3, 4], subscriber.recent());
+        assert_eq!(
+            0,
+            GAUGE_EVENT_BUS_QUEUE_PENDING_SIZE
+                .with_label_values(&["test_pause"])
+                .get()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_oldest_pending_age() -> anyhow::Result<()> {
+        let runtime = create_runtime(2, "test_oldest_pending_age");
+        let event_bus = EventBus::new(
+            runtime.clone(),
+            "test_oldest_pending_age".to_string(),
+            1usize,
+        );
+        assert!(event_bus.oldest_pending_age().is_none());
+
+        // the handler is held by the first event, and the second one waits for the permit
+        let released = Arc::new(AtomicBool::new(false));
+        let cloned = released.clone();
+        event_bus.subscribe(AsyncFnSubscriber::new(move |_: &Event<i32>| {
+            let released = cloned.clone();
+            async move {
+                while !released.load(Ordering::SeqCst) {
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                }
+            }
+            .boxed()
+        }));
+
+        let bus = event_bus.clone();
+        runtime.block_on(async move {
+            bus.publish(1.into()).await?;
+            bus.publish(2.into()).await?;
+            anyhow::Ok(())
+        })?;
+
+        std::thread::sleep(Duration::from_millis(300));
+        let age = event_bus.oldest_pending_age().unwrap();
+        assert!(age >= Duration::from_millis(300));
+        assert!(age < Duration::from_secs(5));
+
+        released.store(true, Ordering::SeqCst);
+        awaitility::at_most(Duration::from_secs(1))
+            .until(|| event_bus.oldest_pending_age().is_none());
+        assert_eq!(
+            0,
+            GAUGE_EVENT_BUS_OLDEST_PENDING_AGE_SECONDS
+                .with_label_values(&["test_oldest_pending_age"])
+                .get()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_bounded_queue_overflow() -> anyhow::Result<()> {
+        let runtime = create_runtime(2, "test_overflow");
+        let options = EventBusOptions {
+            queue_capacity: Some(2),
+            overflow: QueueOverflow::Reject,
+            ..Default::default()
+        };
+        let event_bus =
+            EventBus::with_options(runtime.clone(), "test_overflow".to_string(), 1, options);
+        assert_eq!(Some(2), event_bus.queue_capacity());
+
+        let subscriber = RingBufferSubscriber::new(10);
+        event_bus.subscribe(subscriber.clone());
+        event_bus.pause();
+
+        let bus = event_bus.clone();
+        let rejected = runtime.block_on(async move {
+            bus.publish(0.into()).await?;
+            bus.publish(1.into()).await?;
+            anyhow::Ok(bus.publish(2.into()).await.is_err())
+        })?;
+        assert!(rejected);
+        assert_eq!(
+            1,
+            TOTAL_EVENT_BUS_EVENT_REJECTED_SIZE
+                .with_label_values(&["test_overflow"])
+                .get()
+        );
+
+        event_bus.resume();
+        awaitility::at_most(Duration::from_secs(1)).until(|| subscriber.recent().len() == 2);
+        assert_eq!(vec![0, 1], subscriber.recent());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_cancelled_publish_untracked() -> anyhow::Result<()> {
+        let runtime = create_runtime(2, "test_cancelled_publish");
+        let options = EventBusOptions {
+            queue_capacity: Some(1),
+            overflow: QueueOverflow::Block,
+            ..Default::default()
+        };
+        let event_bus = EventBus::with_options(
+            runtime.clone(),
+            "test_cancelled_publish".to_string(),
+            1,
+            options,
+        );
+        let subscriber = RingBufferSubscriber::new(10);
+        event_bus.subscribe(subscriber.clone());
+        event_bus.pause();
+
+        let bus = event_bus.clone();
+        let cancelled = runtime.block_on(async move {
+            bus.publish(0.into()).await?;
+            // blocked by the full queue and cancelled
+            anyhow::Ok(
+                tokio::time::timeout(Duration::from_millis(100), bus.publish(1.into()))
+                    .await
+                    .is_err(),
+            )
+        })?;
+        assert!(cancelled);
+        assert_eq!(1, event_bus.inner.pending_events.lock().len());
+
+        event_bus.resume();
+        awaitility::at_most(Duration::from_secs(1)).until(|| subscriber.recent().len() == 1);
+        awaitility::at_most(Duration::from_secs(1))
+            .until(|| event_bus.oldest_pending_age().is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_try_publish_on_blocking_queue() -> anyhow::Result<()> {
+        let runtime = create_runtime(2, "test_try_publish");
+        let options = EventBusOptions {
+            queue_capacity: Some(1),
+            overflow: QueueOverflow::Block,
+            ..Default::default()
+        };
+        let event_bus =
+            EventBus::with_options(runtime.clone(), "test_try_publish".to_string(), 1, options);
+        let subscriber = RingBufferSubscriber::new(10);
+        event_bus.subscribe(subscriber.clone());
+        event_bus.pause();
+
+        let bus = event_bus.clone();
+        runtime.block_on(async move { bus.publish(0.into()).await })?;
+        // the full queue is not waited even under the blocking overflow
+        assert!(event_bus.try_publish_from("retry", 1.into()).is_err());
+        assert_eq!(1, event_bus.pending_size());
+        assert_eq!(1, event_bus.inner.pending_events.lock().len());
+
+        event_bus.resume();
+        awaitility::at_most(Duration::from_secs(1)).until(|| subscriber.recent().len() == 1);
+        event_bus.try_publish_from("retry", 2.into())?;
+        awaitility::at_most(Duration::from_secs(1)).until(|| subscriber.recent().len() == 2);
+        assert_eq!(vec![0, 2], subscriber.recent());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_ring_buffer_subscriber() -> anyhow::Result<()> {
+        let runtime = create_runtime(1, "test_ring_buffer");
+        let event_bus = EventBus::new(runtime.clone(), "test_ring_buffer".to_string(), 1usize);
+
+        let subscriber = RingBufferSubscriber::new(3);
+        event_bus.subscribe(subscriber.clone());
+
+        let bus = event_bus.clone();
+        runtime.block_on(async move {
+            for i in 0..5 {
+                bus.publish(i.into()).await?;
+            }
+            anyhow::Ok(())
+        })?;
+
+        awaitility::at_most(Duration::from_secs(1))
+            .until(|| subscriber.recent().last() == Some(&4));
+        assert_eq!(vec![2, 3, 4], subscriber.recent());
+
+        Ok(())
+    }
+
+    struct CrashingSubscriber {
+        // (data, attempt) of the deliveries
+        deliveries: Arc<Mutex<Vec<(i32, u32)>>>,
+        // crashed before acking in these attempts
+        crashes: u32,
+    }
+
+    #[async_trait]
+    impl AckableSubscriber for CrashingSubscriber {
+        type Input = i32;
+
+        async fn on_event(&self, event: &Event<Self::Input>, ack: Ack) {
+            self.deliveries
+                .lock()
+                .push((*event.get_data(), ack.attempt()));
+            if ack.attempt() <= self.crashes {
+                panic!("Crashed in the middle of handling");
+            }
+            ack.ack();
+        }
+    }
+
+    #[test]
+    fn test_ackable_event_bus() -> anyhow::Result<()> {
+        let runtime = create_runtime(2, "test_ackable");
+        let event_bus = AckableEventBus::new(runtime.clone(), "test_ackable".to_string(), 1, 3);
+
+        // case1: redelivered after the crash, and no more delivery once acked
+        let deliveries = Arc::new(Mutex::new(vec![]));
+        let id = event_bus.subscribe(CrashingSubscriber {
+            deliveries: deliveries.clone(),
+            crashes: 1,
+        });
+        let bus = event_bus.clone();
+        runtime.block_on(async move { bus.publish(1.into()).await })?;
+        awaitility::at_most(Duration::from_secs(1)).until(|| deliveries.lock().len() == 2);
+        assert_eq!(vec![(1, 1), (1, 2)], *deliveries.lock());
+        std::thread::sleep(Duration::from_millis(100));
+        assert_eq!(2, deliveries.lock().len());
+        assert_eq!(
+            1,
+            TOTAL_EVENT_BUS_EVENT_REDELIVERED_SIZE
+                .with_label_values(&["test_ackable"])
+                .get()
+        );
+
+        // case2: the redelivery is only for the subscriber who didn't ack
+        let acked = Arc::new(Mutex::new(vec![]));
+        let acked_id = event_bus.subscribe(CrashingSubscriber {
+            deliveries: acked.clone(),
+            crashes: 0,
+        });
+        assert_ne!(id, acked_id);
+        let bus = event_bus.clone();
+        runtime.block_on(async move { bus.publish(2.into()).await })?;
+        awaitility::at_most(Duration::from_secs(1)).until(|| deliveries.lock().len() == 4);
+        std::thread::sleep(Duration::from_millis(100));
+        assert_eq!(vec![(2, 1)], *acked.lock());
+
+        // case3: dropped after the max attempts
+        let event_bus =
+            AckableEventBus::new(runtime.clone(), "test_ackable_exhausted".to_string(), 1, 3);
+        let deliveries = Arc::new(Mutex::new(vec![]));
+        event_bus.subscribe(CrashingSubscriber {
+            deliveries: deliveries.clone(),
+            crashes: u32::MAX,
+        });
+        let bus = event_bus.clone();
+        runtime.block_on(async move { bus.publish(3.into()).await })?;
+        awaitility::at_most(Duration::from_secs(1)).until(|| {
+            TOTAL_EVENT_BUS_EVENT_ACK_EXHAUSTED_SIZE
+                .with_label_values(&["test_ackable_exhausted"])
+                .get()
+                == 1
+        });
+        assert_eq!(vec![(3, 1), (3, 2), (3, 3)], *deliveries.lock());
+        assert_eq!(0, event_bus.pending_size());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_dedup_subscriber() -> anyhow::Result<()> {
+        let runtime = create_runtime(1, "test_dedup");
+        let event_bus = EventBus::new(runtime.clone(), "test_dedup".to_string(), 1usize);
+
+        let inner = RingBufferSubscriber::new(10);
+        event_bus.subscribe(DedupSubscriber::new(
+            "test_dedup",
+            inner.clone(),
+            |data: &(String, i32)| data.0.clone(),
+            2,
+        ));
+
+        let bus = event_bus.clone();
+        runtime.block_on(async move {
+            bus.publish(("key_1".to_string(), 1).into()).await?;
+            bus.publish(("key_1".to_string(), 2).into()).await?;
+            bus.publish(("key_2".to_string(), 3).into()).await
+        })?;
+
+        awaitility::at_most(Duration::from_secs(1)).until(|| {
+            TOTAL_EVENT_BUS_EVENT_HANDLED_SIZE
+                .with_label_values(&["test_dedup"])
+                .get()
+                == 3
+        });
+        assert_eq!(
+            vec![("key_1".to_string(), 1), ("key_2".to_string(), 3)],
+            inner.recent()
+        );
+        assert_eq!(
+            1,
+            TOTAL_EVENT_BUS_EVENT_DEDUPED_SIZE
+                .with_label_values(&["test_dedup"])
+                .get()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_standalone_concurrency_limiter() -> anyhow::Result<()> {
+        let runtime = create_runtime(2, "test_standalone_limiter");
+        let limiter = ConcurrencyLimiter::new("test_standalone_limiter", 2);
+        limiter.set(1);
+
+        let first = runtime.block_on(limiter.acquire());
+        assert_eq!(1, limiter.acquired());
+
+        // blocked until the first one is released
+        let admitted = Arc::new(AtomicBool::new(false));
+        let acquiring = {
+            let limiter = limiter.clone();
+            let admitted = admitted.clone();
+            runtime.spawn(async move {
+                let permit = limiter.acquire().await;
+                admitted.store(true, Ordering::SeqCst);
+                permit
+            })
+        };
+        std::thread::sleep(Duration::from_millis(100));
+        assert!(!admitted.load(Ordering::SeqCst));
+        drop(first);
+        let second = runtime.block_on(acquiring)?;
+        assert_eq!(1, limiter.acquired());
+
+        // the enlarged concurrency admits more at once
+        limiter.set(2);
+        let third = runtime.block_on(limiter.acquire());
+        assert_eq!(2, limiter.acquired());
+
+        drop(second);
+        drop(third);
+        assert_eq!(0, limiter.acquired());
+        Ok(())
+    }
+
+    #[test]
+    fn test_throttled_subscriber() -> anyhow::Result<()> {
+        let runtime = create_runtime(4, "test_throttled");
+        let event_bus = EventBus::new(runtime.clone(), "test_throttled".to_string(), 4usize);
+
+        struct MockedUsage {
+            used_ratio: parking_lot::Mutex<f64>,
+        }
+
+        impl UsageSource for MockedUsage {
+            fn used_ratio(&self) -> Option<f64> {
+                Some(*self.used_ratio.lock())
+            }
+        }
+
+        struct SlowCallback {
+            handling: Arc<AtomicI64>,
+            max_handling: Arc<AtomicI64>,
+            handled: Arc<AtomicI64>,
+        }
+
+        #[async_trait]
+        impl Subscriber for SlowCallback {
+            type Input = i32;
+
+            async fn on_event(&self, _event: &Event<Self::Input>) {
+                let handling = self.handling.fetch_add(1, Ordering::SeqCst) + 1;
+                self.max_handling.fetch_max(handling, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                self.handling.fetch_sub(1, Ordering::SeqCst);
+                self.handled.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        let usage = Arc::new(MockedUsage {
+            used_ratio: parking_lot::Mutex::new(0.95),
+        });
+        let max_handling = Arc::new(AtomicI64::new(0));
+        let handled = Arc::new(AtomicI64::new(0));
+        let subscriber = ThrottledSubscriber::new(
+            SlowCallback {
+                handling: Default::default(),
+                max_handling: max_handling.clone(),
+                handled: handled.clone(),
+            },
+            usage.clone(),
+            event_bus.concurrency_limiter(),
+            0.6,
+            0.8,
+        );
+        assert_eq!(4, subscriber.throttled_concurrency(0.6));
+        assert_eq!(2, subscriber.throttled_concurrency(0.7));
+        assert_eq!(1, subscriber.throttled_concurrency(0.8));
+        event_bus.subscribe(subscriber);
+
+        let publish = |from: i32, to: i32| {
+            let bus = event_bus.clone();
+            runtime.block_on(async move {
+                for i in from..to {
+                    bus.publish(i.into()).await?;
+                }
+                anyhow::Ok(())
+            })
+        };
+
+        // shrinks to 1 above the high watermark
+        publish(0, 1)?;
+        awaitility::at_most(Duration::from_secs(2)).until(|| handled.load(Ordering::SeqCst) == 1);
+        assert_eq!(1, event_bus.effective_concurrency());
+
+        max_handling.store(0, Ordering::SeqCst);
+        publish(1, 5)?;
+        awaitility::at_most(Duration::from_secs(2)).until(|| handled.load(Ordering::SeqCst) == 5);
+        assert_eq!(1, max_handling.load(Ordering::SeqCst));
+
+        // recovers under the low watermark
+        *usage.used_ratio.lock() = 0.5;
+        publish(5, 6)?;
+        awaitility::at_most(Duration::from_secs(2)).until(|| handled.load(Ordering::SeqCst) == 6);
+        assert_eq!(4, event_bus.effective_concurrency());
+
+        max_handling.store(0, Ordering::SeqCst);
+        publish(6, 10)?;
+        awaitility::at_most(Duration::from_secs(2)).until(|| handled.load(Ordering::SeqCst) == 10);
+        assert!(max_handling.load(Ordering::SeqCst) > 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_circuit_breaker_subscriber() -> anyhow::Result<()> {
+        struct FlakyCallback {
+            failing: Arc<AtomicBool>,
+            called: Arc<AtomicI64>,
+        }
+
+        #[async_trait]
+        impl FallibleSubscriber for FlakyCallback {
+            type Input = i32;
+
+            async fn try_on_event(&self, _event: &Event<Self::Input>) -> anyhow::Result<()> {
+                self.called.fetch_add(1, Ordering::SeqCst);
+                if self.failing.load(Ordering::SeqCst) {
+                    return Err(anyhow::anyhow!("backend is down"));
+                }
+                Ok(())
+            }
+        }
+
+        let failing = Arc::new(AtomicBool::new(true));
+        let called = Arc::new(AtomicI64::new(0));
+        let dead_letter = RingBufferSubscriber::new(10);
+        let breaker = CircuitBreakerSubscriber::new(
+            "test_circuit_breaker",
+            FlakyCallback {
+                failing: failing.clone(),
+                called: called.clone(),
+            },
+            ShortCircuitPolicy::DeadLetter(Box::new(dead_letter.clone())),
+            3,
+            Duration::from_millis(200),
+        );
+        let state_gauge = || {
+            GAUGE_EVENT_BUS_CIRCUIT_BREAKER_STATE
+                .with_label_values(&["test_circuit_breaker"])
+                .get()
+        };
+
+        let runtime = create_runtime(1, "test_circuit_breaker");
+        let publish = |data: i32| runtime.block_on(breaker.on_event(&data.into()));
+
+        // case1: opened after the consecutive failures
+        for i in 0..3 {
+            publish(i);
+        }
+        assert_eq!(CircuitState::Open, breaker.state());
+        assert_eq!(1, state_gauge());
+        assert_eq!(3, called.load(Ordering::SeqCst));
+
+        // case2: short-circuited to the dead-letter in the open window
+        publish(3);
+        publish(4);
+        assert_eq!(3, called.load(Ordering::SeqCst));
+        assert_eq!(vec![3, 4], dead_letter.recent());
+        assert_eq!(
+            2,
+            TOTAL_EVENT_BUS_EVENT_SHORT_CIRCUITED_SIZE
+                .with_label_values(&["test_circuit_breaker"])
+                .get()
+        );
+
+        // case3: the failed trial after the cooldown reopens it
+        std::thread::sleep(Duration::from_millis(250));
+        publish(5);
+        assert_eq!(4, called.load(Ordering::SeqCst));
+        assert_eq!(CircuitState::Open, breaker.state());
+        publish(6);
+        assert_eq!(4, called.load(Ordering::SeqCst));
+
+        // case4: the succeeded trial closes it
+        failing.store(false, Ordering::SeqCst);
+        std::thread::sleep(Duration::from_millis(250));
+        publish(7);
+        publish(8);
+        assert_eq!(6, called.load(Ordering::SeqCst));
+        assert_eq!(CircuitState::Closed, breaker.state());
+        assert_eq!(0, state_gauge());
+        assert_eq!(vec![3, 4, 6], dead_letter.recent());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_circuit_breaker_with_cancelled_trial() -> anyhow::Result<()> {
+        struct SlowCallback {
+            delay_millis: Arc<AtomicI64>,
+        }
+
+        #[async_trait]
+        impl FallibleSubscriber for SlowCallback {
+            type Input = i32;
+
+            async fn try_on_event(&self, _event: &Event<Self::Input>) -> anyhow::Result<()> {
+                let delay = self.delay_millis.load(Ordering::SeqCst) as u64;
+                tokio::time::sleep(Duration::from_millis(delay)).await;
+                Ok(())
+            }
+        }
+
+        let delay_millis = Arc::new(AtomicI64::new(1000));
+        let breaker = Arc::new(CircuitBreakerSubscriber::new(
+            "test_circuit_breaker_cancelled",
+            SlowCallback {
+                delay_millis: delay_millis.clone(),
+            },
+            ShortCircuitPolicy::Drop,
+            1,
+            Duration::from_millis(100),
+        ));
+
+        struct BreakerRef(Arc<CircuitBreakerSubscriber<i32>>);
+
+        #[async_trait]
+        impl Subscriber for BreakerRef {
+            type Input = i32;
+
+            async fn on_event(&self, event: &Event<Self::Input>) {
+                self.0.on_event(event).await
+            }
+        }
+
+        let runtime = create_runtime(2, "test_circuit_breaker_cancelled");
+        let event_bus = EventBus::with_handle_timeout(
+            runtime.clone(),
+            "test_circuit_breaker_cancelled".to_string(),
+            1,
+            Some(Duration::from_millis(100)),
+        );
+        event_bus.subscribe(BreakerRef(breaker.clone()));
+        let handled = || {
+            TOTAL_EVENT_BUS_EVENT_HANDLED_SIZE
+                .with_label_values(&["test_circuit_breaker_cancelled"])
+                .get()
+        };
+        let publish = |data: i32| {
+            let bus = event_bus.clone();
+            runtime.block_on(async move { bus.publish(data.into()).await })
+        };
+
+        // the timeout is regarded as the failure
+        publish(0)?;
+        awaitility::at_most(Duration::from_secs(1)).until(|| handled() == 1);
+        assert_eq!(CircuitState::Open, breaker.state());
+
+        // the cancelled trial reopens it rather than being stuck in the half-open
+        std::thread::sleep(Duration::from_millis(150));
+        publish(1)?;
+        awaitility::at_most(Duration::from_secs(1)).until(|| handled() == 2);
+        assert_eq!(CircuitState::Open, breaker.state());
+
+        // the next trial is passed after the cooldown, and closes it
+        delay_millis.store(0, Ordering::SeqCst);
+        std::thread::sleep(Duration::from_millis(150));
+        publish(2)?;
+        awaitility::at_most(Duration::from_secs(1)).until(|| handled() == 3);
+        assert_eq!(CircuitState::Closed, breaker.state());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_handler_span_linked_to_publisher() -> anyhow::Result<()> {
+        // record the (span, parent span) pairs
+        struct SpanParentRecorder {
+            records: Arc<parking_lot::Mutex<Vec<(String, Option<String>)>>>,
+        }
+
+        impl<S: tracing::Subscriber + for<'a> LookupSpan<'a>> Layer<S> for SpanParentRecorder {
+            fn on_new_span(&self, _attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+                if let Some(span) = ctx.span(id) {
+                    let parent = span.parent().map(|x| x.name().to_string());
+                    self.records.lock().push((span.name().to_string(), parent));
+                }
+            }
+        }
+
+        let records = Arc::new(parking_lot::Mutex::new(vec![]));
+        let dispatch =
+            tracing::Dispatch::new(tracing_subscriber::registry().with(SpanParentRecorder {
+                records: records.clone(),
+            }));
+        // scoped to this test rather than the process-global default. The publisher span
+        // is created on this thread and the handler span on the only runtime worker
+        let _guard = tracing::dispatcher::set_default(&dispatch);
+
+        struct NoopCallback;
+        #[async_trait]
+        impl Subscriber for NoopCallback {
+            type Input = String;
+
+            async fn on_event(&self, _event: &Event<Self::Input>) {}
+        }
+
+        let runtime = create_runtime(1, "test_span");
+        let worker_dispatch = dispatch.clone();
+        runtime.block_on(runtime.spawn(async move {
+            // the worker thread is owned by this test runtime, so its default is kept
+            std::mem::forget(tracing::dispatcher::set_default(&worker_dispatch));
+        }))?;
+        let event_bus = EventBus::new(runtime.clone(), "test_span".to_string(), 1usize);
+        event_bus.subscribe(NoopCallback);
+
+        let bus = event_bus.clone();
+        runtime.block_on(async move {
+            bus.publish("event".to_string().into())
+                .instrument(info_span!("publisher"))
+                .await
+        })?;
+
+        awaitility::at_most(Duration::from_secs(1)).until(|| {
+            records.lock().iter().any(|(name, parent)| {
+                name == "event_bus_handle" && parent.as_deref() == Some("publisher")
+            })
+        });
 
         Ok(())
     }
@@ -1,3 +1,4 @@
 pub mod awaittree;
 pub mod metric;
+pub mod slow_log;
 pub mod tracing;
@@ -0,0 +1,104 @@
+use crate::slow_log::PendingSlowLog;
+use hyper::body::HttpBody;
+use hyper::{Body, HeaderMap};
+use pin_project_lite::pin_project;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Instant;
+use tower::{Layer, Service};
+
+/// Reporting the slow request log attached by the handler once the response body has
+/// been written, so that the network write phase is involved.
+#[derive(Clone, Default)]
+pub struct SlowLogMiddlewareLayer;
+
+impl<S> Layer<S> for SlowLogMiddlewareLayer {
+    type Service = SlowLogMiddleware<S>;
+
+    fn layer(&self, service: S) -> Self::Service {
+        SlowLogMiddleware { inner: service }
+    }
+}
+
+#[derive(Clone)]
+pub struct SlowLogMiddleware<S> {
+    inner: S,
+}
+
+impl<S, B> Service<hyper::Request<Body>> for SlowLogMiddleware<S>
+where
+    S: Service<hyper::Request<Body>, Response = hyper::Response<B>> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = hyper::Response<TimedBody<B>>;
+    type Error = S::Error;
+
+    type Future = impl Future<Output = Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: hyper::Request<Body>) -> Self::Future {
+        // This is necessary because tonic internally uses `tower::buffer::Buffer`.
+        // See https://github.com/tower-rs/tower/issues/547#issuecomment-767629149
+        // for details on why this is necessary
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+
+        async move {
+            let mut response = inner.call(req).await?;
+            let pending = response.extensions_mut().remove::<PendingSlowLog>();
+            Ok(response.map(|body| TimedBody {
+                inner: body,
+                start: Instant::now(),
+                pending,
+            }))
+        }
+    }
+}
+
+pin_project! {
+    /// The grpc response body ends with the trailers, which is regarded as written then.
+    pub struct TimedBody<B> {
+        #[pin]
+        inner: B,
+        start: Instant,
+        pending: Option<PendingSlowLog>,
+    }
+}
+
+impl<B: HttpBody> HttpBody for TimedBody<B> {
+    type Data = B::Data;
+    type Error = B::Error;
+
+    fn poll_data(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        self.project().inner.poll_data(cx)
+    }
+
+    fn poll_trailers(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<HeaderMap>, Self::Error>> {
+        let this = self.project();
+        let trailers = this.inner.poll_trailers(cx);
+        if trailers.is_ready() {
+            if let Some(pending) = this.pending.take() {
+                pending.network_written(this.start.elapsed());
+            }
+        }
+        trailers
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> hyper::body::SizeHint {
+        self.inner.size_hint()
+    }
+}
@@ -20,26 +20,32 @@ use crate::app::{
     ReadingIndexViewContext, ReadingOptions, ReadingViewContext, RemoteStorageConfig,
     ReportBlocksContext, RequireBufferContext, WritingViewContext,
 };
+use crate::block_id::BlockIdLayout;
+use crate::busy_score::BUSY_SCORE_METADATA_KEY;
 use crate::constant::StatusCode;
 use crate::error::WorkerError;
 use crate::grpc::protobuf::uniffle::shuffle_server_server::ShuffleServer;
 use crate::grpc::protobuf::uniffle::{
-    AppHeartBeatRequest, AppHeartBeatResponse, FinishShuffleRequest, FinishShuffleResponse,
-    GetLocalShuffleDataRequest, GetLocalShuffleDataResponse, GetLocalShuffleIndexRequest,
-    GetLocalShuffleIndexResponse, GetMemoryShuffleDataRequest, GetMemoryShuffleDataResponse,
+    AppHeartBeatRequest, AppHeartBeatResponse, ChecksumType, FinishShuffleRequest,
+    FinishShuffleResponse, GetLocalShuffleDataRequest, GetLocalShuffleDataResponse,
+    GetLocalShuffleIndexRequest, GetLocalShuffleIndexResponse, GetMemoryShuffleDataRequest,
+    GetMemoryShuffleDataResponse, GetMergedShuffleDataRequest, GetMergedShuffleDataResponse,
     GetShuffleResultForMultiPartRequest, GetShuffleResultForMultiPartResponse,
-    GetShuffleResultRequest, GetShuffleResultResponse, ReportShuffleResultRequest,
-    ReportShuffleResultResponse, RequireBufferRequest, RequireBufferResponse,
-    SendShuffleDataRequest, SendShuffleDataResponse, ShuffleCommitRequest, ShuffleCommitResponse,
-    ShuffleRegisterRequest, ShuffleRegisterResponse, ShuffleUnregisterByAppIdRequest,
-    ShuffleUnregisterByAppIdResponse, ShuffleUnregisterRequest, ShuffleUnregisterResponse,
+    GetShuffleResultRequest, GetShuffleResultResponse, ReassignPartitionRequest,
+    ReassignPartitionResponse, ReportShuffleResultRequest, ReportShuffleResultResponse,
+    RequireBufferRequest, RequireBufferResponse, SendShuffleDataRequest, SendShuffleDataResponse,
+    ShuffleCommitRequest, ShuffleCommitResponse, ShuffleDataBlockSource, ShuffleRegisterRequest,
+    ShuffleRegisterResponse, ShuffleUnregisterByAppIdRequest, ShuffleUnregisterByAppIdResponse,
+    ShuffleUnregisterRequest, ShuffleUnregisterResponse,
 };
 use crate::metric::{
     GRPC_BUFFER_REQUIRE_PROCESS_TIME, GRPC_GET_LOCALFILE_DATA_PROCESS_TIME,
     GRPC_GET_MEMORY_DATA_FREEZE_PROCESS_TIME, GRPC_GET_MEMORY_DATA_PROCESS_TIME,
-    GRPC_GET_MEMORY_DATA_TRANSPORT_TIME, GRPC_SEND_DATA_PROCESS_TIME,
-    GRPC_SEND_DATA_TRANSPORT_TIME,
+    GRPC_GET_MEMORY_DATA_TRANSPORT_TIME, GRPC_GET_MERGED_DATA_PROCESS_TIME,
+    GRPC_SEND_DATA_PROCESS_TIME, GRPC_SEND_DATA_TRANSPORT_TIME, TOTAL_BLOCK_CHECKSUM_MISMATCHED,
 };
+use crate::slow_log;
+use crate::slow_log::{Phase, RequestTimingContext};
 use crate::store::{PartitionedData, ResponseDataIndex};
 use crate::util;
 use await_tree::InstrumentAwait;
@@ -50,6 +56,9 @@ use fastrace::future::FutureExt;
 use fastrace::trace;
 use log::{debug, error, info, warn};
 use std::collections::HashMap;
+use std::future::Future;
+use std::time::Duration;
+use tonic::metadata::MetadataValue;
 use tonic::{Request, Response, Status};
 
 /// Use the maximum value for HTTP/2 connection window size to avoid deadlock among multiplexed
@@ -59,6 +68,9 @@ pub const MAX_CONNECTION_WINDOW_SIZE: u32 = (1 << 31) - 1;
 /// as we don't rely on this for back-pressure.
 pub const STREAM_WINDOW_SIZE: u32 = 32 * 1024 * 1024; // 32 MB
 
+/// The metadata key of the split block sequence of the reassigned partition in the read responses.
+pub const SPLIT_BLOCK_SEQUENCE_METADATA_KEY: &str = "x-riffle-split-block-sequence";
+
 pub struct DefaultShuffleServer {
     app_manager_ref: AppManagerRef,
 }
@@ -67,116 +79,46 @@ impl DefaultShuffleServer {
     pub fn from(app_manager_ref: AppManagerRef) -> DefaultShuffleServer {
         DefaultShuffleServer { app_manager_ref }
     }
-}
-
-#[tonic::async_trait]
-impl ShuffleServer for DefaultShuffleServer {
-    async fn register_shuffle(
-        &self,
-        request: Request<ShuffleRegisterRequest>,
-    ) -> Result<Response<ShuffleRegisterResponse>, Status> {
-        let inner = request.into_inner();
-        // todo: fast fail when hdfs is enabled but empty remote storage info.
-        let remote_storage_info = inner.remote_storage.map(|x| RemoteStorageConfig::from(x));
-        // todo: add more options: huge_partition_threshold. and so on...
-        let app_config_option = AppConfigOptions::new(
-            DataDistribution::LOCAL_ORDER,
-            inner.max_concurrency_per_partition_to_write,
-            remote_storage_info,
-        );
 
-        let status = match self.app_manager_ref.register(
-            inner.app_id.clone(),
-            inner.shuffle_id,
-            app_config_option,
-        ) {
-            Err(e) => {
-                error!(
-                    "Errors on registering for app:{:?}, shuffle:{:?}. error:{:#?}",
-                    &inner.app_id, &inner.shuffle_id, e
-                );
-                StatusCode::INTERNAL_ERROR
-            }
-            _ => StatusCode::SUCCESS,
-        }
-        .into();
-        Ok(Response::new(ShuffleRegisterResponse {
-            status,
-            ret_msg: "".to_string(),
-        }))
+    /// Attach the busy score in the metadata for the client side balancing.
+    fn attach_busy_score<T>(&self, mut response: Response<T>) -> Response<T> {
+        let score = self.app_manager_ref.busy_score();
+        response
+            .metadata_mut()
+            .insert(BUSY_SCORE_METADATA_KEY, MetadataValue::from(score));
+        response
     }
 
-    async fn unregister_shuffle(
+    /// Attach the split point for the reassigned partition, the client should read the
+    /// blocks beyond it from the other servers.
+    fn attach_partition_split<T>(
         &self,
-        request: Request<ShuffleUnregisterRequest>,
-    ) -> Result<Response<ShuffleUnregisterResponse>, Status> {
-        let request = request.into_inner();
-        let shuffle_id = request.shuffle_id;
-        let app_id = request.app_id;
-
-        info!(
-            "Accepted unregister shuffle info for [app:{:?}, shuffle_id:{:?}]",
-            &app_id, shuffle_id
-        );
-        let status_code = self
-            .app_manager_ref
-            .unregister_shuffle(app_id.clone(), shuffle_id)
-            .await
-            .map_or_else(
-                |e| {
-                    warn!(
-                        "Errors on unregister shuffle for appId:{}. shuffleId:{}. err: {:#?}",
-                        &app_id, shuffle_id, e
-                    );
-                    StatusCode::INTERNAL_ERROR
-                },
-                |_| StatusCode::SUCCESS,
-            );
-
-        Ok(Response::new(ShuffleUnregisterResponse {
-            status: status_code.into(),
-            ret_msg: "".to_string(),
-        }))
-    }
-
-    // Once unregister app accepted, the data could be purged.
-    async fn unregister_shuffle_by_app_id(
-        &self,
-        request: Request<ShuffleUnregisterByAppIdRequest>,
-    ) -> Result<Response<ShuffleUnregisterByAppIdResponse>, Status> {
-        let request = request.into_inner();
-        let app_id = request.app_id;
-
-        info!("Accepted unregister app rpc. app_id: {:?}", &app_id);
-
-        let code = self
-            .app_manager_ref
-            .unregister_app(app_id.clone())
-            .await
-            .map_or_else(
-                |e| {
-                    warn!(
-                        "Errors on unregister shuffle for appId:{}. err: {:#?}",
-                        &app_id, e
-                    );
-                    StatusCode::INTERNAL_ERROR
-                },
-                |_| StatusCode::SUCCESS,
+        app_id: &str,
+        shuffle_id: i32,
+        partition_id: i32,
+        mut response: Response<T>,
+    ) -> Response<T> {
+        let split = self.app_manager_ref.get_app(app_id).and_then(|app| {
+            app.partition_split(&PartitionedUId::from(
+                app_id.to_string(),
+                shuffle_id,
+                partition_id,
+            ))
+        });
+        if let Some(split) = split {
+            response.metadata_mut().insert(
+                SPLIT_BLOCK_SEQUENCE_METADATA_KEY,
+                MetadataValue::from(split),
             );
-
-        Ok(Response::new(ShuffleUnregisterByAppIdResponse {
-            status: code.into(),
-            ret_msg: "".to_string(),
-        }))
+        }
+        response
     }
 
-    #[trace]
-    async fn send_shuffle_data(
+    async fn send_shuffle_data_internal(
         &self,
-        request: Request<SendShuffleDataRequest>,
+        req: SendShuffleDataRequest,
     ) -> Result<Response<SendShuffleDataResponse>, Status> {
         let timer = GRPC_SEND_DATA_PROCESS_TIME.start_timer();
-        let req = request.into_inner();
         GRPC_SEND_DATA_TRANSPORT_TIME
             .observe(((util::now_timestamp_as_millis() - req.timestamp as u128) / 1000) as f64);
 
@@ -194,6 +136,9 @@ impl ShuffleServer for DefaultShuffleServer {
             return Ok(Response::new(SendShuffleDataResponse {
                 status: StatusCode::NO_REGISTER.into(),
                 ret_msg: "The app is not found".to_string(),
+                write_sequence: 0,
+                mismatched_block_ids: vec![],
+                split_block_sequence: None,
             }));
         }
 
@@ -214,6 +159,9 @@ impl ShuffleServer for DefaultShuffleServer {
             return Ok(Response::new(SendShuffleDataResponse {
                 status: StatusCode::NO_BUFFER.into(),
                 ret_msg: "No such buffer ticket id, it may be discarded due to timeout".to_string(),
+                write_sequence: 0,
+                mismatched_block_ids: vec![],
+                split_block_sequence: None,
             }));
         }
         let required_len_with_ticket = release_result.unwrap();
@@ -227,8 +175,44 @@ impl ShuffleServer for DefaultShuffleServer {
             blocks.extend(data_blocks);
         }
 
+        // the blocks corrupted in the transport are rejected before admitted into the store
+        if req.checksum_type == i32::from(ChecksumType::Crc32) {
+            let mismatched_block_ids: Vec<i64> = blocks_map
+                .values()
+                .flatten()
+                .filter(|block| !block.is_crc32_matched())
+                .map(|block| block.block_id)
+                .collect();
+            if !mismatched_block_ids.is_empty() {
+                TOTAL_BLOCK_CHECKSUM_MISMATCHED.inc_by(mismatched_block_ids.len() as u64);
+                if let Err(e) = app.dec_allocated_from_budget(required_len_with_ticket) {
+                    warn!(
+                        "Errors on free allocated size: {:?} for app: {:?}. err: {:#?}",
+                        required_len_with_ticket, &app_id, e
+                    );
+                }
+                let err = WorkerError::BLOCK_CHECKSUM_MISMATCHED(mismatched_block_ids.clone());
+                error!(
+                    "Errors on verifying the checksum. app_id: {}, shuffle_id: {}, err: {}",
+                    &app_id, shuffle_id, &err
+                );
+                return Ok(Response::new(SendShuffleDataResponse {
+                    status: err.observe_status_code(),
+                    ret_msg: err.to_string(),
+                    write_sequence: 0,
+                    mismatched_block_ids,
+                    split_block_sequence: None,
+                }));
+            }
+        }
+        // finished on drop, even if the handler is cancelled by the client disconnection
+        let write_sequence_guard = app.next_write_sequence(shuffle_id);
+        let write_sequence = write_sequence_guard.sequence();
+
         let mut inserted_failure_occurs = false;
         let mut inserted_failure_error = None;
+        let mut inserted_failure_status: i32 = StatusCode::INTERNAL_ERROR.into();
+        let mut split_block_sequence = None;
         let mut inserted_total_size = 0;
 
         let insert_start = util::now_timestamp_as_millis();
@@ -244,15 +228,23 @@ impl ShuffleServer for DefaultShuffleServer {
                 shuffle_id,
                 partition_id,
             };
-            let ctx = WritingViewContext::from(uid, blocks);
+            let ctx =
+                WritingViewContext::from(uid, blocks).with_write_sequence(Some(write_sequence));
             let app_ref = app.clone();
-            let inserted = app_ref.insert(ctx).instrument_await(await_tree_msg).await;
+            let inserted = slow_log::record_phase(
+                Phase::StoreLookup,
+                app_ref.insert(ctx).instrument_await(await_tree_msg),
+            )
+            .await;
 
-            if inserted.is_err() {
+            if let Err(err) = &inserted {
+                inserted_failure_status = err.observe_status_code();
+                if let WorkerError::PARTITION_REASSIGNED(split) = err {
+                    split_block_sequence = Some(*split);
+                }
                 let err = format!(
                     "Errors on putting data. app_id: {}, err: {:?}",
-                    &app_id,
-                    inserted.err()
+                    &app_id, err
                 );
                 error!("{}", &err);
 
@@ -264,7 +256,9 @@ impl ShuffleServer for DefaultShuffleServer {
             let inserted_size = inserted.unwrap();
             inserted_total_size += inserted_size as i64;
         }
+        drop(write_sequence_guard);
 
+        slow_log::record_payload_size(inserted_total_size);
         let _ = app.move_allocated_used_from_budget(inserted_total_size);
 
         let unused_allocated_size = required_len_with_ticket - inserted_total_size;
@@ -281,8 +275,11 @@ impl ShuffleServer for DefaultShuffleServer {
 
         if inserted_failure_occurs {
             return Ok(Response::new(SendShuffleDataResponse {
-                status: StatusCode::INTERNAL_ERROR.into(),
+                status: inserted_failure_status,
                 ret_msg: inserted_failure_error.unwrap(),
+                write_sequence: 0,
+                mismatched_block_ids: vec![],
+                split_block_sequence,
             }));
         }
 
@@ -290,14 +287,16 @@ impl ShuffleServer for DefaultShuffleServer {
         Ok(Response::new(SendShuffleDataResponse {
             status: StatusCode::SUCCESS.into(),
             ret_msg: "".to_string(),
+            write_sequence: write_sequence as i64,
+            mismatched_block_ids: vec![],
+            split_block_sequence: None,
         }))
     }
 
-    async fn get_local_shuffle_index(
+    async fn get_local_shuffle_index_internal(
         &self,
-        request: Request<GetLocalShuffleIndexRequest>,
+        req: GetLocalShuffleIndexRequest,
     ) -> Result<Response<GetLocalShuffleIndexResponse>, Status> {
-        let req = request.into_inner();
         let app_id = req.app_id;
         let shuffle_id: i32 = req.shuffle_id;
         let partition_id = req.partition_id;
@@ -319,26 +318,27 @@ impl ShuffleServer for DefaultShuffleServer {
         let app = app_option.unwrap();
 
         let partition_id = PartitionedUId::from(app_id.to_string(), shuffle_id, partition_id);
-        let data_index_wrapper = app
-            .list_index(ReadingIndexViewContext {
+        let data_index_wrapper = slow_log::record_phase(
+            Phase::StoreLookup,
+            app.list_index(ReadingIndexViewContext {
                 partition_id: partition_id.clone(),
             })
             .instrument_await(format!(
                 "get index from localfile. uid: {:?}",
                 &partition_id
-            ))
-            .await;
+            )),
+        )
+        .await;
 
-        if data_index_wrapper.is_err() {
-            let error_msg = data_index_wrapper.err();
+        if let Err(err) = &data_index_wrapper {
             error!(
                 "Errors on getting localfile data index for app:[{}], error: {:?}",
-                &app_id, error_msg
+                &app_id, err
             );
             return Ok(Response::new(GetLocalShuffleIndexResponse {
                 index_data: Default::default(),
-                status: StatusCode::INTERNAL_ERROR.into(),
-                ret_msg: format!("{:?}", error_msg),
+                status: err.observe_status_code(),
+                ret_msg: format!("{:?}", err),
                 data_file_len: 0,
             }));
         }
@@ -355,12 +355,11 @@ impl ShuffleServer for DefaultShuffleServer {
         }
     }
 
-    async fn get_local_shuffle_data(
+    async fn get_local_shuffle_data_internal(
         &self,
-        request: Request<GetLocalShuffleDataRequest>,
+        req: GetLocalShuffleDataRequest,
     ) -> Result<Response<GetLocalShuffleDataResponse>, Status> {
         let timer = GRPC_GET_LOCALFILE_DATA_PROCESS_TIME.start_timer();
-        let req = request.into_inner();
         let app_id = req.app_id;
         let shuffle_id: i32 = req.shuffle_id;
         let partition_id = req.partition_id;
@@ -383,47 +382,52 @@ impl ShuffleServer for DefaultShuffleServer {
             shuffle_id,
             partition_id,
         };
-        let data_fetched_result = app
-            .unwrap()
-            .select(ReadingViewContext {
-                uid: partition_id.clone(),
-                reading_options: ReadingOptions::FILE_OFFSET_AND_LEN(req.offset, req.length as i64),
-                serialized_expected_task_ids_bitmap: Default::default(),
-            })
-            .instrument_await(format!(
-                "select data from localfile. uid: {:?}",
-                &partition_id
-            ))
-            .await;
-
-        if data_fetched_result.is_err() {
-            let err_msg = data_fetched_result.err();
+        let data_fetched_result = slow_log::record_phase(
+            Phase::StoreLookup,
+            app.unwrap()
+                .select(ReadingViewContext {
+                    uid: partition_id.clone(),
+                    reading_options: ReadingOptions::FILE_OFFSET_AND_LEN(
+                        req.offset,
+                        req.length as i64,
+                    ),
+                    serialized_expected_task_ids_bitmap: Default::default(),
+                })
+                .instrument_await(format!(
+                    "select data from localfile. uid: {:?}",
+                    &partition_id
+                )),
+        )
+        .await;
+
+        if let Err(err) = &data_fetched_result {
             error!(
                 "Errors on getting localfile index for app:[{}], error: {:?}",
-                &app_id, err_msg
+                &app_id, err
             );
             return Ok(Response::new(GetLocalShuffleDataResponse {
                 data: Default::default(),
-                status: StatusCode::INTERNAL_ERROR.into(),
-                ret_msg: format!("{:?}", err_msg),
+                status: err.observe_status_code(),
+                ret_msg: format!("{:?}", err),
             }));
         }
 
         timer.observe_duration();
 
+        let data = data_fetched_result.unwrap().from_local();
+        slow_log::record_payload_size(data.len() as i64);
         Ok(Response::new(GetLocalShuffleDataResponse {
-            data: data_fetched_result.unwrap().from_local(),
+            data,
             status: StatusCode::SUCCESS.into(),
             ret_msg: "".to_string(),
         }))
     }
 
-    async fn get_memory_shuffle_data(
+    async fn get_memory_shuffle_data_internal(
         &self,
-        request: Request<GetMemoryShuffleDataRequest>,
+        req: GetMemoryShuffleDataRequest,
     ) -> Result<Response<GetMemoryShuffleDataResponse>, Status> {
         let timer = GRPC_GET_MEMORY_DATA_PROCESS_TIME.start_timer();
-        let req = request.into_inner();
         let app_id = req.app_id;
         let shuffle_id: i32 = req.shuffle_id;
         let partition_id = req.partition_id;
@@ -461,30 +465,31 @@ impl ShuffleServer for DefaultShuffleServer {
                 None
             };
 
-        let data_fetched_result = app
-            .unwrap()
-            .select(ReadingViewContext {
-                uid: partition_id.clone(),
-                reading_options: ReadingOptions::MEMORY_LAST_BLOCK_ID_AND_MAX_SIZE(
-                    req.last_block_id,
-                    req.read_buffer_size as i64,
-                ),
-                serialized_expected_task_ids_bitmap,
-            })
-            .instrument_await(format!("select data from memory. uid: {:?}", &partition_id))
-            .await;
-
-        if data_fetched_result.is_err() {
-            let error_msg = data_fetched_result.err();
+        let data_fetched_result = slow_log::record_phase(
+            Phase::StoreLookup,
+            app.unwrap()
+                .select(ReadingViewContext {
+                    uid: partition_id.clone(),
+                    reading_options: ReadingOptions::MEMORY_LAST_BLOCK_ID_AND_MAX_SIZE(
+                        req.last_block_id,
+                        req.read_buffer_size as i64,
+                    ),
+                    serialized_expected_task_ids_bitmap,
+                })
+                .instrument_await(format!("select data from memory. uid: {:?}", &partition_id)),
+        )
+        .await;
+
+        if let Err(err) = &data_fetched_result {
             error!(
                 "Errors on getting data from memory for [{}], error: {:?}",
-                &app_id, error_msg
+                &app_id, err
             );
             return Ok(Response::new(GetMemoryShuffleDataResponse {
                 shuffle_data_block_segments: vec![],
                 data: Default::default(),
-                status: StatusCode::INTERNAL_ERROR.into(),
-                ret_msg: format!("{:?}", error_msg),
+                status: err.observe_status_code(),
+                ret_msg: format!("{:?}", err),
             }));
         }
 
@@ -492,6 +497,7 @@ impl ShuffleServer for DefaultShuffleServer {
         let data = data_fetched_result.unwrap().from_memory();
         let bytes = data.data.freeze();
         freeze_timer.observe_duration();
+        slow_log::record_payload_size(bytes.len() as i64);
 
         timer.observe_duration();
 
@@ -507,16 +513,342 @@ impl ShuffleServer for DefaultShuffleServer {
         }))
     }
 
+    async fn get_merged_shuffle_data_internal(
+        &self,
+        req: GetMergedShuffleDataRequest,
+    ) -> Result<Response<GetMergedShuffleDataResponse>, Status> {
+        let timer = GRPC_GET_MERGED_DATA_PROCESS_TIME.start_timer();
+        let app_id = req.app_id;
+
+        let app = self.app_manager_ref.get_app(&app_id);
+        if app.is_none() {
+            return Ok(Response::new(GetMergedShuffleDataResponse {
+                status: StatusCode::NO_REGISTER.into(),
+                ret_msg: "No such app in this shuffle server".to_string(),
+                ..Default::default()
+            }));
+        }
+
+        let uid = PartitionedUId {
+            app_id: app_id.to_string(),
+            shuffle_id: req.shuffle_id,
+            partition_id: req.partition_id,
+        };
+
+        let serialized_expected_task_ids_bitmap =
+            if !req.serialized_expected_task_ids_bitmap.is_empty() {
+                match Treemap::deserialize(&req.serialized_expected_task_ids_bitmap) {
+                    Ok(filter) => Some(filter),
+                    Err(e) => {
+                        error!("Failed to deserialize: {}", e);
+                        None
+                    }
+                }
+            } else {
+                None
+            };
+
+        let data_fetched_result = slow_log::record_phase(
+            Phase::StoreLookup,
+            app.unwrap()
+                .select_merged(&uid, serialized_expected_task_ids_bitmap)
+                .instrument_await(format!("select merged data. uid: {:?}", &uid)),
+        )
+        .await;
+
+        let data = match data_fetched_result {
+            Err(err) => {
+                error!(
+                    "Errors on getting merged data for [{}], error: {:?}",
+                    &app_id, err
+                );
+                return Ok(Response::new(GetMergedShuffleDataResponse {
+                    status: err.observe_status_code(),
+                    ret_msg: format!("{:?}", err),
+                    ..Default::default()
+                }));
+            }
+            Ok(data) => data,
+        };
+
+        let bytes = data.data.freeze();
+        slow_log::record_payload_size(bytes.len() as i64);
+        timer.observe_duration();
+
+        Ok(Response::new(GetMergedShuffleDataResponse {
+            shuffle_data_block_segments: data
+                .shuffle_data_block_segments
+                .into_iter()
+                .map(|x| x.into())
+                .collect(),
+            block_sources: data
+                .block_sources
+                .into_iter()
+                .map(|x| Into::<ShuffleDataBlockSource>::into(x) as i32)
+                .collect(),
+            data: bytes,
+            flush_sequence: data.flush_sequence as i64,
+            status: StatusCode::SUCCESS.into(),
+            ret_msg: "".to_string(),
+        }))
+    }
+}
+
+/// The slow log is reported by the layer after the response is written, which has the
+/// network write phase. The failed one is reported on dropped.
+async fn observe_until_written<T, F>(
+    ctx: RequestTimingContext,
+    fut: F,
+) -> Result<Response<T>, Status>
+where
+    F: Future<Output = Result<Response<T>, Status>>,
+{
+    let (response, pending) = slow_log::observe_pending(ctx, fut).await;
+    response.map(|mut response| {
+        response.extensions_mut().insert(pending);
+        response
+    })
+}
+
+#[tonic::async_trait]
+impl ShuffleServer for DefaultShuffleServer {
+    async fn register_shuffle(
+        &self,
+        request: Request<ShuffleRegisterRequest>,
+    ) -> Result<Response<ShuffleRegisterResponse>, Status> {
+        let inner = request.into_inner();
+        // todo: fast fail when hdfs is enabled but empty remote storage info.
+        let remote_storage_info = inner.remote_storage.map(|x| RemoteStorageConfig::from(x));
+        // todo: add more options: huge_partition_threshold. and so on...
+        let mut app_config_option = AppConfigOptions::new(
+            DataDistribution::LOCAL_ORDER,
+            inner.max_concurrency_per_partition_to_write,
+            remote_storage_info,
+        );
+        app_config_option.block_id_layout = inner.block_id_layout.map(BlockIdLayout::from);
+
+        let (status, ret_msg) = match self.app_manager_ref.register(
+            inner.app_id.clone(),
+            inner.shuffle_id,
+            app_config_option,
+        ) {
+            Err(e) => {
+                error!(
+                    "Errors on registering for app:{:?}, shuffle:{:?}. error:{:#?}",
+                    &inner.app_id, &inner.shuffle_id, e
+                );
+                let status = match e.downcast_ref::<WorkerError>() {
+                    Some(error) => error.observe_status_code(),
+                    _ => StatusCode::INTERNAL_ERROR.into(),
+                };
+                (status, e.to_string())
+            }
+            _ => (StatusCode::SUCCESS.into(), "".to_string()),
+        };
+        Ok(Response::new(ShuffleRegisterResponse { status, ret_msg }))
+    }
+
+    async fn unregister_shuffle(
+        &self,
+        request: Request<ShuffleUnregisterRequest>,
+    ) -> Result<Response<ShuffleUnregisterResponse>, Status> {
+        let request = request.into_inner();
+        let shuffle_id = request.shuffle_id;
+        let app_id = request.app_id;
+
+        info!(
+            "Accepted unregister shuffle info for [app:{:?}, shuffle_id:{:?}]",
+            &app_id, shuffle_id
+        );
+        let status_code = self
+            .app_manager_ref
+            .unregister_shuffle(app_id.clone(), shuffle_id)
+            .await
+            .map_or_else(
+                |e| {
+                    warn!(
+                        "Errors on unregister shuffle for appId:{}. shuffleId:{}. err: {:#?}",
+                        &app_id, shuffle_id, e
+                    );
+                    StatusCode::INTERNAL_ERROR
+                },
+                |_| StatusCode::SUCCESS,
+            );
+
+        Ok(Response::new(ShuffleUnregisterResponse {
+            status: status_code.into(),
+            ret_msg: "".to_string(),
+        }))
+    }
+
+    // Once unregister app accepted, the data could be purged.
+    async fn unregister_shuffle_by_app_id(
+        &self,
+        request: Request<ShuffleUnregisterByAppIdRequest>,
+    ) -> Result<Response<ShuffleUnregisterByAppIdResponse>, Status> {
+        let request = request.into_inner();
+        let app_id = request.app_id;
+
+        info!("Accepted unregister app rpc. app_id: {:?}", &app_id);
+
+        let code = self
+            .app_manager_ref
+            .unregister_app(app_id.clone())
+            .await
+            .map_or_else(
+                |e| {
+                    warn!(
+                        "Errors on unregister shuffle for appId:{}. err: {:#?}",
+                        &app_id, e
+                    );
+                    StatusCode::INTERNAL_ERROR
+                },
+                |_| StatusCode::SUCCESS,
+            );
+
+        Ok(Response::new(ShuffleUnregisterByAppIdResponse {
+            status: code.into(),
+            ret_msg: "".to_string(),
+        }))
+    }
+
+    #[trace]
+    async fn send_shuffle_data(
+        &self,
+        request: Request<SendShuffleDataRequest>,
+    ) -> Result<Response<SendShuffleDataResponse>, Status> {
+        let req = request.into_inner();
+        let ctx = RequestTimingContext::new(
+            "send_shuffle_data",
+            &req.app_id,
+            req.shuffle_id,
+            -1,
+            req.timestamp,
+        );
+        observe_until_written(ctx, self.send_shuffle_data_internal(req))
+            .await
+            .map(|response| self.attach_busy_score(response))
+    }
+
+    async fn get_local_shuffle_index(
+        &self,
+        request: Request<GetLocalShuffleIndexRequest>,
+    ) -> Result<Response<GetLocalShuffleIndexResponse>, Status> {
+        let req = request.into_inner();
+        let (app_id, shuffle_id, partition_id) =
+            (req.app_id.clone(), req.shuffle_id, req.partition_id);
+        let ctx = RequestTimingContext::new(
+            "get_local_shuffle_index",
+            &req.app_id,
+            req.shuffle_id,
+            req.partition_id,
+            0,
+        );
+        observe_until_written(ctx, self.get_local_shuffle_index_internal(req))
+            .await
+            .map(|response| {
+                self.attach_partition_split(&app_id, shuffle_id, partition_id, response)
+            })
+    }
+
+    async fn get_local_shuffle_data(
+        &self,
+        request: Request<GetLocalShuffleDataRequest>,
+    ) -> Result<Response<GetLocalShuffleDataResponse>, Status> {
+        let req = request.into_inner();
+        let ctx = RequestTimingContext::new(
+            "get_local_shuffle_data",
+            &req.app_id,
+            req.shuffle_id,
+            req.partition_id,
+            req.timestamp,
+        );
+        observe_until_written(ctx, self.get_local_shuffle_data_internal(req)).await
+    }
+
+    async fn get_memory_shuffle_data(
+        &self,
+        request: Request<GetMemoryShuffleDataRequest>,
+    ) -> Result<Response<GetMemoryShuffleDataResponse>, Status> {
+        let req = request.into_inner();
+        let (app_id, shuffle_id, partition_id) =
+            (req.app_id.clone(), req.shuffle_id, req.partition_id);
+        let ctx = RequestTimingContext::new(
+            "get_memory_shuffle_data",
+            &req.app_id,
+            req.shuffle_id,
+            req.partition_id,
+            req.timestamp,
+        );
+        observe_until_written(ctx, self.get_memory_shuffle_data_internal(req))
+            .await
+            .map(|response| {
+                self.attach_partition_split(&app_id, shuffle_id, partition_id, response)
+            })
+    }
+
+    async fn get_merged_shuffle_data(
+        &self,
+        request: Request<GetMergedShuffleDataRequest>,
+    ) -> Result<Response<GetMergedShuffleDataResponse>, Status> {
+        let req = request.into_inner();
+        let ctx = RequestTimingContext::new(
+            "get_merged_shuffle_data",
+            &req.app_id,
+            req.shuffle_id,
+            req.partition_id,
+            req.timestamp,
+        );
+        observe_until_written(ctx, self.get_merged_shuffle_data_internal(req)).await
+    }
+
     async fn commit_shuffle_task(
         &self,
-        _request: Request<ShuffleCommitRequest>,
+        request: Request<ShuffleCommitRequest>,
     ) -> Result<Response<ShuffleCommitResponse>, Status> {
-        warn!("It has not been supported of committing shuffle data");
-        Ok(Response::new(ShuffleCommitResponse {
-            commit_count: 0,
-            status: StatusCode::INTERNAL_ERROR.into(),
-            ret_msg: "Not supported".to_string(),
-        }))
+        let req = request.into_inner();
+        let app_id = req.app_id;
+        let shuffle_id = req.shuffle_id;
+
+        let app = match self.app_manager_ref.get_app(&app_id) {
+            Some(app) => app,
+            _ => {
+                return Ok(Response::new(ShuffleCommitResponse {
+                    commit_count: 0,
+                    status: StatusCode::NO_REGISTER.into(),
+                    ret_msg: "No such app in this shuffle server".to_string(),
+                }));
+            }
+        };
+
+        // the flush barrier, the data written before are readable from the persistent
+        // store once committed
+        let timeout = Duration::from_millis(req.timeout_ms.max(0) as u64);
+        let flushed = app
+            .wait_until_flushed(shuffle_id, req.write_sequence.max(0) as u64, timeout)
+            .instrument_await(format!(
+                "waiting the write sequence: {} flushed. appId: {}. shuffleId: {}",
+                req.write_sequence, &app_id, shuffle_id
+            ))
+            .await;
+        match flushed {
+            Ok(_) => Ok(Response::new(ShuffleCommitResponse {
+                commit_count: 1,
+                status: StatusCode::SUCCESS.into(),
+                ret_msg: "".to_string(),
+            })),
+            Err(err) => {
+                warn!(
+                    "Errors on committing shuffle data. app_id: {}, shuffle_id: {}, err: {}",
+                    &app_id, shuffle_id, err
+                );
+                Ok(Response::new(ShuffleCommitResponse {
+                    commit_count: 0,
+                    status: err.observe_status_code(),
+                    ret_msg: err.to_string(),
+                }))
+            }
+        }
     }
 
     async fn report_shuffle_result(
@@ -555,10 +887,14 @@ impl ShuffleServer for DefaultShuffleServer {
 
             match app.report_block_ids(ctx).await {
                 Err(e) => {
+                    let status = match e.downcast_ref::<WorkerError>() {
+                        Some(error) => error.observe_status_code(),
+                        _ => StatusCode::INTERNAL_ERROR.into(),
+                    };
                     return Ok(Response::new(ReportShuffleResultResponse {
-                        status: StatusCode::INTERNAL_ERROR.into(),
+                        status,
                         ret_msg: e.to_string(),
-                    }))
+                    }));
                 }
                 _ => (),
             }
@@ -585,17 +921,21 @@ impl ShuffleServer for DefaultShuffleServer {
                 status: StatusCode::NO_REGISTER.into(),
                 ret_msg: "No such app in this shuffle server".to_string(),
                 serialized_bitmap: Default::default(),
+                split_block_sequence: None,
             }));
         }
 
+        let app = app.unwrap();
         let partition_id = PartitionedUId {
             app_id: app_id.to_string(),
             shuffle_id,
             partition_id,
         };
-        let block_ids_result = app.unwrap().get_block_ids(GetBlocksContext {
-            uid: partition_id.clone(),
-        });
+        let block_ids_result = app
+            .get_block_ids(GetBlocksContext {
+                uid: partition_id.clone(),
+            })
+            .await;
 
         if block_ids_result.is_err() {
             let err_msg = block_ids_result.err();
@@ -607,6 +947,7 @@ impl ShuffleServer for DefaultShuffleServer {
                 status: StatusCode::INTERNAL_ERROR.into(),
                 ret_msg: format!("{:?}", err_msg),
                 serialized_bitmap: Default::default(),
+                split_block_sequence: None,
             }));
         }
 
@@ -614,6 +955,7 @@ impl ShuffleServer for DefaultShuffleServer {
             status: StatusCode::SUCCESS.into(),
             ret_msg: "".to_string(),
             serialized_bitmap: block_ids_result.unwrap(),
+            split_block_sequence: app.partition_split(&partition_id),
         }))
     }
 
@@ -641,13 +983,15 @@ impl ShuffleServer for DefaultShuffleServer {
 
         let mut bitmap_result = Treemap::default();
         for partition_id in req.partitions {
-            let block_ids_bitmap_result = app.get_block_ids_bitmap(GetBlocksContext {
-                uid: PartitionedUId {
-                    app_id: app_id.clone(),
-                    shuffle_id,
-                    partition_id,
-                },
-            });
+            let block_ids_bitmap_result = app
+                .get_block_ids_bitmap(GetBlocksContext {
+                    uid: PartitionedUId {
+                        app_id: app_id.clone(),
+                        shuffle_id,
+                        partition_id,
+                    },
+                })
+                .await;
             if block_ids_bitmap_result.is_err() {
                 let err_msg = block_ids_bitmap_result.err();
                 error!(
@@ -734,11 +1078,11 @@ impl ShuffleServer for DefaultShuffleServer {
 
         timer.observe_duration();
 
-        Ok(Response::new(RequireBufferResponse {
+        Ok(self.attach_busy_score(Response::new(RequireBufferResponse {
             require_buffer_id: res.1,
             status: res.0.into(),
             ret_msg: res.2,
-        }))
+        })))
     }
 
     async fn app_heartbeat(
@@ -764,4 +1108,43 @@ impl ShuffleServer for DefaultShuffleServer {
             ret_msg: "".to_string(),
         }))
     }
+
+    async fn reassign_partition(
+        &self,
+        request: Request<ReassignPartitionRequest>,
+    ) -> Result<Response<ReassignPartitionResponse>, Status> {
+        let req = request.into_inner();
+        let app_id = req.app_id;
+
+        let app = match self.app_manager_ref.get_app(&app_id) {
+            Some(app) => app,
+            _ => {
+                return Ok(Response::new(ReassignPartitionResponse {
+                    status: StatusCode::NO_REGISTER.into(),
+                    ret_msg: "No such app in this shuffle server".to_string(),
+                    split_block_sequence: 0,
+                }));
+            }
+        };
+
+        let uid = PartitionedUId::from(app_id.to_string(), req.shuffle_id, req.partition_id);
+        match app.split_partition(&uid, req.split_block_sequence) {
+            Ok(split) => Ok(Response::new(ReassignPartitionResponse {
+                status: StatusCode::SUCCESS.into(),
+                ret_msg: "".to_string(),
+                split_block_sequence: split,
+            })),
+            Err(e) => {
+                warn!(
+                    "Errors on reassigning the partition: {:?}. err: {:#?}",
+                    &uid, e
+                );
+                Ok(Response::new(ReassignPartitionResponse {
+                    status: StatusCode::INVALID_REQUEST.into(),
+                    ret_msg: e.to_string(),
+                    split_block_sequence: 0,
+                }))
+            }
+        }
+    }
 }
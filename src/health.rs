@@ -0,0 +1,230 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::util;
+use async_trait::async_trait;
+use dashmap::DashMap;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+pub static HEALTH_REGISTRY: Lazy<HealthRegistry> = Lazy::new(Default::default);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum HealthStatus {
+    Healthy,
+    Degraded,
+    Unhealthy,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ComponentHealth {
+    pub name: String,
+    pub status: HealthStatus,
+    // the unhealthy non-critical component (like one of disks) only degrades the overall status
+    pub critical: bool,
+    pub details: serde_json::Value,
+}
+
+impl ComponentHealth {
+    pub fn new(
+        name: impl Into<String>,
+        status: HealthStatus,
+        critical: bool,
+        details: serde_json::Value,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            status,
+            critical,
+            details,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HealthReport {
+    pub status: HealthStatus,
+    pub components: Vec<ComponentHealth>,
+}
+
+impl HealthReport {
+    pub fn from(components: Vec<ComponentHealth>) -> Self {
+        let status = components
+            .iter()
+            .map(|x| match (x.status, x.critical) {
+                (HealthStatus::Unhealthy, false) => HealthStatus::Degraded,
+                (status, _) => status,
+            })
+            .max()
+            .unwrap_or(HealthStatus::Healthy);
+        Self { status, components }
+    }
+}
+
+/// The subsystems report their health by implementing this and registering into the registry.
+#[async_trait]
+pub trait HealthProvider: Send + Sync {
+    async fn component_health(&self) -> Vec<ComponentHealth>;
+}
+
+#[derive(Clone, Default)]
+pub struct HealthRegistry {
+    providers: Arc<DashMap<String, Arc<dyn HealthProvider>>>,
+}
+
+impl HealthRegistry {
+    /// The provider with the same name will be replaced.
+    pub fn register(&self, name: &str, provider: Arc<dyn HealthProvider>) {
+        self.providers.insert(name.to_string(), provider);
+    }
+
+    pub async fn report(&self) -> HealthReport {
+        let mut providers: Vec<_> = self
+            .providers
+            .iter()
+            .map(|x| (x.key().to_string(), x.value().clone()))
+            .collect();
+        providers.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let mut components = vec![];
+        for (_, provider) in providers {
+            components.extend(provider.component_health().await);
+        }
+        HealthReport::from(components)
+    }
+}
+
+/// Counting the events in the recent window with per-second buckets,
+/// like the memory allocation failures in the last minute.
+pub struct RecentEventCounter {
+    window_secs: u64,
+    buckets: Mutex<VecDeque<(u64, u64)>>,
+}
+
+impl RecentEventCounter {
+    pub fn new(window_secs: u64) -> Self {
+        Self {
+            window_secs,
+            buckets: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    pub fn record(&self) {
+        self.record_at(util::now_timestamp_as_sec());
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count_at(util::now_timestamp_as_sec())
+    }
+
+    fn record_at(&self, now: u64) {
+        let mut buckets = self.buckets.lock();
+        match buckets.back_mut() {
+            Some((sec, count)) if *sec == now => *count += 1,
+            _ => buckets.push_back((now, 1)),
+        }
+        self.evict(&mut buckets, now);
+    }
+
+    fn count_at(&self, now: u64) -> u64 {
+        let mut buckets = self.buckets.lock();
+        self.evict(&mut buckets, now);
+        buckets.iter().map(|(_, count)| count).sum()
+    }
+
+    fn evict(&self, buckets: &mut VecDeque<(u64, u64)>, now: u64) {
+        while let Some((sec, _)) = buckets.front() {
+            if sec + self.window_secs > now {
+                break;
+            }
+            buckets.pop_front();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::health::{
+        ComponentHealth, HealthProvider, HealthRegistry, HealthReport, HealthStatus,
+        RecentEventCounter,
+    };
+    use async_trait::async_trait;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_overall_status() {
+        let component = |status, critical| {
+            ComponentHealth::new("mock", status, critical, serde_json::Value::Null)
+        };
+
+        let report = HealthReport::from(vec![]);
+        assert_eq!(HealthStatus::Healthy, report.status);
+
+        let report = HealthReport::from(vec![
+            component(HealthStatus::Healthy, true),
+            component(HealthStatus::Unhealthy, false),
+        ]);
+        assert_eq!(HealthStatus::Degraded, report.status);
+
+        let report = HealthReport::from(vec![
+            component(HealthStatus::Degraded, false),
+            component(HealthStatus::Unhealthy, true),
+        ]);
+        assert_eq!(HealthStatus::Unhealthy, report.status);
+    }
+
+    #[tokio::test]
+    async fn test_registry() {
+        struct MockProvider(HealthStatus);
+        #[async_trait]
+        impl HealthProvider for MockProvider {
+            async fn component_health(&self) -> Vec<ComponentHealth> {
+                vec![ComponentHealth::new(
+                    "mock",
+                    self.0,
+                    true,
+                    serde_json::Value::Null,
+                )]
+            }
+        }
+
+        let registry = HealthRegistry::default();
+        registry.register("mock", Arc::new(MockProvider(HealthStatus::Unhealthy)));
+        assert_eq!(HealthStatus::Unhealthy, registry.report().await.status);
+
+        // replaced by the same name
+        registry.register("mock", Arc::new(MockProvider(HealthStatus::Healthy)));
+        let report = registry.report().await;
+        assert_eq!(HealthStatus::Healthy, report.status);
+        assert_eq!(1, report.components.len());
+    }
+
+    #[test]
+    fn test_recent_event_counter() {
+        let counter = RecentEventCounter::new(60);
+        counter.record_at(100);
+        counter.record_at(100);
+        counter.record_at(130);
+        assert_eq!(3, counter.count_at(130));
+        assert_eq!(1, counter.count_at(160));
+        assert_eq!(0, counter.count_at(190));
+    }
+}
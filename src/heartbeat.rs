@@ -1,14 +1,73 @@
 use crate::app::{AppManagerRef, SHUFFLE_SERVER_ID, SHUFFLE_SERVER_IP};
 use crate::config::Config;
 use crate::grpc::protobuf::uniffle::coordinator_server_client::CoordinatorServerClient;
-use crate::grpc::protobuf::uniffle::{ShuffleServerHeartBeatRequest, ShuffleServerId};
+use crate::grpc::protobuf::uniffle::storage_info::{StorageMedia, StorageStatus};
+use crate::grpc::protobuf::uniffle::{
+    ServerStatus, ShuffleServerHeartBeatRequest, ShuffleServerId, StatusCode, StorageInfo,
+};
+use crate::health::{
+    ComponentHealth, HealthProvider, HealthRegistry, HealthReport, HealthStatus, HEALTH_REGISTRY,
+};
+use crate::metric::TOTAL_COORDINATOR_REREGISTRATION;
 use crate::runtime::manager::RuntimeManager;
-use crate::util::get_local_ip;
-use log::info;
-use std::time::Duration;
-use tonic::transport::Channel;
+use crate::shutdown::{PHASE_UNREGISTER, SHUTDOWN_COORDINATOR};
+use crate::util::{get_local_ip, now_timestamp_as_sec};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use log::{info, warn};
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tonic::transport::{Channel, Endpoint};
 
 const DEFAULT_SHUFFLE_SERVER_TAG: &str = "ss_v4";
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(10);
+const MAX_RETRY_BACKOFF: Duration = Duration::from_secs(60);
+const REREGISTRATION_FAILURE_THRESHOLD: u32 = 3;
+
+struct CoordinatorHealthProvider {
+    start_timestamp: u64,
+    // 0 means there is no successful heartbeat yet
+    last_success_timestamp: AtomicU64,
+}
+
+impl CoordinatorHealthProvider {
+    fn new() -> Self {
+        Self {
+            start_timestamp: now_timestamp_as_sec(),
+            last_success_timestamp: Default::default(),
+        }
+    }
+
+    fn mark_success(&self) {
+        self.last_success_timestamp
+            .store(now_timestamp_as_sec(), Ordering::SeqCst);
+    }
+}
+
+#[async_trait]
+impl HealthProvider for CoordinatorHealthProvider {
+    async fn component_health(&self) -> Vec<ComponentHealth> {
+        let last_success = self.last_success_timestamp.load(Ordering::SeqCst);
+        let age = now_timestamp_as_sec().saturating_sub(last_success.max(self.start_timestamp));
+        // tolerate several lost heartbeats
+        let status = if age > 3 * HEARTBEAT_INTERVAL.as_secs() {
+            HealthStatus::Degraded
+        } else {
+            HealthStatus::Healthy
+        };
+        let last_success_age = if last_success > 0 { Some(age) } else { None };
+        vec![ComponentHealth::new(
+            "coordinator",
+            status,
+            false,
+            serde_json::json!({
+                "last_successful_heartbeat_age_sec": last_success_age,
+            }),
+        )]
+    }
+}
 
 pub struct HeartbeatTask;
 
@@ -20,64 +79,522 @@ impl HeartbeatTask {
         let grpc_port = config.grpc_port;
         let urpc_port = config.urpc_port.unwrap_or(0);
 
-        runtime_manager.default_runtime.spawn(async move {
-            let ip = SHUFFLE_SERVER_IP.get().unwrap().to_string();
-            info!("machine ip: {}", &ip);
-
-            let shuffle_server_id = ShuffleServerId {
-                id: SHUFFLE_SERVER_ID.get().unwrap().to_string(),
-                ip,
-                port: grpc_port,
-                netty_port: urpc_port,
-            };
+        let connect_timeout = config.coordinator.connect_timeout().unwrap();
+        let retry_interval = config.coordinator.retry_interval().unwrap();
+
+        let health_provider = Arc::new(CoordinatorHealthProvider::new());
+        HEALTH_REGISTRY.register("coordinator", health_provider.clone());
+
+        let ip = SHUFFLE_SERVER_IP.get().unwrap().to_string();
+        info!("machine ip: {}", &ip);
+        let shuffle_server_id = ShuffleServerId {
+            id: SHUFFLE_SERVER_ID.get().unwrap().to_string(),
+            ip,
+            port: grpc_port,
+            netty_port: urpc_port,
+        };
+
+        HeartbeatTask::register_unregistration(
+            coordinator_quorum.clone(),
+            connect_timeout,
+            retry_interval,
+            shuffle_server_id.clone(),
+            tags.clone(),
+            app_manager.clone(),
+        );
+
+        runtime_manager
+            .default_runtime
+            .spawn_guarded("heartbeat", async move {
+                let mut coordinators =
+                    CoordinatorQuorum::new(&coordinator_quorum, connect_timeout, retry_interval)
+                        .unwrap();
+
+                // block until at least one coordinator accepts the registration
+                loop {
+                    let heartbeat_req = build_heartbeat_request(
+                        &shuffle_server_id,
+                        &tags,
+                        &app_manager,
+                        &HEALTH_REGISTRY,
+                    )
+                    .await;
+                    if coordinators.heartbeat(&heartbeat_req).await > 0 {
+                        health_provider.mark_success();
+                        break;
+                    }
+                    warn!(
+                        "None of coordinators accepts the registration, retry after {:?}",
+                        retry_interval
+                    );
+                    tokio::time::sleep(retry_interval).await;
+                }
+
+                loop {
+                    // todo: add interval as config var
+                    tokio::time::sleep(HEARTBEAT_INTERVAL).await;
+
+                    let heartbeat_req = build_heartbeat_request(
+                        &shuffle_server_id,
+                        &tags,
+                        &app_manager,
+                        &HEALTH_REGISTRY,
+                    )
+                    .await;
+                    if coordinators.heartbeat(&heartbeat_req).await > 0 {
+                        health_provider.mark_success();
+                    }
+                }
+            });
+    }
+}
 
-            let mut multi_coordinator_clients: Vec<CoordinatorServerClient<Channel>> =
-                futures::future::try_join_all(
-                    coordinator_quorum.iter().map(|quorum| {
-                        CoordinatorServerClient::connect(format!("http://{}", quorum))
-                    }),
+impl HeartbeatTask {
+    /// There is no unregistration rpc, so the last heartbeat marks this server as
+    /// unhealthy and decommissioned to stop the new assignments before exiting.
+    fn register_unregistration(
+        coordinator_quorum: Vec<String>,
+        connect_timeout: Duration,
+        retry_interval: Duration,
+        shuffle_server_id: ShuffleServerId,
+        tags: Vec<String>,
+        app_manager: AppManagerRef,
+    ) {
+        SHUTDOWN_COORDINATOR.register(
+            "coordinator_unregistration",
+            PHASE_UNREGISTER,
+            connect_timeout,
+            move || async move {
+                let mut coordinators =
+                    CoordinatorQuorum::new(&coordinator_quorum, connect_timeout, retry_interval)?;
+                let mut request = build_heartbeat_request(
+                    &shuffle_server_id,
+                    &tags,
+                    &app_manager,
+                    &HEALTH_REGISTRY,
                 )
-                .await
-                .unwrap();
+                .await;
+                request.is_healthy = Some(false);
+                request.status = ServerStatus::Decommissioned as i32;
+                if coordinators.heartbeat(&request).await == 0 {
+                    return Err(anyhow!("None of coordinators accepts the unregistration"));
+                }
+                Ok(())
+            },
+        );
+    }
+}
+
+struct CoordinatorEndpoint {
+    address: String,
+    client: CoordinatorServerClient<Channel>,
+    up: bool,
+    consecutive_failures: u32,
+    next_retry: Instant,
+    // the coordinator has forgotten this server, like after its restart
+    registration_required: bool,
+}
+
+/// The clients of all the coordinators in the quorum, like the java server does,
+/// the heartbeat is sent to all of them. The failing ones will be retried with
+/// the exponential backoff independently.
+struct CoordinatorQuorum {
+    endpoints: Vec<CoordinatorEndpoint>,
+    connect_timeout: Duration,
+    retry_interval: Duration,
+}
 
-            loop {
-                // todo: add interval as config var
-                tokio::time::sleep(Duration::from_secs(10)).await;
-
-                let mut all_tags = vec![];
-                all_tags.push(DEFAULT_SHUFFLE_SERVER_TAG.to_string());
-                all_tags.extend_from_slice(&*tags);
-
-                let healthy = app_manager.store_is_healthy().await.unwrap_or(false);
-                let memory_snapshot = app_manager
-                    .store_memory_snapshot()
-                    .await
-                    .unwrap_or((0, 0, 0).into());
-                let memory_spill_event_num =
-                    app_manager.store_memory_spill_event_num().unwrap_or(0) as i32;
-
-                let heartbeat_req = ShuffleServerHeartBeatRequest {
-                    server_id: Some(shuffle_server_id.clone()),
-                    used_memory: memory_snapshot.used(),
-                    pre_allocated_memory: memory_snapshot.allocated(),
-                    available_memory: memory_snapshot.capacity()
-                        - memory_snapshot.used()
-                        - memory_snapshot.allocated(),
-                    event_num_in_flush: memory_spill_event_num,
-                    tags: all_tags,
-                    is_healthy: Some(healthy),
-                    status: 0,
-                    storage_info: Default::default(),
-                };
-
-                // It must use the 0..len to avoid borrow check in loop.
-                for idx in 0..multi_coordinator_clients.len() {
-                    let client = multi_coordinator_clients.get_mut(idx).unwrap();
-                    let _ = client
-                        .heartbeat(tonic::Request::new(heartbeat_req.clone()))
-                        .await;
+impl CoordinatorQuorum {
+    fn new(
+        addresses: &[String],
+        connect_timeout: Duration,
+        retry_interval: Duration,
+    ) -> Result<Self> {
+        let mut endpoints = vec![];
+        for address in addresses {
+            // the lazy channel will reconnect automatically once the coordinator is restarted
+            let channel = Endpoint::from_shared(format!("http://{}", address))?
+                .connect_timeout(connect_timeout)
+                .connect_lazy();
+            endpoints.push(CoordinatorEndpoint {
+                address: address.to_string(),
+                client: CoordinatorServerClient::new(channel),
+                up: false,
+                consecutive_failures: 0,
+                next_retry: Instant::now(),
+                registration_required: false,
+            });
+        }
+        Ok(Self {
+            endpoints,
+            connect_timeout,
+            retry_interval,
+        })
+    }
+
+    /// Send the heartbeat to all the coordinators except for the ones in backoff, and
+    /// re-register immediately to the ones which have forgotten this server.
+    /// Return the number of coordinators accepting it.
+    async fn heartbeat(&mut self, request: &ShuffleServerHeartBeatRequest) -> usize {
+        let now = Instant::now();
+        let mut accepted = self.send(request, |x| x.next_retry <= now).await;
+
+        let forgotten = self
+            .endpoints
+            .iter()
+            .filter(|x| x.registration_required)
+            .count();
+        if forgotten > 0 {
+            TOTAL_COORDINATOR_REREGISTRATION.inc_by(forgotten as u64);
+            let reregistered = self.send(request, |x| x.registration_required).await;
+            accepted.extend(reregistered);
+        }
+        accepted.len()
+    }
+
+    /// Return the indexes of the coordinators accepting the request.
+    async fn send<F: Fn(&CoordinatorEndpoint) -> bool>(
+        &mut self,
+        request: &ShuffleServerHeartBeatRequest,
+        filter: F,
+    ) -> HashSet<usize> {
+        let timeout = self.connect_timeout;
+        let futures = self
+            .endpoints
+            .iter_mut()
+            .enumerate()
+            .filter(|(_, endpoint)| filter(endpoint))
+            .map(|(idx, endpoint)| {
+                let request = tonic::Request::new(request.clone());
+                async move {
+                    let result = tokio::time::timeout(timeout, endpoint.client.heartbeat(request))
+                        .await
+                        .map_err(|_| anyhow!("heartbeat timeout after {:?}", timeout))
+                        .and_then(|x| x.map_err(|e| anyhow!(e)));
+                    (idx, endpoint, result)
                 }
+            });
+
+        let mut accepted = HashSet::new();
+        for (idx, endpoint, result) in futures::future::join_all(futures).await {
+            match result {
+                Ok(response) => {
+                    if !endpoint.up {
+                        info!("Coordinator: {} is UP", &endpoint.address);
+                    }
+                    endpoint.up = true;
+                    if response.into_inner().status == StatusCode::NoRegister as i32 {
+                        warn!(
+                            "Coordinator: {} has forgotten this server, it will be re-registered",
+                            &endpoint.address
+                        );
+                        endpoint.registration_required = true;
+                    } else {
+                        // the coordinator may be restarted during the long failures
+                        endpoint.registration_required =
+                            endpoint.consecutive_failures >= REREGISTRATION_FAILURE_THRESHOLD;
+                        accepted.insert(idx);
+                    }
+                    endpoint.consecutive_failures = 0;
+                }
+                Err(e) => {
+                    if endpoint.up || endpoint.consecutive_failures == 0 {
+                        warn!("Coordinator: {} is DOWN. err: {:?}", &endpoint.address, e);
+                    }
+                    endpoint.up = false;
+                    endpoint.consecutive_failures += 1;
+                    let backoff = self
+                        .retry_interval
+                        .saturating_mul(1 << (endpoint.consecutive_failures - 1).min(16))
+                        .min(MAX_RETRY_BACKOFF);
+                    endpoint.next_retry = Instant::now() + backoff;
+                }
+            }
+        }
+        accepted
+    }
+}
+
+/// All the values are sampled at the heartbeat time.
+async fn build_heartbeat_request(
+    server_id: &ShuffleServerId,
+    tags: &[String],
+    app_manager: &AppManagerRef,
+    health_registry: &HealthRegistry,
+) -> ShuffleServerHeartBeatRequest {
+    let mut all_tags = vec![];
+    all_tags.push(DEFAULT_SHUFFLE_SERVER_TAG.to_string());
+    all_tags.extend_from_slice(tags);
+
+    let health_report = health_registry.report().await;
+    let healthy = app_manager.store_is_healthy().await.unwrap_or(false)
+        && health_report.status != HealthStatus::Unhealthy;
+    let memory_snapshot = app_manager
+        .store_memory_snapshot()
+        .await
+        .unwrap_or((0, 0, 0).into());
+    let memory_spill_event_num = app_manager.store_memory_spill_event_num().unwrap_or(0) as i32;
+
+    ShuffleServerHeartBeatRequest {
+        server_id: Some(server_id.clone()),
+        used_memory: memory_snapshot.used(),
+        pre_allocated_memory: memory_snapshot.allocated(),
+        available_memory: memory_snapshot.capacity()
+            - memory_snapshot.used()
+            - memory_snapshot.allocated(),
+        event_num_in_flush: memory_spill_event_num,
+        tags: all_tags,
+        is_healthy: Some(healthy),
+        status: app_manager.decommission_state().server_status() as i32,
+        storage_info: storage_info_from(&health_report),
+        app_num: app_manager.app_number() as i32,
+        partition_num: app_manager.partition_number() as i64,
+        // the reached limits are reported by the counts to stop the new assignments
+        max_app_num: app_manager.max_app_number().unwrap_or(0) as i32,
+        max_partition_num: app_manager.max_partition_number().unwrap_or(0) as i64,
+    }
+}
+
+/// Extract the per disk storage info from the health data of the localfile store.
+fn storage_info_from(report: &HealthReport) -> HashMap<String, StorageInfo> {
+    let mut storage_info = HashMap::new();
+    for component in &report.components {
+        let mount_point = match component.name.strip_prefix("disk:") {
+            Some(mount_point) => mount_point.to_string(),
+            _ => continue,
+        };
+        let capacity = component.details["capacity"].as_i64().unwrap_or(0);
+        let available = component.details["available"].as_i64().unwrap_or(0);
+        // the degraded disk is the one exceeding the high watermark
+        let status = match component.status {
+            HealthStatus::Healthy => StorageStatus::Normal,
+            HealthStatus::Degraded => StorageStatus::Overused,
+            HealthStatus::Unhealthy => StorageStatus::Unhealthy,
+        };
+        storage_info.insert(
+            mount_point.clone(),
+            StorageInfo {
+                mount_point,
+                storage_media: StorageMedia::StorageTypeUnknown as i32,
+                capacity,
+                used_bytes: capacity - available,
+                status: status as i32,
+                ..Default::default()
+            },
+        );
+    }
+    storage_info
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::app::{AppManager, PartitionedUId, RequireBufferContext, WritingViewContext};
+    use crate::config::{Config, LocalfileStoreConfig, MemoryStoreConfig, StorageType};
+    use crate::grpc::protobuf::uniffle::coordinator_server_client::CoordinatorServerClient;
+    use crate::grpc::protobuf::uniffle::coordinator_server_server::CoordinatorServerServer;
+    use crate::grpc::protobuf::uniffle::storage_info::StorageStatus;
+    use crate::grpc::protobuf::uniffle::*;
+    use crate::health::{ComponentHealth, HealthProvider, HealthRegistry};
+    use crate::heartbeat::{build_heartbeat_request, CoordinatorQuorum};
+    use crate::metric::TOTAL_COORDINATOR_REREGISTRATION;
+    use crate::runtime::manager::RuntimeManager;
+    use crate::store::local::disk::{LocalDisk, LocalDiskConfig};
+    use crate::store::Block;
+    use crate::testing::{free_addr, start_coordinator, MockCoordinator};
+    use async_trait::async_trait;
+    use bytes::Bytes;
+    use std::sync::atomic::Ordering;
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+    use tokio_stream::wrappers::TcpListenerStream;
+    use tonic::transport::Server;
+    use tonic::Request;
+
+    async fn heartbeat_until<F: Fn(usize) -> bool>(
+        quorum: &mut CoordinatorQuorum,
+        condition: F,
+    ) -> bool {
+        let request = ShuffleServerHeartBeatRequest::default();
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while Instant::now() < deadline {
+            if condition(quorum.heartbeat(&request).await) {
+                return true;
             }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        false
+    }
+
+    #[tokio::test]
+    async fn test_coordinator_quorum_failover() -> anyhow::Result<()> {
+        let coordinator_1 = MockCoordinator::default();
+        let coordinator_2 = MockCoordinator::default();
+        let (addr_1, addr_2) = (free_addr(), free_addr());
+        let shutdown_1 = start_coordinator(coordinator_1.clone(), addr_1);
+        let _shutdown_2 = start_coordinator(coordinator_2.clone(), addr_2);
+
+        let mut quorum = CoordinatorQuorum::new(
+            &[addr_1.to_string(), addr_2.to_string()],
+            Duration::from_secs(1),
+            Duration::from_millis(50),
+        )?;
+        assert!(heartbeat_until(&mut quorum, |accepted| accepted == 2).await);
+        assert!(quorum.endpoints.iter().all(|x| x.up));
+
+        // kill the first one, the heartbeats continue to the survivor
+        shutdown_1.send(()).unwrap();
+        assert!(heartbeat_until(&mut quorum, |accepted| accepted == 1).await);
+        assert!(!quorum.endpoints[0].up);
+        assert!(quorum.endpoints[0].consecutive_failures > 0);
+
+        let received_1 = coordinator_1.heartbeats.lock().len();
+        let received_2 = coordinator_2.heartbeats.lock().len();
+        assert!(heartbeat_until(&mut quorum, |accepted| accepted == 1).await);
+        assert_eq!(received_1, coordinator_1.heartbeats.lock().len());
+        assert!(coordinator_2.heartbeats.lock().len() > received_2);
+
+        // restart it, the heartbeats resume after the backoff
+        let _shutdown_1 = start_coordinator(coordinator_1.clone(), addr_1);
+        assert!(heartbeat_until(&mut quorum, |accepted| accepted == 2).await);
+        assert!(coordinator_1.heartbeats.lock().len() > received_1);
+        assert!(quorum.endpoints[0].up);
+        assert_eq!(0, quorum.endpoints[0].consecutive_failures);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_reregister_to_forgetful_coordinator() -> anyhow::Result<()> {
+        let coordinator = MockCoordinator::default();
+        let addr = free_addr();
+        let _shutdown = start_coordinator(coordinator.clone(), addr);
+
+        let mut quorum = CoordinatorQuorum::new(
+            &[addr.to_string()],
+            Duration::from_secs(1),
+            Duration::from_millis(50),
+        )?;
+        assert!(heartbeat_until(&mut quorum, |accepted| accepted == 1).await);
+
+        // the re-registration happens in the same round without waiting for the next interval
+        let received = coordinator.heartbeats.lock().len();
+        let reregistration = TOTAL_COORDINATOR_REREGISTRATION.get();
+        coordinator.forgotten.store(true, Ordering::SeqCst);
+        let request = ShuffleServerHeartBeatRequest::default();
+        assert_eq!(1, quorum.heartbeat(&request).await);
+        assert_eq!(received + 1, coordinator.heartbeats.lock().len());
+        assert!(TOTAL_COORDINATOR_REREGISTRATION.get() > reregistration);
+        assert!(!quorum.endpoints[0].registration_required);
+
+        Ok(())
+    }
+
+    struct DiskHealthProvider(Arc<LocalDisk>);
+
+    #[async_trait]
+    impl HealthProvider for DiskHealthProvider {
+        async fn component_health(&self) -> Vec<ComponentHealth> {
+            vec![self.0.component_health()]
+        }
+    }
+
+    #[test]
+    fn test_heartbeat_with_live_values() -> anyhow::Result<()> {
+        let temp_dir = tempdir::TempDir::new("test_heartbeat_with_live_values")?;
+        let data_path = temp_dir.path().to_str().unwrap().to_string();
+
+        let mut config = Config::default();
+        config.store_type = StorageType::MEMORY_LOCALFILE;
+        config.memory_store = Some(MemoryStoreConfig::new((1024 * 1024).to_string()));
+        config.localfile_store = Some(LocalfileStoreConfig::new(vec![data_path.clone()]));
+        config.app_config.max_concurrent_apps = Some(1);
+
+        let runtime_manager: RuntimeManager = Default::default();
+        let app_manager_ref = AppManager::get_ref(runtime_manager.clone(), config);
+
+        let local_disk = LocalDisk::new(
+            data_path.clone(),
+            LocalDiskConfig::create_mocked_config(),
+            runtime_manager.clone(),
+        );
+        let health_registry = HealthRegistry::default();
+        health_registry.register("disk", Arc::new(DiskHealthProvider(local_disk.clone())));
+
+        // start the mock coordinator
+        let coordinator = MockCoordinator::default();
+        let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+        listener.set_nonblocking(true)?;
+        let addr = listener.local_addr()?;
+        let service = CoordinatorServerServer::new(coordinator.clone());
+        runtime_manager.default_runtime.spawn(async move {
+            let listener = tokio::net::TcpListener::from_std(listener).unwrap();
+            Server::builder()
+                .add_service(service)
+                .serve_with_incoming(TcpListenerStream::new(listener))
+                .await
+                .unwrap();
         });
+
+        let server_id = ShuffleServerId {
+            id: "server_1".to_string(),
+            ..Default::default()
+        };
+        let tags = vec!["tag_1".to_string()];
+
+        let app_manager = app_manager_ref.clone();
+        runtime_manager.wait(async move {
+            let mut client = CoordinatorServerClient::connect(format!("http://{}", addr)).await?;
+            let request =
+                build_heartbeat_request(&server_id, &tags, &app_manager, &health_registry).await;
+            client.heartbeat(Request::new(request)).await?;
+
+            // write data and mark the disk unhealthy
+            app_manager.register("app_1".to_string(), 1, Default::default())?;
+            let app = app_manager.get_app("app_1").unwrap();
+            let uid = PartitionedUId::from("app_1".to_string(), 1, 0);
+            app.require_buffer(RequireBufferContext::new(uid.clone(), 100))
+                .await?;
+            let block = Block {
+                block_id: 0,
+                length: 100,
+                uncompress_length: 100,
+                crc: 0,
+                data: Bytes::from(vec![0; 100]),
+                task_attempt_id: 0,
+            };
+            app.insert(WritingViewContext::from(uid, vec![block]))
+                .await?;
+            app.move_allocated_used_from_budget(100)?;
+            local_disk.mark_corrupted();
+
+            let request =
+                build_heartbeat_request(&server_id, &tags, &app_manager, &health_registry).await;
+            client.heartbeat(Request::new(request)).await?;
+            anyhow::Ok(())
+        })?;
+
+        let heartbeats = coordinator.heartbeats.lock().clone();
+        assert_eq!(2, heartbeats.len());
+        let (before, after) = (&heartbeats[0], &heartbeats[1]);
+
+        assert_eq!(vec!["ss_v4".to_string(), "tag_1".to_string()], before.tags);
+        assert_eq!(0, before.used_memory);
+        assert_eq!(100, after.used_memory);
+        assert_eq!(1024 * 1024 - 100, after.available_memory);
+
+        assert_eq!(0, before.app_num);
+        assert_eq!(1, after.app_num);
+        assert_eq!(0, before.partition_num);
+        assert_eq!(1, after.partition_num);
+        assert_eq!(1, after.max_app_num);
+        assert_eq!(0, after.max_partition_num);
+
+        let disk_before = before.storage_info.get(&data_path).unwrap();
+        assert!(disk_before.capacity > 0);
+        assert_eq!(StorageStatus::Normal as i32, disk_before.status);
+        let disk_after = after.storage_info.get(&data_path).unwrap();
+        assert_eq!(StorageStatus::Unhealthy as i32, disk_after.status);
+
+        Ok(())
     }
 }
@@ -0,0 +1,492 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::app::{AppManagerRef, AppQuota, PartitionedUId};
+use crate::http::Handler;
+use crate::store::PartitionStorageStat;
+use poem::http::StatusCode;
+use poem::web::{Data, Json, Path};
+use poem::{handler, EndpointExt, Request, RouteMethod};
+use serde::{Deserialize, Serialize};
+
+pub(crate) const MAX_PAGE_LIMIT: usize = 1000;
+const DEFAULT_HOT_PARTITIONS_LIMIT: usize = 10;
+
+#[derive(Deserialize, Serialize)]
+#[serde(default)]
+pub struct PageRequest {
+    pub(crate) offset: usize,
+    pub(crate) limit: usize,
+}
+
+impl Default for PageRequest {
+    fn default() -> Self {
+        PageRequest {
+            offset: 0,
+            limit: 100,
+        }
+    }
+}
+
+impl PageRequest {
+    pub(crate) fn slice<T>(&self, items: Vec<T>) -> Vec<T> {
+        let limit = self.limit.min(MAX_PAGE_LIMIT);
+        items.into_iter().skip(self.offset).take(limit).collect()
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Page<T> {
+    pub total: usize,
+    pub offset: usize,
+    pub limit: usize,
+    pub items: Vec<T>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AppInfo {
+    pub app_id: String,
+    pub registered_timestamp: u64,
+    pub latest_heartbeat_timestamp: u64,
+    pub partition_number: usize,
+    pub received_bytes: u64,
+    pub resident_bytes: u64,
+    pub memory_bytes: u64,
+    pub localfile_bytes: u64,
+    pub hdfs_bytes: u64,
+    pub huge_partition_limit_enabled: bool,
+    pub huge_partition_number: usize,
+    pub quota: AppQuotaInfo,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AppQuotaInfo {
+    #[serde(flatten)]
+    pub limit: AppQuota,
+    pub exceeded: bool,
+    // the rejected writing since the last purge
+    pub rejected: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PartitionStorageInfo {
+    pub size: u64,
+    pub location: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PartitionInfo {
+    pub partition_id: i32,
+    pub huge_partition: bool,
+    pub memory: Option<PartitionStorageInfo>,
+    pub localfile: Option<PartitionStorageInfo>,
+    pub hdfs: Option<PartitionStorageInfo>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct HotPartitionInfo {
+    pub shuffle_id: i32,
+    pub partition_id: i32,
+    // the estimated written bytes, which is overestimated by the error at most
+    pub bytes: u64,
+    pub error: u64,
+    pub share: f64,
+    pub huge_partition: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct HotPartitionsInfo {
+    pub app_id: String,
+    pub total_bytes: u64,
+    pub partitions: Vec<HotPartitionInfo>,
+}
+
+#[derive(Deserialize)]
+struct HotPartitionsRequest {
+    limit: Option<usize>,
+}
+
+impl PartitionInfo {
+    fn from(partition_id: i32, huge_partition: bool, stat: PartitionStorageStat) -> Self {
+        let convert = |x: Option<crate::store::PartitionStat>| {
+            x.map(|x| PartitionStorageInfo {
+                size: x.size,
+                location: x.location,
+            })
+        };
+        PartitionInfo {
+            partition_id,
+            huge_partition,
+            memory: convert(stat.memory),
+            localfile: convert(stat.localfile),
+            hdfs: convert(stat.hdfs),
+        }
+    }
+}
+
+#[handler]
+async fn apps_handler(
+    req: &Request,
+    app_manager_ref: Data<&AppManagerRef>,
+) -> poem::Result<Json<Page<AppInfo>>> {
+    let page = req.params::<PageRequest>()?;
+
+    let mut apps = app_manager_ref.list_apps();
+    apps.sort_by(|a, b| a.app_id().cmp(b.app_id()));
+    let total = apps.len();
+
+    let mut items = vec![];
+    for app in page.slice(apps) {
+        let mut memory_bytes = 0;
+        let mut localfile_bytes = 0;
+        let mut hdfs_bytes = 0;
+        let mut huge_partition_number = 0;
+        for uid in app.all_partition_uids() {
+            let stat = app.partition_storage_stat(&uid).await;
+            memory_bytes += stat.memory.map_or(0, |x| x.size);
+            localfile_bytes += stat.localfile.map_or(0, |x| x.size);
+            hdfs_bytes += stat.hdfs.map_or(0, |x| x.size);
+            if app.is_marked_huge_partition(&uid) {
+                huge_partition_number += 1;
+            }
+        }
+        items.push(AppInfo {
+            app_id: app.app_id().to_string(),
+            registered_timestamp: app.registered_timestamp(),
+            latest_heartbeat_timestamp: app.latest_heartbeat_time(),
+            partition_number: app.partition_number(),
+            received_bytes: app.total_received_data_size(),
+            resident_bytes: app.total_resident_data_size(),
+            memory_bytes,
+            localfile_bytes,
+            hdfs_bytes,
+            huge_partition_limit_enabled: app.is_huge_partition_limit_enabled(),
+            huge_partition_number,
+            quota: AppQuotaInfo {
+                limit: app.quota(),
+                exceeded: app.is_quota_exceeded(),
+                rejected: app.quota_rejected(),
+            },
+        });
+    }
+
+    Ok(Json(Page {
+        total,
+        offset: page.offset,
+        limit: page.limit.min(MAX_PAGE_LIMIT),
+        items,
+    }))
+}
+
+#[handler]
+async fn shuffle_partitions_handler(
+    req: &Request,
+    Path((app_id, shuffle_id)): Path<(String, i32)>,
+    app_manager_ref: Data<&AppManagerRef>,
+) -> poem::Result<Json<Page<PartitionInfo>>> {
+    let page = req.params::<PageRequest>()?;
+
+    let app = app_manager_ref
+        .get_app(&app_id)
+        .ok_or(poem::Error::from_string(
+            format!("No such app: {}", &app_id),
+            StatusCode::NOT_FOUND,
+        ))?;
+
+    let partition_ids = app.partition_ids(shuffle_id);
+    let total = partition_ids.len();
+
+    let mut items = vec![];
+    for partition_id in page.slice(partition_ids) {
+        let uid = PartitionedUId::from(app_id.to_string(), shuffle_id, partition_id);
+        let stat = app.partition_storage_stat(&uid).await;
+        items.push(PartitionInfo::from(
+            partition_id,
+            app.is_marked_huge_partition(&uid),
+            stat,
+        ));
+    }
+
+    Ok(Json(Page {
+        total,
+        offset: page.offset,
+        limit: page.limit.min(MAX_PAGE_LIMIT),
+        items,
+    }))
+}
+
+#[handler]
+async fn hot_partitions_handler(
+    req: &Request,
+    Path(app_id): Path<String>,
+    app_manager_ref: Data<&AppManagerRef>,
+) -> poem::Result<Json<HotPartitionsInfo>> {
+    let limit = req
+        .params::<HotPartitionsRequest>()?
+        .limit
+        .unwrap_or(DEFAULT_HOT_PARTITIONS_LIMIT);
+
+    let app = app_manager_ref
+        .get_app(&app_id)
+        .ok_or(poem::Error::from_string(
+            format!("No such app: {}", &app_id),
+            StatusCode::NOT_FOUND,
+        ))?;
+
+    let hot = app.hot_partitions(limit.min(MAX_PAGE_LIMIT));
+    let partitions = hot
+        .partitions
+        .into_iter()
+        .map(|x| {
+            let (shuffle_id, partition_id) = x.key;
+            let uid = PartitionedUId::from(app_id.to_string(), shuffle_id, partition_id);
+            HotPartitionInfo {
+                shuffle_id,
+                partition_id,
+                bytes: x.count,
+                error: x.error,
+                share: x.count as f64 / hot.total.max(1) as f64,
+                huge_partition: app.is_marked_huge_partition(&uid),
+            }
+        })
+        .collect();
+
+    Ok(Json(HotPartitionsInfo {
+        app_id,
+        total_bytes: hot.total,
+        partitions,
+    }))
+}
+
+pub struct AppsHandler {
+    app_manager_ref: AppManagerRef,
+}
+
+impl AppsHandler {
+    pub fn new(app_manager_ref: AppManagerRef) -> Self {
+        Self { app_manager_ref }
+    }
+}
+
+impl Handler for AppsHandler {
+    fn get_route_method(&self) -> RouteMethod {
+        RouteMethod::new().get(apps_handler.data(self.app_manager_ref.clone()))
+    }
+
+    fn get_route_path(&self) -> String {
+        "/apps".to_string()
+    }
+}
+
+pub struct ShufflePartitionsHandler {
+    app_manager_ref: AppManagerRef,
+}
+
+impl ShufflePartitionsHandler {
+    pub fn new(app_manager_ref: AppManagerRef) -> Self {
+        Self { app_manager_ref }
+    }
+}
+
+impl Handler for ShufflePartitionsHandler {
+    fn get_route_method(&self) -> RouteMethod {
+        RouteMethod::new().get(shuffle_partitions_handler.data(self.app_manager_ref.clone()))
+    }
+
+    fn get_route_path(&self) -> String {
+        "/apps/:app_id/shuffles/:shuffle_id".to_string()
+    }
+}
+
+pub struct HotPartitionsHandler {
+    app_manager_ref: AppManagerRef,
+}
+
+impl HotPartitionsHandler {
+    pub fn new(app_manager_ref: AppManagerRef) -> Self {
+        Self { app_manager_ref }
+    }
+}
+
+impl Handler for HotPartitionsHandler {
+    fn get_route_method(&self) -> RouteMethod {
+        RouteMethod::new().get(hot_partitions_handler.data(self.app_manager_ref.clone()))
+    }
+
+    fn get_route_path(&self) -> String {
+        "/apps/:app_id/hot_partitions".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::app::{AppManager, AppQuota, PartitionedUId, WritingViewContext};
+    use crate::config::{Config, HybridStoreConfig, LocalfileStoreConfig, MemoryStoreConfig};
+    use crate::http::apps::{
+        AppInfo, AppsHandler, HotPartitionsHandler, HotPartitionsInfo, Page, PartitionInfo,
+        ShufflePartitionsHandler,
+    };
+    use crate::http::Handler;
+    use crate::store::Block;
+    use poem::test::TestClient;
+    use poem::Route;
+
+    fn mock_config() -> Config {
+        let temp_dir = tempdir::TempDir::new("test_http_apps").unwrap();
+        let temp_path = temp_dir.path().to_str().unwrap().to_string();
+
+        let mut config = Config::default();
+        config.memory_store = Some(MemoryStoreConfig::new((1024 * 1024).to_string()));
+        config.localfile_store = Some(LocalfileStoreConfig::new(vec![temp_path]));
+        config.hybrid_store = HybridStoreConfig::default();
+        config
+    }
+
+    #[tokio::test]
+    async fn test_router() {
+        let app_manager_ref = AppManager::get_ref(Default::default(), mock_config());
+        for app_id in ["app_c", "app_a", "app_b"] {
+            app_manager_ref
+                .register(app_id.to_string(), 1, Default::default())
+                .unwrap();
+        }
+        let app = app_manager_ref.get_app("app_a").unwrap();
+        for partition_id in 0..5 {
+            let uid = PartitionedUId::from("app_a".to_string(), 1, partition_id);
+            let block = Block {
+                block_id: partition_id as i64,
+                length: 10,
+                uncompress_length: 20,
+                crc: 0,
+                data: Default::default(),
+                task_attempt_id: 0,
+            };
+            app.insert(WritingViewContext::from(uid, vec![block]))
+                .await
+                .unwrap();
+        }
+
+        let apps_handler = AppsHandler::new(app_manager_ref.clone());
+        let partitions_handler = ShufflePartitionsHandler::new(app_manager_ref.clone());
+        let hot_partitions_handler = HotPartitionsHandler::new(app_manager_ref.clone());
+        let app = Route::new()
+            .at(
+                apps_handler.get_route_path(),
+                apps_handler.get_route_method(),
+            )
+            .at(
+                partitions_handler.get_route_path(),
+                partitions_handler.get_route_method(),
+            )
+            .at(
+                hot_partitions_handler.get_route_path(),
+                hot_partitions_handler.get_route_method(),
+            );
+        let cli = TestClient::new(app);
+
+        // case1: list apps with pagination
+        let resp = cli
+            .get("/apps")
+            .query("offset", &0)
+            .query("limit", &2)
+            .send()
+            .await;
+        resp.assert_status_is_ok();
+        let body = resp.0.into_body().into_string().await.unwrap();
+        let page: Page<AppInfo> = serde_json::from_str(&body).unwrap();
+        assert_eq!(3, page.total);
+        assert_eq!(2, page.items.len());
+        assert_eq!("app_a", page.items[0].app_id);
+        assert_eq!("app_b", page.items[1].app_id);
+        assert_eq!(5, page.items[0].partition_number);
+        assert_eq!(50, page.items[0].memory_bytes);
+        assert_eq!(50, page.items[0].resident_bytes);
+        assert!(page.items[0].registered_timestamp > 0);
+        assert_eq!(AppQuota::default(), page.items[0].quota.limit);
+        assert!(!page.items[0].quota.exceeded);
+
+        let resp = cli.get("/apps").query("offset", &2).send().await;
+        let body = resp.0.into_body().into_string().await.unwrap();
+        let page: Page<AppInfo> = serde_json::from_str(&body).unwrap();
+        assert_eq!(1, page.items.len());
+        assert_eq!("app_c", page.items[0].app_id);
+
+        // case2: drill into the partitions with pagination
+        let resp = cli
+            .get("/apps/app_a/shuffles/1")
+            .query("offset", &2)
+            .query("limit", &2)
+            .send()
+            .await;
+        resp.assert_status_is_ok();
+        let body = resp.0.into_body().into_string().await.unwrap();
+        let page: Page<PartitionInfo> = serde_json::from_str(&body).unwrap();
+        assert_eq!(5, page.total);
+        assert_eq!(
+            vec![2, 3],
+            page.items
+                .iter()
+                .map(|x| x.partition_id)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(10, page.items[0].memory.as_ref().unwrap().size);
+        assert!(page.items[0].localfile.is_none());
+        assert!(!page.items[0].huge_partition);
+
+        // case3: no such app
+        let resp = cli.get("/apps/app_x/shuffles/1").send().await;
+        resp.assert_status(poem::http::StatusCode::NOT_FOUND);
+
+        // case4: the hot partitions of the skewed app
+        let skewed_app = app_manager_ref.get_app("app_c").unwrap();
+        for partition_id in 0..5 {
+            let uid = PartitionedUId::from("app_c".to_string(), 1, partition_id);
+            let block = Block {
+                block_id: partition_id as i64,
+                length: 10 * (partition_id + 1),
+                uncompress_length: 20,
+                crc: 0,
+                data: Default::default(),
+                task_attempt_id: 0,
+            };
+            skewed_app
+                .insert(WritingViewContext::from(uid, vec![block]))
+                .await
+                .unwrap();
+        }
+        let resp = cli
+            .get("/apps/app_c/hot_partitions")
+            .query("limit", &2)
+            .send()
+            .await;
+        resp.assert_status_is_ok();
+        let body = resp.0.into_body().into_string().await.unwrap();
+        let hot: HotPartitionsInfo = serde_json::from_str(&body).unwrap();
+        assert_eq!(150, hot.total_bytes);
+        assert_eq!(
+            vec![(4, 50), (3, 40)],
+            hot.partitions
+                .iter()
+                .map(|x| (x.partition_id, x.bytes))
+                .collect::<Vec<_>>()
+        );
+        assert!(!hot.partitions[0].huge_partition);
+
+        let resp = cli.get("/apps/app_x/hot_partitions").send().await;
+        resp.assert_status(poem::http::StatusCode::NOT_FOUND);
+    }
+}
@@ -16,41 +16,196 @@
 // under the License.
 
 use crate::await_tree::AWAIT_TREE_REGISTRY;
+use crate::error::WorkerError;
 use crate::http::Handler;
-use poem::endpoint::make;
-use poem::{get, RouteMethod};
+use bytes::Bytes;
+use poem::{handler, Body, Request, Response, RouteMethod};
+use serde::{Deserialize, Serialize};
 
-pub struct AwaitTreeHandler {}
+#[derive(Deserialize, Serialize, Default)]
+#[serde(default)]
+pub struct AwaitTreeRequest {
+    // only the trees containing this substring will be dumped
+    pub(crate) filter: Option<String>,
+    // text or json
+    pub(crate) format: Option<String>,
+}
+
+#[derive(Serialize)]
+struct AwaitTreeDump {
+    id: u64,
+    root: String,
+    spans: Vec<String>,
+}
+
+impl AwaitTreeDump {
+    fn from(id: u64, raw_tree: String) -> Self {
+        let mut lines = raw_tree.lines().map(|x| x.to_string());
+        let root = lines.next().unwrap_or_default();
+        AwaitTreeDump {
+            id,
+            root,
+            spans: lines.collect(),
+        }
+    }
+}
+
+fn dump(filter: Option<&str>) -> Vec<(u64, String)> {
+    let registry_cloned = AWAIT_TREE_REGISTRY.clone().get_inner();
+    let registry = registry_cloned.lock().unwrap();
+    let mut sorted_list: Vec<(u64, String)> = vec![];
+    for (v, tree) in registry.iter() {
+        let raw_tree = format!("{}", tree);
+        if let Some(filter) = filter {
+            if !raw_tree.contains(filter) {
+                continue;
+            }
+        }
+        sorted_list.push((*v, raw_tree));
+    }
+    drop(registry);
+
+    sorted_list.sort_by_key(|kv| kv.0);
+    sorted_list
+}
+
+#[handler]
+async fn await_tree_handler(req: &Request) -> poem::Result<Response, WorkerError> {
+    let req = req.params::<AwaitTreeRequest>()?;
+    let trees = dump(req.filter.as_deref());
+
+    // stream the trees one by one to avoid building the giant string
+    let (content_type, chunks): (_, Vec<Bytes>) = match req.format.as_deref() {
+        Some("json") => {
+            let mut chunks = Vec::with_capacity(trees.len() + 2);
+            chunks.push(Bytes::from_static(b"["));
+            for (idx, (id, raw_tree)) in trees.into_iter().enumerate() {
+                let mut chunk = if idx == 0 { vec![] } else { vec![b','] };
+                serde_json::to_writer(&mut chunk, &AwaitTreeDump::from(id, raw_tree))
+                    .map_err(|e| WorkerError::HTTP_SERVICE_ERROR(format!("{:?}", e)))?;
+                chunks.push(Bytes::from(chunk));
+            }
+            chunks.push(Bytes::from_static(b"]"));
+            ("application/json", chunks)
+        }
+        Some("text") | None => (
+            "text/plain",
+            trees
+                .into_iter()
+                .map(|(_, raw_tree)| Bytes::from(raw_tree + "\n"))
+                .collect(),
+        ),
+        Some(format) => {
+            return Err(WorkerError::HTTP_SERVICE_ERROR(format!(
+                "Unknown format: {}",
+                format
+            )))
+        }
+    };
+
+    let stream = futures::stream::iter(chunks.into_iter().map(Ok::<_, std::io::Error>));
+    Ok(Response::builder()
+        .content_type(content_type)
+        .body(Body::from_bytes_stream(stream)))
+}
+
+pub struct AwaitTreeHandler {
+    path: String,
+}
+
+impl AwaitTreeHandler {
+    pub fn with_path(path: &str) -> Self {
+        Self {
+            path: path.to_string(),
+        }
+    }
+}
 
 impl Default for AwaitTreeHandler {
     fn default() -> Self {
-        Self {}
+        Self::with_path("/debug/await_tree")
     }
 }
 
 impl Handler for AwaitTreeHandler {
     fn get_route_method(&self) -> RouteMethod {
-        get(make(|_| async {
-            let registry_cloned = AWAIT_TREE_REGISTRY.clone().get_inner();
-            let registry = registry_cloned.lock().unwrap();
-            let mut sorted_list: Vec<(u64, String)> = vec![];
-            for (v, tree) in registry.iter() {
-                let raw_tree = format!("{}", tree);
-                sorted_list.push((*v, raw_tree));
-            }
-            drop(registry);
-
-            let mut dynamic_string = String::new();
-            sorted_list.sort_by_key(|kv| kv.0);
-            for (_, raw_tree) in sorted_list {
-                dynamic_string.push_str(raw_tree.as_str());
-                dynamic_string.push('\n');
-            }
-            dynamic_string
-        }))
+        RouteMethod::new().get(await_tree_handler)
     }
 
     fn get_route_path(&self) -> String {
-        "/await-tree".to_string()
+        self.path.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::await_tree::AWAIT_TREE_REGISTRY;
+    use crate::http::await_tree::AwaitTreeHandler;
+    use crate::http::Handler;
+    use await_tree::InstrumentAwait;
+    use poem::test::TestClient;
+    use poem::Route;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_router() {
+        let root = AWAIT_TREE_REGISTRY
+            .clone()
+            .register("test_await_tree_parked_task".to_string())
+            .await;
+        let (tx, rx) = tokio::sync::oneshot::channel::<()>();
+        let handle = tokio::spawn(root.instrument(async move {
+            let _ = rx.instrument_await("parking on the channel").await;
+        }));
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let handler = AwaitTreeHandler::default();
+        let app = Route::new().at(handler.get_route_path(), handler.get_route_method());
+        let cli = TestClient::new(app);
+
+        // case1: text format with filter
+        let resp = cli
+            .get("/debug/await_tree")
+            .query("filter", &"test_await_tree_parked_task")
+            .send()
+            .await;
+        resp.assert_status_is_ok();
+        let body = resp.0.into_body().into_string().await.unwrap();
+        assert!(body.contains("test_await_tree_parked_task"));
+        assert!(body.contains("parking on the channel"));
+
+        // case2: filter out all
+        let resp = cli
+            .get("/debug/await_tree")
+            .query("filter", &"no_such_task_xxxxxx")
+            .send()
+            .await;
+        resp.assert_status_is_ok();
+        resp.assert_text("").await;
+
+        // case3: json format
+        let resp = cli
+            .get("/debug/await_tree")
+            .query("filter", &"test_await_tree_parked_task")
+            .query("format", &"json")
+            .send()
+            .await;
+        resp.assert_status_is_ok();
+        let body = resp.0.into_body().into_string().await.unwrap();
+        let trees: serde_json::Value = serde_json::from_str(&body).unwrap();
+        let trees = trees.as_array().unwrap();
+        assert_eq!(1, trees.len());
+        assert!(trees[0]["root"]
+            .as_str()
+            .unwrap()
+            .contains("test_await_tree_parked_task"));
+        assert!(trees[0]["spans"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|x| x.as_str().unwrap().contains("parking on the channel")));
+
+        let _ = tx.send(());
+        let _ = handle.await;
     }
 }
@@ -0,0 +1,146 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::decommission::{DecommissionManager, DecommissionProgress};
+use crate::http::Handler;
+use poem::http::StatusCode;
+use poem::web::{Data, Json};
+use poem::{handler, EndpointExt, Request, RouteMethod};
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize, Serialize, Default)]
+#[serde(default)]
+pub struct DecommissionRequest {
+    // the duration form like "30m", it will be terminated after it even if apps remain
+    pub(crate) timeout: Option<String>,
+}
+
+#[handler]
+async fn get_decommission_handler(
+    manager: Data<&DecommissionManager>,
+) -> Json<DecommissionProgress> {
+    Json(manager.progress().await)
+}
+
+#[handler]
+async fn start_decommission_handler(
+    req: &Request,
+    manager: Data<&DecommissionManager>,
+) -> poem::Result<Json<DecommissionProgress>> {
+    let params = req.params::<DecommissionRequest>()?;
+    let timeout = match params.timeout {
+        Some(timeout) => Some(
+            humantime::parse_duration(&timeout)
+                .map_err(|e| poem::Error::from_string(e.to_string(), StatusCode::BAD_REQUEST))?,
+        ),
+        _ => None,
+    };
+    manager
+        .start(timeout)
+        .map_err(|e| poem::Error::from_string(e.to_string(), StatusCode::CONFLICT))?;
+    Ok(Json(manager.progress().await))
+}
+
+#[handler]
+async fn cancel_decommission_handler(
+    manager: Data<&DecommissionManager>,
+) -> poem::Result<Json<DecommissionProgress>> {
+    manager
+        .cancel()
+        .map_err(|e| poem::Error::from_string(e.to_string(), StatusCode::CONFLICT))?;
+    Ok(Json(manager.progress().await))
+}
+
+pub struct DecommissionHandler {
+    manager: DecommissionManager,
+}
+
+impl DecommissionHandler {
+    pub fn new(manager: DecommissionManager) -> Self {
+        Self { manager }
+    }
+}
+
+impl Handler for DecommissionHandler {
+    fn get_route_method(&self) -> RouteMethod {
+        RouteMethod::new()
+            .get(get_decommission_handler.data(self.manager.clone()))
+            .post(start_decommission_handler.data(self.manager.clone()))
+            .delete(cancel_decommission_handler.data(self.manager.clone()))
+    }
+
+    fn get_route_path(&self) -> String {
+        "/admin/decommission".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::app::AppManager;
+    use crate::config::{Config, MemoryStoreConfig};
+    use crate::decommission::{DecommissionManager, DecommissionProgress, DecommissionState};
+    use crate::http::decommission::DecommissionHandler;
+    use crate::http::Handler;
+    use poem::http::StatusCode;
+    use poem::test::TestClient;
+    use poem::Route;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_router() {
+        let mut config = Config::default();
+        config.memory_store = Some(MemoryStoreConfig::new((1024 * 1024).to_string()));
+        let app_manager_ref = AppManager::get_ref(Default::default(), config);
+        app_manager_ref
+            .register("app_1".to_string(), 1, Default::default())
+            .unwrap();
+
+        let manager =
+            DecommissionManager::with_options(app_manager_ref, Duration::from_secs(60), || {});
+        let handler = DecommissionHandler::new(manager);
+        let app = Route::new().at(handler.get_route_path(), handler.get_route_method());
+        let cli = TestClient::new(app);
+
+        let resp = cli.get("/admin/decommission").send().await;
+        resp.assert_status_is_ok();
+        let body = resp.0.into_body().into_string().await.unwrap();
+        let progress: DecommissionProgress = serde_json::from_str(&body).unwrap();
+        assert_eq!(DecommissionState::NONE, progress.state);
+        assert_eq!(1, progress.apps_remaining);
+
+        let resp = cli
+            .post("/admin/decommission")
+            .query("timeout", &"30m")
+            .send()
+            .await;
+        resp.assert_status_is_ok();
+        let body = resp.0.into_body().into_string().await.unwrap();
+        let progress: DecommissionProgress = serde_json::from_str(&body).unwrap();
+        assert_ne!(DecommissionState::NONE, progress.state);
+        assert!(progress.deadline_timestamp.is_some());
+
+        // duplicate decommission is rejected
+        let resp = cli.post("/admin/decommission").send().await;
+        resp.assert_status(StatusCode::CONFLICT);
+
+        let resp = cli.delete("/admin/decommission").send().await;
+        resp.assert_status_is_ok();
+        let body = resp.0.into_body().into_string().await.unwrap();
+        let progress: DecommissionProgress = serde_json::from_str(&body).unwrap();
+        assert_eq!(DecommissionState::NONE, progress.state);
+    }
+}
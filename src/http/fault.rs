@@ -0,0 +1,105 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::http::Handler;
+use crate::store::fault::{FaultInjector, FaultRule, FAULT_INJECTOR};
+use poem::web::{Data, Json};
+use poem::{handler, EndpointExt, RouteMethod};
+
+#[handler]
+fn get_faults_handler(injector: Data<&&'static FaultInjector>) -> Json<Vec<FaultRule>> {
+    Json(injector.rules())
+}
+
+/// The body is the json array of rules, which replaces all the existing ones.
+#[handler]
+fn put_faults_handler(
+    Json(rules): Json<Vec<FaultRule>>,
+    injector: Data<&&'static FaultInjector>,
+) -> Json<Vec<FaultRule>> {
+    injector.set_rules(rules);
+    Json(injector.rules())
+}
+
+#[handler]
+fn delete_faults_handler(injector: Data<&&'static FaultInjector>) -> Json<Vec<FaultRule>> {
+    injector.clear();
+    Json(injector.rules())
+}
+
+pub struct FaultInjectionHandler {
+    injector: &'static FaultInjector,
+}
+
+impl Default for FaultInjectionHandler {
+    fn default() -> Self {
+        Self {
+            injector: &FAULT_INJECTOR,
+        }
+    }
+}
+
+impl Handler for FaultInjectionHandler {
+    fn get_route_method(&self) -> RouteMethod {
+        RouteMethod::new()
+            .get(get_faults_handler.data(self.injector))
+            .put(put_faults_handler.data(self.injector))
+            .delete(delete_faults_handler.data(self.injector))
+    }
+
+    fn get_route_path(&self) -> String {
+        "/debug/faults".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::http::fault::FaultInjectionHandler;
+    use crate::http::Handler;
+    use crate::store::fault::{FaultInjector, FaultKind, FaultOperation, FaultRule};
+    use poem::test::TestClient;
+    use poem::Route;
+
+    #[tokio::test]
+    async fn test_router() {
+        let injector: &'static FaultInjector = Box::leak(Box::new(FaultInjector::default()));
+        let handler = FaultInjectionHandler { injector };
+        let app = Route::new().at(handler.get_route_path(), handler.get_route_method());
+        let cli = TestClient::new(app);
+
+        let resp = cli
+            .put("/debug/faults")
+            .body(r#"[{"operation": "SpillInsert", "kind": {"type": "Error"}, "app_id": "app_1"}]"#)
+            .content_type("application/json")
+            .send()
+            .await;
+        resp.assert_status_is_ok();
+        assert!(injector.is_enabled());
+
+        let resp = cli.get("/debug/faults").send().await;
+        resp.assert_status_is_ok();
+        let body = resp.0.into_body().into_string().await.unwrap();
+        let rules: Vec<FaultRule> = serde_json::from_str(&body).unwrap();
+        assert_eq!(1, rules.len());
+        assert_eq!(FaultOperation::SpillInsert, rules[0].operation);
+        assert_eq!(FaultKind::Error, rules[0].kind);
+
+        let resp = cli.delete("/debug/faults").send().await;
+        resp.assert_status_is_ok();
+        assert!(!injector.is_enabled());
+    }
+}
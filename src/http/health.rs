@@ -0,0 +1,186 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::health::{HealthRegistry, HealthStatus};
+use crate::http::Handler;
+use poem::http::StatusCode;
+use poem::web::{Data, Json};
+use poem::{handler, EndpointExt, IntoResponse, Response, RouteMethod};
+
+fn status_code(status: HealthStatus) -> StatusCode {
+    match status {
+        HealthStatus::Unhealthy => StatusCode::SERVICE_UNAVAILABLE,
+        _ => StatusCode::OK,
+    }
+}
+
+#[handler]
+async fn health_handler(registry: Data<&HealthRegistry>) -> Response {
+    let status = registry.report().await.status;
+    let body = serde_json::to_value(status)
+        .ok()
+        .and_then(|x| x.as_str().map(|x| x.to_string()))
+        .unwrap_or_default();
+    body.with_status(status_code(status)).into_response()
+}
+
+#[handler]
+async fn health_detail_handler(registry: Data<&HealthRegistry>) -> Response {
+    let report = registry.report().await;
+    let status = report.status;
+    Json(report)
+        .with_status(status_code(status))
+        .into_response()
+}
+
+pub struct HealthHandler {
+    registry: HealthRegistry,
+}
+
+impl HealthHandler {
+    pub fn new(registry: HealthRegistry) -> Self {
+        Self { registry }
+    }
+}
+
+impl Handler for HealthHandler {
+    fn get_route_method(&self) -> RouteMethod {
+        RouteMethod::new().get(health_handler.data(self.registry.clone()))
+    }
+
+    fn get_route_path(&self) -> String {
+        "/health".to_string()
+    }
+}
+
+pub struct HealthDetailHandler {
+    registry: HealthRegistry,
+}
+
+impl HealthDetailHandler {
+    pub fn new(registry: HealthRegistry) -> Self {
+        Self { registry }
+    }
+}
+
+impl Handler for HealthDetailHandler {
+    fn get_route_method(&self) -> RouteMethod {
+        RouteMethod::new().get(health_detail_handler.data(self.registry.clone()))
+    }
+
+    fn get_route_path(&self) -> String {
+        "/health/detail".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::health::{
+        ComponentHealth, HealthProvider, HealthRegistry, HealthReport, HealthStatus,
+    };
+    use crate::http::health::{HealthDetailHandler, HealthHandler};
+    use crate::http::Handler;
+    use async_trait::async_trait;
+    use parking_lot::Mutex;
+    use poem::http::StatusCode;
+    use poem::test::TestClient;
+    use poem::Route;
+    use std::sync::Arc;
+
+    struct MockDisk {
+        status: Mutex<HealthStatus>,
+    }
+
+    #[async_trait]
+    impl HealthProvider for MockDisk {
+        async fn component_health(&self) -> Vec<ComponentHealth> {
+            vec![ComponentHealth::new(
+                "disk:/mock",
+                *self.status.lock(),
+                false,
+                serde_json::json!({ "last_probe_error": null }),
+            )]
+        }
+    }
+
+    struct MockMemory {
+        status: Mutex<HealthStatus>,
+    }
+
+    #[async_trait]
+    impl HealthProvider for MockMemory {
+        async fn component_health(&self) -> Vec<ComponentHealth> {
+            vec![ComponentHealth::new(
+                "memory_store",
+                *self.status.lock(),
+                true,
+                serde_json::Value::Null,
+            )]
+        }
+    }
+
+    #[tokio::test]
+    async fn test_router() {
+        let registry = HealthRegistry::default();
+        let disk = Arc::new(MockDisk {
+            status: Mutex::new(HealthStatus::Healthy),
+        });
+        let memory = Arc::new(MockMemory {
+            status: Mutex::new(HealthStatus::Healthy),
+        });
+        registry.register("disk", disk.clone());
+        registry.register("memory", memory.clone());
+
+        let health = HealthHandler::new(registry.clone());
+        let detail = HealthDetailHandler::new(registry.clone());
+        let app = Route::new()
+            .at(health.get_route_path(), health.get_route_method())
+            .at(detail.get_route_path(), detail.get_route_method());
+        let cli = TestClient::new(app);
+
+        let resp = cli.get("/health").send().await;
+        resp.assert_status_is_ok();
+        resp.assert_text("HEALTHY").await;
+
+        // case1: the failing disk degrades the overall status
+        *disk.status.lock() = HealthStatus::Unhealthy;
+        let resp = cli.get("/health/detail").send().await;
+        resp.assert_status_is_ok();
+        let body = resp.0.into_body().into_string().await.unwrap();
+        let report: HealthReport = serde_json::from_str(&body).unwrap();
+        assert_eq!(HealthStatus::Degraded, report.status);
+        assert_eq!(2, report.components.len());
+        let disk_component = report
+            .components
+            .iter()
+            .find(|x| x.name == "disk:/mock")
+            .unwrap();
+        assert_eq!(HealthStatus::Unhealthy, disk_component.status);
+
+        let resp = cli.get("/health").send().await;
+        resp.assert_status_is_ok();
+        resp.assert_text("DEGRADED").await;
+
+        // case2: the critical component makes the server unavailable
+        *memory.status.lock() = HealthStatus::Unhealthy;
+        let resp = cli.get("/health").send().await;
+        resp.assert_status(StatusCode::SERVICE_UNAVAILABLE);
+        resp.assert_text("UNHEALTHY").await;
+        let resp = cli.get("/health/detail").send().await;
+        resp.assert_status(StatusCode::SERVICE_UNAVAILABLE);
+    }
+}
@@ -0,0 +1,271 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::http::Handler;
+use crate::mem_allocator::error::ProfError;
+use crate::mem_allocator::{activate_prof, deactivate_prof, dump_prof, is_prof_enabled};
+use crate::util;
+use log::{info, warn};
+use parking_lot::Mutex;
+use poem::http::StatusCode;
+use poem::web::{Data, Path};
+use poem::{handler, EndpointExt, IntoResponse, Response, RouteMethod};
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+const DEFAULT_MAX_RETAINED_DUMP_BYTES: u64 = 256 * 1024 * 1024;
+
+/// The heap profiler driven by the jemalloc `prof.active` and `prof.dump`.
+/// The dumps are retained in the dump dir, and the oldest ones will be
+/// removed once the total size exceeds the limit.
+pub struct HeapProfiler {
+    dump_dir: PathBuf,
+    max_retained_bytes: u64,
+    active: AtomicBool,
+    dumps: Mutex<VecDeque<(PathBuf, u64)>>,
+}
+
+impl Default for HeapProfiler {
+    fn default() -> Self {
+        HeapProfiler::new(
+            std::env::temp_dir().join("heap_profiles"),
+            DEFAULT_MAX_RETAINED_DUMP_BYTES,
+        )
+    }
+}
+
+impl HeapProfiler {
+    pub fn new(dump_dir: PathBuf, max_retained_bytes: u64) -> Self {
+        Self {
+            dump_dir,
+            max_retained_bytes,
+            active: AtomicBool::new(false),
+            dumps: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    fn check_enabled() -> poem::Result<()> {
+        if !is_prof_enabled() {
+            return Err(poem::Error::from_string(
+                ProfError::MemProfilingNotEnabled.to_string(),
+                StatusCode::NOT_IMPLEMENTED,
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn start(&self) -> poem::Result<()> {
+        HeapProfiler::check_enabled()?;
+        if self
+            .active
+            .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
+            .is_err()
+        {
+            return Err(poem::Error::from_string(
+                "heap profiling has been started, dump or stop it",
+                StatusCode::CONFLICT,
+            ));
+        }
+        if let Err(e) = activate_prof() {
+            self.active.store(false, Ordering::SeqCst);
+            return Err(internal_error(e));
+        }
+        info!("Heap profiling has been started");
+        Ok(())
+    }
+
+    pub fn stop(&self) -> poem::Result<()> {
+        HeapProfiler::check_enabled()?;
+        if !self.active.load(Ordering::SeqCst) {
+            return Err(not_started_error());
+        }
+        deactivate_prof().map_err(internal_error)?;
+        self.active.store(false, Ordering::SeqCst);
+        info!("Heap profiling has been stopped");
+        Ok(())
+    }
+
+    /// Dump the heap profile into the dump dir and return the raw jeprof-compatible content.
+    pub fn dump(&self) -> poem::Result<(PathBuf, Vec<u8>)> {
+        HeapProfiler::check_enabled()?;
+        if !self.active.load(Ordering::SeqCst) {
+            return Err(not_started_error());
+        }
+        std::fs::create_dir_all(&self.dump_dir).map_err(|e| internal_error(e.into()))?;
+        let path = self
+            .dump_dir
+            .join(format!("heap_{}.prof", util::now_timestamp_as_millis()));
+        let buf = dump_prof(&path.to_string_lossy()).map_err(internal_error)?;
+        self.retain(path.clone(), buf.len() as u64);
+        Ok((path, buf))
+    }
+
+    fn retain(&self, path: PathBuf, size: u64) {
+        let mut dumps = self.dumps.lock();
+        dumps.push_back((path, size));
+        let mut total: u64 = dumps.iter().map(|(_, size)| size).sum();
+        // the latest dump is always retained
+        while total > self.max_retained_bytes && dumps.len() > 1 {
+            if let Some((path, size)) = dumps.pop_front() {
+                if let Err(e) = std::fs::remove_file(&path) {
+                    warn!(
+                        "Errors on removing the heap dump: {:?}. err: {:?}",
+                        &path, e
+                    );
+                }
+                total -= size;
+            }
+        }
+    }
+}
+
+fn not_started_error() -> poem::Error {
+    poem::Error::from_string(
+        "heap profiling is not started, POST /debug/heap_profile/start firstly",
+        StatusCode::CONFLICT,
+    )
+}
+
+fn internal_error(e: ProfError) -> poem::Error {
+    poem::Error::from_string(e.to_string(), StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+#[handler]
+fn heap_profile_handler(
+    Path(action): Path<String>,
+    profiler: Data<&Arc<HeapProfiler>>,
+) -> poem::Result<Response> {
+    match action.as_str() {
+        "start" => {
+            profiler.start()?;
+            Ok("started".into_response())
+        }
+        "stop" => {
+            profiler.stop()?;
+            Ok("stopped".into_response())
+        }
+        "dump" => {
+            let (path, buf) = profiler.dump()?;
+            let len = buf.len();
+            Ok(buf
+                .with_header("content-length", len)
+                .with_header(
+                    "Content-Disposition",
+                    format!("attachment; filename=\"{}\"", path.to_string_lossy()),
+                )
+                .into_response())
+        }
+        _ => Err(poem::Error::from_string(
+            format!(
+                "unknown action: {}, it should be one of start/dump/stop",
+                action
+            ),
+            StatusCode::NOT_FOUND,
+        )),
+    }
+}
+
+pub struct HeapProfileHandler {
+    profiler: Arc<HeapProfiler>,
+}
+
+impl Default for HeapProfileHandler {
+    fn default() -> Self {
+        HeapProfileHandler::new(Arc::new(HeapProfiler::default()))
+    }
+}
+
+impl HeapProfileHandler {
+    pub fn new(profiler: Arc<HeapProfiler>) -> Self {
+        Self { profiler }
+    }
+}
+
+impl Handler for HeapProfileHandler {
+    fn get_route_method(&self) -> RouteMethod {
+        RouteMethod::new().post(heap_profile_handler.data(self.profiler.clone()))
+    }
+
+    fn get_route_path(&self) -> String {
+        "/debug/heap_profile/:action".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::http::heap_profile::{HeapProfileHandler, HeapProfiler};
+    use crate::http::Handler;
+    use poem::http::StatusCode;
+    use poem::test::TestClient;
+    use poem::Route;
+    use std::sync::Arc;
+
+    #[cfg(not(all(unix, feature = "memory-prof")))]
+    #[tokio::test]
+    async fn test_unsupported() {
+        let temp_dir = tempdir::TempDir::new("test_heap_profile_unsupported").unwrap();
+        let profiler = HeapProfiler::new(temp_dir.path().to_path_buf(), 1024);
+        let handler = HeapProfileHandler::new(Arc::new(profiler));
+        let app = Route::new().at(handler.get_route_path(), handler.get_route_method());
+        let cli = TestClient::new(app);
+
+        for action in ["start", "dump", "stop"] {
+            let resp = cli
+                .post(format!("/debug/heap_profile/{}", action))
+                .send()
+                .await;
+            resp.assert_status(StatusCode::NOT_IMPLEMENTED);
+        }
+    }
+
+    #[cfg(all(unix, feature = "memory-prof"))]
+    #[tokio::test]
+    async fn test_start_dump_stop() {
+        let temp_dir = tempdir::TempDir::new("test_heap_profile").unwrap();
+        let profiler = HeapProfiler::new(temp_dir.path().to_path_buf(), 1024 * 1024 * 1024);
+        let handler = HeapProfileHandler::new(Arc::new(profiler));
+        let app = Route::new().at(handler.get_route_path(), handler.get_route_method());
+        let cli = TestClient::new(app);
+
+        // dump before starting is rejected
+        let resp = cli.post("/debug/heap_profile/dump").send().await;
+        resp.assert_status(StatusCode::CONFLICT);
+
+        let resp = cli.post("/debug/heap_profile/start").send().await;
+        resp.assert_status_is_ok();
+        let resp = cli.post("/debug/heap_profile/start").send().await;
+        resp.assert_status(StatusCode::CONFLICT);
+
+        let _allocated = vec![0u8; 16 * 1024 * 1024];
+        let resp = cli.post("/debug/heap_profile/dump").send().await;
+        resp.assert_status_is_ok();
+        let body = resp.0.into_body().into_vec().await.unwrap();
+        assert!(!body.is_empty());
+
+        let dumps: Vec<_> = std::fs::read_dir(temp_dir.path())
+            .unwrap()
+            .map(|x| x.unwrap().path())
+            .collect();
+        assert_eq!(1, dumps.len());
+        assert!(std::fs::metadata(&dumps[0]).unwrap().len() > 0);
+
+        let resp = cli.post("/debug/heap_profile/stop").send().await;
+        resp.assert_status_is_ok();
+    }
+}
@@ -23,11 +23,12 @@ use poem::http::StatusCode;
 use poem::listener::TcpListener;
 use poem::{get, Route, RouteMethod, Server};
 
+use std::net::SocketAddr;
 use std::sync::Mutex;
 
 use crate::http::{HTTPServer, Handler};
 use crate::runtime::manager::RuntimeManager;
-use crate::util::is_port_used;
+use crate::util::is_addr_used;
 
 impl ResponseError for WorkerError {
     fn status(&self) -> StatusCode {
@@ -63,21 +64,23 @@ impl PoemHTTPServer {
 }
 
 impl HTTPServer for PoemHTTPServer {
-    fn start(&self, runtime_manager: RuntimeManager, port: u16) {
-        if is_port_used(port) {
-            panic!("The http service port:{:?} has been used.", port);
+    fn start(&self, runtime_manager: RuntimeManager, addr: SocketAddr) {
+        if is_addr_used(addr) {
+            panic!("The http service address:{:?} has been used.", addr);
         }
         let mut app = Route::new();
         let handlers = self.handlers.lock().unwrap();
         for handler in handlers.iter() {
             app = app.at(handler.get_route_path(), handler.get_route_method());
         }
-        runtime_manager.http_runtime.spawn(async move {
-            let _ = Server::new(TcpListener::bind(format!("0.0.0.0:{}", port)))
-                .name("uniffle-server-http-service")
-                .run(app)
-                .await;
-        });
+        runtime_manager
+            .http_runtime
+            .spawn_guarded("http_server", async move {
+                let _ = Server::new(TcpListener::bind(addr))
+                    .name("uniffle-server-http-service")
+                    .run(app)
+                    .await;
+            });
     }
 
     fn register_handler(&self, handler: impl Handler + 'static) {
@@ -0,0 +1,173 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::http::Handler;
+use crate::log_service::LogFilterReloader;
+use poem::http::StatusCode;
+use poem::web::Data;
+use poem::{handler, EndpointExt, Request, RouteMethod};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+#[derive(Deserialize, Serialize, Default)]
+#[serde(default)]
+pub struct LogLevelRequest {
+    // the duration form like "10m", the filter will be reverted after it
+    pub(crate) ttl: Option<String>,
+}
+
+#[handler]
+fn get_log_level_handler(reloader: Data<&Arc<LogFilterReloader>>) -> String {
+    reloader.current()
+}
+
+/// The body is the filter directives, like `debug` or `info,uniffle_worker::store=debug`
+#[handler]
+fn put_log_level_handler(
+    req: &Request,
+    body: String,
+    reloader: Data<&Arc<LogFilterReloader>>,
+) -> poem::Result<String> {
+    let params = req.params::<LogLevelRequest>()?;
+    let ttl = match params.ttl {
+        Some(ttl) => Some(
+            humantime::parse_duration(&ttl)
+                .map_err(|e| poem::Error::from_string(e.to_string(), StatusCode::BAD_REQUEST))?,
+        ),
+        _ => None,
+    };
+    reloader
+        .update(body.trim(), ttl)
+        .map_err(|e| poem::Error::from_string(e.to_string(), StatusCode::BAD_REQUEST))?;
+    Ok(reloader.current())
+}
+
+pub struct LogLevelHandler {
+    reloader: Arc<LogFilterReloader>,
+}
+
+impl LogLevelHandler {
+    pub fn new(reloader: Arc<LogFilterReloader>) -> Self {
+        Self { reloader }
+    }
+}
+
+impl Handler for LogLevelHandler {
+    fn get_route_method(&self) -> RouteMethod {
+        RouteMethod::new()
+            .get(get_log_level_handler.data(self.reloader.clone()))
+            .put(put_log_level_handler.data(self.reloader.clone()))
+    }
+
+    fn get_route_path(&self) -> String {
+        "/debug/log_level".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::http::log_level::LogLevelHandler;
+    use crate::http::Handler;
+    use crate::log_service::LogFilterReloader;
+    use parking_lot::Mutex;
+    use poem::test::TestClient;
+    use poem::Route;
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tracing::{Event, Level};
+    use tracing_subscriber::layer::{Context, SubscriberExt};
+    use tracing_subscriber::{reload, EnvFilter, Layer};
+
+    struct EventRecorder {
+        records: Arc<Mutex<Vec<(Level, String)>>>,
+    }
+
+    impl<S: tracing::Subscriber> Layer<S> for EventRecorder {
+        fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+            let metadata = event.metadata();
+            self.records
+                .lock()
+                .push((*metadata.level(), metadata.target().to_string()));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_router() {
+        let records = Arc::new(Mutex::new(vec![]));
+        let (filter, handle) = reload::Layer::new(EnvFilter::new("info"));
+        let subscriber = tracing_subscriber::registry()
+            .with(filter)
+            .with(EventRecorder {
+                records: records.clone(),
+            });
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let reloader = Arc::new(LogFilterReloader::new(handle, "info"));
+        let handler = LogLevelHandler::new(reloader.clone());
+        let app = Route::new().at(handler.get_route_path(), handler.get_route_method());
+        let cli = TestClient::new(app);
+
+        let debug_count = |records: &Arc<Mutex<Vec<(Level, String)>>>| {
+            records
+                .lock()
+                .iter()
+                .filter(|(level, target)| *level == Level::DEBUG && target == "log_level_test")
+                .count()
+        };
+
+        // case1: debug log is filtered by default
+        tracing::debug!(target: "log_level_test", "before change");
+        assert_eq!(0, debug_count(&records));
+
+        let resp = cli.get("/debug/log_level").send().await;
+        resp.assert_status_is_ok();
+        resp.assert_text("info").await;
+
+        // case2: enable the debug log for the target
+        let resp = cli
+            .put("/debug/log_level")
+            .body("info,log_level_test=debug")
+            .send()
+            .await;
+        resp.assert_status_is_ok();
+        tracing::debug!(target: "log_level_test", "after change");
+        tracing::debug!(target: "other_target", "after change");
+        assert_eq!(1, debug_count(&records));
+        assert_eq!("info,log_level_test=debug", reloader.current());
+
+        // case3: illegal directives are rejected
+        let resp = cli
+            .put("/debug/log_level")
+            .body("log_level_test=not_a_level")
+            .send()
+            .await;
+        resp.assert_status(poem::http::StatusCode::BAD_REQUEST);
+        assert_eq!("info,log_level_test=debug", reloader.current());
+
+        // case4: auto revert after the ttl
+        let resp = cli
+            .put("/debug/log_level")
+            .query("ttl", &"100ms")
+            .body("debug")
+            .send()
+            .await;
+        resp.assert_status_is_ok();
+        assert_eq!("debug", reloader.current());
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        assert_eq!("info,log_level_test=debug", reloader.current());
+    }
+}
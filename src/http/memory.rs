@@ -0,0 +1,153 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::app::AppManagerRef;
+use crate::http::apps::{Page, PageRequest, MAX_PAGE_LIMIT};
+use crate::http::Handler;
+use crate::store::mem::snapshot::{BufferEntry, MemoryTotals, TicketReservation};
+use poem::web::{Data, Json};
+use poem::{handler, EndpointExt, Request, RouteMethod};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct MemorySnapshotInfo {
+    pub capacity: i64,
+    pub tracked: MemoryTotals,
+    pub summed: MemoryTotals,
+    pub drift: MemoryTotals,
+    pub drifted: bool,
+    pub tickets: Vec<TicketReservation>,
+    // the buffers are paginated, while the totals are summed over all of them
+    pub buffers: Page<BufferEntry>,
+}
+
+#[handler]
+fn memory_snapshot_handler(
+    req: &Request,
+    app_manager_ref: Data<&AppManagerRef>,
+) -> poem::Result<Json<MemorySnapshotInfo>> {
+    let page = req.params::<PageRequest>()?;
+
+    let snapshot = app_manager_ref.store_memory_buffers_snapshot();
+    let total = snapshot.buffers.len();
+    Ok(Json(MemorySnapshotInfo {
+        capacity: snapshot.capacity,
+        tracked: snapshot.tracked,
+        summed: snapshot.summed,
+        drift: snapshot.drift,
+        drifted: snapshot.drifted,
+        tickets: snapshot.tickets,
+        buffers: Page {
+            total,
+            offset: page.offset,
+            limit: page.limit.min(MAX_PAGE_LIMIT),
+            items: page.slice(snapshot.buffers),
+        },
+    }))
+}
+
+pub struct MemorySnapshotHandler {
+    app_manager_ref: AppManagerRef,
+}
+
+impl MemorySnapshotHandler {
+    pub fn new(app_manager_ref: AppManagerRef) -> Self {
+        Self { app_manager_ref }
+    }
+}
+
+impl Handler for MemorySnapshotHandler {
+    fn get_route_method(&self) -> RouteMethod {
+        RouteMethod::new().get(memory_snapshot_handler.data(self.app_manager_ref.clone()))
+    }
+
+    fn get_route_path(&self) -> String {
+        "/debug/memory/snapshot".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::app::{AppManager, PartitionedUId, WritingViewContext};
+    use crate::config::{Config, MemoryStoreConfig, StorageType};
+    use crate::http::memory::{MemorySnapshotHandler, MemorySnapshotInfo};
+    use crate::http::Handler;
+    use crate::store::Block;
+    use poem::test::TestClient;
+    use poem::Route;
+
+    #[tokio::test]
+    async fn test_router() {
+        let mut config = Config::default();
+        config.memory_store = Some(MemoryStoreConfig::new((1024 * 1024).to_string()));
+        config.store_type = StorageType::MEMORY;
+        let app_manager_ref = AppManager::get_ref(Default::default(), config);
+        app_manager_ref
+            .register("app_a".to_string(), 1, Default::default())
+            .unwrap();
+        let app = app_manager_ref.get_app("app_a").unwrap();
+        for partition_id in (0..3).rev() {
+            let uid = PartitionedUId::from("app_a".to_string(), 1, partition_id);
+            let block = Block {
+                block_id: partition_id as i64,
+                length: 10,
+                uncompress_length: 10,
+                crc: 0,
+                data: Default::default(),
+                task_attempt_id: 0,
+            };
+            app.insert(WritingViewContext::from(uid, vec![block]))
+                .await
+                .unwrap();
+        }
+
+        let handler = MemorySnapshotHandler::new(app_manager_ref);
+        let app = Route::new().at(handler.get_route_path(), handler.get_route_method());
+        let cli = TestClient::new(app);
+
+        let resp = cli
+            .get("/debug/memory/snapshot")
+            .query("limit", &2)
+            .send()
+            .await;
+        resp.assert_status_is_ok();
+        let body = resp.0.into_body().into_string().await.unwrap();
+        let info: MemorySnapshotInfo = serde_json::from_str(&body).unwrap();
+        assert_eq!(1024 * 1024, info.capacity);
+        assert_eq!(30, info.summed.used);
+        assert_eq!(3, info.buffers.total);
+        assert_eq!(
+            vec![0, 1],
+            info.buffers
+                .items
+                .iter()
+                .map(|x| x.partition_id)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(10, info.buffers.items[0].staging_bytes);
+
+        let resp = cli
+            .get("/debug/memory/snapshot")
+            .query("offset", &2)
+            .send()
+            .await;
+        let body = resp.0.into_body().into_string().await.unwrap();
+        let info: MemorySnapshotInfo = serde_json::from_str(&body).unwrap();
+        assert_eq!(1, info.buffers.items.len());
+        assert_eq!(2, info.buffers.items[0].partition_id);
+    }
+}
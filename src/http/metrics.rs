@@ -16,10 +16,20 @@
 // under the License.
 
 use crate::http::Handler;
-use crate::metric::REGISTRY;
+#[cfg(all(unix, feature = "allocator-analysis"))]
+use crate::mem_allocator::ALLOCATOR;
+use crate::metric::gather_all_metrics;
+#[cfg(all(unix, feature = "allocator-analysis"))]
+use crate::metric::GAUGE_ALLOCATOR_ALLOCATED_SIZE;
+use flate2::write::GzEncoder;
+use flate2::Compression;
 use log::error;
 use poem::endpoint::make_sync;
-use poem::{get, RouteMethod};
+use poem::http::header;
+use poem::http::StatusCode;
+use poem::{get, Request, Response, RouteMethod};
+use prometheus::{Encoder, TextEncoder};
+use std::io::Write;
 
 pub struct MetricsHTTPHandler {}
 
@@ -29,44 +39,130 @@ impl Default for MetricsHTTPHandler {
     }
 }
 
+fn accept_gzip(req: &Request) -> bool {
+    req.headers()
+        .get_all(header::ACCEPT_ENCODING)
+        .iter()
+        .filter_map(|x| x.to_str().ok())
+        .any(|x| {
+            x.split(',')
+                .any(|encoding| encoding.trim().starts_with("gzip"))
+        })
+}
+
+fn gzip(data: &[u8]) -> std::io::Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(data)?;
+    encoder.finish()
+}
+
+fn metrics_handler(req: Request) -> Response {
+    // refresh the allocator size metrics
+    #[cfg(all(unix, feature = "allocator-analysis"))]
+    GAUGE_ALLOCATOR_ALLOCATED_SIZE.set(ALLOCATOR.allocated() as i64);
+
+    let encoder = TextEncoder::new();
+    let mut buffer = Vec::new();
+    if let Err(e) = encoder.encode(&gather_all_metrics(), &mut buffer) {
+        error!("could not encode metrics: {:?}", e);
+        return Response::builder()
+            .status(StatusCode::INTERNAL_SERVER_ERROR)
+            .body(format!("could not encode metrics: {:?}", e));
+    }
+
+    let builder = Response::builder().content_type(encoder.format_type());
+    if !accept_gzip(&req) {
+        return builder.body(buffer);
+    }
+    match gzip(&buffer) {
+        Ok(compressed) => builder
+            .header(header::CONTENT_ENCODING, "gzip")
+            .body(compressed),
+        Err(e) => {
+            error!("could not compress metrics with gzip: {:?}", e);
+            builder.body(buffer)
+        }
+    }
+}
+
 impl Handler for MetricsHTTPHandler {
     fn get_route_method(&self) -> RouteMethod {
-        get(make_sync(|_| {
-            use prometheus::Encoder;
-            let encoder = prometheus::TextEncoder::new();
-
-            let mut buffer = Vec::new();
-            if let Err(e) = encoder.encode(&REGISTRY.gather(), &mut buffer) {
-                error!("could not encode custom metrics: {:?}", e);
-            };
-            let mut res = match String::from_utf8(buffer.clone()) {
-                Ok(v) => v,
-                Err(e) => {
-                    error!("custom metrics could not be from_utf8'd: {:?}", e);
-                    String::default()
-                }
-            };
-            buffer.clear();
-
-            let mut buffer = Vec::new();
-            if let Err(e) = encoder.encode(&prometheus::gather(), &mut buffer) {
-                error!("could not encode prometheus metrics: {:?}", e);
-            };
-            let res_custom = match String::from_utf8(buffer.clone()) {
-                Ok(v) => v,
-                Err(e) => {
-                    error!("prometheus metrics could not be from_utf8'd: {}", e);
-                    String::default()
-                }
-            };
-            buffer.clear();
-
-            res.push_str(&res_custom);
-            res
-        }))
+        get(make_sync(metrics_handler))
     }
 
     fn get_route_path(&self) -> String {
         "/metrics".to_string()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::http::metrics::MetricsHTTPHandler;
+    use crate::http::Handler;
+    use crate::metric::{TOTAL_EVENT_BUS_EVENT_HANDLED_SIZE, TOTAL_EVENT_BUS_EVENT_PUBLISHED_SIZE};
+    use flate2::read::GzDecoder;
+    use poem::http::header;
+    use poem::test::TestClient;
+    use poem::Route;
+    use std::io::Read;
+
+    fn parse_counter(body: &str, name: &str, label: &str) -> Option<f64> {
+        body.lines()
+            .filter(|line| !line.starts_with('#'))
+            .find(|line| line.starts_with(name) && line.contains(label))
+            .and_then(|line| line.rsplit(' ').next())
+            .and_then(|value| value.parse::<f64>().ok())
+    }
+
+    #[tokio::test]
+    async fn test_router() {
+        TOTAL_EVENT_BUS_EVENT_PUBLISHED_SIZE
+            .with_label_values(&["test_metrics_endpoint", "test"])
+            .inc_by(3);
+        TOTAL_EVENT_BUS_EVENT_HANDLED_SIZE
+            .with_label_values(&["test_metrics_endpoint"])
+            .inc_by(2);
+
+        let handler = MetricsHTTPHandler::default();
+        let app = Route::new().at(handler.get_route_path(), handler.get_route_method());
+        let cli = TestClient::new(app);
+
+        // case1: plain text
+        let resp = cli.get("/metrics").send().await;
+        resp.assert_status_is_ok();
+        resp.assert_content_type("text/plain; version=0.0.4");
+        let body = resp.0.into_body().into_string().await.unwrap();
+        let label = r#"name="test_metrics_endpoint""#;
+        assert_eq!(
+            Some(3.0),
+            parse_counter(&body, "eventbus_total_published_event_size", label)
+        );
+        assert_eq!(
+            Some(2.0),
+            parse_counter(&body, "eventbus_total_handled_event_size", label)
+        );
+        assert_eq!(
+            1,
+            body.matches("# TYPE eventbus_total_published_event_size ")
+                .count()
+        );
+
+        // case2: gzip
+        let resp = cli
+            .get("/metrics")
+            .header(header::ACCEPT_ENCODING, "deflate, gzip")
+            .send()
+            .await;
+        resp.assert_status_is_ok();
+        resp.assert_header(header::CONTENT_ENCODING, "gzip");
+        let compressed = resp.0.into_body().into_vec().await.unwrap();
+        let mut body = String::new();
+        GzDecoder::new(compressed.as_slice())
+            .read_to_string(&mut body)
+            .unwrap();
+        assert_eq!(
+            Some(3.0),
+            parse_counter(&body, "eventbus_total_published_event_size", label)
+        );
+    }
+}
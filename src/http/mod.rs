@@ -15,33 +15,59 @@
 // specific language governing permissions and limitations
 // under the License.
 
+mod apps;
 mod await_tree;
+mod decommission;
+mod fault;
+mod health;
+mod heap_profile;
 mod http_service;
 mod jeprof;
+mod log_level;
+mod memory;
 mod metrics;
+#[cfg(unix)]
 mod pprof;
+mod read_cache;
+mod spill;
 
+use crate::app::AppManagerRef;
 use crate::config::Config;
+use crate::decommission::DecommissionManager;
+use crate::health::HEALTH_REGISTRY;
+use crate::http::apps::{AppsHandler, HotPartitionsHandler, ShufflePartitionsHandler};
 use crate::http::await_tree::AwaitTreeHandler;
+use crate::http::decommission::DecommissionHandler;
+use crate::http::fault::FaultInjectionHandler;
+use crate::http::health::{HealthDetailHandler, HealthHandler};
+use crate::http::heap_profile::HeapProfileHandler;
 use crate::http::http_service::PoemHTTPServer;
 use crate::http::jeprof::JeProfHandler;
+use crate::http::log_level::LogLevelHandler;
+use crate::http::memory::MemorySnapshotHandler;
 use crate::http::metrics::MetricsHTTPHandler;
+#[cfg(unix)]
 use crate::http::pprof::PProfHandler;
+use crate::http::read_cache::ReadCacheHandler;
+use crate::http::spill::SpillStatusHandler;
+use crate::log_service::LOG_FILTER_RELOADER;
 use crate::runtime::manager::RuntimeManager;
 
 use log::info;
 use poem::RouteMethod;
+use std::net::SocketAddr;
 
 pub struct HttpMonitorService;
 impl HttpMonitorService {
-    pub fn init(config: &Config, runtime_manager: RuntimeManager) {
+    pub fn init(config: &Config, runtime_manager: RuntimeManager, app_manager_ref: AppManagerRef) {
         let http_port = config.http_monitor_service_port;
+        let addr = SocketAddr::new(config.bind_address().unwrap(), http_port);
         info!(
-            "Starting http monitor service with port:[{}] ......",
-            http_port
+            "Starting http monitor service with address:[{}] ......",
+            addr
         );
-        let server = new_server();
-        server.start(runtime_manager, http_port);
+        let server = new_server(app_manager_ref, config.server.fault_injection_http_enabled);
+        server.start(runtime_manager, addr);
     }
 }
 
@@ -52,15 +78,39 @@ pub trait Handler {
 }
 
 pub trait HTTPServer: Send + Sync {
-    fn start(&self, runtime_manager: RuntimeManager, port: u16);
+    fn start(&self, runtime_manager: RuntimeManager, addr: SocketAddr);
     fn register_handler(&self, handler: impl Handler + 'static);
 }
 
-fn new_server() -> Box<PoemHTTPServer> {
+fn new_server(
+    app_manager_ref: AppManagerRef,
+    fault_injection_enabled: bool,
+) -> Box<PoemHTTPServer> {
     let server = PoemHTTPServer::new();
+    #[cfg(unix)]
     server.register_handler(PProfHandler::default());
     server.register_handler(MetricsHTTPHandler::default());
     server.register_handler(AwaitTreeHandler::default());
+    server.register_handler(AwaitTreeHandler::with_path("/await-tree"));
     server.register_handler(JeProfHandler::default());
+    server.register_handler(HeapProfileHandler::default());
+    server.register_handler(HealthHandler::new(HEALTH_REGISTRY.clone()));
+    server.register_handler(HealthDetailHandler::new(HEALTH_REGISTRY.clone()));
+    server.register_handler(AppsHandler::new(app_manager_ref.clone()));
+    server.register_handler(ShufflePartitionsHandler::new(app_manager_ref.clone()));
+    server.register_handler(HotPartitionsHandler::new(app_manager_ref.clone()));
+    server.register_handler(SpillStatusHandler::new(app_manager_ref.clone()));
+    server.register_handler(ReadCacheHandler::new(app_manager_ref.clone()));
+    server.register_handler(MemorySnapshotHandler::new(app_manager_ref.clone()));
+    server.register_handler(DecommissionHandler::new(DecommissionManager::new(
+        app_manager_ref,
+    )));
+    if fault_injection_enabled {
+        server.register_handler(FaultInjectionHandler::default());
+    }
+    // only available when the log service is initialized
+    if let Some(reloader) = LOG_FILTER_RELOADER.get() {
+        server.register_handler(LogLevelHandler::new(reloader.clone()));
+    }
     Box::new(server)
 }
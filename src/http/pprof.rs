@@ -19,19 +19,51 @@ use crate::error::WorkerError;
 use crate::http::Handler;
 use log::error;
 
-use poem::{handler, Request, RouteMethod};
+use poem::http::{header, StatusCode};
+use poem::{handler, Request, Response, RouteMethod};
 use pprof::protos::Message;
 use pprof::ProfilerGuard;
 use serde::{Deserialize, Serialize};
 use std::num::NonZeroI32;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::Duration;
 use tokio::time::sleep as delay_for;
 
+const MAX_PROFILE_SECONDS: u64 = 300;
+
+// only one profile is allowed to run at a time
+static PROFILING: AtomicBool = AtomicBool::new(false);
+
+struct ProfilingPermit;
+
+impl ProfilingPermit {
+    fn try_acquire() -> Option<ProfilingPermit> {
+        PROFILING
+            .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
+            .ok()
+            .map(|_| ProfilingPermit)
+    }
+}
+
+impl Drop for ProfilingPermit {
+    fn drop(&mut self) {
+        PROFILING.store(false, Ordering::SeqCst);
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ProfileFormat {
+    Proto,
+    Flamegraph,
+}
+
 #[derive(Deserialize, Serialize)]
 #[serde(default)]
 pub struct PProfRequest {
     pub(crate) seconds: u64,
     pub(crate) frequency: NonZeroI32,
+    pub(crate) format: ProfileFormat,
 }
 
 impl Default for PProfRequest {
@@ -39,37 +71,63 @@ impl Default for PProfRequest {
         PProfRequest {
             seconds: 5,
             frequency: NonZeroI32::new(100).unwrap(),
+            format: ProfileFormat::Proto,
         }
     }
 }
 
 #[handler]
-async fn pprof_handler(req: &Request) -> poem::Result<Vec<u8>, WorkerError> {
+async fn pprof_handler(req: &Request) -> poem::Result<Response> {
     let req = req.params::<PProfRequest>()?;
-    let mut body: Vec<u8> = Vec::new();
+
+    let _permit = match ProfilingPermit::try_acquire() {
+        Some(permit) => permit,
+        _ => {
+            return Ok(Response::builder()
+                .status(StatusCode::CONFLICT)
+                .body("Another profiling is running, please retry later."))
+        }
+    };
 
     let guard = ProfilerGuard::new(req.frequency.into()).map_err(|e| {
         let msg = format!("could not start profiling: {:?}", e);
         error!("{}", msg);
         WorkerError::HTTP_SERVICE_ERROR(msg)
     })?;
-    delay_for(Duration::from_secs(req.seconds)).await;
+    delay_for(Duration::from_secs(req.seconds.min(MAX_PROFILE_SECONDS))).await;
     let report = guard.report().build().map_err(|e| {
         let msg = format!("could not build profiling report: {:?}", e);
         error!("{}", msg);
         WorkerError::HTTP_SERVICE_ERROR(msg)
     })?;
-    let profile = report.pprof().map_err(|e| {
-        let msg = format!("could not get pprof profile: {:?}", e);
-        error!("{}", msg);
-        WorkerError::HTTP_SERVICE_ERROR(msg)
-    })?;
-    profile.write_to_vec(&mut body).map_err(|e| {
-        let msg = format!("could not write pprof profile: {:?}", e);
-        error!("{}", msg);
-        WorkerError::HTTP_SERVICE_ERROR(msg)
-    })?;
-    Ok(body)
+
+    let mut body: Vec<u8> = Vec::new();
+    let content_type = match req.format {
+        ProfileFormat::Flamegraph => {
+            report.flamegraph(&mut body).map_err(|e| {
+                let msg = format!("could not write flamegraph: {:?}", e);
+                error!("{}", msg);
+                WorkerError::HTTP_SERVICE_ERROR(msg)
+            })?;
+            "image/svg+xml"
+        }
+        ProfileFormat::Proto => {
+            let profile = report.pprof().map_err(|e| {
+                let msg = format!("could not get pprof profile: {:?}", e);
+                error!("{}", msg);
+                WorkerError::HTTP_SERVICE_ERROR(msg)
+            })?;
+            profile.write_to_vec(&mut body).map_err(|e| {
+                let msg = format!("could not write pprof profile: {:?}", e);
+                error!("{}", msg);
+                WorkerError::HTTP_SERVICE_ERROR(msg)
+            })?;
+            "application/octet-stream"
+        }
+    };
+    Ok(Response::builder()
+        .header(header::CONTENT_TYPE, content_type)
+        .body(body))
 }
 
 pub struct PProfHandler {}
@@ -92,22 +150,68 @@ impl Handler for PProfHandler {
 
 #[cfg(test)]
 mod tests {
-    use crate::http::pprof::PProfHandler;
+    use crate::http::pprof::{PProfHandler, ProfilingPermit};
     use crate::http::Handler;
+    use poem::http::StatusCode;
     use poem::test::TestClient;
     use poem::Route;
+    use pprof::protos::{Message, Profile};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
 
     #[tokio::test]
     async fn test_router() {
         let handler = PProfHandler::default();
         let app = Route::new().at(handler.get_route_path(), handler.get_route_method());
         let cli = TestClient::new(app);
+
+        // burn the cpu to make the samples
+        let stopped = Arc::new(AtomicBool::new(false));
+        let stopped_cloned = stopped.clone();
+        let burner = std::thread::spawn(move || {
+            let mut x: u64 = 0;
+            while !stopped_cloned.load(Ordering::Relaxed) {
+                x = x.wrapping_mul(31).wrapping_add(7);
+            }
+            x
+        });
+
+        // case1: proto format
         let resp = cli
             .get("/debug/pprof/profile")
-            .query("seconds", &4)
+            .query("seconds", &1)
             .query("frequency", &100)
             .send()
             .await;
         resp.assert_status_is_ok();
+        let body = resp.0.into_body().into_vec().await.unwrap();
+        let profile = Profile::parse_from_bytes(&body).unwrap();
+        assert!(!profile.sample.is_empty());
+
+        // case2: flamegraph format
+        let resp = cli
+            .get("/debug/pprof/profile")
+            .query("seconds", &1)
+            .query("format", &"flamegraph")
+            .send()
+            .await;
+        resp.assert_status_is_ok();
+        resp.assert_content_type("image/svg+xml");
+        let body = resp.0.into_body().into_string().await.unwrap();
+        assert!(body.contains("<svg"));
+        assert!(body.trim_end().ends_with("</svg>"));
+
+        // case3: reject the concurrent profiling
+        let permit = ProfilingPermit::try_acquire().unwrap();
+        let resp = cli
+            .get("/debug/pprof/profile")
+            .query("seconds", &1)
+            .send()
+            .await;
+        resp.assert_status(StatusCode::CONFLICT);
+        drop(permit);
+
+        stopped.store(true, Ordering::Relaxed);
+        burner.join().unwrap();
     }
 }
@@ -0,0 +1,98 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+use crate::app::AppManagerRef;
+use crate::http::Handler;
+use crate::store::read_cache::ReadCacheStats;
+use poem::web::{Data, Json};
+use poem::{handler, EndpointExt, RouteMethod};
+
+#[handler]
+fn get_read_cache_handler(app_manager_ref: Data<&AppManagerRef>) -> Json<ReadCacheStats> {
+    Json(app_manager_ref.store_read_cache_stats())
+}
+
+#[handler]
+fn clear_read_cache_handler(app_manager_ref: Data<&AppManagerRef>) -> Json<ReadCacheStats> {
+    app_manager_ref.store_clear_read_cache();
+    Json(app_manager_ref.store_read_cache_stats())
+}
+
+pub struct ReadCacheHandler {
+    app_manager_ref: AppManagerRef,
+}
+
+impl ReadCacheHandler {
+    pub fn new(app_manager_ref: AppManagerRef) -> Self {
+        Self { app_manager_ref }
+    }
+}
+
+impl Handler for ReadCacheHandler {
+    fn get_route_method(&self) -> RouteMethod {
+        RouteMethod::new()
+            .get(get_read_cache_handler.data(self.app_manager_ref.clone()))
+            .delete(clear_read_cache_handler.data(self.app_manager_ref.clone()))
+    }
+
+    fn get_route_path(&self) -> String {
+        "/admin/read-cache".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::app::AppManager;
+    use crate::config::{
+        Config, HybridStoreConfig, LocalfileStoreConfig, MemoryStoreConfig, StorageType,
+    };
+    use crate::http::read_cache::ReadCacheHandler;
+    use crate::http::Handler;
+    use crate::store::read_cache::ReadCacheStats;
+    use poem::test::TestClient;
+    use poem::Route;
+
+    #[tokio::test]
+    async fn test_router() {
+        let temp_dir = tempdir::TempDir::new("test_http_read_cache").unwrap();
+        let temp_path = temp_dir.path().to_str().unwrap().to_string();
+
+        let mut config = Config::default();
+        config.memory_store = Some(MemoryStoreConfig::new((1024 * 1024).to_string()));
+        config.localfile_store = Some(LocalfileStoreConfig::new(vec![temp_path]));
+        config.hybrid_store = HybridStoreConfig::default();
+        config.hybrid_store.read_cache_capacity = Some("1M".to_string());
+        config.store_type = StorageType::MEMORY_LOCALFILE;
+        let app_manager_ref = AppManager::get_ref(Default::default(), config);
+
+        let handler = ReadCacheHandler::new(app_manager_ref);
+        let app = Route::new().at(handler.get_route_path(), handler.get_route_method());
+        let cli = TestClient::new(app);
+
+        let resp = cli.get("/admin/read-cache").send().await;
+        resp.assert_status_is_ok();
+        let body = resp.0.into_body().into_string().await.unwrap();
+        let stats: ReadCacheStats = serde_json::from_str(&body).unwrap();
+        assert_eq!(1024 * 1024, stats.capacity);
+        assert_eq!(0, stats.used);
+
+        let resp = cli.delete("/admin/read-cache").send().await;
+        resp.assert_status_is_ok();
+        let body = resp.0.into_body().into_string().await.unwrap();
+        let stats: ReadCacheStats = serde_json::from_str(&body).unwrap();
+        assert_eq!(0, stats.segments);
+    }
+}
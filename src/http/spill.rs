@@ -0,0 +1,102 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::app::AppManagerRef;
+use crate::http::Handler;
+use crate::store::SpillStatus;
+use poem::web::{Data, Json};
+use poem::{handler, EndpointExt, RouteMethod};
+
+#[handler]
+async fn spill_status_handler(app_manager_ref: Data<&AppManagerRef>) -> Json<SpillStatus> {
+    Json(app_manager_ref.store_spill_status().await)
+}
+
+pub struct SpillStatusHandler {
+    app_manager_ref: AppManagerRef,
+}
+
+impl SpillStatusHandler {
+    pub fn new(app_manager_ref: AppManagerRef) -> Self {
+        Self { app_manager_ref }
+    }
+}
+
+impl Handler for SpillStatusHandler {
+    fn get_route_method(&self) -> RouteMethod {
+        RouteMethod::new().get(spill_status_handler.data(self.app_manager_ref.clone()))
+    }
+
+    fn get_route_path(&self) -> String {
+        "/spill-status".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::app::AppManager;
+    use crate::config::{
+        Config, HybridStoreConfig, LocalfileStoreConfig, MemoryStoreConfig, StorageType,
+    };
+    use crate::http::spill::SpillStatusHandler;
+    use crate::http::Handler;
+    use crate::store::{SpillConcurrency, SpillStatus};
+    use poem::test::TestClient;
+    use poem::Route;
+
+    #[tokio::test]
+    async fn test_router() {
+        let temp_dir = tempdir::TempDir::new("test_http_spill_status").unwrap();
+        let temp_path = temp_dir.path().to_str().unwrap().to_string();
+
+        let mut config = Config::default();
+        config.memory_store = Some(MemoryStoreConfig::new((1024 * 1024).to_string()));
+        let mut localfile_config = LocalfileStoreConfig::new(vec![temp_path]);
+        localfile_config.disk_max_concurrency = 10;
+        config.localfile_store = Some(localfile_config);
+        config.hybrid_store = HybridStoreConfig::default();
+        config.hybrid_store.memory_spill_max_concurrency = 5;
+        config.store_type = StorageType::MEMORY_LOCALFILE;
+        let app_manager_ref = AppManager::get_ref(Default::default(), config);
+
+        let handler = SpillStatusHandler::new(app_manager_ref);
+        let app = Route::new().at(handler.get_route_path(), handler.get_route_method());
+        let cli = TestClient::new(app);
+
+        let resp = cli.get("/spill-status").send().await;
+        resp.assert_status_is_ok();
+        let body = resp.0.into_body().into_string().await.unwrap();
+        let status: SpillStatus = serde_json::from_str(&body).unwrap();
+        assert_eq!(0, status.queued);
+        assert_eq!(
+            SpillConcurrency {
+                max_concurrency: 5,
+                available_permits: 5,
+            },
+            status.concurrency
+        );
+        assert_eq!(1, status.tiers.len());
+        assert_eq!("memory->localfile", status.tiers[0].tier);
+        assert_eq!(
+            SpillConcurrency {
+                max_concurrency: 10,
+                available_permits: 10,
+            },
+            status.tiers[0].concurrency
+        );
+    }
+}
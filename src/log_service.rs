@@ -1,12 +1,79 @@
+use anyhow::{anyhow, Result};
+use log::info;
+use parking_lot::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
 use tracing_appender::non_blocking::WorkerGuard;
 use tracing_subscriber::layer::SubscriberExt;
 use tracing_subscriber::util::SubscriberInitExt;
-use tracing_subscriber::{fmt, EnvFilter, Registry};
+use tracing_subscriber::{fmt, reload, EnvFilter, Registry};
 
 use crate::config::{LogConfig, RotationConfig};
 
 const LOG_FILE_NAME: &str = "uniffle-worker.log";
 
+pub static LOG_FILTER_RELOADER: OnceLock<Arc<LogFilterReloader>> = OnceLock::new();
+
+/// To change the log filter directives at runtime, like `info,uniffle_worker::store=debug`.
+pub struct LogFilterReloader {
+    reload: Box<dyn Fn(EnvFilter) -> Result<()> + Send + Sync>,
+    current: Mutex<String>,
+    // increased on every change to make the stale auto-revert no-op
+    version: AtomicU64,
+}
+
+impl LogFilterReloader {
+    pub fn new<S: 'static>(handle: reload::Handle<EnvFilter, S>, directives: &str) -> Self {
+        Self {
+            reload: Box::new(move |filter| {
+                handle
+                    .reload(filter)
+                    .map_err(|e| anyhow!("Errors on reloading log filter. err: {:?}", e))
+            }),
+            current: Mutex::new(directives.to_string()),
+            version: Default::default(),
+        }
+    }
+
+    pub fn current(&self) -> String {
+        self.current.lock().clone()
+    }
+
+    /// Apply the filter directives immediately. If the ttl is specified, the previous
+    /// directives will be restored after the ttl unless it's changed again.
+    /// The auto-revert requires being called in the tokio runtime.
+    pub fn update(self: &Arc<Self>, directives: &str, ttl: Option<Duration>) -> Result<()> {
+        let filter = EnvFilter::try_new(directives)
+            .map_err(|e| anyhow!("Illegal log filter: {}. err: {:?}", directives, e))?;
+
+        let previous = {
+            let mut current = self.current.lock();
+            (self.reload)(filter)?;
+            std::mem::replace(&mut *current, directives.to_string())
+        };
+        let version = self.version.fetch_add(1, Ordering::SeqCst) + 1;
+        info!(
+            "The log filter has been changed from [{}] to [{}], ttl: {:?}",
+            &previous, directives, ttl
+        );
+
+        if let Some(ttl) = ttl {
+            let reloader = self.clone();
+            tokio::spawn(async move {
+                tokio::time::sleep(ttl).await;
+                if reloader.version.load(Ordering::SeqCst) != version {
+                    return;
+                }
+                if let Err(e) = reloader.update(&previous, None) {
+                    log::error!("Errors on reverting the log filter. err: {:?}", e);
+                }
+            });
+        }
+        Ok(())
+    }
+}
+
 pub struct LogService;
 impl LogService {
     pub fn init(log: &LogConfig) -> WorkerGuard {
@@ -16,8 +83,13 @@ impl LogService {
             RotationConfig::Never => tracing_appender::rolling::never(&log.path, LOG_FILE_NAME),
         };
 
-        let env_filter =
-            EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+        let directives = std::env::var(EnvFilter::DEFAULT_ENV)
+            .ok()
+            .filter(|x| EnvFilter::try_new(x).is_ok())
+            .unwrap_or_else(|| "info".to_string());
+        let (env_filter, reload_handle) = reload::Layer::new(EnvFilter::new(&directives));
+        let _ =
+            LOG_FILTER_RELOADER.set(Arc::new(LogFilterReloader::new(reload_handle, &directives)));
         let formatting_layer = fmt::layer().pretty().with_writer(std::io::stderr);
 
         let (non_blocking, _guard) = tracing_appender::non_blocking(file_appender);
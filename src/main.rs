@@ -20,8 +20,9 @@
 extern crate core;
 
 use crate::app::AppManager;
+use crate::await_tree::AWAIT_TREE_REGISTRY;
 use crate::common::init_global_variable;
-use crate::config::Config;
+use crate::config::{Config, StorageType};
 use crate::heartbeat::HeartbeatTask;
 use crate::http::{HTTPServer, HttpMonitorService};
 use crate::log_service::LogService;
@@ -30,22 +31,26 @@ use crate::metric::MetricService;
 use crate::readable_size::ReadableSize;
 use crate::rpc::DefaultRpcService;
 use crate::runtime::manager::RuntimeManager;
+use crate::shutdown::SHUTDOWN_COORDINATOR;
 use crate::tracing::FastraceWrapper;
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use clap::{App, Arg};
-use log::info;
-use std::str::FromStr;
+use log::{error, info, warn};
 use tracing_subscriber::layer::SubscriberExt;
 use tracing_subscriber::util::SubscriberInitExt;
 
 pub mod app;
 mod await_tree;
+mod block_id;
+mod busy_score;
 pub mod common;
 pub mod composed_bytes;
 pub mod config;
 pub mod constant;
+mod decommission;
 mod error;
 pub mod grpc;
+mod health;
 pub mod heartbeat;
 mod http;
 mod log_service;
@@ -54,7 +59,10 @@ mod metric;
 mod readable_size;
 pub mod rpc;
 pub mod runtime;
+mod shutdown;
 pub mod signal;
+mod sketch;
+mod slow_log;
 pub mod store;
 pub mod tracing;
 pub mod urpc;
@@ -81,32 +89,86 @@ fn main() -> Result<()> {
         .get_matches();
 
     let config_path = args_match.value_of("config").unwrap_or("./config.toml");
-    let config = Config::from(config_path);
+    let mut config = Config::from(config_path);
+    config.validate()?;
+    let origin_store_type = config.store_type;
+    let unusable_paths = config.degrade_unusable_stores();
+    if let Err(problems) = config.resolve_stores() {
+        return Err(anyhow!(
+            "Inconsistent store configs: {}",
+            problems.join("; ")
+        ));
+    }
 
     let _guard = LogService::init(&config.log.clone());
+    if let Some(paths) = unusable_paths {
+        error!(
+            "!!! All the localfile paths are unusable: {:?}. The store_type is degraded from {} to {}, the data will not be persisted on the local disks !!!",
+            paths, origin_store_type, config.store_type
+        );
+    }
+
+    for warning in config.lint() {
+        warn!("{}", warning);
+    }
+    check_localfile_mounts(&config);
 
     init_global_variable(&config);
 
     info!("The specified config show as follows: \n {:#?}", config);
 
     let runtime_manager = RuntimeManager::from(config.runtime_config.clone());
+    AWAIT_TREE_REGISTRY.start_watchdog(
+        &runtime_manager.default_runtime,
+        config.server.stuck_task_threshold()?,
+    );
+    SHUTDOWN_COORDINATOR.set_deadline(config.shutdown_timeout()?);
     let app_manager_ref = AppManager::get_ref(runtime_manager.clone(), config.clone());
 
     MetricService::init(&config, runtime_manager.clone());
     FastraceWrapper::init(config.clone());
     HeartbeatTask::init(&config, runtime_manager.clone(), app_manager_ref.clone());
-    HttpMonitorService::init(&config, runtime_manager.clone());
+    HttpMonitorService::init(&config, runtime_manager.clone(), app_manager_ref.clone());
 
     DefaultRpcService {}.start(&config, runtime_manager, app_manager_ref)?;
 
     Ok(())
 }
 
+/// Warn the unusable or unmounted localfile paths, the store is still started with the
+/// healthy ones and will be marked unhealthy if less than the `healthy_check_min_disks`.
+fn check_localfile_mounts(config: &Config) {
+    let localfile_store = match &config.localfile_store {
+        Some(localfile_store) if StorageType::contains_localfile(&config.store_type) => {
+            localfile_store
+        }
+        _ => return,
+    };
+    let health = localfile_store.check_mounts();
+    for path in &health {
+        if !path.is_healthy() {
+            warn!("The localfile path is unusable: {:?}", path);
+        } else if path.distinct_mount == Some(false) {
+            warn!(
+                "The localfile path: {} is on the root filesystem, it may be unmounted",
+                &path.path
+            );
+        }
+    }
+    let healthy = health.iter().filter(|x| x.is_healthy()).count();
+    if (healthy as i32) < localfile_store.healthy_check_min_disks {
+        warn!(
+            "Only {} localfile paths are healthy, less than the healthy_check_min_disks: {}",
+            healthy, localfile_store.healthy_check_min_disks
+        );
+    }
+}
+
 fn setup_max_memory_allocation() {
     #[cfg(all(unix, feature = "allocator-analysis"))]
     {
         let _ = std::env::var(MAX_MEMORY_ALLOCATION_SIZE_ENV_KEY).map(|v| {
-            let readable_size = ReadableSize::from_str(v.as_str()).unwrap();
+            let readable_size = ReadableSize::parse(v.as_str()).unwrap();
             ALLOCATOR.set_limit(readable_size.as_bytes() as usize)
         });
     }
@@ -20,13 +20,19 @@ use crate::config::Config;
 use crate::mem_allocator::ALLOCATOR;
 use crate::readable_size::ReadableSize;
 use crate::runtime::manager::RuntimeManager;
+use crate::shutdown::{PHASE_FINAL, SHUTDOWN_COORDINATOR};
 use log::{error, info};
 use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use prometheus::core::{Collector, Desc};
+use prometheus::proto::{LabelPair, Metric, MetricFamily, MetricType, Quantile, Summary};
 use prometheus::{
     histogram_opts, labels, register_histogram_vec_with_registry, register_int_counter_vec,
-    register_int_gauge_vec, Histogram, HistogramOpts, HistogramVec, IntCounter, IntCounterVec,
-    IntGauge, IntGaugeVec, Registry,
+    register_int_gauge_vec, Gauge, Histogram, HistogramOpts, HistogramVec, IntCounter,
+    IntCounterVec, IntGauge, IntGaugeVec, Registry,
 };
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::{Arc, Once, OnceLock};
 use std::time::Duration;
 
 const DEFAULT_BUCKETS: &[f64] = &[
@@ -96,6 +102,13 @@ pub static GRPC_GET_MEMORY_DATA_PROCESS_TIME: Lazy<Histogram> = Lazy::new(|| {
     histogram
 });
 
+pub static GRPC_GET_MERGED_DATA_PROCESS_TIME: Lazy<Histogram> = Lazy::new(|| {
+    let opts = HistogramOpts::new("grpc_get_merged_data_process_time", "none")
+        .buckets(Vec::from(DEFAULT_BUCKETS as &'static [f64]));
+    let histogram = Histogram::with_opts(opts).unwrap();
+    histogram
+});
+
 pub static GRPC_GET_MEMORY_DATA_FREEZE_PROCESS_TIME: Lazy<Histogram> = Lazy::new(|| {
     let opts = HistogramOpts::new("grpc_get_memory_data_freeze_process_time", "none")
         .buckets(Vec::from(DEFAULT_BUCKETS as &'static [f64]));
@@ -143,6 +156,17 @@ pub static GRPC_BUFFER_REQUIRE_PROCESS_TIME: Lazy<Histogram> = Lazy::new(|| {
     histogram
 });
 
+pub static MEMORY_TICKET_WAIT_DURATION: Lazy<Histogram> = Lazy::new(|| {
+    let opts = HistogramOpts::new(
+        "memory_ticket_wait_duration",
+        "the waiting time in seconds before requiring the memory buffer ticket",
+    )
+    .buckets(Vec::from(DEFAULT_BUCKETS as &'static [f64]));
+
+    let histogram = Histogram::with_opts(opts).unwrap();
+    histogram
+});
+
 pub static GRPC_LATENCY_TIME_SEC: Lazy<HistogramVec> = Lazy::new(|| {
     let opts = histogram_opts!(
         "grpc_duration_seconds",
@@ -211,6 +235,15 @@ pub static TOTAL_LOCAL_DISK_APPEND_OPERATION_BYTES_COUNTER: Lazy<IntCounterVec>
     .unwrap()
 });
 
+pub static TOTAL_LOCAL_DISK_READ_TIMEOUT_COUNTER: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "localfile_disk_read_timeout_counter",
+        "localfile disk read timeout counter",
+        &["root"]
+    )
+    .unwrap()
+});
+
 // for urpc metrics
 
 pub static URPC_GET_LOCALFILE_DATA_PROCESS_TIME: Lazy<Histogram> = Lazy::new(|| {
@@ -244,6 +277,13 @@ pub static URPC_GET_MEMORY_DATA_PROCESS_TIME: Lazy<Histogram> = Lazy::new(|| {
     histogram
 });
 
+pub static URPC_GET_MERGED_DATA_PROCESS_TIME: Lazy<Histogram> = Lazy::new(|| {
+    let opts = HistogramOpts::new("urpc_get_merged_data_process_time", "none")
+        .buckets(Vec::from(DEFAULT_BUCKETS as &'static [f64]));
+    let histogram = Histogram::with_opts(opts).unwrap();
+    histogram
+});
+
 pub static URPC_GET_LOCALFILE_DATA_TRANSPORT_TIME: Lazy<Histogram> = Lazy::new(|| {
     let opts = HistogramOpts::new("urpc_get_localfile_data_transport_time", "none")
         .buckets(Vec::from(DEFAULT_BUCKETS as &'static [f64]));
@@ -255,6 +295,16 @@ pub static URPC_GET_LOCALFILE_DATA_TRANSPORT_TIME: Lazy<Histogram> = Lazy::new(|
 pub static URPC_CONNECTION_NUMBER: Lazy<IntGauge> =
     Lazy::new(|| IntGauge::new("urpc_connection_number", "urpc_connection_number").expect(""));
 
+// the sum / count is the average frames per flush
+pub static URPC_FRAMES_PER_FLUSH: Lazy<Histogram> = Lazy::new(|| {
+    let opts = HistogramOpts::new(
+        "urpc_frames_per_flush",
+        "the response frames written by one flush of urpc connection",
+    )
+    .buckets(vec![1.0, 2.0, 4.0, 8.0, 16.0, 32.0, 64.0, 128.0]);
+    Histogram::with_opts(opts).unwrap()
+});
+
 // ===========
 
 pub static TOTAL_MEMORY_USED: Lazy<IntCounter> = Lazy::new(|| {
@@ -277,6 +327,62 @@ pub static GAUGE_MEMORY_ALLOCATED: Lazy<IntGauge> = Lazy::new(|| {
 pub static GAUGE_MEMORY_CAPACITY: Lazy<IntGauge> = Lazy::new(|| {
     IntGauge::new("memory_capacity", "memory capacity").expect("metric should be created")
 });
+pub static GAUGE_MEMORY_IN_FLIGHT: Lazy<IntGauge> = Lazy::new(|| {
+    IntGauge::new(
+        "memory_in_flight",
+        "memory picked up for spill but not released yet",
+    )
+    .expect("metric should be created")
+});
+pub static GAUGE_MEMORY_SPILL_HIGH_WATERMARK: Lazy<IntGauge> = Lazy::new(|| {
+    IntGauge::new(
+        "memory_spill_high_watermark",
+        "the absolute memory size of the spill high watermark",
+    )
+    .expect("metric should be created")
+});
+pub static GAUGE_MEMORY_SPILL_LOW_WATERMARK: Lazy<IntGauge> = Lazy::new(|| {
+    IntGauge::new(
+        "memory_spill_low_watermark",
+        "the absolute memory size of the spill low watermark",
+    )
+    .expect("metric should be created")
+});
+pub static GAUGE_MEMORY_STORE_SHARD_IMBALANCE_RATIO: Lazy<Gauge> = Lazy::new(|| {
+    Gauge::new(
+        "memory_store_shard_imbalance_ratio",
+        "the max entries of the memory store shards divided by the mean",
+    )
+    .expect("metric should be created")
+});
+pub static GAUGE_MEMORY_STORE_SHARD_MAX_LOCK_WAIT_MICROS: Lazy<IntGauge> = Lazy::new(|| {
+    IntGauge::new(
+        "memory_store_shard_max_lock_wait_micros",
+        "the max sampled lock wait of the memory store shards",
+    )
+    .expect("metric should be created")
+});
+pub static TOTAL_MEMORY_SNAPSHOT_DRIFTED: Lazy<IntCounter> = Lazy::new(|| {
+    IntCounter::new(
+        "total_memory_snapshot_drifted",
+        "memory snapshots whose summed totals drift from the tracked beyond the threshold",
+    )
+    .expect("metric should be created")
+});
+pub static TOTAL_MEMORY_ALLOCATION_FAILED: Lazy<IntCounter> = Lazy::new(|| {
+    IntCounter::new(
+        "total_memory_allocation_failed",
+        "memory allocations rejected by the budget",
+    )
+    .expect("metric should be created")
+});
+pub static TOTAL_MEMORY_HIGH_WATERMARK_EXCEEDED: Lazy<IntCounter> = Lazy::new(|| {
+    IntCounter::new(
+        "total_memory_high_watermark_exceeded",
+        "inserts finding the memory usage above the spill high watermark",
+    )
+    .expect("metric should be created")
+});
 pub static TOTAL_MEMORY_SPILL_OPERATION: Lazy<IntCounter> = Lazy::new(|| {
     IntCounter::new("total_memory_spill", "memory capacity").expect("metric should be created")
 });
@@ -284,6 +390,57 @@ pub static TOTAL_MEMORY_SPILL_OPERATION_FAILED: Lazy<IntCounter> = Lazy::new(||
     IntCounter::new("total_memory_spill_failed", "memory capacity")
         .expect("metric should be created")
 });
+pub static TOTAL_MEMORY_SPILL_TRIGGERED: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "total_memory_spill_triggered",
+        "memory spill operations by the trigger, like watermark or partition size",
+        &["trigger"]
+    )
+    .unwrap()
+});
+
+// labeled by the destination tier: localfile, hdfs or remote
+pub static TOTAL_MEMORY_SPILL_TO: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "total_memory_spill_to",
+        "memory spill operations by the destination",
+        &["to"]
+    )
+    .unwrap()
+});
+pub static TOTAL_MEMORY_SPILL_BYTES_TO: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "total_memory_spill_bytes_to",
+        "memory spilled bytes by the destination",
+        &["to"]
+    )
+    .unwrap()
+});
+pub static TOTAL_MEMORY_SPILL_FAILED_TO: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "total_memory_spill_failed_to",
+        "failed memory spill operations by the destination",
+        &["to"]
+    )
+    .unwrap()
+});
+pub static GAUGE_MEMORY_SPILL_IN_FLIGHT_BYTES_TO: Lazy<IntGaugeVec> = Lazy::new(|| {
+    register_int_gauge_vec!(
+        "memory_spill_in_flight_bytes_to",
+        "in-flight memory spill bytes by the destination",
+        &["to"]
+    )
+    .unwrap()
+});
+pub static MEMORY_SPILL_DURATION_TO: Lazy<HistogramVec> = Lazy::new(|| {
+    let opts = histogram_opts!(
+        "memory_spill_duration_to",
+        "memory spill time by the destination",
+        Vec::from(DEFAULT_BUCKETS)
+    );
+    register_histogram_vec_with_registry!(opts, &["to"], REGISTRY).unwrap()
+});
+
 pub static TOTAL_MEMORY_SPILL_TO_LOCALFILE: Lazy<IntCounter> = Lazy::new(|| {
     IntCounter::new(
         "total_memory_spill_to_localfile",
@@ -311,6 +468,13 @@ pub static TOTAL_PARTITION_NUMBER: Lazy<IntCounter> = Lazy::new(|| {
     IntCounter::new("total_partition_number", "total_partition_number")
         .expect("metrics should be created")
 });
+pub static TOTAL_APP_REGISTRATION_REJECTED: Lazy<IntCounter> = Lazy::new(|| {
+    IntCounter::new(
+        "total_app_registration_rejected",
+        "app registrations rejected by the max concurrent apps",
+    )
+    .expect("metrics should be created")
+});
 pub static GAUGE_APP_NUMBER: Lazy<IntGauge> =
     Lazy::new(|| IntGauge::new("app_number", "app_number").expect("metrics should be created"));
 pub static GAUGE_PARTITION_NUMBER: Lazy<IntGauge> = Lazy::new(|| {
@@ -355,6 +519,15 @@ pub static GAUGE_LOCAL_DISK_IS_HEALTHY: Lazy<IntGaugeVec> = Lazy::new(|| {
     .unwrap()
 });
 
+pub static TOTAL_TASK_PANICS: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "total_task_panics",
+        "total panics of the spawned tasks",
+        &["runtime", "task_group"]
+    )
+    .unwrap()
+});
+
 pub static GAUGE_RUNTIME_ALIVE_THREAD_NUM: Lazy<IntGaugeVec> = Lazy::new(|| {
     register_int_gauge_vec!(
         "runtime_thread_alive_gauge",
@@ -382,9 +555,57 @@ pub static GAUGE_TOPN_APP_RESIDENT_DATA_SIZE: Lazy<IntGaugeVec> = Lazy::new(|| {
     .unwrap()
 });
 
+pub static GAUGE_STUCK_TASKS: Lazy<IntGauge> = Lazy::new(|| {
+    IntGauge::new(
+        "stuck_tasks",
+        "the tasks whose await frames haven't changed for long",
+    )
+    .unwrap()
+});
+
+pub static GAUGE_FLUSH_WATERMARK_LAG: Lazy<IntGauge> = Lazy::new(|| {
+    IntGauge::new(
+        "flush_watermark_lag",
+        "the max write sequences of the shuffle accepted but not flushed",
+    )
+    .unwrap()
+});
+
+pub static GAUGE_BUSY_SCORE: Lazy<IntGauge> = Lazy::new(|| {
+    IntGauge::new(
+        "busy_score",
+        "the busy score from 0 to 100 attached in the responses for the client balancing",
+    )
+    .unwrap()
+});
+
 pub static GAUGE_IN_SPILL_DATA_SIZE: Lazy<IntGauge> =
     Lazy::new(|| IntGauge::new("in_spill_data_size", "total data size in spill").unwrap());
 
+pub static TOTAL_READ_CACHE_HIT: Lazy<IntCounter> = Lazy::new(|| {
+    IntCounter::new(
+        "total_read_cache_hit",
+        "total hit number of the disk segments read cache",
+    )
+    .expect("")
+});
+
+pub static TOTAL_READ_CACHE_MISS: Lazy<IntCounter> = Lazy::new(|| {
+    IntCounter::new(
+        "total_read_cache_miss",
+        "total miss number of the disk segments read cache",
+    )
+    .expect("")
+});
+
+pub static GAUGE_READ_CACHE_USED_SIZE: Lazy<IntGauge> = Lazy::new(|| {
+    IntGauge::new(
+        "read_cache_used_size",
+        "the used size of the disk segments read cache",
+    )
+    .expect("")
+});
+
 pub static TOTAL_GRPC_REQUEST: Lazy<IntCounter> =
     Lazy::new(|| IntCounter::new("total_grpc_request_number", "total request number").expect(""));
 
@@ -392,6 +613,110 @@ pub static GAUGE_GRPC_REQUEST_QUEUE_SIZE: Lazy<IntGauge> = Lazy::new(|| {
     IntGauge::new("grpc_request_number", "current service request queue size").unwrap()
 });
 
+pub static TOTAL_LOCALFILE_INDEX_CACHE_HIT: Lazy<IntCounter> = Lazy::new(|| {
+    IntCounter::new(
+        "total_localfile_index_cache_hit",
+        "total localfile index cache hit number",
+    )
+    .expect("")
+});
+
+pub static TOTAL_LOCALFILE_INDEX_CACHE_MISS: Lazy<IntCounter> = Lazy::new(|| {
+    IntCounter::new(
+        "total_localfile_index_cache_miss",
+        "total localfile index cache miss number, including the partial delta read",
+    )
+    .expect("")
+});
+
+pub static TOTAL_LOCALFILE_MMAP_MAPPED: Lazy<IntCounter> = Lazy::new(|| {
+    IntCounter::new(
+        "total_localfile_mmap_mapped",
+        "total localfile data file mapped number, including the remapping on growth",
+    )
+    .expect("")
+});
+
+pub static TOTAL_LOCALFILE_MMAP_HIT: Lazy<IntCounter> = Lazy::new(|| {
+    IntCounter::new(
+        "total_localfile_mmap_hit",
+        "total localfile reading served by the cached mapping",
+    )
+    .expect("")
+});
+
+pub static TOTAL_LOCALFILE_COMPACTED_PARTITIONS: Lazy<IntCounter> = Lazy::new(|| {
+    IntCounter::new(
+        "total_localfile_compacted_partitions",
+        "total localfile compacted partition number",
+    )
+    .expect("")
+});
+
+pub static TOTAL_LOCALFILE_COMPACTED_BYTES: Lazy<IntCounter> = Lazy::new(|| {
+    IntCounter::new(
+        "total_localfile_compacted_bytes",
+        "total localfile compacted data bytes",
+    )
+    .expect("")
+});
+
+pub static TOTAL_LOCALFILE_COMPACTION_SKIPPED: Lazy<IntCounter> = Lazy::new(|| {
+    IntCounter::new(
+        "total_localfile_compaction_skipped",
+        "total localfile compaction skipped number due to the disk pressure",
+    )
+    .expect("")
+});
+
+pub static TOTAL_SHUFFLE_RESULT_OFFLOADED: Lazy<IntCounter> = Lazy::new(|| {
+    IntCounter::new(
+        "total_shuffle_result_offloaded",
+        "total partitions number of the shuffle results offloaded to localfile",
+    )
+    .expect("")
+});
+
+pub static TOTAL_REASSIGNED_PARTITIONS: Lazy<IntCounter> = Lazy::new(|| {
+    IntCounter::new(
+        "total_reassigned_partitions",
+        "total partitions number split for the reassignment",
+    )
+    .expect("")
+});
+
+pub static TOTAL_REASSIGNED_BLOCKS_REJECTED: Lazy<IntCounter> = Lazy::new(|| {
+    IntCounter::new(
+        "total_reassigned_blocks_rejected",
+        "total blocks number rejected beyond the split point of the reassigned partitions",
+    )
+    .expect("")
+});
+
+pub static TOTAL_SHUFFLE_RESULT_RELOADED: Lazy<IntCounter> = Lazy::new(|| {
+    IntCounter::new(
+        "total_shuffle_result_reloaded",
+        "total partitions number of the shuffle results reloaded from localfile",
+    )
+    .expect("")
+});
+
+pub static TOTAL_BLOCK_CHECKSUM_MISMATCHED: Lazy<IntCounter> = Lazy::new(|| {
+    IntCounter::new(
+        "total_block_checksum_mismatched",
+        "total blocks number failing the checksum verification, which are rejected",
+    )
+    .expect("")
+});
+
+pub static TOTAL_DUPLICATE_BLOCKS_DROPPED: Lazy<IntCounter> = Lazy::new(|| {
+    IntCounter::new(
+        "total_duplicate_blocks_dropped",
+        "total duplicate blocks dropped number, like resent by the speculative tasks",
+    )
+    .expect("")
+});
+
 pub static TOTAL_SPILL_EVENTS_DROPPED: Lazy<IntCounter> = Lazy::new(|| {
     IntCounter::new(
         "total_spill_events_dropped",
@@ -426,6 +751,15 @@ pub static GAUGE_EVENT_BUS_QUEUE_PENDING_SIZE: Lazy<IntGaugeVec> = Lazy::new(||
     .unwrap()
 });
 
+pub static GAUGE_EVENT_BUS_OLDEST_PENDING_AGE_SECONDS: Lazy<IntGaugeVec> = Lazy::new(|| {
+    register_int_gauge_vec!(
+        "eventbus_oldest_pending_age_seconds",
+        "the age of the oldest pending event of event bus",
+        &["name"]
+    )
+    .unwrap()
+});
+
 pub static GAUGE_EVENT_BUS_QUEUE_HANDLING_SIZE: Lazy<IntGaugeVec> = Lazy::new(|| {
     register_int_gauge_vec!(
         "eventbus_queue_handling_size",
@@ -435,11 +769,19 @@ pub static GAUGE_EVENT_BUS_QUEUE_HANDLING_SIZE: Lazy<IntGaugeVec> = Lazy::new(||
     .unwrap()
 });
 
+pub static TOTAL_COORDINATOR_REREGISTRATION: Lazy<IntCounter> = Lazy::new(|| {
+    IntCounter::new(
+        "total_coordinator_reregistration",
+        "total re-registration to the coordinators which have forgotten this server",
+    )
+    .expect("metric should be created")
+});
+
 pub static TOTAL_EVENT_BUS_EVENT_PUBLISHED_SIZE: Lazy<IntCounterVec> = Lazy::new(|| {
     register_int_counter_vec!(
         "eventbus_total_published_event_size",
         "total published event size of event bus",
-        &["name"]
+        &["name", "source"]
     )
     .unwrap()
 });
@@ -453,6 +795,123 @@ pub static TOTAL_EVENT_BUS_EVENT_HANDLED_SIZE: Lazy<IntCounterVec> = Lazy::new(|
     .unwrap()
 });
 
+pub static TOTAL_EVENT_BUS_SUBSCRIBER_TIMEOUT_SIZE: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "eventbus_total_subscriber_timeout_size",
+        "total timeout size of the subscriber handling in event bus",
+        &["name"]
+    )
+    .unwrap()
+});
+
+pub static TOTAL_EVENT_BUS_EVENT_DEDUPED_SIZE: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "eventbus_total_deduped_event_size",
+        "total deduped event size of the dedup subscriber",
+        &["name"]
+    )
+    .unwrap()
+});
+
+pub static TOTAL_EVENT_BUS_EVENT_SHORT_CIRCUITED_SIZE: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "eventbus_total_short_circuited_event_size",
+        "total short-circuited event size of the circuit breaker subscriber",
+        &["name"]
+    )
+    .unwrap()
+});
+
+pub static GAUGE_EVENT_BUS_EFFECTIVE_CONCURRENCY: Lazy<IntGaugeVec> = Lazy::new(|| {
+    register_int_gauge_vec!(
+        "eventbus_effective_concurrency",
+        "effective concurrency of event bus throttled by the usage",
+        &["name"]
+    )
+    .unwrap()
+});
+
+pub static TOTAL_EVENT_BUS_EVENT_REDELIVERED_SIZE: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "eventbus_event_redelivered_size",
+        "redelivered event size of the ackable event bus due to the missing ack",
+        &["name"]
+    )
+    .unwrap()
+});
+
+pub static TOTAL_EVENT_BUS_EVENT_ACK_EXHAUSTED_SIZE: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "eventbus_event_ack_exhausted_size",
+        "dropped event size of the ackable event bus after the max attempts",
+        &["name"]
+    )
+    .unwrap()
+});
+
+pub static TOTAL_EVENT_BUS_EVENT_REJECTED_SIZE: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "eventbus_event_rejected_size",
+        "rejected event size of event bus due to the full queue",
+        &["name"]
+    )
+    .unwrap()
+});
+
+// 0: closed, 1: open, 2: half-open
+pub static GAUGE_EVENT_BUS_CIRCUIT_BREAKER_STATE: Lazy<IntGaugeVec> = Lazy::new(|| {
+    register_int_gauge_vec!(
+        "eventbus_circuit_breaker_state",
+        "state of the circuit breaker subscriber, 0: closed, 1: open, 2: half-open",
+        &["name"]
+    )
+    .unwrap()
+});
+
+pub static TOTAL_EVENT_BUS_CONCURRENCY_STARVED_SIZE: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "eventbus_total_concurrency_starved_size",
+        "total size of the events waiting for the concurrency permit in event bus",
+        &["name"]
+    )
+    .unwrap()
+});
+
+pub static TOTAL_SPILL_EVENTS_DEFERRED_BY_APP_LIMIT: Lazy<IntCounter> = Lazy::new(|| {
+    IntCounter::new(
+        "total_spill_events_deferred_by_app_limit",
+        "total spill events deferred due to the app's spill concurrency being exhausted",
+    )
+    .expect("")
+});
+
+pub static TOTAL_INJECTED_FAULTS: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "total_injected_faults",
+        "total faults injected into the persistent stores",
+        &["kind"]
+    )
+    .unwrap()
+});
+
+pub static TOTAL_BUFFER_ADMISSION: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "total_buffer_admission",
+        "total buffer requirements decided by the spill debt admission",
+        &["outcome"]
+    )
+    .unwrap()
+});
+
+pub static TOTAL_WORKER_ERROR: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "total_worker_error",
+        "total worker errors responded to the client",
+        &["error"]
+    )
+    .unwrap()
+});
+
 pub static EVENT_BUS_HANDLE_DURATION: Lazy<HistogramVec> = Lazy::new(|| {
     let opts = histogram_opts!(
         "eventbus_handle_operation_duration",
@@ -463,236 +922,468 @@ pub static EVENT_BUS_HANDLE_DURATION: Lazy<HistogramVec> = Lazy::new(|| {
     opts
 });
 
-fn register_custom_metrics() {
-    REGISTRY
-        .register(Box::new(TOTAL_MEMORY_BUFFER_SPILL_BYTE_SIZE.clone()))
-        .expect("");
-    REGISTRY
-        .register(Box::new(GAUGE_EVENT_BUS_QUEUE_PENDING_SIZE.clone()))
-        .expect("");
-    REGISTRY
-        .register(Box::new(GAUGE_EVENT_BUS_QUEUE_HANDLING_SIZE.clone()))
-        .expect("");
-    REGISTRY
-        .register(Box::new(TOTAL_EVENT_BUS_EVENT_PUBLISHED_SIZE.clone()))
-        .expect("");
-    REGISTRY
-        .register(Box::new(TOTAL_EVENT_BUS_EVENT_HANDLED_SIZE.clone()))
-        .expect("");
-
-    REGISTRY
-        .register(Box::new(MEMORY_BUFFER_SPILL_BATCH_SIZE_HISTOGRAM.clone()))
-        .expect("");
-
-    REGISTRY
-        .register(Box::new(GAUGE_ALLOCATOR_ALLOCATED_SIZE.clone()))
-        .expect("");
-
-    REGISTRY
-        .register(Box::new(TOTAL_GRPC_REQUEST.clone()))
-        .expect("");
-
-    REGISTRY
-        .register(Box::new(GAUGE_GRPC_REQUEST_QUEUE_SIZE.clone()))
-        .expect("");
-
-    REGISTRY
-        .register(Box::new(TOTAL_SPILL_EVENTS_DROPPED.clone()))
-        .expect("");
-
-    REGISTRY
-        .register(Box::new(GAUGE_TOPN_APP_RESIDENT_DATA_SIZE.clone()))
-        .expect("");
-
-    REGISTRY
-        .register(Box::new(TOTAL_READ_DATA_FROM_LOCALFILE.clone()))
-        .expect("total_read_data must be registered");
-
-    REGISTRY
-        .register(Box::new(TOTAL_READ_DATA_FROM_MEMORY.clone()))
-        .expect("total_read_data must be registered");
-
-    REGISTRY
-        .register(Box::new(GAUGE_IN_SPILL_DATA_SIZE.clone()))
-        .expect("");
-
-    REGISTRY
-        .register(Box::new(GAUGE_LOCAL_DISK_CAPACITY.clone()))
-        .expect("");
-
-    REGISTRY
-        .register(Box::new(GAUGE_LOCAL_DISK_USED.clone()))
-        .expect("");
-
-    REGISTRY
-        .register(Box::new(GAUGE_LOCAL_DISK_IS_HEALTHY.clone()))
-        .expect("");
-
-    REGISTRY
-        .register(Box::new(GAUGE_RUNTIME_ALIVE_THREAD_NUM.clone()))
-        .expect("");
-
-    REGISTRY
-        .register(Box::new(GAUGE_RUNTIME_IDLE_THREAD_NUM.clone()))
-        .expect("");
-
-    REGISTRY
-        .register(Box::new(TOTAL_RECEIVED_DATA.clone()))
-        .expect("total_received_data must be registered");
-    REGISTRY
-        .register(Box::new(TOTAL_READ_DATA.clone()))
-        .expect("total_read_data must be registered");
-    REGISTRY
-        .register(Box::new(TOTAL_MEMORY_USED.clone()))
-        .expect("total_memory_used must be registered");
-    REGISTRY
-        .register(Box::new(TOTAL_LOCALFILE_USED.clone()))
-        .expect("total_localfile_used must be registered");
-    REGISTRY
-        .register(Box::new(TOTAL_HDFS_USED.clone()))
-        .expect("total_hdfs_used must be registered");
-    REGISTRY
-        .register(Box::new(TOTAL_MEMORY_SPILL_OPERATION.clone()))
-        .expect("total_memory_spill_operation must be registered");
-    REGISTRY
-        .register(Box::new(TOTAL_MEMORY_SPILL_OPERATION_FAILED.clone()))
-        .expect("total_memory_spill_operation_failed must be registered");
-    REGISTRY
-        .register(Box::new(TOTAL_APP_NUMBER.clone()))
-        .expect("total_app_number must be registered");
-    REGISTRY
-        .register(Box::new(TOTAL_PARTITION_NUMBER.clone()))
-        .expect("total_partition_number must be registered");
-    REGISTRY
-        .register(Box::new(TOTAL_REQUIRE_BUFFER_FAILED.clone()))
-        .expect("total_require_buffer_failed must be registered");
-    REGISTRY
-        .register(Box::new(TOTAL_HUGE_PARTITION_REQUIRE_BUFFER_FAILED.clone()))
-        .expect("total_huge_partition_require_buffer_failed must be registered");
-    REGISTRY
-        .register(Box::new(TOTAL_MEMORY_SPILL_TO_LOCALFILE.clone()))
-        .expect("total_memory_spill_to_localfile must be registered");
-    REGISTRY
-        .register(Box::new(TOTAL_MEMORY_SPILL_TO_HDFS.clone()))
-        .expect("total_memory_spill_to_hdfs must be registered");
-
-    REGISTRY
-        .register(Box::new(GAUGE_MEMORY_USED.clone()))
-        .expect("memory_used must be registered");
-    REGISTRY
-        .register(Box::new(GAUGE_MEMORY_ALLOCATED.clone()))
-        .expect("memory_allocated must be registered");
-    REGISTRY
-        .register(Box::new(GAUGE_MEMORY_CAPACITY.clone()))
-        .expect("memory_capacity must be registered");
-    REGISTRY
-        .register(Box::new(GAUGE_APP_NUMBER.clone()))
-        .expect("app_number must be registered");
-    REGISTRY
-        .register(Box::new(GAUGE_PARTITION_NUMBER.clone()))
-        .expect("partition_number must be registered");
-    REGISTRY
-        .register(Box::new(GAUGE_MEMORY_SPILL_OPERATION.clone()))
-        .expect("memory_spill_operation must be registered");
-    REGISTRY
-        .register(Box::new(GAUGE_MEMORY_SPILL_TO_LOCALFILE.clone()))
-        .expect("memory_spill_to_localfile must be registered");
-    REGISTRY
-        .register(Box::new(GAUGE_MEMORY_SPILL_TO_HDFS.clone()))
-        .expect("memory_spill_to_hdfs must be registered");
-    REGISTRY
-        .register(Box::new(GRPC_BUFFER_REQUIRE_PROCESS_TIME.clone()))
-        .expect("grpc_buffer_require_process_time must be registered");
-    REGISTRY
-        .register(Box::new(GRPC_SEND_DATA_TRANSPORT_TIME.clone()))
-        .expect("grpc_send_data_transport_time must be registered");
-    REGISTRY
-        .register(Box::new(GRPC_SEND_DATA_PROCESS_TIME.clone()))
-        .expect("grpc_send_data_process_time must be registered");
-
-    REGISTRY
-        .register(Box::new(GRPC_GET_MEMORY_DATA_PROCESS_TIME.clone()))
-        .expect("grpc_get_memory_data_process_time must be registered");
-    REGISTRY
-        .register(Box::new(GRPC_GET_MEMORY_DATA_FREEZE_PROCESS_TIME.clone()))
-        .expect("grpc_get_memory_data_freeze_process_time must be registered");
-    REGISTRY
-        .register(Box::new(GRPC_GET_LOCALFILE_DATA_TRANSPORT_TIME.clone()))
-        .expect("grpc_get_localfile_data_transport_time must be registered");
-    REGISTRY
-        .register(Box::new(GRPC_GET_LOCALFILE_DATA_PROCESS_TIME.clone()))
-        .expect("grpc_get_localfile_data_process_time must be registered");
-    REGISTRY
-        .register(Box::new(GRPC_GET_MEMORY_DATA_TRANSPORT_TIME.clone()))
-        .expect("grpc_get_memory_data_transport_time must be registered");
-
-    // for urpc
-    REGISTRY
-        .register(Box::new(URPC_SEND_DATA_PROCESS_TIME.clone()))
-        .expect("");
-    REGISTRY
-        .register(Box::new(URPC_SEND_DATA_TRANSPORT_TIME.clone()))
-        .expect("");
-    REGISTRY
-        .register(Box::new(URPC_GET_LOCALFILE_DATA_PROCESS_TIME.clone()))
-        .expect("");
-    REGISTRY
-        .register(Box::new(URPC_GET_LOCALFILE_DATA_TRANSPORT_TIME.clone()))
-        .expect("");
-    REGISTRY
-        .register(Box::new(URPC_GET_MEMORY_DATA_PROCESS_TIME.clone()))
-        .expect("");
-    REGISTRY
-        .register(Box::new(URPC_CONNECTION_NUMBER.clone()))
-        .expect("");
-    REGISTRY
-        .register(Box::new(TOTAL_EVICT_TIMEOUT_TICKETS_NUM.clone()))
-        .expect("");
+// the optional summary alongside the EVENT_BUS_HANDLE_DURATION, which is enabled by the config
+pub static EVENT_BUS_HANDLE_SUMMARY: OnceLock<SummaryVec> = OnceLock::new();
+
+// the quantiles are calculated over the recent samples of every label value
+const SUMMARY_WINDOW_SIZE: usize = 1024;
+
+/// The summary with the client-side quantiles, which is not provided by the prometheus crate.
+/// Only the single variable label is supported.
+#[derive(Clone)]
+pub struct SummaryVec {
+    desc: Desc,
+    label: String,
+    quantiles: Vec<f64>,
+    series: Arc<Mutex<HashMap<String, SummarySeries>>>,
+}
+
+#[derive(Default)]
+struct SummarySeries {
+    samples: VecDeque<f64>,
+    count: u64,
+    sum: f64,
+}
+
+impl SummaryVec {
+    pub fn new(
+        name: &str,
+        help: &str,
+        label: &str,
+        quantiles: Vec<f64>,
+    ) -> prometheus::Result<Self> {
+        let desc = Desc::new(
+            name.to_string(),
+            help.to_string(),
+            vec![label.to_string()],
+            HashMap::new(),
+        )?;
+        Ok(Self {
+            desc,
+            label: label.to_string(),
+            quantiles,
+            series: Default::default(),
+        })
+    }
+
+    pub fn observe(&self, label_value: &str, value: f64) {
+        let mut series = self.series.lock();
+        let series = series.entry(label_value.to_string()).or_default();
+        if series.samples.len() >= SUMMARY_WINDOW_SIZE {
+            series.samples.pop_front();
+        }
+        series.samples.push_back(value);
+        series.count += 1;
+        series.sum += value;
+    }
+
+    fn metric(&self, label_value: &str, series: &SummarySeries) -> Metric {
+        let mut sorted: Vec<f64> = series.samples.iter().copied().collect();
+        sorted.sort_by(|a, b| a.total_cmp(b));
+
+        let mut summary = Summary::default();
+        summary.set_sample_count(series.count);
+        summary.set_sample_sum(series.sum);
+        for quantile in &self.quantiles {
+            let idx = ((quantile * sorted.len() as f64).ceil() as usize).max(1) - 1;
+            let mut q = Quantile::default();
+            q.set_quantile(*quantile);
+            q.set_value(sorted.get(idx).copied().unwrap_or(f64::NAN));
+            summary.mut_quantile().push(q);
+        }
+
+        let mut label = LabelPair::default();
+        label.set_name(self.label.clone());
+        label.set_value(label_value.to_string());
+        let mut metric = Metric::default();
+        metric.mut_label().push(label);
+        metric.set_summary(summary);
+        metric
+    }
+}
+
+impl Collector for SummaryVec {
+    fn desc(&self) -> Vec<&Desc> {
+        vec![&self.desc]
+    }
+
+    fn collect(&self) -> Vec<MetricFamily> {
+        let series = self.series.lock();
+        let mut family = MetricFamily::default();
+        family.set_name(self.desc.fq_name.clone());
+        family.set_help(self.desc.help.clone());
+        family.set_field_type(MetricType::SUMMARY);
+        let mut label_values: Vec<&String> = series.keys().collect();
+        label_values.sort();
+        for label_value in label_values {
+            family
+                .mut_metric()
+                .push(self.metric(label_value, &series[label_value]));
+        }
+        vec![family]
+    }
+}
+
+pub static TOTAL_SLOW_REQUEST: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "total_slow_request",
+        "total slow request number exceeding the threshold",
+        &["method"]
+    )
+    .unwrap()
+});
+
+static REGISTER_CUSTOM_METRICS: Once = Once::new();
+
+pub(crate) fn register_custom_metrics() {
+    REGISTER_CUSTOM_METRICS.call_once(do_register_custom_metrics);
+}
+
+/// Gather the metrics from the custom registry and the default registry.
+/// Some metrics are registered into both of them, which will be deduplicated by name.
+pub fn gather_all_metrics() -> Vec<MetricFamily> {
+    let mut names = HashSet::new();
+    let mut metrics = vec![];
+    for family in REGISTRY
+        .gather()
+        .into_iter()
+        .chain(prometheus::gather().into_iter())
+    {
+        if names.insert(family.get_name().to_string()) {
+            metrics.push(family);
+        }
+    }
+    metrics
+}
+
+// all the metrics registered into the custom registry
+fn custom_collectors() -> Vec<Box<dyn Collector>> {
+    vec![
+        Box::new(TOTAL_SLOW_REQUEST.clone()),
+        Box::new(TOTAL_MEMORY_BUFFER_SPILL_BYTE_SIZE.clone()),
+        Box::new(GAUGE_EVENT_BUS_QUEUE_PENDING_SIZE.clone()),
+        Box::new(GAUGE_EVENT_BUS_QUEUE_HANDLING_SIZE.clone()),
+        Box::new(GAUGE_EVENT_BUS_OLDEST_PENDING_AGE_SECONDS.clone()),
+        Box::new(TOTAL_EVENT_BUS_EVENT_PUBLISHED_SIZE.clone()),
+        Box::new(TOTAL_EVENT_BUS_EVENT_HANDLED_SIZE.clone()),
+        Box::new(TOTAL_EVENT_BUS_SUBSCRIBER_TIMEOUT_SIZE.clone()),
+        Box::new(TOTAL_EVENT_BUS_EVENT_DEDUPED_SIZE.clone()),
+        Box::new(TOTAL_EVENT_BUS_CONCURRENCY_STARVED_SIZE.clone()),
+        Box::new(TOTAL_EVENT_BUS_EVENT_SHORT_CIRCUITED_SIZE.clone()),
+        Box::new(TOTAL_EVENT_BUS_EVENT_REJECTED_SIZE.clone()),
+        Box::new(TOTAL_EVENT_BUS_EVENT_REDELIVERED_SIZE.clone()),
+        Box::new(TOTAL_EVENT_BUS_EVENT_ACK_EXHAUSTED_SIZE.clone()),
+        Box::new(GAUGE_EVENT_BUS_CIRCUIT_BREAKER_STATE.clone()),
+        Box::new(GAUGE_EVENT_BUS_EFFECTIVE_CONCURRENCY.clone()),
+        Box::new(TOTAL_BUFFER_ADMISSION.clone()),
+        Box::new(TOTAL_WORKER_ERROR.clone()),
+        Box::new(TOTAL_INJECTED_FAULTS.clone()),
+        Box::new(TOTAL_TASK_PANICS.clone()),
+        Box::new(MEMORY_BUFFER_SPILL_BATCH_SIZE_HISTOGRAM.clone()),
+        Box::new(GAUGE_ALLOCATOR_ALLOCATED_SIZE.clone()),
+        Box::new(TOTAL_GRPC_REQUEST.clone()),
+        Box::new(GAUGE_GRPC_REQUEST_QUEUE_SIZE.clone()),
+        Box::new(TOTAL_SPILL_EVENTS_DROPPED.clone()),
+        Box::new(TOTAL_SPILL_EVENTS_DEFERRED_BY_APP_LIMIT.clone()),
+        Box::new(TOTAL_DUPLICATE_BLOCKS_DROPPED.clone()),
+        Box::new(TOTAL_BLOCK_CHECKSUM_MISMATCHED.clone()),
+        Box::new(TOTAL_SHUFFLE_RESULT_OFFLOADED.clone()),
+        Box::new(TOTAL_SHUFFLE_RESULT_RELOADED.clone()),
+        Box::new(TOTAL_REASSIGNED_PARTITIONS.clone()),
+        Box::new(TOTAL_REASSIGNED_BLOCKS_REJECTED.clone()),
+        Box::new(TOTAL_LOCALFILE_INDEX_CACHE_HIT.clone()),
+        Box::new(TOTAL_LOCALFILE_INDEX_CACHE_MISS.clone()),
+        Box::new(TOTAL_LOCALFILE_MMAP_MAPPED.clone()),
+        Box::new(TOTAL_LOCALFILE_MMAP_HIT.clone()),
+        Box::new(TOTAL_LOCALFILE_COMPACTED_PARTITIONS.clone()),
+        Box::new(TOTAL_LOCALFILE_COMPACTED_BYTES.clone()),
+        Box::new(TOTAL_LOCALFILE_COMPACTION_SKIPPED.clone()),
+        Box::new(TOTAL_READ_CACHE_HIT.clone()),
+        Box::new(TOTAL_READ_CACHE_MISS.clone()),
+        Box::new(GAUGE_READ_CACHE_USED_SIZE.clone()),
+        Box::new(GAUGE_TOPN_APP_RESIDENT_DATA_SIZE.clone()),
+        Box::new(TOTAL_READ_DATA_FROM_LOCALFILE.clone()),
+        Box::new(TOTAL_READ_DATA_FROM_MEMORY.clone()),
+        Box::new(GAUGE_IN_SPILL_DATA_SIZE.clone()),
+        Box::new(GAUGE_STUCK_TASKS.clone()),
+        Box::new(GAUGE_FLUSH_WATERMARK_LAG.clone()),
+        Box::new(GAUGE_BUSY_SCORE.clone()),
+        Box::new(GAUGE_LOCAL_DISK_CAPACITY.clone()),
+        Box::new(GAUGE_LOCAL_DISK_USED.clone()),
+        Box::new(GAUGE_LOCAL_DISK_IS_HEALTHY.clone()),
+        Box::new(GAUGE_RUNTIME_ALIVE_THREAD_NUM.clone()),
+        Box::new(GAUGE_RUNTIME_IDLE_THREAD_NUM.clone()),
+        Box::new(TOTAL_RECEIVED_DATA.clone()),
+        Box::new(TOTAL_READ_DATA.clone()),
+        Box::new(TOTAL_COORDINATOR_REREGISTRATION.clone()),
+        Box::new(TOTAL_MEMORY_USED.clone()),
+        Box::new(TOTAL_LOCALFILE_USED.clone()),
+        Box::new(TOTAL_HDFS_USED.clone()),
+        Box::new(TOTAL_MEMORY_SPILL_OPERATION.clone()),
+        Box::new(TOTAL_MEMORY_SPILL_OPERATION_FAILED.clone()),
+        Box::new(TOTAL_APP_NUMBER.clone()),
+        Box::new(TOTAL_PARTITION_NUMBER.clone()),
+        Box::new(TOTAL_APP_REGISTRATION_REJECTED.clone()),
+        Box::new(TOTAL_REQUIRE_BUFFER_FAILED.clone()),
+        Box::new(TOTAL_HUGE_PARTITION_REQUIRE_BUFFER_FAILED.clone()),
+        Box::new(TOTAL_MEMORY_SPILL_TRIGGERED.clone()),
+        Box::new(TOTAL_MEMORY_SPILL_TO_LOCALFILE.clone()),
+        Box::new(TOTAL_MEMORY_SPILL_TO_HDFS.clone()),
+        Box::new(TOTAL_MEMORY_SPILL_TO.clone()),
+        Box::new(TOTAL_MEMORY_SPILL_BYTES_TO.clone()),
+        Box::new(TOTAL_MEMORY_SPILL_FAILED_TO.clone()),
+        Box::new(GAUGE_MEMORY_SPILL_IN_FLIGHT_BYTES_TO.clone()),
+        Box::new(GAUGE_MEMORY_USED.clone()),
+        Box::new(GAUGE_MEMORY_ALLOCATED.clone()),
+        Box::new(GAUGE_MEMORY_CAPACITY.clone()),
+        Box::new(GAUGE_MEMORY_IN_FLIGHT.clone()),
+        Box::new(GAUGE_MEMORY_SPILL_HIGH_WATERMARK.clone()),
+        Box::new(GAUGE_MEMORY_SPILL_LOW_WATERMARK.clone()),
+        Box::new(GAUGE_MEMORY_STORE_SHARD_IMBALANCE_RATIO.clone()),
+        Box::new(GAUGE_MEMORY_STORE_SHARD_MAX_LOCK_WAIT_MICROS.clone()),
+        Box::new(TOTAL_MEMORY_SNAPSHOT_DRIFTED.clone()),
+        Box::new(TOTAL_MEMORY_ALLOCATION_FAILED.clone()),
+        Box::new(TOTAL_MEMORY_HIGH_WATERMARK_EXCEEDED.clone()),
+        Box::new(GAUGE_APP_NUMBER.clone()),
+        Box::new(GAUGE_PARTITION_NUMBER.clone()),
+        Box::new(GAUGE_MEMORY_SPILL_OPERATION.clone()),
+        Box::new(GAUGE_MEMORY_SPILL_TO_LOCALFILE.clone()),
+        Box::new(GAUGE_MEMORY_SPILL_TO_HDFS.clone()),
+        Box::new(GRPC_BUFFER_REQUIRE_PROCESS_TIME.clone()),
+        Box::new(MEMORY_TICKET_WAIT_DURATION.clone()),
+        Box::new(GRPC_SEND_DATA_TRANSPORT_TIME.clone()),
+        Box::new(GRPC_SEND_DATA_PROCESS_TIME.clone()),
+        Box::new(GRPC_GET_MEMORY_DATA_PROCESS_TIME.clone()),
+        Box::new(GRPC_GET_MEMORY_DATA_FREEZE_PROCESS_TIME.clone()),
+        Box::new(GRPC_GET_MERGED_DATA_PROCESS_TIME.clone()),
+        Box::new(GRPC_GET_LOCALFILE_DATA_TRANSPORT_TIME.clone()),
+        Box::new(GRPC_GET_LOCALFILE_DATA_PROCESS_TIME.clone()),
+        Box::new(GRPC_GET_MEMORY_DATA_TRANSPORT_TIME.clone()),
+        Box::new(URPC_SEND_DATA_PROCESS_TIME.clone()),
+        Box::new(URPC_SEND_DATA_TRANSPORT_TIME.clone()),
+        Box::new(URPC_GET_LOCALFILE_DATA_PROCESS_TIME.clone()),
+        Box::new(URPC_GET_LOCALFILE_DATA_TRANSPORT_TIME.clone()),
+        Box::new(URPC_GET_MEMORY_DATA_PROCESS_TIME.clone()),
+        Box::new(URPC_GET_MERGED_DATA_PROCESS_TIME.clone()),
+        Box::new(URPC_CONNECTION_NUMBER.clone()),
+        Box::new(URPC_FRAMES_PER_FLUSH.clone()),
+        Box::new(TOTAL_EVICT_TIMEOUT_TICKETS_NUM.clone()),
+    ]
+}
+
+fn do_register_custom_metrics() {
+    for collector in custom_collectors() {
+        REGISTRY.register(collector).expect("");
+    }
+}
+
+fn register_summaries(quantiles: Vec<f64>) {
+    let summary = EVENT_BUS_HANDLE_SUMMARY.get_or_init(|| {
+        SummaryVec::new(
+            "eventbus_handle_operation_duration_summary",
+            "event handling duration quantiles of event bus",
+            "name",
+            quantiles,
+        )
+        .expect("metric should be created")
+    });
+    if let Err(err) = REGISTRY.register(Box::new(summary.clone())) {
+        error!("Errors on registering the summaries. {:?}", err);
+    }
+}
+
+/// All the metric names registered by this crate, including the ones registered
+/// into the custom registry and the default registry.
+pub fn all_metric_names() -> Vec<&'static str> {
+    static ALL_METRIC_NAMES: Lazy<Vec<String>> = Lazy::new(|| {
+        let mut collectors = custom_collectors();
+        // the metrics registered by the macros
+        collectors.push(Box::new(GRPC_LATENCY_TIME_SEC.clone()));
+        collectors.push(Box::new(LOCALFILE_DISK_STAT_OPERATION_DURATION.clone()));
+        collectors.push(Box::new(LOCALFILE_DISK_APPEND_OPERATION_DURATION.clone()));
+        collectors.push(Box::new(LOCALFILE_DISK_READ_OPERATION_DURATION.clone()));
+        collectors.push(Box::new(LOCALFILE_DISK_DELETE_OPERATION_DURATION.clone()));
+        collectors.push(Box::new(TOTAL_LOCAL_DISK_APPEND_OPERATION_COUNTER.clone()));
+        collectors.push(Box::new(
+            TOTAL_LOCAL_DISK_APPEND_OPERATION_BYTES_COUNTER.clone(),
+        ));
+        collectors.push(Box::new(TOTAL_LOCAL_DISK_READ_TIMEOUT_COUNTER.clone()));
+        collectors.push(Box::new(EVENT_BUS_HANDLE_DURATION.clone()));
+        collectors.push(Box::new(MEMORY_SPILL_DURATION_TO.clone()));
+
+        let mut names = vec![];
+        for collector in collectors {
+            for desc in collector.desc() {
+                if !names.contains(&desc.fq_name) {
+                    names.push(desc.fq_name.clone());
+                }
+            }
+        }
+        names.sort();
+        names
+    });
+    ALL_METRIC_NAMES.iter().map(|x| x.as_str()).collect()
 }
 
 pub struct MetricService;
 impl MetricService {
     pub fn init(config: &Config, runtime_manager: RuntimeManager) {
+        // the metrics are always registered to be pulled by the http service
+        register_custom_metrics();
+        if let Some(cfg) = config.metrics.as_ref().filter(|x| x.summaries_enabled()) {
+            register_summaries(cfg.summary_quantiles().unwrap());
+        }
+
         if config.metrics.is_none() {
-            info!("Metrics config is not found. Disable this");
+            info!("Metrics config is not found. Disable pushing metrics");
             return;
         }
 
-        register_custom_metrics();
-
         let job_name = "uniffle-worker";
         let cfg = config.metrics.clone().unwrap();
 
+        // the secret is loaded once at startup
+        let auth = match (&cfg.push_gateway_username, cfg.push_gateway_password()) {
+            (Some(username), Ok(password)) => {
+                Some((username.clone(), password.unwrap_or_default()))
+            }
+            (_, Err(e)) => {
+                error!("Errors on loading the push gateway password. {:?}", e);
+                None
+            }
+            _ => None,
+        };
+
         let push_gateway_endpoint = cfg.push_gateway_endpoint;
-        if let Some(ref _endpoint) = push_gateway_endpoint {
+        if let Some(endpoint) = push_gateway_endpoint {
             let push_interval_sec = cfg.push_interval_sec;
-            runtime_manager.default_runtime.spawn(async move {
-                info!("Starting prometheus metrics exporter...");
-                loop {
-                    tokio::time::sleep(Duration::from_secs(push_interval_sec as u64)).await;
-
-                    // refresh the allocator size metrics
-                    #[cfg(all(unix, feature = "allocator-analysis"))]
-                    GAUGE_ALLOCATOR_ALLOCATED_SIZE.set(ALLOCATOR.allocated() as i64);
-
-                    let general_metrics = prometheus::gather();
-                    let custom_metrics = REGISTRY.gather();
-                    let mut metrics = vec![];
-                    metrics.extend_from_slice(&custom_metrics);
-                    metrics.extend_from_slice(&general_metrics);
-
-                    let pushed_result = prometheus::push_add_metrics(
-                        job_name,
-                        labels! {"worker_id".to_owned() => SHUFFLE_SERVER_ID.get().unwrap().to_string(),},
-                        &push_gateway_endpoint.to_owned().unwrap().to_owned(),
-                        metrics,
-                        None,
-                    );
-                    if pushed_result.is_err() {
-                        error!("Errors on pushing metrics. {:?}", pushed_result.err());
+            let pushed_endpoint = endpoint.clone();
+            let pushed_auth = auth.clone();
+            runtime_manager
+                .default_runtime
+                .spawn_guarded("metrics_pusher", async move {
+                    info!("Starting prometheus metrics exporter...");
+                    loop {
+                        tokio::time::sleep(Duration::from_secs(push_interval_sec as u64)).await;
+                        push_metrics(job_name, &pushed_endpoint, &pushed_auth);
                     }
-                }
-            });
+                });
+
+            // push the final metrics before exiting
+            SHUTDOWN_COORDINATOR.register(
+                "metrics_push",
+                PHASE_FINAL,
+                Duration::from_secs(5),
+                move || async move {
+                    push_metrics(job_name, &endpoint, &auth);
+                    Ok(())
+                },
+            );
+        }
+    }
+}
+
+fn push_metrics(job_name: &str, endpoint: &str, auth: &Option<(String, String)>) {
+    // refresh the allocator size metrics
+    #[cfg(all(unix, feature = "allocator-analysis"))]
+    GAUGE_ALLOCATOR_ALLOCATED_SIZE.set(ALLOCATOR.allocated() as i64);
+
+    let metrics = gather_all_metrics();
+
+    let pushed_result = prometheus::push_add_metrics(
+        job_name,
+        labels! {"worker_id".to_owned() => SHUFFLE_SERVER_ID.get().unwrap().to_string(),},
+        endpoint,
+        metrics,
+        auth.as_ref()
+            .map(|(username, password)| prometheus::BasicAuthentication {
+                username: username.clone(),
+                password: password.clone(),
+            }),
+    );
+    if pushed_result.is_err() {
+        error!("Errors on pushing metrics. {:?}", pushed_result.err());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::metric::{all_metric_names, SummaryVec};
+    use prometheus::core::Collector;
+    use prometheus::proto::MetricType;
+    use prometheus::Registry;
+
+    #[test]
+    fn test_all_metric_names() {
+        let names = all_metric_names();
+        for name in [
+            "eventbus_queue_pending_size",
+            "eventbus_queue_handling_size",
+            "eventbus_total_published_event_size",
+            "eventbus_total_handled_event_size",
+            "eventbus_handle_operation_duration",
+            "total_slow_request",
+            "memory_ticket_wait_duration",
+            "total_buffer_admission",
+        ] {
+            assert!(names.contains(&name), "metric: {} is missing", name);
+        }
+
+        let mut deduplicated = names.clone();
+        deduplicated.dedup();
+        assert_eq!(names.len(), deduplicated.len());
+    }
+
+    #[test]
+    fn test_summary() {
+        let summary = SummaryVec::new(
+            "test_summary",
+            "test summary",
+            "name",
+            vec![0.5, 0.95, 0.99],
+        )
+        .unwrap();
+        let registry = Registry::new();
+        registry.register(Box::new(summary.clone())).unwrap();
+
+        for value in 1..=100 {
+            summary.observe("spill", value as f64);
+        }
+        summary.observe("other", 1.0);
+
+        let families = registry.gather();
+        assert_eq!(1, families.len());
+        assert_eq!("test_summary", families[0].get_name());
+        assert_eq!(MetricType::SUMMARY, families[0].get_field_type());
+
+        let metrics = families[0].get_metric();
+        assert_eq!(2, metrics.len());
+        let spill = metrics
+            .iter()
+            .find(|x| x.get_label()[0].get_value() == "spill")
+            .unwrap()
+            .get_summary();
+        assert_eq!(100, spill.get_sample_count());
+        assert_eq!(5050.0, spill.get_sample_sum());
+        let quantiles: Vec<(f64, f64)> = spill
+            .get_quantile()
+            .iter()
+            .map(|x| (x.get_quantile(), x.get_value()))
+            .collect();
+        assert_eq!(vec![(0.5, 50.0), (0.95, 95.0), (0.99, 99.0)], quantiles);
+
+        // the quantiles are calculated over the recent samples only
+        for _ in 0..2048 {
+            summary.observe("spill", 1000.0);
         }
+        let family = &summary.collect()[0];
+        let spill = family
+            .get_metric()
+            .iter()
+            .find(|x| x.get_label()[0].get_value() == "spill")
+            .unwrap()
+            .get_summary();
+        assert_eq!(2148, spill.get_sample_count());
+        assert_eq!(1000.0, spill.get_quantile()[0].get_value());
     }
 }
@@ -12,7 +12,8 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-// This file is copied from https://github.com/tikv/raft-engine/blob/8dd2a39f359ff16f5295f35343f626e0c10132fa/src/util.rs without any modification.
+// This file is copied from https://github.com/tikv/raft-engine/blob/8dd2a39f359ff16f5295f35343f626e0c10132fa/src/util.rs,
+// with the lenient `ReadableSize::parse` added.
 
 use std::fmt;
 use std::fmt::{Display, Write};
@@ -32,6 +33,10 @@ pub const GIB: u64 = MIB * BINARY_DATA_MAGNITUDE;
 pub const TIB: u64 = GIB * BINARY_DATA_MAGNITUDE;
 pub const PIB: u64 = TIB * BINARY_DATA_MAGNITUDE;
 
+const DECIMAL_DATA_MAGNITUDE: u64 = 1000;
+pub const MB: u64 = DECIMAL_DATA_MAGNITUDE * DECIMAL_DATA_MAGNITUDE;
+pub const GB: u64 = MB * DECIMAL_DATA_MAGNITUDE;
+
 #[derive(Clone, Debug, Copy, PartialEq, Eq, PartialOrd)]
 pub struct ReadableSize(pub u64);
 
@@ -48,13 +53,31 @@ impl ReadableSize {
         ReadableSize(count * GIB)
     }
 
-    pub const fn as_mb(self) -> u64 {
-        self.0 / MIB
-    }
-
     pub const fn as_bytes(self) -> u64 {
         self.0
     }
+
+    pub fn as_kib(self) -> f64 {
+        self.0 as f64 / KIB as f64
+    }
+
+    pub fn as_mib(self) -> f64 {
+        self.0 as f64 / MIB as f64
+    }
+
+    pub fn as_gib(self) -> f64 {
+        self.0 as f64 / GIB as f64
+    }
+
+    /// In the decimal unit of 10^6 bytes. Note that the parsed `MB` is 2^20 bytes.
+    pub fn as_mb(self) -> f64 {
+        self.0 as f64 / MB as f64
+    }
+
+    /// In the decimal unit of 10^9 bytes. Note that the parsed `GB` is 2^30 bytes.
+    pub fn as_gb(self) -> f64 {
+        self.0 as f64 / GB as f64
+    }
 }
 
 impl Div<u64> for ReadableSize {
@@ -112,6 +135,18 @@ impl FromStr for ReadableSize {
 
     // This method parses value in binary unit.
     fn from_str(s: &str) -> Result<ReadableSize, String> {
+        ReadableSize::parse_with(s, false)
+    }
+}
+
+impl ReadableSize {
+    /// Parse the size like `from_str`, but the unit letters are case-insensitive,
+    /// such as `256m`, `256Mb` and ` 256 MB `.
+    pub fn parse(s: &str) -> Result<ReadableSize, String> {
+        ReadableSize::parse_with(s, true)
+    }
+
+    fn parse_with(s: &str, lenient: bool) -> Result<ReadableSize, String> {
         let size_str = s.trim();
         if size_str.is_empty() {
             return Err(format!("{:?} is not a valid size.", s));
@@ -131,7 +166,18 @@ impl FromStr for ReadableSize {
         // unit: alphabetic characters
         let (size, unit) = size_str.split_at(size_len);
 
-        let unit = match unit.trim() {
+        // normalize the unit to the canonical form, like `mib` -> `MiB`
+        let unit = match lenient {
+            true => {
+                let unit = unit.trim().to_ascii_uppercase();
+                match unit.strip_suffix("IB") {
+                    Some(prefix) => format!("{}iB", prefix),
+                    None => unit,
+                }
+            }
+            false => unit.trim().to_string(),
+        };
+        let unit = match unit.as_str() {
             "K" | "KB" | "KiB" => KIB,
             "M" | "MB" | "MiB" => MIB,
             "G" | "GB" | "GiB" => GIB,
@@ -223,19 +269,34 @@ mod tests {
     fn test_readable_size() {
         let s = ReadableSize::kb(2);
         assert_eq!(s.0, 2048);
-        assert_eq!(s.as_mb(), 0);
+        assert_eq!(s.as_kib(), 2.0);
         let s = ReadableSize::mb(2);
         assert_eq!(s.0, 2 * 1024 * 1024);
-        assert_eq!(s.as_mb(), 2);
+        assert_eq!(s.as_mib(), 2.0);
         let s = ReadableSize::gb(2);
         assert_eq!(s.0, 2 * 1024 * 1024 * 1024);
-        assert_eq!(s.as_mb(), 2048);
+        assert_eq!(s.as_mib(), 2048.0);
 
         assert_eq!((ReadableSize::mb(2) / 2).0, MIB);
         assert_eq!((ReadableSize::mb(1) / 2).0, 512 * KIB);
         assert_eq!(ReadableSize::mb(2) / ReadableSize::kb(1), 2048);
     }
 
+    #[test]
+    fn test_readable_size_converters() {
+        let s = ReadableSize::from_str("1536M").unwrap();
+        assert_eq!(s.as_gib(), 1.5);
+        assert_eq!(s.as_mib(), 1536.0);
+        assert_eq!(s.as_kib(), 1536.0 * 1024.0);
+
+        // the decimal units
+        let s = ReadableSize(1_500_000_000);
+        assert_eq!(s.as_gb(), 1.5);
+        assert_eq!(s.as_mb(), 1500.0);
+        assert_eq!(ReadableSize::mb(1).as_mb(), 1.048576);
+        assert_eq!(ReadableSize(0).as_gib(), 0.0);
+    }
+
     #[test]
     fn test_parse_readable_size() {
         #[derive(Serialize, Deserialize)]
@@ -320,4 +381,33 @@ mod tests {
             assert!(toml::from_str::<SizeHolder>(&src_str).is_err(), "{}", src);
         }
     }
+
+    #[test]
+    fn test_lenient_parse() {
+        let cases = vec![
+            (" 256 MB ", 256 * MIB),
+            ("256m", 256 * MIB),
+            ("256Mb", 256 * MIB),
+            ("256mib", 256 * MIB),
+            ("0.5 gb", GIB / 2),
+            ("1k", KIB),
+            ("1 KIB", KIB),
+            ("10b", 10),
+            ("\t2 tB\n", 2 * TIB),
+            ("23", 23),
+        ];
+        for (src, exp) in cases {
+            assert_eq!(ReadableSize::parse(src).unwrap().0, exp, "{}", src);
+        }
+
+        let illegal_cases = vec![
+            "", "  ", "M256", "12.3.4G", "1K24B", "5M_", "4B7", "gb", "1 ib", "1.5XB",
+        ];
+        for src in illegal_cases {
+            assert!(ReadableSize::parse(src).is_err(), "{}", src);
+        }
+
+        // the strict parsing is unchanged
+        assert!(ReadableSize::from_str("256m").is_err());
+    }
 }
@@ -3,26 +3,30 @@ use crate::await_tree::AWAIT_TREE_REGISTRY;
 use crate::config::Config;
 use crate::grpc::layer::awaittree::AwaitTreeMiddlewareLayer;
 use crate::grpc::layer::metric::MetricsMiddlewareLayer;
+use crate::grpc::layer::slow_log::SlowLogMiddlewareLayer;
 use crate::grpc::layer::tracing::TracingMiddleWareLayer;
 use crate::grpc::protobuf::uniffle::shuffle_server_server::ShuffleServerServer;
 use crate::grpc::service::{DefaultShuffleServer, MAX_CONNECTION_WINDOW_SIZE, STREAM_WINDOW_SIZE};
 use crate::metric::GRPC_LATENCY_TIME_SEC;
 use crate::runtime::manager::RuntimeManager;
-use crate::signal::details::graceful_wait_for_signal;
+use crate::shutdown::{PHASE_STOP_SERVING, SHUTDOWN_COORDINATOR};
+use crate::signal::details::wait_for_signal;
 use crate::urpc;
+use crate::urpc::connection::CoalesceOptions;
 use crate::util::is_port_used;
 use anyhow::Result;
 use async_trait::async_trait;
 use log::{debug, error, info};
 use once_cell::sync::Lazy;
 use std::future::Future;
-use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::net::SocketAddr;
 use std::num::NonZeroUsize;
+use std::time::Duration;
 use tokio::net::TcpListener;
 use tokio::sync::broadcast;
 use tokio::sync::broadcast::{Receiver, Sender};
 use tokio_stream::wrappers::TcpListenerStream;
-use tonic::transport::Server;
+use tonic::transport::{Certificate, Identity, Server, ServerTlsConfig};
 
 pub static GRPC_PARALLELISM: Lazy<NonZeroUsize> = Lazy::new(|| {
     let available_cores = std::thread::available_parallelism().unwrap();
@@ -59,7 +63,12 @@ impl DefaultRpcService {
         app_manager_ref: AppManagerRef,
     ) -> Result<()> {
         let urpc_port = config.urpc_port.unwrap();
-        info!("Starting urpc server with port:[{}] ......", urpc_port);
+        let bind_address = config.bind_address()?;
+        let coalesce = CoalesceOptions::from(&config.urpc)?;
+        info!(
+            "Starting urpc server with address:[{}:{}] ......",
+            bind_address, urpc_port
+        );
 
         for _ in 0..URPC_PARALLELISM.get() {
             let rx = tx.subscribe();
@@ -73,14 +82,14 @@ impl DefaultRpcService {
             }
 
             let app_manager = app_manager_ref.clone();
-            let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)), urpc_port as u16);
+            let addr = SocketAddr::new(bind_address, urpc_port as u16);
 
             std::thread::spawn(move || {
                 tokio::runtime::Builder::new_current_thread()
                     .enable_all()
                     .build()
                     .unwrap()
-                    .block_on(urpc_serve(addr, shutdown(rx), app_manager));
+                    .block_on(urpc_serve(addr, shutdown(rx), app_manager, coalesce));
             });
         }
 
@@ -94,19 +103,45 @@ impl DefaultRpcService {
         app_manager_ref: AppManagerRef,
     ) -> Result<()> {
         let grpc_port = config.grpc_port;
+        let bind_address = config.bind_address()?;
 
-        info!("Starting grpc server with port:[{}] ......", grpc_port);
+        info!(
+            "Starting grpc server with address:[{}:{}] ......",
+            bind_address, grpc_port
+        );
         let parallelism = GRPC_PARALLELISM.get();
         info!("grpc service with parallelism: [{}]", &parallelism);
 
+        let max_recv_message_size = config.grpc_max_recv_message_size()?;
+        let max_send_message_size = config.grpc_max_send_message_size()?;
+
+        let tls_config = match &config.grpc_tls {
+            Some(tls) => {
+                let cert = std::fs::read(&tls.cert_path)?;
+                let key = std::fs::read(&tls.key_path)?;
+                let mut tls_config = ServerTlsConfig::new().identity(Identity::from_pem(cert, key));
+                if let Some(ca_path) = &tls.ca_path {
+                    let ca = std::fs::read(ca_path)?;
+                    tls_config = tls_config.client_ca_root(Certificate::from_pem(ca));
+                }
+                info!(
+                    "grpc service with tls enabled. client auth: {}",
+                    tls.ca_path.is_some()
+                );
+                Some(tls_config)
+            }
+            _ => None,
+        };
+
         let core_ids = core_affinity::get_core_ids().unwrap();
         for (_, core_id) in core_ids.into_iter().enumerate() {
             let shuffle_server = DefaultShuffleServer::from(app_manager_ref.clone());
-            let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)), grpc_port as u16);
+            let addr = SocketAddr::new(bind_address, grpc_port as u16);
             let service = ShuffleServerServer::new(shuffle_server)
-                .max_decoding_message_size(usize::MAX)
-                .max_encoding_message_size(usize::MAX);
+                .max_decoding_message_size(max_recv_message_size)
+                .max_encoding_message_size(max_send_message_size);
             let service_tx = tx.subscribe();
+            let tls_config = tls_config.clone();
 
             // every std::thread to bound the tokio thread to eliminate thread context switch.
             // this has been verified by benchmark of terasort 1TB that the p99 long tail latency
@@ -121,7 +156,7 @@ impl DefaultRpcService {
                     .enable_all()
                     .build()
                     .unwrap()
-                    .block_on(grpc_serve(service, addr, service_tx));
+                    .block_on(grpc_serve(service, addr, service_tx, tls_config));
             });
         }
 
@@ -162,13 +197,29 @@ impl DefaultRpcService {
             )?;
         }
 
-        graceful_wait_for_signal(tx);
+        SHUTDOWN_COORDINATOR.register(
+            "rpc_services",
+            PHASE_STOP_SERVING,
+            Duration::from_secs(5),
+            move || async move {
+                tx.send(())?;
+                Ok(())
+            },
+        );
+
+        wait_for_signal();
+        SHUTDOWN_COORDINATOR.shutdown_blocking(&runtime_manager.default_runtime);
 
         Ok(())
     }
 }
 
-async fn urpc_serve(addr: SocketAddr, shutdown: impl Future, app_manager_ref: AppManagerRef) {
+async fn urpc_serve(
+    addr: SocketAddr,
+    shutdown: impl Future,
+    app_manager_ref: AppManagerRef,
+    coalesce: Option<CoalesceOptions>,
+) {
     let sock = socket2::Socket::new(
         match addr {
             SocketAddr::V4(_) => socket2::Domain::IPV4,
@@ -186,13 +237,14 @@ async fn urpc_serve(addr: SocketAddr, shutdown: impl Future, app_manager_ref: Ap
     sock.listen(8192).unwrap();
 
     let listener = TcpListener::from_std(sock.into()).unwrap();
-    let _ = urpc::server::run(listener, shutdown, app_manager_ref).await;
+    let _ = urpc::server::run(listener, shutdown, app_manager_ref, coalesce).await;
 }
 
 async fn grpc_serve(
     service: ShuffleServerServer<DefaultShuffleServer>,
     addr: SocketAddr,
     mut rx: broadcast::Receiver<()>,
+    tls_config: Option<ServerTlsConfig>,
 ) {
     let sock = socket2::Socket::new(
         match addr {
@@ -212,7 +264,12 @@ async fn grpc_serve(
 
     let incoming = TcpListenerStream::new(TcpListener::from_std(sock.into()).unwrap());
 
-    Server::builder()
+    let mut builder = Server::builder();
+    if let Some(tls_config) = tls_config {
+        builder = builder.tls_config(tls_config).unwrap();
+    }
+
+    builder
         .initial_connection_window_size(MAX_CONNECTION_WINDOW_SIZE)
         .initial_stream_window_size(STREAM_WINDOW_SIZE)
         .tcp_nodelay(true)
@@ -221,6 +278,7 @@ async fn grpc_serve(
         .layer(AwaitTreeMiddlewareLayer::new_optional(Some(
             AWAIT_TREE_REGISTRY.clone(),
         )))
+        .layer(SlowLogMiddlewareLayer)
         .add_service(service)
         .serve_with_incoming_shutdown(incoming, async {
             if let Err(err) = rx.recv().await {
@@ -15,7 +15,7 @@
 // specific language governing permissions and limitations
 // under the License.
 
-use crate::config::RuntimeConfig;
+use crate::config::{parse_cpuset, PoolType, RuntimeConfig};
 use crate::runtime::{Builder, RuntimeRef};
 use std::future::Future;
 use std::sync::Arc;
@@ -32,6 +32,8 @@ pub struct RuntimeManager {
     // like the data purging/ heartbeat / metric push
     pub default_runtime: RuntimeRef,
     pub dispatch_runtime: RuntimeRef,
+    // for the memory spill flushing, to avoid slowing down the foreground writing
+    pub flush_runtime: RuntimeRef,
 }
 
 pub fn create_runtime(pool_size: usize, name: &str) -> RuntimeRef {
@@ -45,6 +47,34 @@ pub fn create_runtime(pool_size: usize, name: &str) -> RuntimeRef {
     )
 }
 
+// the threads are named like riffle-read-3 to be distinguished in the profiling
+fn create_runtime_with(
+    pool_size: usize,
+    name: &str,
+    thread_name_prefix: &str,
+    cpuset: &Option<String>,
+    pool_type: PoolType,
+) -> RuntimeRef {
+    let cpuset = match cpuset {
+        Some(cpuset) => parse_cpuset(name, cpuset).unwrap(),
+        _ => vec![],
+    };
+    let mut builder = match pool_type {
+        PoolType::MultiThread => Builder::default(),
+        PoolType::CurrentThread => Builder::new_current_thread(),
+    };
+    Arc::new(
+        builder
+            .worker_threads(pool_size)
+            .thread_name(name)
+            .thread_name_prefix(thread_name_prefix)
+            .cpuset(cpuset)
+            .enable_all()
+            .build()
+            .unwrap(),
+    )
+}
+
 impl Default for RuntimeManager {
     fn default() -> Self {
         RuntimeManager::from(Default::default())
@@ -54,11 +84,62 @@ impl Default for RuntimeManager {
 impl RuntimeManager {
     pub fn from(config: RuntimeConfig) -> Self {
         Self {
-            read_runtime: create_runtime(config.read_thread_num, "read_thread_pool"),
-            write_runtime: create_runtime(config.write_thread_num, "write_thread_pool"),
-            http_runtime: create_runtime(config.http_thread_num, "http_thread_pool"),
-            default_runtime: create_runtime(config.default_thread_num, "default_thread_pool"),
-            dispatch_runtime: create_runtime(config.dispatch_thread_num, "dispatch_thread_pool"),
+            read_runtime: create_runtime_with(
+                config.read_thread_num,
+                "read_thread_pool",
+                "riffle-read",
+                &config.read_thread_cpuset,
+                config.pool_type(config.read_thread_num),
+            ),
+            write_runtime: create_runtime_with(
+                config.write_thread_num,
+                "write_thread_pool",
+                "riffle-write",
+                &config.write_thread_cpuset,
+                config.pool_type(config.write_thread_num),
+            ),
+            http_runtime: create_runtime_with(
+                config.http_thread_num,
+                "http_thread_pool",
+                "riffle-http",
+                &config.http_thread_cpuset,
+                config.pool_type(config.http_thread_num),
+            ),
+            default_runtime: create_runtime_with(
+                config.default_thread_num,
+                "default_thread_pool",
+                "riffle-default",
+                &config.default_thread_cpuset,
+                config.pool_type(config.default_thread_num),
+            ),
+            dispatch_runtime: create_runtime_with(
+                config.dispatch_thread_num,
+                "dispatch_thread_pool",
+                "riffle-dispatch",
+                &config.dispatch_thread_cpuset,
+                config.pool_type(config.dispatch_thread_num),
+            ),
+            flush_runtime: create_runtime_with(
+                config.flush_thread_num,
+                "flush_thread_pool",
+                "riffle-flush",
+                &config.flush_thread_cpuset,
+                config.pool_type(config.flush_thread_num),
+            ),
+        }
+    }
+
+    /// Stop all the runtimes, used by the embedded servers to be torn down in tests.
+    pub fn shutdown(&self) {
+        for runtime in [
+            &self.read_runtime,
+            &self.write_runtime,
+            &self.http_runtime,
+            &self.default_runtime,
+            &self.dispatch_runtime,
+            &self.flush_runtime,
+        ] {
+            runtime.shutdown();
         }
     }
 
@@ -67,3 +148,124 @@ impl RuntimeManager {
         self.default_runtime.block_on(future)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::config::{PoolType, RuntimeConfig};
+    use crate::runtime::manager::RuntimeManager;
+    use std::time::{Duration, Instant};
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_thread_names() {
+        let runtime_manager = RuntimeManager::from(RuntimeConfig {
+            read_thread_num: 2,
+            read_thread_cpuset: Some("0".to_string()),
+            ..Default::default()
+        });
+        // make sure the worker threads have been started
+        let name = runtime_manager
+            .wait(
+                runtime_manager
+                    .read_runtime
+                    .spawn(async { std::thread::current().name().map(|x| x.to_string()) }),
+            )
+            .unwrap();
+        assert!(name.unwrap().starts_with("riffle-read-"));
+
+        let names: Vec<String> = std::fs::read_dir("/proc/self/task")
+            .unwrap()
+            .filter_map(|task| std::fs::read_to_string(task.unwrap().path().join("comm")).ok())
+            .map(|comm| comm.trim().to_string())
+            .collect();
+        for expected in [
+            "riffle-read-0",
+            "riffle-read-1",
+            "riffle-write-0",
+            "riffle-flush-0",
+        ] {
+            assert!(
+                names.iter().any(|name| name == expected),
+                "{} is not found in {:?}",
+                expected,
+                names
+            );
+        }
+    }
+
+    #[test]
+    fn test_single_thread_pool() {
+        let runtime_manager = RuntimeManager::from(RuntimeConfig {
+            http_thread_num: 1,
+            single_thread_pool_type: PoolType::CurrentThread,
+            ..Default::default()
+        });
+        let runtime = runtime_manager.http_runtime.clone();
+
+        let cloned = runtime.clone();
+        let (thread_names, sum) = runtime_manager
+            .wait(runtime.spawn(async move {
+                let mut handles = vec![];
+                for i in 1..=10 {
+                    handles.push(cloned.spawn(async move {
+                        tokio::time::sleep(Duration::from_millis(10)).await;
+                        (std::thread::current().name().map(|x| x.to_string()), i)
+                    }));
+                }
+                let mut thread_names = vec![];
+                let mut sum = 0;
+                for handle in handles {
+                    let (name, i) = handle.await.unwrap();
+                    thread_names.push(name.unwrap());
+                    sum += i;
+                }
+                sum += cloned.spawn_blocking(|| 100).await.unwrap();
+                (thread_names, sum)
+            }))
+            .unwrap();
+        assert_eq!(155, sum);
+        // all the tasks are run on the only thread
+        assert!(thread_names.iter().all(|name| name == "riffle-http-0"));
+
+        runtime.shutdown();
+    }
+
+    #[test]
+    fn test_flush_isolated_from_foreground_writing() {
+        let runtime_manager = RuntimeManager::from(RuntimeConfig {
+            write_thread_num: 2,
+            flush_thread_num: 2,
+            ..Default::default()
+        });
+
+        // the mocked foreground insert, which is fast when its runtime is not busy
+        let insert_latency = || {
+            let start = Instant::now();
+            runtime_manager
+                .wait(runtime_manager.write_runtime.spawn(async {
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                }))
+                .unwrap();
+            start.elapsed()
+        };
+        let baseline = insert_latency();
+
+        // saturate all the flush threads with the slow writes
+        for _ in 0..8 {
+            runtime_manager.flush_runtime.spawn_guarded("flush", async {
+                std::thread::sleep(Duration::from_millis(500));
+            });
+        }
+        std::thread::sleep(Duration::from_millis(50));
+
+        for _ in 0..5 {
+            let latency = insert_latency();
+            assert!(
+                latency < baseline + Duration::from_millis(100),
+                "foreground insert latency: {:?}, baseline: {:?}",
+                latency,
+                baseline
+            );
+        }
+    }
+}
@@ -18,10 +18,20 @@
 pub mod manager;
 mod metrics;
 
+use crate::health::{ComponentHealth, HealthProvider, HealthStatus, RecentEventCounter};
+use crate::metric::TOTAL_TASK_PANICS;
 use crate::runtime::metrics::Metrics;
 use anyhow::anyhow;
+use async_trait::async_trait;
+use futures::FutureExt;
+use log::warn;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
 use pin_project_lite::pin_project;
+use std::any::Any;
 use std::fmt::Debug;
+use std::panic::AssertUnwindSafe;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::{
     future::Future,
     pin::Pin,
@@ -29,17 +39,32 @@ use std::{
     task::{Context, Poll},
 };
 use tokio::runtime::Builder as TokioRuntimeBuilder;
+use tokio::runtime::Handle;
 use tokio::runtime::Runtime as TokioRuntime;
+use tokio::sync::oneshot;
 use tokio::task::JoinHandle as TokioJoinHandle;
 
 pub type RuntimeRef = Arc<Runtime>;
 
+// the task panics in the last minute
+static RECENT_TASK_PANICS: Lazy<RecentEventCounter> = Lazy::new(|| RecentEventCounter::new(60));
+
 #[derive(Debug)]
 pub struct Runtime {
-    rt: TokioRuntime,
+    name: String,
+    handle: Handle,
+    // taken out on shutdown, the tasks spawned after that will never be run
+    rt: Mutex<Option<RuntimeOwner>>,
     metrics: Arc<Metrics>,
 }
 
+#[derive(Debug)]
+enum RuntimeOwner {
+    Owned(TokioRuntime),
+    // the current thread runtime is owned and driven by its dedicated thread until stopped
+    DrivingThread(oneshot::Sender<()>),
+}
+
 impl Runtime {
     pub fn spawn<F>(&self, future: F) -> JoinHandle<F::Output>
     where
@@ -47,22 +72,45 @@ impl Runtime {
         F::Output: Send + 'static,
     {
         JoinHandle {
-            inner: self.rt.spawn(future),
+            inner: self.handle.spawn(future),
         }
     }
 
+    /// Spawn the task whose panic will be logged and counted with the task group,
+    /// rather than silently disappearing into the never awaited join handle.
+    /// The panic is still propagated to the join handle.
+    pub fn spawn_guarded<F>(&self, task_group: &str, future: F) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        self.spawn(guarded(self.name.clone(), task_group.to_string(), future))
+    }
+
     pub fn spawn_blocking<F, R>(&self, func: F) -> JoinHandle<R>
     where
         F: FnOnce() -> R + Send + 'static,
         R: Send + 'static,
     {
         JoinHandle {
-            inner: self.rt.spawn_blocking(func),
+            inner: self.handle.spawn_blocking(func),
         }
     }
 
     pub fn block_on<F: Future>(&self, future: F) -> F::Output {
-        self.rt.block_on(future)
+        self.handle.block_on(future)
+    }
+
+    /// Shutdown the runtime without waiting for the running tasks, which is safe to be
+    /// invoked in the async context, unlike dropping the underlying tokio runtime.
+    pub fn shutdown(&self) {
+        match self.rt.lock().take() {
+            Some(RuntimeOwner::Owned(rt)) => rt.shutdown_background(),
+            Some(RuntimeOwner::DrivingThread(stop)) => {
+                let _ = stop.send(());
+            }
+            _ => {}
+        }
     }
 
     pub fn stats(&self) -> RuntimeStats {
@@ -73,6 +121,67 @@ impl Runtime {
     }
 }
 
+async fn guarded<F: Future>(runtime: String, task_group: String, future: F) -> F::Output {
+    match AssertUnwindSafe(future).catch_unwind().await {
+        Ok(output) => output,
+        Err(payload) => {
+            tracing::error!(
+                "Task panicked in runtime: [{}], task group: [{}]. panic: {}",
+                &runtime,
+                &task_group,
+                panic_message(payload.as_ref())
+            );
+            TOTAL_TASK_PANICS
+                .with_label_values(&[&runtime, &task_group])
+                .inc();
+            RECENT_TASK_PANICS.record();
+            std::panic::resume_unwind(payload)
+        }
+    }
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.as_str()
+    } else {
+        "unknown panic payload"
+    }
+}
+
+/// Marking the server unhealthy once the task panics in the last minute reach the threshold.
+pub struct TaskPanicHealthProvider {
+    threshold: u64,
+}
+
+impl TaskPanicHealthProvider {
+    pub fn new(threshold: u64) -> Self {
+        Self { threshold }
+    }
+}
+
+#[async_trait]
+impl HealthProvider for TaskPanicHealthProvider {
+    async fn component_health(&self) -> Vec<ComponentHealth> {
+        let panics = RECENT_TASK_PANICS.count();
+        let status = if panics >= self.threshold {
+            HealthStatus::Unhealthy
+        } else {
+            HealthStatus::Healthy
+        };
+        vec![ComponentHealth::new(
+            "task_panics",
+            status,
+            true,
+            serde_json::json!({
+                "panics_in_last_minute": panics,
+                "threshold": self.threshold,
+            }),
+        )]
+    }
+}
+
 #[derive(Debug)]
 pub struct RuntimeStats {
     pub alive_thread_num: i64,
@@ -106,6 +215,9 @@ impl<T> Future for JoinHandle<T> {
 
 pub struct Builder {
     thread_name: String,
+    thread_name_prefix: Option<String>,
+    cpuset: Vec<usize>,
+    current_thread: bool,
     builder: TokioRuntimeBuilder,
 }
 
@@ -113,11 +225,27 @@ impl Default for Builder {
     fn default() -> Self {
         Self {
             thread_name: "runtime-worker".to_string(),
+            thread_name_prefix: None,
+            cpuset: vec![],
+            current_thread: false,
             builder: TokioRuntimeBuilder::new_multi_thread(),
         }
     }
 }
 
+fn pin_to_cpuset(cpuset: &[usize], thread_idx: usize) {
+    if cpuset.is_empty() {
+        return;
+    }
+    let core_id = core_affinity::CoreId {
+        id: cpuset[thread_idx % cpuset.len()],
+    };
+    // it's always false on the unsupported platforms, just ignore
+    if !core_affinity::set_for_current(core_id) {
+        warn!("Failed to pin the thread to the cpu: {}", core_id.id);
+    }
+}
+
 fn with_metrics<F>(metrics: &Arc<Metrics>, f: F) -> impl Fn()
 where
     F: Fn(&Arc<Metrics>) + 'static,
@@ -129,7 +257,18 @@ where
 }
 
 impl Builder {
+    /// The runtime whose tasks are all run on the single dedicated thread, which saves
+    /// the work stealing overhead of the multi thread one for the small pool.
+    pub fn new_current_thread() -> Self {
+        Self {
+            current_thread: true,
+            builder: TokioRuntimeBuilder::new_current_thread(),
+            ..Default::default()
+        }
+    }
+
     /// Sets the number of worker threads the Runtime will use.
+    /// It's ignored by the current thread runtime.
     ///
     /// This can be any number above 0
     pub fn worker_threads(&mut self, val: usize) -> &mut Self {
@@ -143,6 +282,19 @@ impl Builder {
         self
     }
 
+    /// Sets the thread name like `{prefix}-{index}` rather than the same one for all threads.
+    /// The index is increased for every started thread.
+    pub fn thread_name_prefix(&mut self, val: impl Into<String>) -> &mut Self {
+        self.thread_name_prefix = Some(val.into());
+        self
+    }
+
+    /// Pin the threads to the given cpus in round robin, the empty one means no affinity.
+    pub fn cpuset(&mut self, val: Vec<usize>) -> &mut Self {
+        self.cpuset = val;
+        self
+    }
+
     /// Enable all feature of the underlying runtime
     pub fn enable_all(&mut self) -> &mut Self {
         self.builder.enable_all();
@@ -152,11 +304,29 @@ impl Builder {
     pub fn build(&mut self) -> anyhow::Result<Runtime> {
         let metrics = Arc::new(Metrics::new(&self.thread_name));
 
+        // the index 0 is taken by the driving thread of the current thread runtime
+        let driving_thread_name = match &self.thread_name_prefix {
+            Some(prefix) => {
+                let prefix = prefix.clone();
+                let thread_idx = AtomicUsize::new(self.current_thread as usize);
+                self.builder.thread_name_fn(move || {
+                    format!("{}-{}", &prefix, thread_idx.fetch_add(1, Ordering::SeqCst))
+                });
+                format!("{}-0", &prefix)
+            }
+            _ => {
+                self.builder.thread_name(self.thread_name.clone());
+                self.thread_name.clone()
+            }
+        };
+
+        let cpuset = self.cpuset.clone();
+        let started_threads = AtomicUsize::new(0);
         let rt = self
             .builder
-            .thread_name(self.thread_name.clone())
-            .on_thread_start(with_metrics(&metrics, |m| {
+            .on_thread_start(with_metrics(&metrics, move |m| {
                 m.on_thread_start();
+                pin_to_cpuset(&cpuset, started_threads.fetch_add(1, Ordering::SeqCst));
             }))
             .on_thread_stop(with_metrics(&metrics, |m| {
                 m.on_thread_stop();
@@ -168,17 +338,64 @@ impl Builder {
                 m.on_thread_unpark();
             }))
             .build()?;
+        let handle = rt.handle().clone();
+
+        let owner = match self.current_thread {
+            true => RuntimeOwner::DrivingThread(Self::drive(
+                rt,
+                driving_thread_name,
+                self.cpuset.clone(),
+                metrics.clone(),
+            )?),
+            false => RuntimeOwner::Owned(rt),
+        };
+
+        Ok(Runtime {
+            name: self.thread_name.clone(),
+            handle,
+            rt: Mutex::new(Some(owner)),
+            metrics,
+        })
+    }
 
-        Ok(Runtime { rt, metrics })
+    /// The spawned tasks of the current thread runtime are only run within its `block_on`,
+    /// so it's blocked on the dedicated thread until the returned sender is fired or dropped.
+    fn drive(
+        rt: TokioRuntime,
+        thread_name: String,
+        cpuset: Vec<usize>,
+        metrics: Arc<Metrics>,
+    ) -> anyhow::Result<oneshot::Sender<()>> {
+        let (stop_tx, stop_rx) = oneshot::channel::<()>();
+        std::thread::Builder::new()
+            .name(thread_name)
+            .spawn(move || {
+                metrics.on_thread_start();
+                pin_to_cpuset(&cpuset, 0);
+                rt.block_on(async move {
+                    let _ = stop_rx.await;
+                });
+                rt.shutdown_background();
+                metrics.on_thread_stop();
+            })?;
+        Ok(stop_tx)
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::runtime::{Builder, Runtime};
+    use crate::health::HealthProvider;
+    use crate::metric::TOTAL_TASK_PANICS;
+    use crate::runtime::{guarded, Builder, Runtime, TaskPanicHealthProvider};
+    use parking_lot::Mutex;
+    use std::panic::AssertUnwindSafe;
     use std::sync::Arc;
     use std::thread;
     use std::time::Duration;
+    use tracing::field::{Field, Visit};
+    use tracing::Event;
+    use tracing_subscriber::layer::{Context, SubscriberExt};
+    use tracing_subscriber::Layer;
 
     fn create_runtime(pool_size: usize, name: &str) -> Arc<Runtime> {
         let runtime = Builder::default()
@@ -261,4 +478,85 @@ mod tests {
         });
         assert_eq!(res.unwrap(), 2);
     }
+
+    #[tokio::test]
+    async fn test_shutdown() {
+        let runtime = create_runtime(2usize, "test_shutdown");
+        let handle = runtime.spawn(async {
+            tokio::time::sleep(Duration::from_secs(60)).await;
+        });
+
+        // it's safe to be invoked in the async context
+        runtime.shutdown();
+        assert!(handle.await.is_err());
+        assert!(runtime.spawn(async { 1 }).await.is_err());
+
+        // idempotent
+        runtime.shutdown();
+    }
+
+    struct MessageRecorder {
+        messages: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl<S: tracing::Subscriber> Layer<S> for MessageRecorder {
+        fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+            struct MessageVisitor(String);
+            impl Visit for MessageVisitor {
+                fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+                    if field.name() == "message" {
+                        self.0 = format!("{:?}", value);
+                    }
+                }
+            }
+            let mut visitor = MessageVisitor(String::new());
+            event.record(&mut visitor);
+            self.messages.lock().push(visitor.0);
+        }
+    }
+
+    #[test]
+    fn test_spawn_guarded() {
+        let runtime = create_runtime(1usize, "test_spawn_guarded");
+
+        // case1: the panic is counted and still propagated to the join handle
+        let handle = runtime.spawn_guarded("panic_task", async {
+            panic!("mocked panic");
+        });
+        assert!(runtime.block_on(handle).is_err());
+        assert_eq!(
+            1,
+            TOTAL_TASK_PANICS
+                .with_label_values(&["test_spawn_guarded", "panic_task"])
+                .get()
+        );
+
+        // the normal task is not affected
+        let handle = runtime.spawn_guarded("normal_task", async { 1 });
+        assert_eq!(1, runtime.block_on(handle).unwrap());
+
+        // case2: the panic is logged with the task group
+        let messages = Arc::new(Mutex::new(vec![]));
+        let subscriber = tracing_subscriber::registry().with(MessageRecorder {
+            messages: messages.clone(),
+        });
+        let result = tracing::subscriber::with_default(subscriber, || {
+            std::panic::catch_unwind(AssertUnwindSafe(|| {
+                runtime.block_on(guarded(
+                    "test_runtime".to_string(),
+                    "logged_task".to_string(),
+                    async { panic!("logged panic") },
+                ))
+            }))
+        });
+        assert!(result.is_err());
+        let messages = messages.lock();
+        assert!(messages
+            .iter()
+            .any(|x| x.contains("task group: [logged_task]") && x.contains("logged panic")));
+
+        // case3: the server is unhealthy once reaching the threshold
+        let health = runtime.block_on(TaskPanicHealthProvider::new(1).component_health());
+        assert_eq!(crate::health::HealthStatus::Unhealthy, health[0].status);
+    }
 }
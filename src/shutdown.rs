@@ -0,0 +1,339 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::runtime::RuntimeRef;
+use anyhow::{anyhow, Result};
+use futures::future::{join_all, BoxFuture};
+use futures::FutureExt;
+use log::{error, info, warn};
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+pub static SHUTDOWN_COORDINATOR: Lazy<ShutdownCoordinator> =
+    Lazy::new(|| ShutdownCoordinator::new(DEFAULT_SHUTDOWN_DEADLINE));
+
+pub const DEFAULT_SHUTDOWN_DEADLINE: Duration = Duration::from_secs(60);
+
+/// The phases are executed in the ascending order, and the hooks in the same phase concurrently.
+pub const PHASE_STOP_SERVING: u32 = 0;
+pub const PHASE_DRAIN: u32 = 10;
+pub const PHASE_UNREGISTER: u32 = 20;
+pub const PHASE_FINAL: u32 = 30;
+
+type HookFn = Box<dyn FnOnce() -> BoxFuture<'static, Result<()>> + Send>;
+
+struct ShutdownHook {
+    name: String,
+    phase: u32,
+    timeout: Duration,
+    hook: HookFn,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum HookOutcome {
+    Succeeded,
+    Failed(String),
+    TimedOut,
+}
+
+/// Running the registered shutdown hooks phase by phase, triggered by the
+/// terminate signals (the decommission raises SIGTERM once finished).
+/// The remaining hooks are abandoned once the global deadline passes.
+pub struct ShutdownCoordinator {
+    deadline: Mutex<Duration>,
+    hooks: Mutex<Vec<ShutdownHook>>,
+    triggered: AtomicBool,
+}
+
+impl ShutdownCoordinator {
+    pub fn new(deadline: Duration) -> Self {
+        Self {
+            deadline: Mutex::new(deadline),
+            hooks: Mutex::new(vec![]),
+            triggered: AtomicBool::new(false),
+        }
+    }
+
+    pub fn set_deadline(&self, deadline: Duration) {
+        *self.deadline.lock() = deadline;
+    }
+
+    pub fn deadline(&self) -> Duration {
+        *self.deadline.lock()
+    }
+
+    pub fn register<F, Fut>(&self, name: &str, phase: u32, timeout: Duration, hook: F)
+    where
+        F: FnOnce() -> Fut + Send + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        if self.triggered.load(Ordering::SeqCst) {
+            warn!(
+                "The shutdown has been triggered, ignoring the hook: [{}]",
+                name
+            );
+            return;
+        }
+        self.hooks.lock().push(ShutdownHook {
+            name: name.to_string(),
+            phase,
+            timeout,
+            hook: Box::new(move || hook().boxed()),
+        });
+    }
+
+    /// Execute all the hooks and return their outcomes in the executed order.
+    /// It fails if it has been triggered or the global deadline is exceeded.
+    pub async fn shutdown(&self) -> Result<Vec<(String, HookOutcome)>> {
+        if self.triggered.swap(true, Ordering::SeqCst) {
+            return Err(anyhow!("The shutdown has been triggered"));
+        }
+        let mut hooks: Vec<ShutdownHook> = std::mem::take(&mut *self.hooks.lock());
+        hooks.sort_by_key(|hook| hook.phase);
+
+        let deadline = self.deadline();
+        let outcomes = Mutex::new(vec![]);
+        let start = Instant::now();
+        let executed = tokio::time::timeout(deadline, async {
+            let mut hooks = hooks.into_iter().peekable();
+            while let Some(phase) = hooks.peek().map(|hook| hook.phase) {
+                let mut phase_hooks = vec![];
+                while let Some(hook) = hooks.next_if(|hook| hook.phase == phase) {
+                    phase_hooks.push(hook);
+                }
+                let phase_start = Instant::now();
+                let phase_outcomes = join_all(phase_hooks.into_iter().map(run_hook)).await;
+                info!(
+                    "Shutdown phase: [{}] finished in {:?}",
+                    phase,
+                    phase_start.elapsed()
+                );
+                outcomes.lock().extend(phase_outcomes);
+            }
+        })
+        .await;
+
+        if executed.is_err() {
+            error!(
+                "Shutdown exceeded the deadline: {:?}, the remaining hooks are abandoned",
+                deadline
+            );
+            return Err(anyhow!(
+                "The shutdown exceeded the deadline: {:?}",
+                deadline
+            ));
+        }
+        info!("Shutdown finished in {:?}", start.elapsed());
+        Ok(outcomes.into_inner())
+    }
+
+    /// Run the shutdown in the given runtime and block until finished. The process will be
+    /// forcibly exited if the hooks get stuck beyond the deadline, even blocking the runtime.
+    pub fn shutdown_blocking(&'static self, runtime: &RuntimeRef) {
+        let (tx, rx) = std::sync::mpsc::channel();
+        runtime.spawn_guarded("shutdown", async move {
+            let _ = tx.send(self.shutdown().await);
+        });
+        // a bit more time for the runtime to report the exceeded deadline
+        match rx.recv_timeout(self.deadline() + Duration::from_secs(1)) {
+            Ok(Ok(_)) => {}
+            Ok(Err(e)) => {
+                error!("Errors on shutdown: {}. Forcibly exiting", e);
+                std::process::exit(1);
+            }
+            Err(_) => {
+                error!("Shutdown is stuck. Forcibly exiting");
+                std::process::exit(1);
+            }
+        }
+    }
+}
+
+async fn run_hook(hook: ShutdownHook) -> (String, HookOutcome) {
+    let start = Instant::now();
+    let outcome = match tokio::time::timeout(hook.timeout, (hook.hook)()).await {
+        Ok(Ok(_)) => HookOutcome::Succeeded,
+        Ok(Err(e)) => HookOutcome::Failed(e.to_string()),
+        Err(_) => HookOutcome::TimedOut,
+    };
+    match &outcome {
+        HookOutcome::Succeeded => info!(
+            "Shutdown hook: [{}] of phase: [{}] succeeded in {:?}",
+            &hook.name,
+            hook.phase,
+            start.elapsed()
+        ),
+        _ => warn!(
+            "Shutdown hook: [{}] of phase: [{}] finished in {:?}. outcome: {:?}",
+            &hook.name,
+            hook.phase,
+            start.elapsed(),
+            &outcome
+        ),
+    }
+    (hook.name, outcome)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::Config;
+    use crate::shutdown::{HookOutcome, ShutdownCoordinator, PHASE_DRAIN, PHASE_STOP_SERVING};
+    use anyhow::anyhow;
+    use parking_lot::Mutex;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+
+    #[tokio::test]
+    async fn test_execution_order() -> anyhow::Result<()> {
+        let coordinator = ShutdownCoordinator::new(Duration::from_secs(10));
+        let executed = Arc::new(Mutex::new(vec![]));
+
+        // registered in the reversed order, the drain depends on the stopped serving
+        let recorder = executed.clone();
+        coordinator.register(
+            "drain",
+            PHASE_DRAIN,
+            Duration::from_secs(1),
+            move || async move {
+                recorder.lock().push("drain");
+                Ok(())
+            },
+        );
+        let recorder = executed.clone();
+        coordinator.register(
+            "stop_grpc",
+            PHASE_STOP_SERVING,
+            Duration::from_secs(1),
+            move || async move {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                recorder.lock().push("stop_grpc");
+                Ok(())
+            },
+        );
+        let recorder = executed.clone();
+        coordinator.register(
+            "stop_urpc",
+            PHASE_STOP_SERVING,
+            Duration::from_secs(1),
+            move || async move {
+                recorder.lock().push("stop_urpc");
+                Err(anyhow!("mock failure"))
+            },
+        );
+
+        let outcomes = coordinator.shutdown().await?;
+        // the same phase hooks are concurrent, and the failure won't stop the next phases
+        assert_eq!(vec!["stop_urpc", "stop_grpc", "drain"], *executed.lock());
+        assert_eq!(
+            vec![
+                ("stop_grpc".to_string(), HookOutcome::Succeeded),
+                (
+                    "stop_urpc".to_string(),
+                    HookOutcome::Failed("mock failure".to_string())
+                ),
+                ("drain".to_string(), HookOutcome::Succeeded),
+            ],
+            outcomes
+        );
+
+        // only triggered once
+        assert!(coordinator.shutdown().await.is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_hook_timeout() -> anyhow::Result<()> {
+        let coordinator = ShutdownCoordinator::new(Duration::from_secs(10));
+        coordinator.register(
+            "stuck",
+            PHASE_STOP_SERVING,
+            Duration::from_millis(100),
+            || futures::future::pending(),
+        );
+        coordinator.register("next", PHASE_DRAIN, Duration::from_secs(1), || async {
+            Ok(())
+        });
+
+        let start = Instant::now();
+        let outcomes = coordinator.shutdown().await?;
+        assert!(start.elapsed() < Duration::from_secs(1));
+        assert_eq!(
+            vec![
+                ("stuck".to_string(), HookOutcome::TimedOut),
+                ("next".to_string(), HookOutcome::Succeeded),
+            ],
+            outcomes
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_deadline_from_config() -> anyhow::Result<()> {
+        let mut config = Config::create_simple_config();
+        config.shutdown_timeout = Some("300ms".to_string());
+        let coordinator = ShutdownCoordinator::new(Duration::from_secs(60));
+        coordinator.set_deadline(config.shutdown_timeout()?);
+
+        let stopped = Arc::new(AtomicBool::new(false));
+        let flag = stopped.clone();
+        coordinator.register(
+            "stop_serving",
+            PHASE_STOP_SERVING,
+            Duration::from_secs(1),
+            move || async move {
+                flag.store(true, Ordering::SeqCst);
+                Ok(())
+            },
+        );
+        // the slow drain is bounded by the global deadline rather than its own timeout
+        coordinator.register(
+            "slow_drain",
+            PHASE_DRAIN,
+            Duration::from_secs(60),
+            || async {
+                tokio::time::sleep(Duration::from_secs(10)).await;
+                Ok(())
+            },
+        );
+
+        let start = Instant::now();
+        assert!(coordinator.shutdown().await.is_err());
+        assert!(start.elapsed() >= Duration::from_millis(300));
+        assert!(start.elapsed() < Duration::from_secs(2));
+        assert!(stopped.load(Ordering::SeqCst));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_global_deadline() {
+        let coordinator = ShutdownCoordinator::new(Duration::from_millis(200));
+        coordinator.register(
+            "hanging",
+            PHASE_STOP_SERVING,
+            Duration::from_secs(60),
+            || futures::future::pending(),
+        );
+
+        let start = Instant::now();
+        assert!(coordinator.shutdown().await.is_err());
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+}
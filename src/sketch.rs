@@ -0,0 +1,243 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use parking_lot::Mutex;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HeavyHitter<K> {
+    pub key: K,
+    // the overestimated count, the true one is in [count - error, count]
+    pub count: u64,
+    pub error: u64,
+}
+
+/// The space-saving sketch of the top-k keys by the weighted count. Any key whose true
+/// count is more than `total / capacity` is guaranteed to be kept.
+pub struct SpaceSaving<K> {
+    capacity: usize,
+    counters: HashMap<K, (u64, u64)>,
+    total: u64,
+}
+
+impl<K: Hash + Eq + Clone> SpaceSaving<K> {
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            capacity,
+            counters: HashMap::with_capacity(capacity),
+            total: 0,
+        }
+    }
+
+    pub fn add(&mut self, key: K, weight: u64) {
+        self.total += weight;
+        if let Some((count, _)) = self.counters.get_mut(&key) {
+            *count += weight;
+            return;
+        }
+        if self.counters.len() < self.capacity {
+            self.counters.insert(key, (weight, 0));
+            return;
+        }
+        // replace the minimum one, which inherits its count as the error
+        let (min_key, min_count) = self
+            .counters
+            .iter()
+            .min_by_key(|(_, (count, _))| *count)
+            .map(|(key, (count, _))| (key.clone(), *count))
+            .unwrap();
+        self.counters.remove(&min_key);
+        self.counters.insert(key, (min_count + weight, min_count));
+    }
+
+    pub fn top(&self, n: usize) -> Vec<HeavyHitter<K>> {
+        let mut hitters: Vec<_> = self
+            .counters
+            .iter()
+            .map(|(key, (count, error))| HeavyHitter {
+                key: key.clone(),
+                count: *count,
+                error: *error,
+            })
+            .collect();
+        hitters.sort_by(|a, b| b.count.cmp(&a.count));
+        hitters.truncate(n);
+        hitters
+    }
+
+    /// Drop the keys, the weighted total is decreased by the estimated counts.
+    pub fn retain<F: Fn(&K) -> bool>(&mut self, f: F) {
+        let mut removed = 0;
+        self.counters.retain(|key, (count, _)| {
+            let kept = f(key);
+            if !kept {
+                removed += *count;
+            }
+            kept
+        });
+        self.total = self.total.saturating_sub(removed);
+    }
+
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    pub fn len(&self) -> usize {
+        self.counters.len()
+    }
+}
+
+/// The space-saving sketches sharded by the key, so that adding the different keys
+/// concurrently rarely contends. The capacity is per shard rather than divided across
+/// them, so at most `capacity * shards` keys are tracked in total. It keeps the accuracy
+/// of every shard as the single sketch with the same capacity.
+pub struct ShardedSpaceSaving<K> {
+    shards: Vec<Mutex<SpaceSaving<K>>>,
+}
+
+impl<K: Hash + Eq + Clone> ShardedSpaceSaving<K> {
+    pub fn new(capacity: usize, shards: usize) -> Self {
+        Self {
+            shards: (0..shards.max(1))
+                .map(|_| Mutex::new(SpaceSaving::new(capacity)))
+                .collect(),
+        }
+    }
+
+    fn shard(&self, key: &K) -> &Mutex<SpaceSaving<K>> {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        &self.shards[hasher.finish() as usize % self.shards.len()]
+    }
+
+    pub fn add(&self, key: K, weight: u64) {
+        self.shard(&key).lock().add(key, weight);
+    }
+
+    /// The keys are disjoint among the shards, so the top n is among the top n of every shard.
+    pub fn top(&self, n: usize) -> Vec<HeavyHitter<K>> {
+        let mut hitters: Vec<_> = self
+            .shards
+            .iter()
+            .flat_map(|shard| shard.lock().top(n))
+            .collect();
+        hitters.sort_by(|a, b| b.count.cmp(&a.count));
+        hitters.truncate(n);
+        hitters
+    }
+
+    pub fn retain<F: Fn(&K) -> bool>(&self, f: F) {
+        for shard in &self.shards {
+            shard.lock().retain(&f);
+        }
+    }
+
+    pub fn total(&self) -> u64 {
+        self.shards.iter().map(|shard| shard.lock().total()).sum()
+    }
+
+    pub fn len(&self) -> usize {
+        self.shards.iter().map(|shard| shard.lock().len()).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sketch::{ShardedSpaceSaving, SpaceSaving};
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    // the partition ranked i is written with the probability proportional to 1 / i^s
+    fn zipfian_stream(partitions: usize, s: f64, len: usize, seed: u64) -> Vec<usize> {
+        let weights: Vec<f64> = (1..=partitions)
+            .map(|rank| 1.0 / (rank as f64).powf(s))
+            .collect();
+        let sum: f64 = weights.iter().sum();
+        let mut rng = StdRng::seed_from_u64(seed);
+        (0..len)
+            .map(|_| {
+                let mut point = rng.gen::<f64>() * sum;
+                for (idx, weight) in weights.iter().enumerate() {
+                    if point < *weight {
+                        return idx;
+                    }
+                    point -= weight;
+                }
+                partitions - 1
+            })
+            .collect()
+    }
+
+    #[test]
+    fn zipfian_top_k_test() {
+        let mut sketch = SpaceSaving::new(32);
+        let mut exact = vec![0u64; 1000];
+        for partition in zipfian_stream(1000, 1.2, 100000, 7) {
+            sketch.add(partition, 10);
+            exact[partition] += 10;
+        }
+        assert_eq!(32, sketch.len());
+        assert_eq!(exact.iter().sum::<u64>(), sketch.total());
+
+        let mut ranked: Vec<usize> = (0..exact.len()).collect();
+        ranked.sort_by(|a, b| exact[*b].cmp(&exact[*a]));
+        let reported: Vec<usize> = sketch.top(8).iter().map(|x| x.key).collect();
+        assert_eq!(ranked[0], reported[0]);
+        for partition in &ranked[..5] {
+            assert!(reported.contains(partition));
+        }
+
+        for hitter in sketch.top(32) {
+            let real = exact[hitter.key];
+            assert!(hitter.count >= real);
+            assert!(hitter.count - hitter.error <= real);
+        }
+
+        let second = sketch.top(2)[1].key;
+        sketch.retain(|x| *x != ranked[0]);
+        assert_eq!(31, sketch.len());
+        assert_eq!(second, sketch.top(1)[0].key);
+    }
+
+    #[test]
+    fn sharded_top_k_test() {
+        let sketch = ShardedSpaceSaving::new(8, 4);
+        let mut exact = vec![0u64; 1000];
+        for partition in zipfian_stream(1000, 1.2, 100000, 7) {
+            sketch.add(partition, 10);
+            exact[partition] += 10;
+        }
+        assert_eq!(32, sketch.len());
+        assert_eq!(exact.iter().sum::<u64>(), sketch.total());
+
+        let mut ranked: Vec<usize> = (0..exact.len()).collect();
+        ranked.sort_by(|a, b| exact[*b].cmp(&exact[*a]));
+        let reported = sketch.top(8);
+        assert_eq!(8, reported.len());
+        assert_eq!(ranked[0], reported[0].key);
+        for hitter in &reported {
+            assert!(hitter.count >= exact[hitter.key]);
+        }
+
+        sketch.retain(|x| *x != ranked[0]);
+        assert_eq!(31, sketch.len());
+        assert_ne!(ranked[0], sketch.top(1)[0].key);
+    }
+}
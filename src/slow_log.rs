@@ -0,0 +1,327 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::metric::TOTAL_SLOW_REQUEST;
+use crate::util;
+use log::warn;
+use parking_lot::Mutex;
+use std::fmt::{Display, Formatter};
+use std::future::Future;
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
+
+const DEFAULT_SLOW_REQUEST_THRESHOLD: Duration = Duration::from_secs(2);
+
+static SLOW_REQUEST_THRESHOLD: OnceLock<Duration> = OnceLock::new();
+
+tokio::task_local! {
+    static REQUEST_TIMING_CONTEXT: Arc<RequestTimingContext>;
+}
+
+pub fn init_slow_request_threshold(threshold: Duration) {
+    SLOW_REQUEST_THRESHOLD.get_or_init(|| threshold);
+}
+
+fn slow_request_threshold() -> Duration {
+    *SLOW_REQUEST_THRESHOLD.get_or_init(|| DEFAULT_SLOW_REQUEST_THRESHOLD)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Phase {
+    StoreLookup,
+    DiskRead,
+    NetworkWrite,
+}
+
+impl Phase {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Phase::StoreLookup => "store_lookup",
+            Phase::DiskRead => "disk_read",
+            Phase::NetworkWrite => "network_write",
+        }
+    }
+}
+
+/// The timing context of one rpc request, which is bound to the handling future
+/// by the task local and then the phases could be recorded in the store calls.
+pub struct RequestTimingContext {
+    method: &'static str,
+    app_id: String,
+    shuffle_id: i32,
+    partition_id: i32,
+    queue_wait: Duration,
+    start: Instant,
+    payload_size: Mutex<i64>,
+    phases: Mutex<Vec<(Phase, Duration)>>,
+}
+
+impl RequestTimingContext {
+    /// The client_timestamp is the millis when the client sent the request,
+    /// it will be used to calculate the queue waiting time.
+    pub fn new(
+        method: &'static str,
+        app_id: &str,
+        shuffle_id: i32,
+        partition_id: i32,
+        client_timestamp: i64,
+    ) -> Self {
+        let queue_wait = if client_timestamp > 0 {
+            let now = util::now_timestamp_as_millis() as i64;
+            Duration::from_millis((now - client_timestamp).max(0) as u64)
+        } else {
+            Duration::ZERO
+        };
+        Self {
+            method,
+            app_id: app_id.to_string(),
+            shuffle_id,
+            partition_id,
+            queue_wait,
+            start: Instant::now(),
+            payload_size: Mutex::new(0),
+            phases: Mutex::new(vec![]),
+        }
+    }
+
+    fn add_phase(&self, phase: Phase, duration: Duration) {
+        self.phases.lock().push((phase, duration));
+    }
+
+    fn finish(&self, threshold: Duration) -> Option<SlowRequestRecord> {
+        let elapsed = self.start.elapsed();
+        if elapsed <= threshold {
+            return None;
+        }
+        Some(SlowRequestRecord {
+            method: self.method,
+            app_id: self.app_id.clone(),
+            shuffle_id: self.shuffle_id,
+            partition_id: self.partition_id,
+            payload_size: *self.payload_size.lock(),
+            queue_wait: self.queue_wait,
+            elapsed,
+            phases: self.phases.lock().clone(),
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct SlowRequestRecord {
+    pub method: &'static str,
+    pub app_id: String,
+    pub shuffle_id: i32,
+    pub partition_id: i32,
+    pub payload_size: i64,
+    pub queue_wait: Duration,
+    pub elapsed: Duration,
+    pub phases: Vec<(Phase, Duration)>,
+}
+
+impl Display for SlowRequestRecord {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "method={} app_id={} shuffle_id={} partition_id={} payload_size={} queue_wait_ms={} elapsed_ms={}",
+            self.method,
+            &self.app_id,
+            self.shuffle_id,
+            self.partition_id,
+            self.payload_size,
+            self.queue_wait.as_millis(),
+            self.elapsed.as_millis()
+        )?;
+        for (phase, duration) in &self.phases {
+            write!(f, " {}_ms={}", phase.as_str(), duration.as_millis())?;
+        }
+        Ok(())
+    }
+}
+
+/// Run the request handling future with the timing context. Once it exceeds the
+/// slow request threshold, the warn log with the phase breakdown will be emitted.
+pub async fn observe<F: Future>(ctx: RequestTimingContext, fut: F) -> F::Output {
+    observe_with_threshold(ctx, slow_request_threshold(), fut)
+        .await
+        .0
+}
+
+async fn observe_with_threshold<F: Future>(
+    ctx: RequestTimingContext,
+    threshold: Duration,
+    fut: F,
+) -> (F::Output, Option<SlowRequestRecord>) {
+    let ctx = Arc::new(ctx);
+    let output = REQUEST_TIMING_CONTEXT.scope(ctx.clone(), fut).await;
+    (output, report(&ctx, threshold))
+}
+
+fn report(ctx: &RequestTimingContext, threshold: Duration) -> Option<SlowRequestRecord> {
+    let record = ctx.finish(threshold);
+    if let Some(record) = &record {
+        TOTAL_SLOW_REQUEST.with_label_values(&[record.method]).inc();
+        warn!("[slow request] {}", record);
+    }
+    record
+}
+
+/// Run the grpc request handling future with the timing context, whose response is
+/// written by the transport after the handling. The returned pending log is attached
+/// into the response extensions to be reported with the network write phase.
+pub async fn observe_pending<F: Future>(
+    ctx: RequestTimingContext,
+    fut: F,
+) -> (F::Output, PendingSlowLog) {
+    observe_pending_with_threshold(ctx, slow_request_threshold(), fut).await
+}
+
+async fn observe_pending_with_threshold<F: Future>(
+    ctx: RequestTimingContext,
+    threshold: Duration,
+    fut: F,
+) -> (F::Output, PendingSlowLog) {
+    let ctx = Arc::new(ctx);
+    let output = REQUEST_TIMING_CONTEXT.scope(ctx.clone(), fut).await;
+    let pending = PendingSlowLog {
+        ctx,
+        threshold,
+        reported: false,
+    };
+    (output, pending)
+}
+
+/// The timing context waiting for the response to be sent. It's reported once the
+/// response body is written, or without the network write phase when it's dropped.
+pub struct PendingSlowLog {
+    ctx: Arc<RequestTimingContext>,
+    threshold: Duration,
+    reported: bool,
+}
+
+impl PendingSlowLog {
+    pub fn network_written(mut self, duration: Duration) -> Option<SlowRequestRecord> {
+        self.ctx.add_phase(Phase::NetworkWrite, duration);
+        self.report()
+    }
+
+    fn report(&mut self) -> Option<SlowRequestRecord> {
+        if self.reported {
+            return None;
+        }
+        self.reported = true;
+        report(&self.ctx, self.threshold)
+    }
+}
+
+impl Drop for PendingSlowLog {
+    fn drop(&mut self) {
+        self.report();
+    }
+}
+
+/// Record the phase duration into the current request timing context.
+/// It's no-op when not being in the scope of the request.
+pub async fn record_phase<F: Future>(phase: Phase, fut: F) -> F::Output {
+    let start = Instant::now();
+    let output = fut.await;
+    let elapsed = start.elapsed();
+    let _ = REQUEST_TIMING_CONTEXT.try_with(|ctx| ctx.add_phase(phase, elapsed));
+    output
+}
+
+pub fn record_payload_size(size: i64) {
+    let _ = REQUEST_TIMING_CONTEXT.try_with(|ctx| *ctx.payload_size.lock() += size);
+}
+
+#[cfg(test)]
+mod test {
+    use crate::slow_log::{
+        observe_pending_with_threshold, observe_with_threshold, record_payload_size, record_phase,
+        Phase, RequestTimingContext,
+    };
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_slow_request_record() {
+        // mock the slow store call
+        let slow_store_call = async {
+            record_payload_size(1024);
+            record_phase(Phase::StoreLookup, async {
+                record_phase(
+                    Phase::DiskRead,
+                    tokio::time::sleep(Duration::from_millis(50)),
+                )
+                .await
+            })
+            .await;
+            1
+        };
+
+        let ctx = RequestTimingContext::new("get_local_shuffle_data", "app_1", 1, 2, 0);
+        let (output, record) =
+            observe_with_threshold(ctx, Duration::from_millis(10), slow_store_call).await;
+        assert_eq!(1, output);
+
+        let record = record.unwrap();
+        assert_eq!(1024, record.payload_size);
+        assert_eq!(2, record.phases.len());
+        assert_eq!(Phase::DiskRead, record.phases[0].0);
+        assert!(record.phases[0].1 >= Duration::from_millis(50));
+        assert_eq!(Phase::StoreLookup, record.phases[1].0);
+
+        let log = record.to_string();
+        assert!(log.contains("method=get_local_shuffle_data"));
+        assert!(log.contains("app_id=app_1"));
+        assert!(log.contains("payload_size=1024"));
+        assert!(log.contains("disk_read_ms="));
+        assert!(log.contains("store_lookup_ms="));
+
+        // fast request will not be recorded
+        let ctx = RequestTimingContext::new("get_local_shuffle_data", "app_1", 1, 2, 0);
+        let (_, record) = observe_with_threshold(ctx, Duration::from_secs(10), async {}).await;
+        assert!(record.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_slow_request_with_network_write() {
+        let ctx = RequestTimingContext::new("get_memory_shuffle_data", "app_1", 1, 2, 0);
+        let (output, pending) = observe_pending_with_threshold(
+            ctx,
+            Duration::from_millis(10),
+            record_phase(Phase::StoreLookup, async { 1 }),
+        )
+        .await;
+        assert_eq!(1, output);
+
+        // the response body is being sent
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        let record = pending.network_written(Duration::from_millis(20)).unwrap();
+        assert_eq!(2, record.phases.len());
+        assert_eq!(Phase::StoreLookup, record.phases[0].0);
+        assert_eq!(
+            (Phase::NetworkWrite, Duration::from_millis(20)),
+            record.phases[1]
+        );
+        assert!(record.to_string().contains("network_write_ms=20"));
+    }
+
+    #[tokio::test]
+    async fn test_record_phase_without_context() {
+        let output = record_phase(Phase::DiskRead, async { 1 }).await;
+        assert_eq!(1, output);
+    }
+}
@@ -0,0 +1,213 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! The shared semantics of the [`Store`] implementations, the scenarios are
+//! run against every store and branched by its capabilities only.
+
+use crate::app::{
+    PartitionedUId, PurgeDataContext, ReadingIndexViewContext, ReadingOptions, ReadingViewContext,
+    RegisterAppContext, ReleaseTicketContext, RequireBufferContext, WritingViewContext,
+};
+use crate::config::{Config, LocalfileStoreConfig, MemoryStoreConfig};
+use crate::error::WorkerError;
+use crate::runtime::manager::RuntimeManager;
+use crate::store::hybrid::HybridStore;
+use crate::store::localfile::LocalFileStore;
+use crate::store::memory::MemoryStore;
+use crate::store::{Block, ResponseData, ResponseDataIndex, Store, StoreTier};
+use bytes::{Bytes, BytesMut};
+
+const APP_ID: &str = "conformance_app";
+
+fn uid(shuffle_id: i32) -> PartitionedUId {
+    PartitionedUId::from(APP_ID.to_string(), shuffle_id, 0)
+}
+
+fn blocks(shuffle_id: i32) -> Vec<Block> {
+    (0..2)
+        .map(|idx| {
+            let data = Bytes::from(format!("shuffle-{}-block-{}", shuffle_id, idx));
+            Block {
+                block_id: (shuffle_id * 10 + idx) as i64,
+                length: data.len() as i32,
+                uncompress_length: data.len() as i32,
+                crc: 0,
+                data,
+                task_attempt_id: idx as i64,
+            }
+        })
+        .collect()
+}
+
+fn concat(blocks: &[Block]) -> Bytes {
+    let mut bytes = BytesMut::new();
+    for block in blocks {
+        bytes.extend_from_slice(&block.data);
+    }
+    bytes.freeze()
+}
+
+fn is_unsupported<T>(result: Result<T, WorkerError>) -> bool {
+    matches!(
+        result,
+        Err(WorkerError::UNSUPPORTED_STORE_OPERATION(_, _))
+            | Err(WorkerError::NOT_READ_HDFS_DATA_FROM_SERVER)
+    )
+}
+
+// read the partition data by the reading way the store supports
+async fn read<S: Store>(store: &S, uid: &PartitionedUId) -> Result<Bytes, WorkerError> {
+    let capabilities = store.capabilities();
+    if capabilities.supports_paging {
+        let ctx = ReadingViewContext {
+            uid: uid.clone(),
+            reading_options: ReadingOptions::MEMORY_LAST_BLOCK_ID_AND_MAX_SIZE(-1, 1024 * 1024),
+            serialized_expected_task_ids_bitmap: Default::default(),
+        };
+        return match store.get(ctx).await? {
+            ResponseData::Mem(data) => Ok(data.data.freeze()),
+            ResponseData::Local(_) => panic!("memory-style reading returns the local data"),
+        };
+    }
+
+    let ResponseDataIndex::Local(index) = store
+        .get_index(ReadingIndexViewContext {
+            partition_id: uid.clone(),
+        })
+        .await?;
+    let ctx = ReadingViewContext {
+        uid: uid.clone(),
+        reading_options: ReadingOptions::FILE_OFFSET_AND_LEN(0, index.data_file_len),
+        serialized_expected_task_ids_bitmap: Default::default(),
+    };
+    match store.get(ctx).await? {
+        ResponseData::Local(data) => Ok(data.data),
+        ResponseData::Mem(_) => panic!("file-style reading returns the memory data"),
+    }
+}
+
+async fn check_conformance<S: Store>(store: &S) -> anyhow::Result<()> {
+    let capabilities = store.capabilities();
+    if capabilities.supports_paging {
+        assert_eq!(StoreTier::HOT, capabilities.tier);
+    }
+
+    store
+        .register_app(RegisterAppContext {
+            app_id: APP_ID.to_string(),
+            app_config_options: Default::default(),
+        })
+        .await?;
+
+    // case1: the written data is read back
+    for shuffle_id in [1, 2] {
+        let blocks = blocks(shuffle_id);
+        let size = concat(&blocks).len() as u64;
+        store
+            .insert(WritingViewContext::new(
+                uid(shuffle_id),
+                blocks,
+                false,
+                size,
+            ))
+            .await?;
+    }
+    assert_eq!(concat(&blocks(1)), read(store, &uid(1)).await?);
+    if let Some(stat) = store.partition_stat(&uid(1)) {
+        assert_eq!(concat(&blocks(1)).len() as u64, stat.size);
+    }
+
+    // case2: the unsupported operations are rejected rather than panicking
+    if !capabilities.supports_paging {
+        let ctx = ReadingViewContext {
+            uid: uid(1),
+            reading_options: ReadingOptions::MEMORY_LAST_BLOCK_ID_AND_MAX_SIZE(-1, 1024),
+            serialized_expected_task_ids_bitmap: Default::default(),
+        };
+        assert!(is_unsupported(store.get(ctx).await));
+    }
+    if !capabilities.supports_index {
+        let ctx = ReadingIndexViewContext {
+            partition_id: uid(1),
+        };
+        assert!(is_unsupported(store.get_index(ctx).await));
+    }
+    let required = store
+        .require_buffer(RequireBufferContext::new(uid(1), 1024))
+        .await;
+    if capabilities.supports_buffer {
+        let ticket_id = required?.ticket_id;
+        assert_eq!(
+            1024,
+            store
+                .release_ticket(ReleaseTicketContext::from(ticket_id))
+                .await?
+        );
+    } else {
+        assert!(is_unsupported(required));
+    }
+
+    // case3: the purged shuffle is invisible and the others are kept
+    let removed = store
+        .purge(PurgeDataContext::new(APP_ID.to_string(), Some(1)))
+        .await?;
+    assert_eq!(concat(&blocks(1)).len() as i64, removed);
+    assert!(read(store, &uid(1)).await?.is_empty());
+    assert_eq!(concat(&blocks(2)), read(store, &uid(2)).await?);
+
+    let removed = store.purge(APP_ID.into()).await?;
+    assert_eq!(concat(&blocks(2)).len() as i64, removed);
+    assert!(read(store, &uid(2)).await?.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn memory_store_conformance_test() -> anyhow::Result<()> {
+    let runtime_manager: RuntimeManager = Default::default();
+    let store = MemoryStore::from(
+        MemoryStoreConfig::new("1M".to_string()),
+        runtime_manager.clone(),
+    );
+    runtime_manager.wait(check_conformance(&store))
+}
+
+#[test]
+fn localfile_store_conformance_test() -> anyhow::Result<()> {
+    let temp_dir = tempdir::TempDir::new("localfile_store_conformance_test")?;
+    let temp_path = temp_dir.path().to_str().unwrap().to_string();
+    let runtime_manager: RuntimeManager = Default::default();
+    let store = LocalFileStore::from(
+        LocalfileStoreConfig::new(vec![temp_path]),
+        runtime_manager.clone(),
+    );
+    runtime_manager.wait(check_conformance(&store))
+}
+
+#[test]
+fn hybrid_store_conformance_test() -> anyhow::Result<()> {
+    let runtime_manager: RuntimeManager = Default::default();
+    let store = HybridStore::from(Config::create_simple_config(), runtime_manager.clone());
+    runtime_manager.wait(check_conformance(&store))?;
+
+    let temp_dir = tempdir::TempDir::new("hybrid_store_conformance_test")?;
+    let temp_path = temp_dir.path().to_str().unwrap().to_string();
+    let config = Config::create_mem_localfile_config(0, "1M".to_string(), temp_path);
+    let store = HybridStore::from(config, runtime_manager.clone());
+    assert!(store.capabilities().durable);
+    runtime_manager.wait(check_conformance(&store))
+}
@@ -0,0 +1,416 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::app::{
+    PartitionedUId, PurgeDataContext, ReadingIndexViewContext, ReadingViewContext,
+    RegisterAppContext, ReleaseTicketContext, RequireBufferContext, WritingViewContext,
+};
+use crate::config::StorageType;
+use crate::error::WorkerError;
+use crate::health::{ComponentHealth, HealthProvider};
+use crate::metric::TOTAL_INJECTED_FAULTS;
+use crate::store::hybrid::PersistentStore;
+use crate::store::spill::SpillWritingViewContext;
+use crate::store::{
+    PartitionStat, PartitionedLocalData, Persistent, RequireBufferResponse, ResponseData,
+    ResponseDataIndex, SpillConcurrency, Store, StoreCapabilities,
+};
+use anyhow::Result;
+use async_trait::async_trait;
+use bytes::Bytes;
+use log::warn;
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// The injector shared by all the persistent stores, which could be updated
+/// by the `/debug/faults` http endpoint or programmatically in tests.
+pub static FAULT_INJECTOR: Lazy<FaultInjector> = Lazy::new(Default::default);
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum FaultOperation {
+    Insert,
+    SpillInsert,
+    Get,
+    GetIndex,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type")]
+pub enum FaultKind {
+    Error,
+    Latency { millis: u64 },
+    // only the read data is corrupted
+    Corrupt,
+}
+
+impl FaultKind {
+    fn name(&self) -> &'static str {
+        match self {
+            FaultKind::Error => "error",
+            FaultKind::Latency { .. } => "latency",
+            FaultKind::Corrupt => "corrupt",
+        }
+    }
+}
+
+/// The fault is injected into the matched operations, the unset field matches all.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FaultRule {
+    pub operation: FaultOperation,
+    pub kind: FaultKind,
+    #[serde(default)]
+    pub storage: Option<StorageType>,
+    #[serde(default)]
+    pub app_id: Option<String>,
+    #[serde(default)]
+    pub shuffle_id: Option<i32>,
+    #[serde(default)]
+    pub partition_id: Option<i32>,
+    // inject the first `times` ones of every `every` matched operations, all by default
+    #[serde(default)]
+    pub times: Option<u64>,
+    #[serde(default)]
+    pub every: Option<u64>,
+}
+
+impl FaultRule {
+    pub fn new(operation: FaultOperation, kind: FaultKind) -> Self {
+        Self {
+            operation,
+            kind,
+            storage: None,
+            app_id: None,
+            shuffle_id: None,
+            partition_id: None,
+            times: None,
+            every: None,
+        }
+    }
+
+    fn is_matched(
+        &self,
+        operation: FaultOperation,
+        storage: StorageType,
+        uid: &PartitionedUId,
+    ) -> bool {
+        self.operation == operation
+            && self.storage.map_or(true, |x| x == storage)
+            && self.app_id.as_ref().map_or(true, |x| x == &uid.app_id)
+            && self.shuffle_id.map_or(true, |x| x == uid.shuffle_id)
+            && self.partition_id.map_or(true, |x| x == uid.partition_id)
+    }
+}
+
+struct ActiveRule {
+    rule: FaultRule,
+    matched: AtomicU64,
+}
+
+#[derive(Default)]
+pub struct FaultInjector {
+    rules: RwLock<Vec<Arc<ActiveRule>>>,
+    // the fast path to keep it inert when no rule is set
+    enabled: AtomicBool,
+}
+
+impl FaultInjector {
+    pub fn add_rule(&self, rule: FaultRule) {
+        let mut rules = self.rules.write();
+        rules.push(Arc::new(ActiveRule {
+            rule,
+            matched: Default::default(),
+        }));
+        self.enabled.store(true, Ordering::SeqCst);
+    }
+
+    /// Replace all the rules, the empty rules disable the injection.
+    pub fn set_rules(&self, new_rules: Vec<FaultRule>) {
+        let mut rules = self.rules.write();
+        self.enabled.store(!new_rules.is_empty(), Ordering::SeqCst);
+        *rules = new_rules
+            .into_iter()
+            .map(|rule| {
+                Arc::new(ActiveRule {
+                    rule,
+                    matched: Default::default(),
+                })
+            })
+            .collect();
+    }
+
+    pub fn clear(&self) {
+        self.set_rules(vec![]);
+    }
+
+    pub fn rules(&self) -> Vec<FaultRule> {
+        self.rules.read().iter().map(|x| x.rule.clone()).collect()
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::SeqCst)
+    }
+
+    /// Return the fault of the first matched rule whose turn is to inject.
+    fn pick(
+        &self,
+        operation: FaultOperation,
+        storage: StorageType,
+        uid: &PartitionedUId,
+    ) -> Option<FaultKind> {
+        let rules = self.rules.read();
+        for active in rules.iter() {
+            let rule = &active.rule;
+            if !rule.is_matched(operation, storage, uid) {
+                continue;
+            }
+            let every = rule.every.unwrap_or(1).max(1);
+            let times = rule.times.unwrap_or(every);
+            let sequence = active.matched.fetch_add(1, Ordering::SeqCst);
+            if sequence % every < times {
+                return Some(rule.kind.clone());
+            }
+        }
+        None
+    }
+}
+
+/// Wrapping the persistent store to inject the faults configured by the [`FAULT_INJECTOR`],
+/// which is inert unless any rule is set.
+pub struct FaultInjectedStore<S> {
+    inner: Arc<S>,
+    injector: &'static FaultInjector,
+}
+
+impl<S: PersistentStore> FaultInjectedStore<S> {
+    pub fn new(store: S) -> Self {
+        Self {
+            inner: Arc::new(store),
+            injector: &FAULT_INJECTOR,
+        }
+    }
+
+    /// Fail or delay the operation, and return true if the read data should be corrupted.
+    async fn inject(
+        &self,
+        operation: FaultOperation,
+        uid: &PartitionedUId,
+    ) -> Result<bool, WorkerError> {
+        if !self.injector.is_enabled() {
+            return Ok(false);
+        }
+        let storage = self.inner.name().await;
+        let fault = match self.injector.pick(operation, storage, uid) {
+            Some(fault) => fault,
+            _ => return Ok(false),
+        };
+        warn!(
+            "Injected the fault: {:?} into the {:?} of {:?} for {:?}",
+            &fault, operation, storage, uid
+        );
+        TOTAL_INJECTED_FAULTS
+            .with_label_values(&[fault.name()])
+            .inc();
+        match fault {
+            FaultKind::Error => Err(WorkerError::INJECTED_FAULT(format!(
+                "{:?} of {:?} for {:?}",
+                operation, storage, uid
+            ))),
+            FaultKind::Latency { millis } => {
+                tokio::time::sleep(Duration::from_millis(millis)).await;
+                Ok(false)
+            }
+            FaultKind::Corrupt => Ok(true),
+        }
+    }
+}
+
+fn corrupt(data: ResponseData) -> ResponseData {
+    match data {
+        ResponseData::Local(local) => {
+            let corrupted: Vec<u8> = local.data.iter().map(|x| !x).collect();
+            ResponseData::Local(PartitionedLocalData {
+                data: Bytes::from(corrupted),
+            })
+        }
+        data => data,
+    }
+}
+
+#[async_trait]
+impl<S: PersistentStore + 'static> Store for FaultInjectedStore<S> {
+    fn start(self: Arc<Self>) {
+        self.inner.clone().start()
+    }
+
+    async fn insert(&self, ctx: WritingViewContext) -> Result<(), WorkerError> {
+        self.inject(FaultOperation::Insert, &ctx.uid).await?;
+        self.inner.insert(ctx).await
+    }
+
+    async fn get(&self, ctx: ReadingViewContext) -> Result<ResponseData, WorkerError> {
+        let corrupted = self.inject(FaultOperation::Get, &ctx.uid).await?;
+        let data = self.inner.get(ctx).await?;
+        match corrupted {
+            true => Ok(corrupt(data)),
+            _ => Ok(data),
+        }
+    }
+
+    async fn get_index(
+        &self,
+        ctx: ReadingIndexViewContext,
+    ) -> Result<ResponseDataIndex, WorkerError> {
+        self.inject(FaultOperation::GetIndex, &ctx.partition_id)
+            .await?;
+        self.inner.get_index(ctx).await
+    }
+
+    async fn purge(&self, ctx: PurgeDataContext) -> Result<i64> {
+        self.inner.purge(ctx).await
+    }
+
+    async fn is_healthy(&self) -> Result<bool> {
+        self.inner.is_healthy().await
+    }
+
+    async fn require_buffer(
+        &self,
+        ctx: RequireBufferContext,
+    ) -> Result<RequireBufferResponse, WorkerError> {
+        self.inner.require_buffer(ctx).await
+    }
+
+    async fn release_ticket(&self, ctx: ReleaseTicketContext) -> Result<i64, WorkerError> {
+        self.inner.release_ticket(ctx).await
+    }
+
+    async fn register_app(&self, ctx: RegisterAppContext) -> Result<()> {
+        self.inner.register_app(ctx).await
+    }
+
+    async fn name(&self) -> StorageType {
+        self.inner.name().await
+    }
+
+    fn capabilities(&self) -> StoreCapabilities {
+        self.inner.capabilities()
+    }
+
+    async fn spill_insert(&self, ctx: SpillWritingViewContext) -> Result<(), WorkerError> {
+        self.inject(FaultOperation::SpillInsert, &ctx.uid).await?;
+        self.inner.spill_insert(ctx).await
+    }
+
+    fn partition_stat(&self, uid: &PartitionedUId) -> Option<PartitionStat> {
+        self.inner.partition_stat(uid)
+    }
+}
+
+impl<S: PersistentStore> Persistent for FaultInjectedStore<S> {
+    fn spill_concurrency(&self) -> SpillConcurrency {
+        self.inner.spill_concurrency()
+    }
+
+    fn disk_used_ratio(&self) -> Option<f64> {
+        self.inner.disk_used_ratio()
+    }
+
+    fn disk_queue_ratio(&self) -> Option<f64> {
+        self.inner.disk_queue_ratio()
+    }
+}
+
+#[async_trait]
+impl<S: PersistentStore> HealthProvider for FaultInjectedStore<S> {
+    async fn component_health(&self) -> Vec<ComponentHealth> {
+        self.inner.component_health().await
+    }
+}
+
+impl<S: PersistentStore + 'static> PersistentStore for FaultInjectedStore<S> {}
+
+#[cfg(test)]
+mod tests {
+    use crate::app::PartitionedUId;
+    use crate::config::StorageType;
+    use crate::store::fault::{FaultInjector, FaultKind, FaultOperation, FaultRule};
+
+    #[test]
+    fn test_pick() {
+        let injector = FaultInjector::default();
+        let uid = PartitionedUId::from("app_1".to_string(), 1, 0);
+        assert!(!injector.is_enabled());
+
+        // case1: fail 2 out of every 3 matched operations
+        let mut rule = FaultRule::new(FaultOperation::SpillInsert, FaultKind::Error);
+        rule.app_id = Some("app_1".to_string());
+        rule.times = Some(2);
+        rule.every = Some(3);
+        injector.add_rule(rule);
+        assert!(injector.is_enabled());
+        let picked: Vec<bool> = (0..6)
+            .map(|_| {
+                injector
+                    .pick(FaultOperation::SpillInsert, StorageType::LOCALFILE, &uid)
+                    .is_some()
+            })
+            .collect();
+        assert_eq!(vec![true, true, false, true, true, false], picked);
+
+        // case2: the unmatched operation, app and storage
+        assert!(injector
+            .pick(FaultOperation::Get, StorageType::LOCALFILE, &uid)
+            .is_none());
+        let other = PartitionedUId::from("app_2".to_string(), 1, 0);
+        assert!(injector
+            .pick(FaultOperation::SpillInsert, StorageType::LOCALFILE, &other)
+            .is_none());
+        let mut rule = FaultRule::new(FaultOperation::Get, FaultKind::Corrupt);
+        rule.storage = Some(StorageType::HDFS);
+        injector.add_rule(rule);
+        assert!(injector
+            .pick(FaultOperation::Get, StorageType::LOCALFILE, &uid)
+            .is_none());
+        assert_eq!(
+            Some(FaultKind::Corrupt),
+            injector.pick(FaultOperation::Get, StorageType::HDFS, &uid)
+        );
+
+        // case3: inert after cleared
+        injector.clear();
+        assert!(!injector.is_enabled());
+        assert!(injector.rules().is_empty());
+    }
+
+    #[test]
+    fn test_rule_deserialize() -> anyhow::Result<()> {
+        let rules: Vec<FaultRule> = serde_json::from_str(
+            r#"[
+                {"operation": "GetIndex", "kind": {"type": "Latency", "millis": 100}, "partition_id": 1},
+                {"operation": "Insert", "kind": {"type": "Error"}}
+            ]"#,
+        )?;
+        assert_eq!(FaultKind::Latency { millis: 100 }, rules[0].kind);
+        assert_eq!(Some(1), rules[0].partition_id);
+        assert_eq!(None, rules[1].app_id);
+        Ok(())
+    }
+}
@@ -21,12 +21,13 @@ use crate::app::{
 };
 use crate::config::{HdfsStoreConfig, StorageType};
 use crate::error::WorkerError;
+use crate::health::{ComponentHealth, HealthProvider, HealthStatus};
 use std::collections::HashMap;
 
 use crate::metric::TOTAL_HDFS_USED;
 use crate::store::{
-    Block, Persistent, RequireBufferResponse, ResponseData, ResponseDataIndex,
-    SpillWritingViewContext, Store,
+    Block, PartitionStat, Persistent, RequireBufferResponse, ResponseData, ResponseDataIndex,
+    SpillConcurrency, SpillWritingViewContext, Store, StoreCapabilities, StoreTier,
 };
 use anyhow::{anyhow, Result};
 
@@ -68,6 +69,7 @@ impl Default for PartitionCachedMeta {
 
 pub struct HdfsStore {
     concurrency_access_limiter: Semaphore,
+    max_concurrency: usize,
 
     // key: app_id, value: hdfs_native_client
     app_remote_clients: DashMap<String, HdfsNativeClient>,
@@ -78,13 +80,21 @@ pub struct HdfsStore {
 
 unsafe impl Send for HdfsStore {}
 unsafe impl Sync for HdfsStore {}
-impl Persistent for HdfsStore {}
+impl Persistent for HdfsStore {
+    fn spill_concurrency(&self) -> SpillConcurrency {
+        SpillConcurrency {
+            max_concurrency: self.max_concurrency,
+            available_permits: self.concurrency_access_limiter.available_permits(),
+        }
+    }
+}
 
 impl HdfsStore {
     pub fn from(conf: HdfsStoreConfig) -> Self {
         HdfsStore {
             partition_file_locks: DashMap::new(),
             concurrency_access_limiter: Semaphore::new(conf.max_concurrency),
+            max_concurrency: conf.max_concurrency,
             partition_cached_meta: Default::default(),
             app_remote_clients: Default::default(),
         }
@@ -286,11 +296,17 @@ impl Store for HdfsStore {
         &self,
         _ctx: RequireBufferContext,
     ) -> Result<RequireBufferResponse, WorkerError> {
-        todo!()
+        Err(WorkerError::UNSUPPORTED_STORE_OPERATION(
+            "require_buffer",
+            "hdfs",
+        ))
     }
 
     async fn release_ticket(&self, _ctx: ReleaseTicketContext) -> Result<i64, WorkerError> {
-        todo!()
+        Err(WorkerError::UNSUPPORTED_STORE_OPERATION(
+            "release_ticket",
+            "hdfs",
+        ))
     }
 
     async fn register_app(&self, ctx: RegisterAppContext) -> Result<()> {
@@ -315,6 +331,26 @@ impl Store for HdfsStore {
         StorageType::HDFS
     }
 
+    // the data is read by the client side directly
+    fn capabilities(&self) -> StoreCapabilities {
+        StoreCapabilities {
+            supports_paging: false,
+            supports_index: false,
+            supports_buffer: false,
+            durable: true,
+            tier: StoreTier::COLD,
+        }
+    }
+
+    fn partition_stat(&self, uid: &PartitionedUId) -> Option<PartitionStat> {
+        let (data_file_path, _) = self.get_file_path_by_uid(uid);
+        let meta = self.partition_cached_meta.get(&data_file_path)?;
+        Some(PartitionStat {
+            size: meta.data_len as u64,
+            location: Some(data_file_path),
+        })
+    }
+
     async fn spill_insert(&self, ctx: SpillWritingViewContext) -> Result<(), WorkerError> {
         let uid = ctx.uid;
         let mut data = vec![];
@@ -330,6 +366,26 @@ impl Store for HdfsStore {
     }
 }
 
+#[async_trait]
+impl HealthProvider for HdfsStore {
+    async fn component_health(&self) -> Vec<ComponentHealth> {
+        let healthy = self.is_healthy().await.unwrap_or(false);
+        let status = if healthy {
+            HealthStatus::Healthy
+        } else {
+            HealthStatus::Unhealthy
+        };
+        vec![ComponentHealth::new(
+            "hdfs",
+            status,
+            false,
+            serde_json::json!({
+                "healthy": healthy,
+            }),
+        )]
+    }
+}
+
 #[async_trait]
 trait HdfsDelegator {
     async fn touch(&self, file_path: &str) -> Result<()>;
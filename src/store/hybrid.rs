@@ -19,52 +19,139 @@ use crate::app::{
     PartitionedUId, PurgeDataContext, ReadingIndexViewContext, ReadingOptions, ReadingViewContext,
     RegisterAppContext, ReleaseTicketContext, RequireBufferContext, WritingViewContext,
 };
+use crate::await_tree::AWAIT_TREE_REGISTRY;
 
-use crate::config::{Config, HybridStoreConfig, StorageType};
+use crate::config::{Config, HybridStoreConfig, StorageType, SPILL_EVENT_BUS_SCOPE};
 use crate::error::WorkerError;
+use crate::health::{ComponentHealth, HealthProvider, HealthStatus};
 use crate::metric::{
-    GAUGE_MEMORY_SPILL_TO_HDFS, GAUGE_MEMORY_SPILL_TO_LOCALFILE,
-    MEMORY_BUFFER_SPILL_BATCH_SIZE_HISTOGRAM, TOTAL_MEMORY_BUFFER_SPILL_BYTE_SIZE,
-    TOTAL_MEMORY_SPILL_TO_HDFS, TOTAL_MEMORY_SPILL_TO_LOCALFILE,
+    GAUGE_MEMORY_SPILL_HIGH_WATERMARK, GAUGE_MEMORY_SPILL_IN_FLIGHT_BYTES_TO,
+    GAUGE_MEMORY_SPILL_LOW_WATERMARK, GAUGE_MEMORY_SPILL_TO_HDFS, GAUGE_MEMORY_SPILL_TO_LOCALFILE,
+    MEMORY_BUFFER_SPILL_BATCH_SIZE_HISTOGRAM, MEMORY_SPILL_DURATION_TO,
+    TOTAL_MEMORY_BUFFER_SPILL_BYTE_SIZE, TOTAL_MEMORY_HIGH_WATERMARK_EXCEEDED,
+    TOTAL_MEMORY_SPILL_BYTES_TO, TOTAL_MEMORY_SPILL_FAILED_TO, TOTAL_MEMORY_SPILL_TO,
+    TOTAL_MEMORY_SPILL_TO_HDFS, TOTAL_MEMORY_SPILL_TO_LOCALFILE, TOTAL_MEMORY_SPILL_TRIGGERED,
+    TOTAL_SPILL_EVENTS_DEFERRED_BY_APP_LIMIT,
 };
 use crate::readable_size::ReadableSize;
 #[cfg(feature = "hdfs")]
 use crate::store::hdfs::HdfsStore;
 use crate::store::localfile::LocalFileStore;
 use crate::store::memory::MemoryStore;
+use crate::store::read_cache::{ReadCache, ReadCacheStats, SegmentKey, READ_CACHE_ADMISSION_TTL};
 
-use crate::store::{Persistent, RequireBufferResponse, ResponseData, ResponseDataIndex, Store};
+use crate::store::{
+    Block, BlockSource, DataSegment, PartitionStorageStat, PartitionedLocalData,
+    PartitionedMergedData, Persistent, RequireBufferResponse, ResponseData, ResponseDataIndex,
+    SpillConcurrency, SpillStatus, Store, StoreCapabilities, StoreTier, TierSpillStatus,
+};
 use anyhow::{anyhow, Result};
 
 use async_trait::async_trait;
+use bytes::Buf;
+use croaring::Treemap;
 use log::{debug, error, info, warn};
 use prometheus::core::{Atomic, AtomicU64};
 use std::any::Any;
 
-use std::collections::VecDeque;
+use std::collections::{HashSet, VecDeque};
 use std::ops::Deref;
 
 use await_tree::InstrumentAwait;
 use fastrace::future::FutureExt;
 use fastrace::trace;
-use std::str::FromStr;
 use std::sync::Arc;
+use std::time::Duration;
 
-use crate::event_bus::EventBus;
+use crate::busy_score::{BusyComponents, BusySource};
+use crate::composed_bytes::ComposedBytes;
+use crate::event_bus::{
+    ConcurrencyLimiter, EventBus, EventBusOptions, ThrottledSubscriber, UsageSource,
+};
 use crate::runtime::manager::RuntimeManager;
+use crate::shutdown::{PHASE_DRAIN, SHUTDOWN_COORDINATOR};
+use crate::store::fault::FaultInjectedStore;
+use crate::store::mem::admission::{AdmissionOutcome, SpillDebtAdmission};
+use crate::store::mem::buffer::MemoryBuffer;
 use crate::store::mem::capacity::CapacitySnapshot;
+use crate::store::mem::snapshot::MemorySnapshot;
 use crate::store::spill::event_handler::SpillEventHandler;
 use crate::store::spill::{SpillMessage, SpillWritingViewContext};
-use tokio::sync::Mutex;
+use dashmap::DashMap;
+use tokio::sync::{Mutex, OwnedSemaphorePermit, Semaphore};
 use tokio::time::Instant;
 
-pub trait PersistentStore: Store + Persistent + Send + Sync {}
+pub trait PersistentStore: Store + Persistent + HealthProvider + Send + Sync {}
 impl PersistentStore for LocalFileStore {}
 
 #[cfg(feature = "hdfs")]
 impl PersistentStore for HdfsStore {}
 
 const DEFAULT_MEMORY_SPILL_MAX_CONCURRENCY: i32 = 20;
+const SPILL_DRAIN_TIMEOUT: Duration = Duration::from_secs(30);
+// the hint for the client to retry the rejected writes of the exceeded partition
+const PARTITION_BUFFER_RETRY_AFTER_MILLIS: u64 = 500;
+
+// offset(8) + length(4) + uncompress_length(4) + crc(8) + block_id(8) + task_attempt_id(8)
+const LOCALFILE_INDEX_ENTRY_LEN: usize = 40;
+
+const SPILL_TRIGGER_WATERMARK: &str = "watermark";
+const SPILL_TRIGGER_PARTITION_SIZE: &str = "partition_size";
+const SPILL_TRIGGER_DRAIN: &str = "drain";
+const SPILL_TRIGGER_FLUSH_BARRIER: &str = "flush_barrier";
+const SPILL_TRIGGER_APP_MEMORY_QUOTA: &str = "app_memory_quota";
+
+// the sources of the published spill events
+const SPILL_EVENT_SOURCE: &str = "memory_spill";
+const DEFERRED_SPILL_EVENT_SOURCE: &str = "deferred_spill";
+const RETRIED_SPILL_EVENT_SOURCE: &str = "retried_spill";
+const SPILL_EVENT_REQUEUE_INTERVAL: Duration = Duration::from_millis(100);
+const MAX_SPILL_EVENT_REQUEUE_INTERVAL: Duration = Duration::from_millis(1600);
+
+// the full jittered backoff to spread the requeued events rather than republishing them
+// all at once when the permit or the queue slot frees
+fn spill_event_requeue_delay(attempt: u32) -> Duration {
+    let backoff = SPILL_EVENT_REQUEUE_INTERVAL
+        .saturating_mul(1 << attempt.min(16))
+        .min(MAX_SPILL_EVENT_REQUEUE_INTERVAL);
+    backoff.mul_f64(rand::random::<f64>())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SpillTarget {
+    Warm,
+    Cold,
+}
+
+/// The buffer at or above the cold threshold goes to the cold tier directly, and the
+/// smaller one goes to the warm tier unless it's unavailable. The retried event always
+/// falls back to the cold tier, which is proposed to be stable.
+fn route_spill(
+    spill_size: u64,
+    cold_threshold: Option<u64>,
+    warm_available: bool,
+    retry_cnt: i32,
+) -> (SpillTarget, &'static str) {
+    if retry_cnt >= 1 {
+        return (SpillTarget::Cold, "retry fallback");
+    }
+    if !warm_available {
+        return (SpillTarget::Cold, "warm store unavailable");
+    }
+    match cold_threshold {
+        Some(threshold) if spill_size >= threshold => (SpillTarget::Cold, "cold threshold"),
+        _ => (SpillTarget::Warm, "normal"),
+    }
+}
+
+// the `to` label of the spill metrics, which is the tier the data actually goes to
+fn spill_destination(storage_type: &StorageType) -> &'static str {
+    match storage_type {
+        StorageType::LOCALFILE => "localfile",
+        StorageType::HDFS => "hdfs",
+        _ => "remote",
+    }
+}
 
 pub struct HybridStore {
     // Box<dyn Store> will build fail
@@ -81,8 +168,27 @@ pub struct HybridStore {
     memory_spill_to_cold_threshold_size: Option<u64>,
     memory_spill_max_concurrency: i32,
 
+    partition_buffer_max_size: Option<u64>,
+    partition_buffer_strict: bool,
+
+    // key: app_id, to keep the single app from monopolizing the spill concurrency
+    app_spill_limiters: DashMap<String, Arc<Semaphore>>,
+    per_app_spill_concurrency: Option<usize>,
+
     runtime_manager: RuntimeManager,
 
+    // the recently served segments of the warm store
+    read_cache: Arc<ReadCache>,
+
+    spill_debt_admission: Option<SpillDebtAdmission>,
+
+    // (low, high) of the localfile disks to throttle the spill concurrency, disabled if none
+    spill_disk_watermarks: Option<(f32, f32)>,
+    // only the spill to the warm store is throttled, the cold one doesn't consume the disks
+    warm_spill_limiter: Option<ConcurrencyLimiter>,
+    // the spill goes to the cold store when the warm disks are above it
+    warm_high_watermark: Option<f32>,
+
     pub event_bus: EventBus<SpillMessage>,
 }
 
@@ -96,11 +202,35 @@ impl HybridStore {
             panic!("Storage type must contains memory.");
         }
 
+        let spill_event_bus_options =
+            EventBusOptions::from(&config.event_bus_config(SPILL_EVENT_BUS_SCOPE)).unwrap();
+
+        let read_cache_capacity = match &config.hybrid_store.read_cache_capacity {
+            Some(v) => ReadableSize::parse(v).unwrap().as_bytes(),
+            _ => 0,
+        };
+        let read_cache = Arc::new(ReadCache::new(
+            read_cache_capacity,
+            READ_CACHE_ADMISSION_TTL,
+        ));
+
         let mut persistent_stores: VecDeque<Box<dyn PersistentStore>> = VecDeque::with_capacity(2);
+        let mut spill_disk_watermarks = None;
+        let mut warm_high_watermark = None;
         if StorageType::contains_localfile(&store_type) {
-            let localfile_store =
-                LocalFileStore::from(config.localfile_store.unwrap(), runtime_manager.clone());
-            persistent_stores.push_back(Box::new(localfile_store));
+            let localfile_conf = config.localfile_store.unwrap();
+            spill_disk_watermarks = Some((
+                localfile_conf.disk_low_watermark,
+                localfile_conf.disk_high_watermark,
+            ));
+            warm_high_watermark = Some(localfile_conf.disk_high_watermark);
+            let localfile_store = LocalFileStore::from(localfile_conf, runtime_manager.clone());
+            // the cached segments are stale after the data offsets are changed
+            let cache = read_cache.clone();
+            localfile_store.on_compacted(Box::new(move |uid| {
+                cache.invalidate(&uid.app_id, Some(uid.shuffle_id))
+            }));
+            persistent_stores.push_back(Box::new(FaultInjectedStore::new(localfile_store)));
         }
 
         if StorageType::contains_hdfs(&store_type) {
@@ -110,28 +240,54 @@ impl HybridStore {
             #[cfg(feature = "hdfs")]
             let hdfs_store = HdfsStore::from(config.hdfs_store.unwrap());
             #[cfg(feature = "hdfs")]
-            persistent_stores.push_back(Box::new(hdfs_store));
+            persistent_stores.push_back(Box::new(FaultInjectedStore::new(hdfs_store)));
         }
 
+        let per_app_spill_concurrency = config
+            .app_config
+            .per_app_spill_concurrency
+            .map(|x| x as usize);
         let hybrid_conf = config.hybrid_store;
         let memory_spill_to_cold_threshold_size =
             match &hybrid_conf.memory_spill_to_cold_threshold_size {
-                Some(v) => Some(ReadableSize::from_str(&v.clone()).unwrap().as_bytes()),
+                Some(v) => Some(ReadableSize::parse(&v.clone()).unwrap().as_bytes()),
                 _ => None,
             };
         let memory_spill_max_concurrency = hybrid_conf.memory_spill_max_concurrency;
+        let spill_disk_watermarks = match hybrid_conf.spill_disk_throttle_enable {
+            Some(true) => spill_disk_watermarks,
+            _ => None,
+        };
+        let spill_debt_admission = hybrid_conf
+            .spill_debt_admission
+            .as_ref()
+            .map(|x| SpillDebtAdmission::from(x).unwrap());
+
+        let warm_spill_limiter = spill_disk_watermarks.map(|_| {
+            ConcurrencyLimiter::new(
+                "HybridStoreWarmSpill",
+                memory_spill_max_concurrency as usize,
+            )
+        });
 
-        let event_bus: EventBus<SpillMessage> = EventBus::new(
-            runtime_manager.dispatch_runtime.clone(),
+        let event_bus: EventBus<SpillMessage> = EventBus::with_options(
+            runtime_manager.flush_runtime.clone(),
             "HybridStoreSpill".to_string(),
             memory_spill_max_concurrency as usize,
+            spill_event_bus_options,
         );
 
+        let memory_conf = config.memory_store.unwrap();
+        let memory_capacity = memory_conf.capacity_bytes().unwrap() as f32;
+        GAUGE_MEMORY_SPILL_HIGH_WATERMARK
+            .set((memory_capacity * hybrid_conf.memory_spill_high_watermark) as i64);
+        GAUGE_MEMORY_SPILL_LOW_WATERMARK
+            .set((memory_capacity * hybrid_conf.memory_spill_low_watermark) as i64);
+        let partition_buffer_max_size = memory_conf.partition_buffer_max_size_bytes().unwrap();
+        let partition_buffer_strict = memory_conf.partition_buffer_strict.unwrap_or(false);
+
         let store = HybridStore {
-            hot_store: Arc::new(MemoryStore::from(
-                config.memory_store.unwrap(),
-                runtime_manager.clone(),
-            )),
+            hot_store: Arc::new(MemoryStore::from(memory_conf, runtime_manager.clone())),
             warm_store: persistent_stores.pop_front(),
             cold_store: persistent_stores.pop_front(),
             config: hybrid_conf,
@@ -139,7 +295,16 @@ impl HybridStore {
             memory_spill_event_num: AtomicU64::new(0),
             memory_spill_to_cold_threshold_size,
             memory_spill_max_concurrency,
+            partition_buffer_max_size,
+            partition_buffer_strict,
+            app_spill_limiters: DashMap::new(),
+            per_app_spill_concurrency,
             runtime_manager,
+            read_cache,
+            spill_debt_admission,
+            spill_disk_watermarks,
+            warm_spill_limiter,
+            warm_high_watermark,
             event_bus,
         };
         store
@@ -186,30 +351,33 @@ impl HybridStore {
             .ok_or(anyhow!("empty warm store. It should not happen"))?;
         let cold = self.cold_store.as_ref().unwrap_or(warm);
 
-        // we should cover the following cases
-        // 1. local store is unhealthy. spill to hdfs
-        // 2. event flushed to localfile failed. and exceed retry max cnt, fallback to hdfs
-        // 3. huge partition directly flush to hdfs
-
-        // normal assignment
-        let mut candidate_store = if warm.is_healthy().await? {
-            let cold_spilled_size = self.memory_spill_to_cold_threshold_size.unwrap_or(u64::MAX);
-            // if cold_spilled_size < spill_size as u64 || ctx.owned_by_huge_partition {
-            if cold_spilled_size < spill_size as u64 {
-                cold
-            } else {
-                warm
-            }
-        } else {
-            cold
+        let warm_available =
+            warm.is_healthy().await? && !self.is_warm_above_watermark(warm.as_ref());
+        let (target, reason) = route_spill(
+            spill_size as u64,
+            self.memory_spill_to_cold_threshold_size,
+            warm_available,
+            retry_cnt,
+        );
+        let candidate_store = match target {
+            SpillTarget::Warm => warm,
+            SpillTarget::Cold => cold,
+        };
+        let _warm_spill_permit = match (&target, &self.warm_spill_limiter) {
+            (SpillTarget::Warm, Some(limiter)) => Some(
+                limiter
+                    .acquire()
+                    .instrument_await("waiting for the throttled warm spill concurrency.")
+                    .await,
+            ),
+            _ => None,
         };
-
-        // fallback assignment. propose hdfs always is active and stable
-        if retry_cnt >= 1 {
-            candidate_store = cold;
-        }
 
         let storage_type = candidate_store.name().await;
+        debug!(
+            "Spilling {} bytes of {:?} to {:?} due to {}. cold threshold: {:?}",
+            spill_size, &ctx.uid, &storage_type, reason, self.memory_spill_to_cold_threshold_size
+        );
 
         match &storage_type {
             StorageType::LOCALFILE => {
@@ -222,6 +390,14 @@ impl HybridStore {
             }
             _ => {}
         }
+        let to = spill_destination(&storage_type);
+        TOTAL_MEMORY_SPILL_TO.with_label_values(&[to]).inc();
+        GAUGE_MEMORY_SPILL_IN_FLIGHT_BYTES_TO
+            .with_label_values(&[to])
+            .add(spill_size);
+        let timer = MEMORY_SPILL_DURATION_TO
+            .with_label_values(&[to])
+            .start_timer();
 
         let message = format!(
             "partition uid: {:?}, memory spilled size: {}",
@@ -247,12 +423,29 @@ impl HybridStore {
             }
             _ => {}
         }
+        timer.observe_duration();
+        GAUGE_MEMORY_SPILL_IN_FLIGHT_BYTES_TO
+            .with_label_values(&[to])
+            .sub(spill_size);
+        match &result {
+            Ok(_) => TOTAL_MEMORY_SPILL_BYTES_TO
+                .with_label_values(&[to])
+                .inc_by(spill_size as u64),
+            Err(_) => TOTAL_MEMORY_SPILL_FAILED_TO.with_label_values(&[to]).inc(),
+        }
 
         let _ = result?;
 
         Ok(message)
     }
 
+    fn is_warm_above_watermark(&self, warm: &dyn PersistentStore) -> bool {
+        match (self.warm_high_watermark, warm.disk_used_ratio()) {
+            (Some(watermark), Some(used_ratio)) => used_ratio > watermark as f64,
+            _ => false,
+        }
+    }
+
     pub fn inc_used(&self, size: i64) -> Result<bool> {
         self.hot_store.inc_used(size)
     }
@@ -276,6 +469,61 @@ impl HybridStore {
         self.hot_store.get_partitioned_buffer_size(uid)
     }
 
+    pub async fn partition_storage_stat(&self, uid: &PartitionedUId) -> PartitionStorageStat {
+        let mut stat = PartitionStorageStat {
+            memory: self.hot_store.partition_stat(uid),
+            ..Default::default()
+        };
+        for store in [&self.warm_store, &self.cold_store].into_iter().flatten() {
+            match store.name().await {
+                StorageType::LOCALFILE => stat.localfile = store.partition_stat(uid),
+                StorageType::HDFS => stat.hdfs = store.partition_stat(uid),
+                _ => {}
+            }
+        }
+        stat
+    }
+
+    pub fn memory_store_snapshot(&self) -> MemorySnapshot {
+        self.hot_store.snapshot()
+    }
+
+    pub fn read_cache_stats(&self) -> ReadCacheStats {
+        self.read_cache.stats()
+    }
+
+    /// Returns the released bytes of the read cache.
+    pub fn clear_read_cache(&self) -> u64 {
+        self.read_cache.clear()
+    }
+
+    /// The spill concurrency of the event handlers and every persistent tier.
+    pub async fn spill_status(&self) -> SpillStatus {
+        let mut tiers = vec![];
+        for store in [&self.warm_store, &self.cold_store].into_iter().flatten() {
+            let (tier, in_flight) = match store.name().await {
+                StorageType::LOCALFILE => {
+                    ("memory->localfile", GAUGE_MEMORY_SPILL_TO_LOCALFILE.get())
+                }
+                StorageType::HDFS => ("memory->hdfs", GAUGE_MEMORY_SPILL_TO_HDFS.get()),
+                _ => continue,
+            };
+            tiers.push(TierSpillStatus {
+                tier: tier.to_string(),
+                in_flight,
+                concurrency: store.spill_concurrency(),
+            });
+        }
+        SpillStatus {
+            queued: self.event_bus.pending_size(),
+            concurrency: SpillConcurrency {
+                max_concurrency: self.event_bus.max_concurrency(),
+                available_permits: self.event_bus.available_permits(),
+            },
+            tiers,
+        }
+    }
+
     pub fn memory_spill_event_num(&self) -> Result<u64> {
         Ok(self.memory_spill_event_num.get())
     }
@@ -284,12 +532,95 @@ impl HybridStore {
         MEMORY_BUFFER_SPILL_BATCH_SIZE_HISTOGRAM.observe(message.size as f64);
         TOTAL_MEMORY_BUFFER_SPILL_BYTE_SIZE.inc_by(message.size as u64);
 
-        self.event_bus.publish(message.into()).await?;
+        self.event_bus
+            .publish_from(SPILL_EVENT_SOURCE, message.into())
+            .await?;
         self.memory_spill_event_num.inc_by(1);
 
         Ok(())
     }
 
+    /// Acquire the spill permit of the app, the limiter is returned if it's exhausted.
+    /// None is acquired when the per app concurrency is not limited.
+    pub fn try_acquire_app_spill_permit(
+        &self,
+        app_id: &str,
+    ) -> Result<Option<OwnedSemaphorePermit>, Arc<Semaphore>> {
+        let concurrency = match self.per_app_spill_concurrency {
+            Some(concurrency) => concurrency,
+            _ => return Ok(None),
+        };
+        let limiter = self
+            .app_spill_limiters
+            .entry(app_id.to_string())
+            .or_insert_with(|| Arc::new(Semaphore::new(concurrency)))
+            .clone();
+        match limiter.clone().try_acquire_owned() {
+            Ok(permit) => Ok(Some(permit)),
+            _ => Err(limiter),
+        }
+    }
+
+    /// Republish the spill event once the app's permit is available, so that the
+    /// shared concurrency is not occupied by the waiting event.
+    pub fn defer_spill_event(&self, limiter: Arc<Semaphore>, message: SpillMessage) {
+        TOTAL_SPILL_EVENTS_DEFERRED_BY_APP_LIMIT.inc();
+        let event_bus = self.event_bus.clone();
+        self.runtime_manager
+            .flush_runtime
+            .spawn_guarded("deferred_spill_event", async move {
+                if let Ok(permit) = limiter.acquire().await {
+                    drop(permit);
+                }
+                tokio::time::sleep(spill_event_requeue_delay(0)).await;
+                Self::requeue_until_accepted(&event_bus, DEFERRED_SPILL_EVENT_SOURCE, message)
+                    .await;
+            });
+    }
+
+    /// Requeue the failed spill event from the spill handler. It never waits on the full
+    /// queue in place, since the handler holds the bus permit and nothing would drain it.
+    pub fn retry_spill_event(&self, message: SpillMessage) {
+        let event_bus = self.event_bus.clone();
+        if let Err(err) =
+            event_bus.try_publish_from(RETRIED_SPILL_EVENT_SOURCE, message.clone().into())
+        {
+            warn!(
+                "Errors on requeuing the retried spill event, it will be retried in the background. {:?}",
+                err
+            );
+            self.runtime_manager
+                .flush_runtime
+                .spawn_guarded("retried_spill_event", async move {
+                    tokio::time::sleep(spill_event_requeue_delay(0)).await;
+                    Self::requeue_until_accepted(&event_bus, RETRIED_SPILL_EVENT_SOURCE, message)
+                        .await;
+                });
+        }
+    }
+
+    // the event is kept in memory until spilled, so it's requeued rather than dropped
+    async fn requeue_until_accepted(
+        event_bus: &EventBus<SpillMessage>,
+        source: &'static str,
+        message: SpillMessage,
+    ) {
+        let mut attempt = 0;
+        loop {
+            match event_bus.try_publish_from(source, message.clone().into()) {
+                Ok(_) => return,
+                Err(err) => {
+                    debug!(
+                        "Errors on requeuing the spill event from: {}. {:?}",
+                        source, err
+                    );
+                    attempt += 1;
+                    tokio::time::sleep(spill_event_requeue_delay(attempt)).await;
+                }
+            }
+        }
+    }
+
     pub async fn release_data_in_memory(
         &self,
         data_size: i64,
@@ -299,16 +630,252 @@ impl HybridStore {
         self.hot_store
             .clear_spilled_memory_buffer(uid.clone(), message.flight_id, data_size as u64)
             .await?;
-        self.hot_store.dec_used(data_size)?;
-        self.hot_store.dec_inflight(data_size as u64);
+        self.hot_store.release_flushed(data_size)?;
         Ok(())
     }
 
+    /// Read the whole partition data merged from memory and localfile.
+    ///
+    /// The memory blocks are snapshotted before reading the localfile. Since the spilled
+    /// blocks are only removed from memory (bumping the flush sequence) after being
+    /// persisted, the block missing in the snapshot must be visible in the localfile.
+    /// The blocks being flushed during the read exist in both sides, and the localfile
+    /// copies are dropped, so that every block is visible exactly once.
+    pub async fn get_merged(
+        &self,
+        uid: &PartitionedUId,
+        task_ids: Option<Treemap>,
+    ) -> Result<PartitionedMergedData, WorkerError> {
+        // the data in hdfs is read by the client side directly
+        if self.cold_store.is_some() {
+            return Err(WorkerError::NOT_READ_HDFS_DATA_FROM_SERVER);
+        }
+        let warm = self.warm_store.as_ref();
+        if let Some(warm) = warm {
+            if !warm.capabilities().supports_index {
+                return Err(WorkerError::NOT_READ_HDFS_DATA_FROM_SERVER);
+            }
+        }
+
+        let buffer = self.hot_store.get_memory_buffer(uid);
+        let snapshot = buffer.as_ref().map(|x| x.snapshot(task_ids.as_ref()));
+
+        let mut blocks: Vec<(BlockSource, Block)> = vec![];
+        if let Some(warm) = warm {
+            let index = match warm
+                .get_index(ReadingIndexViewContext {
+                    partition_id: uid.clone(),
+                })
+                .await?
+            {
+                ResponseDataIndex::Local(index) => index,
+            };
+            // the index may be appended concurrently, only the complete entries are visible
+            let mut index_data = index.index_data;
+            index_data
+                .truncate(index_data.len() / LOCALFILE_INDEX_ENTRY_LEN * LOCALFILE_INDEX_ENTRY_LEN);
+
+            let mut segments = vec![];
+            let mut data_len = 0i64;
+            while index_data.has_remaining() {
+                let segment = DataSegment {
+                    offset: index_data.get_i64(),
+                    length: index_data.get_i32(),
+                    uncompress_length: index_data.get_i32(),
+                    crc: index_data.get_i64(),
+                    block_id: index_data.get_i64(),
+                    task_attempt_id: index_data.get_i64(),
+                };
+                data_len = data_len.max(segment.offset + segment.length as i64);
+                segments.push(segment);
+            }
+
+            let data = warm
+                .get(ReadingViewContext {
+                    uid: uid.clone(),
+                    reading_options: ReadingOptions::FILE_OFFSET_AND_LEN(0, data_len),
+                    serialized_expected_task_ids_bitmap: None,
+                })
+                .await?
+                .from_local();
+
+            let in_memory: HashSet<i64> = match &snapshot {
+                Some(snapshot) => snapshot.blocks.iter().map(|x| x.block_id).collect(),
+                _ => HashSet::new(),
+            };
+
+            for segment in segments {
+                if in_memory.contains(&segment.block_id) {
+                    continue;
+                }
+                if let Some(ref expected_task_ids) = task_ids {
+                    if !expected_task_ids.contains(segment.task_attempt_id as u64) {
+                        continue;
+                    }
+                }
+                let start = segment.offset as usize;
+                let end = start + segment.length as usize;
+                if end > data.len() {
+                    return Err(WorkerError::PARTIAL_DATA_LOST(format!("{:?}", uid)));
+                }
+                blocks.push((
+                    BlockSource::LOCALFILE,
+                    Block {
+                        block_id: segment.block_id,
+                        length: segment.length,
+                        uncompress_length: segment.uncompress_length,
+                        crc: segment.crc,
+                        data: data.slice(start..end),
+                        task_attempt_id: segment.task_attempt_id,
+                    },
+                ));
+            }
+        }
+
+        let flush_sequence = snapshot.as_ref().map(|x| x.flush_sequence).unwrap_or(0);
+        if let Some(snapshot) = snapshot {
+            for block in snapshot.blocks {
+                blocks.push((BlockSource::MEMORY, block));
+            }
+        }
+
+        let mut merged = PartitionedMergedData {
+            flush_sequence,
+            ..Default::default()
+        };
+        let mut block_bytes = Vec::with_capacity(blocks.len());
+        let mut offset = 0i64;
+        for (source, block) in blocks {
+            merged.shuffle_data_block_segments.push(DataSegment {
+                block_id: block.block_id,
+                offset,
+                length: block.length,
+                uncompress_length: block.uncompress_length,
+                crc: block.crc,
+                task_attempt_id: block.task_attempt_id,
+            });
+            merged.block_sources.push(source);
+            offset += block.length as i64;
+            block_bytes.push(block.data);
+        }
+        merged.data = ComposedBytes::from(block_bytes, offset as usize).into();
+        Ok(merged)
+    }
+
     #[trace]
     pub async fn watermark_spill(&self) -> Result<()> {
-        let timer = Instant::now();
         let mem_target =
             (self.hot_store.get_capacity()? as f32 * self.config.memory_spill_low_watermark) as i64;
+        self.spill_to_target(mem_target, SPILL_TRIGGER_WATERMARK)
+            .await
+    }
+
+    /// Spill all the staging data in memory, like draining the server before decommissioning.
+    pub async fn spill_all(&self) -> Result<()> {
+        if self.warm_store.is_none() && self.cold_store.is_none() {
+            return Ok(());
+        }
+        let _lock = self.memory_spill_lock.lock().await;
+        let in_flight = self.hot_store.in_flight_size();
+        let staging = self.hot_store.memory_snapshot()?.used() - in_flight;
+        if staging <= 0 {
+            return Ok(());
+        }
+        self.spill_to_target(in_flight, SPILL_TRIGGER_DRAIN).await
+    }
+
+    /// Spill the partition buffer once its staging size exceeds the max size,
+    /// which prevents the skewed partition from absorbing the whole memory.
+    async fn partition_size_spill(&self, uid: &PartitionedUId, max_size: u64) -> Result<()> {
+        let buffer = self.hot_store.get_or_create_memory_buffer(uid.clone());
+        if buffer.staging_size()? as u64 <= max_size {
+            return Ok(());
+        }
+        self.spill_partition_staging(uid, &buffer, SPILL_TRIGGER_PARTITION_SIZE)
+            .await
+    }
+
+    /// Spill the staging data of the partitions, which are waited by the flush barrier
+    /// rather than the memory watermark.
+    pub async fn flush_partitions(&self, uids: Vec<PartitionedUId>) -> Result<()> {
+        self.spill_partitions(uids, SPILL_TRIGGER_FLUSH_BARRIER)
+            .await
+    }
+
+    /// Spill the staging data of the app partitions whose memory exceeds its quota.
+    pub async fn app_memory_quota_spill(&self, uids: Vec<PartitionedUId>) -> Result<()> {
+        self.spill_partitions(uids, SPILL_TRIGGER_APP_MEMORY_QUOTA)
+            .await
+    }
+
+    async fn spill_partitions(&self, uids: Vec<PartitionedUId>, trigger: &str) -> Result<()> {
+        if self.is_memory_only() {
+            return Ok(());
+        }
+        for uid in uids {
+            if let Some(buffer) = self.hot_store.get_memory_buffer(&uid) {
+                if buffer.staging_size()? > 0 {
+                    self.spill_partition_staging(&uid, &buffer, trigger).await?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The memory buffer size of the partitions, including the in-flight ones.
+    pub fn memory_buffer_size(&self, uids: &[PartitionedUId]) -> Result<u64> {
+        let mut size = 0;
+        for uid in uids {
+            if let Some(buffer) = self.hot_store.get_memory_buffer(uid) {
+                size += buffer.total_size()? as u64;
+            }
+        }
+        Ok(size)
+    }
+
+    /// The min write sequence of the partition data not persisted yet. The data of the
+    /// memory only store is never flushed, which is treated as the persisted.
+    pub fn unflushed_write_sequence(&self, uid: &PartitionedUId) -> Option<u64> {
+        if self.is_memory_only() {
+            return None;
+        }
+        self.hot_store
+            .get_memory_buffer(uid)
+            .and_then(|buffer| buffer.unflushed_write_sequence())
+    }
+
+    async fn spill_partition_staging(
+        &self,
+        uid: &PartitionedUId,
+        buffer: &MemoryBuffer,
+        trigger: &str,
+    ) -> Result<()> {
+        let spill_result = buffer.spill()?;
+        let flight_len = spill_result.flight_len();
+        // the staging may have been picked up by the concurrent spill
+        if flight_len == 0 {
+            return Ok(());
+        }
+        TOTAL_MEMORY_SPILL_TRIGGERED
+            .with_label_values(&[trigger])
+            .inc();
+        let message = SpillMessage {
+            ctx: SpillWritingViewContext::new(uid.clone(), spill_result.blocks()),
+            size: flight_len as i64,
+            retry_cnt: 0,
+            previous_spilled_storage: None,
+            flight_id: spill_result.flight_id(),
+        };
+        self.publish_spill_event(message).await?;
+        self.hot_store.inc_inflight(flight_len);
+        Ok(())
+    }
+
+    async fn spill_to_target(&self, mem_target: i64, trigger: &str) -> Result<()> {
+        TOTAL_MEMORY_SPILL_TRIGGERED
+            .with_label_values(&[trigger])
+            .inc();
+        let timer = Instant::now();
         let buffers = self.hot_store.pickup_spilled_blocks(mem_target)?;
         debug!(
             "[Spill] Getting all spill blocks. target_size:{}. it costs {}(ms)",
@@ -345,32 +912,118 @@ impl HybridStore {
     }
 }
 
+// the disk usage of the warm store, which is reported after every spill
+impl UsageSource for HybridStore {
+    fn used_ratio(&self) -> Option<f64> {
+        self.warm_store.as_ref()?.disk_used_ratio()
+    }
+}
+
+impl BusySource for HybridStore {
+    fn busy_components(&self) -> BusyComponents {
+        let memory_used_ratio = match self.hot_store.memory_snapshot() {
+            Ok(snapshot) if snapshot.capacity() > 0 => {
+                (snapshot.used() + snapshot.allocated()) as f64 / snapshot.capacity() as f64
+            }
+            _ => 0.0,
+        };
+        let disk_queue_ratio = self
+            .warm_store
+            .as_ref()
+            .and_then(|store| store.disk_queue_ratio())
+            .unwrap_or(0.0);
+        BusyComponents {
+            memory_used_ratio,
+            event_bus_pending: self.event_bus.pending_size(),
+            event_bus_capacity: self.event_bus.queue_capacity(),
+            disk_queue_ratio,
+        }
+    }
+}
+
 #[async_trait]
 impl Store for HybridStore {
     fn start(self: Arc<HybridStore>) {
+        self.hot_store.clone().start();
         if self.is_memory_only() {
             return;
         }
 
-        self.event_bus.subscribe(SpillEventHandler {
+        let handler = SpillEventHandler {
             store: self.clone(),
-        });
+        };
+        match (self.spill_disk_watermarks, &self.warm_spill_limiter) {
+            (Some((low, high)), Some(limiter)) => {
+                self.event_bus.subscribe(ThrottledSubscriber::new(
+                    handler,
+                    self.clone(),
+                    limiter.clone(),
+                    low as f64,
+                    high as f64,
+                ));
+            }
+            _ => {
+                self.event_bus.subscribe(handler);
+            }
+        }
+
+        // wait for the in-flight spill events to be flushed before exiting
+        let store = Arc::downgrade(&self);
+        SHUTDOWN_COORDINATOR.register(
+            "spill_event_bus_drain",
+            PHASE_DRAIN,
+            SPILL_DRAIN_TIMEOUT,
+            move || async move {
+                while let Some(store) = store.upgrade() {
+                    let pending = store.memory_spill_event_num()?;
+                    if pending == 0 {
+                        break;
+                    }
+                    info!("Waiting for {} spill events to be flushed", pending);
+                    drop(store);
+                    tokio::time::sleep(Duration::from_millis(500)).await;
+                }
+                Ok(())
+            },
+        );
     }
 
     #[trace]
     async fn insert(&self, ctx: WritingViewContext) -> Result<(), WorkerError> {
         let store = self.hot_store.clone();
+        if self.is_memory_only() {
+            return store.insert(ctx).await;
+        }
+
+        let uid = ctx.uid.clone();
+        if let (Some(max_size), true) =
+            (self.partition_buffer_max_size, self.partition_buffer_strict)
+        {
+            let buffer = store.get_or_create_memory_buffer(uid.clone());
+            if buffer.total_size()? as u64 > max_size {
+                return Err(WorkerError::MEMORY_USAGE_LIMITED_BY_PARTITION_BUFFER(
+                    PARTITION_BUFFER_RETRY_AFTER_MILLIS,
+                ));
+            }
+        }
+
         let insert_result = store.insert(ctx).await;
 
-        if self.is_memory_only() {
-            return insert_result;
+        if let Some(max_size) = self.partition_buffer_max_size {
+            if let Err(err) = self.partition_size_spill(&uid, max_size).await {
+                warn!("Errors on partition size spill for {:?}. {:?}", &uid, err)
+            }
         }
 
-        if let Ok(_) = self.memory_spill_lock.try_lock() {
-            let ratio = self.hot_store.calculate_usage_ratio();
-            if ratio > self.config.memory_spill_high_watermark {
-                if let Err(err) = self.watermark_spill().await {
-                    warn!("Errors on watermark spill. {:?}", err)
+        let high_watermark = self.config.memory_spill_high_watermark;
+        if self.hot_store.calculate_usage_ratio() > high_watermark {
+            TOTAL_MEMORY_HIGH_WATERMARK_EXCEEDED.inc();
+            // rechecked within the lock, the concurrent spill may have been finished
+            if let Ok(_) = self.memory_spill_lock.try_lock() {
+                if self.hot_store.calculate_usage_ratio() > high_watermark {
+                    if let Err(err) = self.watermark_spill().await {
+                        warn!("Errors on watermark spill. {:?}", err)
+                    }
                 }
             }
         }
@@ -384,7 +1037,28 @@ impl Store for HybridStore {
             ReadingOptions::MEMORY_LAST_BLOCK_ID_AND_MAX_SIZE(_, _) => {
                 self.hot_store.get(ctx).await
             }
-            _ => self.warm_store.as_ref().unwrap().get(ctx).await,
+            ReadingOptions::FILE_OFFSET_AND_LEN(offset, length) => match &self.warm_store {
+                Some(warm) => {
+                    let key = SegmentKey {
+                        app_id: ctx.uid.app_id.to_string(),
+                        shuffle_id: ctx.uid.shuffle_id,
+                        partition_id: ctx.uid.partition_id,
+                        offset,
+                        length,
+                    };
+                    let data = self
+                        .read_cache
+                        .get(key, || async move {
+                            warm.get(ctx).await.map(|data| data.from_local())
+                        })
+                        .await?;
+                    Ok(ResponseData::Local(PartitionedLocalData { data }))
+                }
+                _ => Err(WorkerError::UNSUPPORTED_STORE_OPERATION(
+                    "get by the file offset",
+                    "hybrid",
+                )),
+            },
         }
     }
 
@@ -393,7 +1067,13 @@ impl Store for HybridStore {
         &self,
         ctx: ReadingIndexViewContext,
     ) -> Result<ResponseDataIndex, WorkerError> {
-        self.warm_store.as_ref().unwrap().get_index(ctx).await
+        match &self.warm_store {
+            Some(warm) => warm.get_index(ctx).await,
+            _ => Err(WorkerError::UNSUPPORTED_STORE_OPERATION(
+                "get_index",
+                "hybrid",
+            )),
+        }
     }
 
     #[trace]
@@ -411,6 +1091,10 @@ impl Store for HybridStore {
             removed_size += self.cold_store.as_ref().unwrap().purge(ctx.clone()).await?;
             info!("Removed data of app:[{}] in cold store", app_id);
         }
+        if ctx.shuffle_id.is_none() {
+            self.app_spill_limiters.remove(app_id);
+        }
+        self.read_cache.invalidate(app_id, ctx.shuffle_id);
         Ok(removed_size)
     }
 
@@ -437,6 +1121,18 @@ impl Store for HybridStore {
         ctx: RequireBufferContext,
     ) -> Result<RequireBufferResponse, WorkerError> {
         let uid = &ctx.uid.clone();
+        if let Some(admission) = &self.spill_debt_admission {
+            let outcome = admission
+                .admit(ctx.size, || {
+                    let snapshot = self.hot_store.memory_snapshot().unwrap();
+                    (snapshot, self.hot_store.in_flight_size())
+                })
+                .instrument_await(format!("waiting for the admission. uid: {:?}", uid))
+                .await;
+            if outcome == AdmissionOutcome::Reject {
+                return Err(WorkerError::NO_ENOUGH_MEMORY_TO_BE_ALLOCATED);
+            }
+        }
         self.hot_store
             .require_buffer(ctx)
             .instrument_await(format!("requiring buffers. uid: {:?}", uid))
@@ -473,9 +1169,65 @@ impl Store for HybridStore {
         unimplemented!()
     }
 
+    fn capabilities(&self) -> StoreCapabilities {
+        let persistent: Vec<StoreCapabilities> = self
+            .warm_store
+            .iter()
+            .chain(self.cold_store.iter())
+            .map(|x| x.capabilities())
+            .collect();
+        StoreCapabilities {
+            supports_paging: true,
+            supports_index: self
+                .warm_store
+                .as_ref()
+                .map_or(false, |x| x.capabilities().supports_index),
+            supports_buffer: true,
+            durable: persistent.iter().any(|x| x.durable),
+            tier: StoreTier::HOT,
+        }
+    }
+
     #[trace]
     async fn spill_insert(&self, _ctx: SpillWritingViewContext) -> Result<(), WorkerError> {
-        todo!()
+        Err(WorkerError::UNSUPPORTED_STORE_OPERATION(
+            "spill_insert",
+            "hybrid",
+        ))
+    }
+}
+
+#[async_trait]
+impl HealthProvider for HybridStore {
+    async fn component_health(&self) -> Vec<ComponentHealth> {
+        let mut components = self.hot_store.component_health().await;
+        if let Some(warm) = &self.warm_store {
+            components.extend(warm.component_health().await);
+        }
+        if let Some(cold) = &self.cold_store {
+            components.extend(cold.component_health().await);
+        }
+        components.extend(self.event_bus.component_health().await);
+
+        // the stuck flushing makes the memory never released
+        let stuck_tasks: Vec<_> = AWAIT_TREE_REGISTRY
+            .stuck_tasks()
+            .into_iter()
+            .filter(|x| x.is_store_flush_task())
+            .map(|x| x.msg)
+            .collect();
+        let status = if stuck_tasks.is_empty() {
+            HealthStatus::Healthy
+        } else {
+            HealthStatus::Unhealthy
+        };
+        components.push(ComponentHealth::new(
+            "store_flush_tasks",
+            status,
+            true,
+            serde_json::json!({ "stuck": stuck_tasks }),
+        ));
+        components
     }
 }
 
@@ -483,21 +1235,39 @@ impl Store for HybridStore {
 mod tests {
     use crate::app::ReadingOptions::MEMORY_LAST_BLOCK_ID_AND_MAX_SIZE;
     use crate::app::{
-        PartitionedUId, ReadingIndexViewContext, ReadingOptions, ReadingViewContext,
+        PartitionedUId, PurgeDataContext, ReadingIndexViewContext, ReadingOptions,
+        ReadingViewContext, RegisterAppContext, ReleaseTicketContext, RequireBufferContext,
         WritingViewContext,
     };
     use crate::config::{
         Config, HybridStoreConfig, LocalfileStoreConfig, MemoryStoreConfig, StorageType,
     };
 
-    use crate::store::hybrid::HybridStore;
+    use crate::error::WorkerError;
+    use crate::health::{ComponentHealth, HealthProvider};
+    use crate::metric::{
+        GAUGE_MEMORY_SPILL_IN_FLIGHT_BYTES_TO, TOTAL_MEMORY_SPILL_BYTES_TO,
+        TOTAL_MEMORY_SPILL_FAILED_TO, TOTAL_MEMORY_SPILL_TO, TOTAL_MEMORY_SPILL_TRIGGERED,
+    };
+    use crate::runtime::manager::RuntimeManager;
+    use crate::store::hybrid::{
+        route_spill, spill_event_requeue_delay, HybridStore, PersistentStore, SpillTarget,
+        MAX_SPILL_EVENT_REQUEUE_INTERVAL, SPILL_EVENT_REQUEUE_INTERVAL,
+    };
+    use crate::store::localfile::LocalFileStore;
+    use crate::store::spill::SpillWritingViewContext;
     use crate::store::ResponseData::Mem;
-    use crate::store::{Block, ResponseData, ResponseDataIndex, Store};
+    use crate::store::{
+        Block, PartitionStat, Persistent, RequireBufferResponse, ResponseData, ResponseDataIndex,
+        SpillConcurrency, Store, StoreCapabilities,
+    };
+    use async_trait::async_trait;
     use bytes::{Buf, Bytes};
 
     use std::any::Any;
-    use std::collections::VecDeque;
+    use std::collections::{HashSet, VecDeque};
 
+    use std::sync::atomic::{AtomicU64, Ordering};
     use std::sync::Arc;
     use std::thread;
 
@@ -744,6 +1514,174 @@ mod tests {
         }
     }
 
+    #[test]
+    fn partition_buffer_max_size_test() -> anyhow::Result<()> {
+        let data = b"hello world!";
+        let data_len = data.len() as u64;
+        let max_size = data_len * 10;
+
+        let temp_dir = tempdir::TempDir::new("partition_buffer_max_size_test").unwrap();
+        let temp_path = temp_dir.path().to_str().unwrap().to_string();
+
+        let mut memory_conf = MemoryStoreConfig::new((data_len * 10000).to_string());
+        memory_conf.partition_buffer_max_size = Some(max_size.to_string());
+        memory_conf.partition_buffer_strict = Some(true);
+        let mut config = Config::default();
+        config.memory_store = Some(memory_conf);
+        config.localfile_store = Some(LocalfileStoreConfig::new(vec![temp_path]));
+        config.hybrid_store = HybridStoreConfig::new(0.8, 0.2, None);
+        config.store_type = StorageType::MEMORY_LOCALFILE;
+
+        let store = Arc::new(HybridStore::from(config, Default::default()));
+        store.clone().start();
+        let runtime = store.runtime_manager.clone();
+
+        let small_uid = PartitionedUId {
+            app_id: "1000".to_string(),
+            shuffle_id: 0,
+            partition_id: 0,
+        };
+        let skewed_uid = PartitionedUId {
+            partition_id: 1,
+            ..small_uid.clone()
+        };
+        let writing_ctx = |uid: &PartitionedUId, block_id: i64| {
+            let block = Block {
+                block_id,
+                length: data_len as i32,
+                uncompress_length: 100,
+                crc: 0,
+                data: Bytes::copy_from_slice(data),
+                task_attempt_id: 0,
+            };
+            WritingViewContext::new(uid.clone(), vec![block], false, data_len)
+        };
+        for block_id in 0..5 {
+            runtime.wait(store.insert(writing_ctx(&small_uid, block_id)))?;
+            store.inc_used(data_len as i64)?;
+        }
+
+        // the writes are rejected until the spill finished, so the footprint stays near the cap
+        for block_id in 0..200 {
+            loop {
+                match runtime.wait(store.insert(writing_ctx(&skewed_uid, block_id))) {
+                    Ok(_) => {
+                        store.inc_used(data_len as i64)?;
+                        break;
+                    }
+                    Err(WorkerError::MEMORY_USAGE_LIMITED_BY_PARTITION_BUFFER(_)) => {
+                        thread::sleep(Duration::from_millis(10));
+                    }
+                    Err(err) => return Err(err.into()),
+                }
+            }
+            let footprint =
+                runtime.wait(store.get_hot_store_memory_partitioned_buffer_size(&skewed_uid))?;
+            assert!(footprint <= max_size + data_len);
+        }
+        assert!(
+            TOTAL_MEMORY_SPILL_TRIGGERED
+                .with_label_values(&["partition_size"])
+                .get()
+                > 0
+        );
+
+        // the small partition is not affected by the skewed one
+        assert_eq!(
+            data_len * 5,
+            runtime.wait(store.get_hot_store_memory_partitioned_buffer_size(&small_uid))?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn merged_read_with_concurrent_flush_test() -> anyhow::Result<()> {
+        let data = b"hello world!";
+        let data_len = data.len() as u64;
+        let block_num = 500;
+
+        let temp_dir = tempdir::TempDir::new("merged_read_with_concurrent_flush_test").unwrap();
+        let temp_path = temp_dir.path().to_str().unwrap().to_string();
+
+        let mut config = Config::default();
+        config.memory_store = Some(MemoryStoreConfig::new((data_len * 10000).to_string()));
+        config.localfile_store = Some(LocalfileStoreConfig::new(vec![temp_path]));
+        config.hybrid_store = HybridStoreConfig::new(0.8, 0.2, None);
+        config.store_type = StorageType::MEMORY_LOCALFILE;
+
+        let store = Arc::new(HybridStore::from(config, Default::default()));
+        store.clone().start();
+        let runtime = store.runtime_manager.clone();
+
+        let uid = PartitionedUId {
+            app_id: "1000".to_string(),
+            shuffle_id: 0,
+            partition_id: 0,
+        };
+
+        // the number of blocks that have been inserted
+        let written = Arc::new(AtomicU64::new(0));
+        let writer = {
+            let store = store.clone();
+            let runtime = runtime.clone();
+            let uid = uid.clone();
+            let written = written.clone();
+            thread::spawn(move || -> anyhow::Result<()> {
+                for block_id in 0..block_num {
+                    let block = Block {
+                        block_id,
+                        length: data_len as i32,
+                        uncompress_length: 100,
+                        crc: 0,
+                        data: Bytes::copy_from_slice(data),
+                        task_attempt_id: 0,
+                    };
+                    let ctx = WritingViewContext::new(uid.clone(), vec![block], false, data_len);
+                    runtime.wait(store.insert(ctx))?;
+                    store.inc_used(data_len as i64)?;
+                    written.fetch_add(1, Ordering::SeqCst);
+                    if block_id % 10 == 0 {
+                        runtime.wait(store.spill_all())?;
+                    }
+                }
+                Ok(())
+            })
+        };
+
+        let check = |expected_min: u64| -> anyhow::Result<u64> {
+            let merged = runtime.wait(store.get_merged(&uid, None))?;
+            let bytes = merged.data.freeze();
+            let mut block_ids = HashSet::new();
+            for segment in &merged.shuffle_data_block_segments {
+                // no duplicate block
+                assert!(block_ids.insert(segment.block_id));
+                let start = segment.offset as usize;
+                let end = start + segment.length as usize;
+                assert_eq!(&data[..], &bytes[start..end]);
+            }
+            assert_eq!(
+                merged.shuffle_data_block_segments.len(),
+                merged.block_sources.len()
+            );
+            // no lost block that has been inserted before reading
+            for block_id in 0..expected_min as i64 {
+                assert!(block_ids.contains(&block_id));
+            }
+            Ok(block_ids.len() as u64)
+        };
+
+        while !writer.is_finished() {
+            check(written.load(Ordering::SeqCst))?;
+        }
+        writer.join().unwrap()?;
+
+        runtime.wait(store.spill_all())?;
+        let read_num = check(block_num as u64)?;
+        assert_eq!(block_num as u64, read_num);
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_localfile_disk_corrupted() {
         // when the local disk is corrupted, the data will be aborted.
@@ -814,4 +1752,235 @@ mod tests {
             }
         }
     }
+
+    // the localfile store disguised as the hdfs to be the cold store
+    struct HdfsLikeStore {
+        inner: LocalFileStore,
+    }
+
+    #[async_trait]
+    impl Store for HdfsLikeStore {
+        fn start(self: Arc<Self>) {}
+
+        async fn insert(&self, ctx: WritingViewContext) -> Result<(), WorkerError> {
+            self.inner.insert(ctx).await
+        }
+
+        async fn get(&self, ctx: ReadingViewContext) -> Result<ResponseData, WorkerError> {
+            self.inner.get(ctx).await
+        }
+
+        async fn get_index(
+            &self,
+            ctx: ReadingIndexViewContext,
+        ) -> Result<ResponseDataIndex, WorkerError> {
+            self.inner.get_index(ctx).await
+        }
+
+        async fn purge(&self, ctx: PurgeDataContext) -> anyhow::Result<i64> {
+            self.inner.purge(ctx).await
+        }
+
+        async fn is_healthy(&self) -> anyhow::Result<bool> {
+            self.inner.is_healthy().await
+        }
+
+        async fn require_buffer(
+            &self,
+            ctx: RequireBufferContext,
+        ) -> Result<RequireBufferResponse, WorkerError> {
+            self.inner.require_buffer(ctx).await
+        }
+
+        async fn release_ticket(&self, ctx: ReleaseTicketContext) -> Result<i64, WorkerError> {
+            self.inner.release_ticket(ctx).await
+        }
+
+        async fn register_app(&self, ctx: RegisterAppContext) -> anyhow::Result<()> {
+            self.inner.register_app(ctx).await
+        }
+
+        async fn name(&self) -> StorageType {
+            StorageType::HDFS
+        }
+
+        fn capabilities(&self) -> StoreCapabilities {
+            self.inner.capabilities()
+        }
+
+        async fn spill_insert(&self, ctx: SpillWritingViewContext) -> Result<(), WorkerError> {
+            self.inner.spill_insert(ctx).await
+        }
+
+        fn partition_stat(&self, uid: &PartitionedUId) -> Option<PartitionStat> {
+            self.inner.partition_stat(uid)
+        }
+    }
+
+    impl Persistent for HdfsLikeStore {
+        fn spill_concurrency(&self) -> SpillConcurrency {
+            self.inner.spill_concurrency()
+        }
+    }
+
+    #[async_trait]
+    impl HealthProvider for HdfsLikeStore {
+        async fn component_health(&self) -> Vec<ComponentHealth> {
+            vec![]
+        }
+    }
+
+    impl PersistentStore for HdfsLikeStore {}
+
+    // the memory store backed by the localfile and the hdfs like cold store
+    fn start_tiered_store(
+        temp_dir: &tempdir::TempDir,
+        cold_threshold: &str,
+        healthy_check_min_disks: i32,
+        runtime_manager: &RuntimeManager,
+    ) -> Arc<HybridStore> {
+        let warm_path = temp_dir.path().join("warm").to_str().unwrap().to_string();
+        let cold_path = temp_dir.path().join("cold").to_str().unwrap().to_string();
+
+        let mut localfile_config = LocalfileStoreConfig::new(vec![warm_path]);
+        localfile_config.healthy_check_min_disks = healthy_check_min_disks;
+
+        let mut config = Config::default();
+        config.memory_store = Some(MemoryStoreConfig::new("1M".to_string()));
+        config.localfile_store = Some(localfile_config);
+        // the spill not smaller than the threshold goes to the cold store
+        config.hybrid_store = HybridStoreConfig::new(0.8, 0.2, None);
+        config.hybrid_store.memory_spill_to_cold_threshold_size = Some(cold_threshold.to_string());
+        config.store_type = StorageType::MEMORY_LOCALFILE;
+
+        let mut store = HybridStore::from(config, runtime_manager.clone());
+        store.cold_store = Some(Box::new(HdfsLikeStore {
+            inner: LocalFileStore::from(
+                LocalfileStoreConfig::new(vec![cold_path]),
+                runtime_manager.clone(),
+            ),
+        }));
+        let store = Arc::new(store);
+        store.clone().start();
+        store
+    }
+
+    #[test]
+    fn test_spill_event_requeue_delay() {
+        let delays = (0..100)
+            .map(|_| spill_event_requeue_delay(0))
+            .collect::<Vec<_>>();
+        assert!(delays.iter().all(|d| *d <= SPILL_EVENT_REQUEUE_INTERVAL));
+        // jittered to not republish at once
+        assert!(delays.iter().any(|d| *d != delays[0]));
+
+        for attempt in [5, 16, u32::MAX] {
+            assert!(spill_event_requeue_delay(attempt) <= MAX_SPILL_EVENT_REQUEUE_INTERVAL);
+        }
+    }
+
+    #[test]
+    fn test_route_spill() {
+        assert_eq!(SpillTarget::Cold, route_spill(100, Some(100), true, 0).0);
+        assert_eq!(SpillTarget::Warm, route_spill(99, Some(100), true, 0).0);
+        assert_eq!(SpillTarget::Warm, route_spill(u64::MAX, None, true, 0).0);
+        // the unavailable warm store or the retry overrides the threshold
+        assert_eq!(
+            (SpillTarget::Cold, "warm store unavailable"),
+            route_spill(1, Some(100), false, 0)
+        );
+        assert_eq!(
+            (SpillTarget::Cold, "retry fallback"),
+            route_spill(1, None, true, 1)
+        );
+    }
+
+    #[test]
+    fn test_spill_routing_by_cold_threshold() -> anyhow::Result<()> {
+        let temp_dir = tempdir::TempDir::new("test_spill_routing_by_cold_threshold")?;
+        let runtime_manager: RuntimeManager = Default::default();
+        let store = start_tiered_store(&temp_dir, "120", 0, &runtime_manager);
+
+        let data = b"hello world!";
+        let small = PartitionedUId::from("app".to_string(), 0, 0);
+        let huge = PartitionedUId::from("app".to_string(), 0, 1);
+        runtime_manager.wait(write_some_data(store.clone(), small.clone(), 12, data, 1));
+        runtime_manager.wait(write_some_data(store.clone(), huge.clone(), 12, data, 10));
+        runtime_manager.wait(store.spill_all())?;
+        awaitility::at_most(Duration::from_secs(5))
+            .until(|| store.memory_spill_event_num().unwrap() == 0);
+
+        let stat = runtime_manager.wait(store.partition_storage_stat(&small));
+        assert_eq!(12, stat.localfile.unwrap().size);
+        assert!(stat.hdfs.is_none());
+        let stat = runtime_manager.wait(store.partition_storage_stat(&huge));
+        assert!(stat.localfile.is_none());
+        assert_eq!(120, stat.hdfs.unwrap().size);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_spill_routing_with_unhealthy_localfile() -> anyhow::Result<()> {
+        let temp_dir = tempdir::TempDir::new("test_spill_routing_with_unhealthy_localfile")?;
+        let runtime_manager: RuntimeManager = Default::default();
+        // the only disk is less than the required healthy disks
+        let store = start_tiered_store(&temp_dir, "120", 1, &runtime_manager);
+
+        let data = b"hello world!";
+        let small = PartitionedUId::from("app".to_string(), 0, 0);
+        runtime_manager.wait(write_some_data(store.clone(), small.clone(), 12, data, 1));
+        runtime_manager.wait(store.spill_all())?;
+        awaitility::at_most(Duration::from_secs(5))
+            .until(|| store.memory_spill_event_num().unwrap() == 0);
+
+        let stat = runtime_manager.wait(store.partition_storage_stat(&small));
+        assert!(stat.localfile.is_none());
+        assert_eq!(12, stat.hdfs.unwrap().size);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_spill_metrics_by_destination() -> anyhow::Result<()> {
+        let temp_dir = tempdir::TempDir::new("test_spill_metrics_by_destination")?;
+        let runtime_manager: RuntimeManager = Default::default();
+        let store = start_tiered_store(&temp_dir, "100", 0, &runtime_manager);
+
+        let spilled_bytes = |to: &str| TOTAL_MEMORY_SPILL_BYTES_TO.with_label_values(&[to]).get();
+        let localfile_bytes = spilled_bytes("localfile");
+        let hdfs_bytes = spilled_bytes("hdfs");
+        let hdfs_spills = TOTAL_MEMORY_SPILL_TO.with_label_values(&["hdfs"]).get();
+
+        let data = b"hello world!";
+        let small = PartitionedUId::from("app".to_string(), 0, 0);
+        let huge = PartitionedUId::from("app".to_string(), 0, 1);
+        runtime_manager.wait(write_some_data(store.clone(), small, 12, data, 1));
+        runtime_manager.wait(write_some_data(store.clone(), huge, 12, data, 20));
+        runtime_manager.wait(store.spill_all())?;
+        awaitility::at_most(Duration::from_secs(5))
+            .until(|| store.memory_spill_event_num().unwrap() == 0);
+
+        // the localfile label may be shared with the other cases
+        assert!(spilled_bytes("localfile") - localfile_bytes >= 12);
+        assert_eq!(240, spilled_bytes("hdfs") - hdfs_bytes);
+        assert_eq!(
+            1,
+            TOTAL_MEMORY_SPILL_TO.with_label_values(&["hdfs"]).get() - hdfs_spills
+        );
+        assert_eq!(
+            0,
+            GAUGE_MEMORY_SPILL_IN_FLIGHT_BYTES_TO
+                .with_label_values(&["hdfs"])
+                .get()
+        );
+        assert_eq!(
+            0,
+            TOTAL_MEMORY_SPILL_FAILED_TO
+                .with_label_values(&["hdfs"])
+                .get()
+        );
+
+        Ok(())
+    }
 }
@@ -0,0 +1,309 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::app::PartitionedUId;
+use crate::composed_bytes::ComposedBytes;
+use crate::config::LocalfileCompactionConfig;
+use crate::metric::{
+    TOTAL_LOCALFILE_COMPACTED_BYTES, TOTAL_LOCALFILE_COMPACTED_PARTITIONS,
+    TOTAL_LOCALFILE_COMPACTION_SKIPPED,
+};
+use crate::store::local::index_cache::{CachedIndex, IndexCache};
+use crate::store::localfile::LockedObj;
+use crate::util;
+use anyhow::Result;
+use await_tree::InstrumentAwait;
+use bytes::{BufMut, Bytes, BytesMut};
+use dashmap::DashMap;
+use log::{info, warn};
+use parking_lot::RwLock as SyncRwLock;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+
+const COMPACTING_FILE_SUFFIX: &str = ".compacting";
+// the rewritten data is appended by this batch size
+const COMPACTION_WRITE_BATCH_SIZE: usize = 8 * 1024 * 1024;
+
+/// Notified with the compacted partition, whose data offsets have been changed.
+pub type CompactionListener = Box<dyn Fn(&PartitionedUId) + Send + Sync>;
+
+pub type PartitionLocks = Arc<DashMap<String, Arc<RwLock<LockedObj>>>>;
+
+#[derive(Debug, Clone)]
+pub struct CompactionOptions {
+    pub segment_threshold: u64,
+    pub idle_duration: Duration,
+    pub interval: Duration,
+    // bytes per second
+    pub io_rate_limit: u64,
+}
+
+impl CompactionOptions {
+    pub fn from(config: &LocalfileCompactionConfig) -> Result<Self> {
+        Ok(Self {
+            segment_threshold: config.segment_threshold,
+            idle_duration: config.idle_duration()?,
+            interval: config.interval()?,
+            io_rate_limit: config.io_rate_limit()?,
+        })
+    }
+}
+
+/// Pacing the consumed bytes to the rate since created.
+struct IoRateLimiter {
+    rate: u64,
+    started: Instant,
+    consumed: u64,
+}
+
+impl IoRateLimiter {
+    fn new(rate: u64) -> Self {
+        Self {
+            rate: rate.max(1),
+            started: Instant::now(),
+            consumed: 0,
+        }
+    }
+
+    async fn acquire(&mut self, bytes: u64) {
+        self.consumed += bytes;
+        let expected = Duration::from_secs_f64(self.consumed as f64 / self.rate as f64);
+        let elapsed = self.started.elapsed();
+        if expected > elapsed {
+            tokio::time::sleep(expected - elapsed).await;
+        }
+    }
+}
+
+/// Rewriting the data file of the idle partition with too many flushed segments into the
+/// single one sorted by the task attempt, and swapping the index together.
+///
+/// The data is copied under the partition read lock, so the writing is blocked while
+/// the reading goes on. Then the files are renamed under the write lock, the reader sees
+/// either the old or the new layout.
+pub struct LocalFileCompactor {
+    options: CompactionOptions,
+    partition_locks: PartitionLocks,
+    index_cache: Arc<IndexCache>,
+    listeners: SyncRwLock<Vec<CompactionListener>>,
+}
+
+impl LocalFileCompactor {
+    pub fn new(
+        options: CompactionOptions,
+        partition_locks: PartitionLocks,
+        index_cache: Arc<IndexCache>,
+    ) -> Self {
+        Self {
+            options,
+            partition_locks,
+            index_cache,
+            listeners: Default::default(),
+        }
+    }
+
+    pub fn interval(&self) -> Duration {
+        self.options.interval
+    }
+
+    pub fn add_listener(&self, listener: CompactionListener) {
+        self.listeners.write().push(listener);
+    }
+
+    fn is_candidate(&self, locked_obj: &LockedObj, now: u64) -> bool {
+        locked_obj.segments.load(Ordering::SeqCst) > self.options.segment_threshold
+            && now.saturating_sub(locked_obj.last_accessed.load(Ordering::SeqCst))
+                >= self.options.idle_duration.as_millis() as u64
+    }
+
+    /// Returns the number of the compacted partitions.
+    pub async fn compact(&self) -> usize {
+        let now = util::now_timestamp_as_millis() as u64;
+        let candidates: Vec<(String, Arc<RwLock<LockedObj>>)> = self
+            .partition_locks
+            .iter()
+            .filter(|entry| match entry.value().try_read() {
+                Ok(locked_obj) => self.is_candidate(&locked_obj, now),
+                // being written
+                _ => false,
+            })
+            .map(|entry| (entry.key().to_string(), entry.value().clone()))
+            .collect();
+
+        let mut limiter = IoRateLimiter::new(self.options.io_rate_limit);
+        let mut compacted = 0;
+        for (data_file_path, locked_obj) in candidates {
+            match self
+                .compact_partition(&data_file_path, locked_obj, &mut limiter)
+                .await
+            {
+                Ok(true) => compacted += 1,
+                Ok(false) => {}
+                Err(e) => warn!(
+                    "Errors on compacting the partition: {}. err: {:#?}",
+                    &data_file_path, e
+                ),
+            }
+        }
+        compacted
+    }
+
+    fn is_registered(&self, data_file_path: &str, locked_obj: &Arc<RwLock<LockedObj>>) -> bool {
+        match self.partition_locks.get(data_file_path) {
+            Some(current) => Arc::ptr_eq(current.value(), locked_obj),
+            _ => false,
+        }
+    }
+
+    async fn compact_partition(
+        &self,
+        data_file_path: &str,
+        locked_obj: Arc<RwLock<LockedObj>>,
+        limiter: &mut IoRateLimiter,
+    ) -> Result<bool> {
+        let index_file_path = format!("{}.index", data_file_path.trim_end_matches(".data"));
+        let compacting_data_path = format!("{}{}", data_file_path, COMPACTING_FILE_SUFFIX);
+        let compacting_index_path = format!("{}{}", &index_file_path, COMPACTING_FILE_SUFFIX);
+
+        let (disk, pointer, accesses) = {
+            let locked = locked_obj
+                .read()
+                .instrument_await("waiting the localfile partition read lock...")
+                .await;
+            let disk = locked.disk.clone();
+            if disk.is_corrupted()? || disk.is_under_pressure() {
+                TOTAL_LOCALFILE_COMPACTION_SKIPPED.inc();
+                return Ok(false);
+            }
+            let pointer = locked.pointer.load(Ordering::SeqCst);
+            let accesses = locked.accesses.load(Ordering::SeqCst);
+
+            let index = CachedIndex::from(disk.read(&index_file_path, 0, None).await?);
+            let indexed_len: i64 = index.segments.iter().map(|x| x.length as i64).sum();
+            if indexed_len != pointer {
+                warn!(
+                    "Skip compacting the partition: {} whose indexed len: {} is not matched with the data len: {}",
+                    data_file_path, indexed_len, pointer
+                );
+                return Ok(false);
+            }
+            let mut segments = index.segments.clone();
+            // stable, the order within the task attempt is kept
+            segments.sort_by_key(|x| x.task_attempt_id);
+            if segments
+                .iter()
+                .zip(index.segments.iter())
+                .all(|(x, y)| x.offset == y.offset)
+            {
+                locked.segments.store(1, Ordering::SeqCst);
+                return Ok(false);
+            }
+
+            disk.delete(&compacting_data_path).await?;
+            disk.delete(&compacting_index_path).await?;
+
+            let mut index_bytes = BytesMut::with_capacity(index.index_data.len());
+            let mut batch: Vec<Bytes> = vec![];
+            let mut batch_size = 0;
+            let mut next_offset = 0i64;
+            for segment in &segments {
+                limiter.acquire(segment.length as u64).await;
+                let data = disk
+                    .read(data_file_path, segment.offset, Some(segment.length as i64))
+                    .instrument_await("reading the segment to compact")
+                    .await?;
+
+                index_bytes.put_i64(next_offset);
+                index_bytes.put_i32(segment.length);
+                index_bytes.put_i32(segment.uncompress_length);
+                index_bytes.put_i64(segment.crc);
+                index_bytes.put_i64(segment.block_id);
+                index_bytes.put_i64(segment.task_attempt_id);
+                next_offset += segment.length as i64;
+
+                batch_size += data.len();
+                batch.push(data);
+                if batch_size >= COMPACTION_WRITE_BATCH_SIZE {
+                    limiter.acquire(batch_size as u64).await;
+                    let composed = ComposedBytes::from(std::mem::take(&mut batch), batch_size);
+                    disk.append(composed, &compacting_data_path).await?;
+                    batch_size = 0;
+                }
+            }
+            if batch_size > 0 {
+                limiter.acquire(batch_size as u64).await;
+                disk.append(
+                    ComposedBytes::from(batch, batch_size),
+                    &compacting_data_path,
+                )
+                .await?;
+            }
+            disk.append(index_bytes.freeze(), &compacting_index_path)
+                .await?;
+            (disk, pointer, accesses)
+        };
+
+        let locked = locked_obj
+            .write()
+            .instrument_await("waiting the localfile partition write lock...")
+            .await;
+        // the partition has been written or purged meanwhile, or read with the old index
+        // whose offsets are stale after swapping
+        if locked.pointer.load(Ordering::SeqCst) != pointer
+            || locked.accesses.load(Ordering::SeqCst) != accesses
+            || !self.is_registered(data_file_path, &locked_obj)
+        {
+            disk.delete(&compacting_data_path).await?;
+            disk.delete(&compacting_index_path).await?;
+            return Ok(false);
+        }
+        disk.rename(&compacting_data_path, data_file_path).await?;
+        disk.rename(&compacting_index_path, &index_file_path)
+            .await?;
+        self.index_cache.invalidate(&index_file_path);
+        locked.segments.store(1, Ordering::SeqCst);
+
+        for listener in self.listeners.read().iter() {
+            listener(&locked.uid);
+        }
+        TOTAL_LOCALFILE_COMPACTED_PARTITIONS.inc();
+        TOTAL_LOCALFILE_COMPACTED_BYTES.inc_by(pointer as u64);
+        info!(
+            "Compacted the partition: {} with {} bytes",
+            data_file_path, pointer
+        );
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::store::local::compaction::IoRateLimiter;
+    use std::time::{Duration, Instant};
+
+    #[tokio::test]
+    async fn test_io_rate_limiter() {
+        let mut limiter = IoRateLimiter::new(1000);
+        let started = Instant::now();
+        limiter.acquire(100).await;
+        limiter.acquire(100).await;
+        assert!(started.elapsed() >= Duration::from_millis(200));
+        assert!(started.elapsed() < Duration::from_secs(1));
+    }
+}
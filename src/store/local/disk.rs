@@ -16,25 +16,34 @@
 // under the License.
 
 use crate::await_tree::AWAIT_TREE_REGISTRY;
+use crate::config::FsyncPolicy;
+use crate::error::WorkerError;
+use crate::health::{ComponentHealth, HealthStatus};
 use crate::metric::{
     GAUGE_LOCAL_DISK_CAPACITY, GAUGE_LOCAL_DISK_IS_HEALTHY, GAUGE_LOCAL_DISK_USED,
     LOCALFILE_DISK_APPEND_OPERATION_DURATION, LOCALFILE_DISK_DELETE_OPERATION_DURATION,
     LOCALFILE_DISK_READ_OPERATION_DURATION, LOCALFILE_DISK_STAT_OPERATION_DURATION,
     TOTAL_LOCAL_DISK_APPEND_OPERATION_BYTES_COUNTER, TOTAL_LOCAL_DISK_APPEND_OPERATION_COUNTER,
+    TOTAL_LOCAL_DISK_READ_TIMEOUT_COUNTER,
 };
 use crate::runtime::manager::RuntimeManager;
-use crate::store::BytesWrapper;
+use crate::runtime::RuntimeRef;
+use crate::store::{BytesWrapper, SpillConcurrency};
 use anyhow::{anyhow, Result};
 use await_tree::InstrumentAwait;
-use bytes::{Bytes, BytesMut};
+use bytes::Bytes;
 use log::{debug, error, info, warn};
 use opendal::services::Fs;
-use opendal::{Metadata, Operator};
-use std::io::{Read, Seek, SeekFrom, Write};
+use opendal::{Metadata, Operator, Writer};
+use parking_lot::Mutex;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::os::unix::fs::FileExt;
+use std::path::Path;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
-use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt, BufReader, BufWriter};
+use tokio::io::{AsyncWriteExt, BufWriter};
 use tokio::sync::Semaphore;
 
 pub struct LocalDiskConfig {
@@ -42,6 +51,8 @@ pub struct LocalDiskConfig {
     pub(crate) low_watermark: f32,
     pub(crate) max_concurrency: i32,
     pub(crate) write_buf_capacity: u64,
+    pub(crate) fsync_policy: FsyncPolicy,
+    pub(crate) read_timeout: Duration,
 }
 
 impl LocalDiskConfig {
@@ -51,6 +62,8 @@ impl LocalDiskConfig {
             low_watermark: 0.6,
             max_concurrency: 20,
             write_buf_capacity: 1024 * 1024,
+            fsync_policy: FsyncPolicy::Never,
+            read_timeout: Duration::from_secs(30),
         }
     }
 }
@@ -62,6 +75,36 @@ impl Default for LocalDiskConfig {
             low_watermark: 0.6,
             max_concurrency: 40,
             write_buf_capacity: 1024 * 1024,
+            fsync_policy: FsyncPolicy::Never,
+            read_timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+/// The blocking reading of the file, which is invoked in the blocking thread. The buffer
+/// is allocated and owned by the reading itself, so the one abandoned by the timeout
+/// never writes into the memory of the caller.
+pub trait FileReader: Send + Sync {
+    fn read(&self, path: &Path, offset: u64, length: Option<u64>) -> std::io::Result<Bytes>;
+}
+
+pub struct PreadFileReader;
+
+impl FileReader for PreadFileReader {
+    fn read(&self, path: &Path, offset: u64, length: Option<u64>) -> std::io::Result<Bytes> {
+        let mut file = File::open(path)?;
+        match length {
+            Some(length) => {
+                let mut buf = vec![0u8; length as usize];
+                file.read_exact_at(&mut buf, offset)?;
+                Ok(Bytes::from(buf))
+            }
+            _ => {
+                let mut buf = vec![];
+                file.seek(SeekFrom::Start(offset))?;
+                file.read_to_end(&mut buf)?;
+                Ok(Bytes::from(buf))
+            }
         }
     }
 }
@@ -72,7 +115,12 @@ pub struct LocalDisk {
     concurrency_limiter: Semaphore,
     is_corrupted: AtomicBool,
     is_healthy: AtomicBool,
+    // the read has timed out, which is cleared once the next probe succeeds
+    is_suspect: AtomicBool,
+    last_probe_error: Mutex<Option<String>>,
     config: LocalDiskConfig,
+    reader: Arc<dyn FileReader>,
+    read_runtime: RuntimeRef,
 
     capacity: u64,
 
@@ -80,10 +128,26 @@ pub struct LocalDisk {
 }
 
 impl LocalDisk {
+    pub fn spill_concurrency(&self) -> SpillConcurrency {
+        SpillConcurrency {
+            max_concurrency: self.config.max_concurrency as usize,
+            available_permits: self.concurrency_limiter.available_permits(),
+        }
+    }
+
     pub fn new(
         root: String,
         config: LocalDiskConfig,
         runtime_manager: RuntimeManager,
+    ) -> Arc<Self> {
+        LocalDisk::with_reader(root, config, runtime_manager, Arc::new(PreadFileReader))
+    }
+
+    pub fn with_reader(
+        root: String,
+        config: LocalDiskConfig,
+        runtime_manager: RuntimeManager,
+        reader: Arc<dyn FileReader>,
     ) -> Arc<Self> {
         let mut builder = Fs::default();
         builder.root(&root);
@@ -99,7 +163,11 @@ impl LocalDisk {
             concurrency_limiter: Semaphore::new(config.max_concurrency as usize),
             is_corrupted: AtomicBool::new(false),
             is_healthy: AtomicBool::new(true),
+            is_suspect: AtomicBool::new(false),
+            last_probe_error: Mutex::new(None),
             config,
+            reader,
+            read_runtime: runtime_manager.read_runtime.clone(),
             capacity: disk_capacity,
             write_buf_capacity,
         };
@@ -108,9 +176,9 @@ impl LocalDisk {
         let runtime = runtime_manager.default_runtime.clone();
         let cloned = instance.clone();
         let await_tree_registry = AWAIT_TREE_REGISTRY.clone();
-        runtime.spawn(async move {
+        runtime.spawn_guarded("disk_checker", async move {
             let await_root = await_tree_registry
-                .register(format!("Disk healthy check: {}", &cloned.root))
+                .register_long_running(format!("Disk healthy check: {}", &cloned.root))
                 .await;
             info!("Starting the disk healthy check, root: {}", &cloned.root);
             await_root
@@ -161,7 +229,8 @@ impl LocalDisk {
             let check_succeed: Result<()> = LocalDisk::write_read_check(local_disk.clone())
                 .instrument_await("write+read checking")
                 .await;
-            if check_succeed.is_err() {
+            if let Err(e) = &check_succeed {
+                local_disk.set_last_probe_error(Some(e.to_string()));
                 local_disk.mark_corrupted();
                 GAUGE_LOCAL_DISK_IS_HEALTHY
                     .with_label_values(&[root_ref])
@@ -175,7 +244,8 @@ impl LocalDisk {
             // get the disk used ratio.
             let disk_capacity = local_disk.capacity;
             let disk_available = Self::get_disk_available(root_ref);
-            if disk_available.is_err() {
+            if let Err(e) = &disk_available {
+                local_disk.set_last_probe_error(Some(e.to_string()));
                 error!(
                     "Errors on getting the available of the local disk. err: {:?}",
                     disk_available.err()
@@ -183,6 +253,12 @@ impl LocalDisk {
                 continue;
             }
             let disk_available = disk_available.unwrap();
+            if check_succeed.is_ok() {
+                local_disk.set_last_probe_error(None);
+                if local_disk.is_suspect.swap(false, Ordering::SeqCst) {
+                    info!("Disk={} is not suspect after probing.", &local_disk.root);
+                }
+            }
             let used_ratio = 1.0 - (disk_available as f64 / disk_capacity as f64);
 
             GAUGE_LOCAL_DISK_USED
@@ -245,16 +321,22 @@ impl LocalDisk {
             .with_label_values(&[self.root.as_str()])
             .start_timer();
 
-        let writer = self
-            .operator
-            .writer_with(path)
-            .append(true)
-            .instrument_await("with append options")
-            .await?;
-
-        // todo: the capacity should be optimized.
-        let mut writer = BufWriter::with_capacity(self.write_buf_capacity as usize, writer);
+        let fsync_policy = self.config.fsync_policy;
+        if fsync_policy == FsyncPolicy::Always {
+            // every buffer is written by its own writer, which is synced on closing
+            for x in data.always_composed().iter() {
+                let mut writer = self.append_writer(path).await?;
+                writer
+                    .write_all(&x)
+                    .instrument_await("writing bytes")
+                    .await?;
+                writer.shutdown().instrument_await("writer syncing").await?;
+            }
+            timer.observe_duration();
+            return Ok(());
+        }
 
+        let mut writer = self.append_writer(path).await?;
         for x in data.always_composed().iter() {
             // we must use the write_all to ensure the buffer consumed by the OS.
             // Please see the detail: https://doc.rust-lang.org/std/io/trait.Write.html#method.write_all
@@ -264,11 +346,29 @@ impl LocalDisk {
                 .await?;
         }
         writer.flush().instrument_await("writer flushing").await?;
+        // closing the writer makes the fs backend fsync the file
+        if fsync_policy == FsyncPolicy::OnClose {
+            writer.shutdown().instrument_await("writer syncing").await?;
+        }
         timer.observe_duration();
 
         Ok(())
     }
 
+    async fn append_writer(&self, path: &str) -> Result<BufWriter<Writer>> {
+        let writer = self
+            .operator
+            .writer_with(path)
+            .append(true)
+            .instrument_await("with append options")
+            .await?;
+        // todo: the capacity should be optimized.
+        Ok(BufWriter::with_capacity(
+            self.write_buf_capacity as usize,
+            writer,
+        ))
+    }
+
     pub async fn stat(&self, path: &str) -> Result<FileStat> {
         let timer = LOCALFILE_DISK_STAT_OPERATION_DURATION
             .with_label_values(&[self.root.as_str()])
@@ -278,35 +378,54 @@ impl LocalDisk {
         return Ok(FileStat::from(meta));
     }
 
-    pub async fn read(&self, path: &str, offset: i64, length: Option<i64>) -> Result<Bytes> {
+    /// Read the whole file if the length is none. The blocking reading hung by the failing
+    /// disk is abandoned after the read timeout, and the disk is marked as suspect.
+    pub async fn read(
+        &self,
+        path: &str,
+        offset: i64,
+        length: Option<i64>,
+    ) -> Result<Bytes, WorkerError> {
         let timer = LOCALFILE_DISK_READ_OPERATION_DURATION
             .with_label_values(&[self.root.as_str()])
             .start_timer();
 
-        if length.is_none() {
-            return Ok(Bytes::from(self.operator.read(path).await?));
-        }
-        let length = length.unwrap() as usize;
-
-        let reader = self.operator.reader(path).await?;
-
-        // todo: as the 8KB, but this could be optimized
-        let mut reader = BufReader::with_capacity(8 * 1024, reader);
-        reader
-            .seek(SeekFrom::Start(offset as u64))
-            .instrument_await("seeking")
-            .await?;
-
-        let mut bytes_buffer = BytesMut::with_capacity(length);
-        unsafe {
-            bytes_buffer.set_len(length);
-        }
-        reader.read_exact(&mut bytes_buffer).await?;
-        let bytes = bytes_buffer.freeze();
+        let reader = self.reader.clone();
+        let file_path = Path::new(&self.root).join(path);
+        let handle = self.read_runtime.spawn_blocking(move || {
+            reader.read(&file_path, offset as u64, length.map(|x| x as u64))
+        });
+        let timeout = self.config.read_timeout;
+        let bytes = match tokio::time::timeout(timeout, handle)
+            .instrument_await("reading in the blocking thread")
+            .await
+        {
+            Ok(read_result) => read_result??,
+            Err(_) => {
+                self.on_read_timeout(path);
+                return Err(WorkerError::DISK_TIMEOUT(self.root.to_string(), timeout));
+            }
+        };
         timer.observe_duration();
         Ok(bytes)
     }
 
+    fn on_read_timeout(&self, path: &str) {
+        TOTAL_LOCAL_DISK_READ_TIMEOUT_COUNTER
+            .with_label_values(&[self.root.as_str()])
+            .inc();
+        warn!(
+            "Reading the file: {} of disk={} timed out, marking it as suspect.",
+            path, &self.root
+        );
+        self.mark_suspect();
+    }
+
+    pub async fn rename(&self, from: &str, to: &str) -> Result<()> {
+        self.operator.rename(from, to).await?;
+        Ok(())
+    }
+
     pub async fn delete(&self, path: &str) -> Result<()> {
         let timer = LOCALFILE_DISK_DELETE_OPERATION_DURATION
             .with_label_values(&[self.root.as_str()])
@@ -328,6 +447,14 @@ impl LocalDisk {
         self.is_healthy.store(true, Ordering::SeqCst);
     }
 
+    pub fn mark_suspect(&self) {
+        self.is_suspect.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_suspect(&self) -> bool {
+        self.is_suspect.load(Ordering::SeqCst)
+    }
+
     pub fn is_corrupted(&self) -> Result<bool> {
         Ok(self.is_corrupted.load(Ordering::SeqCst))
     }
@@ -336,6 +463,59 @@ impl LocalDisk {
         Ok(self.is_healthy.load(Ordering::SeqCst))
     }
 
+    /// Probed on calling, rather than the result of the periodic check.
+    pub fn used_ratio(&self) -> Option<f64> {
+        Self::get_disk_used_ratio(&self.root, self.capacity).ok()
+    }
+
+    /// The ratio of the occupied writing permits, which reflects the queued IO depth.
+    pub fn queue_ratio(&self) -> f64 {
+        let max_concurrency = self.config.max_concurrency.max(1) as f64;
+        let occupied = max_concurrency - self.concurrency_limiter.available_permits() as f64;
+        (occupied / max_concurrency).clamp(0.0, 1.0)
+    }
+
+    /// The usage has been above the low watermark, the optional IO like compaction should be skipped.
+    pub fn is_under_pressure(&self) -> bool {
+        !self.is_healthy.load(Ordering::SeqCst)
+            || self
+                .used_ratio()
+                .map_or(true, |x| x >= self.config.low_watermark as f64)
+    }
+
+    fn set_last_probe_error(&self, error: Option<String>) {
+        *self.last_probe_error.lock() = error;
+    }
+
+    pub fn component_health(&self) -> ComponentHealth {
+        let corrupted = self.is_corrupted.load(Ordering::SeqCst);
+        let healthy = self.is_healthy.load(Ordering::SeqCst);
+        let suspect = self.is_suspect();
+        let status = if corrupted {
+            HealthStatus::Unhealthy
+        } else if !healthy || suspect {
+            HealthStatus::Degraded
+        } else {
+            HealthStatus::Healthy
+        };
+        let used_ratio = Self::get_disk_used_ratio(&self.root, self.capacity).ok();
+        let available = Self::get_disk_available(&self.root).ok();
+        ComponentHealth::new(
+            format!("disk:{}", &self.root),
+            status,
+            false,
+            serde_json::json!({
+                "healthy": healthy && !corrupted,
+                "corrupted": corrupted,
+                "suspect": suspect,
+                "used_ratio": used_ratio,
+                "capacity": self.capacity,
+                "available": available,
+                "last_probe_error": self.last_probe_error.lock().clone(),
+            }),
+        )
+    }
+
     fn get_disk_used_ratio(root: &str, capacity: u64) -> Result<f64> {
         // Get the total and available space in bytes
         let available_space = fs2::available_space(root)?;
@@ -364,12 +544,76 @@ impl From<Metadata> for FileStat {
 
 #[cfg(test)]
 mod tests {
+    use crate::composed_bytes::ComposedBytes;
+    use crate::config::FsyncPolicy;
+    use crate::error::WorkerError;
+    use crate::health::HealthStatus;
+    use crate::metric::TOTAL_LOCAL_DISK_READ_TIMEOUT_COUNTER;
     use crate::runtime::manager::RuntimeManager;
-    use crate::store::local::disk::{LocalDisk, LocalDiskConfig};
+    use crate::store::local::disk::{FileReader, LocalDisk, LocalDiskConfig};
     use bytes::Bytes;
     use opendal::Metadata;
+    use std::path::Path;
+    use std::sync::Arc;
     use std::time::Duration;
 
+    struct SleepyReader(Duration);
+
+    impl FileReader for SleepyReader {
+        fn read(&self, _: &Path, _: u64, length: Option<u64>) -> std::io::Result<Bytes> {
+            std::thread::sleep(self.0);
+            Ok(Bytes::from(vec![0u8; length.unwrap_or(0) as usize]))
+        }
+    }
+
+    #[test]
+    fn test_read_timeout() {
+        let temp_dir = tempdir::TempDir::new("test_read_timeout").unwrap();
+        let temp_path = temp_dir.path().to_str().unwrap().to_string();
+
+        let runtime: RuntimeManager = Default::default();
+        let config = LocalDiskConfig {
+            read_timeout: Duration::from_millis(100),
+            ..LocalDiskConfig::create_mocked_config()
+        };
+        let local_disk = LocalDisk::with_reader(
+            temp_path.clone(),
+            config,
+            runtime.clone(),
+            Arc::new(SleepyReader(Duration::from_millis(500))),
+        );
+        let timeouts = TOTAL_LOCAL_DISK_READ_TIMEOUT_COUNTER
+            .with_label_values(&[&temp_path])
+            .get();
+
+        match runtime.wait(local_disk.read("a/b", 0, Some(10))) {
+            Err(WorkerError::DISK_TIMEOUT(root, timeout)) => {
+                assert_eq!(temp_path, root);
+                assert_eq!(Duration::from_millis(100), timeout);
+            }
+            _ => panic!(),
+        }
+        assert!(local_disk.is_suspect());
+        assert_eq!(HealthStatus::Degraded, local_disk.component_health().status);
+        assert_eq!(
+            timeouts + 1,
+            TOTAL_LOCAL_DISK_READ_TIMEOUT_COUNTER
+                .with_label_values(&[&temp_path])
+                .get()
+        );
+
+        // the reading within the timeout
+        let local_disk = LocalDisk::with_reader(
+            temp_path.clone(),
+            LocalDiskConfig::create_mocked_config(),
+            runtime.clone(),
+            Arc::new(SleepyReader(Duration::from_millis(10))),
+        );
+        let data = runtime.wait(local_disk.read("a/b", 0, Some(10))).unwrap();
+        assert_eq!(10, data.len());
+        assert!(!local_disk.is_suspect());
+    }
+
     #[test]
     fn test_local_disk_delete_operation() {
         let temp_dir = tempdir::TempDir::new("test_local_disk_delete_operation-dir").unwrap();
@@ -416,6 +660,38 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_append_with_fsync_policy() {
+        let temp_dir = tempdir::TempDir::new("test_append_with_fsync_policy").unwrap();
+        let temp_path = temp_dir.path().to_str().unwrap().to_string();
+
+        let runtime: RuntimeManager = Default::default();
+        for policy in [
+            FsyncPolicy::Never,
+            FsyncPolicy::OnClose,
+            FsyncPolicy::Always,
+        ] {
+            let config = LocalDiskConfig {
+                fsync_policy: policy,
+                ..LocalDiskConfig::default()
+            };
+            let local_disk = LocalDisk::new(temp_path.clone(), config, runtime.clone());
+            let path = format!("{:?}", policy);
+
+            let data = ComposedBytes::from(
+                vec![Bytes::from_static(b"hello"), Bytes::from_static(b" world")],
+                11,
+            );
+            runtime.wait(local_disk.append(data, &path)).unwrap();
+            runtime
+                .wait(local_disk.append(Bytes::from_static(b"!"), &path))
+                .unwrap();
+
+            let read = runtime.wait(local_disk.read(&path, 0, None)).unwrap();
+            assert_eq!(Bytes::from_static(b"hello world!"), read);
+        }
+    }
+
     #[test]
     fn local_disk_corruption_healthy_check() {
         let temp_dir = tempdir::TempDir::new("test_directory").unwrap();
@@ -0,0 +1,256 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::metric::{TOTAL_LOCALFILE_INDEX_CACHE_HIT, TOTAL_LOCALFILE_INDEX_CACHE_MISS};
+use crate::store::DataSegment;
+use anyhow::Result;
+use bytes::{Buf, Bytes, BytesMut};
+use hashlink::LruCache;
+use parking_lot::Mutex;
+use std::future::Future;
+use std::sync::Arc;
+
+// offset(8) + length(4) + uncompress_length(4) + crc(8) + block_id(8) + task_attempt_id(8)
+pub const INDEX_ENTRY_LEN: u64 = 40;
+
+/// The parsed view of the append-only index file. Only the complete entries are cached,
+/// so the cached length is always aligned with the entry.
+#[derive(Default, Debug)]
+pub struct CachedIndex {
+    pub index_data: Bytes,
+    pub segments: Vec<DataSegment>,
+}
+
+impl From<Bytes> for CachedIndex {
+    fn from(index_data: Bytes) -> Self {
+        CachedIndex::default().append(index_data)
+    }
+}
+
+impl CachedIndex {
+    fn len(&self) -> u64 {
+        self.index_data.len() as u64
+    }
+
+    fn append(&self, delta: Bytes) -> CachedIndex {
+        let mut index_data = BytesMut::with_capacity(self.index_data.len() + delta.len());
+        index_data.extend_from_slice(&self.index_data);
+        index_data.extend_from_slice(&delta);
+
+        let mut segments = self.segments.clone();
+        let mut delta = delta;
+        while delta.has_remaining() {
+            segments.push(DataSegment {
+                offset: delta.get_i64(),
+                length: delta.get_i32(),
+                uncompress_length: delta.get_i32(),
+                crc: delta.get_i64(),
+                block_id: delta.get_i64(),
+                task_attempt_id: delta.get_i64(),
+            });
+        }
+
+        CachedIndex {
+            index_data: index_data.freeze(),
+            segments,
+        }
+    }
+}
+
+/// The LRU cache of the localfile index by partition, keyed by the index file path.
+pub struct IndexCache {
+    entries: Mutex<LruCache<String, Arc<CachedIndex>>>,
+    enabled: bool,
+}
+
+impl IndexCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: Mutex::new(LruCache::new(capacity.max(1))),
+            enabled: capacity > 0,
+        }
+    }
+
+    /// Get the index with the file length, only the bytes beyond the cached length are
+    /// read by the `read_delta(offset, len)`.
+    pub async fn get<F, Fut>(
+        &self,
+        path: &str,
+        file_len: u64,
+        read_delta: F,
+    ) -> Result<Arc<CachedIndex>>
+    where
+        F: FnOnce(u64, u64) -> Fut,
+        Fut: Future<Output = Result<Bytes>>,
+    {
+        let file_len = file_len / INDEX_ENTRY_LEN * INDEX_ENTRY_LEN;
+        let cached = match self.enabled {
+            true => self.entries.lock().get(path).cloned(),
+            false => None,
+        };
+        // the index file may be recreated after purging, reload it fully
+        let cached = cached.filter(|x| x.len() <= file_len).unwrap_or_default();
+
+        if cached.len() == file_len && file_len > 0 {
+            TOTAL_LOCALFILE_INDEX_CACHE_HIT.inc();
+            return Ok(cached);
+        }
+        TOTAL_LOCALFILE_INDEX_CACHE_MISS.inc();
+        if file_len == 0 {
+            return Ok(cached);
+        }
+
+        let delta = read_delta(cached.len(), file_len - cached.len()).await?;
+        let index = Arc::new(cached.append(delta));
+
+        if self.enabled {
+            let mut entries = self.entries.lock();
+            // keep the longer one when racing with the other reader
+            let stale = match entries.get(path) {
+                Some(current) => current.len() < index.len(),
+                None => true,
+            };
+            if stale {
+                entries.insert(path.to_string(), index.clone());
+            }
+        }
+        Ok(index)
+    }
+
+    pub fn invalidate(&self, path: &str) {
+        self.entries.lock().remove(path);
+    }
+
+    pub fn invalidate_prefix(&self, prefix: &str) {
+        let mut entries = self.entries.lock();
+        let keys: Vec<String> = entries
+            .iter()
+            .filter(|(key, _)| key.starts_with(prefix))
+            .map(|(key, _)| key.to_string())
+            .collect();
+        for key in keys {
+            entries.remove(&key);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.lock().len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::store::local::index_cache::{IndexCache, INDEX_ENTRY_LEN};
+    use bytes::{BufMut, Bytes, BytesMut};
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::Arc;
+
+    fn index_file(block_num: i64) -> Bytes {
+        let mut bytes = BytesMut::new();
+        for block_id in 0..block_num {
+            bytes.put_i64(block_id * 10);
+            bytes.put_i32(10);
+            bytes.put_i32(10);
+            bytes.put_i64(0);
+            bytes.put_i64(block_id);
+            bytes.put_i64(0);
+        }
+        bytes.freeze()
+    }
+
+    #[tokio::test]
+    async fn test_delta_read() -> anyhow::Result<()> {
+        let cache = IndexCache::new(10);
+        let read_bytes = Arc::new(AtomicU64::new(0));
+        let reader = |file: Bytes| {
+            let read_bytes = read_bytes.clone();
+            move |offset: u64, len: u64| async move {
+                read_bytes.fetch_add(len, Ordering::SeqCst);
+                anyhow::Ok(file.slice(offset as usize..(offset + len) as usize))
+            }
+        };
+
+        let file = index_file(2);
+        let index = cache
+            .get("a/0/p-0", file.len() as u64, reader(file))
+            .await?;
+        assert_eq!(2, index.segments.len());
+        assert_eq!(2 * INDEX_ENTRY_LEN, read_bytes.load(Ordering::SeqCst));
+
+        // no more flushes, nothing is read
+        let file = index_file(2);
+        let index = cache
+            .get("a/0/p-0", file.len() as u64, reader(file))
+            .await?;
+        assert_eq!(2, index.segments.len());
+        assert_eq!(2 * INDEX_ENTRY_LEN, read_bytes.load(Ordering::SeqCst));
+
+        // the appended entries are read only
+        let file = index_file(5);
+        let index = cache
+            .get("a/0/p-0", file.len() as u64, reader(file.clone()))
+            .await?;
+        assert_eq!(5 * INDEX_ENTRY_LEN, read_bytes.load(Ordering::SeqCst));
+        assert_eq!(file, index.index_data);
+        assert_eq!(
+            vec![0, 1, 2, 3, 4],
+            index
+                .segments
+                .iter()
+                .map(|x| x.block_id)
+                .collect::<Vec<_>>()
+        );
+
+        // the incomplete entry being appended is invisible
+        let file = index_file(6);
+        let partial = file.slice(..file.len() - 1);
+        let index = cache
+            .get("a/0/p-0", partial.len() as u64, reader(partial))
+            .await?;
+        assert_eq!(5, index.segments.len());
+        assert_eq!(5 * INDEX_ENTRY_LEN, read_bytes.load(Ordering::SeqCst));
+
+        // invalidated by purge, then read fully again
+        cache.invalidate_prefix("a/0/");
+        assert_eq!(0, cache.len());
+        let index = cache
+            .get("a/0/p-0", file.len() as u64, reader(file))
+            .await?;
+        assert_eq!(6, index.segments.len());
+        assert_eq!(11 * INDEX_ENTRY_LEN, read_bytes.load(Ordering::SeqCst));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_lru_eviction() -> anyhow::Result<()> {
+        let cache = IndexCache::new(2);
+        let file = index_file(1);
+        for path in ["a/0/p-0", "a/0/p-1", "a/0/p-2"] {
+            let file = file.clone();
+            cache
+                .get(
+                    path,
+                    file.len() as u64,
+                    |_, _| async move { anyhow::Ok(file) },
+                )
+                .await?;
+        }
+        assert_eq!(2, cache.len());
+        Ok(())
+    }
+}
@@ -0,0 +1,205 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::metric::{TOTAL_LOCALFILE_MMAP_HIT, TOTAL_LOCALFILE_MMAP_MAPPED};
+use anyhow::{anyhow, Result};
+use bytes::Bytes;
+use hashlink::LruCache;
+use memmap2::Mmap;
+use parking_lot::Mutex;
+use std::fs::File;
+use std::ops::Range;
+use std::path::Path;
+use std::sync::Arc;
+
+/// The slice of the mapping, which keeps the map alive until the last slice is dropped.
+struct MmapSlice {
+    map: Arc<Mmap>,
+    range: Range<usize>,
+}
+
+impl AsRef<[u8]> for MmapSlice {
+    fn as_ref(&self) -> &[u8] {
+        &self.map[self.range.clone()]
+    }
+}
+
+/// The LRU cache of the mapped data files, keyed by the relative path. The evicted or
+/// invalidated map is unmapped once no slice refers to it.
+///
+/// The file is mapped with its length at the time, so the bytes appended later are
+/// invisible and the file is remapped when reading beyond it.
+pub struct MmapCache {
+    maps: Mutex<LruCache<String, Arc<Mmap>>>,
+}
+
+impl MmapCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            maps: Mutex::new(LruCache::new(capacity.max(1))),
+        }
+    }
+
+    fn map(file_path: &Path) -> Result<Mmap> {
+        let file = File::open(file_path)?;
+        // safe: the data file is append-only and never truncated in place. It's replaced
+        // by the renaming of compaction or unlinked by purging, the mapped inode is kept.
+        let map = unsafe { Mmap::map(&file)? };
+        TOTAL_LOCALFILE_MMAP_MAPPED.inc();
+        Ok(map)
+    }
+
+    /// Read the range of the file under the root as the view over the mapping without copying.
+    pub fn read(&self, root: &str, path: &str, offset: u64, len: u64) -> Result<Bytes> {
+        let end = (offset + len) as usize;
+        let cached = self.maps.lock().get(path).cloned();
+        let map = match cached {
+            Some(map) if map.len() >= end => {
+                TOTAL_LOCALFILE_MMAP_HIT.inc();
+                map
+            }
+            // not mapped yet or the file has grown
+            _ => {
+                let map = Arc::new(Self::map(&Path::new(root).join(path))?);
+                let mut maps = self.maps.lock();
+                // keep the longer one when racing with the other reader
+                let stale = match maps.get(path) {
+                    Some(current) => current.len() < map.len(),
+                    None => true,
+                };
+                if stale {
+                    maps.insert(path.to_string(), map.clone());
+                }
+                map
+            }
+        };
+        if map.len() < end {
+            return Err(anyhow!(
+                "Reading the range: [{}, {}) beyond the len: {} of the file: {}",
+                offset,
+                end,
+                map.len(),
+                path
+            ));
+        }
+        Ok(Bytes::from_owner(MmapSlice {
+            map,
+            range: offset as usize..end,
+        }))
+    }
+
+    pub fn invalidate(&self, path: &str) {
+        self.maps.lock().remove(path);
+    }
+
+    pub fn invalidate_prefix(&self, prefix: &str) {
+        let mut maps = self.maps.lock();
+        let keys: Vec<String> = maps
+            .iter()
+            .filter(|(key, _)| key.starts_with(prefix))
+            .map(|(key, _)| key.to_string())
+            .collect();
+        for key in keys {
+            maps.remove(&key);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.maps.lock().len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::store::local::mmap::MmapCache;
+    use std::fs::OpenOptions;
+    use std::io::Write;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_read_and_remap_on_growth() -> anyhow::Result<()> {
+        let temp_dir = tempdir::TempDir::new("test_mmap_read")?;
+        let root = temp_dir.path().to_str().unwrap().to_string();
+        std::fs::write(temp_dir.path().join("a.data"), b"hello")?;
+
+        let cache = MmapCache::new(1);
+        assert_eq!(b"ell", cache.read(&root, "a.data", 1, 3)?.as_ref());
+
+        // the slice is still readable after appending and remapping
+        let slice = cache.read(&root, "a.data", 0, 5)?;
+        OpenOptions::new()
+            .append(true)
+            .open(temp_dir.path().join("a.data"))?
+            .write_all(b" world")?;
+        assert_eq!(b"world", cache.read(&root, "a.data", 6, 5)?.as_ref());
+        assert_eq!(b"hello", slice.as_ref());
+
+        // beyond the file len
+        assert!(cache.read(&root, "a.data", 6, 10).is_err());
+
+        // evicted by the capacity, the slice keeps the old map alive
+        std::fs::write(temp_dir.path().join("b.data"), b"riffle")?;
+        assert_eq!(b"riffle", cache.read(&root, "b.data", 0, 6)?.as_ref());
+        assert_eq!(1, cache.len());
+        assert_eq!(b"hello", slice.as_ref());
+
+        cache.invalidate_prefix("b");
+        assert_eq!(0, cache.len());
+        Ok(())
+    }
+
+    #[test]
+    fn test_concurrent_append_and_read() -> anyhow::Result<()> {
+        let temp_dir = tempdir::TempDir::new("test_mmap_concurrent")?;
+        let root = temp_dir.path().to_str().unwrap().to_string();
+        let file_path = temp_dir.path().join("a.data");
+        std::fs::write(&file_path, b"")?;
+
+        // every record is 8 bytes of its index, the written len is published after appending
+        let written = Arc::new(AtomicU64::new(0));
+        let records = 10000u64;
+        let writer = {
+            let written = written.clone();
+            std::thread::spawn(move || {
+                let mut file = OpenOptions::new().append(true).open(file_path).unwrap();
+                for idx in 0..records {
+                    file.write_all(&idx.to_be_bytes()).unwrap();
+                    file.flush().unwrap();
+                    written.store((idx + 1) * 8, Ordering::SeqCst);
+                }
+            })
+        };
+
+        let cache = MmapCache::new(10);
+        loop {
+            let len = written.load(Ordering::SeqCst);
+            if len > 0 {
+                // the latest record and the first one are always consistent
+                let last = cache.read(&root, "a.data", len - 8, 8)?;
+                assert_eq!(len / 8 - 1, u64::from_be_bytes(last.as_ref().try_into()?));
+                let first = cache.read(&root, "a.data", 0, 8)?;
+                assert_eq!(0, u64::from_be_bytes(first.as_ref().try_into()?));
+            }
+            if len == records * 8 {
+                break;
+            }
+        }
+        writer.join().unwrap();
+        Ok(())
+    }
+}
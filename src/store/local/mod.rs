@@ -15,4 +15,7 @@
 // specific language governing permissions and limitations
 // under the License.
 
+pub mod compaction;
 pub mod disk;
+pub mod index_cache;
+pub mod mmap;
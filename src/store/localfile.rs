@@ -20,59 +20,118 @@ use crate::app::{
     PartitionedUId, PurgeDataContext, ReadingIndexViewContext, ReadingViewContext,
     RegisterAppContext, ReleaseTicketContext, RequireBufferContext, WritingViewContext,
 };
-use crate::config::{LocalfileStoreConfig, StorageType};
+use crate::config::{LocalfileStoreConfig, ReadEngine, StorageType};
 use crate::error::WorkerError;
+use crate::health::{ComponentHealth, HealthProvider};
 use crate::metric::TOTAL_LOCALFILE_USED;
 use crate::store::ResponseDataIndex::Local;
 use crate::store::{
-    Block, LocalDataIndex, PartitionedLocalData, Persistent, RequireBufferResponse, ResponseData,
-    ResponseDataIndex, Store,
+    Block, LocalDataIndex, PartitionStat, PartitionedLocalData, Persistent, RequireBufferResponse,
+    ResponseData, ResponseDataIndex, SpillConcurrency, Store, StoreCapabilities, StoreTier,
 };
 use std::ops::Deref;
 use std::path::Path;
-use std::str::FromStr;
 
 use anyhow::Result;
 use async_trait::async_trait;
 use await_tree::InstrumentAwait;
-use bytes::{BufMut, BytesMut};
-use dashmap::DashMap;
+use bytes::{BufMut, Bytes, BytesMut};
+use futures::TryFutureExt;
 
 use log::{debug, error, warn};
 
 use crate::composed_bytes::ComposedBytes;
 use crate::readable_size::ReadableSize;
 use crate::runtime::manager::RuntimeManager;
+use crate::runtime::RuntimeRef;
+use crate::slow_log;
+use crate::slow_log::Phase;
+use crate::util;
 use dashmap::mapref::entry::Entry;
-use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
+use crate::store::local::compaction::{
+    CompactionListener, CompactionOptions, LocalFileCompactor, PartitionLocks,
+};
 use crate::store::local::disk::{LocalDisk, LocalDiskConfig};
+use crate::store::local::index_cache::IndexCache;
+use crate::store::local::mmap::MmapCache;
 use crate::store::spill::SpillWritingViewContext;
 
-struct LockedObj {
-    disk: Arc<LocalDisk>,
-    pointer: AtomicI64,
+pub(crate) struct LockedObj {
+    pub(crate) uid: PartitionedUId,
+    pub(crate) disk: Arc<LocalDisk>,
+    pub(crate) pointer: AtomicI64,
+    // the number of the flushed segments since created or compacted
+    pub(crate) segments: AtomicU64,
+    // the last written or read timestamp in millis
+    pub(crate) last_accessed: AtomicU64,
+    // the number of the writing and reading, which is bumped on every access
+    pub(crate) accesses: AtomicU64,
 }
 
-impl From<Arc<LocalDisk>> for LockedObj {
-    fn from(value: Arc<LocalDisk>) -> Self {
+impl LockedObj {
+    fn new(uid: PartitionedUId, disk: Arc<LocalDisk>) -> Self {
         Self {
-            disk: value.clone(),
+            uid,
+            disk,
             pointer: Default::default(),
+            segments: Default::default(),
+            last_accessed: AtomicU64::new(util::now_timestamp_as_millis() as u64),
+            accesses: Default::default(),
         }
     }
+
+    fn touch(&self) {
+        self.last_accessed
+            .store(util::now_timestamp_as_millis() as u64, Ordering::SeqCst);
+        self.accesses.fetch_add(1, Ordering::SeqCst);
+    }
 }
 
 pub struct LocalFileStore {
     local_disks: Vec<Arc<LocalDisk>>,
+    // the disks of the write paths, where the partition data is placed
+    write_disks: Vec<Arc<LocalDisk>>,
+    // the disks of the paths only for reading, whose data is placed by others and kept as is
+    read_only_disks: Vec<Arc<LocalDisk>>,
     healthy_check_min_disks: i32,
     runtime_manager: RuntimeManager,
-    partition_locks: DashMap<String, Arc<RwLock<LockedObj>>>,
+    partition_locks: PartitionLocks,
+    index_cache: Arc<IndexCache>,
+    compactor: Option<Arc<LocalFileCompactor>>,
+    // the data files are read by the mapping if set
+    mmap_cache: Option<Arc<MmapCache>>,
 }
 
-impl Persistent for LocalFileStore {}
+impl Persistent for LocalFileStore {
+    fn spill_concurrency(&self) -> SpillConcurrency {
+        let mut concurrency = SpillConcurrency::default();
+        for disk in &self.write_disks {
+            let disk_concurrency = disk.spill_concurrency();
+            concurrency.max_concurrency += disk_concurrency.max_concurrency;
+            concurrency.available_permits += disk_concurrency.available_permits;
+        }
+        concurrency
+    }
+
+    fn disk_used_ratio(&self) -> Option<f64> {
+        self.write_disks
+            .iter()
+            .filter(|disk| !disk.is_corrupted().unwrap())
+            .filter_map(|disk| disk.used_ratio())
+            .reduce(f64::max)
+    }
+
+    fn disk_queue_ratio(&self) -> Option<f64> {
+        self.write_disks
+            .iter()
+            .map(|disk| disk.queue_ratio())
+            .reduce(f64::max)
+    }
+}
 
 unsafe impl Send for LocalFileStore {}
 unsafe impl Sync for LocalFileStore {}
@@ -90,46 +149,119 @@ impl LocalFileStore {
             ));
         }
         LocalFileStore {
+            write_disks: local_disk_instances.clone(),
+            read_only_disks: vec![],
             local_disks: local_disk_instances,
             healthy_check_min_disks: 1,
             runtime_manager,
             partition_locks: Default::default(),
+            index_cache: Arc::new(IndexCache::new(0)),
+            compactor: None,
+            mmap_cache: None,
         }
     }
 
     pub fn from(localfile_config: LocalfileStoreConfig, runtime_manager: RuntimeManager) -> Self {
+        let write_paths = localfile_config.effective_write_paths().to_vec();
         let mut local_disk_instances = vec![];
-        for path in localfile_config.data_paths {
-            // clear up all previous disk data
-            if let Err(e) = LocalFileStore::remove_dir_children(path.as_str()) {
-                panic!(
-                    "Errors on clear up children files of path: {:?}. err: {:#?}",
-                    path.as_str(),
-                    e
-                );
+        for path in localfile_config.all_paths() {
+            // clear up all previous disk data, the read only data is served as is
+            if write_paths.contains(&path) {
+                if let Err(e) = LocalFileStore::remove_dir_children(path.as_str()) {
+                    panic!(
+                        "Errors on clear up children files of path: {:?}. err: {:#?}",
+                        path.as_str(),
+                        e
+                    );
+                }
             }
 
             let config = LocalDiskConfig {
                 high_watermark: localfile_config.disk_high_watermark,
                 low_watermark: localfile_config.disk_low_watermark,
                 max_concurrency: localfile_config.disk_max_concurrency,
-                write_buf_capacity: ReadableSize::from_str(
+                write_buf_capacity: ReadableSize::parse(
                     localfile_config.disk_write_buf_capacity.as_str(),
                 )
                 .unwrap()
                 .as_bytes(),
+                fsync_policy: localfile_config.fsync_policy(),
+                read_timeout: localfile_config.read_timeout().unwrap(),
             };
 
             local_disk_instances.push(LocalDisk::new(path, config, runtime_manager.clone()));
         }
+        let (write_disks, read_only_disks) = local_disk_instances
+            .iter()
+            .cloned()
+            .partition(|disk| write_paths.contains(&disk.root));
+
+        let partition_locks: PartitionLocks = Default::default();
+        let index_cache = Arc::new(IndexCache::new(localfile_config.index_cache_capacity));
+        let compactor = localfile_config.compaction.as_ref().map(|conf| {
+            let compactor = Arc::new(LocalFileCompactor::new(
+                CompactionOptions::from(conf).unwrap(),
+                partition_locks.clone(),
+                index_cache.clone(),
+            ));
+            Self::start_compactor(&compactor, &runtime_manager);
+            compactor
+        });
+        let mmap_cache = match localfile_config.read_engine() {
+            ReadEngine::Mmap => Some(Arc::new(MmapCache::new(
+                localfile_config.mmap_cache_capacity,
+            ))),
+            ReadEngine::Pread => None,
+        };
+        if let (Some(compactor), Some(mmap_cache)) = (&compactor, &mmap_cache) {
+            // the mapping of the replaced file is stale
+            let mmap_cache = mmap_cache.clone();
+            compactor.add_listener(Box::new(move |uid| {
+                let (data_file_path, _) = LocalFileStore::gen_relative_path_for_partition(uid);
+                mmap_cache.invalidate(&data_file_path);
+            }));
+        }
         LocalFileStore {
             local_disks: local_disk_instances,
+            write_disks,
+            read_only_disks,
             healthy_check_min_disks: localfile_config.healthy_check_min_disks,
             runtime_manager,
-            partition_locks: Default::default(),
+            partition_locks,
+            index_cache,
+            compactor,
+            mmap_cache,
         }
     }
 
+    /// The listener is notified after the partition is compacted, no-op if the compaction is disabled.
+    pub fn on_compacted(&self, listener: CompactionListener) {
+        if let Some(compactor) = &self.compactor {
+            compactor.add_listener(listener);
+        }
+    }
+
+    fn start_compactor(compactor: &Arc<LocalFileCompactor>, runtime_manager: &RuntimeManager) {
+        let interval = compactor.interval();
+        let compactor = Arc::downgrade(compactor);
+        runtime_manager
+            .default_runtime
+            .spawn_guarded("localfile_compactor", async move {
+                loop {
+                    tokio::time::sleep(interval).await;
+                    // stopped once the store is dropped
+                    let compactor = match compactor.upgrade() {
+                        Some(compactor) => compactor,
+                        _ => return,
+                    };
+                    let compacted = compactor.compact().await;
+                    if compacted > 0 {
+                        debug!("Compacted {} partitions of localfile", compacted);
+                    }
+                }
+            });
+    }
+
     fn remove_dir_children(parent: &str) -> Result<()> {
         for entry in std::fs::read_dir(parent)? {
             let entry = entry?;
@@ -169,7 +301,8 @@ impl LocalFileStore {
 
     fn healthy_check(&self) -> Result<bool> {
         let mut available = 0;
-        for local_disk in &self.local_disks {
+        // the read only disks are not counted, which can't accept the writing
+        for local_disk in &self.write_disks {
             if local_disk.is_healthy()? && !local_disk.is_corrupted()? {
                 available += 1;
             }
@@ -182,12 +315,108 @@ impl LocalFileStore {
         Ok(available > self.healthy_check_min_disks)
     }
 
+    /// The read only disk holding the partition data file, which is not tracked by the writing.
+    async fn locate_read_only_disk(&self, data_file_path: &str) -> Option<Arc<LocalDisk>> {
+        for disk in &self.read_only_disks {
+            if !disk.is_corrupted().unwrap_or(true) && disk.stat(data_file_path).await.is_ok() {
+                return Some(disk.clone());
+            }
+        }
+        None
+    }
+
+    async fn read_data(
+        &self,
+        local_disk: &LocalDisk,
+        data_file_path: &str,
+        offset: i64,
+        len: i64,
+    ) -> Result<Bytes, WorkerError> {
+        if local_disk.is_corrupted()? {
+            return Err(WorkerError::LOCAL_DISK_OWNED_BY_PARTITION_CORRUPTED(
+                local_disk.root.to_string(),
+            ));
+        }
+
+        let data = match &self.mmap_cache {
+            Some(mmap_cache) => {
+                mmap_cache.read(&local_disk.root, data_file_path, offset as u64, len as u64)?
+            }
+            _ => {
+                slow_log::record_phase(
+                    Phase::DiskRead,
+                    local_disk
+                        .read(data_file_path, offset, Some(len))
+                        .instrument_await(format!(
+                            "getting data from localfile: {:?}",
+                            data_file_path
+                        )),
+                )
+                .await?
+            }
+        };
+        Ok(data)
+    }
+
+    async fn read_index(
+        &self,
+        local_disk: &LocalDisk,
+        data_file_path: &str,
+        index_file_path: &str,
+    ) -> Result<ResponseDataIndex, WorkerError> {
+        if local_disk.is_corrupted()? {
+            return Err(WorkerError::LOCAL_DISK_OWNED_BY_PARTITION_CORRUPTED(
+                local_disk.root.to_string(),
+            ));
+        }
+
+        let index_file_len = local_disk
+            .stat(index_file_path)
+            .instrument_await(format!("getting file len from file: {:?}", index_file_path))
+            .await?
+            .content_length;
+        // only the index appended since the last reading is read from the disk
+        let index = self
+            .index_cache
+            .get(index_file_path, index_file_len, |offset, len| {
+                slow_log::record_phase(
+                    Phase::DiskRead,
+                    local_disk
+                        .read(index_file_path, offset as i64, Some(len as i64))
+                        .instrument_await(format!(
+                            "reading index data from file: {:?}",
+                            index_file_path
+                        )),
+                )
+                .map_err(anyhow::Error::from)
+            })
+            .await
+            // keep the disk timeout to the client
+            .map_err(|e| match e.downcast::<WorkerError>() {
+                Ok(e) => e,
+                Err(e) => WorkerError::Other(e),
+            })?;
+        let index_data_result = index.index_data.clone();
+        let file_stat = local_disk
+            .stat(data_file_path)
+            .instrument_await(format!("getting file len from file: {:?}", data_file_path))
+            .await?;
+        let len = file_stat.content_length as i64;
+        Ok(Local(LocalDataIndex {
+            index_data: index_data_result,
+            data_file_len: len,
+        }))
+    }
+
     fn select_disk(&self, uid: &PartitionedUId) -> Result<Arc<LocalDisk>, WorkerError> {
         let hash_value = PartitionedUId::get_hash(uid);
 
         let mut candidates = vec![];
-        for local_disk in &self.local_disks {
-            if !local_disk.is_corrupted().unwrap() && local_disk.is_healthy().unwrap() {
+        for local_disk in &self.write_disks {
+            if !local_disk.is_corrupted().unwrap()
+                && local_disk.is_healthy().unwrap()
+                && !local_disk.is_suspect()
+            {
                 candidates.push(local_disk);
             }
         }
@@ -210,6 +439,7 @@ impl LocalFileStore {
         &self,
         uid: PartitionedUId,
         blocks: Vec<&Block>,
+        runtime: &RuntimeRef,
     ) -> Result<(), WorkerError> {
         let (data_file_path, index_file_path) =
             LocalFileStore::gen_relative_path_for_partition(&uid);
@@ -219,7 +449,7 @@ impl LocalFileStore {
             Entry::Vacant(e) => {
                 parent_dir_is_created = true;
                 let disk = self.select_disk(&uid)?;
-                let locked_obj = Arc::new(RwLock::new(LockedObj::from(disk)));
+                let locked_obj = Arc::new(RwLock::new(LockedObj::new(uid.clone(), disk)));
                 let obj = e.insert_entry(locked_obj.clone());
                 obj.get().clone()
             }
@@ -279,7 +509,7 @@ impl LocalFileStore {
         }
 
         let disk = local_disk.clone();
-        let handler = self.runtime_manager.write_runtime.spawn(async move {
+        let handler = runtime.spawn_guarded("localfile_append", async move {
             disk.append(
                 ComposedBytes::from(data_bytes_holder, total_size as usize),
                 &data_file_path,
@@ -301,6 +531,8 @@ impl LocalFileStore {
             .deref()
             .pointer
             .store(next_offset, Ordering::SeqCst);
+        locked_obj.segments.fetch_add(1, Ordering::SeqCst);
+        locked_obj.touch();
 
         Ok(())
     }
@@ -319,14 +551,20 @@ impl Store for LocalFileStore {
 
         let uid = ctx.uid;
         let blocks: Vec<&Block> = ctx.data_blocks.iter().collect();
-        self.data_insert(uid, blocks).await
+        self.data_insert(uid, blocks, &self.runtime_manager.write_runtime)
+            .await
     }
 
     async fn get(&self, ctx: ReadingViewContext) -> Result<ResponseData, WorkerError> {
         let uid = ctx.uid;
         let (offset, len) = match ctx.reading_options {
             FILE_OFFSET_AND_LEN(offset, len) => (offset, len),
-            _ => (0, 0),
+            _ => {
+                return Err(WorkerError::UNSUPPORTED_STORE_OPERATION(
+                    "get by the last block id",
+                    "localfile",
+                ))
+            }
         };
 
         if len == 0 {
@@ -339,6 +577,10 @@ impl Store for LocalFileStore {
         let (data_file_path, _) = LocalFileStore::gen_relative_path_for_partition(&uid);
 
         if !self.partition_locks.contains_key(&data_file_path) {
+            if let Some(disk) = self.locate_read_only_disk(&data_file_path).await {
+                let data = self.read_data(&disk, &data_file_path, offset, len).await?;
+                return Ok(ResponseData::Local(PartitionedLocalData { data }));
+            }
             warn!(
                 "There is no cached data in localfile store for [{:?}]",
                 &uid
@@ -352,27 +594,17 @@ impl Store for LocalFileStore {
             .partition_locks
             .entry(data_file_path.clone())
             .or_insert_with(|| {
-                Arc::new(RwLock::new(LockedObj::from(
+                Arc::new(RwLock::new(LockedObj::new(
+                    uid.clone(),
                     self.select_disk(&uid).unwrap(),
                 )))
             })
             .clone();
 
         let locked_object = locked_object.read().await;
-        let local_disk = &locked_object.disk;
-
-        if local_disk.is_corrupted()? {
-            return Err(WorkerError::LOCAL_DISK_OWNED_BY_PARTITION_CORRUPTED(
-                local_disk.root.to_string(),
-            ));
-        }
-
-        let data = local_disk
-            .read(&data_file_path, offset, Some(len))
-            .instrument_await(format!(
-                "getting data from localfile: {:?}",
-                &data_file_path
-            ))
+        locked_object.touch();
+        let data = self
+            .read_data(&locked_object.disk, &data_file_path, offset, len)
             .await?;
         Ok(ResponseData::Local(PartitionedLocalData { data }))
     }
@@ -386,6 +618,11 @@ impl Store for LocalFileStore {
             LocalFileStore::gen_relative_path_for_partition(&uid);
 
         if !self.partition_locks.contains_key(&data_file_path) {
+            if let Some(disk) = self.locate_read_only_disk(&data_file_path).await {
+                return self
+                    .read_index(&disk, &data_file_path, &index_file_path)
+                    .await;
+            }
             warn!(
                 "There is no cached data in localfile store for [{:?}]",
                 &uid
@@ -400,36 +637,17 @@ impl Store for LocalFileStore {
             .partition_locks
             .entry(data_file_path.clone())
             .or_insert_with(|| {
-                Arc::new(RwLock::new(LockedObj::from(
+                Arc::new(RwLock::new(LockedObj::new(
+                    uid.clone(),
                     self.select_disk(&uid).unwrap(),
                 )))
             })
             .clone();
 
         let locked_object = locked_object.read().await;
-        let local_disk = &locked_object.disk;
-        if local_disk.is_corrupted()? {
-            return Err(WorkerError::LOCAL_DISK_OWNED_BY_PARTITION_CORRUPTED(
-                local_disk.root.to_string(),
-            ));
-        }
-
-        let index_data_result = local_disk
-            .read(&index_file_path, 0, None)
-            .instrument_await(format!(
-                "reading index data from file: {:?}",
-                &index_file_path
-            ))
-            .await?;
-        let file_stat = local_disk
-            .stat(&data_file_path)
-            .instrument_await(format!("getting file len from file: {:?}", &data_file_path))
-            .await?;
-        let len = file_stat.content_length as i64;
-        Ok(Local(LocalDataIndex {
-            index_data: index_data_result,
-            data_file_len: len,
-        }))
+        locked_object.touch();
+        self.read_index(&locked_object.disk, &data_file_path, &index_file_path)
+            .await
     }
 
     async fn purge(&self, ctx: PurgeDataContext) -> Result<i64> {
@@ -441,10 +659,15 @@ impl Store for LocalFileStore {
             _ => LocalFileStore::gen_relative_path_for_app(&app_id),
         };
 
-        for local_disk_ref in &self.local_disks {
+        for local_disk_ref in &self.write_disks {
             let disk = local_disk_ref.clone();
             disk.delete(&data_relative_dir_path).await?;
         }
+        self.index_cache
+            .invalidate_prefix(&format!("{}/", &data_relative_dir_path));
+        if let Some(mmap_cache) = &self.mmap_cache {
+            mmap_cache.invalidate_prefix(&format!("{}/", &data_relative_dir_path));
+        }
 
         let keys_to_delete: Vec<_> = self
             .partition_locks
@@ -473,11 +696,17 @@ impl Store for LocalFileStore {
         &self,
         _ctx: RequireBufferContext,
     ) -> Result<RequireBufferResponse, WorkerError> {
-        todo!()
+        Err(WorkerError::UNSUPPORTED_STORE_OPERATION(
+            "require_buffer",
+            "localfile",
+        ))
     }
 
     async fn release_ticket(&self, _ctx: ReleaseTicketContext) -> Result<i64, WorkerError> {
-        todo!()
+        Err(WorkerError::UNSUPPORTED_STORE_OPERATION(
+            "release_ticket",
+            "localfile",
+        ))
     }
 
     async fn register_app(&self, _ctx: RegisterAppContext) -> Result<()> {
@@ -488,6 +717,27 @@ impl Store for LocalFileStore {
         StorageType::LOCALFILE
     }
 
+    fn capabilities(&self) -> StoreCapabilities {
+        StoreCapabilities {
+            supports_paging: false,
+            supports_index: true,
+            supports_buffer: false,
+            durable: true,
+            tier: StoreTier::WARM,
+        }
+    }
+
+    fn partition_stat(&self, uid: &PartitionedUId) -> Option<PartitionStat> {
+        let (data_file_path, _) = LocalFileStore::gen_relative_path_for_partition(uid);
+        let locked_object = self.partition_locks.get(&data_file_path)?.clone();
+        // skip the partition being written to avoid blocking
+        let locked_object = locked_object.try_read().ok()?;
+        Some(PartitionStat {
+            size: locked_object.pointer.load(Ordering::SeqCst) as u64,
+            location: Some(format!("{}/{}", &locked_object.disk.root, &data_file_path)),
+        })
+    }
+
     async fn spill_insert(&self, ctx: SpillWritingViewContext) -> Result<(), WorkerError> {
         let uid = ctx.uid;
         let mut data = vec![];
@@ -499,12 +749,23 @@ impl Store for LocalFileStore {
         }
         // for AQE
         data.sort_by_key(|block| block.task_attempt_id);
-        self.data_insert(uid, data)
+        // the spilled data is flushed in the dedicated runtime to isolate from the foreground writing
+        self.data_insert(uid, data, &self.runtime_manager.flush_runtime)
             .instrument_await("data insert")
             .await
     }
 }
 
+#[async_trait]
+impl HealthProvider for LocalFileStore {
+    async fn component_health(&self) -> Vec<ComponentHealth> {
+        self.local_disks
+            .iter()
+            .map(|disk| disk.component_health())
+            .collect()
+    }
+}
+
 #[cfg(test)]
 mod test {
     use crate::app::{
@@ -513,10 +774,17 @@ mod test {
     };
     use crate::store::localfile::LocalFileStore;
 
+    use crate::config::{LocalfileCompactionConfig, LocalfileStoreConfig, ReadEngine};
     use crate::error::WorkerError;
+    use crate::health::{HealthProvider, HealthReport, HealthStatus};
+    use crate::metric::TOTAL_LOCALFILE_COMPACTION_SKIPPED;
+    use crate::store::local::index_cache::CachedIndex;
     use crate::store::{Block, ResponseData, ResponseDataIndex, Store};
     use bytes::{Buf, Bytes, BytesMut};
     use log::{error, info};
+    use parking_lot::Mutex;
+    use std::path::Path;
+    use std::sync::Arc;
 
     fn create_writing_ctx() -> WritingViewContext {
         let uid = PartitionedUId {
@@ -552,6 +820,42 @@ mod test {
         writing_ctx
     }
 
+    #[test]
+    fn local_store_health_test() {
+        let temp_dir = tempdir::TempDir::new("local_store_health_test").unwrap();
+        let path_1 = temp_dir.path().join("disk1");
+        let path_2 = temp_dir.path().join("disk2");
+        std::fs::create_dir_all(&path_1).unwrap();
+        std::fs::create_dir_all(&path_2).unwrap();
+        let path_1 = path_1.to_str().unwrap().to_string();
+        let path_2 = path_2.to_str().unwrap().to_string();
+
+        let local_store = LocalFileStore::new(vec![path_1.clone(), path_2.clone()]);
+        let runtime = local_store.runtime_manager.clone();
+
+        let report = HealthReport::from(runtime.wait(local_store.component_health()));
+        assert_eq!(HealthStatus::Healthy, report.status);
+        assert_eq!(2, report.components.len());
+
+        // simulate the failing disk
+        local_store.local_disks[0].mark_corrupted();
+        let report = HealthReport::from(runtime.wait(local_store.component_health()));
+        assert_eq!(HealthStatus::Degraded, report.status);
+        let failing_disk = report
+            .components
+            .iter()
+            .find(|x| x.name == format!("disk:{}", &path_1))
+            .unwrap();
+        assert_eq!(HealthStatus::Unhealthy, failing_disk.status);
+        assert_eq!(false, failing_disk.details["healthy"].as_bool().unwrap());
+        let healthy_disk = report
+            .components
+            .iter()
+            .find(|x| x.name == format!("disk:{}", &path_2))
+            .unwrap();
+        assert_eq!(HealthStatus::Healthy, healthy_disk.status);
+    }
+
     #[test]
     fn local_disk_under_exception_test() -> anyhow::Result<()> {
         let temp_dir = tempdir::TempDir::new("local_disk_under_exception_test").unwrap();
@@ -798,4 +1102,299 @@ mod test {
 
         temp_dir.close().unwrap();
     }
+
+    fn create_compaction_store(path: String, disk_low_watermark: f32) -> LocalFileStore {
+        create_rate_limited_compaction_store(path, disk_low_watermark, "64M")
+    }
+
+    fn create_rate_limited_compaction_store(
+        path: String,
+        disk_low_watermark: f32,
+        io_rate_limit: &str,
+    ) -> LocalFileStore {
+        let mut config = LocalfileStoreConfig::new(vec![path]);
+        config.disk_low_watermark = disk_low_watermark;
+        config.disk_high_watermark = 1.0;
+        config.compaction = Some(LocalfileCompactionConfig {
+            segment_threshold: 2,
+            idle_duration: "0s".to_string(),
+            // triggered manually
+            interval: "1h".to_string(),
+            io_rate_limit: io_rate_limit.to_string(),
+        });
+        LocalFileStore::from(config, Default::default())
+    }
+
+    fn create_block_ctx(uid: &PartitionedUId, block_id: i64, task: i64) -> WritingViewContext {
+        let data = Bytes::from(format!("block-{}", block_id).repeat(block_id as usize + 1));
+        WritingViewContext::from(
+            uid.clone(),
+            vec![Block {
+                block_id,
+                length: data.len() as i32,
+                uncompress_length: data.len() as i32,
+                crc: 0,
+                data,
+                task_attempt_id: task,
+            }],
+        )
+    }
+
+    fn insert_block(local_store: &LocalFileStore, uid: &PartitionedUId, block_id: i64, task: i64) {
+        local_store
+            .runtime_manager
+            .wait(local_store.insert(create_block_ctx(uid, block_id, task)))
+            .unwrap();
+    }
+
+    // (block_id, task_attempt_id, data) in the index order
+    async fn read_all_blocks(
+        local_store: &LocalFileStore,
+        uid: &PartitionedUId,
+    ) -> Vec<(i64, i64, Bytes)> {
+        let index = match local_store
+            .get_index(ReadingIndexViewContext {
+                partition_id: uid.clone(),
+            })
+            .await
+            .unwrap()
+        {
+            ResponseDataIndex::Local(index) => CachedIndex::from(index.index_data),
+            _ => panic!(),
+        };
+        let mut blocks = vec![];
+        for segment in index.segments {
+            let reading_ctx = ReadingViewContext {
+                uid: uid.clone(),
+                reading_options: ReadingOptions::FILE_OFFSET_AND_LEN(
+                    segment.offset,
+                    segment.length as i64,
+                ),
+                serialized_expected_task_ids_bitmap: Default::default(),
+            };
+            match local_store.get(reading_ctx).await.unwrap() {
+                ResponseData::Local(data) => {
+                    blocks.push((segment.block_id, segment.task_attempt_id, data.data))
+                }
+                _ => panic!(),
+            }
+        }
+        blocks
+    }
+
+    #[test]
+    fn compaction_test() {
+        let temp_dir = tempdir::TempDir::new("compaction_test").unwrap();
+        let temp_path = temp_dir.path().to_str().unwrap().to_string();
+        // never under the disk pressure
+        let local_store = create_compaction_store(temp_path, 1.0);
+        let runtime = local_store.runtime_manager.clone();
+
+        let compacted_uids = Arc::new(Mutex::new(vec![]));
+        let cloned = compacted_uids.clone();
+        local_store.on_compacted(Box::new(move |uid| cloned.lock().push(uid.clone())));
+
+        let uid = PartitionedUId {
+            app_id: "compaction_test-app-id".to_string(),
+            shuffle_id: 0,
+            partition_id: 0,
+        };
+        // the task attempts are interleaved across the flushes
+        for (block_id, task) in [(0, 2), (1, 1), (2, 2), (3, 1)] {
+            insert_block(&local_store, &uid, block_id, task);
+        }
+        let mut before = runtime.wait(read_all_blocks(&local_store, &uid));
+        assert_eq!(4, before.len());
+
+        let compactor = local_store.compactor.clone().unwrap();
+        assert_eq!(1, runtime.wait(compactor.compact()));
+        assert_eq!(vec![uid.clone()], *compacted_uids.lock());
+
+        // sorted by the task attempt, and the same blocks are read back
+        let mut after = runtime.wait(read_all_blocks(&local_store, &uid));
+        assert_eq!(
+            vec![1, 3, 0, 2],
+            after.iter().map(|x| x.0).collect::<Vec<_>>()
+        );
+        before.sort_by_key(|x| x.0);
+        after.sort_by_key(|x| x.0);
+        assert_eq!(before, after);
+
+        // the compacted partition is below the threshold
+        assert_eq!(0, runtime.wait(compactor.compact()));
+
+        // the later writing is appended after the compacted data
+        insert_block(&local_store, &uid, 4, 0);
+        let blocks = runtime.wait(read_all_blocks(&local_store, &uid));
+        assert_eq!(
+            vec![1, 3, 0, 2, 4],
+            blocks.iter().map(|x| x.0).collect::<Vec<_>>()
+        );
+        assert_eq!(Bytes::from("block-4".repeat(5)), blocks[4].2);
+    }
+
+    #[test]
+    fn compaction_with_concurrent_reading_test() {
+        let temp_dir = tempdir::TempDir::new("compaction_with_concurrent_reading_test").unwrap();
+        let temp_path = temp_dir.path().to_str().unwrap().to_string();
+        // the copying of the 70 bytes data takes more than 1s
+        let local_store = create_rate_limited_compaction_store(temp_path, 1.0, "100B");
+        let runtime = local_store.runtime_manager.clone();
+
+        let uid = PartitionedUId {
+            app_id: "compaction_with_concurrent_reading_test-app-id".to_string(),
+            shuffle_id: 0,
+            partition_id: 0,
+        };
+        for (block_id, task) in [(0, 2), (1, 1), (2, 2), (3, 1)] {
+            insert_block(&local_store, &uid, block_id, task);
+        }
+        let before = runtime.wait(read_all_blocks(&local_store, &uid));
+
+        let compactor = local_store.compactor.clone().unwrap();
+        let cloned = compactor.clone();
+        let handle = runtime
+            .default_runtime
+            .spawn(async move { cloned.compact().await });
+        std::thread::sleep(std::time::Duration::from_millis(200));
+
+        // the index read during the copying keeps the old offsets, so the swap is aborted
+        assert_eq!(before, runtime.wait(read_all_blocks(&local_store, &uid)));
+        assert_eq!(0, runtime.wait(handle).unwrap());
+        assert_eq!(before, runtime.wait(read_all_blocks(&local_store, &uid)));
+
+        // compacted without the reading
+        assert_eq!(1, runtime.wait(compactor.compact()));
+        assert_eq!(
+            vec![1, 3, 0, 2],
+            runtime
+                .wait(read_all_blocks(&local_store, &uid))
+                .iter()
+                .map(|x| x.0)
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn compaction_under_disk_pressure_test() {
+        let temp_dir = tempdir::TempDir::new("compaction_under_disk_pressure_test").unwrap();
+        let temp_path = temp_dir.path().to_str().unwrap().to_string();
+        // always above the low watermark
+        let local_store = create_compaction_store(temp_path, 0.0);
+        let runtime = local_store.runtime_manager.clone();
+
+        let uid = PartitionedUId {
+            app_id: "compaction_under_disk_pressure_test-app-id".to_string(),
+            shuffle_id: 0,
+            partition_id: 0,
+        };
+        for (block_id, task) in [(0, 2), (1, 1), (2, 2)] {
+            insert_block(&local_store, &uid, block_id, task);
+        }
+        let before = runtime.wait(read_all_blocks(&local_store, &uid));
+
+        let skipped = TOTAL_LOCALFILE_COMPACTION_SKIPPED.get();
+        let compactor = local_store.compactor.clone().unwrap();
+        assert_eq!(0, runtime.wait(compactor.compact()));
+        assert!(TOTAL_LOCALFILE_COMPACTION_SKIPPED.get() > skipped);
+        assert_eq!(before, runtime.wait(read_all_blocks(&local_store, &uid)));
+    }
+
+    #[test]
+    fn read_only_paths_test() {
+        let temp_dir = tempdir::TempDir::new("read_only_paths_test").unwrap();
+        let write_path = temp_dir.path().join("write").to_str().unwrap().to_string();
+        let read_path = temp_dir.path().join("read").to_str().unwrap().to_string();
+        std::fs::create_dir_all(&write_path).unwrap();
+        std::fs::create_dir_all(&read_path).unwrap();
+        let uid = PartitionedUId {
+            app_id: "read_only_paths_test-app-id".to_string(),
+            shuffle_id: 0,
+            partition_id: 0,
+        };
+
+        // the data placed by the previous store
+        let previous_store = LocalFileStore::from(
+            LocalfileStoreConfig::new(vec![read_path.clone()]),
+            Default::default(),
+        );
+        insert_block(&previous_store, &uid, 0, 0);
+        insert_block(&previous_store, &uid, 1, 0);
+        let runtime = previous_store.runtime_manager.clone();
+        let expected = runtime.wait(read_all_blocks(&previous_store, &uid));
+        assert_eq!(2, expected.len());
+
+        let mut config = LocalfileStoreConfig::new(vec![]);
+        config.write_paths = Some(vec![write_path.clone()]);
+        config.read_paths = Some(vec![read_path.clone()]);
+        let local_store = LocalFileStore::from(config, Default::default());
+        assert_eq!(1, local_store.write_disks.len());
+        assert_eq!(1, local_store.read_only_disks.len());
+
+        // not wiped and served by the read only disk
+        assert_eq!(expected, runtime.wait(read_all_blocks(&local_store, &uid)));
+
+        // the writing is placed on the write path only
+        let other = PartitionedUId {
+            partition_id: 1,
+            ..uid.clone()
+        };
+        insert_block(&local_store, &other, 2, 0);
+        let (other_data_file_path, _) = LocalFileStore::gen_relative_path_for_partition(&other);
+        assert!(Path::new(&write_path).join(&other_data_file_path).exists());
+        assert!(!Path::new(&read_path).join(&other_data_file_path).exists());
+
+        // the read only data is kept on purging
+        runtime
+            .wait(local_store.purge(PurgeDataContext::new(uid.app_id.to_string(), None)))
+            .unwrap();
+        assert_eq!(expected, runtime.wait(read_all_blocks(&local_store, &uid)));
+    }
+
+    #[test]
+    fn mmap_read_engine_test() {
+        let temp_dir = tempdir::TempDir::new("mmap_read_engine_test").unwrap();
+        let temp_path = temp_dir.path().to_str().unwrap().to_string();
+        let mut config = LocalfileStoreConfig::new(vec![temp_path]);
+        config.read_engine = Some(ReadEngine::Mmap);
+        let local_store = LocalFileStore::from(config, Default::default());
+        let runtime = local_store.runtime_manager.clone();
+
+        let uid = PartitionedUId {
+            app_id: "mmap_read_engine_test-app-id".to_string(),
+            shuffle_id: 0,
+            partition_id: 0,
+        };
+        let blocks = 200;
+
+        // the reading is interleaved with the appending, which remaps on the growth
+        let writer = async {
+            for block_id in 0..blocks {
+                let ctx = create_block_ctx(&uid, block_id, 0);
+                local_store.insert(ctx).await.unwrap();
+                tokio::task::yield_now().await;
+            }
+        };
+        let reader = async {
+            loop {
+                let read = read_all_blocks(&local_store, &uid).await;
+                for (block_id, _, data) in &read {
+                    let expected = format!("block-{}", block_id).repeat(*block_id as usize + 1);
+                    assert_eq!(expected.as_bytes(), data.as_ref());
+                }
+                if read.len() == blocks as usize {
+                    break;
+                }
+                tokio::task::yield_now().await;
+            }
+        };
+        runtime.wait(async { tokio::join!(writer, reader) });
+
+        // the mapping is released on purging
+        assert_eq!(1, local_store.mmap_cache.as_ref().unwrap().len());
+        runtime
+            .wait(local_store.purge(PurgeDataContext::new(uid.app_id.to_string(), None)))
+            .unwrap();
+        assert_eq!(0, local_store.mmap_cache.as_ref().unwrap().len());
+    }
 }
@@ -0,0 +1,234 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+use crate::config::SpillDebtAdmissionConfig;
+use crate::metric::TOTAL_BUFFER_ADMISSION;
+use crate::store::mem::capacity::CapacitySnapshot;
+use anyhow::Result;
+use std::time::Duration;
+use tokio::time::Instant;
+
+const ADMISSION_CHECK_INTERVAL: Duration = Duration::from_millis(10);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AdmissionOutcome {
+    Admit,
+    // admitted after waiting for the spill debt released
+    Delay,
+    Reject,
+}
+
+impl AdmissionOutcome {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AdmissionOutcome::Admit => "admit",
+            AdmissionOutcome::Delay => "delay",
+            AdmissionOutcome::Reject => "reject",
+        }
+    }
+}
+
+/// The admission of the buffer requirements by the spill debt, that is the bytes picked up
+/// for spill but not released yet. The requirement is admitted only when it fits the capacity
+/// now and the projected usage after the debt released is still under the watermark,
+/// rather than overshooting into the allocation failures.
+pub struct SpillDebtAdmission {
+    projected_watermark: f32,
+    max_delay: Duration,
+}
+
+impl SpillDebtAdmission {
+    pub fn from(config: &SpillDebtAdmissionConfig) -> Result<Self> {
+        Ok(Self {
+            projected_watermark: config.projected_watermark,
+            max_delay: config.max_delay()?,
+        })
+    }
+
+    pub fn is_admitted(&self, snapshot: &CapacitySnapshot, debt: i64, requested: i64) -> bool {
+        let capacity = snapshot.capacity();
+        let occupied = snapshot.allocated() + snapshot.used();
+        // the single requirement beyond the watermark is left to the capacity check
+        if occupied == 0 {
+            return true;
+        }
+        let fits_now = occupied + requested <= capacity;
+        let projected = occupied - debt + requested;
+        fits_now && projected as f64 <= capacity as f64 * self.projected_watermark as f64
+    }
+
+    /// Wait until admitted by the latest snapshot and debt within the max delay.
+    pub async fn admit<F>(&self, requested: i64, state: F) -> AdmissionOutcome
+    where
+        F: Fn() -> (CapacitySnapshot, i64),
+    {
+        let outcome = self.decide(requested, state).await;
+        TOTAL_BUFFER_ADMISSION
+            .with_label_values(&[outcome.as_str()])
+            .inc();
+        outcome
+    }
+
+    async fn decide<F>(&self, requested: i64, state: F) -> AdmissionOutcome
+    where
+        F: Fn() -> (CapacitySnapshot, i64),
+    {
+        let (snapshot, debt) = state();
+        if self.is_admitted(&snapshot, debt, requested) {
+            return AdmissionOutcome::Admit;
+        }
+        let deadline = Instant::now() + self.max_delay;
+        while Instant::now() < deadline {
+            tokio::time::sleep(ADMISSION_CHECK_INTERVAL).await;
+            let (snapshot, debt) = state();
+            if self.is_admitted(&snapshot, debt, requested) {
+                return AdmissionOutcome::Delay;
+            }
+        }
+        AdmissionOutcome::Reject
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::SpillDebtAdmissionConfig;
+    use crate::store::mem::admission::{AdmissionOutcome, SpillDebtAdmission};
+    use crate::store::mem::capacity::CapacitySnapshot;
+    use parking_lot::Mutex;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_is_admitted() -> anyhow::Result<()> {
+        let admission = SpillDebtAdmission::from(&SpillDebtAdmissionConfig::default())?;
+        let snapshot: CapacitySnapshot = (1000, 100, 700).into();
+
+        // fits the capacity and the projected usage after spill
+        assert!(admission.is_admitted(&snapshot, 0, 100));
+        // the projected usage exceeds the watermark without the debt
+        assert!(!admission.is_admitted(&snapshot, 0, 150));
+        assert!(admission.is_admitted(&snapshot, 100, 150));
+        // doesn't fit the capacity until the debt released
+        assert!(!admission.is_admitted(&snapshot, 500, 250));
+        // the first requirement is always admitted
+        assert!(admission.is_admitted(&(1000, 0, 0).into(), 0, 950));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_admit() -> anyhow::Result<()> {
+        let mut config = SpillDebtAdmissionConfig::default();
+        config.max_delay = "100ms".to_string();
+        let admission = SpillDebtAdmission::from(&config)?;
+
+        // value: (used, debt)
+        let state = Arc::new(Mutex::new((950i64, 800i64)));
+        let current = || {
+            let (used, debt) = *state.lock();
+            let snapshot: CapacitySnapshot = (1000, 0, used).into();
+            (snapshot, debt)
+        };
+        assert_eq!(
+            AdmissionOutcome::Reject,
+            admission.admit(100, current).await
+        );
+
+        // admitted once the debt released in the waiting
+        let cloned = state.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+            *cloned.lock() = (150, 0);
+        });
+        assert_eq!(AdmissionOutcome::Delay, admission.admit(100, current).await);
+        assert_eq!(AdmissionOutcome::Admit, admission.admit(100, current).await);
+
+        // rejected at once without the delay
+        config.max_delay = "0s".to_string();
+        let admission = SpillDebtAdmission::from(&config)?;
+        *state.lock() = (950, 800);
+        assert_eq!(
+            AdmissionOutcome::Reject,
+            admission.admit(100, current).await
+        );
+        Ok(())
+    }
+
+    struct Simulated {
+        failures: u64,
+        admitted: u64,
+        peak: i64,
+    }
+
+    // the bursty writes against the watermark spill, whose debt is released after the flush latency
+    fn simulate(admission: Option<&SpillDebtAdmission>) -> Simulated {
+        let (capacity, high_watermark, low_watermark) = (1000i64, 0.8, 0.2);
+        let (flush_ticks, max_wait_ticks, size) = (3, 5, 50i64);
+        let (mut used, mut debt) = (0i64, 0i64);
+        let mut flights: Vec<(u64, i64)> = vec![];
+        let mut waiting: Vec<u64> = vec![];
+        let mut result = Simulated {
+            failures: 0,
+            admitted: 0,
+            peak: 0,
+        };
+        for tick in 0..200u64 {
+            for (_, released) in flights.iter().filter(|(at, _)| *at <= tick) {
+                used -= released;
+                debt -= released;
+            }
+            flights.retain(|(at, _)| *at > tick);
+
+            let burst = if tick % 5 == 0 { 12 } else { 1 };
+            let mut requests = std::mem::take(&mut waiting);
+            requests.extend((0..burst).map(|_| tick + max_wait_ticks));
+            for deadline in requests {
+                let snapshot: CapacitySnapshot = (capacity, 0, used).into();
+                let admitted = match admission {
+                    Some(admission) => admission.is_admitted(&snapshot, debt, size),
+                    _ => used + size <= capacity,
+                };
+                if !admitted {
+                    match admission {
+                        Some(_) if tick < deadline => waiting.push(deadline),
+                        _ => result.failures += 1,
+                    }
+                    continue;
+                }
+                used += size;
+                result.admitted += 1;
+                if (used - debt) as f64 > capacity as f64 * high_watermark {
+                    let spilled = used - debt - (capacity as f64 * low_watermark) as i64;
+                    debt += spilled;
+                    flights.push((tick + flush_ticks, spilled));
+                }
+            }
+            result.peak = result.peak.max(used);
+        }
+        result
+    }
+
+    #[test]
+    fn test_simulated_bursty_workload() -> anyhow::Result<()> {
+        let baseline = simulate(None);
+        let admission = SpillDebtAdmission::from(&SpillDebtAdmissionConfig::default())?;
+        let projected = simulate(Some(&admission));
+
+        assert!(baseline.failures > 0);
+        assert!(projected.failures < baseline.failures);
+        assert!(projected.admitted >= baseline.admitted);
+        assert!(projected.peak <= 1000);
+        Ok(())
+    }
+}
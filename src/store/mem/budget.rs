@@ -1,28 +1,62 @@
-use crate::metric::{GAUGE_MEMORY_ALLOCATED, GAUGE_MEMORY_CAPACITY, GAUGE_MEMORY_USED};
+use crate::metric::{
+    GAUGE_MEMORY_ALLOCATED, GAUGE_MEMORY_CAPACITY, GAUGE_MEMORY_IN_FLIGHT, GAUGE_MEMORY_USED,
+    TOTAL_MEMORY_ALLOCATION_FAILED,
+};
 use crate::store::mem::capacity::CapacitySnapshot;
 use anyhow::Result;
 use fastrace::trace;
+use prometheus::{IntCounter, IntGauge};
 use std::sync::Arc;
 
+/// The metrics updated within the budget lock, so the spikes won't be missed like sampling.
+#[derive(Clone)]
+pub(crate) struct BudgetMetrics {
+    pub capacity: IntGauge,
+    pub allocated: IntGauge,
+    pub used: IntGauge,
+    pub in_flight: IntGauge,
+    pub allocation_failed: IntCounter,
+}
+
+impl Default for BudgetMetrics {
+    fn default() -> Self {
+        Self {
+            capacity: GAUGE_MEMORY_CAPACITY.clone(),
+            allocated: GAUGE_MEMORY_ALLOCATED.clone(),
+            used: GAUGE_MEMORY_USED.clone(),
+            in_flight: GAUGE_MEMORY_IN_FLIGHT.clone(),
+            allocation_failed: TOTAL_MEMORY_ALLOCATION_FAILED.clone(),
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct MemoryBudget {
     capacity: i64,
     inner: Arc<parking_lot::Mutex<BudgetInner>>,
+    metrics: BudgetMetrics,
 }
 
 #[derive(Default)]
 struct BudgetInner {
     allocated: i64,
     used: i64,
+    // the part of used picked up for spill, which is released after being flushed
+    in_flight: i64,
     allocation_inc_counter: i64,
 }
 
 impl MemoryBudget {
     pub(crate) fn new(capacity: i64) -> MemoryBudget {
-        GAUGE_MEMORY_CAPACITY.set(capacity);
+        MemoryBudget::with_metrics(capacity, Default::default())
+    }
+
+    pub(crate) fn with_metrics(capacity: i64, metrics: BudgetMetrics) -> MemoryBudget {
+        metrics.capacity.set(capacity);
         MemoryBudget {
             capacity,
             inner: Default::default(),
+            metrics,
         }
     }
 
@@ -46,10 +80,11 @@ impl MemoryBudget {
 
         let remaining = capacity - allocated - used;
         if remaining < size {
+            self.metrics.allocation_failed.inc();
             Ok((false, -1))
         } else {
             inner.allocated += size;
-            GAUGE_MEMORY_ALLOCATED.set(inner.allocated);
+            self.metrics.allocated.set(inner.allocated);
             inner.allocation_inc_counter += 1;
             Ok((true, inner.allocation_inc_counter))
         }
@@ -68,15 +103,15 @@ impl MemoryBudget {
             inner.allocated -= size;
         }
         inner.used += desc;
-        GAUGE_MEMORY_ALLOCATED.set(inner.allocated);
-        GAUGE_MEMORY_USED.set(inner.used);
+        self.metrics.allocated.set(inner.allocated);
+        self.metrics.used.set(inner.used);
         Ok(true)
     }
 
     pub fn inc_used(&self, size: i64) -> Result<bool> {
         let mut inner = self.inner.lock();
         inner.used += size;
-        GAUGE_MEMORY_USED.set(inner.used);
+        self.metrics.used.set(inner.used);
         Ok(true)
     }
 
@@ -88,7 +123,7 @@ impl MemoryBudget {
         } else {
             inner.used -= size;
         }
-        GAUGE_MEMORY_USED.set(inner.used);
+        self.metrics.used.set(inner.used);
         Ok(true)
     }
 
@@ -100,7 +135,35 @@ impl MemoryBudget {
         } else {
             inner.allocated -= size;
         }
-        GAUGE_MEMORY_ALLOCATED.set(inner.allocated);
+        self.metrics.allocated.set(inner.allocated);
+        Ok(true)
+    }
+
+    pub fn in_flight(&self) -> i64 {
+        self.inner.lock().in_flight
+    }
+
+    pub fn inc_in_flight(&self, size: i64) {
+        let mut inner = self.inner.lock();
+        inner.in_flight += size;
+        self.metrics.in_flight.set(inner.in_flight);
+    }
+
+    pub fn dec_in_flight(&self, size: i64) {
+        let mut inner = self.inner.lock();
+        inner.in_flight = (inner.in_flight - size).max(0);
+        self.metrics.in_flight.set(inner.in_flight);
+    }
+
+    /// Release the flushed data from both the used and in flight at once,
+    /// the in flight never exceeds the used in any observed state.
+    #[trace]
+    pub fn release_flushed(&self, size: i64) -> Result<bool> {
+        let mut inner = self.inner.lock();
+        inner.used = (inner.used - size).max(0);
+        inner.in_flight = (inner.in_flight - size).max(0);
+        self.metrics.used.set(inner.used);
+        self.metrics.in_flight.set(inner.in_flight);
         Ok(true)
     }
 }
@@ -108,7 +171,8 @@ impl MemoryBudget {
 #[cfg(test)]
 mod test {
     use crate::metric::{GAUGE_MEMORY_ALLOCATED, GAUGE_MEMORY_USED};
-    use crate::store::mem::budget::MemoryBudget;
+    use crate::store::mem::budget::{BudgetMetrics, MemoryBudget};
+    use prometheus::{IntCounter, IntGauge};
 
     #[test]
     fn basic() -> anyhow::Result<()> {
@@ -148,4 +212,71 @@ mod test {
 
         Ok(())
     }
+
+    fn detached_metrics() -> BudgetMetrics {
+        let gauge = |name: &str| IntGauge::new(name, name).unwrap();
+        BudgetMetrics {
+            capacity: gauge("capacity"),
+            allocated: gauge("allocated"),
+            used: gauge("used"),
+            in_flight: gauge("in_flight"),
+            allocation_failed: IntCounter::new("allocation_failed", "allocation_failed").unwrap(),
+        }
+    }
+
+    #[test]
+    fn gauge_trajectory_test() -> anyhow::Result<()> {
+        let metrics = detached_metrics();
+        let budget = MemoryBudget::with_metrics(100, metrics.clone());
+        assert_eq!(100, metrics.capacity.get());
+
+        // the gauges are always equal to the accounted bytes
+        let check = |budget: &MemoryBudget| {
+            let snapshot = budget.snapshot();
+            assert_eq!(snapshot.used(), metrics.used.get());
+            assert_eq!(snapshot.allocated(), metrics.allocated.get());
+            assert_eq!(budget.in_flight(), metrics.in_flight.get());
+            assert!(metrics.in_flight.get() <= metrics.used.get());
+            assert!(metrics.used.get() + metrics.allocated.get() <= metrics.capacity.get());
+            metrics.used.get()
+        };
+
+        // fill
+        let mut last_used = 0;
+        for _ in 0..10 {
+            let (succeed, _) = budget.require_allocated(10)?;
+            assert!(succeed);
+            check(&budget);
+            budget.move_allocated_to_used(10)?;
+            let used = check(&budget);
+            assert!(used >= last_used);
+            last_used = used;
+        }
+        assert_eq!(100, last_used);
+        let (succeed, _) = budget.require_allocated(1)?;
+        assert!(!succeed);
+        assert_eq!(1, metrics.allocation_failed.get());
+
+        // spill
+        for _ in 0..4 {
+            budget.inc_in_flight(20);
+            check(&budget);
+        }
+        assert_eq!(80, metrics.in_flight.get());
+
+        // drain
+        let mut last_in_flight = metrics.in_flight.get();
+        for _ in 0..4 {
+            budget.release_flushed(20)?;
+            let used = check(&budget);
+            assert!(used <= last_used);
+            assert!(metrics.in_flight.get() <= last_in_flight);
+            last_used = used;
+            last_in_flight = metrics.in_flight.get();
+        }
+        assert_eq!(20, metrics.used.get());
+        assert_eq!(0, metrics.in_flight.get());
+
+        Ok(())
+    }
 }
@@ -64,6 +64,12 @@ impl BufferReadResult {
     }
 }
 
+#[derive(Debug)]
+pub struct BufferSnapshot {
+    pub flush_sequence: u64,
+    pub blocks: Vec<Block>,
+}
+
 #[derive(Debug)]
 pub struct BufferInternal {
     total_size: i64,
@@ -74,6 +80,13 @@ pub struct BufferInternal {
 
     flight: HashMap<u64, Arc<BatchMemoryBlock>>,
     flight_counter: u64,
+    // bumped once a flight is persisted and removed from memory
+    flush_sequence: u64,
+
+    // the min write sequence of the staging and every flight, the data written
+    // before them have been persisted
+    staging_write_sequence: Option<u64>,
+    flight_write_sequences: HashMap<u64, u64>,
 }
 
 impl BufferInternal {
@@ -85,6 +98,9 @@ impl BufferInternal {
             staging: Default::default(),
             flight: Default::default(),
             flight_counter: 0,
+            flush_sequence: 0,
+            staging_write_sequence: None,
+            flight_write_sequences: Default::default(),
         }
     }
 }
@@ -111,6 +127,12 @@ impl MemoryBuffer {
         return Ok(self.buffer.read().staging_size);
     }
 
+    /// The (staging, flight) sizes read together under the lock.
+    pub fn staging_and_flight_size(&self) -> (i64, i64) {
+        let buffer = self.buffer.read();
+        (buffer.staging_size, buffer.flight_size)
+    }
+
     #[trace]
     pub fn clear(&self, flight_id: u64, flight_size: u64) -> Result<()> {
         let mut buffer = self.buffer.write();
@@ -119,10 +141,61 @@ impl MemoryBuffer {
         if let Some(block_ref) = removed {
             buffer.total_size -= flight_size as i64;
             buffer.flight_size -= flight_size as i64;
+            buffer.flush_sequence += 1;
         }
+        buffer.flight_write_sequences.remove(&flight_id);
         Ok(())
     }
 
+    pub fn flush_sequence(&self) -> u64 {
+        self.buffer.read().flush_sequence
+    }
+
+    /// The min write sequence of the data not persisted yet, None if all the sequenced
+    /// writes have been flushed.
+    pub fn unflushed_write_sequence(&self) -> Option<u64> {
+        let buffer = self.buffer.read();
+        buffer
+            .flight_write_sequences
+            .values()
+            .copied()
+            .chain(buffer.staging_write_sequence)
+            .min()
+    }
+
+    /// Snapshot all the in-memory blocks (flight + staging) with the flush sequence
+    /// under the read lock. The flight blocks are only removed after being persisted,
+    /// so the blocks missing from this snapshot must have been flushed before.
+    #[trace]
+    pub fn snapshot(&self, task_ids: Option<&Treemap>) -> BufferSnapshot {
+        let buffer = self.buffer.read();
+        let mut flight_ids: Vec<&u64> = buffer.flight.keys().collect();
+        flight_ids.sort();
+
+        let batches = flight_ids
+            .into_iter()
+            .map(|id| buffer.flight.get(id).unwrap().iter())
+            .flatten()
+            .chain(buffer.staging.iter());
+
+        let mut blocks = vec![];
+        for batch in batches {
+            for block in batch {
+                if let Some(expected_task_ids) = task_ids {
+                    if !expected_task_ids.contains(block.task_attempt_id as u64) {
+                        continue;
+                    }
+                }
+                blocks.push(block.clone());
+            }
+        }
+
+        BufferSnapshot {
+            flush_sequence: buffer.flush_sequence,
+            blocks,
+        }
+    }
+
     #[trace]
     pub fn get_v2(
         &self,
@@ -319,6 +392,10 @@ impl MemoryBuffer {
         let flight = &mut buffer.flight;
         flight.insert(flight_id, staging_ref.clone());
 
+        if let Some(sequence) = buffer.staging_write_sequence.take() {
+            buffer.flight_write_sequences.insert(flight_id, sequence);
+        }
+
         let spill_size = buffer.staging_size;
         buffer.flight_counter += 1;
         buffer.flight_size += spill_size;
@@ -332,11 +409,16 @@ impl MemoryBuffer {
     }
 
     #[trace]
-    pub fn append(&self, blocks: Vec<Block>, size: u64) -> Result<()> {
+    pub fn append(&self, blocks: Vec<Block>, size: u64, write_sequence: Option<u64>) -> Result<()> {
         let mut buffer = self.buffer.write();
         let mut staging = &mut buffer.staging;
         staging.push(blocks);
 
+        if let Some(sequence) = write_sequence {
+            let staging_sequence = buffer.staging_write_sequence.get_or_insert(sequence);
+            *staging_sequence = (*staging_sequence).min(sequence);
+        }
+
         buffer.staging_size += size as i64;
         buffer.total_size += size as i64;
 
@@ -348,7 +430,7 @@ impl MemoryBuffer {
 impl MemoryBuffer {
     fn direct_push(&self, blocks: Vec<Block>) -> Result<()> {
         let len: u64 = blocks.iter().map(|block| block.length).sum::<i32>() as u64;
-        self.append(blocks, len)
+        self.append(blocks, len, None)
     }
 }
 
@@ -450,6 +532,50 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn test_snapshot() -> anyhow::Result<()> {
+        let buffer = MemoryBuffer::new();
+        buffer.direct_push(create_blocks(0, 5, 10))?;
+        let spill_result = buffer.spill()?;
+        buffer.direct_push(create_blocks(5, 5, 10))?;
+
+        let snapshot = buffer.snapshot(None);
+        assert_eq!(0, snapshot.flush_sequence);
+        let block_ids: Vec<i64> = snapshot.blocks.iter().map(|x| x.block_id).collect();
+        assert_eq!((0..10).collect::<Vec<i64>>(), block_ids);
+
+        buffer.clear(spill_result.flight_id(), spill_result.flight_len())?;
+        let snapshot = buffer.snapshot(None);
+        assert_eq!(1, snapshot.flush_sequence);
+        assert_eq!(5, snapshot.blocks.len());
+        assert_eq!(5, snapshot.blocks[0].block_id);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_unflushed_write_sequence() -> anyhow::Result<()> {
+        let buffer = MemoryBuffer::new();
+        assert_eq!(None, buffer.unflushed_write_sequence());
+
+        buffer.append(create_blocks(0, 5, 10), 50, Some(2))?;
+        buffer.append(create_blocks(5, 5, 10), 50, Some(1))?;
+        assert_eq!(Some(1), buffer.unflushed_write_sequence());
+
+        let first = buffer.spill()?;
+        buffer.append(create_blocks(10, 5, 10), 50, Some(3))?;
+        let second = buffer.spill()?;
+        assert_eq!(Some(1), buffer.unflushed_write_sequence());
+
+        // the flights may be persisted out of order
+        buffer.clear(second.flight_id(), second.flight_len())?;
+        assert_eq!(Some(1), buffer.unflushed_write_sequence());
+        buffer.clear(first.flight_id(), first.flight_len())?;
+        assert_eq!(None, buffer.unflushed_write_sequence());
+
+        Ok(())
+    }
+
     #[test]
     fn test_linked_hashmap() {
         let mut map = LinkedHashMap::new();
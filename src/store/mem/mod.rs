@@ -15,9 +15,12 @@
 // specific language governing permissions and limitations
 // under the License.
 
+pub mod admission;
 pub mod budget;
 pub mod buffer;
 pub mod capacity;
+pub mod shard;
+pub mod snapshot;
 pub mod ticket;
 
 pub use await_tree::InstrumentAwait;
@@ -0,0 +1,240 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::config::MapHasherType;
+use dashmap::DashMap;
+use fxhash::FxHasher;
+use std::collections::hash_map::{DefaultHasher, RandomState};
+use std::hash::{BuildHasher, Hash, Hasher};
+use std::time::{Duration, Instant};
+
+/// The hasher of the memory store map chosen by the config.
+#[derive(Clone, Default)]
+pub enum MapHasher {
+    Std(RandomState),
+    Ahash(ahash::RandomState),
+    #[default]
+    Fxhash,
+}
+
+impl MapHasher {
+    pub fn new(hasher_type: MapHasherType) -> Self {
+        match hasher_type {
+            MapHasherType::Std => MapHasher::Std(RandomState::new()),
+            MapHasherType::Ahash => MapHasher::Ahash(ahash::RandomState::new()),
+            MapHasherType::Fxhash => MapHasher::Fxhash,
+        }
+    }
+
+    pub fn hasher_type(&self) -> MapHasherType {
+        match self {
+            MapHasher::Std(_) => MapHasherType::Std,
+            MapHasher::Ahash(_) => MapHasherType::Ahash,
+            MapHasher::Fxhash => MapHasherType::Fxhash,
+        }
+    }
+}
+
+impl BuildHasher for MapHasher {
+    type Hasher = MapHasherState;
+
+    fn build_hasher(&self) -> Self::Hasher {
+        match self {
+            MapHasher::Std(state) => MapHasherState::Std(state.build_hasher()),
+            MapHasher::Ahash(state) => MapHasherState::Ahash(state.build_hasher()),
+            MapHasher::Fxhash => MapHasherState::Fxhash(FxHasher::default()),
+        }
+    }
+}
+
+pub enum MapHasherState {
+    Std(DefaultHasher),
+    Ahash(ahash::AHasher),
+    Fxhash(FxHasher),
+}
+
+impl Hasher for MapHasherState {
+    fn finish(&self) -> u64 {
+        match self {
+            MapHasherState::Std(hasher) => hasher.finish(),
+            MapHasherState::Ahash(hasher) => hasher.finish(),
+            MapHasherState::Fxhash(hasher) => hasher.finish(),
+        }
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        match self {
+            MapHasherState::Std(hasher) => hasher.write(bytes),
+            MapHasherState::Ahash(hasher) => hasher.write(bytes),
+            MapHasherState::Fxhash(hasher) => hasher.write(bytes),
+        }
+    }
+
+    // the partition id is hashed by the integers, which are specialized by the hashers
+    fn write_i32(&mut self, i: i32) {
+        match self {
+            MapHasherState::Std(hasher) => hasher.write_i32(i),
+            MapHasherState::Ahash(hasher) => hasher.write_i32(i),
+            MapHasherState::Fxhash(hasher) => hasher.write_i32(i),
+        }
+    }
+
+    fn write_u64(&mut self, i: u64) {
+        match self {
+            MapHasherState::Std(hasher) => hasher.write_u64(i),
+            MapHasherState::Ahash(hasher) => hasher.write_u64(i),
+            MapHasherState::Fxhash(hasher) => hasher.write_u64(i),
+        }
+    }
+
+    fn write_usize(&mut self, i: usize) {
+        match self {
+            MapHasherState::Std(hasher) => hasher.write_usize(i),
+            MapHasherState::Ahash(hasher) => hasher.write_usize(i),
+            MapHasherState::Fxhash(hasher) => hasher.write_usize(i),
+        }
+    }
+}
+
+/// The sampled entries and the read lock wait of every shard.
+#[derive(Debug, Clone, Default)]
+pub struct ShardStats {
+    pub entries: Vec<usize>,
+    pub lock_waits: Vec<Duration>,
+}
+
+impl ShardStats {
+    /// The shards are locked one by one, so the stats are not the consistent snapshot.
+    pub fn sample<K, V, S>(map: &DashMap<K, V, S>) -> Self
+    where
+        K: Eq + Hash,
+        S: BuildHasher + Clone,
+    {
+        let shards = map.shards();
+        let mut entries = Vec::with_capacity(shards.len());
+        let mut lock_waits = Vec::with_capacity(shards.len());
+        for shard in shards.iter() {
+            let started = Instant::now();
+            let guard = shard.read();
+            lock_waits.push(started.elapsed());
+            entries.push(guard.len());
+        }
+        Self {
+            entries,
+            lock_waits,
+        }
+    }
+
+    pub fn total_entries(&self) -> usize {
+        self.entries.iter().sum()
+    }
+
+    /// The max entries divided by the mean, 1.0 means the entries are evenly distributed.
+    pub fn imbalance_ratio(&self) -> f64 {
+        let total = self.total_entries();
+        if total == 0 {
+            return 1.0;
+        }
+        let max = self.entries.iter().max().copied().unwrap_or(0);
+        let mean = total as f64 / self.entries.len() as f64;
+        max as f64 / mean
+    }
+
+    pub fn max_lock_wait(&self) -> Duration {
+        self.lock_waits.iter().max().copied().unwrap_or_default()
+    }
+
+    /// The (shard index, entries) of the top n shards by the entries.
+    pub fn worst_shards(&self, n: usize) -> Vec<(usize, usize)> {
+        let mut shards: Vec<(usize, usize)> = self.entries.iter().copied().enumerate().collect();
+        shards.sort_by(|x, y| y.1.cmp(&x.1));
+        shards.truncate(n);
+        shards
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::MapHasherType;
+    use crate::store::mem::shard::{MapHasher, ShardStats};
+    use dashmap::DashMap;
+    use fxhash::FxHasher;
+    use std::hash::{BuildHasher, Hash, Hasher};
+
+    #[test]
+    fn test_skewed_shard_stats() {
+        let map: DashMap<i32, i32, MapHasher> =
+            DashMap::with_hasher_and_shard_amount(MapHasher::default(), 16);
+        let stats = ShardStats::sample(&map);
+        assert_eq!(16, stats.entries.len());
+        assert_eq!(1.0, stats.imbalance_ratio());
+
+        // all the keys are located in the same shard
+        let skewed_shard = map.determine_map(&0);
+        let mut key = 0;
+        while map.len() < 160 {
+            if map.determine_map(&key) == skewed_shard {
+                map.insert(key, key);
+            }
+            key += 1;
+        }
+        let stats = ShardStats::sample(&map);
+        assert_eq!(16.0, stats.imbalance_ratio());
+        assert_eq!(vec![(skewed_shard, 160)], stats.worst_shards(1));
+
+        // evenly distributed
+        map.clear();
+        let mut key = 0;
+        while map.len() < 160 {
+            let shard = map.determine_map(&key);
+            if map.shards()[shard].read().len() < 10 {
+                map.insert(key, key);
+            }
+            key += 1;
+        }
+        let stats = ShardStats::sample(&map);
+        assert_eq!(1.0, stats.imbalance_ratio());
+    }
+
+    #[test]
+    fn test_map_hasher() {
+        for hasher_type in [
+            MapHasherType::Std,
+            MapHasherType::Ahash,
+            MapHasherType::Fxhash,
+        ] {
+            assert_eq!(hasher_type, MapHasher::new(hasher_type).hasher_type());
+        }
+
+        let hash = |build_hasher: &dyn Fn() -> Box<dyn Hasher>| {
+            let mut hasher = build_hasher();
+            ("app", 1, 2).hash(&mut hasher);
+            hasher.finish()
+        };
+        let fxhash = MapHasher::new(MapHasherType::Fxhash);
+        assert_eq!(
+            hash(&|| Box::new(FxHasher::default())),
+            hash(&|| Box::new(fxhash.build_hasher()))
+        );
+
+        // the std and ahash are randomly seeded per instance
+        let std = MapHasher::new(MapHasherType::Std);
+        let std_hash = hash(&|| Box::new(std.build_hasher()));
+        assert_eq!(std_hash, hash(&|| Box::new(std.build_hasher())));
+        assert_ne!(std_hash, hash(&|| Box::new(fxhash.build_hasher())));
+    }
+}
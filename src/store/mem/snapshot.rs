@@ -0,0 +1,114 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BufferEntry {
+    pub app_id: String,
+    pub shuffle_id: i32,
+    pub partition_id: i32,
+    pub staging_bytes: i64,
+    // picked up for spill and not flushed yet
+    pub in_flight_bytes: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TicketReservation {
+    pub app_id: String,
+    pub tickets: usize,
+    pub reserved_bytes: i64,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+pub struct MemoryTotals {
+    pub used: i64,
+    pub in_flight: i64,
+    pub allocated: i64,
+}
+
+impl MemoryTotals {
+    fn minus(&self, other: &MemoryTotals) -> MemoryTotals {
+        MemoryTotals {
+            used: self.used - other.used,
+            in_flight: self.in_flight - other.in_flight,
+            allocated: self.allocated - other.allocated,
+        }
+    }
+
+    fn max_abs(&self) -> i64 {
+        self.used
+            .abs()
+            .max(self.in_flight.abs())
+            .max(self.allocated.abs())
+    }
+}
+
+/// The dump of every buffer and ticket reservation of the memory store. It's taken by
+/// iterating the shards one by one, so the totals summed from them are only best-effort
+/// consistent with the ones tracked by the budget under the concurrent writing.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MemorySnapshot {
+    pub capacity: i64,
+    // tracked by the memory budget
+    pub tracked: MemoryTotals,
+    // summed from the buffers and the tickets
+    pub summed: MemoryTotals,
+    // the tracked minus the summed
+    pub drift: MemoryTotals,
+    // the drift exceeds the threshold
+    pub drifted: bool,
+    // sorted by the partition
+    pub buffers: Vec<BufferEntry>,
+    pub tickets: Vec<TicketReservation>,
+}
+
+impl MemorySnapshot {
+    pub fn new(
+        capacity: i64,
+        tracked: MemoryTotals,
+        mut buffers: Vec<BufferEntry>,
+        mut tickets: Vec<TicketReservation>,
+        drift_threshold: u64,
+    ) -> Self {
+        buffers.sort_by(|a, b| {
+            (&a.app_id, a.shuffle_id, a.partition_id).cmp(&(
+                &b.app_id,
+                b.shuffle_id,
+                b.partition_id,
+            ))
+        });
+        tickets.sort_by(|a, b| a.app_id.cmp(&b.app_id));
+
+        let in_flight: i64 = buffers.iter().map(|x| x.in_flight_bytes).sum();
+        let summed = MemoryTotals {
+            used: buffers.iter().map(|x| x.staging_bytes).sum::<i64>() + in_flight,
+            in_flight,
+            allocated: tickets.iter().map(|x| x.reserved_bytes).sum(),
+        };
+        let drift = tracked.minus(&summed);
+        MemorySnapshot {
+            capacity,
+            tracked,
+            summed,
+            drift,
+            drifted: drift.max_abs() as u64 > drift_threshold,
+            buffers,
+            tickets,
+        }
+    }
+}
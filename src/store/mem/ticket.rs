@@ -24,6 +24,7 @@ use await_tree::InstrumentAwait;
 use dashmap::DashMap;
 use fastrace::trace;
 use log::warn;
+use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::Duration;
 use tracing::Instrument;
@@ -122,6 +123,19 @@ impl TicketManager {
         size
     }
 
+    /// The (tickets number, reserved size) of every app.
+    pub fn reservations(&self) -> HashMap<String, (usize, i64)> {
+        let mut reservations: HashMap<String, (usize, i64)> = HashMap::new();
+        for ticket in self.ticket_store.iter() {
+            let reserved = reservations
+                .entry(ticket.owned_by_app_id.clone())
+                .or_default();
+            reserved.0 += 1;
+            reserved.1 += ticket.size;
+        }
+        reservations
+    }
+
     /// insert one ticket managed by this ticket manager
     #[trace]
     pub fn insert(&self, ticket_id: i64, size: i64, created_timestamp: u64, app_id: &str) -> bool {
@@ -143,17 +157,19 @@ impl TicketManager {
         runtime_manager: RuntimeManager,
     ) {
         let await_tree_registry = AWAIT_TREE_REGISTRY.clone();
-        runtime_manager.default_runtime.spawn(async move {
-            let await_root = await_tree_registry
-                .register("Ticket schedule to check".to_string())
-                .await;
-            await_root
-                .instrument(TicketManager::ticket_check(
-                    ticket_manager,
-                    free_allocated_fn,
-                ))
-                .await;
-        });
+        runtime_manager
+            .default_runtime
+            .spawn_guarded("ticket_checker", async move {
+                let await_root = await_tree_registry
+                    .register_long_running("Ticket schedule to check".to_string())
+                    .await;
+                await_root
+                    .instrument(TicketManager::ticket_check(
+                        ticket_manager,
+                        free_allocated_fn,
+                    ))
+                    .await;
+            });
     }
 
     async fn ticket_check<F: FnMut(i64) -> bool + Send + 'static>(
@@ -20,41 +20,61 @@ use crate::app::{
     PartitionedUId, PurgeDataContext, ReadingIndexViewContext, ReadingViewContext,
     RegisterAppContext, ReleaseTicketContext, RequireBufferContext, WritingViewContext,
 };
-use crate::config::{MemoryStoreConfig, StorageType};
+use crate::config::{MemoryStoreConfig, StorageType, DEFAULT_SNAPSHOT_DRIFT_THRESHOLD};
 use crate::error::WorkerError;
-use crate::metric::TOTAL_MEMORY_USED;
-use crate::readable_size::ReadableSize;
-use crate::store::{Block, RequireBufferResponse, ResponseData, ResponseDataIndex, Store};
+use crate::health::{ComponentHealth, HealthProvider, HealthStatus, RecentEventCounter};
+use crate::metric::{
+    GAUGE_MEMORY_STORE_SHARD_IMBALANCE_RATIO, GAUGE_MEMORY_STORE_SHARD_MAX_LOCK_WAIT_MICROS,
+    MEMORY_TICKET_WAIT_DURATION, TOTAL_MEMORY_SNAPSHOT_DRIFTED, TOTAL_MEMORY_USED,
+};
+use crate::store::{
+    Block, PartitionStat, RequireBufferResponse, ResponseData, ResponseDataIndex, Store,
+    StoreCapabilities, StoreTier,
+};
 use crate::*;
 use async_trait::async_trait;
 use dashmap::DashMap;
 
 use std::collections::{BTreeMap, HashMap};
-use std::hash::BuildHasherDefault;
-
-use std::str::FromStr;
 
 use crate::store::mem::budget::MemoryBudget;
 use crate::store::mem::buffer::MemoryBuffer;
 use crate::store::mem::capacity::CapacitySnapshot;
+use crate::store::mem::shard::{MapHasher, ShardStats};
+use crate::store::mem::snapshot::{BufferEntry, MemorySnapshot, MemoryTotals, TicketReservation};
 use crate::store::mem::ticket::TicketManager;
 use crate::store::spill::SpillWritingViewContext;
 use anyhow::anyhow;
 use croaring::Treemap;
 use fastrace::trace;
-use fxhash::{FxBuildHasher, FxHasher};
 use log::{debug, warn};
-use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Semaphore;
+
+// the worst shards are logged when the imbalance ratio exceeds it
+const SHARD_IMBALANCE_WARN_RATIO: f64 = 2.0;
+// the nearly empty store is always skewed by the few buffers, so it's not warned
+const SHARD_IMBALANCE_WARN_MIN_ENTRIES: usize = 1024;
+const SHARD_IMBALANCE_LOGGED_SHARDS: usize = 5;
+
+// the requirement queued at the fairness gate waits for the capacity at most this long
+const TICKET_FAIRNESS_WAIT_TIMEOUT: Duration = Duration::from_secs(1);
+const TICKET_FAIRNESS_RECHECK_INTERVAL: Duration = Duration::from_millis(10);
 
 pub struct MemoryStore {
-    state: DashMap<PartitionedUId, Arc<MemoryBuffer>, BuildHasherDefault<FxHasher>>,
+    state: DashMap<PartitionedUId, Arc<MemoryBuffer>, MapHasher>,
+    shard_stats_interval: Option<Duration>,
     budget: MemoryBudget,
     // key: app_id, value: allocated memory size
     memory_capacity: i64,
-    in_flush_buffer_size: AtomicU64,
     runtime_manager: RuntimeManager,
     ticket_manager: TicketManager,
+    allocation_failures: RecentEventCounter,
+    // the tokio semaphore is FIFO, so the long-waiting requirements won't be jumped. It's
+    // only held by the one waiting for the capacity, the others queue behind it
+    ticket_fairness_gate: Option<Semaphore>,
+    snapshot_drift_threshold: u64,
 }
 
 unsafe impl Send for MemoryStore {}
@@ -74,17 +94,20 @@ impl MemoryStore {
             TicketManager::new(5 * 60, 10, release_allocated_func, runtime_manager.clone());
         MemoryStore {
             budget,
-            state: DashMap::with_hasher(FxBuildHasher::default()),
+            state: DashMap::with_hasher(MapHasher::default()),
+            shard_stats_interval: None,
             memory_capacity: max_memory_size,
             ticket_manager,
-            in_flush_buffer_size: Default::default(),
             runtime_manager,
+            allocation_failures: RecentEventCounter::new(60),
+            ticket_fairness_gate: None,
+            snapshot_drift_threshold: DEFAULT_SNAPSHOT_DRIFT_THRESHOLD,
         }
     }
 
     pub fn from(conf: MemoryStoreConfig, runtime_manager: RuntimeManager) -> Self {
-        let capacity = ReadableSize::from_str(&conf.capacity).unwrap();
-        let budget = MemoryBudget::new(capacity.as_bytes() as i64);
+        let capacity = conf.capacity_bytes().unwrap();
+        let budget = MemoryBudget::new(capacity as i64);
 
         let budget_clone = budget.clone();
         let release_allocated_func =
@@ -99,16 +122,129 @@ impl MemoryStore {
 
         /// the dashmap shard that will effect the lookup performance.
         let shard_amount = conf.dashmap_shard_amount;
-        let dashmap = DashMap::with_hasher_and_shard_amount(FxBuildHasher::default(), shard_amount);
+        let dashmap = DashMap::with_hasher_and_shard_amount(
+            MapHasher::new(conf.dashmap_hasher),
+            shard_amount,
+        );
+        let shard_stats_interval = conf.dashmap_shard_stats_interval().unwrap();
+
+        let ticket_fairness_gate = match conf.ticket_fairness {
+            Some(true) => Some(Semaphore::new(1)),
+            _ => None,
+        };
+        let snapshot_drift_threshold = conf.snapshot_drift_threshold_bytes().unwrap();
 
         MemoryStore {
             state: dashmap,
-            budget: MemoryBudget::new(capacity.as_bytes() as i64),
-            memory_capacity: capacity.as_bytes() as i64,
+            shard_stats_interval: Some(shard_stats_interval),
+            budget,
+            memory_capacity: capacity as i64,
             ticket_manager,
-            in_flush_buffer_size: Default::default(),
             runtime_manager,
+            allocation_failures: RecentEventCounter::new(60),
+            ticket_fairness_gate,
+            snapshot_drift_threshold,
+        }
+    }
+
+    /// The fast path goes without the gate when nobody is waiting. Otherwise the requirement
+    /// queues at the gate, and the holder waits for the capacity until the timeout.
+    async fn require_allocated_fairly(
+        &self,
+        gate: &Semaphore,
+        size: i64,
+    ) -> Result<(bool, i64), WorkerError> {
+        if gate.available_permits() > 0 {
+            let allocated = self.budget.require_allocated(size)?;
+            if allocated.0 {
+                return Ok(allocated);
+            }
+        }
+        let _permit = gate.acquire().await?;
+        let deadline = Instant::now() + TICKET_FAIRNESS_WAIT_TIMEOUT;
+        loop {
+            let allocated = self.budget.require_allocated(size)?;
+            if allocated.0 || Instant::now() >= deadline {
+                return Ok(allocated);
+            }
+            tokio::time::sleep(TICKET_FAIRNESS_RECHECK_INTERVAL).await;
+        }
+    }
+
+    pub fn shard_stats(&self) -> ShardStats {
+        ShardStats::sample(&self.state)
+    }
+
+    fn report_shard_stats(&self) -> ShardStats {
+        let stats = self.shard_stats();
+        let ratio = stats.imbalance_ratio();
+        GAUGE_MEMORY_STORE_SHARD_IMBALANCE_RATIO.set(ratio);
+        GAUGE_MEMORY_STORE_SHARD_MAX_LOCK_WAIT_MICROS.set(stats.max_lock_wait().as_micros() as i64);
+        if is_shard_imbalance_warned(&stats) {
+            warn!(
+                "The memory store shards are imbalanced with the ratio: {:.2}, hasher: {:?}. The worst (shard, entries): {:?}",
+                ratio,
+                self.state.hasher().hasher_type(),
+                stats.worst_shards(SHARD_IMBALANCE_LOGGED_SHARDS)
+            );
         }
+        stats
+    }
+
+    /// Dump every buffer and ticket reservation without stopping the world, the drift of
+    /// the summed totals from the tracked ones beyond the threshold is warned.
+    pub fn snapshot(&self) -> MemorySnapshot {
+        // only the refs are collected under the shard locks
+        let buffers: Vec<(PartitionedUId, Arc<MemoryBuffer>)> = self
+            .state
+            .iter()
+            .map(|x| (x.key().clone(), x.value().clone()))
+            .collect();
+        let buffers = buffers
+            .into_iter()
+            .map(|(uid, buffer)| {
+                let (staging_bytes, in_flight_bytes) = buffer.staging_and_flight_size();
+                BufferEntry {
+                    app_id: uid.app_id,
+                    shuffle_id: uid.shuffle_id,
+                    partition_id: uid.partition_id,
+                    staging_bytes,
+                    in_flight_bytes,
+                }
+            })
+            .collect();
+        let tickets = self
+            .ticket_manager
+            .reservations()
+            .into_iter()
+            .map(|(app_id, (tickets, reserved_bytes))| TicketReservation {
+                app_id,
+                tickets,
+                reserved_bytes,
+            })
+            .collect();
+
+        let budget = self.budget.snapshot();
+        let tracked = MemoryTotals {
+            used: budget.used(),
+            in_flight: self.budget.in_flight(),
+            allocated: budget.allocated(),
+        };
+        let snapshot = MemorySnapshot::new(
+            budget.capacity(),
+            tracked,
+            buffers,
+            tickets,
+            self.snapshot_drift_threshold,
+        );
+        if snapshot.drifted {
+            TOTAL_MEMORY_SNAPSHOT_DRIFTED.inc();
+            warn!(
+                "The memory snapshot drifts beyond the threshold: {} bytes. tracked: {:?}, summed: {:?}",
+                self.snapshot_drift_threshold, &snapshot.tracked, &snapshot.summed
+            );
+        }
+        snapshot
     }
 
     pub fn memory_snapshot(&self) -> Result<CapacitySnapshot> {
@@ -121,16 +257,24 @@ impl MemoryStore {
 
     pub fn calculate_usage_ratio(&self) -> f32 {
         let snapshot = self.budget.snapshot();
-        (snapshot.used() - self.in_flush_buffer_size.load(Ordering::SeqCst) as i64) as f32
-            / snapshot.capacity() as f32
+        (snapshot.used() - self.budget.in_flight()) as f32 / snapshot.capacity() as f32
     }
 
     pub fn inc_inflight(&self, size: u64) {
-        self.in_flush_buffer_size.fetch_add(size, Ordering::SeqCst);
+        self.budget.inc_in_flight(size as i64);
+    }
+
+    pub fn in_flight_size(&self) -> i64 {
+        self.budget.in_flight()
     }
 
     pub fn dec_inflight(&self, size: u64) {
-        self.in_flush_buffer_size.fetch_sub(size, Ordering::SeqCst);
+        self.budget.dec_in_flight(size as i64);
+    }
+
+    /// Release the spilled data which has been flushed into the persistent store.
+    pub fn release_flushed(&self, size: i64) -> Result<bool> {
+        self.budget.release_flushed(size)
     }
 
     pub fn inc_used(&self, size: i64) -> Result<bool> {
@@ -223,6 +367,10 @@ impl MemoryStore {
         buffer.clone()
     }
 
+    pub fn get_memory_buffer(&self, uid: &PartitionedUId) -> Option<Arc<MemoryBuffer>> {
+        self.state.get(uid).map(|buffer| buffer.clone())
+    }
+
     fn get_underlying_partition_buffer(&self, pid: &PartitionedUId) -> Arc<MemoryBuffer> {
         self.state.get(pid).unwrap().clone()
     }
@@ -253,10 +401,32 @@ impl MemoryStore {
     }
 }
 
+fn is_shard_imbalance_warned(stats: &ShardStats) -> bool {
+    stats.total_entries() >= SHARD_IMBALANCE_WARN_MIN_ENTRIES
+        && stats.imbalance_ratio() > SHARD_IMBALANCE_WARN_RATIO
+}
+
 #[async_trait]
 impl Store for MemoryStore {
     fn start(self: Arc<Self>) {
-        // ignore
+        let interval = match self.shard_stats_interval {
+            Some(interval) => interval,
+            _ => return,
+        };
+        let store = Arc::downgrade(&self);
+        self.runtime_manager
+            .default_runtime
+            .spawn_guarded("memory_shard_stats", async move {
+                loop {
+                    tokio::time::sleep(interval).await;
+                    match store.upgrade() {
+                        Some(store) => {
+                            store.report_shard_stats();
+                        }
+                        _ => break,
+                    }
+                }
+            });
     }
 
     #[trace]
@@ -266,7 +436,7 @@ impl Store for MemoryStore {
         let size = ctx.data_size;
 
         let buffer = self.get_or_create_memory_buffer(uid);
-        buffer.append(blocks, ctx.data_size)?;
+        buffer.append(blocks, ctx.data_size, ctx.write_sequence)?;
 
         TOTAL_MEMORY_USED.inc_by(size);
 
@@ -284,7 +454,12 @@ impl Store for MemoryStore {
                 max_size,
                 ctx.serialized_expected_task_ids_bitmap,
             )?,
-            _ => panic!("Should not happen."),
+            _ => {
+                return Err(WorkerError::UNSUPPORTED_STORE_OPERATION(
+                    "get by the file offset",
+                    "memory",
+                ))
+            }
         };
 
         Ok(ResponseData::Mem(read_data))
@@ -295,7 +470,10 @@ impl Store for MemoryStore {
         &self,
         _ctx: ReadingIndexViewContext,
     ) -> Result<ResponseDataIndex, WorkerError> {
-        panic!("It should not be invoked.")
+        Err(WorkerError::UNSUPPORTED_STORE_OPERATION(
+            "get_index",
+            "memory",
+        ))
     }
 
     #[trace]
@@ -349,7 +527,12 @@ impl Store for MemoryStore {
         &self,
         ctx: RequireBufferContext,
     ) -> Result<RequireBufferResponse, WorkerError> {
-        let (succeed, ticket_id) = self.budget.require_allocated(ctx.size)?;
+        let timer = Instant::now();
+        let (succeed, ticket_id) = match &self.ticket_fairness_gate {
+            Some(gate) => self.require_allocated_fairly(gate, ctx.size).await?,
+            _ => self.budget.require_allocated(ctx.size)?,
+        };
+        MEMORY_TICKET_WAIT_DURATION.observe(timer.elapsed().as_secs_f64());
         debug!(
             "gotten the requirement: {:?} for uid: {:?}",
             succeed, &ctx.uid
@@ -366,7 +549,10 @@ impl Store for MemoryStore {
                 debug!("Inserted into the ticket for uid: {:?}", &ctx.uid);
                 Ok(require_buffer_resp)
             }
-            _ => Err(WorkerError::NO_ENOUGH_MEMORY_TO_BE_ALLOCATED),
+            _ => {
+                self.allocation_failures.record();
+                Err(WorkerError::NO_ENOUGH_MEMORY_TO_BE_ALLOCATED)
+            }
         }
     }
 
@@ -386,25 +572,57 @@ impl Store for MemoryStore {
         StorageType::MEMORY
     }
 
+    fn capabilities(&self) -> StoreCapabilities {
+        StoreCapabilities {
+            supports_paging: true,
+            supports_index: false,
+            supports_buffer: true,
+            durable: false,
+            tier: StoreTier::HOT,
+        }
+    }
+
+    fn partition_stat(&self, uid: &PartitionedUId) -> Option<PartitionStat> {
+        self.state.get(uid).map(|buffer| PartitionStat {
+            size: buffer.total_size().unwrap_or(0) as u64,
+            location: None,
+        })
+    }
+
     #[trace]
     async fn spill_insert(&self, _ctx: SpillWritingViewContext) -> Result<(), WorkerError> {
-        todo!()
+        Err(WorkerError::UNSUPPORTED_STORE_OPERATION(
+            "spill_insert",
+            "memory",
+        ))
     }
 }
 
-pub struct MemorySnapshot {
-    capacity: i64,
-    allocated: i64,
-    used: i64,
-}
-
-impl From<(i64, i64, i64)> for MemorySnapshot {
-    fn from(value: (i64, i64, i64)) -> Self {
-        MemorySnapshot {
-            capacity: value.0,
-            allocated: value.1,
-            used: value.2,
-        }
+#[async_trait]
+impl HealthProvider for MemoryStore {
+    async fn component_health(&self) -> Vec<ComponentHealth> {
+        let snapshot = self.budget.snapshot();
+        let usage_ratio =
+            (snapshot.used() + snapshot.allocated()) as f64 / snapshot.capacity().max(1) as f64;
+        let allocation_failures = self.allocation_failures.count();
+        // the allocation failures mean the clients are under backpressure
+        let status = if allocation_failures > 0 {
+            HealthStatus::Degraded
+        } else {
+            HealthStatus::Healthy
+        };
+        vec![ComponentHealth::new(
+            "memory_store",
+            status,
+            true,
+            serde_json::json!({
+                "usage_ratio": usage_ratio,
+                "capacity": snapshot.capacity(),
+                "used": snapshot.used(),
+                "allocated": snapshot.allocated(),
+                "allocation_failures_in_last_minute": allocation_failures,
+            }),
+        )]
     }
 }
 
@@ -415,7 +633,17 @@ mod test {
         WritingViewContext,
     };
 
-    use crate::store::memory::MemoryStore;
+    use crate::config::{MapHasherType, MemoryStoreConfig};
+    use crate::metric::{
+        register_custom_metrics, GAUGE_MEMORY_STORE_SHARD_IMBALANCE_RATIO,
+        MEMORY_TICKET_WAIT_DURATION, REGISTRY, TOTAL_MEMORY_SNAPSHOT_DRIFTED,
+    };
+    use crate::runtime::manager::RuntimeManager;
+    use crate::store::mem::shard::ShardStats;
+    use crate::store::mem::snapshot::{BufferEntry, MemoryTotals, TicketReservation};
+    use crate::store::memory::{
+        is_shard_imbalance_warned, MemoryStore, SHARD_IMBALANCE_WARN_MIN_ENTRIES,
+    };
     use crate::store::ResponseData::Mem;
 
     use crate::store::{Block, PartitionedMemoryData, ResponseData, Store};
@@ -423,10 +651,195 @@ mod test {
     use bytes::BytesMut;
     use core::panic;
     use std::sync::Arc;
+    use std::time::Duration;
 
     use anyhow::Result;
     use croaring::Treemap;
 
+    #[test]
+    fn test_snapshot() -> Result<()> {
+        let mut store = MemoryStore::new(1024);
+        store.snapshot_drift_threshold = 10;
+        let runtime = store.runtime_manager.clone();
+
+        let write = |uid: &PartitionedUId, len: i32| -> Result<()> {
+            let block = Block {
+                block_id: 0,
+                length: len,
+                uncompress_length: len,
+                crc: 0,
+                data: Default::default(),
+                task_attempt_id: 0,
+            };
+            runtime.wait(store.insert(WritingViewContext::new(
+                uid.clone(),
+                vec![block],
+                false,
+                len as u64,
+            )))?;
+            store.inc_used(len as i64)?;
+            Ok(())
+        };
+        let uid_1 = PartitionedUId::from("app_b".to_string(), 1, 0);
+        let uid_2 = PartitionedUId::from("app_a".to_string(), 2, 1);
+        write(&uid_1, 30)?;
+        write(&uid_2, 20)?;
+        // the buffer of app_b is picked up for spill
+        let flight = store.get_memory_buffer(&uid_1).unwrap().spill()?;
+        store.inc_inflight(flight.flight_len());
+        write(&uid_1, 5)?;
+        // the tickets reserved and not released yet
+        for _ in 0..2 {
+            runtime.wait(store.require_buffer(RequireBufferContext::new(uid_2.clone(), 40)))?;
+        }
+
+        // case1: consistent with the known state
+        let snapshot = store.snapshot();
+        assert_eq!(1024, snapshot.capacity);
+        let expected = MemoryTotals {
+            used: 55,
+            in_flight: 30,
+            allocated: 80,
+        };
+        assert_eq!(expected, snapshot.tracked);
+        assert_eq!(expected, snapshot.summed);
+        assert_eq!(MemoryTotals::default(), snapshot.drift);
+        assert!(!snapshot.drifted);
+        assert_eq!(
+            vec![
+                BufferEntry {
+                    app_id: "app_a".to_string(),
+                    shuffle_id: 2,
+                    partition_id: 1,
+                    staging_bytes: 20,
+                    in_flight_bytes: 0,
+                },
+                BufferEntry {
+                    app_id: "app_b".to_string(),
+                    shuffle_id: 1,
+                    partition_id: 0,
+                    staging_bytes: 5,
+                    in_flight_bytes: 30,
+                },
+            ],
+            snapshot.buffers
+        );
+        assert_eq!(
+            vec![TicketReservation {
+                app_id: "app_a".to_string(),
+                tickets: 2,
+                reserved_bytes: 80,
+            }],
+            snapshot.tickets
+        );
+
+        // case2: the drift within the threshold is tolerated
+        store.inc_used(10)?;
+        let snapshot = store.snapshot();
+        assert_eq!(10, snapshot.drift.used);
+        assert!(!snapshot.drifted);
+
+        // case3: the corrupted counter is detected
+        let drifted = TOTAL_MEMORY_SNAPSHOT_DRIFTED.get();
+        store.dec_inflight(30);
+        let snapshot = store.snapshot();
+        assert_eq!(-30, snapshot.drift.in_flight);
+        assert!(snapshot.drifted);
+        assert!(TOTAL_MEMORY_SNAPSHOT_DRIFTED.get() > drifted);
+        Ok(())
+    }
+
+    #[test]
+    fn test_shard_stats() {
+        let toml_str = r#"
+        capacity = "1M"
+        dashmap_shard_amount = 8
+        dashmap_hasher = "ahash"
+        "#;
+        let config: MemoryStoreConfig = toml::from_str(toml_str).unwrap();
+        assert_eq!(MapHasherType::Ahash, config.dashmap_hasher);
+        let store = MemoryStore::from(config, Default::default());
+        assert_eq!(MapHasherType::Ahash, store.state.hasher().hasher_type());
+
+        // the skewed partitions located in the same shard
+        let skewed_shard =
+            store
+                .state
+                .determine_map(&PartitionedUId::from("app".to_string(), 0, 0));
+        let mut partition_id = 0;
+        while store.state.len() < 80 {
+            let uid = PartitionedUId::from("app".to_string(), 0, partition_id);
+            if store.state.determine_map(&uid) == skewed_shard {
+                store.get_or_create_memory_buffer(uid);
+            }
+            partition_id += 1;
+        }
+        let stats = store.report_shard_stats();
+        assert_eq!(8.0, stats.imbalance_ratio());
+        assert_eq!(8.0, GAUGE_MEMORY_STORE_SHARD_IMBALANCE_RATIO.get());
+        assert_eq!(vec![(skewed_shard, 80)], stats.worst_shards(1));
+        // too few entries to be warned
+        assert!(!is_shard_imbalance_warned(&stats));
+
+        let mut stats = ShardStats {
+            entries: vec![0; 8],
+            lock_waits: vec![Default::default(); 8],
+        };
+        stats.entries[0] = SHARD_IMBALANCE_WARN_MIN_ENTRIES;
+        assert!(is_shard_imbalance_warned(&stats));
+        stats.entries = vec![SHARD_IMBALANCE_WARN_MIN_ENTRIES; 8];
+        assert!(!is_shard_imbalance_warned(&stats));
+    }
+
+    #[test]
+    fn test_ticket_fairness() {
+        let toml_str = r#"
+        capacity = "1M"
+        ticket_fairness = true
+        "#;
+        let config: MemoryStoreConfig = toml::from_str(toml_str).unwrap();
+        assert_eq!(Some(true), config.ticket_fairness);
+
+        let runtime_manager: RuntimeManager = Default::default();
+        let store = MemoryStore::from(config, runtime_manager.clone());
+        assert!(store.ticket_fairness_gate.is_some());
+
+        let wait_count = MEMORY_TICKET_WAIT_DURATION.get_sample_count();
+        let uid = PartitionedUId::from("app".to_string(), 0, 0);
+        runtime_manager
+            .wait(store.require_buffer(RequireBufferContext::new(uid.clone(), 1024)))
+            .unwrap();
+        // the memory is not enough until the wait timeout
+        assert!(runtime_manager
+            .wait(store.require_buffer(RequireBufferContext::new(uid.clone(), 1024 * 1024)))
+            .is_err());
+        assert!(MEMORY_TICKET_WAIT_DURATION.get_sample_count() >= wait_count + 2);
+
+        // the queued requirement is served once the capacity is released
+        runtime_manager
+            .wait(store.require_buffer(RequireBufferContext::new(uid.clone(), 1023 * 1024)))
+            .unwrap();
+        let (waited, released) = runtime_manager.wait(async {
+            tokio::join!(
+                store.require_buffer(RequireBufferContext::new(uid, 1024)),
+                async {
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                    let gate = store.ticket_fairness_gate.as_ref().unwrap();
+                    assert_eq!(0, gate.available_permits());
+                    store.budget.dec_allocated(1024)
+                }
+            )
+        });
+        assert!(released.unwrap());
+        assert!(waited.is_ok());
+
+        register_custom_metrics();
+        assert!(REGISTRY
+            .gather()
+            .iter()
+            .any(|x| x.get_name() == "memory_ticket_wait_duration"));
+    }
+
     #[test]
     fn test_read_buffer_in_flight() {
         let store = MemoryStore::new(1024);
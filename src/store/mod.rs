@@ -15,6 +15,9 @@
 // specific language governing permissions and limitations
 // under the License.
 
+#[cfg(test)]
+mod conformance;
+pub mod fault;
 #[cfg(feature = "hdfs")]
 pub mod hdfs;
 pub mod hybrid;
@@ -22,6 +25,7 @@ pub mod local;
 pub mod localfile;
 pub mod mem;
 pub mod memory;
+pub mod read_cache;
 mod spill;
 
 use crate::app::{
@@ -30,7 +34,9 @@ use crate::app::{
 };
 use crate::config::{Config, StorageType};
 use crate::error::WorkerError;
-use crate::grpc::protobuf::uniffle::{ShuffleData, ShuffleDataBlockSegment};
+use crate::grpc::protobuf::uniffle::{
+    ShuffleData, ShuffleDataBlockSegment, ShuffleDataBlockSource,
+};
 use crate::store::hybrid::HybridStore;
 use std::fmt::{Display, Formatter};
 
@@ -38,6 +44,7 @@ use crate::util::now_timestamp_as_sec;
 use anyhow::Result;
 use async_trait::async_trait;
 use bytes::Bytes;
+use serde::{Deserialize, Serialize};
 
 use crate::composed_bytes::ComposedBytes;
 use crate::runtime::manager::RuntimeManager;
@@ -61,6 +68,13 @@ pub struct Block {
     pub task_attempt_id: i64,
 }
 
+impl Block {
+    /// Whether the data matches the crc32 computed by the client.
+    pub fn is_crc32_matched(&self) -> bool {
+        crc32fast::hash(&self.data) as i64 == self.crc
+    }
+}
+
 impl From<ShuffleData> for PartitionedData {
     fn from(shuffle_data: ShuffleData) -> PartitionedData {
         let mut blocks = vec![];
@@ -114,6 +128,32 @@ impl ResponseData {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(non_camel_case_types)]
+pub enum BlockSource {
+    MEMORY,
+    LOCALFILE,
+}
+
+impl Into<ShuffleDataBlockSource> for BlockSource {
+    fn into(self) -> ShuffleDataBlockSource {
+        match self {
+            BlockSource::MEMORY => ShuffleDataBlockSource::Memory,
+            BlockSource::LOCALFILE => ShuffleDataBlockSource::Localfile,
+        }
+    }
+}
+
+/// The partition data merged from memory and localfile, every segment
+/// is tagged with the source of its block by `block_sources`.
+#[derive(Default, Debug)]
+pub struct PartitionedMergedData {
+    pub shuffle_data_block_segments: Vec<DataSegment>,
+    pub block_sources: Vec<BlockSource>,
+    pub data: BytesWrapper,
+    pub flush_sequence: u64,
+}
+
 #[derive(Debug)]
 pub struct PartitionedLocalData {
     pub data: Bytes,
@@ -225,6 +265,67 @@ impl RequireBufferResponse {
 
 // =====================================================
 
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PartitionStat {
+    pub size: u64,
+    // the underlying file path for the persistent store
+    pub location: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PartitionStorageStat {
+    pub memory: Option<PartitionStat>,
+    pub localfile: Option<PartitionStat>,
+    pub hdfs: Option<PartitionStat>,
+}
+
+/// Derived from the concurrency limiter acquired by the spill writing.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct SpillConcurrency {
+    pub max_concurrency: usize,
+    pub available_permits: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TierSpillStatus {
+    // like "memory->localfile"
+    pub tier: String,
+    pub in_flight: i64,
+    #[serde(flatten)]
+    pub concurrency: SpillConcurrency,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpillStatus {
+    // the spill events waiting for the handler concurrency
+    pub queued: usize,
+    #[serde(flatten)]
+    pub concurrency: SpillConcurrency,
+    pub tiers: Vec<TierSpillStatus>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum StoreTier {
+    HOT,
+    WARM,
+    COLD,
+}
+
+/// The operations out of the capabilities are rejected by the
+/// [`WorkerError::UNSUPPORTED_STORE_OPERATION`] rather than panicking.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct StoreCapabilities {
+    // memory-style reading by the last block id and the max size
+    pub supports_paging: bool,
+    // file-style reading by the index and then the data offset and length
+    pub supports_index: bool,
+    // the buffer should be required before the writing
+    pub supports_buffer: bool,
+    // the data is kept across the server restarts
+    pub durable: bool,
+    pub tier: StoreTier,
+}
+
 #[async_trait]
 pub trait Store {
     fn start(self: Arc<Self>);
@@ -246,10 +347,28 @@ pub trait Store {
 
     async fn name(&self) -> StorageType;
 
+    fn capabilities(&self) -> StoreCapabilities;
+
     async fn spill_insert(&self, ctx: SpillWritingViewContext) -> Result<(), WorkerError>;
+
+    fn partition_stat(&self, _uid: &PartitionedUId) -> Option<PartitionStat> {
+        None
+    }
 }
 
-pub trait Persistent {}
+pub trait Persistent {
+    fn spill_concurrency(&self) -> SpillConcurrency;
+
+    /// The max used ratio of the disks to be spilled into, None for the non-disk stores.
+    fn disk_used_ratio(&self) -> Option<f64> {
+        None
+    }
+
+    /// The max writing queue ratio of the disks, None for the non-disk stores.
+    fn disk_queue_ratio(&self) -> Option<f64> {
+        None
+    }
+}
 
 pub struct StoreProvider {}
 
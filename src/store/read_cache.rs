@@ -0,0 +1,263 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+use crate::metric::{GAUGE_READ_CACHE_USED_SIZE, TOTAL_READ_CACHE_HIT, TOTAL_READ_CACHE_MISS};
+use bytes::Bytes;
+use hashlink::LruCache;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+// the segment is admitted only when it's read again within this duration
+pub const READ_CACHE_ADMISSION_TTL: Duration = Duration::from_secs(60);
+// the segments read once are bounded, to avoid growing with the one-pass scanning
+const READ_CACHE_CANDIDATES_CAPACITY: usize = 10000;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SegmentKey {
+    pub app_id: String,
+    pub shuffle_id: i32,
+    pub partition_id: i32,
+    pub offset: i64,
+    pub length: i64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct ReadCacheStats {
+    pub capacity: u64,
+    pub used: u64,
+    pub segments: usize,
+}
+
+struct ReadCacheInner {
+    segments: LruCache<SegmentKey, Bytes>,
+    used: u64,
+    // the first read time of the segments not admitted yet
+    candidates: LruCache<SegmentKey, Instant>,
+}
+
+/// The LRU cache of the recently served disk segments, which is bounded by its own capacity
+/// rather than the memory store's, so it never competes with the writing.
+pub struct ReadCache {
+    inner: Mutex<ReadCacheInner>,
+    capacity: u64,
+    ttl: Duration,
+}
+
+impl ReadCache {
+    pub fn new(capacity: u64, ttl: Duration) -> Self {
+        Self {
+            inner: Mutex::new(ReadCacheInner {
+                segments: LruCache::new_unbounded(),
+                used: 0,
+                candidates: LruCache::new(READ_CACHE_CANDIDATES_CAPACITY),
+            }),
+            capacity,
+            ttl,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.capacity > 0
+    }
+
+    /// Get the segment from the cache, otherwise it's read by the `read` and admitted
+    /// if it has been read within the ttl.
+    pub async fn get<F, Fut, E>(&self, key: SegmentKey, read: F) -> Result<Bytes, E>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<Bytes, E>>,
+    {
+        if !self.is_enabled() {
+            return read().await;
+        }
+        if let Some(data) = self.inner.lock().segments.get(&key).cloned() {
+            TOTAL_READ_CACHE_HIT.inc();
+            return Ok(data);
+        }
+        TOTAL_READ_CACHE_MISS.inc();
+        let data = read().await?;
+        self.admit(key, &data);
+        Ok(data)
+    }
+
+    fn admit(&self, key: SegmentKey, data: &Bytes) {
+        let size = data.len() as u64;
+        // the partial segment may be read before the flushing finished
+        if size == 0 || size != key.length as u64 || size > self.capacity {
+            return;
+        }
+        let mut inner = self.inner.lock();
+        let now = Instant::now();
+        let read_again = matches!(
+            inner.candidates.remove(&key),
+            Some(first) if now.duration_since(first) <= self.ttl
+        );
+        if !read_again {
+            inner.candidates.insert(key, now);
+            return;
+        }
+        if let Some(replaced) = inner.segments.insert(key, data.clone()) {
+            inner.used -= replaced.len() as u64;
+        }
+        inner.used += size;
+        while inner.used > self.capacity {
+            match inner.segments.remove_lru() {
+                Some((_, evicted)) => inner.used -= evicted.len() as u64,
+                None => break,
+            }
+        }
+        GAUGE_READ_CACHE_USED_SIZE.set(inner.used as i64);
+    }
+
+    /// Drop the segments of the app, or the shuffle only if specified.
+    pub fn invalidate(&self, app_id: &str, shuffle_id: Option<i32>) {
+        let matched = |key: &SegmentKey| {
+            key.app_id == app_id && shuffle_id.map_or(true, |id| id == key.shuffle_id)
+        };
+        let mut inner = self.inner.lock();
+        let keys: Vec<SegmentKey> = inner
+            .segments
+            .iter()
+            .filter(|(key, _)| matched(key))
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in keys {
+            if let Some(data) = inner.segments.remove(&key) {
+                inner.used -= data.len() as u64;
+            }
+        }
+        let keys: Vec<SegmentKey> = inner
+            .candidates
+            .iter()
+            .filter(|(key, _)| matched(key))
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in keys {
+            inner.candidates.remove(&key);
+        }
+        GAUGE_READ_CACHE_USED_SIZE.set(inner.used as i64);
+    }
+
+    /// Clear all the cached segments, returns the released bytes.
+    pub fn clear(&self) -> u64 {
+        let mut inner = self.inner.lock();
+        let released = inner.used;
+        inner.segments.clear();
+        inner.candidates.clear();
+        inner.used = 0;
+        GAUGE_READ_CACHE_USED_SIZE.set(0);
+        released
+    }
+
+    pub fn stats(&self) -> ReadCacheStats {
+        let inner = self.inner.lock();
+        ReadCacheStats {
+            capacity: self.capacity,
+            used: inner.used,
+            segments: inner.segments.len(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::store::read_cache::{ReadCache, SegmentKey};
+    use bytes::Bytes;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    fn key(partition_id: i32, offset: i64, length: i64) -> SegmentKey {
+        SegmentKey {
+            app_id: "app".to_string(),
+            shuffle_id: 1,
+            partition_id,
+            offset,
+            length,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reducers_read() -> anyhow::Result<()> {
+        let cache = ReadCache::new(1024, Duration::from_secs(60));
+        let disk_reads = Arc::new(AtomicU64::new(0));
+        let reader = |len: usize| {
+            let disk_reads = disk_reads.clone();
+            move || async move {
+                disk_reads.fetch_add(1, Ordering::SeqCst);
+                anyhow::Ok(Bytes::from(vec![0u8; len]))
+            }
+        };
+
+        // the broadcast-like partition read by 100 reducers
+        for _ in 0..100 {
+            let data = cache.get(key(1, 0, 100), reader(100)).await?;
+            assert_eq!(100, data.len());
+        }
+        assert_eq!(2, disk_reads.load(Ordering::SeqCst));
+        assert_eq!(100, cache.stats().used);
+
+        // the segment read once is not admitted
+        cache.get(key(2, 0, 100), reader(100)).await?;
+        assert_eq!(1, cache.stats().segments);
+
+        // the partial segment is not admitted
+        for _ in 0..3 {
+            cache.get(key(3, 0, 100), reader(10)).await?;
+        }
+        assert_eq!(1, cache.stats().segments);
+
+        cache.invalidate("app", Some(1));
+        assert_eq!(0, cache.stats().used);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_admission_and_eviction() -> anyhow::Result<()> {
+        let reader = |len: usize| move || async move { anyhow::Ok(Bytes::from(vec![0u8; len])) };
+
+        // the second read out of the ttl is not admitted
+        let cache = ReadCache::new(1024, Duration::ZERO);
+        cache.get(key(1, 0, 100), reader(100)).await?;
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        cache.get(key(1, 0, 100), reader(100)).await?;
+        assert_eq!(0, cache.stats().segments);
+
+        // the least recently read segment is evicted beyond the capacity
+        let cache = ReadCache::new(250, Duration::from_secs(60));
+        for offset in [0, 100, 0, 200] {
+            for _ in 0..2 {
+                cache.get(key(1, offset, 100), reader(100)).await?;
+            }
+        }
+        let stats = cache.stats();
+        assert_eq!(200, stats.used);
+        assert_eq!(2, stats.segments);
+
+        assert_eq!(200, cache.clear());
+        assert_eq!(0, cache.stats().used);
+
+        // disabled without the capacity
+        let cache = ReadCache::new(0, Duration::from_secs(60));
+        for _ in 0..3 {
+            cache.get(key(1, 0, 100), reader(100)).await?;
+        }
+        assert_eq!(0, cache.stats().segments);
+        Ok(())
+    }
+}
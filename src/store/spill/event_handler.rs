@@ -26,11 +26,19 @@ impl Subscriber for SpillEventHandler {
         let message = event.get_data();
         let size = message.size;
 
+        let store_ref = &self.store;
+        let _app_permit = match store_ref.try_acquire_app_spill_permit(&message.ctx.uid.app_id) {
+            Ok(permit) => permit,
+            Err(limiter) => {
+                store_ref.defer_spill_event(limiter, message.clone());
+                return;
+            }
+        };
+
         GAUGE_IN_SPILL_DATA_SIZE.add(size);
         TOTAL_MEMORY_SPILL_OPERATION.inc();
         GAUGE_MEMORY_SPILL_OPERATION.inc();
 
-        let store_ref = &self.store;
         match store_ref
             .memory_spill_to_persistent_store(message.clone())
             .instrument_await("memory_spill_to_persistent_store.")
@@ -70,7 +78,7 @@ impl Subscriber for SpillEventHandler {
                 let mut new_message = message.clone();
                 new_message.retry_cnt = message.retry_cnt + 1;
                 // re-push to the queue to execute
-                let _ = store_ref.publish_spill_event(new_message).await;
+                store_ref.retry_spill_event(new_message);
             }
         }
         GAUGE_IN_SPILL_DATA_SIZE.sub(size);
@@ -0,0 +1,587 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::app::AppManagerRef;
+use crate::config::Config;
+use crate::grpc::protobuf::uniffle::shuffle_server_client::ShuffleServerClient;
+use crate::grpc::protobuf::uniffle::{
+    ChecksumType, GetLocalShuffleDataRequest, GetLocalShuffleIndexRequest,
+    GetMemoryShuffleDataRequest, PartitionToBlockIds, ReportShuffleResultRequest,
+    RequireBufferRequest, SendShuffleDataRequest, SendShuffleDataResponse, ShuffleBlock,
+    ShuffleData, ShuffleRegisterRequest,
+};
+use crate::heartbeat::HeartbeatTask;
+use crate::runtime::manager::RuntimeManager;
+use crate::start_uniffle_worker_with_shutdown;
+use crate::store::local::index_cache::INDEX_ENTRY_LEN;
+use crate::testing::coordinator::{free_addr, start_coordinator, MockCoordinator};
+use anyhow::{anyhow, Result};
+use bytes::{Buf, Bytes};
+use parking_lot::Mutex;
+use std::collections::{BTreeMap, HashSet};
+use std::path::Path;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::time::{Duration, Instant};
+use tempdir::TempDir;
+use tokio::sync::oneshot;
+use tonic::transport::Channel;
+
+const READY_TIMEOUT: Duration = Duration::from_secs(10);
+
+type ConfigCustomizer = Box<dyn Fn(&mut Config) + Send + Sync>;
+
+pub struct MiniRiffleClusterBuilder {
+    servers: usize,
+    memory_capacity: String,
+    partition_buffer_max_size: Option<String>,
+    customizer: Option<ConfigCustomizer>,
+}
+
+impl Default for MiniRiffleClusterBuilder {
+    fn default() -> Self {
+        Self {
+            servers: 1,
+            memory_capacity: "1G".to_string(),
+            partition_buffer_max_size: None,
+            customizer: None,
+        }
+    }
+}
+
+impl MiniRiffleClusterBuilder {
+    pub fn servers(mut self, servers: usize) -> Self {
+        self.servers = servers;
+        self
+    }
+
+    pub fn memory_capacity(mut self, capacity: &str) -> Self {
+        self.memory_capacity = capacity.to_string();
+        self
+    }
+
+    /// The partition buffer exceeding it is spilled immediately, the tiny one like "10B"
+    /// makes every written block go into the localfile.
+    pub fn partition_buffer_max_size(mut self, size: &str) -> Self {
+        self.partition_buffer_max_size = Some(size.to_string());
+        self
+    }
+
+    /// Customize the config of every server after the ports and paths are assigned.
+    pub fn config<F>(mut self, customizer: F) -> Self
+    where
+        F: Fn(&mut Config) + Send + Sync + 'static,
+    {
+        self.customizer = Some(Box::new(customizer));
+        self
+    }
+
+    pub async fn build(self) -> Result<MiniRiffleCluster> {
+        if self.servers == 0 {
+            return Err(anyhow!("At least one server is required"));
+        }
+
+        let coordinator = MockCoordinator::default();
+        let coordinator_addr = free_addr();
+        let coordinator_shutdown = start_coordinator(coordinator.clone(), coordinator_addr);
+
+        let mut cluster = MiniRiffleCluster {
+            servers: vec![],
+            coordinator,
+            coordinator_shutdown: Some(coordinator_shutdown),
+            registered: Default::default(),
+            block_id: AtomicI64::new(0),
+        };
+        for idx in 0..self.servers {
+            let data_dir = TempDir::new(&format!("mini_riffle_cluster_{}", idx))?;
+            let mut config = Config::create_mem_localfile_config(
+                free_addr().port() as i32,
+                self.memory_capacity.clone(),
+                data_dir.path().to_str().unwrap().to_string(),
+            );
+            config.http_monitor_service_port = free_addr().port();
+            config.coordinator_quorum = vec![coordinator_addr.to_string()];
+            config.runtime_config.read_thread_num = 4;
+            config.runtime_config.write_thread_num = 4;
+            config.runtime_config.dispatch_thread_num = 4;
+            config.runtime_config.flush_thread_num = 4;
+            config.runtime_config.default_thread_num = 4;
+            if let Some(memory_config) = config.memory_store.as_mut() {
+                memory_config.partition_buffer_max_size = self.partition_buffer_max_size.clone();
+            }
+            if let Some(customizer) = &self.customizer {
+                customizer(&mut config);
+            }
+            // pushed before starting to be torn down even if the later one fails
+            cluster
+                .servers
+                .push(MiniRiffleServer::start(config, data_dir).await?);
+        }
+
+        let ports: HashSet<i32> = cluster.servers.iter().map(|x| x.grpc_port).collect();
+        let deadline = Instant::now() + READY_TIMEOUT;
+        while !ports.is_subset(&cluster.coordinator.registered_ports()) {
+            if Instant::now() > deadline {
+                return Err(anyhow!("Not all servers are registered to the coordinator"));
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        Ok(cluster)
+    }
+}
+
+/// The in-process server with the MEMORY_LOCALFILE store in its own temp dir,
+/// which is stopped with its runtimes and data removed on drop.
+pub struct MiniRiffleServer {
+    pub grpc_port: i32,
+    pub http_port: u16,
+    app_manager: AppManagerRef,
+    runtime_manager: RuntimeManager,
+    shutdown: Option<oneshot::Sender<()>>,
+    data_dir: TempDir,
+}
+
+impl MiniRiffleServer {
+    async fn start(config: Config, data_dir: TempDir) -> Result<Self> {
+        let runtime_manager = RuntimeManager::from(config.runtime_config.clone());
+        let (tx, rx) = oneshot::channel::<()>();
+        let shutdown = async move {
+            let _ = rx.await;
+        };
+
+        let grpc_port = config.grpc_port;
+        let http_port = config.http_monitor_service_port;
+        let app_manager = match start_uniffle_worker_with_shutdown(
+            config.clone(),
+            runtime_manager.clone(),
+            shutdown,
+        )
+        .await
+        {
+            Ok(app_manager) => app_manager,
+            Err(e) => {
+                runtime_manager.shutdown();
+                return Err(e);
+            }
+        };
+        HeartbeatTask::init(&config, runtime_manager.clone(), app_manager.clone());
+
+        let server = Self {
+            grpc_port,
+            http_port,
+            app_manager,
+            runtime_manager,
+            shutdown: Some(tx),
+            data_dir,
+        };
+
+        let deadline = Instant::now() + READY_TIMEOUT;
+        while server.client().await.is_err() {
+            if Instant::now() > deadline {
+                return Err(anyhow!("The server: {} is not ready", server.address()));
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        Ok(server)
+    }
+
+    pub fn address(&self) -> String {
+        format!("http://127.0.0.1:{}", self.grpc_port)
+    }
+
+    pub async fn client(&self) -> Result<ShuffleServerClient<Channel>> {
+        Ok(ShuffleServerClient::connect(self.address()).await?)
+    }
+
+    pub fn app_manager(&self) -> &AppManagerRef {
+        &self.app_manager
+    }
+
+    pub fn data_path(&self) -> &Path {
+        self.data_dir.path()
+    }
+
+    /// Spill all the staging data in memory and wait for the flushing finished.
+    pub async fn force_spill(&self) -> Result<()> {
+        self.app_manager.store_spill_all().await?;
+        let deadline = Instant::now() + READY_TIMEOUT;
+        while self.app_manager.store_memory_spill_event_num()? > 0 {
+            if Instant::now() > deadline {
+                return Err(anyhow!(
+                    "The spill of server: {} is timeout",
+                    self.address()
+                ));
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        Ok(())
+    }
+
+    fn stop(&mut self) {
+        if let Some(tx) = self.shutdown.take() {
+            let _ = tx.send(());
+        }
+        self.runtime_manager.shutdown();
+    }
+}
+
+impl Drop for MiniRiffleServer {
+    fn drop(&mut self) {
+        // the runtimes must be stopped before the data dir is deleted
+        self.stop();
+    }
+}
+
+/// The stored blocks of one partition keyed by the block id.
+#[derive(Default, Debug)]
+pub struct StoredPartition {
+    pub memory: BTreeMap<i64, Bytes>,
+    pub localfile: BTreeMap<i64, Bytes>,
+}
+
+impl StoredPartition {
+    /// All the blocks ordered by the block id, the one being spilled may be in both.
+    pub fn blocks(&self) -> Vec<Bytes> {
+        let mut blocks = self.localfile.clone();
+        blocks.extend(self.memory.clone());
+        blocks.into_values().collect()
+    }
+}
+
+/// The in-process cluster with the mock coordinator, the partition is always routed to
+/// the server by `partition_id % servers`.
+///
+/// ```ignore
+/// let cluster = MiniRiffleCluster::builder().servers(2).build().await?;
+/// cluster.write_blocks("app", 0, 1, vec![data.clone()]).await?;
+/// cluster.assert_stored("app", 0, 1, &[data]).await?;
+/// ```
+pub struct MiniRiffleCluster {
+    servers: Vec<MiniRiffleServer>,
+    coordinator: MockCoordinator,
+    coordinator_shutdown: Option<oneshot::Sender<()>>,
+    registered: Mutex<HashSet<(String, i32)>>,
+    block_id: AtomicI64,
+}
+
+impl MiniRiffleCluster {
+    pub fn builder() -> MiniRiffleClusterBuilder {
+        MiniRiffleClusterBuilder::default()
+    }
+
+    pub fn servers(&self) -> &[MiniRiffleServer] {
+        &self.servers
+    }
+
+    pub fn coordinator(&self) -> &MockCoordinator {
+        &self.coordinator
+    }
+
+    pub fn server_of(&self, partition_id: i32) -> &MiniRiffleServer {
+        &self.servers[partition_id as usize % self.servers.len()]
+    }
+
+    /// Register the shuffle to all the servers.
+    pub async fn register_app(&self, app_id: &str, shuffle_id: i32) -> Result<()> {
+        for server in &self.servers {
+            let response = server
+                .client()
+                .await?
+                .register_shuffle(ShuffleRegisterRequest {
+                    app_id: app_id.to_string(),
+                    shuffle_id,
+                    partition_ranges: vec![],
+                    remote_storage: None,
+                    user: "".to_string(),
+                    shuffle_data_distribution: 1,
+                    max_concurrency_per_partition_to_write: 10,
+                    block_id_layout: None,
+                })
+                .await?
+                .into_inner();
+            if response.status != 0 {
+                return Err(anyhow!("Errors on registering: {}", response.ret_msg));
+            }
+        }
+        self.registered
+            .lock()
+            .insert((app_id.to_string(), shuffle_id));
+        Ok(())
+    }
+
+    /// Write the blocks into the partition, the shuffle is registered if not yet.
+    /// The assigned block ids are returned.
+    pub async fn write_blocks(
+        &self,
+        app_id: &str,
+        shuffle_id: i32,
+        partition_id: i32,
+        blocks: Vec<Bytes>,
+    ) -> Result<Vec<i64>> {
+        let (block_ids, response) = self
+            .send_blocks(app_id, shuffle_id, partition_id, blocks, None)
+            .await?;
+        if response.status != 0 {
+            return Err(anyhow!("Errors on sending data: {}", response.ret_msg));
+        }
+
+        let mut client = self.server_of(partition_id).client().await?;
+        let response = client
+            .report_shuffle_result(ReportShuffleResultRequest {
+                app_id: app_id.to_string(),
+                shuffle_id,
+                task_attempt_id: 0,
+                bitmap_num: 1,
+                partition_to_block_ids: vec![PartitionToBlockIds {
+                    partition_id,
+                    block_ids: block_ids.clone(),
+                }],
+            })
+            .await?
+            .into_inner();
+        if response.status != 0 {
+            return Err(anyhow!("Errors on reporting result: {}", response.ret_msg));
+        }
+        Ok(block_ids)
+    }
+
+    /// Send the blocks with one byte of the block at the index flipped after its crc computed,
+    /// like corrupted in the transport. Returns the block ids with the raw response.
+    pub async fn write_corrupted_blocks(
+        &self,
+        app_id: &str,
+        shuffle_id: i32,
+        partition_id: i32,
+        blocks: Vec<Bytes>,
+        corrupted_index: usize,
+    ) -> Result<(Vec<i64>, SendShuffleDataResponse)> {
+        self.send_blocks(
+            app_id,
+            shuffle_id,
+            partition_id,
+            blocks,
+            Some(corrupted_index),
+        )
+        .await
+    }
+
+    async fn send_blocks(
+        &self,
+        app_id: &str,
+        shuffle_id: i32,
+        partition_id: i32,
+        blocks: Vec<Bytes>,
+        corrupted_index: Option<usize>,
+    ) -> Result<(Vec<i64>, SendShuffleDataResponse)> {
+        let registered = self
+            .registered
+            .lock()
+            .contains(&(app_id.to_string(), shuffle_id));
+        if !registered {
+            self.register_app(app_id, shuffle_id).await?;
+        }
+
+        let mut client = self.server_of(partition_id).client().await?;
+        let size: usize = blocks.iter().map(|x| x.len()).sum();
+        let response = client
+            .require_buffer(RequireBufferRequest {
+                require_size: size as i32,
+                app_id: app_id.to_string(),
+                shuffle_id,
+                partition_ids: vec![partition_id],
+            })
+            .await?
+            .into_inner();
+        if response.status != 0 {
+            return Err(anyhow!("Errors on requiring buffer: {}", response.ret_msg));
+        }
+
+        let mut block_ids = vec![];
+        let mut shuffle_blocks = vec![];
+        for (idx, data) in blocks.into_iter().enumerate() {
+            let block_id = self.block_id.fetch_add(1, Ordering::SeqCst);
+            block_ids.push(block_id);
+            let crc = crc32fast::hash(&data) as i64;
+            let data = match corrupted_index {
+                Some(corrupted) if corrupted == idx => {
+                    let mut corrupted = data.to_vec();
+                    corrupted[0] ^= 0xff;
+                    Bytes::from(corrupted)
+                }
+                _ => data,
+            };
+            shuffle_blocks.push(ShuffleBlock {
+                block_id,
+                length: data.len() as i32,
+                uncompress_length: data.len() as i32,
+                crc,
+                data,
+                task_attempt_id: 0,
+            });
+        }
+        let response = client
+            .send_shuffle_data(SendShuffleDataRequest {
+                app_id: app_id.to_string(),
+                shuffle_id,
+                require_buffer_id: response.require_buffer_id,
+                shuffle_data: vec![ShuffleData {
+                    partition_id,
+                    block: shuffle_blocks,
+                }],
+                timestamp: 0,
+                stage_attempt_number: 0,
+                contiguous_shuffle_data: Default::default(),
+                checksum_type: ChecksumType::Crc32.into(),
+            })
+            .await?
+            .into_inner();
+        Ok((block_ids, response))
+    }
+
+    /// Force all the servers to spill the data in memory into the localfile.
+    pub async fn force_spill(&self) -> Result<()> {
+        for server in &self.servers {
+            server.force_spill().await?;
+        }
+        Ok(())
+    }
+
+    /// Read the partition from both the memory and the localfile.
+    pub async fn read_partition(
+        &self,
+        app_id: &str,
+        shuffle_id: i32,
+        partition_id: i32,
+    ) -> Result<StoredPartition> {
+        let mut client = self.server_of(partition_id).client().await?;
+        let mut stored = StoredPartition::default();
+
+        // the memory data is paged by the last block id
+        let mut last_block_id = -1;
+        loop {
+            let response = client
+                .get_memory_shuffle_data(GetMemoryShuffleDataRequest {
+                    app_id: app_id.to_string(),
+                    shuffle_id,
+                    partition_id,
+                    last_block_id,
+                    read_buffer_size: 10 * 1024 * 1024,
+                    timestamp: 0,
+                    serialized_expected_task_ids_bitmap: Default::default(),
+                })
+                .await?
+                .into_inner();
+            if response.status != 0 {
+                return Err(anyhow!("Errors on reading memory: {}", response.ret_msg));
+            }
+            let segments = response.shuffle_data_block_segments;
+            let last = match segments.last() {
+                Some(last) => last.block_id,
+                _ => break,
+            };
+            for segment in &segments {
+                let start = segment.offset as usize;
+                let end = start + segment.length as usize;
+                stored
+                    .memory
+                    .insert(segment.block_id, response.data.slice(start..end));
+            }
+            // the last block has been flushed, the reading would restart from the head
+            if last == last_block_id {
+                break;
+            }
+            last_block_id = last;
+        }
+
+        let response = client
+            .get_local_shuffle_index(GetLocalShuffleIndexRequest {
+                app_id: app_id.to_string(),
+                shuffle_id,
+                partition_id,
+                partition_num_per_range: 1,
+                partition_num: 0,
+            })
+            .await?
+            .into_inner();
+        if response.status != 0 {
+            return Err(anyhow!("Errors on reading index: {}", response.ret_msg));
+        }
+        let mut index = response.index_data;
+        let mut entries = vec![];
+        while index.remaining() >= INDEX_ENTRY_LEN as usize {
+            let offset = index.get_i64();
+            let length = index.get_i32();
+            index.get_i32();
+            index.get_i64();
+            let block_id = index.get_i64();
+            index.get_i64();
+            entries.push((offset as usize, length as usize, block_id));
+        }
+        let data_len = entries
+            .iter()
+            .map(|(offset, length, _)| offset + length)
+            .max()
+            .unwrap_or(0);
+        if data_len > 0 {
+            let response = client
+                .get_local_shuffle_data(GetLocalShuffleDataRequest {
+                    app_id: app_id.to_string(),
+                    shuffle_id,
+                    partition_id,
+                    partition_num_per_range: 1,
+                    partition_num: 0,
+                    offset: 0,
+                    length: data_len as i32,
+                    timestamp: 0,
+                })
+                .await?
+                .into_inner();
+            if response.status != 0 || response.data.len() < data_len {
+                return Err(anyhow!("Errors on reading data: {}", response.ret_msg));
+            }
+            for (offset, length, block_id) in entries {
+                stored
+                    .localfile
+                    .insert(block_id, response.data.slice(offset..offset + length));
+            }
+        }
+        Ok(stored)
+    }
+
+    /// Assert the partition stores exactly the expected blocks in the written order.
+    pub async fn assert_stored(
+        &self,
+        app_id: &str,
+        shuffle_id: i32,
+        partition_id: i32,
+        expected: &[Bytes],
+    ) -> Result<()> {
+        let stored = self
+            .read_partition(app_id, shuffle_id, partition_id)
+            .await?;
+        assert_eq!(expected, stored.blocks().as_slice());
+        Ok(())
+    }
+}
+
+impl Drop for MiniRiffleCluster {
+    fn drop(&mut self) {
+        self.servers.clear();
+        if let Some(tx) = self.coordinator_shutdown.take() {
+            let _ = tx.send(());
+        }
+    }
+}
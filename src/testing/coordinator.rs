@@ -0,0 +1,172 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::grpc::protobuf::uniffle::coordinator_server_server::{
+    CoordinatorServer, CoordinatorServerServer,
+};
+use crate::grpc::protobuf::uniffle::*;
+use parking_lot::Mutex;
+use std::collections::HashSet;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio::sync::oneshot;
+use tokio_stream::wrappers::TcpListenerStream;
+use tonic::transport::Server;
+use tonic::{Request, Response, Status};
+
+/// The coordinator only recording the received heartbeats.
+#[derive(Clone, Default)]
+pub struct MockCoordinator {
+    pub heartbeats: Arc<Mutex<Vec<ShuffleServerHeartBeatRequest>>>,
+    // mock the coordinator forgetting the registered servers
+    pub forgotten: Arc<AtomicBool>,
+}
+
+impl MockCoordinator {
+    /// The grpc ports of the servers which have sent the heartbeats.
+    pub fn registered_ports(&self) -> HashSet<i32> {
+        self.heartbeats
+            .lock()
+            .iter()
+            .filter_map(|x| x.server_id.as_ref().map(|id| id.port))
+            .collect()
+    }
+}
+
+#[tonic::async_trait]
+impl CoordinatorServer for MockCoordinator {
+    async fn get_shuffle_server_list(
+        &self,
+        _request: Request<()>,
+    ) -> Result<Response<GetShuffleServerListResponse>, Status> {
+        Err(Status::unimplemented(""))
+    }
+
+    async fn get_shuffle_server_num(
+        &self,
+        _request: Request<()>,
+    ) -> Result<Response<GetShuffleServerNumResponse>, Status> {
+        Err(Status::unimplemented(""))
+    }
+
+    async fn get_shuffle_assignments(
+        &self,
+        _request: Request<GetShuffleServerRequest>,
+    ) -> Result<Response<GetShuffleAssignmentsResponse>, Status> {
+        Err(Status::unimplemented(""))
+    }
+
+    async fn heartbeat(
+        &self,
+        request: Request<ShuffleServerHeartBeatRequest>,
+    ) -> Result<Response<ShuffleServerHeartBeatResponse>, Status> {
+        if self.forgotten.swap(false, Ordering::SeqCst) {
+            return Ok(Response::new(ShuffleServerHeartBeatResponse {
+                status: StatusCode::NoRegister as i32,
+                ret_msg: "unknown server".to_string(),
+            }));
+        }
+        self.heartbeats.lock().push(request.into_inner());
+        Ok(Response::new(Default::default()))
+    }
+
+    async fn get_shuffle_data_storage_info(
+        &self,
+        _request: Request<()>,
+    ) -> Result<Response<GetShuffleDataStorageInfoResponse>, Status> {
+        Err(Status::unimplemented(""))
+    }
+
+    async fn check_service_available(
+        &self,
+        _request: Request<()>,
+    ) -> Result<Response<CheckServiceAvailableResponse>, Status> {
+        Err(Status::unimplemented(""))
+    }
+
+    async fn app_heartbeat(
+        &self,
+        _request: Request<AppHeartBeatRequest>,
+    ) -> Result<Response<AppHeartBeatResponse>, Status> {
+        Err(Status::unimplemented(""))
+    }
+
+    async fn report_client_operation(
+        &self,
+        _request: Request<ReportShuffleClientOpRequest>,
+    ) -> Result<Response<ReportShuffleClientOpResponse>, Status> {
+        Err(Status::unimplemented(""))
+    }
+
+    async fn register_application_info(
+        &self,
+        _request: Request<ApplicationInfoRequest>,
+    ) -> Result<Response<ApplicationInfoResponse>, Status> {
+        Err(Status::unimplemented(""))
+    }
+
+    async fn access_cluster(
+        &self,
+        _request: Request<AccessClusterRequest>,
+    ) -> Result<Response<AccessClusterResponse>, Status> {
+        Err(Status::unimplemented(""))
+    }
+
+    async fn fetch_client_conf(
+        &self,
+        _request: Request<()>,
+    ) -> Result<Response<FetchClientConfResponse>, Status> {
+        Err(Status::unimplemented(""))
+    }
+
+    async fn fetch_remote_storage(
+        &self,
+        _request: Request<FetchRemoteStorageRequest>,
+    ) -> Result<Response<FetchRemoteStorageResponse>, Status> {
+        Err(Status::unimplemented(""))
+    }
+}
+
+/// Serve the coordinator on the current tokio runtime until the returned sender is fired or dropped.
+pub fn start_coordinator(coordinator: MockCoordinator, addr: SocketAddr) -> oneshot::Sender<()> {
+    let sock = socket2::Socket::new(socket2::Domain::IPV4, socket2::Type::STREAM, None).unwrap();
+    sock.set_reuse_address(true).unwrap();
+    sock.set_nonblocking(true).unwrap();
+    sock.bind(&addr.into()).unwrap();
+    sock.listen(128).unwrap();
+    let listener = tokio::net::TcpListener::from_std(sock.into()).unwrap();
+
+    let (tx, rx) = oneshot::channel();
+    tokio::spawn(async move {
+        Server::builder()
+            .add_service(CoordinatorServerServer::new(coordinator))
+            .serve_with_incoming_shutdown(TcpListenerStream::new(listener), async {
+                let _ = rx.await;
+            })
+            .await
+            .unwrap();
+    });
+    tx
+}
+
+pub fn free_addr() -> SocketAddr {
+    std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+}
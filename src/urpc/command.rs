@@ -5,17 +5,21 @@ use crate::app::{
 use crate::constant::StatusCode;
 use crate::metric::{
     URPC_GET_LOCALFILE_DATA_PROCESS_TIME, URPC_GET_MEMORY_DATA_PROCESS_TIME,
-    URPC_SEND_DATA_PROCESS_TIME, URPC_SEND_DATA_TRANSPORT_TIME,
+    URPC_GET_MERGED_DATA_PROCESS_TIME, URPC_SEND_DATA_PROCESS_TIME, URPC_SEND_DATA_TRANSPORT_TIME,
 };
+use crate::slow_log;
+use crate::slow_log::{Phase, RequestTimingContext};
 use crate::store::ResponseDataIndex::Local;
-use crate::store::{Block, LocalDataIndex, ResponseData};
-use crate::urpc::connection::Connection;
+use crate::store::{Block, LocalDataIndex, PartitionedMergedData, ResponseData};
+use crate::urpc::connection::{Connection, FlushHint};
 use crate::urpc::frame::Frame;
 use crate::urpc::shutdown::Shutdown;
 use crate::util;
 use anyhow::Result;
 use await_tree::InstrumentAwait;
 use bytes::Bytes;
+use croaring::treemap::JvmSerializer;
+use croaring::Treemap;
 use log::{debug, error};
 use std::collections::HashMap;
 
@@ -25,6 +29,7 @@ pub enum Command {
     GetMem(GetMemoryDataRequestCommand),
     GetLocalIndex(GetLocalDataIndexRequestCommand),
     GetLocalData(GetLocalDataRequestCommand),
+    GetMerged(GetMergedDataRequestCommand),
 }
 
 impl Command {
@@ -34,6 +39,7 @@ impl Command {
             Frame::GetMemoryData(req) => Ok(Command::GetMem(req)),
             Frame::GetLocalDataIndex(req) => Ok(Command::GetLocalIndex(req)),
             Frame::GetLocalData(req) => Ok(Command::GetLocalData(req)),
+            Frame::GetMergedData(req) => Ok(Command::GetMerged(req)),
             _ => todo!(),
         }
     }
@@ -45,10 +51,56 @@ impl Command {
         shutdown: &mut Shutdown,
     ) -> Result<()> {
         match self {
-            Command::Send(req) => req.apply(app_manager_ref, conn, shutdown).await?,
-            Command::GetMem(req) => req.apply(app_manager_ref, conn, shutdown).await?,
-            Command::GetLocalIndex(req) => req.apply(app_manager_ref, conn, shutdown).await?,
-            Command::GetLocalData(req) => req.apply(app_manager_ref, conn, shutdown).await?,
+            Command::Send(req) => {
+                let ctx = RequestTimingContext::new(
+                    "urpc_send_shuffle_data",
+                    &req.app_id,
+                    req.shuffle_id,
+                    -1,
+                    req.timestamp,
+                );
+                slow_log::observe(ctx, req.apply(app_manager_ref, conn, shutdown)).await?
+            }
+            Command::GetMem(req) => {
+                let ctx = RequestTimingContext::new(
+                    "urpc_get_memory_shuffle_data",
+                    &req.app_id,
+                    req.shuffle_id,
+                    req.partition_id,
+                    req.timestamp,
+                );
+                slow_log::observe(ctx, req.apply(app_manager_ref, conn, shutdown)).await?
+            }
+            Command::GetLocalIndex(req) => {
+                let ctx = RequestTimingContext::new(
+                    "urpc_get_local_shuffle_index",
+                    &req.app_id,
+                    req.shuffle_id,
+                    req.partition_id,
+                    0,
+                );
+                slow_log::observe(ctx, req.apply(app_manager_ref, conn, shutdown)).await?
+            }
+            Command::GetLocalData(req) => {
+                let ctx = RequestTimingContext::new(
+                    "urpc_get_local_shuffle_data",
+                    &req.app_id,
+                    req.shuffle_id,
+                    req.partition_id,
+                    req.timestamp,
+                );
+                slow_log::observe(ctx, req.apply(app_manager_ref, conn, shutdown)).await?
+            }
+            Command::GetMerged(req) => {
+                let ctx = RequestTimingContext::new(
+                    "urpc_get_merged_shuffle_data",
+                    &req.app_id,
+                    req.shuffle_id,
+                    req.partition_id,
+                    req.timestamp,
+                );
+                slow_log::observe(ctx, req.apply(app_manager_ref, conn, shutdown)).await?
+            }
             _ => {}
         }
         Ok(())
@@ -104,10 +156,10 @@ impl GetMemoryDataRequestCommand {
             serialized_expected_task_ids_bitmap: None,
         };
 
-        let response = match app.select(ctx).await {
+        let response = match slow_log::record_phase(Phase::StoreLookup, app.select(ctx)).await {
             Err(e) => GetMemoryDataResponseCommand {
                 request_id,
-                status_code: StatusCode::INTERNAL_ERROR.into(),
+                status_code: e.observe_status_code(),
                 ret_msg: format!("Errors on getting memory data. err: {:#?}", e),
                 data: ResponseData::Mem(Default::default()),
             },
@@ -125,6 +177,83 @@ impl GetMemoryDataRequestCommand {
     }
 }
 
+#[derive(Debug, Clone)]
+pub struct GetMergedDataRequestCommand {
+    pub(crate) request_id: i64,
+    pub(crate) app_id: String,
+    pub(crate) shuffle_id: i32,
+    pub(crate) partition_id: i32,
+    pub(crate) expected_tasks_bitmap_raw: Option<Bytes>,
+    pub(crate) timestamp: i64,
+}
+
+impl GetMergedDataRequestCommand {
+    pub(crate) async fn apply(
+        &self,
+        app_manager_ref: AppManagerRef,
+        conn: &mut Connection,
+        shutdown: &mut Shutdown,
+    ) -> Result<()> {
+        let timer = URPC_GET_MERGED_DATA_PROCESS_TIME.start_timer();
+        let request_id = self.request_id;
+        let app_id = self.app_id.as_str();
+
+        let app = app_manager_ref.get_app(&app_id);
+        if app.is_none() {
+            let response = RpcResponseCommand {
+                request_id,
+                status_code: StatusCode::NO_REGISTER.into(),
+                ret_msg: "No such app in server side".to_string(),
+            };
+            write_response(conn, response).await?;
+            return Ok(());
+        }
+
+        let app = app.unwrap();
+        let uid = PartitionedUId::from(app_id.to_string(), self.shuffle_id, self.partition_id);
+        let task_ids = match &self.expected_tasks_bitmap_raw {
+            Some(raw) => match Treemap::deserialize(raw) {
+                Ok(filter) => Some(filter),
+                Err(e) => {
+                    error!("Failed to deserialize: {}", e);
+                    None
+                }
+            },
+            None => None,
+        };
+
+        let response =
+            match slow_log::record_phase(Phase::StoreLookup, app.select_merged(&uid, task_ids))
+                .await
+            {
+                Err(e) => GetMergedDataResponseCommand {
+                    request_id,
+                    status_code: e.observe_status_code(),
+                    ret_msg: format!("Errors on getting merged data. err: {:#?}", e),
+                    data: Default::default(),
+                },
+                Ok(data) => GetMergedDataResponseCommand {
+                    request_id,
+                    status_code: StatusCode::SUCCESS.into(),
+                    ret_msg: "".to_string(),
+                    data,
+                },
+            };
+        let frame = Frame::GetMergedDataResponse(response);
+        conn.write_frame(&frame).await?;
+        timer.observe_duration();
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct GetMergedDataResponseCommand {
+    pub(crate) request_id: i64,
+    pub(crate) status_code: i32,
+    pub(crate) ret_msg: String,
+    pub(crate) data: PartitionedMergedData,
+}
+
 #[derive(Debug)]
 pub struct GetLocalDataResponseCommand {
     pub(crate) request_id: i64,
@@ -183,14 +312,16 @@ impl GetLocalDataRequestCommand {
             reading_options: ReadingOptions::FILE_OFFSET_AND_LEN(offset, length as i64),
             serialized_expected_task_ids_bitmap: None,
         };
-        let command = match app
-            .select(ctx)
-            .instrument_await(format!("getting local shuffle data for app:{}", &app_id))
-            .await
+        let command = match slow_log::record_phase(
+            Phase::StoreLookup,
+            app.select(ctx)
+                .instrument_await(format!("getting local shuffle data for app:{}", &app_id)),
+        )
+        .await
         {
             Err(e) => GetLocalDataResponseCommand {
                 request_id,
-                status_code: StatusCode::INTERNAL_ERROR.into(),
+                status_code: e.observe_status_code(),
                 ret_msg: format!("Errors on getting file data. err: {:#?}", e),
                 data: Default::default(),
             },
@@ -267,14 +398,16 @@ impl GetLocalDataIndexRequestCommand {
         let uid = PartitionedUId::from(app_id.to_string(), shuffle_id, partition_id);
         let ctx = ReadingIndexViewContext { partition_id: uid };
 
-        let command = match app
-            .list_index(ctx)
-            .instrument_await(format!("listing localfile index for app:{}", &app_id))
-            .await
+        let command = match slow_log::record_phase(
+            Phase::StoreLookup,
+            app.list_index(ctx)
+                .instrument_await(format!("listing localfile index for app:{}", &app_id)),
+        )
+        .await
         {
             Err(err) => GetLocalDataIndexResponseCommand {
                 request_id,
-                status_code: StatusCode::INTERNAL_ERROR.into(),
+                status_code: err.observe_status_code(),
                 ret_msg: format!("Errors on listing local index. err: {:#?}", err),
                 data_index: Default::default(),
             },
@@ -331,7 +464,9 @@ pub struct RpcResponseCommand {
 
 async fn write_response(conn: &mut Connection, command: RpcResponseCommand) -> Result<()> {
     let frame = Frame::RpcResponse(command);
-    conn.write_frame(&frame).await
+    // the ack is latency-sensitive, which should not wait for the coalescing
+    conn.write_frame_with_hint(&frame, FlushHint::Immediate)
+        .await
 }
 
 impl SendDataRequestCommand {
@@ -372,7 +507,7 @@ impl SendDataRequestCommand {
             Err(e) => {
                 let response = RpcResponseCommand {
                     request_id,
-                    status_code: StatusCode::INTERNAL_ERROR.into(),
+                    status_code: e.observe_status_code(),
                     ret_msg: "No such ticket id. Maybe it has been out of date".to_string(),
                 };
                 write_response(conn, response).await?;
@@ -383,6 +518,7 @@ impl SendDataRequestCommand {
 
         let mut insert_failure_occur = false;
         let mut insert_failure_message = None;
+        let mut insert_failure_status: i32 = StatusCode::INTERNAL_ERROR.into();
 
         let mut insert_len = 0;
 
@@ -392,13 +528,16 @@ impl SendDataRequestCommand {
             let partition_blocks = block.1;
             let uid = PartitionedUId::from(app_id.to_string(), shuffle_id, partition_id);
             let ctx = WritingViewContext::from(uid, partition_blocks);
-            match app
-                .insert(ctx)
-                .instrument_await(format!("inserting shuffle data for app:{}", &app_id))
-                .await
+            match slow_log::record_phase(
+                Phase::StoreLookup,
+                app.insert(ctx)
+                    .instrument_await(format!("inserting shuffle data for app:{}", &app_id)),
+            )
+            .await
             {
                 Ok(size) => insert_len += size as i64,
                 Err(e) => {
+                    insert_failure_status = e.observe_status_code();
                     let msg = format!(
                         "Errors on inserting data for app: {:?}. error:{:#?}",
                         &app_id, e
@@ -409,6 +548,7 @@ impl SendDataRequestCommand {
                 }
             }
         }
+        slow_log::record_payload_size(insert_len);
         let _ = app.move_allocated_used_from_budget(insert_len);
         let unused = ticket_len - insert_len;
         if unused > 0 {
@@ -419,7 +559,7 @@ impl SendDataRequestCommand {
         let response = match insert_failure_occur {
             true => RpcResponseCommand {
                 request_id,
-                status_code: StatusCode::INTERNAL_ERROR.into(),
+                status_code: insert_failure_status,
                 ret_msg: insert_failure_message.unwrap(),
             },
             _ => RpcResponseCommand {
@@ -1,25 +1,65 @@
 use bytes::{Buf, BytesMut};
 use std::io::Cursor;
+use std::time::{Duration, Instant};
 use tokio::io::{AsyncReadExt, AsyncWriteExt, BufWriter};
 use tokio::net::TcpStream;
 
+use crate::config::UrpcConfig;
 use crate::error::WorkerError;
+use crate::metric::URPC_FRAMES_PER_FLUSH;
+use crate::slow_log;
+use crate::slow_log::Phase;
 use crate::urpc::frame::Frame;
 use anyhow::Result;
 
 const INITIAL_BUFFER_LENGTH: usize = 1024 * 1024;
+// the default capacity of the BufWriter
+const DEFAULT_WRITE_BUFFER_LENGTH: usize = 8 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CoalesceOptions {
+    pub max_bytes: usize,
+    pub max_delay: Duration,
+}
+
+impl CoalesceOptions {
+    pub fn from(config: &UrpcConfig) -> Result<Option<Self>> {
+        Ok(config.write_coalesce()?.map(|(max_bytes, max_delay)| Self {
+            max_bytes,
+            max_delay,
+        }))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FlushHint {
+    // pending until the coalescing budget is reached
+    Coalesce,
+    // the latency-sensitive frame is flushed together with the pending ones at once
+    Immediate,
+}
 
 #[derive(Debug)]
 pub struct Connection {
     stream: BufWriter<TcpStream>,
     buffer: BytesMut,
+    coalesce: Option<CoalesceOptions>,
+    // the written but not flushed frames
+    pending_frames: usize,
+    pending_since: Option<Instant>,
 }
 
 impl Connection {
-    pub fn new(socket: TcpStream) -> Self {
+    pub fn new(socket: TcpStream, coalesce: Option<CoalesceOptions>) -> Self {
+        let write_buffer_len = coalesce.map_or(DEFAULT_WRITE_BUFFER_LENGTH, |x| {
+            x.max_bytes.max(DEFAULT_WRITE_BUFFER_LENGTH)
+        });
         Connection {
-            stream: BufWriter::new(socket),
+            stream: BufWriter::with_capacity(write_buffer_len, socket),
             buffer: BytesMut::with_capacity(INITIAL_BUFFER_LENGTH),
+            coalesce,
+            pending_frames: 0,
+            pending_since: None,
         }
     }
 
@@ -40,11 +80,53 @@ impl Connection {
     }
 
     pub async fn write_frame(&mut self, frame: &Frame) -> Result<()> {
-        Frame::write(&mut self.stream, frame).await?;
+        self.write_frame_with_hint(frame, FlushHint::Coalesce).await
+    }
+
+    pub async fn write_frame_with_hint(&mut self, frame: &Frame, hint: FlushHint) -> Result<()> {
+        slow_log::record_phase(Phase::NetworkWrite, async {
+            Frame::write(&mut self.stream, frame).await?;
+            self.pending_frames += 1;
+            let since = *self.pending_since.get_or_insert_with(Instant::now);
+            let flush = match (&self.coalesce, hint) {
+                (Some(coalesce), FlushHint::Coalesce) => {
+                    self.stream.buffer().len() >= coalesce.max_bytes
+                        || since.elapsed() >= coalesce.max_delay
+                }
+                _ => true,
+            };
+            if flush {
+                self.flush().await?;
+            }
+            Ok::<(), anyhow::Error>(())
+        })
+        .await
+    }
+
+    /// Flush all the pending frames.
+    pub async fn flush(&mut self) -> Result<()> {
+        if self.pending_frames == 0 {
+            return Ok(());
+        }
         self.stream.flush().await?;
+        URPC_FRAMES_PER_FLUSH.observe(self.pending_frames as f64);
+        self.pending_frames = 0;
+        self.pending_since = None;
         Ok(())
     }
 
+    /// The time the pending frames should be flushed at, none if nothing is pending.
+    pub fn flush_deadline(&self) -> Option<Instant> {
+        match (&self.coalesce, self.pending_since) {
+            (Some(coalesce), Some(since)) => Some(since + coalesce.max_delay),
+            _ => None,
+        }
+    }
+
+    pub fn pending_frames(&self) -> usize {
+        self.pending_frames
+    }
+
     pub async fn read_frame(&mut self) -> Result<Option<Frame>, WorkerError> {
         loop {
             // Attempt to parse a frame from the buffered data. If enough data
@@ -72,3 +154,105 @@ impl Connection {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::urpc::command::RpcResponseCommand;
+    use crate::urpc::connection::{CoalesceOptions, Connection, FlushHint};
+    use crate::urpc::frame::Frame;
+    use std::time::{Duration, Instant};
+    use tokio::io::AsyncReadExt;
+    use tokio::net::{TcpListener, TcpStream};
+
+    // the header and the content of the response without the message
+    const RESPONSE_FRAME_LEN: usize = 9 + 16;
+
+    async fn connect(coalesce: Option<CoalesceOptions>) -> anyhow::Result<(Connection, TcpStream)> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let client = TcpStream::connect(listener.local_addr()?).await?;
+        let (socket, _) = listener.accept().await?;
+        Ok((Connection::new(socket, coalesce), client))
+    }
+
+    fn response(request_id: i64) -> Frame {
+        Frame::RpcResponse(RpcResponseCommand {
+            request_id,
+            status_code: 0,
+            ret_msg: "".to_string(),
+        })
+    }
+
+    fn readable_len(client: &TcpStream) -> usize {
+        let mut buf = [0u8; 4096];
+        match client.try_read(&mut buf) {
+            Ok(len) => len,
+            _ => 0,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_write_coalesce() -> anyhow::Result<()> {
+        let coalesce = CoalesceOptions {
+            max_bytes: 4 * RESPONSE_FRAME_LEN,
+            max_delay: Duration::from_secs(10),
+        };
+        let (mut conn, mut client) = connect(Some(coalesce)).await?;
+
+        // pending until reaching the bytes budget
+        for id in 0..3 {
+            conn.write_frame(&response(id)).await?;
+        }
+        assert_eq!(3, conn.pending_frames());
+        assert!(conn.flush_deadline().unwrap() > Instant::now());
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(0, readable_len(&client));
+
+        conn.write_frame(&response(3)).await?;
+        assert_eq!(0, conn.pending_frames());
+        assert_eq!(None, conn.flush_deadline());
+        let mut buf = vec![0u8; 4 * RESPONSE_FRAME_LEN];
+        client.read_exact(&mut buf).await?;
+
+        // flushed by the delay budget
+        let coalesce = CoalesceOptions {
+            max_bytes: 1024 * 1024,
+            max_delay: Duration::from_millis(10),
+        };
+        let (mut conn, mut client) = connect(Some(coalesce)).await?;
+        conn.write_frame(&response(0)).await?;
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        conn.write_frame(&response(1)).await?;
+        assert_eq!(0, conn.pending_frames());
+        let mut buf = vec![0u8; 2 * RESPONSE_FRAME_LEN];
+        client.read_exact(&mut buf).await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_write_bypass_coalesce() -> anyhow::Result<()> {
+        let coalesce = CoalesceOptions {
+            max_bytes: 1024 * 1024,
+            max_delay: Duration::from_secs(10),
+        };
+        let (mut conn, mut client) = connect(Some(coalesce)).await?;
+        conn.write_frame(&response(0)).await?;
+        conn.write_frame(&response(1)).await?;
+        assert_eq!(2, conn.pending_frames());
+
+        // the pending ones are flushed together
+        conn.write_frame_with_hint(&response(2), FlushHint::Immediate)
+            .await?;
+        assert_eq!(0, conn.pending_frames());
+        let mut buf = vec![0u8; 3 * RESPONSE_FRAME_LEN];
+        client.read_exact(&mut buf).await?;
+
+        // every frame is flushed when disabled
+        let (mut conn, mut client) = connect(None).await?;
+        conn.write_frame(&response(0)).await?;
+        assert_eq!(0, conn.pending_frames());
+        assert_eq!(None, conn.flush_deadline());
+        let mut buf = vec![0u8; RESPONSE_FRAME_LEN];
+        client.read_exact(&mut buf).await?;
+        Ok(())
+    }
+}
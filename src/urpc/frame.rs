@@ -1,11 +1,12 @@
 use crate::error::WorkerError;
 use crate::error::WorkerError::{STREAM_INCOMPLETE, STREAM_INCORRECT};
 use crate::store::ResponseData::Mem;
-use crate::store::{Block, BytesWrapper};
+use crate::store::{Block, BlockSource, BytesWrapper};
 use crate::urpc::command::{
     GetLocalDataIndexRequestCommand, GetLocalDataIndexResponseCommand, GetLocalDataRequestCommand,
     GetLocalDataResponseCommand, GetMemoryDataRequestCommand, GetMemoryDataResponseCommand,
-    RpcResponseCommand, SendDataRequestCommand,
+    GetMergedDataRequestCommand, GetMergedDataResponseCommand, RpcResponseCommand,
+    SendDataRequestCommand,
 };
 use anyhow::{Error, Result};
 use bytes::{Buf, Bytes};
@@ -50,6 +51,9 @@ enum MessageType {
     GetLocalData = 5,
     GetLocalDataResponse = 15,
 
+    GetMergedData = 7,
+    GetMergedDataResponse = 17,
+
     RpcResponse = 0,
 }
 
@@ -68,6 +72,9 @@ pub enum Frame {
     GetLocalData(GetLocalDataRequestCommand),
     GetLocalDataResponse(GetLocalDataResponseCommand),
 
+    GetMergedData(GetMergedDataRequestCommand),
+    GetMergedDataResponse(GetMergedDataResponseCommand),
+
     RpcResponse(RpcResponseCommand),
 }
 
@@ -191,6 +198,61 @@ impl Frame {
                 }
                 return Ok(());
             }
+            Frame::GetMergedDataResponse(resp) => {
+                let request_id = resp.request_id;
+                let status_code = resp.status_code;
+
+                let msg = &resp.ret_msg;
+                let msg_bytes = msg.as_bytes();
+
+                let merged_data = &resp.data;
+                let data_bytes_wrapper = &merged_data.data;
+                let data_bytes_len = data_bytes_wrapper.len() as i32;
+
+                // the segment is followed by the 1 byte source compared with the memory one
+                let segments = &merged_data.shuffle_data_block_segments;
+                let segments_encode_len = (4 + segments.len() * (3 * 8 + 3 * 4 + 1)) as i32;
+
+                // header
+                stream
+                    .write_i32(msg_bytes.len() as i32 + 8 + 4 + 4 + 8 + segments_encode_len)
+                    .await?;
+                stream
+                    .write_u8(MessageType::GetMergedDataResponse as u8)
+                    .await?;
+                stream.write_i32(data_bytes_len).await?;
+
+                // partial content with general response info
+                stream.write_i64(request_id).await?;
+                stream.write_i32(status_code).await?;
+
+                stream.write_i32(msg_bytes.len() as i32).await?;
+                stream.write_all(msg_bytes).await?;
+
+                stream.write_i64(merged_data.flush_sequence as i64).await?;
+
+                // write segment
+                stream.write_i32(segments.len() as i32).await?;
+                for (segment, source) in segments.iter().zip(merged_data.block_sources.iter()) {
+                    stream.write_i64(segment.block_id).await?;
+                    stream.write_i32(segment.offset as i32).await?;
+                    stream.write_i32(segment.length).await?;
+                    stream.write_i32(segment.uncompress_length).await?;
+                    stream.write_i64(segment.crc).await?;
+                    stream.write_i64(segment.task_attempt_id).await?;
+                    let source = match source {
+                        BlockSource::MEMORY => 0u8,
+                        BlockSource::LOCALFILE => 1u8,
+                    };
+                    stream.write_u8(source).await?;
+                }
+
+                // data_bytes
+                for composed_byte in data_bytes_wrapper.always_composed().iter() {
+                    stream.write_all(&composed_byte).await?;
+                }
+                return Ok(());
+            }
             Frame::RpcResponse(resp) => {
                 let request_id = resp.request_id;
                 let status_code = resp.status_code;
@@ -367,6 +429,26 @@ impl Frame {
         })
     }
 
+    fn parse_to_get_merged_data_command(
+        src: &mut Cursor<&[u8]>,
+    ) -> Result<GetMergedDataRequestCommand> {
+        let request_id = get_i64(src)?;
+        let app_id = get_string(src)?;
+        let shuffle_id = get_i32(src)?;
+        let partition_id = get_i32(src)?;
+        let timestamp = get_i64(src)?;
+
+        let expected_task_bitmap_raw_option = get_bytes(src)?;
+        Ok(GetMergedDataRequestCommand {
+            request_id,
+            app_id,
+            shuffle_id,
+            partition_id,
+            expected_tasks_bitmap_raw: expected_task_bitmap_raw_option,
+            timestamp,
+        })
+    }
+
     pub fn parse(src: &mut Cursor<&[u8]>) -> Result<Frame, WorkerError> {
         let encode_msg_len = get_i32(src)?;
         let msg_type = get_u8(src)?;
@@ -396,6 +478,10 @@ impl Frame {
                 let command = Frame::parse_to_get_memory_data_command(src)?;
                 return Ok(Frame::GetMemoryData(command));
             }
+            MessageType::GetMergedData => {
+                let command = Frame::parse_to_get_merged_data_command(src)?;
+                return Ok(Frame::GetMergedData(command));
+            }
             MessageType::SendShuffleData => {
                 let command = Frame::parse_to_send_shuffle_data_command(src)?;
                 return Ok(Frame::SendShuffleData(command));
@@ -542,4 +628,39 @@ mod test {
 
         Ok(())
     }
+
+    #[test]
+    fn merged_data_frame_parse() -> Result<()> {
+        let app_id = "app-1";
+        let mut content = BytesMut::new();
+        content.put_i64(1);
+        content.put_i32(app_id.len() as i32);
+        content.put(app_id.as_bytes());
+        content.put_i32(2);
+        content.put_i32(3);
+        content.put_i64(100);
+        // without the expected task ids bitmap
+        content.put_i32(0);
+
+        let mut request = BytesMut::new();
+        request.put_i32(content.len() as i32);
+        request.put_u8(7);
+        request.put_i32(0);
+        request.put(content);
+
+        let cursor = &mut Cursor::new(&request[..]);
+        match Frame::parse(cursor)? {
+            Frame::GetMergedData(command) => {
+                assert_eq!(1, command.request_id);
+                assert_eq!(app_id, command.app_id);
+                assert_eq!(2, command.shuffle_id);
+                assert_eq!(3, command.partition_id);
+                assert_eq!(100, command.timestamp);
+                assert!(command.expected_tasks_bitmap_raw.is_none());
+            }
+            _ => panic!(),
+        }
+
+        Ok(())
+    }
 }
@@ -2,11 +2,11 @@ use log::{debug, error, info};
 use std::future::Future;
 use std::net::SocketAddr;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tokio::net::{TcpListener, TcpStream};
 use tokio::sync::{broadcast, mpsc, Semaphore};
 
-use crate::urpc::connection::Connection;
+use crate::urpc::connection::{CoalesceOptions, Connection};
 use crate::urpc::shutdown::Shutdown;
 
 use crate::app::AppManagerRef;
@@ -25,6 +25,7 @@ struct Listener {
     limit_connections: Arc<Semaphore>,
     notify_shutdown: broadcast::Sender<()>,
     shutdown_complete_tx: mpsc::Sender<()>,
+    coalesce: Option<CoalesceOptions>,
 }
 
 impl Listener {
@@ -45,14 +46,14 @@ impl Listener {
             debug!("Accepted connection from client: {}", &addr);
 
             let mut handler = Handler {
-                connection: Connection::new(socket),
+                connection: Connection::new(socket, self.coalesce),
                 shutdown: Shutdown::new(self.notify_shutdown.subscribe()),
                 _shutdown_complete: self.shutdown_complete_tx.clone(),
             };
 
             let await_registry = AWAIT_TREE_REGISTRY.clone();
             let await_root = await_registry
-                .register(format!("urpc connection with remote client: {}", addr))
+                .register_long_running(format!("urpc connection with remote client: {}", addr))
                 .await;
             tokio::spawn(await_root.instrument(async move {
                 URPC_CONNECTION_NUMBER.inc();
@@ -97,9 +98,16 @@ impl Handler {
     /// util it reaches a safe state, at which point it is terminated
     async fn run(&mut self, app_manager_ref: AppManagerRef) -> Result<(), WorkerError> {
         while !self.shutdown.is_shutdown() {
+            // the coalesced responses are flushed when no more request comes in time
+            let flush_deadline = self.connection.flush_deadline();
             let maybe_frame = tokio::select! {
                 res = self.connection.read_frame() => res?,
+                _ = tokio::time::sleep_until(flush_deadline.unwrap_or_else(Instant::now).into()), if flush_deadline.is_some() => {
+                    self.connection.flush().await?;
+                    continue;
+                },
                 _ = self.shutdown.recv() => {
+                    self.connection.flush().await?;
                     return Ok(());
                 },
             };
@@ -118,11 +126,18 @@ impl Handler {
                 .instrument_await("handling the complete request")
                 .await?;
         }
+        // the shutdown observed by the command leaves the coalesced responses pending
+        self.connection.flush().await?;
         Ok(())
     }
 }
 
-pub async fn run(listener: TcpListener, shutdown: impl Future, app_manager_ref: AppManagerRef) {
+pub async fn run(
+    listener: TcpListener,
+    shutdown: impl Future,
+    app_manager_ref: AppManagerRef,
+    coalesce: Option<CoalesceOptions>,
+) {
     let (notify_shutdown, _) = broadcast::channel(1);
     let (shutdown_complete_tx, mut shutdown_complete_rx) = mpsc::channel(1);
 
@@ -131,6 +146,7 @@ pub async fn run(listener: TcpListener, shutdown: impl Future, app_manager_ref:
         limit_connections: Arc::new(Semaphore::new(MAX_CONNECTIONS)),
         notify_shutdown,
         shutdown_complete_tx,
+        coalesce,
     };
 
     tokio::select! {
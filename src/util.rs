@@ -19,7 +19,10 @@ use bytes::Bytes;
 use crc32fast::Hasher;
 
 use crate::config::Config;
+use log::warn;
+use std::fs;
 use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::Path;
 use std::sync::Mutex;
 use std::time::{SystemTime, UNIX_EPOCH};
 
@@ -37,8 +40,17 @@ pub fn get_local_ip() -> Result<IpAddr, std::io::Error> {
     }
 }
 
+/// The ip registered to the coordinator. The specific bind address is advertised as is,
+/// since the picked local ip may be of the other NIC that's not listened on.
+pub fn get_advertised_ip(config: &Config) -> Result<IpAddr, std::io::Error> {
+    match config.bind_address() {
+        Ok(ip) if !ip.is_unspecified() => Ok(ip),
+        _ => get_local_ip(),
+    }
+}
+
 pub fn generate_worker_uid(config: &Config) -> String {
-    let ip = get_local_ip().unwrap().to_string();
+    let ip = get_advertised_ip(config).unwrap().to_string();
     let grpc_port = config.grpc_port;
     let urpc_port = config.urpc_port;
     if urpc_port.is_none() {
@@ -47,9 +59,65 @@ pub fn generate_worker_uid(config: &Config) -> String {
     return format!("{}-{}-{}", &ip, grpc_port, urpc_port.unwrap());
 }
 
-pub fn gen_worker_uid(grpc_port: i32) -> String {
-    let ip = get_local_ip().unwrap().to_string();
-    format!("{}-{}", ip.clone(), grpc_port)
+/// The persisted id will be reused across restarts when the `server.id_storage_path`
+/// is set, otherwise or the path is not writable, fallback to the ip-port derived one.
+pub fn load_or_generate_worker_uid(config: &Config) -> String {
+    match &config.server.id_storage_path {
+        Some(path) => match load_or_persist_uid(Path::new(path)) {
+            Ok(uid) => uid,
+            Err(e) => {
+                warn!(
+                    "Errors on persisting the server id into {}, fallback to the ip-port derived one. err: {:?}",
+                    path, e
+                );
+                generate_worker_uid(config)
+            }
+        },
+        _ => generate_worker_uid(config),
+    }
+}
+
+fn load_or_persist_uid(path: &Path) -> anyhow::Result<String> {
+    if let Ok(content) = fs::read_to_string(path) {
+        let uid = content.trim();
+        if is_valid_uuid(uid) {
+            return Ok(uid.to_string());
+        }
+        warn!(
+            "The persisted server id: [{}] in {:?} is corrupted, it will be regenerated",
+            uid, path
+        );
+    }
+
+    let uid = random_uuid();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    // write into the temp file and then rename to avoid the partial written file
+    let temp_path = path.with_extension("tmp");
+    fs::write(&temp_path, &uid)?;
+    fs::rename(&temp_path, path)?;
+    Ok(uid)
+}
+
+fn random_uuid() -> String {
+    let hex = format!("{:032x}", rand::random::<u128>());
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[0..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..32]
+    )
+}
+
+fn is_valid_uuid(uid: &str) -> bool {
+    uid.len() == 36
+        && uid.char_indices().all(|(idx, c)| match idx {
+            8 | 13 | 18 | 23 => c == '-',
+            _ => c.is_ascii_hexdigit(),
+        })
 }
 
 const LENGTH_PER_CRC: usize = 4 * 1024;
@@ -82,10 +150,14 @@ pub fn now_timestamp_as_sec() -> u64 {
 }
 
 pub fn is_port_used(port: u16) -> bool {
-    match std::net::TcpListener::bind(SocketAddr::new(
+    is_addr_used(SocketAddr::new(
         IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)),
         port as u16,
-    )) {
+    ))
+}
+
+pub fn is_addr_used(addr: SocketAddr) -> bool {
+    match std::net::TcpListener::bind(addr) {
         Ok(_) => false,
         _ => true,
     }
@@ -93,7 +165,11 @@ pub fn is_port_used(port: u16) -> bool {
 
 #[cfg(test)]
 mod test {
-    use crate::util::{get_crc, is_port_used, now_timestamp_as_sec};
+    use crate::config::Config;
+    use crate::util::{
+        generate_worker_uid, get_advertised_ip, get_crc, is_port_used, is_valid_uuid,
+        load_or_generate_worker_uid, now_timestamp_as_sec,
+    };
     use bytes::Bytes;
     use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 
@@ -122,4 +198,37 @@ mod test {
         // This value is the same with java's implementation
         assert_eq!(3871485936, crc_value);
     }
+
+    #[test]
+    fn test_advertised_ip() -> anyhow::Result<()> {
+        let mut config = Config::default();
+        config.grpc_port = 19999;
+        config.bind_address = Some("10.0.0.2".to_string());
+        assert_eq!("10.0.0.2".parse::<IpAddr>()?, get_advertised_ip(&config)?);
+        assert_eq!("10.0.0.2-19999", generate_worker_uid(&config));
+        Ok(())
+    }
+
+    #[test]
+    fn test_persistent_worker_uid() -> anyhow::Result<()> {
+        let temp_dir = tempdir::TempDir::new("test_persistent_worker_uid")?;
+        let id_path = temp_dir.path().join("server").join("id");
+
+        let mut config = Config::default();
+        config.server.id_storage_path = Some(id_path.to_str().unwrap().to_string());
+
+        // stable across the constructions
+        let uid = load_or_generate_worker_uid(&config);
+        assert!(is_valid_uuid(&uid));
+        assert_eq!(uid, load_or_generate_worker_uid(&config));
+
+        // the corrupted one will be regenerated
+        std::fs::write(&id_path, "corrupted")?;
+        let regenerated = load_or_generate_worker_uid(&config);
+        assert!(is_valid_uuid(&regenerated));
+        assert_ne!(uid, regenerated);
+        assert_eq!(regenerated, std::fs::read_to_string(&id_path)?);
+
+        Ok(())
+    }
 }
@@ -0,0 +1,52 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use uniffle_worker::bench::{run, BenchConfig};
+    use uniffle_worker::testing::MiniRiffleCluster;
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn bench_smoke_test() -> Result<()> {
+        let cluster = MiniRiffleCluster::builder()
+            .memory_capacity("10M")
+            .build()
+            .await?;
+
+        let report = run(BenchConfig {
+            address: cluster.servers()[0].address(),
+            apps: 2,
+            partitions: 4,
+            blocks_per_partition: 5,
+            block_size_min: 100,
+            block_size_max: 1000,
+            concurrency: 4,
+            read_ratio: 0.5,
+            verify: true,
+        })
+        .await?;
+        println!("{}", report);
+
+        assert_eq!(0, report.total_errors());
+        assert_eq!(40, report.operation("send_shuffle_data").unwrap().count);
+        let verify = report.verify.unwrap();
+        assert_eq!(40, verify.expected_blocks);
+        assert!(verify.is_passed());
+        Ok(())
+    }
+}
@@ -0,0 +1,56 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+#[cfg(test)]
+mod test {
+    use bytes::Bytes;
+    use std::time::Instant;
+    use uniffle_worker::store::Block;
+
+    #[test]
+    #[ignore]
+    fn crc32_verification_test() {
+        // 1000 requests(16 blocks of 64k in single batch), about 1G
+        let requests = 1000;
+        let block_size = 64 * 1024;
+
+        let blocks: Vec<Block> = (0..16)
+            .map(|idx| {
+                let data = Bytes::from(vec![idx as u8; block_size]);
+                Block {
+                    block_id: idx,
+                    length: block_size as i32,
+                    uncompress_length: block_size as i32,
+                    crc: crc32fast::hash(&data) as i64,
+                    data,
+                    task_attempt_id: 0,
+                }
+            })
+            .collect();
+
+        let timer = Instant::now();
+        for _ in 0..requests {
+            assert!(blocks.iter().all(|block| block.is_crc32_matched()));
+        }
+        let elapsed = timer.elapsed();
+        let total_bytes = (requests * blocks.len() * block_size) as f64;
+        println!(
+            "crc32 verification time cost: {} ms, throughput: {:.2} GB/s",
+            elapsed.as_millis(),
+            total_bytes / elapsed.as_secs_f64() / (1024.0 * 1024.0 * 1024.0)
+        );
+    }
+}
@@ -0,0 +1,99 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use bytes::Bytes;
+    use riffle_client::{Block, ClientOptions, RiffleClient, StreamExt};
+    use uniffle_worker::testing::MiniRiffleCluster;
+
+    async fn read_all(client: &RiffleClient, partition_id: i32) -> Result<Vec<(i64, Bytes)>> {
+        let mut stream = client.read_partition("app", 0, partition_id);
+        let mut blocks = vec![];
+        while let Some(block) = stream.next().await {
+            let block = block?;
+            blocks.push((block.block_id, block.data));
+        }
+        blocks.sort_by_key(|x| x.0);
+        Ok(blocks)
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn client_round_trip() -> Result<()> {
+        let cluster = MiniRiffleCluster::builder().build().await?;
+        // the tiny message size makes every block sent in its own request
+        let options = ClientOptions {
+            max_message_size: 8,
+            ..Default::default()
+        };
+        let client = RiffleClient::connect_with(cluster.servers()[0].address(), options).await?;
+        client.register_app("app", 0).await?;
+
+        let data: Vec<Bytes> = ["hello", "world", "riffle"]
+            .iter()
+            .map(|x| Bytes::from(*x))
+            .collect();
+        let blocks = data
+            .iter()
+            .enumerate()
+            .map(|(idx, x)| Block::new(idx as i64, x.clone()))
+            .collect();
+        client.send_blocks("app", 0, 1, blocks).await?;
+        client
+            .report_shuffle_result("app", 0, 0, 1, vec![0, 1, 2])
+            .await?;
+        assert_eq!(vec![0, 1, 2], client.get_shuffle_result("app", 0, 1).await?);
+
+        let expected: Vec<(i64, Bytes)> = data
+            .iter()
+            .enumerate()
+            .map(|(idx, x)| (idx as i64, x.clone()))
+            .collect();
+        assert_eq!(expected, read_all(&client, 1).await?);
+
+        // read from the localfile after spilled
+        cluster.force_spill().await?;
+        let stored = cluster.read_partition("app", 0, 1).await?;
+        assert_eq!(3, stored.localfile.len());
+        assert_eq!(expected, read_all(&client, 1).await?);
+
+        // the empty partition
+        assert!(read_all(&client, 2).await?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn vendored_proto_in_sync() {
+        assert_eq!(
+            include_str!("../src/grpc/protobuf/uniffle.proto"),
+            include_str!("../riffle-client/proto/uniffle.proto"),
+            "The vendored proto of the riffle-client should be synced with the server"
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn client_unregistered_app() -> Result<()> {
+        let cluster = MiniRiffleCluster::builder().build().await?;
+        let client = RiffleClient::connect(cluster.servers()[0].address()).await?;
+        let result = client
+            .send_blocks("unknown", 0, 0, vec![Block::new(0, Bytes::from("hello"))])
+            .await;
+        assert!(result.is_err());
+        Ok(())
+    }
+}
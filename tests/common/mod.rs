@@ -0,0 +1,81 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use anyhow::Result;
+use bytes::{Bytes, BytesMut};
+use riffle_client::{Block, RiffleClient, StreamExt};
+
+/// Write the blocks into the different partitions by the riffle-client, and then read them
+/// back from the memory or the localfile.
+pub async fn write_read_for_one_time(client: &RiffleClient) -> Result<()> {
+    let app_id = "write_read_test-app-id";
+    client.register_app(app_id, 0).await?;
+
+    let mut all_bytes_data = BytesMut::new();
+    let mut block_ids = vec![];
+
+    let batch_size = 3000;
+
+    for idx in 0..batch_size {
+        block_ids.push(idx as i64);
+
+        let data = b"hello world";
+        all_bytes_data.extend_from_slice(data);
+
+        client
+            .send_blocks(
+                app_id,
+                0,
+                idx,
+                vec![Block::new(idx as i64, Bytes::copy_from_slice(data))],
+            )
+            .await?;
+
+        // report the finished block ids
+        client
+            .report_shuffle_result(app_id, 0, 0, idx, vec![idx as i64])
+            .await?;
+    }
+
+    tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+
+    let mut accepted_block_ids = vec![];
+    let mut accepted_data_bytes = BytesMut::new();
+
+    for idx in 0..batch_size {
+        assert_eq!(
+            vec![idx as i64],
+            client.get_shuffle_result(app_id, 0, idx).await?
+        );
+
+        let mut stream = client.read_partition(app_id, 0, idx);
+        while let Some(block) = stream.next().await {
+            let block = block?;
+            accepted_block_ids.push(block.block_id);
+            accepted_data_bytes.extend_from_slice(&block.data);
+        }
+    }
+
+    // check the block ids
+    assert_eq!(batch_size as usize, accepted_block_ids.len());
+    assert_eq!(block_ids, accepted_block_ids);
+
+    // check the shuffle data
+    assert_eq!(all_bytes_data.freeze(), accepted_data_bytes.freeze());
+
+    Ok(())
+}
@@ -0,0 +1,163 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use bytes::{Buf, Bytes};
+    use riffle_client::proto::{GetLocalShuffleDataRequest, GetLocalShuffleIndexRequest};
+    use riffle_client::RiffleClient;
+    use std::time::Duration;
+    use uniffle_worker::metric::{TOTAL_INJECTED_FAULTS, TOTAL_MEMORY_SPILL_OPERATION_FAILED};
+    use uniffle_worker::store::fault::{FaultKind, FaultOperation, FaultRule, FAULT_INJECTOR};
+    use uniffle_worker::testing::MiniRiffleCluster;
+
+    const DATA: &[u8] = b"hello world";
+
+    // every block exceeds the partition buffer max size, which will be spilled immediately
+    async fn start_cluster() -> Result<MiniRiffleCluster> {
+        MiniRiffleCluster::builder()
+            .memory_capacity("1M")
+            .partition_buffer_max_size("10B")
+            .build()
+            .await
+    }
+
+    async fn write(cluster: &MiniRiffleCluster, app_id: &str, partitions: i32) -> Result<()> {
+        for partition_id in 0..partitions {
+            cluster
+                .write_blocks(app_id, 0, partition_id, vec![Bytes::from_static(DATA)])
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Read the only block of the partition from the localfile, return the crc in index and data.
+    /// The raw rpc is used since the crc in index is not exposed by the typed reading.
+    async fn read_localfile(
+        client: &RiffleClient,
+        app_id: &str,
+        partition_id: i32,
+    ) -> Result<(i64, Bytes)> {
+        let mut client = client.raw();
+        let mut index = Bytes::new();
+        for _ in 0..100 {
+            index = client
+                .get_local_shuffle_index(GetLocalShuffleIndexRequest {
+                    app_id: app_id.to_string(),
+                    shuffle_id: 0,
+                    partition_id,
+                    partition_num_per_range: 1,
+                    partition_num: 0,
+                })
+                .await?
+                .into_inner()
+                .index_data;
+            if !index.is_empty() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        assert!(
+            !index.is_empty(),
+            "partition: {} is not spilled",
+            partition_id
+        );
+
+        let offset = index.get_i64();
+        let length = index.get_i32();
+        index.get_i32();
+        let crc = index.get_i64();
+        let data = client
+            .get_local_shuffle_data(GetLocalShuffleDataRequest {
+                app_id: app_id.to_string(),
+                shuffle_id: 0,
+                partition_id,
+                partition_num_per_range: 1,
+                partition_num: 0,
+                offset,
+                length,
+                timestamp: 0,
+            })
+            .await?
+            .into_inner()
+            .data;
+        Ok((crc, data))
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn spill_fallback_with_injected_errors() -> Result<()> {
+        let cluster = start_cluster().await?;
+        let client = RiffleClient::connect(cluster.servers()[0].address()).await?;
+
+        let app_id = "spill_fallback_with_injected_errors";
+        // the first 2 spills fail, which should be retried rather than losing data
+        let mut rule = FaultRule::new(FaultOperation::SpillInsert, FaultKind::Error);
+        rule.app_id = Some(app_id.to_string());
+        rule.times = Some(2);
+        rule.every = Some(u64::MAX);
+        FAULT_INJECTOR.add_rule(rule);
+
+        let injected = TOTAL_INJECTED_FAULTS.with_label_values(&["error"]).get();
+        let failed = TOTAL_MEMORY_SPILL_OPERATION_FAILED.get();
+
+        write(&cluster, app_id, 5).await?;
+        for partition_id in 0..5 {
+            let (crc, data) = read_localfile(&client, app_id, partition_id).await?;
+            assert_eq!(Bytes::from_static(DATA), data);
+            assert_eq!(crc32fast::hash(DATA) as i64, crc);
+        }
+
+        assert_eq!(
+            2,
+            TOTAL_INJECTED_FAULTS.with_label_values(&["error"]).get() - injected
+        );
+        assert!(TOTAL_MEMORY_SPILL_OPERATION_FAILED.get() - failed >= 2);
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn read_corruption_detected_by_crc() -> Result<()> {
+        let cluster = start_cluster().await?;
+        let client = RiffleClient::connect(cluster.servers()[0].address()).await?;
+
+        let app_id = "read_corruption_detected_by_crc";
+        let mut rule = FaultRule::new(FaultOperation::Get, FaultKind::Corrupt);
+        rule.app_id = Some(app_id.to_string());
+        rule.partition_id = Some(1);
+        FAULT_INJECTOR.add_rule(rule);
+
+        let injected = TOTAL_INJECTED_FAULTS.with_label_values(&["corrupt"]).get();
+
+        write(&cluster, app_id, 2).await?;
+
+        // the untouched partition is intact
+        let (crc, data) = read_localfile(&client, app_id, 0).await?;
+        assert_eq!(crc, crc32fast::hash(&data) as i64);
+
+        // the corrupted one is detectable by the client with the crc in index
+        let (crc, data) = read_localfile(&client, app_id, 1).await?;
+        assert_eq!(DATA.len(), data.len());
+        assert_ne!(crc, crc32fast::hash(&data) as i64);
+
+        assert_eq!(
+            1,
+            TOTAL_INJECTED_FAULTS.with_label_values(&["corrupt"]).get() - injected
+        );
+        Ok(())
+    }
+}
@@ -15,25 +15,19 @@
 // specific language governing permissions and limitations
 // under the License.
 
+mod common;
+
 #[cfg(test)]
 mod test {
     use std::time::Duration;
 
     use signal_hook::consts::SIGTERM;
     use signal_hook::low_level::raise;
-    use tonic::transport::Channel;
 
+    use crate::common::write_read_for_one_time;
+    use riffle_client::RiffleClient;
     use uniffle_worker::config::{Config, LocalfileStoreConfig, StorageType};
-    use uniffle_worker::grpc::protobuf::uniffle::shuffle_server_client::ShuffleServerClient;
-    use uniffle_worker::{start_uniffle_worker, write_read_for_one_time};
-
-    async fn get_data_from_remote(
-        _client: &ShuffleServerClient<Channel>,
-        _app_id: &str,
-        _shuffle_id: i32,
-        _partitions: Vec<i32>,
-    ) {
-    }
+    use uniffle_worker::start_uniffle_worker;
 
     async fn start_embedded_worker(path: String, port: i32) {
         let config = Config::create_mem_localfile_config(port, "1G".to_string(), path);
@@ -50,16 +44,15 @@ mod test {
         let port = 21101;
         let _ = start_embedded_worker(temp_path, port).await;
 
-        let client =
-            match ShuffleServerClient::connect(format!("http://{}:{}", "0.0.0.0", port)).await {
-                Ok(client) => client,
-                Err(e) => {
-                    // Handle the error, e.g., by panicking or logging it.
-                    panic!("Failed to connect: {}", e);
-                }
-            };
+        let client = match RiffleClient::connect(format!("http://{}:{}", "0.0.0.0", port)).await {
+            Ok(client) => client,
+            Err(e) => {
+                // Handle the error, e.g., by panicking or logging it.
+                panic!("Failed to connect: {}", e);
+            }
+        };
 
-        let jh = tokio::spawn(async move { write_read_for_one_time(client).await });
+        let jh = tokio::spawn(async move { write_read_for_one_time(&client).await });
 
         // raise shutdown signal
         tokio::spawn(async {
@@ -0,0 +1,238 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use bytes::Bytes;
+    use std::time::Duration;
+    use tonic::metadata::MetadataMap;
+    use uniffle_worker::block_id::BlockIdLayout;
+    use uniffle_worker::busy_score::BUSY_SCORE_METADATA_KEY;
+    use uniffle_worker::constant::StatusCode;
+    use uniffle_worker::grpc::protobuf::uniffle::{
+        GetMemoryShuffleDataRequest, GetShuffleResultRequest, ReassignPartitionRequest,
+        RequireBufferRequest, SendShuffleDataRequest, ShuffleBlock, ShuffleData,
+    };
+    use uniffle_worker::grpc::service::SPLIT_BLOCK_SEQUENCE_METADATA_KEY;
+    use uniffle_worker::testing::MiniRiffleCluster;
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn round_trip_with_two_servers() -> Result<()> {
+        let cluster = MiniRiffleCluster::builder().servers(2).build().await?;
+        cluster
+            .write_blocks("app", 0, 1, vec![Bytes::from("hello")])
+            .await?;
+        cluster
+            .assert_stored("app", 0, 1, &[Bytes::from("hello")])
+            .await
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn reject_corrupted_blocks() -> Result<()> {
+        let cluster = MiniRiffleCluster::builder().build().await?;
+        let blocks = vec![
+            Bytes::from("hello"),
+            Bytes::from("world"),
+            Bytes::from("riffle"),
+        ];
+        let (block_ids, response) = cluster
+            .write_corrupted_blocks("app", 0, 1, blocks.clone(), 1)
+            .await?;
+        assert_eq!(i32::from(StatusCode::CHECKSUM_MISMATCHED), response.status);
+        assert_eq!(vec![block_ids[1]], response.mismatched_block_ids);
+
+        // the whole batch is rejected, only the resent blocks are stored
+        cluster.write_blocks("app", 0, 1, blocks.clone()).await?;
+        cluster.assert_stored("app", 0, 1, &blocks).await
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn force_spill_and_teardown() -> Result<()> {
+        let cluster = MiniRiffleCluster::builder().servers(2).build().await?;
+        assert_eq!(2, cluster.coordinator().registered_ports().len());
+
+        let blocks = vec![Bytes::from("hello"), Bytes::from("world")];
+        for partition_id in 0..4 {
+            cluster
+                .write_blocks("app", 0, partition_id, blocks.clone())
+                .await?;
+            let stored = cluster.read_partition("app", 0, partition_id).await?;
+            assert_eq!(2, stored.memory.len());
+            assert!(stored.localfile.is_empty());
+        }
+
+        cluster.force_spill().await?;
+        for partition_id in 0..4 {
+            let stored = cluster.read_partition("app", 0, partition_id).await?;
+            assert_eq!(2, stored.localfile.len());
+            assert_eq!(blocks, stored.blocks());
+        }
+
+        let paths: Vec<_> = cluster
+            .servers()
+            .iter()
+            .map(|x| x.data_path().to_path_buf())
+            .collect();
+        drop(cluster);
+        assert!(paths.iter().all(|x| !x.exists()));
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn busy_score_in_response_metadata() -> Result<()> {
+        let cluster = MiniRiffleCluster::builder()
+            .memory_capacity("10M")
+            .build()
+            .await?;
+        cluster.register_app("app", 0).await?;
+        let mut client = cluster.servers()[0].client().await?;
+
+        let require_buffer = |size: i32| {
+            let request = RequireBufferRequest {
+                require_size: size,
+                app_id: "app".to_string(),
+                shuffle_id: 0,
+                partition_ids: vec![],
+            };
+            let mut client = client.clone();
+            async move { client.require_buffer(request).await }
+        };
+        let busy_score = |metadata: &MetadataMap| -> u32 {
+            metadata
+                .get(BUSY_SCORE_METADATA_KEY)
+                .unwrap()
+                .to_str()
+                .unwrap()
+                .parse()
+                .unwrap()
+        };
+
+        let response = require_buffer(1).await?;
+        assert!(busy_score(response.metadata()) <= 10);
+
+        // the allocated memory is counted in after the next sampling
+        let response = require_buffer(6 * 1024 * 1024).await?;
+        assert_eq!(0, response.get_ref().status);
+        tokio::time::sleep(Duration::from_secs(2)).await;
+        let response = require_buffer(1).await?;
+        assert!(busy_score(response.metadata()) >= 25);
+
+        // attached in the send data response too
+        let response = client
+            .send_shuffle_data(SendShuffleDataRequest {
+                app_id: "app".to_string(),
+                shuffle_id: 0,
+                require_buffer_id: -1,
+                ..Default::default()
+            })
+            .await?;
+        assert!(busy_score(response.metadata()) >= 25);
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn reassign_huge_partition() -> Result<()> {
+        let cluster = MiniRiffleCluster::builder().build().await?;
+        // the assigned block ids are all of the sequence 0
+        let block_ids = cluster
+            .write_blocks("app", 0, 1, vec![Bytes::from("hello")])
+            .await?;
+        let mut client = cluster.server_of(1).client().await?;
+
+        let response = client
+            .reassign_partition(ReassignPartitionRequest {
+                app_id: "app".to_string(),
+                shuffle_id: 0,
+                partition_id: 1,
+                split_block_sequence: 0,
+            })
+            .await?
+            .into_inner();
+        assert_eq!(0, response.status);
+        assert_eq!(0, response.split_block_sequence);
+
+        // the blocks beyond the split point are rejected
+        let data = Bytes::from("world");
+        let ticket = client
+            .require_buffer(RequireBufferRequest {
+                require_size: data.len() as i32,
+                app_id: "app".to_string(),
+                shuffle_id: 0,
+                partition_ids: vec![1],
+            })
+            .await?
+            .into_inner();
+        let response = client
+            .send_shuffle_data(SendShuffleDataRequest {
+                app_id: "app".to_string(),
+                shuffle_id: 0,
+                require_buffer_id: ticket.require_buffer_id,
+                shuffle_data: vec![ShuffleData {
+                    partition_id: 1,
+                    block: vec![ShuffleBlock {
+                        block_id: BlockIdLayout::default().encode(1, 1, 0)?,
+                        length: data.len() as i32,
+                        uncompress_length: data.len() as i32,
+                        crc: 0,
+                        data,
+                        task_attempt_id: 0,
+                    }],
+                }],
+                ..Default::default()
+            })
+            .await?
+            .into_inner();
+        assert_eq!(i32::from(StatusCode::REASSIGNED), response.status);
+        assert_eq!(Some(0), response.split_block_sequence);
+
+        // the split is reported on reading, and only the prefix is kept
+        let response = client
+            .get_shuffle_result(GetShuffleResultRequest {
+                app_id: "app".to_string(),
+                shuffle_id: 0,
+                partition_id: 1,
+            })
+            .await?
+            .into_inner();
+        assert_eq!(Some(0), response.split_block_sequence);
+        let response = client
+            .get_memory_shuffle_data(GetMemoryShuffleDataRequest {
+                app_id: "app".to_string(),
+                shuffle_id: 0,
+                partition_id: 1,
+                last_block_id: -1,
+                read_buffer_size: 1024,
+                ..Default::default()
+            })
+            .await?;
+        assert_eq!(
+            "0",
+            response
+                .metadata()
+                .get(SPLIT_BLOCK_SEQUENCE_METADATA_KEY)
+                .unwrap()
+                .to_str()?
+        );
+        let segments = &response.get_ref().shuffle_data_block_segments;
+        assert_eq!(
+            block_ids,
+            segments.iter().map(|x| x.block_id).collect::<Vec<_>>()
+        );
+        Ok(())
+    }
+}
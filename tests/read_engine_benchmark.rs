@@ -0,0 +1,99 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+#[cfg(test)]
+mod test {
+    use bytes::Bytes;
+    use std::time::Instant;
+    use uniffle_worker::app::{
+        PartitionedUId, ReadingOptions, ReadingViewContext, WritingViewContext,
+    };
+    use uniffle_worker::config::{LocalfileStoreConfig, ReadEngine};
+    use uniffle_worker::runtime::manager::RuntimeManager;
+    use uniffle_worker::store::localfile::LocalFileStore;
+    use uniffle_worker::store::{Block, ResponseData, Store};
+
+    fn bench(engine: ReadEngine, rounds: usize, blocks: usize, block_size: usize) {
+        let temp_dir = tempdir::TempDir::new("read_engine_benchmark").unwrap();
+        let mut config =
+            LocalfileStoreConfig::new(vec![temp_dir.path().to_str().unwrap().to_string()]);
+        config.read_engine = Some(engine);
+        let runtime_manager = RuntimeManager::default();
+        let store = LocalFileStore::from(config, runtime_manager.clone());
+
+        let uid = PartitionedUId {
+            app_id: "read_engine_benchmark".to_string(),
+            shuffle_id: 0,
+            partition_id: 0,
+        };
+        let data = Bytes::from(vec![1u8; block_size]);
+        for block_id in 0..blocks {
+            let ctx = WritingViewContext::from(
+                uid.clone(),
+                vec![Block {
+                    block_id: block_id as i64,
+                    length: block_size as i32,
+                    uncompress_length: block_size as i32,
+                    crc: 0,
+                    data: data.clone(),
+                    task_attempt_id: 0,
+                }],
+            );
+            runtime_manager.wait(store.insert(ctx)).unwrap();
+        }
+
+        let read_all = || {
+            for block_id in 0..blocks {
+                let ctx = ReadingViewContext {
+                    uid: uid.clone(),
+                    reading_options: ReadingOptions::FILE_OFFSET_AND_LEN(
+                        (block_id * block_size) as i64,
+                        block_size as i64,
+                    ),
+                    serialized_expected_task_ids_bitmap: Default::default(),
+                };
+                match runtime_manager.wait(store.get(ctx)).unwrap() {
+                    ResponseData::Local(data) => assert_eq!(block_size, data.data.len()),
+                    _ => panic!(),
+                }
+            }
+        };
+        // warm up the page cache and the mapping
+        read_all();
+
+        let timer = Instant::now();
+        for _ in 0..rounds {
+            read_all();
+        }
+        let elapsed = timer.elapsed();
+        let total_bytes = (rounds * blocks * block_size) as f64;
+        println!(
+            "{:?} engine time cost: {} ms, throughput: {:.2} GB/s",
+            engine,
+            elapsed.as_millis(),
+            total_bytes / elapsed.as_secs_f64() / (1024.0 * 1024.0 * 1024.0)
+        );
+    }
+
+    #[test]
+    #[ignore]
+    fn read_engine_benchmark_test() {
+        // 1024 blocks of 64k, about 64M per round
+        for engine in [ReadEngine::Pread, ReadEngine::Mmap] {
+            bench(engine, 10, 1024, 64 * 1024);
+        }
+    }
+}
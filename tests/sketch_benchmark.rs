@@ -0,0 +1,72 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+#[cfg(test)]
+mod test {
+    use parking_lot::Mutex;
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Instant;
+    use uniffle_worker::sketch::{ShardedSpaceSaving, SpaceSaving};
+
+    const THREADS: usize = 16;
+    const ADDS_PER_THREAD: usize = 1_000_000;
+    const PARTITIONS: i32 = 10000;
+
+    fn bench<F: Fn(i32) + Send + Sync + 'static>(name: &str, add: F) {
+        let add = Arc::new(add);
+        let timer = Instant::now();
+        let handles: Vec<_> = (0..THREADS)
+            .map(|idx| {
+                let add = add.clone();
+                thread::spawn(move || {
+                    for k in 0..ADDS_PER_THREAD {
+                        add(((idx * ADDS_PER_THREAD + k) % PARTITIONS as usize) as i32);
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        let elapsed = timer.elapsed();
+        println!(
+            "{} time cost: {} ms, throughput: {:.2} M adds/s",
+            name,
+            elapsed.as_millis(),
+            (THREADS * ADDS_PER_THREAD) as f64 / elapsed.as_secs_f64() / 1_000_000.0
+        );
+    }
+
+    #[test]
+    #[ignore]
+    fn sketch_benchmark_test() {
+        // the same total capacity of the single and the sharded sketches
+        let single = Mutex::new(SpaceSaving::new(32 * 16));
+        bench("single sketch", move |partition_id| {
+            single.lock().add((0, partition_id), 1)
+        });
+
+        for shards in [4, 16, 64] {
+            let sharded = ShardedSpaceSaving::new(32 * 16 / shards, shards);
+            bench(
+                &format!("sharded sketch of {} shards", shards),
+                move |partition_id| sharded.add((0, partition_id), 1),
+            );
+        }
+    }
+}